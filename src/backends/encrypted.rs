@@ -0,0 +1,410 @@
+//! Whole-file encryption at rest: a storage backend that encrypts every page of the database file,
+//! including the table names, keys, and `redb` internal structures that per-value encryption
+//! leaves readable.
+//!
+//! The file starts with a short plain-text header, which holds a magic number, the page size, and
+//! the database's length. The database is split into fixed-size pages after it, and each page is
+//! stored in a slot of its own, sealed by the active encryptor with a fresh random nonce every time
+//! it's written. The page's index is authenticated along with it, so that pages can't be swapped
+//! or moved without being detected. Slots that were never written are all zeroes, and are read as
+//! zeroed pages.
+//!
+//! Each slot is larger than its page by the encryptor's tag, nonce, key ID, and format marker:
+//! about 34 bytes. An attacker who can write to the file can still roll a page back to an older version of
+//! itself; per-page authentication doesn't detect replays.
+
+use crate::layers::core::{Bytes, Direction};
+use crate::layers::encryptors::{
+    ActiveEncryptor,
+    AssociatedData,
+    Encryptable,
+    Encryptor,
+    FORMAT_MARKER,
+    KeyBytes,
+    NONCE_SIZE,
+};
+use redb::StorageBackend;
+use std::io;
+use std::ops::Range;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// Size of the pages the database is encrypted in, unless set with
+/// [`EncryptedBackend::with_page_size`]. Matches `redb`'s default page size.
+pub const DEFAULT_PAGE_SIZE: usize = 4_096;
+
+/// Marks a file written by an [`EncryptedBackend`].
+const MAGIC: &[u8; 8] = b"ATLPAGE1";
+
+/// Size of the plain-text header: the magic number, the page size, and the database's length.
+const HEADER_SIZE: usize = 24;
+
+/// Size of the authentication tag the active encryptor appends to each page.
+const TAG_SIZE: usize = 16;
+
+/// Size of the key ID the active encryptor appends to each page.
+const KEY_ID_SIZE: usize = 2;
+
+/// Number of bytes each slot holds on top of its page.
+const SLOT_OVERHEAD: usize = TAG_SIZE + NONCE_SIZE + KEY_ID_SIZE + FORMAT_MARKER.len();
+
+/// The table name that pages are authenticated under. Their key is their index.
+const PAGE_CONTEXT: &str = "#pages";
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The active encryptor, sealing whole pages.
+type PageCipher = ActiveEncryptor<Page>;
+
+/// A page of the database file.
+struct Page;
+
+impl Encryptable for Page {
+    const DIRECTION: Direction = Direction::Both;
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A storage backend that encrypts another backend's contents, page by page, with a master key.
+/// See the [module documentation](self).
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let file = std::fs::OpenOptions::new()
+///     .read(true)
+///     .write(true)
+///     .create(true)
+///     .truncate(false)
+///     .open("creatures.redb")?;
+/// let backend = EncryptedBackend::new(FileBackend::new(file)?, master_key)?;
+/// let db = Database::with_backend(backend)?;
+/// ```
+pub struct EncryptedBackend<B> {
+    inner: B,
+    key: KeyBytes<'static>,
+    page_size: usize,
+    len: AtomicU64,
+    writes: Mutex<()>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<B: StorageBackend> EncryptedBackend<B> {
+    /// Wraps a backend, encrypting it in pages of [`DEFAULT_PAGE_SIZE`] bytes. An empty backend is
+    /// initialized with a header; anything else must have been written by an `EncryptedBackend`.
+    ///
+    /// # Errors
+    ///
+    /// * See [`EncryptedBackend::with_page_size`].
+    pub fn new(inner: B, key: impl Into<KeyBytes<'static>>) -> io::Result<Self> {
+        Self::with_page_size(inner, key, DEFAULT_PAGE_SIZE)
+    }
+
+    /// Wraps a backend, encrypting it in pages of `page_size` bytes. The page size can't be
+    /// changed once the file is written.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`io::ErrorKind::InvalidInput`] if the page size is zero.
+    ///
+    /// * Returns [`io::ErrorKind::InvalidData`] if the backend isn't empty and wasn't written by an
+    ///   `EncryptedBackend`, or was written with another page size.
+    ///
+    /// * Returns any error raised by the wrapped backend.
+    pub fn with_page_size(
+        inner: B,
+        key: impl Into<KeyBytes<'static>>,
+        page_size: usize,
+    ) -> io::Result<Self> {
+        if page_size == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "page size must not be zero"));
+        }
+
+        let backend = Self {
+            inner,
+            key: key.into(),
+            page_size,
+            len: AtomicU64::new(0),
+            writes: Mutex::new(()),
+        };
+
+        if backend.inner.len()? == 0 {
+            backend.inner.set_len(HEADER_SIZE as u64)?;
+            backend.write_header(0)?;
+        } else {
+            let len = backend.read_header()?;
+            backend.len.store(len, Ordering::Release);
+        }
+
+        Ok(backend)
+    }
+
+    /// Returns the wrapped backend, which holds the encrypted pages.
+    #[must_use]
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    /// Reads the header, checking that it was written by an `EncryptedBackend` with the same page
+    /// size, and returns the database's length.
+    fn read_header(&self) -> io::Result<u64> {
+        let header = self.inner.read(0, HEADER_SIZE)?;
+        let (magic, header) = header.split_at(MAGIC.len());
+        let (page_size, len) = header.split_at(size_of::<u64>());
+        let read_u64 = |bytes: &[u8]| u64::from_le_bytes(bytes.try_into().unwrap_or_default());
+
+        if magic != MAGIC {
+            return Err(invalid_data("not a file written by an `EncryptedBackend`".to_string()));
+        }
+        if read_u64(page_size) != self.page_size as u64 {
+            return Err(invalid_data(format!(
+                "file was encrypted in {}-byte pages, not {}-byte pages",
+                read_u64(page_size),
+                self.page_size,
+            )));
+        }
+        Ok(read_u64(len))
+    }
+
+    /// Writes the header, recording the database's length.
+    fn write_header(&self, len: u64) -> io::Result<()> {
+        let mut header = Vec::with_capacity(HEADER_SIZE);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&(self.page_size as u64).to_le_bytes());
+        header.extend_from_slice(&len.to_le_bytes());
+        self.inner.write(0, &header)
+    }
+
+    /// Returns the size of each page's slot in the wrapped backend.
+    const fn slot_size(&self) -> usize {
+        self.page_size + SLOT_OVERHEAD
+    }
+
+    /// Returns the offset of a page's slot in the wrapped backend.
+    const fn slot_offset(&self, page: u64) -> u64 {
+        HEADER_SIZE as u64 + page * self.slot_size() as u64
+    }
+
+    /// Returns the indexes of the pages that the bytes from `start` up to `end` fall in.
+    const fn pages(&self, start: u64, end: u64) -> Range<u64> {
+        let page_size = self.page_size as u64;
+        start / page_size..end.div_ceil(page_size)
+    }
+
+    /// Returns the part of a page that the bytes from `start` up to `end` fall in, as a range of
+    /// offsets into the page.
+    #[allow(
+        clippy::cast_possible_truncation,
+        reason = "offsets into a page are smaller than the page size, which is a `usize`"
+    )]
+    const fn within(&self, page: u64, start: u64, end: u64) -> Range<usize> {
+        let page_size = self.page_size as u64;
+        let page_start = page * page_size;
+        let from = start.saturating_sub(page_start);
+        let to = if end < page_start + page_size { end - page_start } else { page_size };
+        from as usize..to as usize
+    }
+
+    /// Returns a copy of the key that can be handed to the encryptor.
+    fn key(&self) -> KeyBytes<'_> {
+        KeyBytes::from_array(self.key.as_ref()).with_id(self.key.id())
+    }
+
+    /// Reads and decrypts a page. A slot that was never written reads as a zeroed page.
+    fn read_page(&self, page: u64) -> io::Result<Vec<u8>> {
+        let slot = self.inner.read(self.slot_offset(page), self.slot_size())?;
+        if slot.iter().all(|byte| *byte == 0) {
+            return Ok(vec![0; self.page_size]);
+        }
+
+        let index = page.to_le_bytes();
+        let context = AssociatedData::new(PAGE_CONTEXT, &index);
+        let plain_text = PageCipher::decrypt(Bytes::from_vec(slot), self.key(), &context)
+            .map_err(|error| invalid_data(format!("page {page} could not be decrypted: {error}")))?;
+
+        let plain_text = Vec::from(plain_text);
+        if plain_text.len() != self.page_size {
+            return Err(invalid_data(format!("page {page} has the wrong size")));
+        }
+        Ok(plain_text)
+    }
+
+    /// Encrypts and writes a page.
+    fn write_page(&self, page: u64, plain_text: &[u8]) -> io::Result<()> {
+        let index = page.to_le_bytes();
+        let context = AssociatedData::new(PAGE_CONTEXT, &index);
+        let slot = PageCipher::encrypt(Bytes::from_slice(plain_text), self.key(), &context, None)
+            .map_err(io::Error::other)?;
+        self.inner.write(self.slot_offset(page), &Vec::from(slot))
+    }
+
+    /// Takes the write lock, so that pages aren't rewritten by two writers at once.
+    fn lock_writes(&self) -> io::Result<std::sync::MutexGuard<'_, ()>> {
+        self.writes.lock().map_err(|_| io::Error::other("encrypted backend lock is poisoned"))
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl<B: StorageBackend> StorageBackend for EncryptedBackend<B> {
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.len.load(Ordering::Acquire))
+    }
+
+    fn read(&self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        let end = offset
+            .checked_add(len as u64)
+            .filter(|end| *end <= self.len.load(Ordering::Acquire))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "read past the end"))?;
+
+        let mut buffer = Vec::with_capacity(len);
+        for page in self.pages(offset, end) {
+            let plain_text = self.read_page(page)?;
+            buffer.extend_from_slice(&plain_text[self.within(page, offset, end)]);
+        }
+        Ok(buffer)
+    }
+
+    fn set_len(&self, len: u64) -> io::Result<()> {
+        let _guard = self.lock_writes()?;
+        let page_size = self.page_size as u64;
+
+        // The end of the new last page is zeroed, so that it reads as zeroes if the database grows
+        // again:
+        if len < self.len.load(Ordering::Acquire) && !len.is_multiple_of(page_size) {
+            let page = len / page_size;
+            let mut plain_text = self.read_page(page)?;
+            plain_text[self.within(page, len, page * page_size + page_size)].fill(0);
+            self.write_page(page, &plain_text)?;
+        }
+
+        self.inner.set_len(self.slot_offset(len.div_ceil(page_size)))?;
+        self.write_header(len)?;
+        self.len.store(len, Ordering::Release);
+        Ok(())
+    }
+
+    fn sync_data(&self, eventual: bool) -> io::Result<()> {
+        self.inner.sync_data(eventual)
+    }
+
+    fn write(&self, offset: u64, data: &[u8]) -> io::Result<()> {
+        let _guard = self.lock_writes()?;
+        let end = offset
+            .checked_add(data.len() as u64)
+            .filter(|end| *end <= self.len.load(Ordering::Acquire))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "write past the end"))?;
+
+        let mut written = 0;
+        for page in self.pages(offset, end) {
+            let within = self.within(page, offset, end);
+            let chunk = &data[written..written + within.len()];
+            written += within.len();
+
+            if within.len() == self.page_size {
+                self.write_page(page, chunk)?;
+            } else {
+                let mut plain_text = self.read_page(page)?;
+                plain_text[within].copy_from_slice(chunk);
+                self.write_page(page, &plain_text)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<B: std::fmt::Debug> std::fmt::Debug for EncryptedBackend<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptedBackend")
+            .field("inner", &self.inner)
+            .field("page_size", &self.page_size)
+            .field("len", &self.len.load(Ordering::Acquire))
+            .finish_non_exhaustive()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Returns an error for a file that isn't a valid encrypted database, or can't be decrypted.
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use redb::backends::InMemoryBackend;
+    use redb::TableDefinition;
+    use std::sync::Arc;
+
+    const KEY: &[u8; 32] = b"an example very very secret key.";
+
+    const CREATURES: TableDefinition<&str, &str> = TableDefinition::new("creatures");
+
+    /// A backend that can be reopened after a database using it is dropped.
+    #[derive(Clone, Debug)]
+    struct Shared(Arc<InMemoryBackend>);
+
+    impl StorageBackend for Shared {
+        fn len(&self) -> io::Result<u64> { self.0.len() }
+        fn read(&self, offset: u64, len: usize) -> io::Result<Vec<u8>> { self.0.read(offset, len) }
+        fn set_len(&self, len: u64) -> io::Result<()> { self.0.set_len(len) }
+        fn sync_data(&self, eventual: bool) -> io::Result<()> { self.0.sync_data(eventual) }
+        fn write(&self, offset: u64, data: &[u8]) -> io::Result<()> { self.0.write(offset, data) }
+    }
+
+    #[test]
+    fn unaligned_writes_round_trip() {
+        let backend = EncryptedBackend::with_page_size(InMemoryBackend::new(), *KEY, 64).unwrap();
+        backend.set_len(300).unwrap();
+        backend.write(50, &[7; 100]).unwrap();
+
+        assert_eq!(backend.read(50, 100).unwrap(), vec![7; 100]);
+        assert_eq!(backend.read(0, 50).unwrap(), vec![0; 50]);
+        assert!(backend.read(250, 51).is_err());
+
+        backend.set_len(60).unwrap();
+        backend.set_len(300).unwrap();
+        assert_eq!(backend.read(50, 20).unwrap(), [&[7; 10][..], &[0; 10]].concat());
+    }
+
+    #[test]
+    fn hides_table_names_and_keys() {
+        let shared = Shared(Arc::new(InMemoryBackend::new()));
+        let backend = EncryptedBackend::new(shared.clone(), *KEY).unwrap();
+        let db = redb::Builder::new().create_with_backend(backend).unwrap();
+        let txn = db.begin_write().unwrap();
+        txn.open_table(CREATURES).unwrap().insert("wile e. coyote", "genius").unwrap();
+        txn.commit().unwrap();
+        drop(db);
+
+        let stored = shared.read(0, usize::try_from(shared.len().unwrap()).unwrap()).unwrap();
+        assert!(!stored.windows(9).any(|window| window == b"creatures"));
+        assert!(!stored.windows(14).any(|window| window == b"wile e. coyote"));
+
+        let backend = EncryptedBackend::new(shared.clone(), *KEY).unwrap();
+        let db = redb::Builder::new().create_with_backend(backend).unwrap();
+        let txn = db.begin_read().unwrap();
+        let table = txn.open_table(CREATURES).unwrap();
+        assert_eq!(table.get("wile e. coyote").unwrap().unwrap().value(), "genius");
+        drop((table, txn, db));
+
+        let wrong_key = EncryptedBackend::new(shared, *b"another example very secret key.");
+        assert!(redb::Builder::new().create_with_backend(wrong_key.unwrap()).is_err());
+    }
+}
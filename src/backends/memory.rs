@@ -0,0 +1,161 @@
+//! An in-memory storage backend whose contents can be saved and restored, for targets without a
+//! file system, such as browsers on `wasm32-unknown-unknown`.
+//!
+//! The database lives in memory. Each time `redb` makes a commit durable, the whole database is
+//! handed to a persist callback, which can store it wherever the host keeps data, for example in
+//! `IndexedDB` or local storage. On the next start, the saved bytes are passed to
+//! [`MemoryBackend::from_bytes`] to restore the database. Commits with eventual durability aren't
+//! persisted until the next durable one.
+
+use redb::StorageBackend;
+use std::io;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Saves the whole database somewhere that outlives the process.
+type Persist = Box<dyn Fn(&[u8]) -> io::Result<()> + Send + Sync>;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A storage backend that keeps the database in memory, and hands it to a callback to be saved on
+/// every durable commit. See the [module documentation](self).
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let saved = load_from_indexed_db("creatures")?;
+/// let backend = MemoryBackend::from_bytes(saved.unwrap_or_default())
+///     .on_persist(|bytes| save_to_indexed_db("creatures", bytes));
+/// let db = Database::with_backend(backend)?;
+/// ```
+#[derive(Default)]
+pub struct MemoryBackend {
+    contents: RwLock<Vec<u8>>,
+    persist: Option<Persist>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl MemoryBackend {
+    /// Instantiates an empty backend that isn't persisted.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Instantiates a backend holding a database that was saved by a persist callback.
+    #[must_use]
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Self { contents: RwLock::new(bytes), persist: None }
+    }
+
+    /// Sets a callback that saves the whole database each time a commit is made durable. If it
+    /// fails, the commit fails with its error.
+    #[must_use]
+    pub fn on_persist(
+        mut self,
+        persist: impl Fn(&[u8]) -> io::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.persist = Some(Box::new(persist));
+        self
+    }
+
+    /// Takes the lock for reading.
+    fn read_lock(&self) -> io::Result<RwLockReadGuard<'_, Vec<u8>>> {
+        self.contents.read().map_err(|_| io::Error::other("memory backend lock is poisoned"))
+    }
+
+    /// Takes the lock for writing.
+    fn write_lock(&self) -> io::Result<RwLockWriteGuard<'_, Vec<u8>>> {
+        self.contents.write().map_err(|_| io::Error::other("memory backend lock is poisoned"))
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl StorageBackend for MemoryBackend {
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.read_lock()?.len() as u64)
+    }
+
+    fn read(&self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        let contents = self.read_lock()?;
+        usize::try_from(offset)
+            .ok()
+            .and_then(|start| contents.get(start..start.checked_add(len)?))
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "read past the end"))
+    }
+
+    fn set_len(&self, len: u64) -> io::Result<()> {
+        let len = usize::try_from(len).map_err(io::Error::other)?;
+        self.write_lock()?.resize(len, 0);
+        Ok(())
+    }
+
+    fn sync_data(&self, eventual: bool) -> io::Result<()> {
+        match &self.persist {
+            Some(persist) if !eventual => persist(&self.read_lock()?),
+            _ => Ok(()),
+        }
+    }
+
+    fn write(&self, offset: u64, data: &[u8]) -> io::Result<()> {
+        let mut contents = self.write_lock()?;
+        usize::try_from(offset)
+            .ok()
+            .and_then(|start| contents.get_mut(start..start.checked_add(data.len())?))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "write past the end"))?
+            .copy_from_slice(data);
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for MemoryBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MemoryBackend")
+            .field("len", &self.read_lock().map(|contents| contents.len()).ok())
+            .field("persisted", &self.persist.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use redb::TableDefinition;
+    use std::sync::{Arc, Mutex};
+
+    const CREATURES: TableDefinition<&str, &str> = TableDefinition::new("creatures");
+
+    #[test]
+    fn restores_what_was_persisted() {
+        let saved = Arc::new(Mutex::new(Vec::new()));
+        let save = Arc::clone(&saved);
+        let backend = MemoryBackend::new().on_persist(move |bytes| {
+            bytes.clone_into(&mut save.lock().unwrap());
+            Ok(())
+        });
+
+        let db = redb::Builder::new().create_with_backend(backend).unwrap();
+        let txn = db.begin_write().unwrap();
+        txn.open_table(CREATURES).unwrap().insert("road runner", "fast").unwrap();
+        txn.commit().unwrap();
+        drop(db);
+
+        let backend = MemoryBackend::from_bytes(saved.lock().unwrap().clone());
+        let db = redb::Builder::new().create_with_backend(backend).unwrap();
+        let txn = db.begin_read().unwrap();
+        let table = txn.open_table(CREATURES).unwrap();
+        assert_eq!(table.get("road runner").unwrap().unwrap().value(), "fast");
+    }
+}
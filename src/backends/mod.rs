@@ -0,0 +1,22 @@
+//! `redb` storage backends that wrap other backends, for use with `Database::with_backend`.
+//!
+//! `redb`'s own backends, such as `FileBackend` and `InMemoryBackend`, are re-exported under
+//! `atlatl::redb::backends`.
+
+#[cfg(feature = "encryptors")]
+mod encrypted;
+
+mod memory;
+pub use crate::backends::memory::MemoryBackend;
+
+mod object_store;
+pub use crate::backends::object_store::{
+    DEFAULT_CACHE_PAGES,
+    DEFAULT_FETCH_SIZE,
+    ObjectStore,
+    ObjectStoreBackend,
+    ObjectVersion,
+};
+
+#[cfg(feature = "encryptors")]
+pub use crate::backends::encrypted::{DEFAULT_PAGE_SIZE, EncryptedBackend};
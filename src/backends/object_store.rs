@@ -0,0 +1,608 @@
+//! A storage backend for read-mostly databases kept in an object store, such as S3 or GCS, so that
+//! read replicas can be shipped to serverless or edge deployments without a local copy of the
+//! file.
+//!
+//! The database is one object. Reads are served by ranged GETs, a page at a time, and the pages
+//! are kept in a bounded local cache so that hot pages are only fetched once. Writes are staged in
+//! memory, and the whole object is uploaded with one PUT when `redb` makes a commit durable.
+//! Commits with eventual durability stay staged until the next durable one. An object store
+//! replaces objects atomically, so other readers see either the old database or the new one.
+//!
+//! `atlatl` doesn't depend on an object store client. Implement [`ObjectStore`] with the client of
+//! your choice.
+//!
+//! # Notes
+//!
+//! * Every durable commit uploads the whole database, so this backend suits databases that are
+//!   written rarely, for example by a publishing job, and read often.
+//!
+//! * The object's version, such as its `ETag`, is pinned when the backend is opened, and every
+//!   ranged GET asks for that version. If the object is replaced by another writer, pages that
+//!   aren't cached can no longer be read, and reads fail instead of mixing two versions of the
+//!   database. Reopen the backend to read the new version.
+//!
+//! * Uploads are conditional on the pinned version too, so two writers can't overwrite each
+//!   other's uploads: the second writer's durable commit fails. Only one writer should use an
+//!   object at a time.
+
+use redb::StorageBackend;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io;
+use std::ops::Range;
+use std::sync::{Mutex, MutexGuard};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// Size of the pages fetched with each ranged GET, unless set with
+/// [`ObjectStoreBackend::page_size`].
+pub const DEFAULT_FETCH_SIZE: usize = 64 * 1_024;
+
+/// Number of fetched pages kept in the local cache, unless set with
+/// [`ObjectStoreBackend::cache_pages`]. With the default page size, about 64 MiB.
+pub const DEFAULT_CACHE_PAGES: usize = 1_024;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A single object in an object store, such as an S3 or GCS bucket's object, that holds the
+/// database file.
+///
+/// Objects are identified by a version: an `ETag`, or a version ID or generation number if the
+/// bucket keeps them. Reads and writes are conditional on the version the backend was opened with,
+/// so that a replaced object is noticed instead of read or overwritten.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// #[derive(Debug)]
+/// struct S3Object { client: s3::Client, bucket: String, key: String }
+///
+/// impl ObjectStore for S3Object {
+///     fn head(&self) -> std::io::Result<Option<ObjectVersion>> {
+///         block_on(self.client.head_object().bucket(&self.bucket).key(&self.key).send())
+///             .map(|head| Some(ObjectVersion {
+///                 len: head.content_length().unwrap_or_default() as u64,
+///                 tag: head.e_tag().unwrap_or_default().to_string(),
+///             }))
+///             .or_else(not_found_as_none)
+///     }
+///
+///     fn get_range(&self, range: Range<u64>, tag: &str) -> std::io::Result<Vec<u8>> {
+///         let range = format!("bytes={}-{}", range.start, range.end - 1);
+///         let object = self.client.get_object().bucket(&self.bucket).key(&self.key);
+///         let object = object.range(range).if_match(tag);
+///         block_on(collect_body(object.send())).map_err(std::io::Error::other)
+///     }
+///
+///     fn put(&self, contents: Vec<u8>, tag: Option<&str>) -> std::io::Result<String> {
+///         let object = self.client.put_object().bucket(&self.bucket).key(&self.key);
+///         let object = match tag {
+///             Some(tag) => object.if_match(tag),
+///             None => object.if_none_match("*"),
+///         };
+///         block_on(object.body(contents.into()).send())
+///             .map(|put| put.e_tag().unwrap_or_default().to_string())
+///             .map_err(std::io::Error::other)
+///     }
+/// }
+/// ```
+pub trait ObjectStore: std::fmt::Debug + Send + Sync + 'static {
+    /// Returns the size and version of the object, or `None` if it doesn't exist yet.
+    ///
+    /// # Errors
+    ///
+    /// * The object store couldn't be reached, or refused the request.
+    fn head(&self) -> io::Result<Option<ObjectVersion>>;
+
+    /// Reads a range of the object's bytes, with a ranged GET, if the object is still the version
+    /// tagged `tag`. For example, by sending the tag in an `If-Match` header. The range is never
+    /// empty, and never extends past the object's end.
+    ///
+    /// # Errors
+    ///
+    /// * The object has been replaced by another version.
+    ///
+    /// * The object store couldn't be reached, or refused the request.
+    fn get_range(&self, range: Range<u64>, tag: &str) -> io::Result<Vec<u8>>;
+
+    /// Replaces the object's contents if it's still the version tagged `tag`, or creates it if
+    /// `tag` is `None` and it doesn't exist yet. For example, with an `If-Match` or an
+    /// `If-None-Match: *` header. Returns the tag of the new version.
+    ///
+    /// # Errors
+    ///
+    /// * The object has been replaced by another version, or was created by another writer.
+    ///
+    /// * The object store couldn't be reached, or refused the request.
+    fn put(&self, contents: Vec<u8>, tag: Option<&str>) -> io::Result<String>;
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The size and version of an object, as its object store reports them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ObjectVersion {
+    /// The object's size, in bytes.
+    pub len: u64,
+
+    /// The object's version: its `ETag`, or a version ID or generation number. For example:
+    /// `"\"9b2cf535f27731c974343645a3985328\""`.
+    pub tag: String,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A storage backend that serves a database from an [`ObjectStore`]. See the
+/// [module documentation](self).
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let backend = ObjectStoreBackend::new(S3Object::new(client, "replicas", "creatures.redb"))?
+///     .cache_pages(4_096);
+/// let db = Database::with_backend(backend)?;
+/// ```
+#[derive(Debug)]
+pub struct ObjectStoreBackend<S> {
+    store: S,
+    page_size: usize,
+    cache_pages: usize,
+    state: Mutex<State>,
+
+    /// Held for the whole of an upload, so that uploads don't overlap. Unlike the state's lock,
+    /// it's held across network requests.
+    uploading: Mutex<()>,
+}
+
+/// What an [`ObjectStoreBackend`] knows about the database, between uploads.
+#[derive(Debug, Default)]
+struct State {
+    /// The database's length, including staged changes.
+    len: u64,
+
+    /// The number of leading bytes of the object that are still part of the database. Shrinking
+    /// the database discards the rest, which must then read as zeroes.
+    uploaded: u64,
+
+    /// Pages written since the last upload.
+    staged: BTreeMap<u64, Vec<u8>>,
+
+    /// The tag of the object's version that's read from and replaced, or `None` if the object
+    /// hasn't been created yet.
+    tag: Option<String>,
+
+    /// Pages fetched from the object store.
+    cache: HashMap<u64, Vec<u8>>,
+
+    /// The order pages were cached in, oldest first, for eviction.
+    cached_order: VecDeque<u64>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<S: ObjectStore> ObjectStoreBackend<S> {
+    /// Opens the database held in an object, which is created on the first durable commit if it
+    /// doesn't exist yet. The object's current version is pinned, and is the only version read.
+    ///
+    /// # Errors
+    ///
+    /// * Returns any error raised by the object store.
+    pub fn new(store: S) -> io::Result<Self> {
+        let (len, tag) = store
+            .head()?
+            .map_or((0, None), |version| (version.len, Some(version.tag)));
+        let state = State { len, uploaded: len, tag, ..State::default() };
+        Ok(Self {
+            store,
+            page_size: DEFAULT_FETCH_SIZE,
+            cache_pages: DEFAULT_CACHE_PAGES,
+            state: Mutex::new(state),
+            uploading: Mutex::new(()),
+        })
+    }
+
+    /// Sets the size of the pages fetched with each ranged GET (defaults to `65536`). Larger pages
+    /// make fewer requests, but fetch more bytes that may not be needed.
+    #[must_use]
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Sets the number of fetched pages kept in the local cache (defaults to `1024`).
+    #[must_use]
+    pub const fn cache_pages(mut self, cache_pages: usize) -> Self {
+        self.cache_pages = cache_pages;
+        self
+    }
+
+    /// Returns the object store that holds the database.
+    #[must_use]
+    pub const fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Returns the indexes of the pages that the bytes from `start` up to `end` fall in.
+    const fn pages(&self, start: u64, end: u64) -> Range<u64> {
+        let page_size = self.page_size as u64;
+        start / page_size..end.div_ceil(page_size)
+    }
+
+    /// Returns the part of a page that the bytes from `start` up to `end` fall in, as a range of
+    /// offsets into the page. The range is empty if none of them do.
+    #[allow(
+        clippy::cast_possible_truncation,
+        reason = "offsets into a page are smaller than the page size, which is a `usize`"
+    )]
+    fn within(&self, page: u64, start: u64, end: u64) -> Range<usize> {
+        let page_start = page * self.page_size as u64;
+        let page_end = page_start + self.page_size as u64;
+        let from = start.clamp(page_start, page_end) - page_start;
+        let to = end.clamp(page_start, page_end) - page_start;
+        from as usize..to.max(from) as usize
+    }
+
+    /// Returns a page's current contents: staged, cached, or fetched from the object store. The
+    /// state's lock is released while fetching, so that other pages can be read meanwhile.
+    fn page<'s>(
+        &'s self,
+        state: MutexGuard<'s, State>,
+        page: u64,
+    ) -> io::Result<(MutexGuard<'s, State>, Vec<u8>)> {
+        if let Some(contents) = state.staged.get(&page).or_else(|| state.cache.get(&page)) {
+            let contents = contents.clone();
+            return Ok((state, contents));
+        }
+
+        let fetch = self.within(page, 0, state.uploaded);
+        let tag = state.tag.clone();
+        drop(state);
+
+        let mut contents = match tag {
+            Some(tag) if !fetch.is_empty() => {
+                let start = page * self.page_size as u64;
+                self.fetch(start + fetch.start as u64..start + fetch.end as u64, &tag)?
+            },
+            _ => Vec::new(),
+        };
+        contents.resize(self.page_size, 0);
+
+        let mut state = self.lock()?;
+        self.cache(&mut state, page, contents.clone());
+        Ok((state, contents))
+    }
+
+    /// Keeps a fetched page in the cache, evicting the oldest pages if it's full.
+    fn cache(&self, state: &mut State, page: u64, contents: Vec<u8>) {
+        if self.cache_pages == 0 || state.staged.contains_key(&page) {
+            return;
+        }
+        if state.cache.insert(page, contents).is_none() {
+            state.cached_order.push_back(page);
+        }
+        while state.cache.len() > self.cache_pages {
+            let Some(oldest) = state.cached_order.pop_front() else { break };
+            state.cache.remove(&oldest);
+        }
+    }
+
+    /// Reads a range of the pinned version of the object, and checks that all of it was returned.
+    ///
+    /// # Errors
+    ///
+    /// * Returns any error raised by the object store, or [`io::ErrorKind::UnexpectedEof`] if it
+    ///   returned fewer bytes than were asked for.
+    fn fetch(&self, range: Range<u64>, tag: &str) -> io::Result<Vec<u8>> {
+        let expected = range.end - range.start;
+        let contents = self.store.get_range(range, tag)?;
+        if contents.len() as u64 == expected {
+            Ok(contents)
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("object store returned {} bytes, not {expected}", contents.len()),
+            ))
+        }
+    }
+
+    /// Uploads the database, with its staged pages, as the object's new contents.
+    ///
+    /// The object is assembled from the staged and cached pages, and only the pages that are
+    /// neither are fetched. The state's lock isn't held while fetching or uploading, so that pages
+    /// can be read meanwhile. Staged pages become cached pages once they've been uploaded.
+    fn upload(&self) -> io::Result<()> {
+        let _uploading = self
+            .uploading
+            .lock()
+            .map_err(|_| io::Error::other("object store backend lock is poisoned"))?;
+
+        let page_size = self.page_size as u64;
+        let page_start = |page: u64| usize::try_from(page * page_size).map_err(io::Error::other);
+
+        let state = self.lock()?;
+        if state.staged.is_empty() && state.uploaded == state.len {
+            return Ok(());
+        }
+        let len = state.len;
+        let kept = state.uploaded.min(len);
+        let tag = state.tag.clone();
+        let staged = state.staged.clone();
+
+        // Pages that are neither staged nor cached, grouped into runs that are fetched together:
+        let mut contents = vec![0; usize::try_from(len).map_err(io::Error::other)?];
+        let mut missing: Vec<Range<u64>> = Vec::new();
+        for page in self.pages(0, len) {
+            let range = self.within(page, 0, len);
+            let start = page_start(page)?;
+            match state.staged.get(&page).or_else(|| state.cache.get(&page)) {
+                Some(known) => contents[start + range.start..start + range.end]
+                    .copy_from_slice(&known[range]),
+                None if self.within(page, 0, kept).is_empty() => {},
+                None => match missing.last_mut() {
+                    Some(run) if run.end == page => run.end = page + 1,
+                    _ => missing.push(page..page + 1),
+                },
+            }
+        }
+        drop(state);
+
+        for run in missing {
+            let range = run.start * page_size..(run.end * page_size).min(kept);
+            let start = page_start(run.start)?;
+            let fetched = match &tag {
+                Some(tag) => self.fetch(range.clone(), tag)?,
+                None => return Err(io::Error::other("object store object has no version")),
+            };
+            contents[start..start + fetched.len()].copy_from_slice(&fetched);
+        }
+
+        let tag = self.store.put(contents, tag.as_deref())?;
+
+        let mut state = self.lock()?;
+        state.tag = Some(tag);
+        state.uploaded = len.min(state.len);
+        for (page, contents) in staged {
+            // A page that was written again during the upload stays staged:
+            if state.staged.get(&page) == Some(&contents) {
+                state.staged.remove(&page);
+                self.cache(&mut state, page, contents);
+            }
+        }
+        drop(state);
+        Ok(())
+    }
+
+    /// Takes the state's lock.
+    fn lock(&self) -> io::Result<MutexGuard<'_, State>> {
+        self.state.lock().map_err(|_| io::Error::other("object store backend lock is poisoned"))
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+#[allow(
+    clippy::significant_drop_tightening,
+    reason = "the state is locked for a whole operation, so that its pages are consistent"
+)]
+impl<S: ObjectStore> StorageBackend for ObjectStoreBackend<S> {
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.lock()?.len)
+    }
+
+    fn read(&self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        let mut state = self.lock()?;
+        let end = offset
+            .checked_add(len as u64)
+            .filter(|end| *end <= state.len)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "read past the end"))?;
+
+        let mut buffer = Vec::with_capacity(len);
+        for page in self.pages(offset, end) {
+            let (guard, contents) = self.page(state, page)?;
+            state = guard;
+            buffer.extend_from_slice(&contents[self.within(page, offset, end)]);
+        }
+        Ok(buffer)
+    }
+
+    fn set_len(&self, len: u64) -> io::Result<()> {
+        let mut state = self.lock()?;
+        if len < state.len {
+            // Bytes past the new end must read as zeroes if the database grows again:
+            let page_size = self.page_size as u64;
+            let last_page = len / page_size;
+            let tail = self.within(last_page, len, last_page * page_size + page_size);
+            if !tail.is_empty() {
+                let (guard, mut contents) = self.page(state, last_page)?;
+                state = guard;
+                contents[tail].fill(0);
+                state.cache.remove(&last_page);
+                state.staged.insert(last_page, contents);
+            }
+
+            let first_removed = len.div_ceil(page_size);
+            state.staged.retain(|page, _| *page < first_removed);
+            state.cache.retain(|page, _| *page < first_removed);
+            state.uploaded = state.uploaded.min(len);
+        }
+        state.len = len;
+        Ok(())
+    }
+
+    fn sync_data(&self, eventual: bool) -> io::Result<()> {
+        if eventual {
+            return Ok(());
+        }
+        self.upload()
+    }
+
+    fn write(&self, offset: u64, data: &[u8]) -> io::Result<()> {
+        let mut state = self.lock()?;
+        let end = offset
+            .checked_add(data.len() as u64)
+            .filter(|end| *end <= state.len)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "write past the end"))?;
+
+        let mut written = 0;
+        for page in self.pages(offset, end) {
+            let within = self.within(page, offset, end);
+            let chunk = &data[written..written + within.len()];
+            written += within.len();
+
+            let contents = if within.len() == self.page_size {
+                chunk.to_vec()
+            } else {
+                let (guard, mut contents) = self.page(state, page)?;
+                state = guard;
+                contents[within].copy_from_slice(chunk);
+                contents
+            };
+            state.cache.remove(&page);
+            state.staged.insert(page, contents);
+        }
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use redb::TableDefinition;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    const CREATURES: TableDefinition<&str, &str> = TableDefinition::new("creatures");
+
+    /// An object's contents and version number, or `None` if it doesn't exist.
+    type Versioned = Option<(Vec<u8>, u64)>;
+
+    /// An object kept in memory, with its version number, counting the requests made to it and
+    /// the bytes fetched.
+    #[derive(Clone, Debug, Default)]
+    struct Bucket {
+        object: Arc<Mutex<Versioned>>,
+        gets: Arc<AtomicU64>,
+        fetched: Arc<AtomicU64>,
+        puts: Arc<AtomicU64>,
+    }
+
+    impl Bucket {
+        /// Replaces the object, as another writer would.
+        fn replace(&self, contents: Vec<u8>) {
+            let mut object = self.object.lock().unwrap();
+            let version = object.as_ref().map_or(0, |(_, version)| version + 1);
+            *object = Some((contents, version));
+        }
+    }
+
+    #[allow(
+        clippy::significant_drop_tightening,
+        reason = "the object is locked for a whole request, so that requests are atomic"
+    )]
+    impl ObjectStore for Bucket {
+        fn head(&self) -> io::Result<Option<ObjectVersion>> {
+            Ok(self.object.lock().unwrap().as_ref().map(|(object, version)| ObjectVersion {
+                len: object.len() as u64,
+                tag: version.to_string(),
+            }))
+        }
+
+        fn get_range(&self, range: Range<u64>, tag: &str) -> io::Result<Vec<u8>> {
+            self.gets.fetch_add(1, Ordering::Relaxed);
+            self.fetched.fetch_add(range.end - range.start, Ordering::Relaxed);
+            let object = self.object.lock().unwrap();
+            let (object, version) = object.as_ref().unwrap();
+            if version.to_string() != tag {
+                return Err(io::Error::other("precondition failed"));
+            }
+            let range = usize::try_from(range.start).unwrap()..usize::try_from(range.end).unwrap();
+            Ok(object[range].to_vec())
+        }
+
+        fn put(&self, contents: Vec<u8>, tag: Option<&str>) -> io::Result<String> {
+            self.puts.fetch_add(1, Ordering::Relaxed);
+            let mut object = self.object.lock().unwrap();
+            let current = object.as_ref().map(|(_, version)| version.to_string());
+            if current.as_deref() != tag {
+                return Err(io::Error::other("precondition failed"));
+            }
+            let version = object.as_ref().map_or(0, |(_, version)| version + 1);
+            *object = Some((contents, version));
+            Ok(version.to_string())
+        }
+    }
+
+    #[test]
+    fn replicas_read_what_was_uploaded() {
+        let bucket = Bucket::default();
+        let backend = ObjectStoreBackend::new(bucket.clone()).unwrap().page_size(4_096);
+        let db = redb::Builder::new().create_with_backend(backend).unwrap();
+        let txn = db.begin_write().unwrap();
+        txn.open_table(CREATURES).unwrap().insert("wile e. coyote", "genius").unwrap();
+        txn.commit().unwrap();
+        drop(db);
+        assert!(bucket.puts.load(Ordering::Relaxed) > 0);
+
+        let backend = ObjectStoreBackend::new(bucket).unwrap().page_size(4_096);
+        let db = redb::Builder::new().create_with_backend(backend).unwrap();
+        let txn = db.begin_read().unwrap();
+        let table = txn.open_table(CREATURES).unwrap();
+        assert_eq!(table.get("wile e. coyote").unwrap().unwrap().value(), "genius");
+    }
+
+    #[test]
+    fn pages_are_fetched_once_and_staged_until_durable() {
+        let bucket = Bucket::default();
+        bucket.replace(vec![7; 300]);
+        let backend = ObjectStoreBackend::new(bucket.clone()).unwrap().page_size(100);
+
+        assert_eq!(backend.read(150, 20).unwrap(), vec![7; 20]);
+        assert_eq!(backend.read(160, 20).unwrap(), vec![7; 20]);
+        assert_eq!(bucket.gets.load(Ordering::Relaxed), 1);
+
+        backend.write(290, &[1; 10]).unwrap();
+        backend.sync_data(true).unwrap();
+        assert_eq!(bucket.puts.load(Ordering::Relaxed), 0);
+        backend.sync_data(false).unwrap();
+        assert_eq!(bucket.puts.load(Ordering::Relaxed), 1);
+
+        // Only the page that was neither cached nor staged was fetched for the upload:
+        assert_eq!(bucket.gets.load(Ordering::Relaxed), 3);
+        assert_eq!(bucket.fetched.load(Ordering::Relaxed), 300);
+        assert_eq!(bucket.get_range(280..300, "1").unwrap(), [[7; 10], [1; 10]].concat());
+
+        backend.set_len(250).unwrap();
+        backend.set_len(300).unwrap();
+        assert_eq!(backend.read(240, 20).unwrap(), [[7; 10], [0; 10]].concat());
+    }
+
+    #[test]
+    fn a_replaced_object_is_neither_read_nor_overwritten() {
+        let bucket = Bucket::default();
+        bucket.replace(vec![7; 300]);
+        let backend = ObjectStoreBackend::new(bucket.clone()).unwrap().page_size(100);
+        assert_eq!(backend.read(0, 10).unwrap(), vec![7; 10]);
+
+        // Another writer replaces the object. Cached pages are still the pinned version's:
+        bucket.replace(vec![9; 300]);
+        assert_eq!(backend.read(0, 10).unwrap(), vec![7; 10]);
+        assert!(backend.read(100, 10).is_err());
+
+        backend.write(0, &[1; 10]).unwrap();
+        assert!(backend.sync_data(false).is_err());
+        assert_eq!(bucket.object.lock().unwrap().as_ref().unwrap().0, vec![9; 300]);
+
+        // Reopening pins the new version:
+        let backend = ObjectStoreBackend::new(bucket).unwrap().page_size(100);
+        assert_eq!(backend.read(100, 10).unwrap(), vec![9; 10]);
+    }
+}
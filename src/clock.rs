@@ -0,0 +1,54 @@
+//! The wall clock that history, audit log, retention, snapshot, bulk load, and merge timestamps are
+//! read from.
+//!
+//! `std::time::SystemTime::now` panics on `wasm32-unknown-unknown`, which has no clock without
+//! JavaScript bindings. There, install one with [`set_clock`] before the database is written, for
+//! example with `js-sys`:
+//!
+//! ```rust,ignore
+//! use std::time::{Duration, SystemTime, UNIX_EPOCH};
+//!
+//! atlatl::clock::set_clock(|| {
+//!     UNIX_EPOCH + Duration::from_millis(js_sys::Date::now() as u64)
+//! });
+//! ```
+//!
+//! Until a clock is installed, every reading on that target is the Unix epoch, so that a missing
+//! clock never stops a write. History versions are then all stamped at the epoch, and retention
+//! finds nothing to expire. Other targets read the system clock unless another one is installed.
+
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Whether the target has no clock that the standard library can read.
+const NO_CLOCK: bool = cfg!(all(target_arch = "wasm32", target_os = "unknown"));
+
+/// The installed clock, if any.
+static CLOCK: OnceLock<fn() -> SystemTime> = OnceLock::new();
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Installs the clock that `atlatl` reads the current time from, for the rest of the process.
+/// Returns `false`, and leaves the clock as it was, if one has already been installed.
+pub fn set_clock(clock: fn() -> SystemTime) -> bool {
+    CLOCK.set(clock).is_ok()
+}
+
+/// Returns the current time from the installed clock, or the system clock if there's none. On
+/// targets without a system clock, returns the Unix epoch until a clock is installed.
+#[must_use]
+pub fn now() -> SystemTime {
+    match CLOCK.get() {
+        Some(clock) => clock(),
+        None if NO_CLOCK => UNIX_EPOCH,
+        None => SystemTime::now(),
+    }
+}
+
+/// Returns the time since `earlier`, or zero if the clock reads earlier than that.
+#[must_use]
+pub fn since(earlier: SystemTime) -> Duration {
+    now().duration_since(earlier).unwrap_or_default()
+}
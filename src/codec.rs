@@ -0,0 +1,65 @@
+//! Conversion of keys and values to and from the bytes stored in `redb` tables.
+
+use crate::Error;
+use crate::layers::core::Bytes;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Converts a key or value of type `T` to and from the bytes stored in a `redb` table.
+///
+/// Every type that the enabled serializer supports implements `Codec` through the
+/// [`Serializer`](crate::layers::Serializer) trait, so it rarely needs to be implemented by hand.
+///
+/// `Codec` only serializes. Record types that are compressed, encrypted, or protected by error
+/// correction declare their [`RecordLayers`] with [`HasTable::layers`], and transactions pass
+/// their records through them after serializing and before deserializing. Values written through
+/// the raw [`TableMut`](crate::typed::TableMut) and [`TableRef`](crate::typed::TableRef) wrappers
+/// are only serialized.
+///
+/// [`RecordLayers`]: crate::typed::record_layers::RecordLayers
+/// [`HasTable::layers`]: crate::indexing::HasTable::layers
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let bytes = u64::serialize(&7)?;
+/// assert_eq!(u64::deserialize(&bytes)?, 7);
+/// ```
+pub trait Codec<T> {
+    /// Serializes a value into the bytes that are stored.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Serialize`] if the serializer can't represent the value.
+    fn serialize(value: &T) -> Result<Vec<u8>, Error>;
+
+    /// Deserializes a value from the bytes that were stored.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Deserialize`] if the bytes are corrupted, or don't represent a value of
+    ///   type `T`.
+    fn deserialize(bytes: &[u8]) -> Result<T, Error>;
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+#[cfg(not(feature = "serialize-rkyv"))]
+impl<T> Codec<T> for T
+where
+    T: for<'b> crate::layers::Serializer<'b, T>,
+{
+    fn serialize(value: &T) -> Result<Vec<u8>, Error> {
+        Ok(value.serialize_ref()?.into())
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<T, Error> {
+        <T as crate::layers::Serializer<'_, T>>::deserialize(Bytes::from_slice(bytes))?
+            .into_owned()
+            .ok_or_else(|| Error::Corrupted {
+                message: "deserializer returned a borrowed value".to_string(),
+            })
+    }
+}
@@ -0,0 +1,108 @@
+//! Per-type hooks that fill in missing or derived fields just before a record is serialized on
+//! insert.
+//!
+//! Centralizing normalization here, rather than at each call site, means an index built on a
+//! normalized field (a lower-cased email, a slug derived from a name) can't be poisoned by a write
+//! from some code path that forgot to normalize.
+
+use std::borrow::Cow;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Fills in missing or derived fields on a record before it's written.
+///
+/// Defaults are applied on every insert, before the record is serialized and before its secondary
+/// index keys are computed.
+///
+/// # Example
+///
+/// ```rust
+/// use atlatl::defaults::Defaults;
+///
+/// #[derive(Clone)]
+/// struct Article { title: String, slug: String }
+///
+/// impl Defaults for Article {
+///     fn apply_defaults(&mut self) {
+///         if self.slug.is_empty() {
+///             self.slug = self.title.to_lowercase().replace(' ', "-");
+///         }
+///     }
+/// }
+///
+/// let article = Article { title: "Hello World".into(), slug: String::new() };
+/// assert_eq!(article.with_defaults().slug, "hello-world");
+/// ```
+///
+/// # Notes
+///
+/// * Primary keys should not be derived fields. The primary key is read from the record as it was
+///   passed in, before defaults are applied.
+///
+/// * Hooks should be idempotent. A record that's read, modified, and written back will have its
+///   defaults applied again.
+pub trait Defaults: Clone {
+    /// Fills in missing or derived fields in place. The default implementation does nothing.
+    fn apply_defaults(&mut self) {}
+
+    /// Returns the record with its defaults applied.
+    ///
+    /// The default implementation clones the record and calls [`Defaults::apply_defaults`].
+    /// Override this method to return `Cow::Borrowed(self)` when the record is already
+    /// normalized, or when the type has no derived fields, to avoid the clone.
+    fn with_defaults(&self) -> Cow<'_, Self> {
+        let mut record = self.clone();
+        record.apply_defaults();
+        Cow::Owned(record)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct User {
+        name: String,
+        email: String,
+    }
+
+    impl Defaults for User {
+        fn apply_defaults(&mut self) {
+            self.email = self.email.trim().to_lowercase();
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Counter(u64);
+
+    impl Defaults for Counter {
+        fn with_defaults(&self) -> Cow<'_, Self> {
+            Cow::Borrowed(self)
+        }
+    }
+
+    #[test]
+    fn defaults_are_applied() {
+        let user = User { name: "Jane".into(), email: "  Jane@Example.COM ".into() };
+        assert_eq!(user.with_defaults().email, "jane@example.com");
+    }
+
+    #[test]
+    fn defaults_are_idempotent() {
+        let user = User { name: "Jane".into(), email: "Jane@Example.com".into() };
+        let once = user.with_defaults().into_owned();
+        let twice = once.with_defaults().into_owned();
+        assert_eq!(once, twice);
+    }
+
+    #[test]
+    fn borrowed_defaults_do_not_clone() {
+        let counter = Counter(7);
+        assert!(matches!(counter.with_defaults(), Cow::Borrowed(_)));
+    }
+}
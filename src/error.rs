@@ -1,10 +1,29 @@
 //! Error returned from the `atlatl` crate. This includes codec errors, storage errors, database
 //! errors, and so on.
+//!
+//! Errors from table operations carry where they happened: the operation, the table, the key, and
+//! the layer that failed, if any. See [`Error::context`].
+
+use std::fmt::{Display, Formatter};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// The number of key bytes shown by [`DisplayKey`]. Longer keys are truncated.
+const SHOWN_KEY_BYTES: usize = 32;
 
 // -------------------------------------------------------------------------------------------------
 //
 /// Error returned from the `atlatl` crate. This includes codec errors, storage errors, database
 /// errors, and so on.
+///
+/// # Storage errors
+///
+/// The `Redb*` variants carry errors from `redb`, the storage engine underneath `atlatl`. Any
+/// method that opens, reads, or writes a table may return one. They're caused by input/output
+/// failures, a full disk, missing permissions, or a corrupted database file. Once a write fails
+/// part-way, `redb` may also refuse further operations until the database is reopened.
 #[derive(thiserror::Error, Debug)]
 #[non_exhaustive]
 pub enum Error {
@@ -52,6 +71,334 @@ pub enum Error {
     #[error("index kind not found for index lookup")]
     MissingIndexKind,
 
+    /// A parameterized query was executed before one of its placeholders was bound to a value.
+    #[error("query parameter `{name}` was not bound to a value")]
+    UnboundParameter {
+        name: &'static str,
+    },
+
+    /// A prepared query's template has a custom predicate that wasn't shared when the query was
+    /// prepared, so it can't be copied into an executable query. This indicates a bug in `atlatl`
+    /// rather than in the query.
+    #[cfg(feature = "custom-queries")]
+    #[error("custom predicate of a prepared query is not shared")]
+    UnsharedPredicate,
+
+    /// A value was bound to a placeholder name that doesn't appear in the parameterized query.
+    #[error("query has no parameter named `{name}`")]
+    UnknownParameter {
+        name: &'static str,
+    },
+
+    /// A parameterized query's placeholder was bound to a different index look-up type than the
+    /// one it was declared with. For example, a `Habitat` placeholder bound with a `Species`.
+    #[error("query parameter `{name}` expects `{expected}` but `{provided}` was bound")]
+    ParameterTypeMismatch {
+        name: &'static str,
+        expected: &'static str,
+        provided: &'static str,
+    },
+
+    /// A record refers to a primary key that doesn't exist in the referenced table.
+    #[error("`{table}.{reference}` refers to a primary key that does not exist in `{parent_table}`")]
+    ForeignKeyViolation {
+        table: &'static str,
+        reference: &'static str,
+        parent_table: &'static str,
+        key: Vec<u8>,
+    },
+
+    /// A record could not be deleted because another record still refers to it, and that
+    /// reference's `on_delete` policy is `Restrict`.
+    #[error("record in `{parent_table}` is still referenced by `{table}.{reference}`")]
+    ReferencedByDependent {
+        parent_table: &'static str,
+        table: &'static str,
+        reference: &'static str,
+        key: Vec<u8>,
+    },
+
+    /// A record was changed by another writer since it was read. Its generation no longer matches
+    /// the generation the caller expected.
+    #[error("record in `{table}` is at generation {found}, but generation {expected} was expected")]
+    GenerationMismatch {
+        table: &'static str,
+        key: Vec<u8>,
+        expected: u64,
+        found: u64,
+    },
+
+    /// An entry couldn't be decoded, so it was kept rather than passed to a `retain` or
+    /// `extract_if` predicate.
+    #[error("entry in `{table}` couldn't be decoded: {source}")]
+    Undecodable {
+        table: String,
+        key: Vec<u8>,
+        source: Box<Self>,
+    },
+
+    /// An error that happened during a table operation, with where it happened. The original
+    /// error is the `source`, and can be matched with [`Error::root`].
+    #[error("{context}: {source}")]
+    Context {
+        context: Box<ErrorContext>,
+        source: Box<Self>,
+    },
+
+    /// A record write would grow a table past its quota. Nothing was written. `current` is the
+    /// table's usage before the write, and `requested` is its usage after it.
+    #[error(
+        "`{table}` would hold {requested} {resource}, over its quota of {limit} ({current} used)"
+    )]
+    QuotaExceeded {
+        table: String,
+        resource: &'static str,
+        current: u64,
+        requested: u64,
+        limit: u64,
+    },
+
+    /// A record type was deleted through its reverse index, but it doesn't declare one.
+    #[error("`{table}` doesn't declare a reverse index")]
+    ReverseIndexNotDeclared {
+        table: &'static str,
+    },
+
+    /// A record type's history was read, but it doesn't declare a history table.
+    #[error("`{table}` doesn't declare a history table")]
+    HistoryNotDeclared {
+        table: &'static str,
+    },
+
+    /// A row in a record type's history table is too short to be decoded.
+    #[error("`{table}` has an invalid history row")]
+    HistoryRowInvalid {
+        table: &'static str,
+    },
+
+    /// A record type that declares layers was written with delta encoding, which stores records
+    /// without their layers.
+    #[error("`{table}` declares layers, which delta-encoded records aren't stored with")]
+    DeltaEncodingLayered {
+        table: &'static str,
+    },
+
+    /// A resumable scan was given a continuation token that's malformed, or that was issued for
+    /// another table.
+    #[error("invalid scan token: {reason}")]
+    InvalidScanToken {
+        reason: String,
+    },
+
+    /// A database repair was aborted from its repair callback, so the database was not opened.
+    #[error("database repair was aborted")]
+    RepairAborted,
+
+    /// The database file failed its integrity check and could not be repaired.
+    #[error("database is corrupted and could not be repaired: {message}")]
+    Corrupted {
+        message: String,
+    },
+
+    /// The database file is locked by another open handle, in this process or another one. A
+    /// database file can only be open once at a time, for reads or writes.
+    #[error(
+        "database `{}` is already open, possibly by another process; close it and try again",
+        path.display()
+    )]
+    DatabaseLocked {
+        path: std::path::PathBuf,
+    },
+
+    /// A backup was asked to write to a file that already exists. Backups are always written to a
+    /// fresh file, so that an existing database is never overwritten or merged into.
+    #[error("backup target `{}` already exists", path.display())]
+    BackupTargetExists {
+        path: std::path::PathBuf,
+    },
+
+    /// An incremental backup, a retained snapshot, or a read at an earlier point was requested,
+    /// but the database doesn't keep a change log.
+    #[error("the change log is not enabled for this database")]
+    ChangeLogNotEnabled,
+
+    /// A snapshot was read that was never retained, or has been released.
+    #[error("snapshot {snapshot} is not retained")]
+    SnapshotNotFound {
+        snapshot: u64,
+    },
+
+    /// A read at a change log sequence number was requested, but the change log doesn't reach it
+    /// yet.
+    #[error("change {sequence} has not been written, the change log ends at change {last}")]
+    SequenceNotReached {
+        sequence: u64,
+        last: u64,
+    },
+
+    /// A change log entry, or an incremental backup, was truncated or malformed.
+    #[error("malformed change log: {reason}")]
+    MalformedChangeLog {
+        reason: &'static str,
+    },
+
+    /// A log's hash chain is broken: an entry was modified, removed, or reordered.
+    #[cfg(feature = "hash-chain")]
+    #[error("{log} hash chain is broken at sequence {sequence}: {reason}")]
+    ChainBroken {
+        log: &'static str,
+        sequence: u64,
+        reason: &'static str,
+    },
+
+    /// An audit log entry was truncated or malformed.
+    #[error("malformed audit log: {reason}")]
+    MalformedAuditLog {
+        reason: &'static str,
+    },
+
+    /// An incremental backup starts after a change that the database it's being restored onto
+    /// doesn't have. The backups in between must be restored first.
+    #[error(
+        "incremental backup starts after change {expected}, \
+        but the database only has changes up to {found}"
+    )]
+    IncrementalBackupGap {
+        expected: u64,
+        found: u64,
+    },
+
+    /// An export archive was truncated or malformed.
+    #[error("malformed archive: {reason}")]
+    MalformedArchive {
+        reason: &'static str,
+    },
+
+    /// An export archive was written by a build with different layer features, so its record bytes
+    /// can't be read by this build.
+    #[error("archive was written with layers `{archived}`, but this build uses `{active}`")]
+    ArchiveLayerMismatch {
+        archived: String,
+        active: String,
+    },
+
+    /// No blob with the given name is stored.
+    #[cfg(feature = "blobs")]
+    #[error("blob `{name}` not found")]
+    BlobNotFound {
+        name: String,
+    },
+
+    /// A blob's manifest or one of its chunks is malformed, missing, or doesn't match its hash.
+    #[cfg(feature = "blobs")]
+    #[error("blob `{name}` is malformed: {reason}")]
+    MalformedBlob {
+        name: String,
+        reason: &'static str,
+    },
+
+    /// A record type is encrypted by its [record layers](crate::typed::record_layers), but the
+    /// transaction has no key: it isn't a tenant's, and no record key was set with
+    /// [`Database::set_record_key`](crate::typed::database::Database::set_record_key).
+    #[error("records in `{table}` are encrypted, but no record key is set")]
+    RecordKeyMissing {
+        table: String,
+    },
+
+    /// A record couldn't be moved to a new encryption key during a key rotation. Records rotated
+    /// in earlier batches stay rotated, and the rotation can be resumed once the record is fixed.
+    #[cfg(feature = "encryptors")]
+    #[error("record in `{table}` couldn't be re-encrypted: {source}")]
+    KeyRotation {
+        table: String,
+        key: Vec<u8>,
+        source: crate::layers::core::LayerFailure,
+    },
+
+    /// A value or secondary key couldn't be encrypted or decrypted outside of the layer pipeline.
+    /// For example, a deterministically encrypted secondary key.
+    #[cfg(feature = "encryptors")]
+    #[error(transparent)]
+    Encryption(#[from] crate::layers::encryptors::Error),
+
+    /// An index row couldn't be protected or recovered by the corrector outside of the layer
+    /// pipeline. For example, a `KeySet` corrupted beyond repair.
+    #[cfg(feature = "correctors")]
+    #[error(transparent)]
+    Correction(#[from] crate::layers::correctors::Error),
+
+    /// No further unique nonce can be drawn from the database's nonce counter.
+    #[cfg(feature = "encryptors")]
+    #[error(transparent)]
+    NonceCounter(#[from] crate::layers::encryptors::NonceCounterError),
+
+    /// A key or value couldn't be serialized.
+    #[cfg(feature = "serializers")]
+    #[error(transparent)]
+    Serialize(#[from] crate::layers::serializers::SerializeError),
+
+    /// A key or value couldn't be deserialized from its stored bytes.
+    #[cfg(feature = "serializers")]
+    #[error(transparent)]
+    Deserialize(#[from] crate::layers::serializers::DeserializeError),
+
+    /// A value couldn't be passed through one of its layers. For example, it failed decryption's
+    /// authentication check.
+    #[cfg(any(
+        feature = "serializers",
+        feature = "compressors",
+        feature = "correctors",
+        feature = "encryptors",
+    ))]
+    #[error(transparent)]
+    Layer(#[from] crate::layers::core::LayerFailure),
+
+    /// A sync peer sent a frame that was malformed, or unexpected at that point of the protocol.
+    #[cfg(feature = "sync")]
+    #[error("sync protocol error: {reason}")]
+    SyncProtocol {
+        reason: &'static str,
+    },
+
+    /// A sync peer was built with different layer features, so its stored bytes can't be copied
+    /// as-is.
+    #[cfg(feature = "sync")]
+    #[error("sync peer uses layers `{remote}`, but this build uses `{local}`")]
+    SyncLayerMismatch {
+        remote: String,
+        local: String,
+    },
+
+    /// A database server answered a request with an error status.
+    #[cfg(feature = "server")]
+    #[error("server responded with status {status}: {message}")]
+    ServerResponse {
+        status: u16,
+        message: String,
+    },
+
+    /// A line of a JSON Lines import couldn't be parsed into a record.
+    #[cfg(feature = "serde")]
+    #[error("line {line} of the JSON Lines input is not a valid record: {source}")]
+    MalformedJsonLine {
+        line: u64,
+        source: serde_json::Error,
+    },
+
+    /// A record couldn't be written as JSON.
+    #[cfg(feature = "serde")]
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    /// A CSV row couldn't be read or built into a record. The error includes the row's position.
+    #[cfg(feature = "csv-import")]
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+
+    /// A record failed one or more of its `Validate` rules, and was not written.
+    #[error(transparent)]
+    ConstraintViolations(#[from] crate::validation::ConstraintViolations),
+
     /// [redb](https://www.redb.org/)
     /// [transaction error](https://docs.rs/redb/latest/redb/enum.CommitError.html).
     #[error(transparent)]
@@ -62,6 +409,11 @@ pub enum Error {
     #[error(transparent)]
     RedbDatabase(#[from] redb::DatabaseError),
 
+    /// [redb](https://www.redb.org/)
+    /// [savepoint error](https://docs.rs/redb/latest/redb/enum.SavepointError.html).
+    #[error(transparent)]
+    RedbSavepoint(#[from] redb::SavepointError),
+
     /// [redb](https://www.redb.org/)
     /// [storage error](https://docs.rs/redb/latest/redb/enum.StorageError.html).
     #[error(transparent)]
@@ -81,11 +433,54 @@ pub enum Error {
     #[error(transparent)]
     RkyvRancor(#[from] rkyv::rancor::Error),
 
+    /// An input/output error from reading or writing a file or stream outside of the database.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
     /// An external error supplied by the caller.
     #[error("external error: {0}")]
     External(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
 }
 
+// -------------------------------------------------------------------------------------------------
+//
+/// Where an error happened: the table operation that failed, and the table, key, and layer it
+/// failed on, where known. Attached to an error with [`Error::during`].
+///
+/// # Example
+///
+/// ```rust,ignore
+/// if let Err(error) = table.get(&7) {
+///     tracing::error!(
+///         table = error.table_name(),
+///         key = error.display_key().map(tracing::field::display),
+///         layer = error.layer(),
+///         "{error}",
+///     );
+/// }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ErrorContext {
+    /// The operation that failed. For example, `"get"` or `"insert"`.
+    pub operation: &'static str,
+
+    /// The qualified name of the table the operation was on.
+    pub table_name: Option<String>,
+
+    /// The serialized key the operation was on.
+    pub key: Option<Vec<u8>>,
+
+    /// The name of the layer that failed. For example, `"encryption"`.
+    pub layer: Option<&'static str>,
+}
+
+/// Shows serialized key bytes as lowercase hexadecimal, for logs and error messages.
+///
+/// Keys longer than 32 bytes are truncated and end with `…`, so that large composite keys don't
+/// bloat logs.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DisplayKey<'k>(pub &'k [u8]);
+
 // -------------------------------------------------------------------------------------------------
 //
 // Method Implementations
@@ -168,6 +563,144 @@ impl Error {
 
         Self::External(boxed)
     }
+
+    /// Attaches where the error happened. The error is kept as the `source` of an
+    /// [`Error::Context`], and can still be matched with [`Error::root`].
+    #[must_use]
+    pub fn during(self, context: ErrorContext) -> Self {
+        Self::Context { context: Box::new(context), source: Box::new(self) }
+    }
+
+    /// Returns where the error happened, if that was attached with [`Error::during`]. Use
+    /// [`Error::table_name`], [`Error::key_bytes`], and [`Error::layer`] to also take what's known
+    /// from the error itself.
+    #[must_use]
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Self::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Returns the original error, without any [`Error::Context`] around it, for matching.
+    #[must_use]
+    pub fn root(&self) -> &Self {
+        let mut error = self;
+        while let Self::Context { source, .. } = error {
+            error = source;
+        }
+        error
+    }
+
+    /// Returns the table operation that failed, if known. For example, `"get"`.
+    #[must_use]
+    pub fn operation(&self) -> Option<&'static str> {
+        self.contexts().next().map(|context| context.operation)
+    }
+
+    /// Returns the name of the table the error happened on, if known.
+    #[must_use]
+    pub fn table_name(&self) -> Option<&str> {
+        self.contexts()
+            .find_map(|context| context.table_name.as_deref())
+            .or_else(|| match self.root() {
+                Self::NotFound { table_name: table, .. }
+                | Self::Undecodable { table, .. }
+                | Self::QuotaExceeded { table, .. } => Some(table.as_str()),
+                #[cfg(feature = "encryptors")]
+                Self::KeyRotation { table, .. } => Some(table.as_str()),
+                Self::ForeignKeyViolation { table, .. }
+                | Self::ReferencedByDependent { table, .. }
+                | Self::GenerationMismatch { table, .. }
+                | Self::ReverseIndexNotDeclared { table }
+                | Self::HistoryNotDeclared { table }
+                | Self::HistoryRowInvalid { table }
+                | Self::DeltaEncodingLayered { table } => Some(*table),
+                _ => None,
+            })
+    }
+
+    /// Returns the serialized primary key of the record the error happened on, if known. Use
+    /// [`Error::display_key`] to show it in a log.
+    #[must_use]
+    pub fn key_bytes(&self) -> Option<&[u8]> {
+        self.contexts()
+            .find_map(|context| context.key.as_deref())
+            .or_else(|| match self.root() {
+                Self::NotFound { key, .. }
+                | Self::Undecodable { key, .. }
+                | Self::GenerationMismatch { key, .. } => Some(key.as_slice()),
+                #[cfg(feature = "encryptors")]
+                Self::KeyRotation { key, .. } => Some(key.as_slice()),
+                _ => None,
+            })
+    }
+
+    /// Returns the serialized primary key of the record the error happened on, if known, as
+    /// displayable hexadecimal.
+    #[must_use]
+    pub fn display_key(&self) -> Option<DisplayKey<'_>> {
+        self.key_bytes().map(DisplayKey)
+    }
+
+    /// Returns the name of the layer that failed, if the error came from one. For example,
+    /// `"encryption"` if a value failed its authentication check.
+    #[must_use]
+    pub fn layer(&self) -> Option<&'static str> {
+        self.contexts()
+            .find_map(|context| context.layer)
+            .or_else(|| match self.root() {
+                #[cfg(any(
+                    feature = "serializers",
+                    feature = "compressors",
+                    feature = "correctors",
+                    feature = "encryptors",
+                ))]
+                Self::Layer(failure) => Some(failure.layer.name()),
+                #[cfg(feature = "encryptors")]
+                Self::KeyRotation { source, .. } => Some(source.layer.name()),
+                _ => None,
+            })
+    }
+
+    /// Returns the contexts attached to the error, outermost first.
+    fn contexts(&self) -> impl Iterator<Item = &ErrorContext> {
+        std::iter::successors(Some(self), |error| match error {
+            Self::Context { source, .. } => Some(source),
+            _ => None,
+        })
+        .filter_map(Self::context)
+    }
+}
+
+impl ErrorContext {
+    /// Instantiates the context of a failed table operation, for example `"get"`, whose table, key,
+    /// and layer aren't known.
+    #[must_use]
+    pub const fn new(operation: &'static str) -> Self {
+        Self { operation, table_name: None, key: None, layer: None }
+    }
+
+    /// Sets the qualified name of the table the operation was on.
+    #[must_use]
+    pub fn table(mut self, table_name: impl Into<String>) -> Self {
+        self.table_name = Some(table_name.into());
+        self
+    }
+
+    /// Sets the serialized key the operation was on.
+    #[must_use]
+    pub fn key(mut self, key: impl Into<Vec<u8>>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    /// Sets the name of the layer that failed. For example, `"encryption"`.
+    #[must_use]
+    pub const fn layer(mut self, layer: &'static str) -> Self {
+        self.layer = Some(layer);
+        self
+    }
 }
 
 // -------------------------------------------------------------------------------------------------
@@ -179,4 +712,64 @@ impl From<anyhow::Error> for Error {
     fn from(error: anyhow::Error) -> Self {
         Self::External(error.into_boxed_dyn_error())
     }
-}
\ No newline at end of file
+}
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl Display for ErrorContext {
+    /// Formats the context as, for example, `` `get` on `creatures` at key 0007 ``.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}`", self.operation)?;
+        if let Some(table_name) = &self.table_name {
+            write!(f, " on `{table_name}`")?;
+        }
+        if let Some(key) = &self.key {
+            write!(f, " at key {}", DisplayKey(key))?;
+        }
+        if let Some(layer) = self.layer {
+            write!(f, " in the {layer} layer")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for DisplayKey<'_> {
+    /// Formats the key's bytes as lowercase hexadecimal.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for byte in self.0.iter().take(SHOWN_KEY_BYTES) {
+            write!(f, "{byte:02x}")?;
+        }
+        if self.0.len() > SHOWN_KEY_BYTES {
+            write!(f, "…")?;
+        }
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context_is_found_through_wrapping() {
+        let error = Error::NotFound { table_name: "creatures".to_string(), key: vec![0x07] }
+            .during(ErrorContext::new("get").key([0x00, 0x07]).layer("encryption"))
+            .during(ErrorContext::new("get_or_insert_with").table("tenant42.creatures"));
+
+        assert_eq!(error.operation(), Some("get_or_insert_with"));
+        assert_eq!(error.table_name(), Some("tenant42.creatures"));
+        assert_eq!(error.display_key().map(|key| key.to_string()).as_deref(), Some("0007"));
+        assert_eq!(error.layer(), Some("encryption"));
+        assert!(matches!(error.root(), Error::NotFound { .. }));
+        assert!(error.to_string().starts_with("`get_or_insert_with` on `tenant42.creatures`: "));
+
+        let bare = Error::RepairAborted;
+        assert_eq!((bare.table_name(), bare.key_bytes(), bare.layer()), (None, None, None));
+        assert_eq!(DisplayKey(&[0xFF; 40]).to_string(), format!("{}…", "ff".repeat(32)));
+    }
+}
@@ -0,0 +1,171 @@
+//! Error correction of secondary index tables.
+//!
+//! Record values flow through the corrector, but index rows don't: a single flipped bit in a
+//! serialized `KeySet` makes it fail to deserialize, and the records it lists silently drop out of
+//! query results. An [`IndexCorrection`] adds parity data to the row values of the indexes it's
+//! told about, which are repaired as they're read:
+//!
+//! * `KeySet` values, including the overflow shards of large key sets.
+//!
+//! * The primary key stored by a unique index.
+//!
+//! Secondary keys are table keys, and are left to `redb`'s own page checksums.
+
+use crate::indexing::Index;
+use crate::layers::core::{Bytes, Direction};
+use crate::layers::correctors::{ActiveCorrector, Correctable, Corrector, Level};
+use crate::Error;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Routes the row values of selected index tables through the active corrector.
+///
+/// Attach it to a transaction with `with_index_correction`. Every read and write of a corrected
+/// index then goes through it:
+///
+/// ```rust,ignore
+/// let correction = Arc::new(IndexCorrection::new().index::<Habitat>());
+///
+/// let txn = db.begin_write()?.with_index_correction(correction.clone());
+/// txn.insert(&creature.id, &creature)?;
+/// txn.commit()?;
+///
+/// let txn = db.begin_read()?.with_index_correction(correction);
+/// let desert_dwellers = txn.query::<CreatureId, Creature>(Habitat::Desert)?;
+/// ```
+///
+/// # Notes
+///
+/// * When an index is also protected by an `IndexProtection`, its rows are encrypted first and
+///   parity is added to the cipher text, so corruption is repaired before the value is decrypted.
+///
+/// * Every transaction that writes a corrected index must carry the same `IndexCorrection`, and
+///   an index must be rebuilt after it's added to or removed from the correction.
+#[derive(Clone, Debug, Default)]
+pub struct IndexCorrection {
+    /// Table names of the corrected indexes.
+    indexes: BTreeSet<&'static str>,
+
+    /// Whether every index is corrected, regardless of `indexes`.
+    all: bool,
+}
+
+/// Marks index row values, which are always protected and recovered.
+struct IndexRows;
+
+impl Correctable for IndexRows {
+    const DIRECTION: Direction = Direction::Both;
+    const LEVEL: Level = Level::Medium;
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl IndexCorrection {
+    /// Instantiates an `IndexCorrection` that doesn't correct any index yet.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Instantiates an `IndexCorrection` that corrects every index.
+    #[must_use]
+    pub const fn all() -> Self {
+        Self { indexes: BTreeSet::new(), all: true }
+    }
+
+    /// Corrects the index of the given secondary key type.
+    #[must_use]
+    pub fn index<I: Index>(self) -> Self {
+        self.index_named(I::index_name())
+    }
+
+    /// Corrects the index with the given table name.
+    #[must_use]
+    pub fn index_named(mut self, index_name: &'static str) -> Self {
+        self.indexes.insert(index_name);
+        self
+    }
+
+    /// Returns `true` if the named index is corrected.
+    #[must_use]
+    pub fn is_corrected(&self, index_name: &str) -> bool {
+        self.all || self.indexes.contains(index_name)
+    }
+
+    /// Adds parity data to a row's value, for example a serialized `KeySet`, if the index is
+    /// corrected.
+    ///
+    /// # Errors
+    ///
+    /// Consult the documentation of the corrector backend you are using for more detail on
+    /// protection behavior and potential limitations.
+    pub fn protect(&self, index_name: &str, value_bytes: Vec<u8>) -> Result<Vec<u8>, Error> {
+        if !self.is_corrected(index_name) {
+            return Ok(value_bytes);
+        }
+
+        let protected = ActiveCorrector::<IndexRows>::protect(Bytes::from_vec(value_bytes))
+            .map_err(crate::layers::correctors::Error::from)?;
+
+        Ok(protected.into_bytes().into_owned())
+    }
+
+    /// Checks a row's value that was protected with [`IndexCorrection::protect`] for corruption,
+    /// and repairs it from its parity data if needed. The value is returned as-is if the index
+    /// isn't corrected. Every repair is reported as a
+    /// [`RepairEvent`](crate::layers::correctors::RepairEvent).
+    ///
+    /// # Errors
+    ///
+    /// This method may fail for several reasons, including:
+    ///
+    /// * Input bytes are corrupted beyond repair, or malformed.
+    pub fn recover<'v>(
+        &self,
+        index_name: &str,
+        value_bytes: &'v [u8],
+    ) -> Result<Cow<'v, [u8]>, Error> {
+        if !self.is_corrected(index_name) {
+            return Ok(Cow::Borrowed(value_bytes));
+        }
+
+        let recovered = ActiveCorrector::<IndexRows>::recover(Bytes::from_slice(value_bytes))
+            .map_err(crate::layers::correctors::Error::from)?;
+        if let Some(shards_recovered) = recovered.shards_recovered() {
+            crate::layers::correctors::report_repair(
+                shards_recovered,
+                <ActiveCorrector::<IndexRows> as Corrector<IndexRows>>::METHOD
+            );
+        }
+
+        Ok(recovered.into_bytes())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corrupted_rows_of_corrected_indexes_are_repaired() {
+        let correction = IndexCorrection::new().index_named("creatures_by_habitat");
+        let key_set = (0..64_u8).collect::<Vec<u8>>();
+
+        let mut protected = correction.protect("creatures_by_habitat", key_set.clone()).unwrap();
+        assert_ne!(protected, key_set);
+        protected[3] ^= 0xFF;
+        let recovered = correction.recover("creatures_by_habitat", &protected).unwrap();
+        assert_eq!(&*recovered, &key_set[..]);
+
+        assert_eq!(correction.protect("creatures_by_age", vec![1, 2, 3]).unwrap(), vec![1, 2, 3]);
+        assert!(IndexCorrection::all().is_corrected("creatures_by_age"));
+    }
+}
@@ -0,0 +1,147 @@
+//! Covering indexes, which store a small projection of each record alongside its index entry so
+//! that queries needing only those fields never touch the primary table.
+
+use crate::indexing::{IndexKind, IndexLookup, KeyNormalization};
+use crate::{Codec, Error};
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A record's serialized projection, to be stored in a covering table.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CoveringBytes {
+    /// Name of the covering table. For example: `"creatures_by_habitat_covering"`.
+    pub table_name: &'static str,
+
+    /// The serialized projection.
+    pub projection_bytes: Vec<u8>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// An index look-up that also stores a projection of the record, making the index a covering
+/// index.
+///
+/// The projection is stored in a separate covering table, keyed by the secondary key and primary
+/// key, and is written and removed along with the index entry.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// #[derive(Deserialize, Serialize)]
+/// struct CreatureName { id: u64, species: String }
+///
+/// impl<'i> Indexable<'i> for Creature {
+///     type Index = Covered<Habitat, CreatureName>;
+///     type Indexes = Vec<Self::Index>;
+///
+///     fn indexes(&'i self) -> Result<Self::Indexes, Error> {
+///         let name = CreatureName { id: self.id, species: self.species.clone() };
+///         Ok(vec![Covered::new(Habitat(self.habitat.clone()), "creatures_by_habitat_covering", name)])
+///     }
+/// }
+///
+/// // Lists the desert's creatures without reading a single `Creature`:
+/// let names = txn.covered::<u64, CreatureName>(
+///     "creatures_by_habitat_covering",
+///     &Habitat("Desert".into())
+/// )?;
+/// ```
+pub struct Covered<I, P> {
+    /// The underlying index look-up.
+    lookup: I,
+
+    /// Name of the covering table.
+    table_name: &'static str,
+
+    /// The record's projection.
+    projection: P,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<I: IndexLookup, P: Codec<P>> Covered<I, P> {
+    /// Instantiates a covered index look-up, storing `projection` in the `table_name` covering
+    /// table.
+    #[must_use]
+    pub const fn new(lookup: I, table_name: &'static str, projection: P) -> Self {
+        Self { lookup, table_name, projection }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl<I: IndexLookup, P: Codec<P>> IndexLookup for Covered<I, P> {
+    type Record = I::Record;
+
+    /// Returns the name of the underlying secondary index table.
+    fn index_name(&self) -> &'static str {
+        self.lookup.index_name()
+    }
+
+    /// Returns whether the underlying index is `Unique` or `NonUnique`.
+    fn index_kind(&self) -> &IndexKind {
+        self.lookup.index_kind()
+    }
+
+    /// Returns how the underlying look-up's key is normalized.
+    fn key_normalization(&self) -> KeyNormalization {
+        self.lookup.key_normalization()
+    }
+
+    /// Encodes the underlying look-up's secondary key.
+    fn index_key_bytes(&self) -> Result<Vec<u8>, Error> {
+        self.lookup.index_key_bytes()
+    }
+
+    /// Serializes the projection for the covering table.
+    fn covering(&self) -> Result<Option<CoveringBytes>, Error> {
+        Ok(Some(CoveringBytes {
+            table_name: self.table_name,
+            projection_bytes: P::serialize(&self.projection)?,
+        }))
+    }
+
+    /// Formats the underlying look-up.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        IndexLookup::fmt(&self.lookup, f)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Returns the covering table key for a secondary key and primary key.
+///
+/// The key is the secondary key's `u32` little-endian length, the secondary key, and the primary
+/// key, so all of one secondary key's rows are adjacent and share [`covering_prefix`].
+///
+/// # Errors
+///
+/// * Returns [`Error::BufferTooLargeForTarget`] if the secondary key is longer than `u32::MAX`.
+pub fn covering_key(secondary_key_bytes: &[u8], primary_key_bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut key = covering_prefix(secondary_key_bytes)?;
+    key.extend_from_slice(primary_key_bytes);
+    Ok(key)
+}
+
+/// Returns the prefix shared by every covering table key of a secondary key.
+///
+/// # Errors
+///
+/// * Returns [`Error::BufferTooLargeForTarget`] if the secondary key is longer than `u32::MAX`.
+pub fn covering_prefix(secondary_key_bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    let len = u32::try_from(secondary_key_bytes.len()).map_err(|_| Error::BufferTooLargeForTarget {
+        buffer_len: secondary_key_bytes.len(),
+        target_len: u32::MAX as usize,
+    })?;
+
+    let mut prefix = Vec::with_capacity(size_of::<u32>() + secondary_key_bytes.len());
+    prefix.extend_from_slice(&len.to_le_bytes());
+    prefix.extend_from_slice(secondary_key_bytes);
+    Ok(prefix)
+}
@@ -0,0 +1,133 @@
+//! A collection of index look-ups of mixed types, for records that participate in several
+//! indexes, or that have multi-valued indexed fields such as `tags: Vec<String>`.
+
+use crate::indexing::{CoveringBytes, HasTable, IndexKind, IndexLookup, KeyNormalization};
+use crate::querying::DynLookup;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The index entries of a record, returned from [`Indexable::indexes`].
+///
+/// A multi-valued field emits one entry per element, so a query on any element finds the record,
+/// and deleting the record removes every element's entry. Duplicate elements produce a single
+/// entry.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// impl<'i> Indexable<'i> for Article {
+///     type Index = Box<DynLookup<Self>>;
+///     type Indexes = IndexEntries<Self>;
+///
+///     fn indexes(&'i self) -> Result<Self::Indexes, Error> {
+///         Ok(IndexEntries::new()
+///             .with(Author(self.author.clone()))
+///             .with_each(&self.tags, |tag| Tag(tag.clone())))
+///     }
+/// }
+///
+/// // Finds every article tagged "rust", whatever its other tags are:
+/// let rust = txn.query::<u64, Article>(Query::lookup(Tag("rust".into())))?;
+/// ```
+///
+/// [`Indexable::indexes`]: crate::indexing::Indexable::indexes
+pub struct IndexEntries<V: HasTable>(Vec<Box<DynLookup<V>>>);
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<V: HasTable> IndexEntries<V> {
+    /// Instantiates an empty set of index entries.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Adds an index entry for a single-valued field.
+    #[must_use]
+    pub fn with(mut self, lookup: impl IndexLookup<Record = V> + 'static) -> Self {
+        self.0.push(Box::new(lookup));
+        self
+    }
+
+    /// Adds one index entry per element of a multi-valued field.
+    #[must_use]
+    pub fn with_each<T, I>(mut self, values: impl IntoIterator<Item = T>, lookup: impl Fn(T) -> I) -> Self
+    where
+        I: IndexLookup<Record = V> + 'static,
+    {
+        self.0.extend(values.into_iter().map(|value| Box::new(lookup(value)) as Box<DynLookup<V>>));
+        self
+    }
+
+    /// Returns the number of index entries, including any duplicates.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if there are no index entries.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl<V: HasTable> Default for IndexEntries<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: HasTable> IntoIterator for IndexEntries<V> {
+    type Item = Box<DynLookup<V>>;
+    type IntoIter = std::vec::IntoIter<Box<DynLookup<V>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<V: HasTable> IndexLookup for Box<DynLookup<V>> {
+    type Record = V;
+
+    /// Returns the name of the secondary index table being queried.
+    fn index_name(&self) -> &'static str {
+        self.as_ref().index_name()
+    }
+
+    /// Returns whether the index is `Unique` or `NonUnique`.
+    fn index_kind(&self) -> &IndexKind {
+        self.as_ref().index_kind()
+    }
+
+    /// Returns how the boxed look-up's key is normalized.
+    fn key_normalization(&self) -> KeyNormalization {
+        self.as_ref().key_normalization()
+    }
+
+    /// Encodes the boxed look-up's secondary key.
+    fn index_key_bytes(&self) -> Result<Vec<u8>, crate::Error> {
+        self.as_ref().index_key_bytes()
+    }
+
+    /// Formats the boxed look-up.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        IndexLookup::fmt(self.as_ref(), f)
+    }
+
+    /// Returns the boxed look-up's placeholder, if it's a query parameter.
+    fn parameter(&self) -> Option<(&'static str, &'static str)> {
+        self.as_ref().parameter()
+    }
+
+    /// Returns the boxed look-up's projection, if it belongs to a covering index.
+    fn covering(&self) -> Result<Option<CoveringBytes>, crate::Error> {
+        self.as_ref().covering()
+    }
+}
@@ -0,0 +1,96 @@
+//! Typed descriptions of secondary index tables, and enumeration of their distinct keys.
+
+use crate::indexing::{HasTable, IndexKind};
+use crate::typed::transaction::ReadTransaction;
+use crate::{Codec, Error};
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Describes a secondary index table: the record type it indexes, and the type of the field it's
+/// keyed by.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// struct HabitatIndex;
+///
+/// impl Index for HabitatIndex {
+///     type Record = Creature;
+///     type Field = String;
+///     fn index_name() -> &'static str { "creatures_by_habitat" }
+/// }
+///
+/// // Every habitat that at least one creature lives in, for a filter drop-down:
+/// let habitats = HabitatIndex::keys(&txn)?.collect::<Result<Vec<String>, _>>()?;
+/// ```
+pub trait Index {
+    /// Type of the indexed record.
+    type Record: HasTable;
+
+    /// Type of the indexed field, which is the index's secondary key.
+    type Field: Codec<Self::Field>;
+
+    /// Returns the name of the secondary index table. For example: `"creatures_by_habitat"`.
+    fn index_name() -> &'static str;
+
+    /// Returns the kind of index: `Unique` or `NonUnique`. Defaults to `NonUnique`.
+    #[must_use] 
+    fn index_kind() -> IndexKind {
+        IndexKind::NonUnique
+    }
+
+    /// Returns an iterator over every distinct secondary key in the index, in ascending key order.
+    ///
+    /// Only the index table is read. Each secondary key is listed once, however many records share
+    /// it. An index that hasn't been created yet has no keys.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///
+    /// * Each item may fail to deserialize into `Self::Field`.
+    fn keys(
+        txn: &ReadTransaction
+    ) -> Result<impl Iterator<Item = Result<Self::Field, Error>> + use<Self>, Error> {
+        let range = txn
+            .open_raw_index_table(Self::index_name())?
+            .map(|index_table| index_table.range::<&[u8]>(..))
+            .transpose()?;
+
+        Ok(range.into_iter().flatten().map(|entry| {
+            let (secondary_key, _key_set) = entry?;
+            Self::Field::deserialize(secondary_key.value())
+        }))
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "writes"))]
+mod tests {
+    use super::*;
+    use crate::typed::database::Database;
+    use crate::typed::test_records::{Animal, EnclosureIndex};
+
+    #[test]
+    fn lists_each_distinct_key_once_in_order() {
+        let db = Database::in_memory().unwrap();
+        assert_eq!(EnclosureIndex::keys(&db.read().unwrap()).unwrap().count(), 0);
+
+        let mut txn = db.write().unwrap();
+        txn.bulk_insert::<u64, Animal>([
+            Animal::new(1, "Lion", "Savannah"),
+            Animal::new(2, "Penguin", "Arctic"),
+            Animal::new(3, "Zebra", "Savannah"),
+        ]).unwrap();
+        txn.commit().unwrap();
+
+        let keys = EnclosureIndex::keys(&db.read().unwrap())
+            .unwrap()
+            .collect::<Result<Vec<String>, _>>()
+            .unwrap();
+        assert_eq!(keys, ["Arctic", "Savannah"]);
+    }
+}
@@ -0,0 +1,75 @@
+//! Realigns serialized key sets, so that `rkyv` can read them in place.
+
+use rkyv::util::AlignedVec;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Serialized key set bytes, at an address that an archived key set can be read from.
+///
+/// `rkyv` only reads an archived key set that starts at an aligned address, but values read
+/// straight from a `redb` page are only byte-aligned. Aligned bytes are borrowed as they are, and
+/// others are copied into an aligned buffer.
+pub enum AlignedBytes<'b> {
+    /// The bytes were already aligned.
+    Borrowed(&'b [u8]),
+
+    /// The bytes were copied into an aligned buffer.
+    Copied(AlignedVec),
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<'b> AlignedBytes<'b> {
+    /// Alignment of the buffers that `rkyv` serializes key sets into.
+    const ALIGNMENT: usize = 16;
+
+    /// Borrows the bytes if they're aligned, or copies them into an aligned buffer if they aren't.
+    #[must_use]
+    pub fn new(bytes: &'b [u8]) -> Self {
+        if bytes.as_ptr().align_offset(Self::ALIGNMENT) == 0 {
+            Self::Borrowed(bytes)
+        } else {
+            let mut copied = AlignedVec::with_capacity(bytes.len());
+            copied.extend_from_slice(bytes);
+            Self::Copied(copied)
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl std::ops::Deref for AlignedBytes<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Borrowed(bytes) => bytes,
+            Self::Copied(copied) => copied,
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_only_misaligned_bytes() {
+        let mut buffer = AlignedVec::<16>::new();
+        buffer.extend_from_slice(&[0, 1, 2, 3, 4]);
+        assert!(matches!(AlignedBytes::new(&buffer), AlignedBytes::Borrowed(_)));
+
+        let realigned = AlignedBytes::new(&buffer[1..]);
+        assert!(matches!(realigned, AlignedBytes::Copied(_)));
+        assert_eq!(&*realigned, [1, 2, 3, 4]);
+        assert_eq!(realigned.as_ptr().align_offset(16), 0);
+    }
+}
@@ -2,11 +2,12 @@
 //! [Rust Standard Library](https://doc.rust-lang.org/std/)'s
 //! [BTreeSet](https://doc.rust-lang.org/std/collections/struct.BTreeSet.html).
 
-mod archived_key_set;
 mod readable_key_set;
 mod upgradable_key_set;
 
-use crate::indexing::key_set::ReadableKeySet;
+use crate::indexing::key_set::{delta, ReadableKeySet};
+
+pub use crate::indexing::key_set::delta::ArchivedKeySet;
 use std::collections::BTreeSet;
 
 // -------------------------------------------------------------------------------------------------
@@ -15,8 +16,8 @@ use std::collections::BTreeSet;
 /// example, it could be used to list all `creatures` that have a `Habitat` of `"Cloud Forest"`.
 ///
 /// This implementation is powered by the [Rust Standard Library](https://doc.rust-lang.org/std/)'s
-/// [BTreeSet](https://doc.rust-lang.org/std/collections/struct.BTreeSet.html) and
-/// [David Koloski](https://crates.io/users/djkoloski)'s [rkyv](https://crates.io/crates/rkyv).
+/// [BTreeSet](https://doc.rust-lang.org/std/collections/struct.BTreeSet.html). It's stored in index tables with prefix-delta and varint
+/// encoding (see the `delta` module).
 ///
 /// This set lists all of the primary keys associated with an index entry. Primary keys are in
 /// serialized form, represented by bytes. This collection is used internally to manage non-unique
@@ -88,7 +89,7 @@ use std::collections::BTreeSet;
 /// * It's used internally to resolve one-to-many relationships via indexes.
 /// * Backed by efficient data structures like `BTreeSet` or deserialized on demand.
 /// * Critical for fast index queries like: "Give me everything in this habitat."
-#[derive(Debug, Default, Eq, PartialEq, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+#[derive(Debug, Default, Eq, PartialEq)]
 pub struct KeySet(pub(crate) BTreeSet<Vec<u8>>);
 
 // -------------------------------------------------------------------------------------------------
@@ -145,13 +146,10 @@ impl KeySet {
     ///
     /// # Errors
     ///
-    /// * This method will return an error if deserialization of the `KeySet`
-    ///   fails.
+    /// * This method will return an error if decoding of the `KeySet` fails.
     #[inline]
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, crate::Error> {
-        let archived = rkyv::access::<ArchivedKeySet, rkyv::rancor::Error>(bytes)?;
-        let deserialized = rkyv::deserialize::<Self, rkyv::rancor::Error>(archived)?;
-        Ok(deserialized)
+        delta::DeltaKeys::new(bytes)?.collect()
     }
 
     /// Serializes the `KeySet` to its binary representation.
@@ -162,7 +160,7 @@ impl KeySet {
     ///   fails.
     #[inline]
     pub fn to_bytes(&self) -> Result<Vec<u8>, crate::Error> {
-        Ok(rkyv::to_bytes::<rkyv::rancor::Error>(self)?.to_vec())
+        Ok(delta::encode(self.0.iter().map(Vec::as_slice)))
     }
 
     // +----------------+
@@ -253,6 +251,54 @@ impl KeySet {
 
         Self(result)
     }
+
+    // +-----------------------------+
+    // | By-Reference Set Operations |
+    // +-----------------------------+
+
+    /// Keeps only the primary keys that are also present in another set, in place.
+    ///
+    /// This is the non-consuming form of [`KeySet::intersection`]. It's useful when combining many
+    /// sets, since the result isn't moved or reallocated for every set.
+    #[inline]
+    pub fn intersect_with(&mut self, other: &impl ReadableKeySet) {
+        self.0.retain(|member| other.contains(member));
+    }
+
+    /// Adds the primary keys of another set, in place. Only the keys that aren't already present
+    /// are cloned.
+    ///
+    /// This is the non-consuming form of [`KeySet::union`].
+    #[inline]
+    pub fn union_with(&mut self, other: &Self) {
+        for member in &other.0 {
+            if !self.0.contains(member) {
+                self.0.insert(member.clone());
+            }
+        }
+    }
+
+    /// Removes the primary keys that are present in another set, in place.
+    ///
+    /// This is the non-consuming form of [`KeySet::difference`].
+    #[inline]
+    pub fn difference_with(&mut self, other: &impl ReadableKeySet) {
+        self.0.retain(|member| !other.contains(member));
+    }
+
+    /// Keeps the primary keys that are present in either set but not both, in place.
+    ///
+    /// This is the non-consuming form of [`KeySet::symmetric_difference`].
+    #[inline]
+    pub fn symmetric_difference_with(&mut self, other: &Self) {
+        for member in &other.0 {
+            if self.0.contains(member) {
+                self.remove(member);
+            } else {
+                self.0.insert(member.clone());
+            }
+        }
+    }
 }
 
 // -------------------------------------------------------------------------------------------------
@@ -345,6 +391,49 @@ impl Extend<Vec<u8>> for KeySet {
     }
 }
 
+impl std::ops::BitAnd<&KeySet> for &KeySet {
+    type Output = KeySet;
+
+    /// Returns the intersection of two sets as a new `KeySet`, without consuming either:
+    /// `&a & &b`. The smaller set is walked, and its matching keys are cloned.
+    fn bitand(self, other: &KeySet) -> KeySet {
+        let (smaller, larger) = if self.0.len() <= other.0.len() { (self, other) } else { (other, self) };
+        smaller.0.iter().filter(|member| larger.0.contains(*member)).cloned().collect()
+    }
+}
+
+impl std::ops::BitOr<&KeySet> for &KeySet {
+    type Output = KeySet;
+
+    /// Returns the union of two sets as a new `KeySet`, without consuming either: `&a | &b`.
+    fn bitor(self, other: &KeySet) -> KeySet {
+        let mut union: KeySet = self.0.iter().cloned().collect();
+        union.union_with(other);
+        union
+    }
+}
+
+impl std::ops::Sub<&KeySet> for &KeySet {
+    type Output = KeySet;
+
+    /// Returns the difference of two sets as a new `KeySet`, without consuming either: `&a - &b`.
+    fn sub(self, other: &KeySet) -> KeySet {
+        self.0.iter().filter(|member| !other.0.contains(*member)).cloned().collect()
+    }
+}
+
+impl std::ops::BitXor<&KeySet> for &KeySet {
+    type Output = KeySet;
+
+    /// Returns the symmetric difference of two sets as a new `KeySet`, without consuming either:
+    /// `&a ^ &b`.
+    fn bitxor(self, other: &KeySet) -> KeySet {
+        let mut symmetric_difference = self - other;
+        symmetric_difference.union_with(&(other - self));
+        symmetric_difference
+    }
+}
+
 // -------------------------------------------------------------------------------------------------
 //
 // Tests
@@ -362,4 +451,25 @@ fn set_operations() {
 
     let expected = KeySet::from_iter(vec![vec![2]]);
     assert_eq!(result, expected);
-}
\ No newline at end of file
+}
+#[test]
+fn by_reference_set_operations() {
+    let a = KeySet::from_iter(vec![vec![1], vec![2], vec![3]]);
+    let b = KeySet::from_iter(vec![vec![3], vec![4]]);
+
+    assert_eq!((&a & &b).len(), 1);
+    assert_eq!((&a | &b).len(), 4);
+    assert_eq!((&a - &b).len(), 2);
+    assert_eq!((&a ^ &b).len(), 3);
+
+    let mut c = KeySet::from_iter(vec![vec![1], vec![2], vec![3]]);
+    c.intersect_with(&b);
+    c.union_with(&KeySet::from_iter(vec![vec![2]]));
+    c.difference_with(&b);
+    assert_eq!(c.len(), 1);
+    assert!(c.contains(&[2]));
+
+    c.symmetric_difference_with(&a);
+    assert_eq!(c.len(), 2);
+    assert!(!c.contains(&[2]));
+}
@@ -0,0 +1,350 @@
+//! Prefix-delta and varint encoding for the sorted key-set backends (`key-set-btree` and
+//! `key-set-vec`).
+//!
+//! Primary keys that sort next to each other usually share a long prefix: big-endian integer IDs
+//! share their leading bytes, and string keys often share a common stem. Rather than storing every
+//! key in full, each key is stored as the length of the prefix it shares with the previous key,
+//! followed by the rest of the key.
+//!
+//! ```text
+//! ┌─────────┬───────┬──────────────────────────────────────────────┐
+//! │ version │ count │ shared · suffix length · suffix  (per key)   │
+//! │   u8    │ varint│ varint · varint        · bytes               │
+//! └─────────┴───────┴──────────────────────────────────────────────┘
+//! ```
+//!
+//! For example, the `u64` primary keys `[12, 48, 301]` take 19 bytes instead of 24 plus rkyv's
+//! per-key overhead. Lengths are LEB128 varints.
+
+use crate::Error;
+use crate::indexing::key_set::{ReadableKeySet, UpgradableKeySet};
+use crate::indexing::KeySet;
+use std::cmp::Ordering;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Version tag that begins every delta-encoded key set.
+const FORMAT_VERSION: u8 = 1;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// An iterator that decodes the primary keys in a delta-encoded key set, in ascending order.
+#[derive(Clone, Debug)]
+pub struct DeltaKeys<'b> {
+    /// The encoded keys that haven't been decoded yet.
+    rest: &'b [u8],
+
+    /// How many keys haven't been decoded yet.
+    remaining: usize,
+
+    /// The most recently decoded key, which the next key's shared prefix is taken from.
+    previous: Vec<u8>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A read-only view of a delta-encoded key set, borrowed from an index entry.
+///
+/// The key set is validated once, when the view is created. Membership checks decode keys from the
+/// front, stopping as soon as they pass the key being looked for, and subset checks walk both sets
+/// in step.
+#[derive(Clone, Copy, Debug)]
+pub struct ArchivedKeySet<'b> {
+    /// The delta-encoded key set.
+    bytes: &'b [u8],
+
+    /// How many primary keys the key set holds.
+    len: usize,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<'b> ArchivedKeySet<'b> {
+    /// Instantiates an `ArchivedKeySet` from its binary representation.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Corrupted`] if the key set is malformed.
+    #[inline]
+    pub fn from_bytes(bytes: &'b [u8]) -> Result<Self, Error> {
+        Ok(Self { bytes, len: validate(bytes)? })
+    }
+
+    /// Returns an iterator over the primary keys in the index set, in ascending order.
+    ///
+    /// # Notes
+    ///
+    /// * The primary keys will be returned in serialized form, as raw bytes. If needed, each key
+    ///   can be deserialized into its full form by using `K::deserialize(&item)`
+    ///
+    /// * Primary keys are used to get actual records from the database.
+    pub fn iter(&self) -> impl Iterator<Item = Vec<u8>> + use<'b> {
+        // The key set was validated when the view was created, so decoding can't fail.
+        DeltaKeys::new(self.bytes).into_iter().flatten().map_while(Result::ok)
+    }
+
+    /// Walks this set and another in step, and returns `true` as soon as `stop` returns `true`.
+    ///
+    /// `stop` is called once per distinct key with `Less` if only `self` holds it, `Greater` if
+    /// only `other` holds it, or `Equal` if both do.
+    fn walk(&self, other: &Self, mut stop: impl FnMut(Ordering) -> bool) -> bool {
+        let (mut left, mut right) = (self.iter().peekable(), other.iter().peekable());
+        loop {
+            let ordering = match (left.peek(), right.peek()) {
+                (Some(l), Some(r)) => l.cmp(r),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => return false,
+            };
+            if stop(ordering) {
+                return true;
+            }
+            match ordering {
+                Ordering::Less => { left.next(); },
+                Ordering::Greater => { right.next(); },
+                Ordering::Equal => { left.next(); right.next(); },
+            }
+        }
+    }
+}
+
+impl<'b> DeltaKeys<'b> {
+    /// Starts decoding a delta-encoded key set.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Corrupted`] if the key set has an unknown version or a malformed count.
+    pub fn new(bytes: &'b [u8]) -> Result<Self, Error> {
+        let Some((&FORMAT_VERSION, mut rest)) = bytes.split_first() else {
+            return Err(corrupted("has an unknown format version"));
+        };
+        let remaining = take_varint(&mut rest)?;
+        Ok(Self { rest, remaining, previous: Vec::new() })
+    }
+
+    /// Returns how many keys haven't been decoded yet.
+    #[must_use]
+    pub const fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// Decodes the next key.
+    fn decode_next(&mut self) -> Result<Vec<u8>, Error> {
+        let shared = take_varint(&mut self.rest)?;
+        let suffix_len = take_varint(&mut self.rest)?;
+        if shared > self.previous.len() || suffix_len > self.rest.len() {
+            return Err(corrupted("is truncated"));
+        }
+
+        let (suffix, rest) = self.rest.split_at(suffix_len);
+        self.rest = rest;
+        self.previous.truncate(shared);
+        self.previous.extend_from_slice(suffix);
+        Ok(self.previous.clone())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Readable Key-Set Implementation
+
+impl ReadableKeySet for ArchivedKeySet<'_> {
+    // +----------------------+
+    // | Basic Set Operations |
+    // +----------------------+
+
+    /// Returns how many primary keys are in this index set.
+    ///
+    /// For example, `"Tide Pools"` might return `3` if there are three known creatures found there.
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if this index set is empty.
+    ///
+    /// For example, `"ISO Class 1 Cleanroom"` might return `true` because no creatures live in the
+    /// habitat.
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // +---------------------------+
+    // | Set Membership Operations |
+    // +---------------------------+
+
+    /// Returns `true` if the index contains the given primary key.
+    ///
+    /// For example: `key_set.contains(&hermit_crab_id_bytes)`
+    ///
+    /// # Notes
+    ///
+    /// * The primary key must be in serialized form (as raw bytes).
+    #[inline]
+    fn contains(&self, primary_key_bytes: &[u8]) -> bool {
+        self.iter()
+            .find(|member| member.as_slice() >= primary_key_bytes)
+            .is_some_and(|member| member == primary_key_bytes)
+    }
+
+    /// Returns `true` if this set is a subset of another.
+    ///
+    /// “Are all elements in `self` also in `other`?”
+    #[inline]
+    fn is_subset(&self, other: &Self) -> bool {
+        self.len <= other.len && !self.walk(other, Ordering::is_lt)
+    }
+
+    /// Returns `true` if this set is a superset of another.
+    ///
+    /// “Are all elements in `other` also in `self`?”
+    #[inline]
+    fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Returns `true` if this set and another intersect.
+    ///
+    /// “Do these sets share any elements?”
+    #[inline]
+    fn intersects(&self, other: &Self) -> bool {
+        self.walk(other, Ordering::is_eq)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Upgradable Key-Set Implementation
+
+impl UpgradableKeySet for ArchivedKeySet<'_> {
+    /// Upgrades the [`ArchivedKeySet`] into an owned & mutable [`KeySet`] by decoding every
+    /// primary key.
+    ///
+    /// This method is typically used when write access is required to complete a set operation.
+    ///
+    /// # Errors
+    ///
+    /// * This method will return an error if decoding of the underlying [`ArchivedKeySet`] fails.
+    #[inline]
+    fn upgrade(self) -> Result<KeySet, Error> {
+        DeltaKeys::new(self.bytes)?.collect()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl Iterator for DeltaKeys<'_> {
+    type Item = Result<Vec<u8>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let key = self.decode_next();
+        if key.is_err() {
+            self.remaining = 0;
+        }
+        Some(key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Encodes primary keys, which must be sorted and unique, as a delta-encoded key set.
+pub fn encode<'k>(keys: impl ExactSizeIterator<Item = &'k [u8]>) -> Vec<u8> {
+    let mut bytes = vec![FORMAT_VERSION];
+    put_varint(&mut bytes, keys.len());
+
+    let mut previous: &[u8] = &[];
+    for key in keys {
+        let shared = previous.iter().zip(key).take_while(|(p, k)| p == k).count();
+        put_varint(&mut bytes, shared);
+        put_varint(&mut bytes, key.len() - shared);
+        bytes.extend_from_slice(&key[shared..]);
+        previous = key;
+    }
+
+    bytes
+}
+
+/// Checks that every key in a delta-encoded key set can be decoded, and returns how many keys it
+/// holds.
+///
+/// # Errors
+///
+/// * Returns [`Error::Corrupted`] if the key set is malformed.
+pub fn validate(bytes: &[u8]) -> Result<usize, Error> {
+    let keys = DeltaKeys::new(bytes)?;
+    let len = keys.remaining();
+    for key in keys {
+        key?;
+    }
+    Ok(len)
+}
+
+/// Appends a LEB128 varint.
+#[allow(clippy::cast_possible_truncation, reason = "each byte is masked to seven bits first")]
+fn put_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/// Splits a LEB128 varint off the front of the encoded keys.
+fn take_varint(rest: &mut &[u8]) -> Result<usize, Error> {
+    let mut value = 0_usize;
+    for shift in (0..usize::BITS).step_by(7) {
+        let Some((&byte, tail)) = rest.split_first() else { break };
+        *rest = tail;
+        value |= usize::from(byte & 0x7F).checked_shl(shift).unwrap_or_default();
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(corrupted("has a malformed length"))
+}
+
+/// Describes a malformed delta-encoded key set.
+fn corrupted(reason: &str) -> Error {
+    Error::Corrupted { message: format!("delta-encoded key set {reason}") }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_sorted_keys() {
+        let keys: Vec<Vec<u8>> = [12_u64, 48, 301, 70_000]
+            .iter()
+            .map(|id| id.to_be_bytes().to_vec())
+            .chain([b"coral".to_vec(), b"coral reef".to_vec(), b"cove".to_vec()])
+            .collect();
+
+        let bytes = encode(keys.iter().map(Vec::as_slice));
+        assert!(bytes.len() < keys.iter().map(Vec::len).sum());
+        assert_eq!(validate(&bytes).unwrap(), keys.len());
+        assert_eq!(DeltaKeys::new(&bytes).unwrap().collect::<Result<Vec<_>, _>>().unwrap(), keys);
+
+        assert!(validate(&bytes[..bytes.len() - 1]).is_err());
+        assert!(validate(&[]).is_err());
+    }
+}
@@ -36,9 +36,13 @@ impl ArchivedKeySet {
 
     /// Instantiates an `ArchivedKeySet` from its binary representation.
     ///
+    /// The bytes are read in place, so they must be aligned. Bytes read straight from a `redb` page
+    /// should be wrapped in [`AlignedBytes`](crate::indexing::AlignedBytes) first.
+    ///
     /// # Errors
     ///
-    /// * This method will return an error if `rkyv`'s access check of the `ArchivedKeySet` fails.
+    /// * This method will return an error if `rkyv`'s access check of the `ArchivedKeySet` fails,
+    ///   which includes the bytes not being aligned.
     #[inline]
     pub fn from_bytes(bytes: &[u8]) -> Result<&Self, crate::Error> {
         let archived = rkyv::access::<Self, rkyv::rancor::Error>(bytes)?;
@@ -158,7 +158,8 @@ impl KeySet {
     ///   fails.
     #[inline]
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, crate::Error> {
-        let archived = rkyv::access::<ArchivedKeySet, rkyv::rancor::Error>(bytes)?;
+        let bytes = crate::indexing::AlignedBytes::new(bytes);
+        let archived = rkyv::access::<ArchivedKeySet, rkyv::rancor::Error>(&bytes)?;
         let deserialized = rkyv::deserialize::<Self, rkyv::rancor::Error>(archived)?;
         Ok(deserialized)
     }
@@ -262,6 +263,54 @@ impl KeySet {
 
         Self(result)
     }
+
+    // +-----------------------------+
+    // | By-Reference Set Operations |
+    // +-----------------------------+
+
+    /// Keeps only the primary keys that are also present in another set, in place.
+    ///
+    /// This is the non-consuming form of [`KeySet::intersection`]. It's useful when combining many
+    /// sets, since the result isn't moved or reallocated for every set.
+    #[inline]
+    pub fn intersect_with(&mut self, other: &impl ReadableKeySet) {
+        self.0.retain(|member| other.contains(member));
+    }
+
+    /// Adds the primary keys of another set, in place. Only the keys that aren't already present
+    /// are cloned.
+    ///
+    /// This is the non-consuming form of [`KeySet::union`].
+    #[inline]
+    pub fn union_with(&mut self, other: &Self) {
+        for member in &other.0 {
+            if !self.0.contains(member) {
+                self.0.insert(member.clone());
+            }
+        }
+    }
+
+    /// Removes the primary keys that are present in another set, in place.
+    ///
+    /// This is the non-consuming form of [`KeySet::difference`].
+    #[inline]
+    pub fn difference_with(&mut self, other: &impl ReadableKeySet) {
+        self.0.retain(|member| !other.contains(member));
+    }
+
+    /// Keeps the primary keys that are present in either set but not both, in place.
+    ///
+    /// This is the non-consuming form of [`KeySet::symmetric_difference`].
+    #[inline]
+    pub fn symmetric_difference_with(&mut self, other: &Self) {
+        for member in &other.0 {
+            if self.0.contains(member) {
+                self.remove(member);
+            } else {
+                self.0.insert(member.clone());
+            }
+        }
+    }
 }
 
 // -------------------------------------------------------------------------------------------------
@@ -357,6 +406,49 @@ impl Extend<Vec<u8>> for KeySet {
     }
 }
 
+impl std::ops::BitAnd<&KeySet> for &KeySet {
+    type Output = KeySet;
+
+    /// Returns the intersection of two sets as a new `KeySet`, without consuming either:
+    /// `&a & &b`. The smaller set is walked, and its matching keys are cloned.
+    fn bitand(self, other: &KeySet) -> KeySet {
+        let (smaller, larger) = if self.0.len() <= other.0.len() { (self, other) } else { (other, self) };
+        smaller.0.iter().filter(|member| larger.0.contains(*member)).cloned().collect()
+    }
+}
+
+impl std::ops::BitOr<&KeySet> for &KeySet {
+    type Output = KeySet;
+
+    /// Returns the union of two sets as a new `KeySet`, without consuming either: `&a | &b`.
+    fn bitor(self, other: &KeySet) -> KeySet {
+        let mut union: KeySet = self.0.iter().cloned().collect();
+        union.union_with(other);
+        union
+    }
+}
+
+impl std::ops::Sub<&KeySet> for &KeySet {
+    type Output = KeySet;
+
+    /// Returns the difference of two sets as a new `KeySet`, without consuming either: `&a - &b`.
+    fn sub(self, other: &KeySet) -> KeySet {
+        self.0.iter().filter(|member| !other.0.contains(*member)).cloned().collect()
+    }
+}
+
+impl std::ops::BitXor<&KeySet> for &KeySet {
+    type Output = KeySet;
+
+    /// Returns the symmetric difference of two sets as a new `KeySet`, without consuming either:
+    /// `&a ^ &b`.
+    fn bitxor(self, other: &KeySet) -> KeySet {
+        let mut symmetric_difference = self - other;
+        symmetric_difference.union_with(&(other - self));
+        symmetric_difference
+    }
+}
+
 // -------------------------------------------------------------------------------------------------
 //
 // Tests
@@ -374,4 +466,25 @@ fn set_operations() {
 
     let expected = KeySet::from_iter(vec![vec![2]]);
     assert_eq!(result, expected);
-}
\ No newline at end of file
+}
+#[test]
+fn by_reference_set_operations() {
+    let a = KeySet::from_iter(vec![vec![1], vec![2], vec![3]]);
+    let b = KeySet::from_iter(vec![vec![3], vec![4]]);
+
+    assert_eq!((&a & &b).len(), 1);
+    assert_eq!((&a | &b).len(), 4);
+    assert_eq!((&a - &b).len(), 2);
+    assert_eq!((&a ^ &b).len(), 3);
+
+    let mut c = KeySet::from_iter(vec![vec![1], vec![2], vec![3]]);
+    c.intersect_with(&b);
+    c.union_with(&KeySet::from_iter(vec![vec![2]]));
+    c.difference_with(&b);
+    assert_eq!(c.len(), 1);
+    assert!(c.contains(&[2]));
+
+    c.symmetric_difference_with(&a);
+    assert_eq!(c.len(), 2);
+    assert!(!c.contains(&[2]));
+}
@@ -2,9 +2,11 @@
 
 // Trait representing read-only access to a set of primary keys in an index entry.
 
+mod aligned;
 mod readable_key_set;
 mod upgradable_key_set;
 
+pub use crate::indexing::key_set::aligned::AlignedBytes;
 pub use crate::indexing::key_set::readable_key_set::ReadableKeySet;
 pub use crate::indexing::key_set::upgradable_key_set::UpgradableKeySet;
 
@@ -20,10 +22,11 @@ macro_rules! count_features {
 }
 
 const _KEY_SET_FEATURE_COUNT: usize = count_features!(
-    "ahash-key-set",
-    "b-tree-key-set",
-    "hash-key-set",
-    "vec-key-set",
+    "key-set-ahash",
+    "key-set-btree",
+    "key-set-hash",
+    "roaring-key-set",
+    "key-set-vec",
 );
 
 const _: () = {
@@ -32,10 +35,11 @@ const _: () = {
         // find `atlatl` under `[dependencies]`, 3. ensure only one key-set index feature is enabled.
         !(_KEY_SET_FEATURE_COUNT > 1),
         "Multiple key-set features enabled! Please enable only one of: \
-        `ahash-key-set`, \
-        `b-tree-key-set`, \
-        `hash-key-set`, or \
-        `vec-key-set`",
+        `key-set-ahash`, \
+        `key-set-btree`, \
+        `key-set-hash`, \
+        `roaring-key-set`, or \
+        `key-set-vec`",
     );
 };
 
@@ -45,32 +49,45 @@ const _: () = {
 
 // ahash-backed index sets
 
-#[cfg(feature = "ahash-key-set")]
+#[cfg(feature = "key-set-ahash")]
 pub(super) mod ahash_set;
 
-#[cfg(feature = "ahash-key-set")]
+#[cfg(feature = "key-set-ahash")]
 pub use crate::indexing::key_set::ahash_set::{ArchivedKeySet, KeySet};
 
 // HashSet-backed index sets
 
-#[cfg(feature = "hash-key-set")]
+#[cfg(feature = "key-set-hash")]
 pub(super) mod hash_set;
 
-#[cfg(feature = "hash-key-set")]
+#[cfg(feature = "key-set-hash")]
 pub use crate::indexing::key_set::hash_set::{ArchivedKeySet, KeySet};
 
 // BTreeSet-backed index sets
 
-#[cfg(feature = "b-tree-key-set")]
+#[cfg(feature = "key-set-btree")]
 pub(super) mod b_tree_set;
 
-#[cfg(feature = "b-tree-key-set")]
+#[cfg(feature = "key-set-btree")]
 pub use crate::indexing::key_set::b_tree_set::{ArchivedKeySet, KeySet};
 
+// Prefix-delta encoding shared by the sorted index sets
+
+#[cfg(any(feature = "key-set-btree", feature = "key-set-vec"))]
+mod delta;
+
+// Roaring-bitmap-backed index sets
+
+#[cfg(feature = "roaring-key-set")]
+pub(super) mod roaring;
+
+#[cfg(feature = "roaring-key-set")]
+pub use crate::indexing::key_set::roaring::{ArchivedKeySet, KeySet};
+
 // Vec-backed index sets
 
-#[cfg(feature = "vec-key-set")]
+#[cfg(feature = "key-set-vec")]
 pub(super) mod vec;
 
-#[cfg(feature = "vec-key-set")]
+#[cfg(feature = "key-set-vec")]
 pub use crate::indexing::key_set::vec::{ArchivedKeySet, KeySet};
\ No newline at end of file
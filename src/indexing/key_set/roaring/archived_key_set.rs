@@ -1,9 +1,6 @@
-//! Implementations for `b_tree_set::ArchivedKeySet`, the zero-copy key-set view.
+//! Implementations for `roaring::ArchivedKeySet`, the zero-copy key-set view.
 
-use crate::indexing::key_set::{b_tree_set::{ArchivedKeySet, KeySet}, ReadableKeySet};
-use rkyv::collections::btree_set::ArchivedBTreeSet;
-use rkyv::vec::ArchivedVec;
-use std::ops::ControlFlow::{Break, Continue};
+use crate::indexing::key_set::{roaring::{ArchivedKeySet, KeySet, split}, ReadableKeySet};
 
 // -------------------------------------------------------------------------------------------------
 //
@@ -14,23 +11,35 @@ impl ArchivedKeySet {
     // | Basic Methods |
     // +---------------+
 
-    /// Returns the owned, inner collection of primary keys.
-    #[inline]
-    #[must_use]
-    pub const fn into_inner(self) -> ArchivedBTreeSet<ArchivedVec<u8>> {
-        self.0
-    }
-
     /// Instantiates an `ArchivedKeySet` from its binary representation.
     ///
+    /// The bytes are read in place, so they must be aligned. Bytes read straight from a `redb` page
+    /// should be wrapped in [`AlignedBytes`](crate::indexing::AlignedBytes) first.
+    ///
     /// # Errors
     ///
-    /// * This method will return an error if `rkyv`'s access check of the `ArchivedKeySet` fails.
+    /// * This method will return an error if `rkyv`'s access check of the `ArchivedKeySet` fails,
+    ///   which includes the bytes not being aligned.
     #[inline]
     pub fn from_bytes(bytes: &[u8]) -> Result<&Self, crate::Error> {
         let archived = rkyv::access::<Self, rkyv::rancor::Error>(bytes)?;
         Ok(archived)
     }
+
+    /// Returns the integer that a primary key is stored as in the bitmap, or `None` if it isn't
+    /// the set's width.
+    fn integer_of(&self, primary_key_bytes: &[u8]) -> Option<u64> {
+        (self.width != 0 && primary_key_bytes.len() == usize::from(self.width))
+            .then(|| primary_key_bytes.iter().fold(0, |integer, byte| integer << 8 | u64::from(*byte)))
+    }
+
+    /// Returns an iterator over the primary keys in the archived set, by deserializing it.
+    fn upgrade_iter(&self) -> impl Iterator<Item = Vec<u8>> {
+        rkyv::deserialize::<KeySet, rkyv::rancor::Error>(self)
+            .map(KeySet::into_inner)
+            .unwrap_or_default()
+            .into_iter()
+    }
 }
 
 // -------------------------------------------------------------------------------------------------
@@ -47,7 +56,7 @@ impl ReadableKeySet for &ArchivedKeySet {
     /// For example, `"Tide Pools"` might return `3` if there are three known creatures found there.
     #[inline]
     fn len(&self) -> usize {
-        self.0.len()
+        self.chunks.iter().map(|chunk| chunk.container.len()).sum::<usize>() + self.spill.len()
     }
 
     /// Returns `true` if this index set is empty.
@@ -56,7 +65,7 @@ impl ReadableKeySet for &ArchivedKeySet {
     /// habitat.
     #[inline]
     fn is_empty(&self) -> bool {
-        self.len() == 0
+        self.chunks.is_empty() && self.spill.is_empty()
     }
 
     // +---------------------------+
@@ -72,7 +81,19 @@ impl ReadableKeySet for &ArchivedKeySet {
     /// * The primary key must be in serialized form (as raw bytes).
     #[inline]
     fn contains(&self, primary_key_bytes: &[u8]) -> bool {
-        self.0.contains_key(primary_key_bytes)
+        self.integer_of(primary_key_bytes).map_or_else(
+            || {
+                self.spill
+                    .binary_search_by(|member| member.as_slice().cmp(primary_key_bytes))
+                    .is_ok()
+            },
+            |integer| {
+                let (high, low) = split(integer);
+                self.chunks
+                    .binary_search_by(|chunk| chunk.high.to_native().cmp(&high))
+                    .is_ok_and(|position| self.chunks[position].container.contains(low))
+            },
+        )
     }
 
     /// Returns `true` if this set is a subset of another.
@@ -80,13 +101,7 @@ impl ReadableKeySet for &ArchivedKeySet {
     /// “Are all elements in `self` also in `other`?”
     #[inline]
     fn is_subset(&self, other: &Self) -> bool {
-        self.0
-            .visit(|member| if other.contains(member) {
-                Continue(())
-            } else {
-                Break(())
-            })
-            .is_none()
+        self.len() <= other.len() && self.upgrade_iter().all(|member| other.contains(&member))
     }
 
     /// Returns `true` if this set is a superset of another.
@@ -94,13 +109,7 @@ impl ReadableKeySet for &ArchivedKeySet {
     /// “Are all elements in `other` also in `self`?”
     #[inline]
     fn is_superset(&self, other: &Self) -> bool {
-        other.0
-            .visit(|member| if self.contains(member) {
-                Continue(())
-            } else {
-                Break(())
-            })
-            .is_none()
+        other.is_subset(self)
     }
 
     /// Returns `true` if this set and another intersect.
@@ -108,13 +117,7 @@ impl ReadableKeySet for &ArchivedKeySet {
     /// “Do these sets share any elements?”
     #[inline]
     fn intersects(&self, other: &Self) -> bool {
-        self.0
-            .visit(|member| if other.contains(member) {
-                Break(())
-            } else {
-                Continue(())
-            })
-            .is_some()
+        self.upgrade_iter().any(|member| other.contains(&member))
     }
 }
 
@@ -138,25 +141,3 @@ impl crate::indexing::key_set::UpgradableKeySet for &ArchivedKeySet {
         Ok(deserialized)
     }
 }
-
-// -------------------------------------------------------------------------------------------------
-//
-// Trait Implementations
-
-impl std::ops::Deref for ArchivedKeySet {
-    type Target = ArchivedBTreeSet<ArchivedVec<u8>>;
-
-    /// Dereferences an `ArchivedKeySet` into its underlying `ArchivedBTreeSet<ArchivedVec<u8>>`
-    /// collection.
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
-
-impl std::ops::DerefMut for ArchivedKeySet {
-    /// Dereferences an `ArchivedKeySet` into its underlying `ArchivedBTreeSet<ArchivedVec<u8>>`
-    /// collection.
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
-    }
-}
\ No newline at end of file
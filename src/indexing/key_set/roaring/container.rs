@@ -0,0 +1,297 @@
+//! Roaring containers, which each hold the low 16 bits of every primary key that shares the same
+//! high bits.
+
+use rkyv::Archived;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Array containers are converted into bitmap containers once they hold more than this many
+/// values. At this size, both representations take 8 KiB.
+pub const ARRAY_LIMIT: usize = 4_096;
+
+/// The number of `u64` words in a bitmap container, enough for one bit per `u16` value.
+pub const BITMAP_WORDS: usize = 1_024;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A set of `u16` values, stored as a sorted array while sparse and as a bitmap once dense.
+#[derive(Clone, Debug, Eq, PartialEq, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+pub enum Container {
+    /// Sorted, unique values. Holds at most [`ARRAY_LIMIT`] values.
+    Array(Vec<u16>),
+
+    /// One bit per possible value, in [`BITMAP_WORDS`] words. Holds more than [`ARRAY_LIMIT`]
+    /// values.
+    Bitmap(Vec<u64>),
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// An iterator over the values in a [`Container`], in ascending order.
+pub enum Values<'c> {
+    /// Iterates over an array container.
+    Array(std::iter::Copied<std::slice::Iter<'c, u16>>),
+
+    /// Iterates over a bitmap container. `word` holds the bits of `words[index]` that haven't been
+    /// returned yet.
+    Bitmap { words: &'c [u64], index: usize, word: u64 },
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Container {
+    /// Returns how many values are in the container.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Array(values) => values.len(),
+            Self::Bitmap(words) => words.iter().map(|word| word.count_ones() as usize).sum(),
+        }
+    }
+
+    /// Returns `true` if the container holds no values.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Array(values) => values.is_empty(),
+            Self::Bitmap(words) => words.iter().all(|word| *word == 0),
+        }
+    }
+
+    /// Returns `true` if the container holds the given value.
+    #[must_use]
+    pub fn contains(&self, value: u16) -> bool {
+        match self {
+            Self::Array(values) => values.binary_search(&value).is_ok(),
+            Self::Bitmap(words) => words[word_of(value)] & bit_of(value) != 0,
+        }
+    }
+
+    /// Inserts a value, converting an array container that grows too large into a bitmap. Returns
+    /// `true` if the value wasn't already present.
+    pub fn insert(&mut self, value: u16) -> bool {
+        match self {
+            Self::Array(values) => match values.binary_search(&value) {
+                Ok(_) => false,
+                Err(position) => {
+                    values.insert(position, value);
+                    if values.len() > ARRAY_LIMIT {
+                        *self = Self::Bitmap(to_words(values));
+                    }
+                    true
+                }
+            },
+            Self::Bitmap(words) => {
+                let inserted = words[word_of(value)] & bit_of(value) == 0;
+                words[word_of(value)] |= bit_of(value);
+                inserted
+            }
+        }
+    }
+
+    /// Removes a value, converting a bitmap container that shrinks enough into an array. Returns
+    /// `true` if the value was present.
+    pub fn remove(&mut self, value: u16) -> bool {
+        match self {
+            Self::Array(values) => values
+                .binary_search(&value)
+                .map(|position| values.remove(position))
+                .is_ok(),
+            Self::Bitmap(words) => {
+                let removed = words[word_of(value)] & bit_of(value) != 0;
+                words[word_of(value)] &= !bit_of(value);
+                if removed && self.len() <= ARRAY_LIMIT {
+                    *self = Self::Array(self.iter().collect());
+                }
+                removed
+            }
+        }
+    }
+
+    /// Keeps only the values for which the predicate returns `true`.
+    pub fn retain(&mut self, mut keep: impl FnMut(u16) -> bool) {
+        let retained: Vec<u16> = self.iter().filter(|value| keep(*value)).collect();
+        *self = Self::from_sorted(retained);
+    }
+
+    /// Returns an iterator over the container's values, in ascending order.
+    #[must_use]
+    pub fn iter(&self) -> Values<'_> {
+        match self {
+            Self::Array(values) => Values::Array(values.iter().copied()),
+            Self::Bitmap(words) => Values::Bitmap {
+                words,
+                index: 0,
+                word: words.first().copied().unwrap_or_default(),
+            },
+        }
+    }
+
+    /// Returns the values present in both containers.
+    #[must_use]
+    pub fn and(&self, other: &Self) -> Self {
+        match (self, other) {
+            (Self::Array(left), Self::Array(right)) => Self::Array(merge(left, right, |l, r| l && r)),
+            (Self::Array(values), bitmap) | (bitmap, Self::Array(values)) => Self::Array(
+                values.iter().copied().filter(|value| bitmap.contains(*value)).collect()
+            ),
+            (Self::Bitmap(left), Self::Bitmap(right)) => Self::from_words(
+                left.iter().zip(right).map(|(l, r)| l & r).collect()
+            ),
+        }
+    }
+
+    /// Returns the values present in either container.
+    #[must_use]
+    pub fn or(&self, other: &Self) -> Self {
+        self.combine(other, |l, r| l || r, |l, r| l | r)
+    }
+
+    /// Returns the values present in this container but not the other.
+    #[must_use]
+    pub fn and_not(&self, other: &Self) -> Self {
+        self.combine(other, |l, r| l && !r, |l, r| l & !r)
+    }
+
+    /// Returns the values present in exactly one of the containers.
+    #[must_use]
+    pub fn xor(&self, other: &Self) -> Self {
+        self.combine(other, |l, r| l != r, |l, r| l ^ r)
+    }
+
+    /// Combines two containers: arrays are merged value-by-value, and anything involving a bitmap
+    /// is combined word-by-word.
+    fn combine(
+        &self,
+        other: &Self,
+        keep: impl Fn(bool, bool) -> bool,
+        word_op: impl Fn(u64, u64) -> u64,
+    ) -> Self {
+        if let (Self::Array(left), Self::Array(right)) = (self, other) {
+            return Self::from_sorted(merge(left, right, keep));
+        }
+
+        let (left, right) = (self.words(), other.words());
+        Self::from_words(left.iter().zip(&right).map(|(l, r)| word_op(*l, *r)).collect())
+    }
+
+    /// Returns the container's values as bitmap words.
+    fn words(&self) -> Vec<u64> {
+        match self {
+            Self::Array(values) => to_words(values),
+            Self::Bitmap(words) => words.clone(),
+        }
+    }
+
+    /// Builds the appropriate container for sorted, unique values.
+    fn from_sorted(values: Vec<u16>) -> Self {
+        if values.len() > ARRAY_LIMIT {
+            Self::Bitmap(to_words(&values))
+        } else {
+            Self::Array(values)
+        }
+    }
+
+    /// Builds the appropriate container for bitmap words.
+    fn from_words(words: Vec<u64>) -> Self {
+        let bitmap = Self::Bitmap(words);
+        if bitmap.len() > ARRAY_LIMIT {
+            bitmap
+        } else {
+            Self::Array(bitmap.iter().collect())
+        }
+    }
+}
+
+impl ArchivedContainer {
+    /// Returns how many values are in the container.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Array(values) => values.len(),
+            Self::Bitmap(words) => words
+                .iter()
+                .map(|word| word.to_native().count_ones() as usize)
+                .sum(),
+        }
+    }
+
+    /// Returns `true` if the container holds the given value.
+    #[must_use]
+    pub fn contains(&self, value: u16) -> bool {
+        match self {
+            Self::Array(values) => values
+                .binary_search_by(|member| member.to_native().cmp(&value))
+                .is_ok(),
+            Self::Bitmap(words) => words
+                .get(word_of(value))
+                .is_some_and(|word: &Archived<u64>| word.to_native() & bit_of(value) != 0),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl Iterator for Values<'_> {
+    type Item = u16;
+
+    fn next(&mut self) -> Option<u16> {
+        match self {
+            Self::Array(values) => values.next(),
+            Self::Bitmap { words, index, word } => {
+                while *word == 0 {
+                    *index += 1;
+                    *word = *words.get(*index)?;
+                }
+                let bit = word.trailing_zeros() as usize;
+                *word &= *word - 1;
+                u16::try_from(*index * 64 + bit).ok()
+            }
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Returns the index of the bitmap word that holds a value.
+const fn word_of(value: u16) -> usize {
+    value as usize / 64
+}
+
+/// Returns the bit within its bitmap word that represents a value.
+const fn bit_of(value: u16) -> u64 {
+    1 << (value % 64)
+}
+
+/// Converts sorted values into bitmap words.
+fn to_words(values: &[u16]) -> Vec<u64> {
+    let mut words = vec![0; BITMAP_WORDS];
+    for value in values {
+        words[word_of(*value)] |= bit_of(*value);
+    }
+    words
+}
+
+/// Merges two sorted arrays, keeping each value for which `keep(in_left, in_right)` is `true`.
+fn merge(left: &[u16], right: &[u16], keep: impl Fn(bool, bool) -> bool) -> Vec<u16> {
+    let mut merged = Vec::with_capacity(left.len().max(right.len()));
+    let (mut l, mut r) = (left.iter().peekable(), right.iter().peekable());
+    loop {
+        let (value, in_left, in_right) = match (l.peek(), r.peek()) {
+            (Some(lv), Some(rv)) if lv == rv => (**lv, true, true),
+            (Some(lv), Some(rv)) if lv < rv => (**lv, true, false),
+            (_, Some(rv)) => (**rv, false, true),
+            (Some(lv), None) => (**lv, true, false),
+            (None, None) => return merged,
+        };
+        if in_left { l.next(); }
+        if in_right { r.next(); }
+        if keep(in_left, in_right) { merged.push(value); }
+    }
+}
@@ -0,0 +1,590 @@
+//! An index `KeySet` help manages non-unique indexes. This implementation is a
+//! [Roaring bitmap](https://roaringbitmap.org/), for indexes whose primary keys are fixed-width
+//! integers.
+
+mod archived_key_set;
+mod container;
+mod readable_key_set;
+mod upgradable_key_set;
+
+use crate::indexing::key_set::ReadableKeySet;
+use crate::indexing::key_set::roaring::container::Container;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A collection of primary keys (serialized as raw bytes) associated with a given index entry. For
+/// example, it could be used to list all `creatures` that have a `Habitat` of `"Cloud Forest"`.
+///
+/// This implementation is a [Roaring bitmap](https://roaringbitmap.org/), archived with
+/// [David Koloski](https://crates.io/users/djkoloski)'s [rkyv](https://crates.io/crates/rkyv).
+///
+/// # Integer Primary Keys
+///
+/// The first primary key inserted that's 1 to 8 bytes long sets the set's key width. Every primary
+/// key of that width is read as a big-endian integer and stored in the bitmap: its high bits select
+/// a container, and its low 16 bits are stored in that container, either as a sorted `u16` array
+/// or, once there are more than 4,096 of them, as an 8 KiB bitmap.
+///
+/// For example, the `"Coral Reef"` → `[12, 48, 301]` index entry for `u64` primary keys is stored
+/// as one container holding `[12, 48, 301]` as `u16`s, rather than as three 8-byte vectors. Large
+/// non-unique indexes shrink dramatically, and unions and symmetric differences between key sets
+/// of the same width are computed container-by-container.
+///
+/// Primary keys of any other width are stored as-is alongside the bitmap, so every primary key
+/// type works, just without the savings.
+///
+/// # Notes
+///
+/// * Primary keys are returned by value, in ascending byte order for keys of the set's width,
+///   followed by the remaining keys in byte order.
+///
+/// * This backend is best suited to serializers that encode integers at a fixed width.
+#[derive(Clone, Debug, Default, Eq, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+pub struct KeySet {
+    /// Length, in bytes, of the primary keys stored in the bitmap. `0` until the first such key is
+    /// inserted.
+    pub(crate) width: u8,
+
+    /// The bitmap's containers, sorted by their high bits.
+    pub(crate) chunks: Vec<Chunk>,
+
+    /// Sorted, unique primary keys that aren't `width` bytes long.
+    pub(crate) spill: Vec<Vec<u8>>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// One of the bitmap's containers, along with the high bits that its values share.
+#[derive(Clone, Debug, Eq, PartialEq, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+pub struct Chunk {
+    /// The integer key's bits above the low 16.
+    pub(crate) high: u64,
+
+    /// The low 16 bits of every integer key with these high bits.
+    pub(crate) container: Container,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl KeySet {
+    // +---------------+
+    // | Basic Methods |
+    // +---------------+
+
+    /// Creates an empty `KeySet`.
+    ///
+    /// # Notes
+    ///
+    /// * This method is provided for compatibility. A bitmap's size depends on how its keys are
+    ///   distributed rather than how many there are, so `capacity` is ignored.
+    #[inline]
+    #[must_use]
+    pub fn with_capacity(_capacity: usize) -> Self {
+        Self::default()
+    }
+
+    /// Inserts the given primary key into the set.
+    ///
+    /// # Notes
+    ///
+    /// * The primary key must be represented in serialized form, as bytes.
+    pub fn insert(&mut self, primary_key_bytes: Vec<u8>) {
+        if self.width == 0 && (1..=8).contains(&primary_key_bytes.len()) {
+            self.width = u8::try_from(primary_key_bytes.len()).unwrap_or_default();
+        }
+
+        if let Some(integer) = self.integer_of(&primary_key_bytes) {
+            self.insert_integer(integer);
+        } else if let Err(position) = self.spill.binary_search(&primary_key_bytes) {
+            self.spill.insert(position, primary_key_bytes);
+        }
+    }
+
+    /// Removes the given primary key from the set.
+    ///
+    /// # Notes
+    ///
+    /// * The primary key must be represented in serialized form, as a slice of bytes.
+    pub fn remove(&mut self, primary_key_bytes: &[u8]) {
+        if let Some(integer) = self.integer_of(primary_key_bytes) {
+            let (high, low) = split(integer);
+            if let Ok(position) = self.chunks.binary_search_by_key(&high, |chunk| chunk.high) {
+                self.chunks[position].container.remove(low);
+                if self.chunks[position].container.is_empty() {
+                    self.chunks.remove(position);
+                }
+            }
+        } else if let Ok(position) = self.spill.binary_search_by(|m| m.as_slice().cmp(primary_key_bytes)) {
+            self.spill.remove(position);
+        }
+    }
+
+    /// Returns an iterator over the primary keys in the index set.
+    ///
+    /// # Notes
+    ///
+    /// * The primary keys will be returned in serialized form, as raw bytes. If needed, each key
+    ///   can be deserialized into its full form by using `K::deserialize(&item)`
+    ///
+    /// * Primary keys are used to get actual records from the database.
+    pub fn iter(&self) -> impl Iterator<Item = Vec<u8>> + '_ {
+        self.chunks
+            .iter()
+            .flat_map(|chunk| chunk.container.iter().map(|low| join(chunk.high, low)))
+            .map(|integer| key_bytes(self.width, integer))
+            .chain(self.spill.iter().cloned())
+    }
+
+    /// Returns the primary keys, collected into a `Vec`.
+    #[must_use]
+    pub fn into_inner(self) -> Vec<Vec<u8>> {
+        self.iter().collect()
+    }
+
+    /// Deserializes a `KeySet` from its binary representation.
+    ///
+    /// # Errors
+    ///
+    /// * This method will return an error if deserialization of the `KeySet`
+    ///   fails.
+    #[inline]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, crate::Error> {
+        let bytes = crate::indexing::AlignedBytes::new(bytes);
+        let archived = rkyv::access::<ArchivedKeySet, rkyv::rancor::Error>(&bytes)?;
+        let deserialized = rkyv::deserialize::<Self, rkyv::rancor::Error>(archived)?;
+        Ok(deserialized)
+    }
+
+    /// Serializes the `KeySet` to its binary representation.
+    ///
+    /// # Errors
+    ///
+    /// * This method will return an error if serialization of the `KeySet`
+    ///   fails.
+    #[inline]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, crate::Error> {
+        Ok(rkyv::to_bytes::<rkyv::rancor::Error>(self)?.to_vec())
+    }
+
+    // +----------------+
+    // | Set Operations |
+    // +----------------+
+
+    /// Returns the intersection of this set and another.
+    ///
+    /// Primary keys that are present in both sets will be included in the result.
+    ///
+    /// # Notes
+    ///
+    /// * The primary keys will be returned in serialized form, as bytes. If needed, each key can be
+    ///   deserialized into its full form by using `K::deserialize(item)`
+    ///
+    /// * Primary keys are used to get actual records from the database.
+    #[must_use]
+    pub fn intersection(mut self, other: &impl ReadableKeySet) -> Self {
+        self.retain(|member| other.contains(member));
+        self
+    }
+
+    /// Returns the union of this set and another.
+    ///
+    /// Primary keys that are present in either set will be included in the result.
+    ///
+    /// # Notes
+    ///
+    /// * The primary keys will be returned in serialized form, as bytes. If needed, each key can be
+    ///   deserialized into its full form by using `K::deserialize(item)`
+    ///
+    /// * Primary keys are used to get actual records from the database.
+    #[must_use]
+    pub fn union(self, other: Self) -> Self {
+        if !self.is_compatible(&other) {
+            let mut union = self;
+            union.extend(other);
+            return union;
+        }
+
+        let width = self.width.max(other.width);
+        let chunks = merge_chunks(self.chunks, other.chunks, Container::or);
+        let mut spill = self.spill;
+        spill.extend(other.spill);
+        spill.sort_unstable();
+        spill.dedup();
+
+        Self { width, chunks, spill }
+    }
+
+    /// Returns the difference between this set and another.
+    ///
+    /// Primary keys that are present in `self` but not in `other` will be included in the result.
+    ///
+    /// # Notes
+    ///
+    /// * The primary keys will be returned in serialized form, as bytes. If needed, each key can be
+    ///   deserialized into its full form by using `K::deserialize(item)`
+    ///
+    /// * Primary keys are used to get actual records from the database.
+    #[must_use]
+    pub fn difference(mut self, other: &impl ReadableKeySet) -> Self {
+        self.retain(|member| !other.contains(member));
+        self
+    }
+
+    /// Returns the symmetric difference of this set and another.
+    ///
+    /// Primary keys that are present in either set but not both will be included in the result.
+    ///
+    /// # Notes
+    ///
+    /// * The primary keys will be returned in serialized form, as bytes. If needed, each key can be
+    ///   deserialized into its full form by using `K::deserialize(item)`
+    ///
+    /// * Primary keys are used to get actual records from the database.
+    #[must_use]
+    pub fn symmetric_difference(self, other: Self) -> Self {
+        if !self.is_compatible(&other) {
+            let right: Vec<Vec<u8>> = other.iter().filter(|member| !self.contains(member)).collect();
+            let mut symmetric_difference = self.difference(&other);
+            symmetric_difference.extend(right);
+            return symmetric_difference;
+        }
+
+        let width = self.width.max(other.width);
+        let chunks = merge_chunks(self.chunks, other.chunks, Container::xor);
+        let mut spill: Vec<Vec<u8>> = self.spill
+            .iter()
+            .filter(|member| other.spill.binary_search(member).is_err())
+            .cloned()
+            .collect();
+        spill.extend(other.spill.into_iter().filter(|member| self.spill.binary_search(member).is_err()));
+        spill.sort_unstable();
+
+        Self { width, chunks, spill }
+    }
+
+    // +-----------------------------+
+    // | By-Reference Set Operations |
+    // +-----------------------------+
+
+    /// Keeps only the primary keys that are also present in another set, in place.
+    ///
+    /// This is the non-consuming form of [`KeySet::intersection`]. It's useful when combining many
+    /// sets, since the result isn't moved or reallocated for every set.
+    #[inline]
+    pub fn intersect_with(&mut self, other: &impl ReadableKeySet) {
+        self.retain(|member| other.contains(member));
+    }
+
+    /// Adds the primary keys of another set, in place.
+    ///
+    /// This is the non-consuming form of [`KeySet::union`]. Only `other`'s containers are cloned,
+    /// which is cheap compared to cloning its keys one-by-one.
+    #[inline]
+    pub fn union_with(&mut self, other: &Self) {
+        *self = std::mem::take(self).union(other.clone());
+    }
+
+    /// Removes the primary keys that are present in another set, in place.
+    ///
+    /// This is the non-consuming form of [`KeySet::difference`].
+    #[inline]
+    pub fn difference_with(&mut self, other: &impl ReadableKeySet) {
+        self.retain(|member| !other.contains(member));
+    }
+
+    /// Keeps the primary keys that are present in either set but not both, in place.
+    ///
+    /// This is the non-consuming form of [`KeySet::symmetric_difference`].
+    #[inline]
+    pub fn symmetric_difference_with(&mut self, other: &Self) {
+        *self = std::mem::take(self).symmetric_difference(other.clone());
+    }
+
+    // +-----------------+
+    // | Private Methods |
+    // +-----------------+
+
+    /// Returns the integer that a primary key is stored as in the bitmap, or `None` if it isn't
+    /// the set's width.
+    fn integer_of(&self, primary_key_bytes: &[u8]) -> Option<u64> {
+        (self.width != 0 && primary_key_bytes.len() == usize::from(self.width))
+            .then(|| primary_key_bytes.iter().fold(0, |integer, byte| integer << 8 | u64::from(*byte)))
+    }
+
+    /// Inserts an integer key into the bitmap.
+    fn insert_integer(&mut self, integer: u64) {
+        let (high, low) = split(integer);
+        match self.chunks.binary_search_by_key(&high, |chunk| chunk.high) {
+            Ok(position) => { self.chunks[position].container.insert(low); },
+            Err(position) => self.chunks.insert(
+                position,
+                Chunk { high, container: Container::Array(vec![low]) }
+            ),
+        }
+    }
+
+    /// Keeps only the primary keys for which the predicate returns `true`.
+    fn retain(&mut self, mut keep: impl FnMut(&[u8]) -> bool) {
+        let width = self.width;
+        for chunk in &mut self.chunks {
+            let high = chunk.high;
+            chunk.container.retain(|low| keep(&key_bytes(width, join(high, low))));
+        }
+        self.chunks.retain(|chunk| !chunk.container.is_empty());
+        self.spill.retain(|member| keep(member));
+    }
+
+    /// Returns `true` if the two sets' bitmaps hold keys of the same width, so that they can be
+    /// combined container-by-container.
+    const fn is_compatible(&self, other: &Self) -> bool {
+        self.width == other.width || self.width == 0 || other.width == 0
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl IntoIterator for KeySet {
+    type Item = Vec<u8>;
+    type IntoIter = std::vec::IntoIter<Vec<u8>>;
+
+    /// Returns an owned iterator over the primary keys in the index set.
+    ///
+    /// # Notes
+    ///
+    /// * The primary keys will be returned in serialized form, as raw bytes. If needed, each key
+    ///   can be deserialized into its full form by using `K::deserialize(&item)`
+    ///
+    /// * Primary keys are used to get actual records from the database.
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_inner().into_iter()
+    }
+}
+
+impl PartialEq for KeySet {
+    /// Returns `true` if both sets hold the same primary keys, however they're stored. A key's
+    /// width decides whether it's in the bitmap or spilled, so two sets that were first given keys
+    /// of different widths can store the same keys differently.
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.is_subset(other)
+    }
+}
+
+impl FromIterator<Vec<u8>> for KeySet {
+    /// Builds an `KeySet` collection from an iterator over primary keys.
+    ///
+    /// # Notes
+    ///
+    /// * The primary key must be represented in serialized form, as a slice of bytes.
+    fn from_iter<I: IntoIterator<Item = Vec<u8>>>(iter: I) -> Self {
+        let mut key_set = Self::default();
+        key_set.extend(iter);
+        key_set
+    }
+}
+
+impl FromIterator<Self> for KeySet {
+    /// Builds an `KeySet` collection from an iterator over other key sets.
+    fn from_iter<I: IntoIterator<Item = Self>>(iter: I) -> Self {
+        iter.into_iter().fold(Self::default(), Self::union)
+    }
+}
+
+impl Extend<Vec<u8>> for KeySet {
+    /// Extends a `KeySet` collection using an iterator over primary keys.
+    ///
+    /// # Notes
+    ///
+    /// * The primary keys will be returned in serialized form, as raw bytes. If needed, each key
+    ///   can be deserialized into its full form by using `K::deserialize(item)`
+    fn extend<T: IntoIterator<Item=Vec<u8>>>(&mut self, iter: T) {
+        iter.into_iter().for_each(|primary_key_bytes| self.insert(primary_key_bytes));
+    }
+}
+
+impl std::ops::BitAnd<&KeySet> for &KeySet {
+    type Output = KeySet;
+
+    /// Returns the intersection of two sets as a new `KeySet`, without consuming either:
+    /// `&a & &b`. Only chunks found in both sets are combined, container-by-container.
+    fn bitand(self, other: &KeySet) -> KeySet {
+        if !self.is_compatible(other) {
+            return self.iter().filter(|member| other.contains(member)).collect();
+        }
+
+        KeySet {
+            width: self.width.max(other.width),
+            chunks: intersect_chunks(&self.chunks, &other.chunks),
+            spill: self.spill
+                .iter()
+                .filter(|member| other.spill.binary_search(member).is_ok())
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+impl std::ops::BitOr<&KeySet> for &KeySet {
+    type Output = KeySet;
+
+    /// Returns the union of two sets as a new `KeySet`, without consuming either: `&a | &b`.
+    fn bitor(self, other: &KeySet) -> KeySet {
+        self.clone().union(other.clone())
+    }
+}
+
+impl std::ops::Sub<&KeySet> for &KeySet {
+    type Output = KeySet;
+
+    /// Returns the difference of two sets as a new `KeySet`, without consuming either: `&a - &b`.
+    fn sub(self, other: &KeySet) -> KeySet {
+        self.clone().difference(other)
+    }
+}
+
+impl std::ops::BitXor<&KeySet> for &KeySet {
+    type Output = KeySet;
+
+    /// Returns the symmetric difference of two sets as a new `KeySet`, without consuming either:
+    /// `&a ^ &b`.
+    fn bitxor(self, other: &KeySet) -> KeySet {
+        self.clone().symmetric_difference(other.clone())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Splits an integer key into its container's high bits and its low 16 bits.
+const fn split(integer: u64) -> (u64, u16) {
+    (integer >> 16, (integer & 0xFFFF) as u16)
+}
+
+/// Joins a container's high bits and a low 16-bit value back into an integer key.
+const fn join(high: u64, low: u16) -> u64 {
+    high << 16 | low as u64
+}
+
+/// Converts an integer key back into its serialized, big-endian primary key of the given width.
+fn key_bytes(width: u8, integer: u64) -> Vec<u8> {
+    integer.to_be_bytes()[8 - usize::from(width)..].to_vec()
+}
+
+/// Merges two sorted lists of chunks, combining the containers of chunks with the same high bits.
+/// Chunks found in only one list are kept as-is, and empty containers are dropped.
+fn merge_chunks(
+    left: Vec<Chunk>,
+    right: Vec<Chunk>,
+    combine: fn(&Container, &Container) -> Container,
+) -> Vec<Chunk> {
+    let mut merged = Vec::with_capacity(left.len().max(right.len()));
+    let (mut left, mut right) = (left.into_iter().peekable(), right.into_iter().peekable());
+    loop {
+        let chunk = match (left.peek(), right.peek()) {
+            (Some(l), Some(r)) if l.high == r.high => {
+                let (l, r) = (left.next(), right.next());
+                l.zip(r).map(|(l, r)| Chunk { high: l.high, container: combine(&l.container, &r.container) })
+            },
+            (Some(l), Some(r)) if l.high < r.high => left.next(),
+            (_, Some(_)) => right.next(),
+            (Some(_), None) => left.next(),
+            (None, None) => return merged,
+        };
+        merged.extend(chunk.filter(|chunk| !chunk.container.is_empty()));
+    }
+}
+
+/// Intersects two sorted lists of chunks, combining the containers of chunks with the same high
+/// bits. Chunks found in only one list, and empty containers, are dropped.
+fn intersect_chunks(left: &[Chunk], right: &[Chunk]) -> Vec<Chunk> {
+    left.iter()
+        .filter_map(|l| {
+            let r = right.binary_search_by_key(&l.high, |chunk| chunk.high).ok()?;
+            Some(Chunk { high: l.high, container: l.container.and(&right[r].container) })
+        })
+        .filter(|chunk| !chunk.container.is_empty())
+        .collect()
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[test]
+fn set_operations() {
+    let a = KeySet::from_iter(vec![vec![1], vec![2], vec![3]]);
+    let b = KeySet::from_iter(vec![vec![3], vec![4]]);
+    let c = KeySet::from_iter(vec![vec![2]]);
+
+    let result = a
+        .intersection(&b)     // [3]
+        .union(c)             // [2, 3]
+        .difference(&b);      // [2]
+
+    let expected = KeySet::from_iter(vec![vec![2]]);
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn dense_and_mixed_width_keys() {
+    let dense: KeySet = (0_u64..10_000).map(|id| id.to_be_bytes().to_vec()).collect();
+    let odd: KeySet = (0_u64..10_000).filter(|id| id % 2 == 1).map(|id| id.to_be_bytes().to_vec()).collect();
+    assert_eq!(dense.len(), 10_000);
+    assert!(dense.to_bytes().unwrap().len() < 10_000);
+
+    let even = dense.clone().symmetric_difference(odd.clone());
+    assert_eq!(even.len(), 5_000);
+    assert!(even.contains(&4_u64.to_be_bytes()));
+    assert!(!even.contains(&5_u64.to_be_bytes()));
+    assert_eq!(even.union(odd), dense);
+
+    let mut mixed = KeySet::from_iter(vec![7_u32.to_be_bytes().to_vec(), b"hermit crab".to_vec()]);
+    mixed.insert(7_u16.to_be_bytes().to_vec());
+    assert_eq!(mixed.len(), 3);
+    let bytes = mixed.to_bytes().unwrap();
+    assert_eq!(ArchivedKeySet::from_bytes(&bytes).unwrap().len(), 3);
+    mixed.remove(b"hermit crab");
+    assert!(!mixed.contains(b"hermit crab"));
+    assert!(mixed.contains(&7_u16.to_be_bytes()));
+}
+
+#[test]
+fn equality_compares_primary_keys() {
+    let short = 7_u16.to_be_bytes().to_vec();
+    let long = 7_u64.to_be_bytes().to_vec();
+    let short_first = KeySet::from_iter(vec![short.clone(), long.clone()]);
+    let long_first = KeySet::from_iter(vec![long.clone(), short]);
+    assert_ne!(short_first.width, long_first.width);
+    assert_eq!(short_first, long_first);
+
+    let mut emptied = KeySet::from_iter(vec![long.clone()]);
+    emptied.remove(&long);
+    assert_eq!(emptied, KeySet::default());
+    assert_ne!(short_first, KeySet::from_iter(vec![long]));
+}
+
+#[test]
+fn by_reference_set_operations() {
+    let a = KeySet::from_iter(vec![vec![1], vec![2], vec![3]]);
+    let b = KeySet::from_iter(vec![vec![3], vec![4]]);
+
+    assert_eq!((&a & &b).len(), 1);
+    assert_eq!((&a | &b).len(), 4);
+    assert_eq!((&a - &b).len(), 2);
+    assert_eq!((&a ^ &b).len(), 3);
+
+    let mut c = KeySet::from_iter(vec![vec![1], vec![2], vec![3]]);
+    c.intersect_with(&b);
+    c.union_with(&KeySet::from_iter(vec![vec![2]]));
+    c.difference_with(&b);
+    assert_eq!(c.len(), 1);
+    assert!(c.contains(&[2]));
+
+    c.symmetric_difference_with(&a);
+    assert_eq!(c.len(), 2);
+    assert!(!c.contains(&[2]));
+}
@@ -0,0 +1,82 @@
+//! `ReadableKeySet` implementation for `roaring::Keyset`
+
+use crate::indexing::key_set::roaring::KeySet;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Readable Key-Set Implementation
+
+impl crate::indexing::key_set::ReadableKeySet for KeySet {
+    // +----------------------+
+    // | Basic Set Operations |
+    // +----------------------+
+
+    /// Returns how many primary keys are in this index set.
+    ///
+    /// For example, `"Tide Pools"` might return `3` if there are three known creatures found there.
+    #[inline]
+    fn len(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.container.len()).sum::<usize>() + self.spill.len()
+    }
+
+    /// Returns `true` if this index set is empty.
+    ///
+    /// For example, `"ISO Class 1 Cleanroom"` might return `true` because no creatures live in the
+    /// habitat.
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.chunks.is_empty() && self.spill.is_empty()
+    }
+
+    // +---------------------------+
+    // | Set Membership Operations |
+    // +---------------------------+
+
+    /// Returns `true` if the index contains the given primary key.
+    ///
+    /// For example: `key_set.contains(&hermit_crab_id_bytes)`
+    ///
+    /// # Notes
+    ///
+    /// * The primary key must be in serialized form (as raw bytes).
+    #[inline]
+    fn contains(&self, primary_key_bytes: &[u8]) -> bool {
+        self.integer_of(primary_key_bytes).map_or_else(
+            || {
+                self.spill
+                    .binary_search_by(|member| member.as_slice().cmp(primary_key_bytes))
+                    .is_ok()
+            },
+            |integer| {
+                let (high, low) = super::split(integer);
+                self.chunks
+                    .binary_search_by_key(&high, |chunk| chunk.high)
+                    .is_ok_and(|position| self.chunks[position].container.contains(low))
+            },
+        )
+    }
+
+    /// Returns `true` if this set is a subset of another.
+    ///
+    /// “Are all elements in `self` also in `other`?”
+    #[inline]
+    fn is_subset(&self, other: &Self) -> bool {
+        self.len() <= other.len() && self.iter().all(|member| other.contains(&member))
+    }
+
+    /// Returns `true` if this set is a superset of another.
+    ///
+    /// “Are all elements in `other` also in `self`?”
+    #[inline]
+    fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Returns `true` if this set and another intersect.
+    ///
+    /// “Do these sets share any elements?”
+    #[inline]
+    fn intersects(&self, other: &Self) -> bool {
+        self.iter().any(|member| other.contains(&member))
+    }
+}
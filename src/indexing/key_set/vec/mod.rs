@@ -2,11 +2,12 @@
 //! [Rust Standard Library](https://doc.rust-lang.org/std/)'s
 //! [Vec](https://doc.rust-lang.org/std/vec/struct.Vec.html).
 
-mod archived_key_set;
 mod readable_key_set;
 mod upgradable_key_set;
 
-use crate::indexing::key_set::ReadableKeySet;
+use crate::indexing::key_set::{delta, ReadableKeySet};
+
+pub use crate::indexing::key_set::delta::ArchivedKeySet;
 
 // -------------------------------------------------------------------------------------------------
 //
@@ -14,8 +15,9 @@ use crate::indexing::key_set::ReadableKeySet;
 /// example, it could be used to list all `creatures` that have a `Habitat` of `"Cloud Forest"`.
 ///
 /// This implementation is powered by the [Rust Standard Library](https://doc.rust-lang.org/std/)'s
-/// [Vec](https://doc.rust-lang.org/std/vec/struct.Vec.html) and
-/// [David Koloski](https://crates.io/users/djkoloski)'s [rkyv](https://crates.io/crates/rkyv).
+/// [Vec](https://doc.rust-lang.org/std/vec/struct.Vec.html). Its primary keys are kept sorted and
+/// unique, so lookups are a binary search, and it's stored in index tables with prefix-delta and
+/// varint encoding (see the `delta` module).
 ///
 /// This set lists all of the primary keys associated with an index entry. Primary keys are in
 /// serialized form, represented by bytes. This collection is used internally to manage non-unique
@@ -87,7 +89,7 @@ use crate::indexing::key_set::ReadableKeySet;
 /// * It's used internally to resolve one-to-many relationships via indexes.
 /// * Backed by efficient data structures like `Vec` or deserialized on demand.
 /// * Critical for fast index queries like: "Give me everything in this habitat."
-#[derive(Debug, Default, Eq, PartialEq, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+#[derive(Debug, Default, Eq, PartialEq)]
 pub struct KeySet(pub(crate) Vec<Vec<u8>>);
 
 // -------------------------------------------------------------------------------------------------
@@ -108,14 +110,17 @@ impl KeySet {
         Self(Vec::<Vec<u8>>::with_capacity(capacity))
     }
 
-    /// Inserts the given primary key into the set.
+    /// Inserts the given primary key into the set. A primary key that's already in the set isn't
+    /// inserted again.
     ///
     /// # Notes
     ///
     /// * The primary key must be represented in serialized form, as bytes.
     #[inline]
     pub fn insert(&mut self, primary_key_bytes: Vec<u8>) {
-        self.0.push(primary_key_bytes);
+        if let Err(position) = self.0.binary_search(&primary_key_bytes) {
+            self.0.insert(position, primary_key_bytes);
+        }
     }
 
     /// Removes the given primary key from the set.
@@ -125,7 +130,19 @@ impl KeySet {
     /// * The primary key must be represented in serialized form, as a slice of bytes.
     #[inline]
     pub fn remove(&mut self, primary_key_bytes: &[u8]) {
-        self.0.retain(|member| member != primary_key_bytes);
+        if let Ok(position) = self.0.binary_search_by(|member| member.as_slice().cmp(primary_key_bytes)) {
+            self.0.remove(position);
+        }
+    }
+
+    /// Sorts the primary keys and drops any duplicates.
+    ///
+    /// `KeySet` keeps its primary keys sorted and unique, so this is only needed after the
+    /// underlying `Vec` has been modified directly, through `DerefMut`.
+    #[inline]
+    pub fn normalize(&mut self) {
+        self.0.sort_unstable();
+        self.0.dedup();
     }
 
     /// Returns a borrowed iterator over the primary keys in the index set.
@@ -152,13 +169,10 @@ impl KeySet {
     ///
     /// # Errors
     ///
-    /// * This method will return an error if deserialization of the `KeySet`
-    ///   fails.
+    /// * This method will return an error if decoding of the `KeySet` fails.
     #[inline]
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, crate::Error> {
-        let archived = rkyv::access::<ArchivedKeySet, rkyv::rancor::Error>(bytes)?;
-        let deserialized = rkyv::deserialize::<Self, rkyv::rancor::Error>(archived)?;
-        Ok(deserialized)
+        delta::DeltaKeys::new(bytes)?.collect()
     }
 
     /// Serializes the `KeySet` to its binary representation.
@@ -167,9 +181,21 @@ impl KeySet {
     ///
     /// * This method will return an error if serialization of the `KeySet`
     ///   fails.
+    ///
+    /// # Notes
+    ///
+    /// * If the underlying `Vec` was modified directly and is no longer sorted and unique, a
+    ///   normalized copy of the primary keys is encoded instead.
     #[inline]
     pub fn to_bytes(&self) -> Result<Vec<u8>, crate::Error> {
-        Ok(rkyv::to_bytes::<rkyv::rancor::Error>(self)?.to_vec())
+        if is_normalized(&self.0) {
+            return Ok(delta::encode(self.0.iter().map(Vec::as_slice)));
+        }
+
+        let mut sorted: Vec<&[u8]> = self.0.iter().map(Vec::as_slice).collect();
+        sorted.sort_unstable();
+        sorted.dedup();
+        Ok(delta::encode(sorted.into_iter()))
     }
 
     // +----------------+
@@ -207,12 +233,7 @@ impl KeySet {
     ///
     /// * Primary keys are used to get actual records from the database.
     pub fn union(self, other: KeySet) -> Self {
-        let union_result: Vec<Vec<u8>> = self.0
-            .into_iter()
-            .chain(other.0)
-            .collect();
-
-        Self(union_result)
+        Self(merge(self.0, other.0))
     }
 
     /// Returns the difference between this set and another.
@@ -246,17 +267,66 @@ impl KeySet {
     ///
     /// * Primary keys are used to get actual records from the database.
     pub fn symmetric_difference(self, other: KeySet) -> Self {
-        let difference: Vec<Vec<u8>> = self.0
-            .into_iter()
+        let left: Vec<Vec<u8>> = self.0
+            .iter()
             .filter(|member| !other.contains(member))
+            .cloned()
             .collect();
 
-        let symmetric_difference: Vec<Vec<u8>> = other
+        let right: Vec<Vec<u8>> = other.0
             .into_iter()
-            .filter(|member| !difference.contains(member))
+            .filter(|member| !self.contains(member))
             .collect();
 
-        Self(symmetric_difference)
+        Self(merge(left, right))
+    }
+
+    // +-----------------------------+
+    // | By-Reference Set Operations |
+    // +-----------------------------+
+
+    /// Keeps only the primary keys that are also present in another set, in place.
+    ///
+    /// This is the non-consuming form of [`KeySet::intersection`]. It's useful when combining many
+    /// sets, since the result isn't moved or reallocated for every set.
+    #[inline]
+    pub fn intersect_with(&mut self, other: &impl ReadableKeySet) {
+        self.0.retain(|member| other.contains(member));
+    }
+
+    /// Adds the primary keys of another set, in place. Only the keys that aren't already present
+    /// are cloned.
+    ///
+    /// This is the non-consuming form of [`KeySet::union`].
+    #[inline]
+    pub fn union_with(&mut self, other: &Self) {
+        for member in &other.0 {
+            if !self.contains(member) {
+                self.insert(member.clone());
+            }
+        }
+    }
+
+    /// Removes the primary keys that are present in another set, in place.
+    ///
+    /// This is the non-consuming form of [`KeySet::difference`].
+    #[inline]
+    pub fn difference_with(&mut self, other: &impl ReadableKeySet) {
+        self.0.retain(|member| !other.contains(member));
+    }
+
+    /// Keeps the primary keys that are present in either set but not both, in place.
+    ///
+    /// This is the non-consuming form of [`KeySet::symmetric_difference`].
+    #[inline]
+    pub fn symmetric_difference_with(&mut self, other: &Self) {
+        for member in &other.0 {
+            if self.contains(member) {
+                self.remove(member);
+            } else {
+                self.insert(member.clone());
+            }
+        }
     }
 }
 
@@ -321,23 +391,16 @@ impl FromIterator<Vec<u8>> for KeySet {
     ///
     /// * The primary key must be represented in serialized form, as a slice of bytes.
     fn from_iter<I: IntoIterator<Item = Vec<u8>>>(iter: I) -> Self {
-        Self(iter.into_iter().collect())
+        let mut key_set = Self(iter.into_iter().collect());
+        key_set.normalize();
+        key_set
     }
 }
 
 impl FromIterator<KeySet> for KeySet {
     /// Builds an `KeySet` collection from an iterator over other key sets.
     fn from_iter<I: IntoIterator<Item = KeySet>>(iter: I) -> Self {
-        let mut dest_key_set = KeySet::default();
-
-        iter
-            .into_iter()
-            .for_each(|src_key_set| {
-                dest_key_set.reserve(src_key_set.len());
-                dest_key_set.extend(src_key_set)
-            });
-
-        dest_key_set
+        iter.into_iter().fold(Self::default(), Self::union)
     }
 }
 
@@ -349,7 +412,77 @@ impl Extend<Vec<u8>> for KeySet {
     /// * The primary keys will be returned in serialized form, as raw bytes. If needed, each key
     ///   can be deserialized into its full form by using `K::deserialize(item)`
     fn extend<T: IntoIterator<Item=Vec<u8>>>(&mut self, iter: T) {
-       self.0.extend(iter)
+        self.0.extend(iter);
+        self.normalize();
+    }
+}
+
+impl std::ops::BitAnd<&KeySet> for &KeySet {
+    type Output = KeySet;
+
+    /// Returns the intersection of two sets as a new `KeySet`, without consuming either:
+    /// `&a & &b`. The smaller set is walked, and its matching keys are cloned.
+    fn bitand(self, other: &KeySet) -> KeySet {
+        let (smaller, larger) = if self.0.len() <= other.0.len() { (self, other) } else { (other, self) };
+        smaller.0.iter().filter(|member| larger.contains(member)).cloned().collect()
+    }
+}
+
+impl std::ops::BitOr<&KeySet> for &KeySet {
+    type Output = KeySet;
+
+    /// Returns the union of two sets as a new `KeySet`, without consuming either: `&a | &b`.
+    fn bitor(self, other: &KeySet) -> KeySet {
+        let mut union: KeySet = self.0.iter().cloned().collect();
+        union.union_with(other);
+        union
+    }
+}
+
+impl std::ops::Sub<&KeySet> for &KeySet {
+    type Output = KeySet;
+
+    /// Returns the difference of two sets as a new `KeySet`, without consuming either: `&a - &b`.
+    fn sub(self, other: &KeySet) -> KeySet {
+        self.0.iter().filter(|member| !other.contains(member)).cloned().collect()
+    }
+}
+
+impl std::ops::BitXor<&KeySet> for &KeySet {
+    type Output = KeySet;
+
+    /// Returns the symmetric difference of two sets as a new `KeySet`, without consuming either:
+    /// `&a ^ &b`.
+    fn bitxor(self, other: &KeySet) -> KeySet {
+        let mut symmetric_difference = self - other;
+        symmetric_difference.union_with(&(other - self));
+        symmetric_difference
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Returns `true` if the primary keys are sorted, with no duplicates.
+fn is_normalized(members: &[Vec<u8>]) -> bool {
+    members.windows(2).all(|pair| pair[0] < pair[1])
+}
+
+/// Merges two sorted, unique lists of primary keys into one. Primary keys found in both lists are
+/// kept once.
+fn merge(left: Vec<Vec<u8>>, right: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let (mut left, mut right) = (left.into_iter().peekable(), right.into_iter().peekable());
+    loop {
+        let member = match (left.peek(), right.peek()) {
+            (Some(l), Some(r)) if l == r => { right.next(); left.next() },
+            (Some(l), Some(r)) if l < r => left.next(),
+            (_, Some(_)) => right.next(),
+            (Some(_), None) => left.next(),
+            (None, None) => return merged,
+        };
+        merged.extend(member);
     }
 }
 
@@ -370,4 +503,45 @@ fn set_operations() {
 
     let expected = KeySet::from_iter(vec![vec![2]]);
     assert_eq!(result, expected);
-}
\ No newline at end of file
+}
+#[test]
+fn by_reference_set_operations() {
+    let a = KeySet::from_iter(vec![vec![1], vec![2], vec![3]]);
+    let b = KeySet::from_iter(vec![vec![3], vec![4]]);
+
+    assert_eq!((&a & &b).len(), 1);
+    assert_eq!((&a | &b).len(), 4);
+    assert_eq!((&a - &b).len(), 2);
+    assert_eq!((&a ^ &b).len(), 3);
+
+    let mut c = KeySet::from_iter(vec![vec![1], vec![2], vec![3]]);
+    c.intersect_with(&b);
+    c.union_with(&KeySet::from_iter(vec![vec![2]]));
+    c.difference_with(&b);
+    assert_eq!(c.len(), 1);
+    assert!(c.contains(&[2]));
+
+    c.symmetric_difference_with(&a);
+    assert_eq!(c.len(), 2);
+    assert!(!c.contains(&[2]));
+}
+
+#[test]
+fn stays_sorted_and_unique() {
+    let mut a = KeySet::from_iter(vec![vec![3], vec![1], vec![3], vec![2]]);
+    a.insert(vec![2]);
+    a.insert(vec![0]);
+    assert_eq!(a.0, vec![vec![0], vec![1], vec![2], vec![3]]);
+
+    let b = a.union(KeySet::from_iter(vec![vec![1], vec![5]]));
+    assert_eq!(b.0, vec![vec![0], vec![1], vec![2], vec![3], vec![5]]);
+    assert!(b.contains(&[5]) && !b.contains(&[4]));
+
+    let mut c = KeySet::default();
+    c.push(vec![9]);
+    c.push(vec![9]);
+    c.push(vec![8]);
+    assert_eq!(KeySet::from_bytes(&c.to_bytes().unwrap()).unwrap().0, vec![vec![8], vec![9]]);
+    c.normalize();
+    assert_eq!(c.0, vec![vec![8], vec![9]]);
+}
@@ -41,7 +41,7 @@ impl crate::indexing::key_set::ReadableKeySet for KeySet {
     /// * The primary key must be in serialized form (as raw bytes).
     #[inline]
     fn contains(&self, primary_key_bytes: &[u8]) -> bool {
-        self.0.iter().any(|member| member == primary_key_bytes)
+        self.0.binary_search_by(|member| member.as_slice().cmp(primary_key_bytes)).is_ok()
     }
 
     /// Returns `true` if this set is a subset of another.
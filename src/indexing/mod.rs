@@ -1,18 +1,54 @@
 mod key_set;
 
 
-pub use crate::indexing::key_set::{ArchivedKeySet, KeySet, ReadableKeySet, UpgradableKeySet};
+pub use crate::indexing::key_set::{
+    AlignedBytes, ArchivedKeySet, KeySet, ReadableKeySet, UpgradableKeySet
+};
 
+mod references;
+pub use crate::indexing::references::{Dependent, HasDependents, OnDelete, Reference, References};
 
+mod covering;
+pub use crate::indexing::covering::{CoveringBytes, Covered, covering_key, covering_prefix};
 
+mod entries;
+pub use crate::indexing::entries::IndexEntries;
 
+mod index;
+pub use crate::indexing::index::Index;
 
+mod normalize;
+pub use crate::indexing::normalize::KeyNormalization;
 
+#[cfg(any(feature = "kdf-blake3", feature = "kdf-sha256"))]
+mod protection;
+#[cfg(any(feature = "kdf-blake3", feature = "kdf-sha256"))]
+pub use crate::indexing::protection::IndexProtection;
 
+#[cfg(feature = "correctors")]
+mod correction;
+#[cfg(feature = "correctors")]
+pub use crate::indexing::correction::IndexCorrection;
 
+mod reverse;
+pub use crate::indexing::reverse::ReverseEntry;
 
+mod shards;
+pub use crate::indexing::shards::{
+    KeySetCursor,
+    SHARD_CAPACITY,
+    is_full_shard,
+    read_overflow,
+    read_overflow_with,
+    shard_key,
+    shard_table_name,
+};
 
+mod stats;
+pub use crate::indexing::stats::{HISTOGRAM_BUCKETS, IndexStats, STATS_TABLE_NAME};
 
+mod verify;
+pub use crate::indexing::verify::{IndexIssue, IndexReport};
 
 
 
@@ -22,6 +58,18 @@ pub use crate::indexing::key_set::{ArchivedKeySet, KeySet, ReadableKeySet, Upgra
 
 
 
+
+
+
+
+
+
+
+
+
+
+
+use crate::typed::record_layers::RecordLayers;
 use crate::{Codec, Error};
 
 // atlatl stuff
@@ -34,7 +82,48 @@ pub trait HasTable {
     ///
     /// For example, a primary table could consist of all types creatures on Earth: of varying
     /// `Habitat`s, `Species`, `Diet`s, etc.
+    ///
+    /// The name is unqualified. When the table is opened, it's prefixed with the transaction's
+    /// [`Namespace`](crate::typed::Namespace), as are index and other table names.
     fn table_name() -> &'static str;
+
+    /// Returns the name of the record type's reverse index table, if it has one.
+    ///
+    /// A reverse index maps each record's primary key to every secondary index entry it appears
+    /// in, so that deletes and updates can clean up index entries without reading the old record.
+    /// It costs one extra row per record. For example: `Some("creatures_reverse")`.
+    ///
+    /// Defaults to `None`, which disables the reverse index.
+    #[must_use] 
+    fn reverse_index_name() -> Option<&'static str> {
+        None
+    }
+
+    /// Returns the name of the record type's history table, if it keeps one.
+    ///
+    /// A history table keeps every version of each record that has been replaced or deleted by an
+    /// index-aware write, along with when it was current, so that a record can be read as it was
+    /// at an earlier time. See [`Historied`](crate::typed::history::Historied). It costs one extra
+    /// row per write. For example: `Some("creatures_history")`.
+    ///
+    /// Defaults to `None`, which disables history.
+    #[must_use] 
+    fn history_table_name() -> Option<&'static str> {
+        None
+    }
+
+    /// Returns the layers that the record type's values are stored with: compression, encryption,
+    /// and error correction, in the order of its [`LayerStack`]. For example:
+    /// `Some(RecordLayers::of::<Self>())`. See the
+    /// [`record_layers`](crate::typed::record_layers) module.
+    ///
+    /// Defaults to `None`, which stores values as their [`Codec`] serializes them.
+    ///
+    /// [`LayerStack`]: crate::layers::LayerStack
+    #[must_use]
+    fn layers() -> Option<RecordLayers<Self>> where Self: Sized {
+        None
+    }
 }
 
 /// A trait for types that can declare their associated table name and primary key.
@@ -65,7 +154,7 @@ impl<'pk, PK: Codec<PK>> PrimaryKey<'pk, PK> {
     /// * Returns an error if the key cannot be serialized by the active
     ///   `Codec`.
     pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
-        Ok(PK::serialize(self.0)?)
+        PK::serialize(self.0)
     }
 }
 
@@ -90,7 +179,7 @@ impl<'pk, PK: Codec<PK>> From<&'pk PK> for PrimaryKey<'pk, PK> {
 ///
 /// This trait enables type-safe and ergonomic lookups such as:
 ///
-/// ```rust
+/// ```rust,ignore
 /// let user = db.get_indexed(Birthday(date))?;
 /// ```
 ///
@@ -131,6 +220,16 @@ pub trait IndexLookup {
     /// Returns the kind of index: `Unique` or `NonUnique`.
     #[must_use] fn index_kind(&self) -> &IndexKind;
 
+    /// Returns how the index's string keys are normalized, for example
+    /// [`KeyNormalization::Lowercase`] for case-insensitive look-ups.
+    ///
+    /// The mode is applied by encoding the key with [`KeyNormalization::encode`] in
+    /// [`IndexLookup::index_key_bytes`], so that index entries and query keys agree. Defaults to
+    /// [`KeyNormalization::Exact`].
+    #[must_use] fn key_normalization(&self) -> KeyNormalization {
+        KeyNormalization::Exact
+    }
+
     /// Encodes the index key into bytes to look-up the key-set.
     ///
     /// For example, this secondary key (in raw serialized bytes) could represent a
@@ -148,9 +247,33 @@ pub trait IndexLookup {
     fn index_key_bytes(&self) -> Result<Vec<u8>, crate::Error>;
 
     /// Optional method to provide a human-readable representation of the index key.
+    ///
+    /// # Errors
+    ///
+    /// * Returns an error if the formatter fails to write.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", std::any::type_name::<Self>())
     }
+
+    /// Returns the placeholder's name and expected look-up type if this look-up is a query
+    /// parameter that's yet to be bound to a value.
+    ///
+    /// Regular index look-ups should not override this method.
+    fn parameter(&self) -> Option<(&'static str, &'static str)> {
+        None
+    }
+
+    /// Returns the record's serialized projection and the covering table it's stored in, if this
+    /// look-up belongs to a covering index. See [`Covered`].
+    ///
+    /// Regular index look-ups should not override this method.
+    ///
+    /// # Errors
+    ///
+    /// * Returns an error if the projection could not be serialized.
+    fn covering(&self) -> Result<Option<CoveringBytes>, crate::Error> {
+        Ok(None)
+    }
 }
 
 
@@ -235,7 +358,8 @@ impl<V> PreparedIndexLookup<V> {
     ///
     /// * `index_key_bytes` · And that we would like to see the critters listed in the
     ///   `Habitat("Rain Forest")` index.
-    pub fn new(
+    #[must_use] 
+    pub const fn new(
         index_name: &'static str,
         index_kind: IndexKind,
         index_key_bytes: Vec<u8>,
@@ -268,6 +392,146 @@ impl<V: HasTable> IndexLookup for PreparedIndexLookup<V> {
     }
 }
 
+/// Wraps an [`IndexLookup`] so that its secondary key is looked-up by search token rather than by
+/// plain text.
+///
+/// This is used for fields that are encrypted at rest but still need equality search, for example
+/// a user's email address. When the record is indexed, the field's serialized bytes are hashed into
+/// a [`BlindToken`] and only the token is written to the index table. When the index is queried,
+/// the search term is hashed the same way, so the plain text never appears in any table.
+///
+/// Tokens are only written for indexes registered on the write transaction with
+/// [`with_blind_index`], using the same `BlindIndex` that's given to the look-up.
+///
+/// ```rust
+/// use atlatl::indexing::{BlindLookup, Habitat, IndexLookup};
+/// use atlatl::layers::encryptors::{BlindIndex, KeyBytes};
+///
+/// let key = KeyBytes::from_array(&[7_u8; 32]);
+/// let habitat_index = BlindIndex::new(&key, "creatures_by_habitat");
+/// let lookup = BlindLookup::new(Habitat("Desert".into()), &habitat_index);
+///
+/// let plain_text = Habitat("Desert".into()).index_key_bytes()?;
+/// assert_eq!(lookup.index_key_bytes()?, habitat_index.token(&plain_text).to_vec());
+/// # Ok::<(), atlatl::Error>(())
+/// ```
+///
+/// [`with_blind_index`]: crate::typed::transaction::WriteTransaction::with_blind_index
+///
+/// [`BlindToken`]: crate::layers::encryptors::BlindToken
+#[cfg(any(feature = "kdf-blake3", feature = "kdf-sha256"))]
+pub struct BlindLookup<I> {
+    /// The underlying plain text index look-up. For example: `Email("jane@example.com")`.
+    pub index_lookup: I,
+
+    /// The blind index used to convert the look-up's key into a search token.
+    pub blind_index: crate::layers::encryptors::BlindIndex,
+}
+
+#[cfg(any(feature = "kdf-blake3", feature = "kdf-sha256"))]
+impl<I: IndexLookup> BlindLookup<I> {
+    /// Instantiates a new `BlindLookup` from a plain text look-up and the field's `BlindIndex`.
+    pub fn new(index_lookup: I, blind_index: &crate::layers::encryptors::BlindIndex) -> Self {
+        Self { index_lookup, blind_index: blind_index.clone() }
+    }
+}
+
+#[cfg(any(feature = "kdf-blake3", feature = "kdf-sha256"))]
+impl<I: IndexLookup> IndexLookup for BlindLookup<I> {
+    type Record = I::Record;
+
+    /// Returns the name of the secondary index table being queried.
+    fn index_name(&self) -> &'static str {
+        self.index_lookup.index_name()
+    }
+
+    /// Returns whether the index is `Unique` or `NonUnique`.
+    fn index_kind(&self) -> &IndexKind {
+        self.index_lookup.index_kind()
+    }
+
+    /// Returns how the underlying look-up's key is normalized before it's hashed.
+    fn key_normalization(&self) -> KeyNormalization {
+        self.index_lookup.key_normalization()
+    }
+
+    /// Returns the search token for the secondary key, rather than its serialized plain text.
+    fn index_key_bytes(&self) -> Result<Vec<u8>, crate::Error> {
+        let plain_text = self.index_lookup.index_key_bytes()?;
+        Ok(self.blind_index.token(&plain_text).to_vec())
+    }
+
+    /// Formats the look-up without revealing the plain text search term.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BLIND({})", self.index_lookup.index_name())
+    }
+}
+
+/// Wraps an [`IndexLookup`] so that its secondary key is looked-up by deterministic cipher text
+/// rather than by plain text.
+///
+/// This works like a [`BlindLookup`], except that the secondary key is encrypted with a
+/// [`DeterministicCipher`] instead of hashed, so the index's keys can still be decrypted, for
+/// example to list them. The plain text never appears in any table.
+///
+/// ```rust,ignore
+/// let email_index = DeterministicCipher::new(&key, "users_by_email");
+/// let query = Query::deterministic(Email("jane@example.com".into()), &email_index);
+/// ```
+///
+/// [`DeterministicCipher`]: crate::layers::encryptors::DeterministicCipher
+#[cfg(any(feature = "kdf-blake3", feature = "kdf-sha256"))]
+pub struct DeterministicLookup<I> {
+    /// The underlying plain text index look-up. For example: `Email("jane@example.com")`.
+    pub index_lookup: I,
+
+    /// The cipher used to encrypt the look-up's key.
+    pub cipher: crate::layers::encryptors::DeterministicCipher,
+}
+
+#[cfg(any(feature = "kdf-blake3", feature = "kdf-sha256"))]
+impl<I: IndexLookup> DeterministicLookup<I> {
+    /// Instantiates a new `DeterministicLookup` from a plain text look-up and the field's
+    /// `DeterministicCipher`.
+    pub fn new(index_lookup: I, cipher: &crate::layers::encryptors::DeterministicCipher) -> Self {
+        Self { index_lookup, cipher: cipher.clone() }
+    }
+}
+
+#[cfg(any(feature = "kdf-blake3", feature = "kdf-sha256"))]
+impl<I: IndexLookup> IndexLookup for DeterministicLookup<I> {
+    type Record = I::Record;
+
+    /// Returns the name of the secondary index table being queried.
+    fn index_name(&self) -> &'static str {
+        self.index_lookup.index_name()
+    }
+
+    /// Returns whether the index is `Unique` or `NonUnique`.
+    fn index_kind(&self) -> &IndexKind {
+        self.index_lookup.index_kind()
+    }
+
+    /// Returns how the underlying look-up's key is normalized before it's encrypted.
+    fn key_normalization(&self) -> KeyNormalization {
+        self.index_lookup.key_normalization()
+    }
+
+    /// Returns the deterministic cipher text of the secondary key, rather than its serialized
+    /// plain text.
+    fn index_key_bytes(&self) -> Result<Vec<u8>, crate::Error> {
+        let plain_text = self.index_lookup.index_key_bytes()?;
+        self.cipher
+            .encrypt(&plain_text)
+            .map_err(|error| crate::layers::encryptors::Error::from(error).into())
+    }
+
+    /// Formats the look-up without revealing the plain text search term.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DETERMINISTIC({})", self.index_lookup.index_name())
+    }
+}
+
 
 
 
@@ -336,6 +600,10 @@ pub trait IndexMultiLookup {
     ///
     /// These are later used to fetch associated primary keys for exclusion. For example, if the
     /// caller wanted to exclude `Habitat`s of `"Forest"`, `"Savannah"` and `"Desert"`.
+    ///
+    /// # Errors
+    ///
+    /// * Returns an error if any of the secondary keys could not be serialized.
     fn to_key_set(&self) -> Result<KeySet, crate::Error>;
 }
 
@@ -347,17 +615,17 @@ where
 
     /// Returns the name of the primary record table associated with this index.
     fn table_name(&self) -> Option<&'static str> {
-        Some(self.table_name())
+        Some(IndexLookup::table_name(self))
     }
 
     /// Returns the name of the secondary index table being queried.
     fn index_name(&self) -> Option<&'static str> {
-        Some(self.index_name())
+        Some(IndexLookup::index_name(self))
     }
 
     /// Returns whether the index is `Unique` or `NonUnique`.
     fn index_kind(&self) -> Option<&IndexKind> {
-        Some(self.index_kind())
+        Some(IndexLookup::index_kind(self))
     }
 
     /// Returns the set of keys to look-up from the secondary index table.
@@ -379,22 +647,22 @@ where
 
     /// Returns the name of the primary record table associated with this index.
     fn table_name(&self) -> Option<&'static str> {
-        self.first().map(|item| item.table_name())
+        self.first().map(IndexLookup::table_name)
     }
 
     /// Returns the name of the secondary index table being queried.
     fn index_name(&self) -> Option<&'static str> {
-        self.first().map(|item| item.index_name())
+        self.first().map(IndexLookup::index_name)
     }
 
     /// Returns whether the index is `Unique` or `NonUnique`.
     fn index_kind(&self) -> Option<&IndexKind> {
-        self.first().map(|item| item.index_kind())
+        self.first().map(IndexLookup::index_kind)
     }
 
     /// Returns the set of keys to look-up from the secondary index table.
     fn to_key_set(&self) -> Result<KeySet, Error> {
-        self.iter().map(|item| item.index_key_bytes()).collect()
+        self.iter().map(IndexLookup::index_key_bytes).collect()
     }
 }
 
@@ -409,22 +677,22 @@ where
 
     /// Returns the name of the primary record table associated with this index.
     fn table_name(&self) -> Option<&'static str> {
-        self.first().map(|item| item.table_name())
+        self.first().map(IndexLookup::table_name)
     }
 
     /// Returns the name of the secondary index table being queried.
     fn index_name(&self) -> Option<&'static str> {
-        self.first().map(|item| item.index_name())
+        self.first().map(IndexLookup::index_name)
     }
 
     /// Returns whether the index is `Unique` or `NonUnique`.
     fn index_kind(&self) -> Option<&IndexKind> {
-        self.first().map(|item| item.index_kind())
+        self.first().map(IndexLookup::index_kind)
     }
 
     /// Returns the set of keys to look-up from the secondary index table.
     fn to_key_set(&self) -> Result<KeySet, Error> {
-        self.iter().map(|item| item.index_key_bytes()).collect()
+        self.iter().map(IndexLookup::index_key_bytes).collect()
     }
 }
 
@@ -527,7 +795,7 @@ where
 
 
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[repr(u8)]
 pub enum IndexKind {
     Unique = 0,
@@ -561,6 +829,11 @@ pub trait Indexable<'i> {
     type Index: IndexLookup;
     type Indexes: IntoIterator<Item = Self::Index>;
 
+    /// Returns the index look-ups for every index the record participates in.
+    ///
+    /// # Errors
+    ///
+    /// * Returns an error if a secondary key couldn't be derived from the record.
     fn indexes(&'i self) -> Result<Self::Indexes, Error>;
 }
 
@@ -755,16 +1028,67 @@ impl<'pk, 'sk, K> IndexEntry<'pk, 'sk, K>
 where
     K: Codec<K> + IndexLookup
 {
-    pub const fn new(
-        key: &'sk IndexKey<K>,
-        primary_key_bytes: &'pk [u8],
-    ) -> Result<Self, Error> {
-        Ok(Self {
+    /// Instantiates an index entry that points the given index key at a serialized primary key.
+    #[must_use]
+    pub const fn new(key: &'sk IndexKey<K>, primary_key_bytes: &'pk [u8]) -> Self {
+        Self {
             index_name: key.index_name,
             index_kind: key.index_kind,
             secondary_key: key.secondary_key,
             primary_key_bytes,
-        })
+        }
+    }
+}
+
+/// An owned, serialized secondary index key of a record, used to maintain the record's index
+/// entries when it's deleted.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IndexKeyBytes {
+    /// Name of the secondary index table. For example: `"creatures_by_habitat"`.
+    pub index_name: &'static str,
+
+    /// Indicates whether the index is `Unique` or `NonUnique`.
+    pub index_kind: IndexKind,
+
+    /// The secondary key, in serialized form.
+    pub secondary_key_bytes: Vec<u8>,
+
+    /// The record's projection, if the index is a covering index.
+    pub covering: Option<CoveringBytes>,
+}
+
+impl IndexKeyBytes {
+    /// Returns `true` if both keys refer to the same index entry, regardless of any projection.
+    #[must_use]
+    pub fn is_same_entry(&self, other: &Self) -> bool {
+        self.index_name == other.index_name && self.secondary_key_bytes == other.secondary_key_bytes
+    }
+
+    /// Returns the serialized secondary index keys of every index the record participates in.
+    ///
+    /// A multi-valued field contributes one key per element. Duplicate keys, for example a tag
+    /// that's listed twice, are returned once.
+    ///
+    /// # Errors
+    ///
+    /// * Returns an error if any secondary key could not be serialized.
+    pub fn of<'v, V: Indexable<'v>>(value: &'v V) -> Result<Vec<Self>, Error> {
+        let mut index_keys = value
+            .indexes()?
+            .into_iter()
+            .map(|index| Ok(Self {
+                index_name: IndexLookup::index_name(&index),
+                index_kind: *IndexLookup::index_kind(&index),
+                secondary_key_bytes: index.index_key_bytes()?,
+                covering: index.covering()?,
+            }))
+            .collect::<Result<Vec<Self>, Error>>()?;
+
+        index_keys.sort_by(|a, b| (a.index_name, &a.secondary_key_bytes)
+            .cmp(&(b.index_name, &b.secondary_key_bytes)));
+        index_keys.dedup_by(|a, b| a.is_same_entry(b));
+
+        Ok(index_keys)
     }
 }
 
@@ -787,7 +1111,8 @@ use serde::{Deserialize, Serialize};
 
 
 
-unsafe impl crate::layers::serializers::SafeForRmpSerde for Creature {}
+#[cfg(feature = "serde-safety")]
+unsafe impl crate::layers::serializers::SafeForSerde for Creature {}
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Creature {
@@ -803,7 +1128,7 @@ impl HasTable for Creature {
 }
 
 impl HasPrimaryKey<'_, u64> for Creature {
-    fn primary_key(&self) -> PrimaryKey<u64> {
+    fn primary_key(&self) -> PrimaryKey<'_, u64> {
         PrimaryKey::new(&self.id)
     }
 }
@@ -824,7 +1149,7 @@ impl IndexLookup for Habitat {
     }
 
     fn index_key_bytes(&self) -> Result<Vec<u8>, crate::Error> {
-        Ok(Codec::<String>::serialize(&self.0)?)
+        <String as Codec<String>>::serialize(&self.0)
     }
 }
 
@@ -845,6 +1170,6 @@ impl IndexLookup for Species {
     }
 
     fn index_key_bytes(&self) -> Result<Vec<u8>, crate::Error> {
-        Ok(Codec::<String>::serialize(&self.0)?)
+        <String as Codec<String>>::serialize(&self.0)
     }
 }
\ No newline at end of file
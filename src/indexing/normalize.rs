@@ -0,0 +1,178 @@
+//! Normalization of string secondary keys, so that keys which differ only in case or Unicode
+//! representation resolve to the same index entry.
+
+use crate::{Codec, Error};
+use std::borrow::Cow;
+#[cfg(feature = "unicode-normalization")]
+use unicode_normalization::UnicodeNormalization;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// How a string secondary key is normalized before it's encoded.
+///
+/// Each index selects its mode with [`IndexLookup::key_normalization`]. Normalization must be
+/// applied symmetrically: the same mode is used when a record's index entries are written, and
+/// when a look-up key is encoded for a query. Both go through [`IndexLookup::index_key_bytes`], so
+/// encoding there with the index's mode covers both:
+///
+/// ```rust,ignore
+/// impl IndexLookup for Habitat {
+///     // ...
+///     fn key_normalization(&self) -> KeyNormalization {
+///         KeyNormalization::Lowercase
+///     }
+///
+///     fn index_key_bytes(&self) -> Result<Vec<u8>, crate::Error> {
+///         self.key_normalization().encode(&self.0)
+///     }
+/// }
+///
+/// // `Habitat("desert")` and `Habitat("Desert")` now find the same records.
+/// ```
+///
+/// Changing an index's normalization changes its keys, so the index must be rebuilt afterwards.
+///
+/// # Unicode Normalization Forms
+///
+/// The NFC and NFKC modes require the `unicode-normalization` feature. NFC composes characters
+/// that have more than one encoding, so that `"é"` typed as one code point or as `"e"` followed by
+/// a combining accent are the same key. NFKC also folds compatibility characters, such as the
+/// `"ﬁ"` ligature or full-width letters, into their plain forms.
+///
+/// [`IndexLookup::index_key_bytes`]: crate::indexing::IndexLookup::index_key_bytes
+/// [`IndexLookup::key_normalization`]: crate::indexing::IndexLookup::key_normalization
+#[derive(Clone, Copy, Debug, Default)]
+pub enum KeyNormalization {
+    /// Keys are encoded as-is. Matching is case-sensitive.
+    #[default]
+    Exact,
+
+    /// Keys are converted to Unicode lowercase using [`str::to_lowercase`].
+    Lowercase,
+
+    /// Keys are converted to Unicode Normalization Form C (canonical composition).
+    #[cfg(feature = "unicode-normalization")]
+    Nfc,
+
+    /// Keys are converted to Unicode Normalization Form KC (compatibility composition).
+    #[cfg(feature = "unicode-normalization")]
+    Nfkc,
+
+    /// Keys are converted to NFC, and then to Unicode lowercase.
+    #[cfg(feature = "unicode-normalization")]
+    NfcLowercase,
+
+    /// Keys are converted to NFKC, and then to Unicode lowercase.
+    #[cfg(feature = "unicode-normalization")]
+    NfkcLowercase,
+
+    /// Keys are normalized by a custom function. For example: trimming whitespace, or removing
+    /// accents.
+    Custom(fn(&str) -> String),
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl KeyNormalization {
+    /// Returns the normalized form of a key. Doesn't allocate for [`KeyNormalization::Exact`].
+    #[must_use]
+    pub fn apply(self, key: &str) -> Cow<'_, str> {
+        match self {
+            Self::Exact => Cow::Borrowed(key),
+            Self::Lowercase => Cow::Owned(key.to_lowercase()),
+            #[cfg(feature = "unicode-normalization")]
+            Self::Nfc => Cow::Owned(key.nfc().collect()),
+            #[cfg(feature = "unicode-normalization")]
+            Self::Nfkc => Cow::Owned(key.nfkc().collect()),
+            #[cfg(feature = "unicode-normalization")]
+            Self::NfcLowercase => Cow::Owned(key.nfc().collect::<String>().to_lowercase()),
+            #[cfg(feature = "unicode-normalization")]
+            Self::NfkcLowercase => Cow::Owned(key.nfkc().collect::<String>().to_lowercase()),
+            Self::Custom(normalize) => Cow::Owned(normalize(key)),
+        }
+    }
+
+    /// Normalizes a key and serializes it as a `String`, for use as a secondary key.
+    ///
+    /// # Errors
+    ///
+    /// * Returns an error if the key cannot be serialized by the active `Codec`.
+    pub fn encode(self, key: &str) -> Result<Vec<u8>, Error> {
+        <String as Codec<String>>::serialize(&self.apply(key).into_owned())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexing::{IndexKind, IndexLookup};
+    use crate::typed::test_records::Animal;
+
+    /// A case-insensitive look-up, to check that an index's own mode is used for its keys.
+    struct Keeper(&'static str);
+
+    impl IndexLookup for Keeper {
+        type Record = Animal;
+
+        fn index_name(&self) -> &'static str {
+            "animals_by_keeper"
+        }
+
+        fn index_kind(&self) -> &IndexKind {
+            &IndexKind::NonUnique
+        }
+
+        fn key_normalization(&self) -> KeyNormalization {
+            KeyNormalization::Lowercase
+        }
+
+        fn index_key_bytes(&self) -> Result<Vec<u8>, Error> {
+            self.key_normalization().encode(self.0)
+        }
+    }
+
+    #[test]
+    fn lowercase_folds_unicode_case() {
+        assert_eq!(KeyNormalization::Lowercase.apply("Tide Pool"), "tide pool");
+        assert_eq!(KeyNormalization::Lowercase.apply("ÉTANG"), "étang");
+        assert!(matches!(KeyNormalization::Exact.apply("Desert"), Cow::Borrowed("Desert")));
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn normalization_forms_unify_representations() {
+        let composed = "\u{e9}tang";
+        let decomposed = "e\u{301}tang";
+        assert_eq!(KeyNormalization::Nfc.apply(decomposed), composed);
+        assert_eq!(KeyNormalization::Nfc.apply("\u{fb01}sh"), "\u{fb01}sh");
+        assert_eq!(KeyNormalization::Nfkc.apply("\u{fb01}sh"), "fish");
+        assert_eq!(KeyNormalization::NfcLowercase.apply("E\u{301}TANG"), composed);
+        assert_eq!(KeyNormalization::NfkcLowercase.apply("\u{ff26}ISH"), "fish");
+        assert_eq!(
+            KeyNormalization::Nfc.encode(decomposed).unwrap(),
+            KeyNormalization::Nfc.encode(composed).unwrap(),
+        );
+    }
+
+    #[test]
+    fn lookups_use_their_index_mode() {
+        assert_eq!(
+            Keeper("Ada").index_key_bytes().unwrap(),
+            Keeper("ADA").index_key_bytes().unwrap(),
+        );
+        let boxed: Box<crate::querying::DynLookup<Animal>> = Box::new(Keeper("Ada"));
+        assert!(matches!(boxed.key_normalization(), KeyNormalization::Lowercase));
+    }
+
+    #[test]
+    fn custom_applies_function() {
+        let trimmed = KeyNormalization::Custom(|key| key.trim().to_lowercase());
+        assert_eq!(trimmed.apply("  Desert "), "desert");
+    }
+}
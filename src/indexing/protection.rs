@@ -0,0 +1,248 @@
+//! Encryption of secondary index tables.
+//!
+//! Encrypting a record's value doesn't protect its index entries: a secondary index is keyed by the
+//! indexed field's serialized plain text, and its values are the primary keys of the records that
+//! share it. An [`IndexProtection`] closes this gap for the indexes it's told about:
+//!
+//! * Secondary keys are replaced with [`BlindIndex`] search tokens, so the plain text never appears
+//!   in the index table while exact-match look-ups still work.
+//!
+//! * `KeySet` values (and the primary key stored by a unique index) are encrypted with the active
+//!   encryptor. The index table's name and the row's key are authenticated as associated data, so
+//!   an encrypted key set can't be moved to another row without being detected.
+//!
+//! [`BlindIndex`]: crate::layers::encryptors::BlindIndex
+
+use crate::indexing::{Index, IndexKeyBytes};
+use crate::layers::core::{Bytes, Direction};
+use crate::layers::encryptors::{
+    ActiveEncryptor,
+    AssociatedData,
+    BlindIndex,
+    Encryptable,
+    Encryptor,
+    KEY_SIZE,
+    KeyBytes,
+    KeyId,
+};
+use crate::Error;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use zeroize::Zeroize;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Routes the secondary keys and key sets of selected indexes through the encryption layer.
+///
+/// Attach it to a transaction with `with_index_protection`. Every read and write of a protected
+/// index then goes through it, so queries are written exactly as they would be for a plain text
+/// index:
+///
+/// ```rust,ignore
+/// let protection = Arc::new(
+///     IndexProtection::new(&key)
+///         .index::<Email>()
+///         .index::<Habitat>()
+/// );
+///
+/// let txn = db.begin_write()?.with_index_protection(protection.clone());
+/// txn.insert(&user.id, &user)?;
+/// txn.commit()?;
+///
+/// let txn = db.begin_read()?.with_index_protection(protection);
+/// let jane = txn.query::<UserId, User>(Email("jane@example.com".into()))?;
+/// ```
+///
+/// # Notes
+///
+/// * Look-ups of a protected index must use the plain text lookup. Wrapping it in a `BlindLookup`
+///   as well would hash the search term twice and never match.
+///
+/// * Protected indexes only support exact-match look-ups. Range queries, prefix scans, string
+///   matching, and ordered iteration see search tokens rather than secondary keys.
+///
+/// * Covering index projections and the primary keys used as record table keys aren't encrypted
+///   by this option.
+///
+/// * Every transaction that writes a protected index must carry the same `IndexProtection`, and
+///   an index must be rebuilt after it's added to or removed from the protection.
+#[derive(Clone)]
+pub struct IndexProtection {
+    /// Key that key sets are encrypted with.
+    key: [u8; KEY_SIZE],
+
+    /// ID of the key, which is recorded alongside every encrypted key set.
+    key_id: KeyId,
+
+    /// Blind index of each protected index, by index table name.
+    indexes: BTreeMap<&'static str, BlindIndex>,
+}
+
+/// Marks key sets, which are always encrypted and decrypted.
+struct IndexedKeys;
+
+impl Encryptable for IndexedKeys {
+    const DIRECTION: Direction = Direction::Both;
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl IndexProtection {
+    /// Instantiates an `IndexProtection` that doesn't protect any index yet.
+    #[must_use]
+    pub fn new(key: &KeyBytes<'_>) -> Self {
+        Self { key: **key, key_id: key.id(), indexes: BTreeMap::new() }
+    }
+
+    /// Protects the index of the given secondary key type.
+    #[must_use]
+    pub fn index<I: Index>(self) -> Self {
+        self.index_named(I::index_name())
+    }
+
+    /// Protects the index with the given table name.
+    #[must_use]
+    pub fn index_named(mut self, index_name: &'static str) -> Self {
+        let blind_index = BlindIndex::new(&self.key_bytes(), index_name);
+        self.indexes.insert(index_name, blind_index);
+        self
+    }
+
+    /// Returns `true` if the named index is protected.
+    #[must_use]
+    pub fn is_protected(&self, index_name: &str) -> bool {
+        self.indexes.contains_key(index_name)
+    }
+
+    /// Returns the key that a serialized secondary key is stored under: its search token if the
+    /// index is protected, or the secondary key itself if it isn't.
+    #[must_use]
+    pub fn secondary_key(&self, index_name: &str, secondary_key_bytes: Vec<u8>) -> Vec<u8> {
+        match self.indexes.get(index_name) {
+            Some(blind_index) => blind_index.token(&secondary_key_bytes).to_vec(),
+            None => secondary_key_bytes,
+        }
+    }
+
+    /// Replaces the secondary keys of protected indexes with their search tokens.
+    #[must_use]
+    pub fn protect_index_keys(&self, index_keys: Vec<IndexKeyBytes>) -> Vec<IndexKeyBytes> {
+        index_keys
+            .into_iter()
+            .map(|index_key| IndexKeyBytes {
+                secondary_key_bytes: self.secondary_key(
+                    index_key.index_name,
+                    index_key.secondary_key_bytes
+                ),
+                ..index_key
+            })
+            .collect()
+    }
+
+    /// Encrypts a row's value, for example a serialized `KeySet`, if the index is protected.
+    ///
+    /// # Errors
+    ///
+    /// Consult the documentation of the encryptor backend you are using for more detail on
+    /// encryption and potential limitations.
+    pub fn seal(
+        &self,
+        index_name: &str,
+        row_key: &[u8],
+        value_bytes: Vec<u8>,
+    ) -> Result<Vec<u8>, Error> {
+        if !self.is_protected(index_name) {
+            return Ok(value_bytes);
+        }
+
+        let cipher_text = ActiveEncryptor::<IndexedKeys>::encrypt(
+            Bytes::from_vec(value_bytes),
+            self.key_bytes(),
+            &AssociatedData::new(index_name, row_key),
+            None,
+        ).map_err(crate::layers::encryptors::Error::from)?;
+
+        Ok(cipher_text.into_bytes().into_owned())
+    }
+
+    /// Decrypts a row's value that was encrypted with [`IndexProtection::seal`]. The value is
+    /// returned as-is if the index isn't protected.
+    ///
+    /// # Errors
+    ///
+    /// This method may fail for several reasons, including:
+    ///
+    /// * The value was encrypted with another key, or for another row, or
+    /// * Input bytes are corrupted or malformed.
+    pub fn open<'v>(
+        &self,
+        index_name: &str,
+        row_key: &[u8],
+        value_bytes: &'v [u8],
+    ) -> Result<Cow<'v, [u8]>, Error> {
+        if !self.is_protected(index_name) {
+            return Ok(Cow::Borrowed(value_bytes));
+        }
+
+        let plain_text = ActiveEncryptor::<IndexedKeys>::decrypt(
+            Bytes::from_slice(value_bytes),
+            self.key_bytes(),
+            &AssociatedData::new(index_name, row_key),
+        ).map_err(crate::layers::encryptors::Error::from)?;
+
+        Ok(Cow::Owned(plain_text.into_bytes().into_owned()))
+    }
+
+    /// Returns the key, carrying its ID.
+    fn key_bytes(&self) -> KeyBytes<'_> {
+        KeyBytes::from_array(&self.key).with_id(self.key_id)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl Drop for IndexProtection {
+    /// Wipes the key from memory.
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+impl std::fmt::Debug for IndexProtection {
+    /// Formats the `IndexProtection` without exposing the key.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IndexProtection")
+            .field("key_id", &self.key_id)
+            .field("indexes", &self.indexes.keys().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_protected_indexes_are_transformed() {
+        let key = KeyBytes::from_array(&[0x42; KEY_SIZE]);
+        let protection = IndexProtection::new(&key).index_named("users_by_email");
+
+        let token = protection.secondary_key("users_by_email", b"jane@example.com".to_vec());
+        assert_ne!(token, b"jane@example.com");
+        assert_eq!(protection.secondary_key("users_by_age", vec![42]), vec![42]);
+
+        let sealed = protection.seal("users_by_email", &token, vec![1, 2, 3]).unwrap();
+        assert_ne!(sealed, vec![1, 2, 3]);
+        assert_eq!(&*protection.open("users_by_email", &token, &sealed).unwrap(), &[1, 2, 3]);
+        assert!(protection.open("users_by_email", b"another row", &sealed).is_err());
+        assert_eq!(&*protection.open("users_by_age", &[42], &[1, 2, 3]).unwrap(), &[1, 2, 3]);
+    }
+}
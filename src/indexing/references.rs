@@ -0,0 +1,239 @@
+//! Foreign-key metadata: declares that a field of a record must exist as a primary key in another
+//! table, and what should happen to the record when that primary key is deleted.
+
+use crate::indexing::{HasTable, Indexable, IndexKeyBytes};
+use crate::layers::encryptors::{Nonce, NonceStrategy};
+use crate::typed::record_layers::RecordContext;
+use crate::{Codec, Error};
+
+// -------------------------------------------------------------------------------------------------
+
+/// Decodes a dependent record, given its table's context, primary key, and stored bytes, and
+/// returns its references.
+type ReferencesOf = fn(&RecordContext, &[u8], &[u8]) -> Result<Vec<Reference>, Error>;
+
+/// Decodes a dependent record, clears the named reference, and encodes the record again with the
+/// given nonce.
+type ClearReference = fn(
+    &RecordContext,
+    &[u8],
+    &[u8],
+    &'static str,
+    Option<Nonce<'static>>,
+) -> Result<Vec<u8>, Error>;
+
+/// Decodes a dependent record and returns its secondary index keys.
+type IndexKeysOf = fn(&RecordContext, &[u8], &[u8]) -> Result<Vec<IndexKeyBytes>, Error>;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// What happens to a referring record when the record it refers to is deleted.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum OnDelete {
+    /// The delete fails with [`Error::ReferencedByDependent`] while any record still refers to the
+    /// deleted record. This is the default.
+    #[default]
+    Restrict,
+
+    /// Referring records are deleted along with the record they refer to.
+    Cascade,
+
+    /// Referring records are kept, but their reference is cleared using
+    /// [`References::clear_reference`]. This is similar to SQL's `ON DELETE SET NULL`.
+    Tombstone,
+}
+
+/// A single foreign key held by a record.
+///
+/// For example, an `Order` might hold a `customer_id` that must exist as a primary key in the
+/// `customers` table.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Reference {
+    /// Name of the reference, typically the field name. For example: `"customer_id"`.
+    pub name: &'static str,
+
+    /// Name of the primary table that the reference points to. For example: `"customers"`.
+    pub parent_table: &'static str,
+
+    /// The referenced primary key, in serialized form. `None` if the reference is unset, in which
+    /// case it isn't checked.
+    pub key_bytes: Option<Vec<u8>>,
+
+    /// What happens to the referring record when the referenced record is deleted.
+    pub on_delete: OnDelete,
+}
+
+impl Reference {
+    /// Instantiates a new reference to the `PK` primary key of a `P` record.
+    ///
+    /// # Errors
+    ///
+    /// * Returns an error if the primary key could not be serialized.
+    pub fn to<P, PK>(
+        name: &'static str,
+        primary_key: Option<&PK>,
+        on_delete: OnDelete
+    ) -> Result<Self, Error>
+    where
+        P: HasTable,
+        PK: Codec<PK>
+    {
+        Ok(Self {
+            name,
+            parent_table: P::table_name(),
+            key_bytes: primary_key.map(PK::serialize).transpose()?,
+            on_delete,
+        })
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Declares the foreign keys held by a record type.
+///
+/// References are checked on every insert through the write transaction: if a referenced primary
+/// key doesn't exist, the insert fails with [`Error::ForeignKeyViolation`]. When a referenced record
+/// is deleted, each referring record is handled according to its reference's [`OnDelete`] policy.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// impl References for Order {
+///     fn references(&self) -> Result<Vec<Reference>, Error> {
+///         Ok(vec![
+///             Reference::to::<Customer, _>("customer_id", Some(&self.customer_id), OnDelete::Restrict)?,
+///             Reference::to::<Coupon, _>("coupon_id", self.coupon_id.as_ref(), OnDelete::Tombstone)?,
+///         ])
+///     }
+///
+///     fn clear_reference(&mut self, name: &'static str) {
+///         if name == "coupon_id" { self.coupon_id = None; }
+///     }
+/// }
+/// ```
+pub trait References: HasTable {
+    /// Returns the foreign keys held by this record.
+    ///
+    /// # Errors
+    ///
+    /// * Returns an error if a referenced primary key could not be serialized.
+    fn references(&self) -> Result<Vec<Reference>, Error>;
+
+    /// Clears the named reference. Called for references with an [`OnDelete::Tombstone`] policy
+    /// when the referenced record is deleted.
+    ///
+    /// The default implementation does nothing.
+    fn clear_reference(&mut self, _name: &'static str) {}
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A type-erased description of a table whose records may refer to another table's records.
+///
+/// Referenced record types list their dependents using [`HasDependents`], so that deletes can find
+/// and handle the records that refer to them.
+///
+/// Its callbacks are handed the dependent table's [`RecordContext`] and each record's primary key,
+/// so that records with [`RecordLayers`](crate::typed::record_layers::RecordLayers) can be
+/// decoded and re-encoded.
+#[derive(Clone, Copy)]
+pub struct Dependent {
+    /// Name of the dependent primary table. For example: `"orders"`.
+    pub table_name: &'static str,
+
+    /// Decodes a dependent record and returns its references.
+    pub references: ReferencesOf,
+
+    /// Decodes a dependent record, clears the named reference, and re-encodes the record with the
+    /// given nonce.
+    pub clear_reference: ClearReference,
+
+    /// How nonces are chosen when the dependent record type's layers encrypt it, so that a
+    /// counter-based nonce can be drawn before a record is re-encoded.
+    pub nonce_strategy: NonceStrategy,
+
+    /// Returns the dependent record type's own dependents, so that cascading deletes can follow
+    /// chains of references. For example: customers → orders → order lines.
+    pub dependents: fn() -> Vec<Self>,
+
+    /// Decodes a dependent record and returns its secondary index keys, so that cascading deletes
+    /// can remove its index entries. Returns no keys unless set using [`Dependent::with_indexes`].
+    pub index_keys: IndexKeysOf,
+
+    /// Name of the dependent record type's reverse index table, if it has one. Cascading deletes
+    /// use it to remove the dependent's index entries without decoding the record.
+    pub reverse_index_name: Option<&'static str>,
+
+    /// Name of the dependent record type's history table, if it has one. Cascading deletes and
+    /// cleared references record the dependent's previous version in it.
+    pub history_table_name: Option<&'static str>,
+}
+
+impl Dependent {
+    /// Describes the dependent record type `D`, which has no dependents of its own.
+    #[must_use]
+    pub fn of<D: References + Codec<D>>() -> Self {
+        Self {
+            table_name: D::table_name(),
+            references: |context, primary_key, value_bytes| {
+                context.open::<D>(primary_key, value_bytes)?.references()
+            },
+            clear_reference: |context, primary_key, value_bytes, name, nonce| {
+                let mut record: D = context.open(primary_key, value_bytes)?;
+                record.clear_reference(name);
+                context.seal(primary_key, &record, nonce)
+            },
+            nonce_strategy: D::layers()
+                .map_or(NonceStrategy::Random, |layers| layers.nonce_strategy()),
+            dependents: Vec::new,
+            index_keys: |_context, _primary_key, _value_bytes| Ok(Vec::new()),
+            reverse_index_name: D::reverse_index_name(),
+            history_table_name: D::history_table_name(),
+        }
+    }
+
+    /// Describes the dependent record type `D`, which is itself referred to by other record types.
+    #[must_use]
+    pub fn nested<D: References + HasDependents + Codec<D>>() -> Self {
+        Self { dependents: D::dependents, ..Self::of::<D>() }
+    }
+
+    /// Removes the dependent record's secondary index entries when it's deleted by a cascade.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// Dependent::of::<Order>().with_indexes::<Order>()
+    /// ```
+    #[must_use]
+    pub fn with_indexes<D: for<'i> Indexable<'i> + HasTable + Codec<D>>(self) -> Self {
+        Self {
+            index_keys: |context, primary_key, value_bytes| {
+                IndexKeyBytes::of(&context.open::<D>(primary_key, value_bytes)?)
+            },
+            ..self
+        }
+    }
+}
+
+impl std::fmt::Debug for Dependent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Dependent").field("table_name", &self.table_name).finish_non_exhaustive()
+    }
+}
+
+/// Lists the record types that may refer to this record type.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// impl HasDependents for Customer {
+///     fn dependents() -> Vec<Dependent> {
+///         vec![Dependent::of::<Order>(), Dependent::of::<Invoice>()]
+///     }
+/// }
+/// ```
+pub trait HasDependents: HasTable {
+    /// Returns the record types that may refer to this record type.
+    fn dependents() -> Vec<Dependent>;
+}
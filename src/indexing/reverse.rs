@@ -0,0 +1,172 @@
+//! Reverse index rows, which map a record's primary key to every secondary index entry it appears
+//! in, so that those entries can be removed without reading the record.
+
+use crate::Error;
+use crate::indexing::{IndexKeyBytes, IndexKind};
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A secondary index entry that a record appears in, decoded from a reverse index row.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReverseEntry {
+    /// Name of the secondary index table. For example: `"creatures_by_habitat"`.
+    pub index_name: String,
+
+    /// Indicates whether the index is `Unique` or `NonUnique`.
+    pub index_kind: IndexKind,
+
+    /// The secondary key, in serialized form.
+    pub secondary_key_bytes: Vec<u8>,
+
+    /// Name of the covering table that holds the record's projection, if the index is a covering
+    /// index.
+    pub covering_table_name: Option<String>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl ReverseEntry {
+    /// Encodes a record's secondary index keys as a reverse index row.
+    ///
+    /// Each entry is stored as a `u16` index name length, the index name, a `u8` index kind, a
+    /// `u32` secondary key length, the secondary key, a `u16` covering table name length, and the
+    /// covering table name. Lengths are little-endian, and a covering table name length of zero
+    /// means the index isn't a covering index.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::BufferTooLargeForTarget`] if an index name or secondary key is too long
+    ///   for its length prefix.
+    pub fn encode_row(index_keys: &[IndexKeyBytes]) -> Result<Vec<u8>, Error> {
+        let mut row = Vec::new();
+        for index_key in index_keys {
+            let index_name_len = name_len(index_key.index_name)?;
+            let covering_table_name = index_key
+                .covering
+                .as_ref()
+                .map_or("", |covering| covering.table_name);
+            let key_len = u32::try_from(index_key.secondary_key_bytes.len())
+                .map_err(|_| Error::BufferTooLargeForTarget {
+                    buffer_len: index_key.secondary_key_bytes.len(),
+                    target_len: u32::MAX as usize,
+                })?;
+
+            row.extend_from_slice(&index_name_len.to_le_bytes());
+            row.extend_from_slice(index_key.index_name.as_bytes());
+            row.push(index_key.index_kind as u8);
+            row.extend_from_slice(&key_len.to_le_bytes());
+            row.extend_from_slice(&index_key.secondary_key_bytes);
+            row.extend_from_slice(&name_len(covering_table_name)?.to_le_bytes());
+            row.extend_from_slice(covering_table_name.as_bytes());
+        }
+        Ok(row)
+    }
+
+    /// Decodes a reverse index row into its entries.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Corrupted`] if the row is truncated, or holds an unknown index kind or a
+    ///   non-UTF-8 index name.
+    pub fn decode_row(mut row: &[u8]) -> Result<Vec<Self>, Error> {
+        let mut entries = Vec::new();
+        while !row.is_empty() {
+            let index_name = take_name(&mut row)?;
+            let index_kind = match take_array::<1>(&mut row)? {
+                [0] => IndexKind::Unique,
+                [1] => IndexKind::NonUnique,
+                [kind] => return Err(corrupted(&format!("unknown index kind {kind}"))),
+            };
+            let key_len = u32::from_le_bytes(take_array(&mut row)?) as usize;
+            let secondary_key_bytes = take(&mut row, key_len)?.to_vec();
+            let covering_table_name = Some(take_name(&mut row)?).filter(|name| !name.is_empty());
+
+            entries.push(Self { index_name, index_kind, secondary_key_bytes, covering_table_name });
+        }
+        Ok(entries)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Returns a table name's length, as stored in a row.
+fn name_len(name: &str) -> Result<u16, Error> {
+    u16::try_from(name.len()).map_err(|_| Error::BufferTooLargeForTarget {
+        buffer_len: name.len(),
+        target_len: u16::MAX as usize,
+    })
+}
+
+/// Splits a length-prefixed table name off the front of a row.
+fn take_name(row: &mut &[u8]) -> Result<String, Error> {
+    let len = u16::from_le_bytes(take_array(row)?) as usize;
+    std::str::from_utf8(take(row, len)?)
+        .map(str::to_string)
+        .map_err(|error| corrupted(&error.to_string()))
+}
+
+/// Splits `len` bytes off the front of a row.
+fn take<'r>(row: &mut &'r [u8], len: usize) -> Result<&'r [u8], Error> {
+    if row.len() < len {
+        return Err(corrupted("row is truncated"));
+    }
+    let (head, tail) = row.split_at(len);
+    *row = tail;
+    Ok(head)
+}
+
+/// Splits a fixed-size array off the front of a row.
+fn take_array<const N: usize>(row: &mut &[u8]) -> Result<[u8; N], Error> {
+    take(row, N)?.try_into().map_err(|_| corrupted("row is truncated"))
+}
+
+/// Describes a malformed reverse index row.
+fn corrupted(reason: &str) -> Error {
+    Error::Corrupted { message: format!("reverse index {reason}") }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexing::CoveringBytes;
+
+    #[test]
+    fn round_trips_rows() {
+        let index_keys = vec![
+            IndexKeyBytes {
+                index_name: "creatures_by_habitat",
+                index_kind: IndexKind::NonUnique,
+                secondary_key_bytes: b"Desert".to_vec(),
+                covering: Some(CoveringBytes {
+                    table_name: "creatures_by_habitat_covering",
+                    projection_bytes: Vec::new(),
+                }),
+            },
+            IndexKeyBytes {
+                index_name: "creatures_by_species",
+                index_kind: IndexKind::Unique,
+                secondary_key_bytes: Vec::new(),
+                covering: None,
+            },
+        ];
+
+        let row = ReverseEntry::encode_row(&index_keys).unwrap();
+        let entries = ReverseEntry::decode_row(&row).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].index_name, "creatures_by_habitat");
+        assert_eq!(entries[0].secondary_key_bytes, b"Desert");
+        assert_eq!(entries[0].covering_table_name.as_deref(), Some("creatures_by_habitat_covering"));
+        assert!(matches!(entries[1].index_kind, IndexKind::Unique));
+        assert_eq!(entries[1].covering_table_name, None);
+
+        assert!(ReverseEntry::decode_row(&row[..row.len() - 1]).is_err());
+    }
+}
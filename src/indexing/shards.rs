@@ -0,0 +1,202 @@
+//! Sharding of very large key sets across multiple rows.
+//!
+//! A secondary key that maps to millions of primary keys would otherwise be stored as one enormous
+//! `redb` value, which must be read and rewritten in full whenever a single primary key is added
+//! or removed. Instead, a key set that outgrows [`SHARD_CAPACITY`] is split into shards:
+//!
+//! ```text
+//! creatures_by_habitat            creatures_by_habitat#shards
+//! ┌──────────┬────────────┐       ┌────────────┬────────────┐
+//! │ "Desert" │ shard #0   │       │ "Desert"#1 │ shard #1   │
+//! │ "Tundra" │ shard #0   │       │ "Desert"#2 │ shard #2   │
+//! └──────────┴────────────┘       └────────────┴────────────┘
+//! ```
+//!
+//! Shard #0 stays in the index table, so entries that were never sharded are stored exactly as
+//! before. Every shard but the last is kept full, so a reader only needs to look for more shards
+//! when shard #0 holds [`SHARD_CAPACITY`] primary keys. Inserting or removing a primary key
+//! rewrites at most two shards.
+
+use crate::indexing::{KeySet, ReadableKeySet};
+use crate::Error;
+use redb::ReadableTable;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The most primary keys stored in a single shard of a key set.
+pub const SHARD_CAPACITY: usize = 16_384;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// An iterator over every primary key of a secondary key, across all of its shards.
+///
+/// Shards are read one at a time, as the cursor reaches them.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let index_table = txn.open_raw_index_table("creatures_by_habitat")?.unwrap();
+/// let shard_table = txn.open_raw_index_table(&shard_table_name("creatures_by_habitat"))?;
+/// let desert = Habitat("Desert".into()).index_key_bytes()?;
+///
+/// let first_shard = index_table.get(&*desert)?.unwrap();
+/// for primary_key in KeySetCursor::new(first_shard.value(), shard_table.as_ref(), &desert)? {
+///     let id = u64::deserialize(&primary_key?)?;
+/// }
+/// ```
+pub struct KeySetCursor<'t> {
+    /// Overflow shards that haven't been read yet. `None` if the key set isn't sharded.
+    shards: Option<redb::Range<'t, &'static [u8], &'static [u8]>>,
+
+    /// Primary keys of the shard being read.
+    current: <KeySet as IntoIterator>::IntoIter,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<'t> KeySetCursor<'t> {
+    /// Starts iterating over a key set, given its first shard from the index table and the index's
+    /// shard table, if it has one.
+    ///
+    /// # Errors
+    ///
+    /// * Deserialization errors when instantiating the first shard's `KeySet`.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn new<T>(
+        first_shard_bytes: &[u8],
+        shard_table: Option<&'t T>,
+        secondary_key_bytes: &[u8],
+    ) -> Result<Self, Error>
+    where
+        T: ReadableTable<&'static [u8], &'static [u8]>,
+    {
+        let first_shard = KeySet::from_bytes(first_shard_bytes)?;
+        let shards = match shard_table {
+            Some(shard_table) if is_full_shard(first_shard.len()) => Some(shard_table.range::<&[u8]>(
+                &*shard_key(secondary_key_bytes, 1)?..=&*shard_key(secondary_key_bytes, u32::MAX)?
+            )?),
+            _ => None,
+        };
+
+        Ok(Self { shards, current: first_shard.into_iter() })
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl Iterator for KeySetCursor<'_> {
+    type Item = Result<Vec<u8>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(primary_key) = self.current.next() {
+                return Some(Ok(primary_key));
+            }
+
+            let shard = self.shards.as_mut()?.next()?;
+            match shard.map_err(Error::from).and_then(|(_, bytes)| KeySet::from_bytes(bytes.value())) {
+                Ok(shard) => self.current = shard.into_iter(),
+                Err(error) => {
+                    self.shards = None;
+                    return Some(Err(error));
+                }
+            }
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Returns the name of the table that holds an index's overflow shards. For example:
+/// `"creatures_by_habitat#shards"`.
+#[must_use]
+pub fn shard_table_name(index_name: &str) -> String {
+    format!("{index_name}#shards")
+}
+
+/// Returns the shard table key of one of a secondary key's shards.
+///
+/// The key is the secondary key's length as a little-endian `u32`, the secondary key, and the shard
+/// number as a big-endian `u32`, so that a secondary key's shards are stored next to each other, in
+/// order.
+///
+/// # Errors
+///
+/// * Returns [`Error::BufferTooLargeForTarget`] if the secondary key is longer than `u32::MAX`.
+pub fn shard_key(secondary_key_bytes: &[u8], shard: u32) -> Result<Vec<u8>, Error> {
+    let mut key = crate::indexing::covering_prefix(secondary_key_bytes)?;
+    key.extend_from_slice(&shard.to_be_bytes());
+    Ok(key)
+}
+
+/// Returns `true` if a shard of the given size is full, meaning that the key set may continue in
+/// another shard.
+#[must_use]
+pub const fn is_full_shard(shard_len: usize) -> bool {
+    shard_len >= SHARD_CAPACITY
+}
+
+/// Reads the overflow shards of a key set whose first shard is full, merged into one `KeySet`.
+///
+/// # Errors
+///
+/// * Deserialization errors when instantiating a shard's `KeySet`.
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+pub fn read_overflow(
+    shard_table: &impl ReadableTable<&'static [u8], &'static [u8]>,
+    secondary_key_bytes: &[u8],
+) -> Result<KeySet, Error> {
+    read_overflow_with(shard_table, secondary_key_bytes, |_, bytes| KeySet::from_bytes(bytes))
+}
+
+/// Reads the overflow shards of a key set whose first shard is full, merged into one `KeySet`.
+///
+/// Each shard is decoded by the `decode` closure, which is given the shard's key and bytes. This
+/// lets encrypted shards be decrypted before they're deserialized.
+///
+/// # Errors
+///
+/// * Errors returned by the `decode` closure.
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+pub fn read_overflow_with(
+    shard_table: &impl ReadableTable<&'static [u8], &'static [u8]>,
+    secondary_key_bytes: &[u8],
+    decode: impl Fn(&[u8], &[u8]) -> Result<KeySet, Error>,
+) -> Result<KeySet, Error> {
+    shard_table
+        .range::<&[u8]>(&*shard_key(secondary_key_bytes, 1)?..=&*shard_key(secondary_key_bytes, u32::MAX)?)?
+        .map(|shard| {
+            let (shard_key, shard_bytes) = shard?;
+            decode(shard_key.value(), shard_bytes.value())
+        })
+        .collect()
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shard_keys_sort_by_secondary_key_then_shard() {
+        let mut keys = [shard_key(b"Desert", 10).unwrap(),
+            shard_key(b"Tundra", 1).unwrap(),
+            shard_key(b"Desert", 2).unwrap()];
+        keys.sort();
+        assert_eq!(keys[0], shard_key(b"Desert", 2).unwrap());
+        assert_eq!(keys[1], shard_key(b"Desert", 10).unwrap());
+        assert!(is_full_shard(SHARD_CAPACITY) && !is_full_shard(SHARD_CAPACITY - 1));
+    }
+}
@@ -0,0 +1,172 @@
+//! Cardinality statistics for secondary indexes, used for reporting and by the query planner.
+
+use crate::Error;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Name of the internal table that stores each analyzed index's [`IndexStats`], keyed by index
+/// table name.
+pub const STATS_TABLE_NAME: &str = "__atlatl_index_stats";
+
+/// Number of buckets in an [`IndexStats`] key-set size histogram.
+pub const HISTOGRAM_BUCKETS: usize = 32;
+
+/// Size of an encoded [`IndexStats`] row in the stats table.
+const ENCODED_LEN: usize = (2 + HISTOGRAM_BUCKETS) * size_of::<u64>();
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Cardinality statistics for a secondary index.
+///
+/// Statistics are gathered for an index the first time it's analyzed, and are then kept up to
+/// date as records are written and deleted. For example, a `creatures_by_habitat` index with
+/// `"Desert"` → `{Scorpion, Jerboa}` and `"Tide Pool"` → `{Snail}` has 2 distinct keys, 3 total
+/// entries, one key set in the `1` bucket and one in the `2..4` bucket.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct IndexStats {
+    /// The number of distinct secondary keys in the index.
+    pub distinct_keys: u64,
+
+    /// The total number of primary keys across all key sets.
+    pub total_entries: u64,
+
+    /// A histogram of key-set sizes. Bucket `i` counts the key sets holding `2^i` up to
+    /// `2^(i+1) - 1` primary keys, and the last bucket also counts any larger sets.
+    pub histogram: [u64; HISTOGRAM_BUCKETS],
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl IndexStats {
+    /// Returns the average number of primary keys per secondary key, or `0.0` for an empty index.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss, reason = "an estimate doesn't need every digit")]
+    pub fn average_set_size(&self) -> f64 {
+        if self.distinct_keys == 0 {
+            0.0
+        } else {
+            self.total_entries as f64 / self.distinct_keys as f64
+        }
+    }
+
+    /// Returns the histogram bucket a key set of the given non-zero size is counted in.
+    #[must_use]
+    pub const fn bucket_of(set_size: u64) -> usize {
+        let bucket = if set_size == 0 { 0 } else { set_size.ilog2() as usize };
+        if bucket < HISTOGRAM_BUCKETS { bucket } else { HISTOGRAM_BUCKETS - 1 }
+    }
+
+    /// Counts a key set of the given size. Empty key sets aren't stored, and aren't counted.
+    pub const fn record(&mut self, set_size: u64) {
+        if set_size > 0 {
+            self.distinct_keys += 1;
+            self.total_entries = self.total_entries.saturating_add(set_size);
+            self.histogram[Self::bucket_of(set_size)] += 1;
+        }
+    }
+
+    /// Stops counting a key set of the given size, which was previously recorded.
+    pub const fn forget(&mut self, set_size: u64) {
+        if set_size > 0 {
+            self.distinct_keys = self.distinct_keys.saturating_sub(1);
+            self.total_entries = self.total_entries.saturating_sub(set_size);
+            let bucket = &mut self.histogram[Self::bucket_of(set_size)];
+            *bucket = bucket.saturating_sub(1);
+        }
+    }
+
+    /// Records a key set changing size, for example when a primary key is added to it. A size of
+    /// zero means the key set didn't exist, or no longer exists.
+    pub const fn resize(&mut self, old_set_size: u64, new_set_size: u64) {
+        if old_set_size != new_set_size {
+            self.forget(old_set_size);
+            self.record(new_set_size);
+        }
+    }
+
+    /// Encodes the statistics for the stats table, as little-endian `u64`s.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(ENCODED_LEN);
+        bytes.extend_from_slice(&self.distinct_keys.to_le_bytes());
+        bytes.extend_from_slice(&self.total_entries.to_le_bytes());
+        for count in self.histogram {
+            bytes.extend_from_slice(&count.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Decodes statistics from the stats table.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Corrupted`] if the row isn't the expected length.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != ENCODED_LEN {
+            return Err(Error::Corrupted {
+                message: format!(
+                    "index statistics row is {} bytes, expected {ENCODED_LEN}",
+                    bytes.len()
+                ),
+            });
+        }
+
+        let mut words = bytes
+            .chunks_exact(size_of::<u64>())
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap_or_default()));
+
+        let mut stats = Self {
+            distinct_keys: words.next().unwrap_or_default(),
+            total_entries: words.next().unwrap_or_default(),
+            histogram: [0; HISTOGRAM_BUCKETS],
+        };
+        for (bucket, count) in stats.histogram.iter_mut().zip(words) {
+            *bucket = count;
+        }
+
+        Ok(stats)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_and_forget_track_buckets() {
+        let mut stats = IndexStats::default();
+        stats.record(1);
+        stats.record(3);
+        stats.record(1_000);
+        assert_eq!(stats.distinct_keys, 3);
+        assert_eq!(stats.total_entries, 1_004);
+        assert_eq!(stats.histogram[0], 1);
+        assert_eq!(stats.histogram[1], 1);
+        assert_eq!(stats.histogram[9], 1);
+
+        stats.resize(3, 4);
+        assert_eq!(stats.histogram[1], 0);
+        assert_eq!(stats.histogram[2], 1);
+
+        stats.resize(1, 0);
+        assert_eq!(stats.distinct_keys, 2);
+        assert_eq!(stats.total_entries, 1_004);
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let mut stats = IndexStats::default();
+        stats.record(2);
+        stats.record(u64::MAX);
+        assert_eq!(IndexStats::bucket_of(u64::MAX), HISTOGRAM_BUCKETS - 1);
+        assert_eq!(IndexStats::from_bytes(&stats.to_bytes()).unwrap(), stats);
+        assert!(IndexStats::from_bytes(&[0; 3]).is_err());
+    }
+}
@@ -0,0 +1,60 @@
+//! Reports produced by cross-checking secondary indexes against their primary table.
+
+use crate::indexing::IndexKind;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// An inconsistency between a secondary index and its primary table.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum IndexIssue {
+    /// A record's secondary key has no index entry pointing to the record. Queries on that key
+    /// won't find the record.
+    MissingEntry {
+        index_name: &'static str,
+        index_kind: IndexKind,
+        secondary_key_bytes: Vec<u8>,
+        primary_key_bytes: Vec<u8>,
+    },
+
+    /// An index entry points to a record that doesn't exist, or that no longer has that secondary
+    /// key. Queries on that key will return the wrong record, or fail to load it.
+    DanglingKey {
+        index_name: &'static str,
+        index_kind: IndexKind,
+        secondary_key_bytes: Vec<u8>,
+        primary_key_bytes: Vec<u8>,
+    },
+
+    /// More than one record has the same secondary key in a `Unique` index. This can't be
+    /// repaired automatically: all but one of the records must be changed or deleted.
+    UniqueViolation {
+        index_name: &'static str,
+        secondary_key_bytes: Vec<u8>,
+        primary_keys: Vec<Vec<u8>>,
+    },
+}
+
+/// The result of checking, and optionally repairing, a record type's secondary indexes.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct IndexReport {
+    /// The number of records read from the primary table.
+    pub records_checked: u64,
+
+    /// Every inconsistency that was found.
+    pub issues: Vec<IndexIssue>,
+
+    /// The number of issues that were repaired. Always zero when only verifying.
+    pub repaired: u64,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl IndexReport {
+    /// Returns `true` if no inconsistencies were found.
+    #[must_use]
+    pub const fn is_consistent(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
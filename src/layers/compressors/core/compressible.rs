@@ -51,4 +51,31 @@ pub trait Compressible {
     ///
     /// The [`Level`] configuration applied to all values of this type.
     const LEVEL: crate::layers::compressors::Level;
+
+    /// The smallest value, in bytes, that compression is attempted for.
+    ///
+    /// Compressing a few bytes rarely makes them smaller, since every compressed frame carries a
+    /// header. Values shorter than this are stored as they are, and aren't decompressed on read.
+    /// Values that don't shrink when compressed, such as images or archives, are also stored as
+    /// they are, whatever their size.
+    ///
+    /// # Returns
+    ///
+    /// The minimum size, in bytes, for values of this type to be compressed. Defaults to `64`.
+    const MIN_SIZE: usize = 64;
+
+    /// The frame size, in bytes, that large values of this type are compressed in.
+    ///
+    /// Values are normally compressed as a single buffer, which spikes memory for very large
+    /// values. When a chunk size is set, values larger than it are split into frames of this size,
+    /// and each frame is compressed and decompressed on its own. This bounds the compressor's
+    /// working memory, at the cost of a slightly worse compression ratio.
+    ///
+    /// Chunking isn't used for values compressed with a dictionary.
+    ///
+    /// # Returns
+    ///
+    /// The frame size, in bytes, or `None` to compress values as a single buffer. Defaults to
+    /// `None`.
+    const CHUNK_SIZE: Option<usize> = None;
 }
\ No newline at end of file
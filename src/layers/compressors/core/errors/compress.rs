@@ -14,7 +14,7 @@ pub enum Error {
     /// documentation: <https://docs.rs/brotli>
     #[cfg(feature = "compress-brotli")]
     #[error("brotli compression failed")]
-    Brotli { #[from] #[source] source: std::io::Error },
+    Brotli { #[source] source: std::io::Error },
 
     /// Error returned from the [bzip2](https://crates.io/crates/bzip2) crate.
     ///
@@ -22,7 +22,7 @@ pub enum Error {
     /// documentation: <https://docs.rs/bzip2>
     #[cfg(feature = "compress-bzip2")]
     #[error("bzip2 compression failed")]
-    Bzip2 { #[from] #[source] source: std::io::Error },
+    Bzip2 { #[source] source: std::io::Error },
 
     /// Error returned from the [flate2](https://crates.io/crates/flate2) crate.
     ///
@@ -30,7 +30,7 @@ pub enum Error {
     /// documentation: <https://docs.rs/flate2>
     #[cfg(feature = "compress-deflate")]
     #[error("deflate compression failed")]
-    Deflate { #[from] #[source] source: std::io::Error },
+    Deflate { #[source] source: std::io::Error },
 
     /// Error returned from the [flate2](https://crates.io/crates/flate2) crate.
     ///
@@ -38,7 +38,7 @@ pub enum Error {
     /// documentation: <https://docs.rs/flate2>
     #[cfg(feature = "compress-gzip")]
     #[error("gzip compression failed")]
-    Gzip { #[from] #[source] source: std::io::Error },
+    Gzip { #[source] source: std::io::Error },
 
     /// Error returned from the [flate2](https://crates.io/crates/flate2) crate.
     ///
@@ -54,5 +54,14 @@ pub enum Error {
     /// documentation: <https://docs.rs/zstd>
     #[cfg(feature = "compress-zstd")]
     #[error("zstd compression failed")]
-    Zstd { #[from] #[source] source: std::io::Error },
+    Zstd { #[source] source: std::io::Error },
+
+    /// The selected compression method's feature isn't enabled in `Cargo.toml`.
+    #[error("{method} compression is not enabled")]
+    Unsupported { method: crate::layers::compressors::Method },
+
+    /// A value compressed in chunks produced a frame, or a number of frames, that doesn't fit in a
+    /// `u32`. Use a smaller `Compressible::CHUNK_SIZE`.
+    #[error("compressed frame length {len} exceeds the maximum of {}", u32::MAX)]
+    FrameTooLarge { len: usize },
 }
\ No newline at end of file
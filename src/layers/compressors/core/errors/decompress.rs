@@ -14,7 +14,7 @@ pub enum Error {
     /// official documentation: <https://docs.rs/brotli>
     #[cfg(feature = "compress-brotli")]
     #[error("brotli decompression failed")]
-    Brotli { #[from] #[source] source: std::io::Error },
+    Brotli { #[source] source: std::io::Error },
 
     /// Error returned from the [bzip2](https://crates.io/crates/bzip2) crate.
     ///
@@ -22,7 +22,7 @@ pub enum Error {
     /// official documentation: <https://docs.rs/bzip2>
     #[cfg(feature = "compress-bzip2")]
     #[error("bzip2 decompression failed")]
-    Bzip2 { #[from] #[source] source: std::io::Error },
+    Bzip2 { #[source] source: std::io::Error },
 
     /// Error returned from the [flate2](https://crates.io/crates/flate2) crate.
     ///
@@ -30,7 +30,7 @@ pub enum Error {
     /// official documentation: <https://docs.rs/flate2>
     #[cfg(feature = "compress-deflate")]
     #[error("deflate decompression failed")]
-    Deflate { #[from] #[source] source: std::io::Error },
+    Deflate { #[source] source: std::io::Error },
 
     /// Error returned from the [flate2](https://crates.io/crates/flate2) crate.
     ///
@@ -38,7 +38,7 @@ pub enum Error {
     /// official documentation: <https://docs.rs/flate2>
     #[cfg(feature = "compress-gzip")]
     #[error("gzip decompression failed")]
-    Gzip { #[from] #[source] source: std::io::Error },
+    Gzip { #[source] source: std::io::Error },
 
     /// Error returned from the [lz4_flex](https://crates.io/crates/lz4_flex) crate.
     ///
@@ -62,5 +62,23 @@ pub enum Error {
     /// official documentation: <https://docs.rs/zstd>
     #[cfg(feature = "compress-zstd")]
     #[error("zstd decompression failed")]
-    Zstd { #[from] #[source] source: std::io::Error },
+    Zstd { #[source] source: std::io::Error },
+
+    /// The value was written with a compression method whose feature isn't enabled in `Cargo.toml`.
+    #[error("value was compressed with {method}, which is not enabled")]
+    Unsupported { method: crate::layers::compressors::Method },
+
+    /// The value is empty, so it doesn't carry the compression method it was written with.
+    #[error("compressed value is missing its compression method")]
+    MissingMethod,
+
+    /// The value's compression method tag isn't recognized, which usually indicates data
+    /// corruption.
+    #[error("unrecognized compression method {0}")]
+    UnrecognizedMethod(u8),
+
+    /// The frame lengths of a value that was compressed in chunks don't match its data, which
+    /// usually indicates data corruption.
+    #[error("compressed frames are malformed")]
+    MalformedFrames,
 }
\ No newline at end of file
@@ -40,6 +40,27 @@ pub enum Method {
     Zstd    = 6,
 }
 
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Method {
+    /// Returns `true` if this compression method's feature is enabled in `Cargo.toml`, meaning that
+    /// values can be written with it and values that were written with it can be read.
+    #[must_use]
+    pub const fn is_enabled(self) -> bool {
+        match self {
+            Self::Brotli  => cfg!(feature = "compress-brotli"),
+            Self::Bzip2   => cfg!(feature = "compress-bzip2"),
+            Self::Deflate => cfg!(feature = "compress-deflate"),
+            Self::Gzip    => cfg!(feature = "compress-gzip"),
+            Self::Lz4     => cfg!(feature = "compress-lz4"),
+            Self::Zlib    => cfg!(feature = "compress-zlib"),
+            Self::Zstd    => cfg!(feature = "compress-zstd"),
+        }
+    }
+}
+
 // -------------------------------------------------------------------------------------------------
 //
 // Trait Implementations
@@ -3,7 +3,9 @@
 //! [brotli](https://crates.io/crates/brotli) crate.
 
 use crate::layers::compressors::impls::{BUFFER_LEN, RESERVATION_FACTOR};
-use crate::layers::compressors::{Compressible, Compressor, Level, Method};
+use crate::layers::compressors::{
+    Compressible, CompressError, Compressor, DecompressError, Level, Method
+};
 use crate::layers::core::Bytes;
 use std::io::{Cursor, Write};
 
@@ -83,8 +85,9 @@ impl<'b, V: Compressible> Compressor<'b, V> for Brotli<V> {
                 compression_level::<V>(),
                 LGWIN,
             );
-            writer.write_all(&uncompressed_bytes)?;
-            writer.flush()?; // Important!
+            writer.write_all(&uncompressed_bytes)
+                .map_err(|source| CompressError::Brotli { source })?;
+            writer.flush().map_err(|source| CompressError::Brotli { source })?; // Important!
         }
         Ok(compressed_bytes.into())
     }
@@ -113,7 +116,8 @@ impl<'b, V: Compressible> Compressor<'b, V> for Brotli<V> {
             compressed_data.len().saturating_mul(RESERVATION_FACTOR)
         );
         let mut decompressor = brotli::Decompressor::new(Cursor::new(compressed_data), BUFFER_LEN);
-        std::io::copy(&mut decompressor, &mut output)?;
+        std::io::copy(&mut decompressor, &mut output)
+            .map_err(|source| DecompressError::Brotli { source })?;
         Ok(Bytes::from_parts(metadata, output.into()))
     }
 }
\ No newline at end of file
@@ -0,0 +1,547 @@
+//! Runtime dispatch between the compiled-in compressors.
+//!
+//! Every compressed value starts with [`MARKER`], and ends with a single byte that identifies the
+//! compression [`Method`] it was written with:
+//!
+//! | `marker`  | `data`  | `method` |
+//! |-----------|---------|----------|
+//! | `[u8; 4]` | `&[u8]` | `u8`     |
+//!
+//! Values are decompressed with the compressor named by their tag, rather than with the one that's
+//! currently used for writing. This lets a database written with one algorithm be read by a build
+//! that writes with another, as long as both compressor features are enabled.
+//!
+//! Values that are shorter than [`Compressible::MIN_SIZE`], or that don't shrink when compressed,
+//! are stored as they are. Their tag is [`STORED`] rather than a method, and they aren't
+//! decompressed on read. This avoids spending CPU on tiny or already-compressed values.
+//!
+//! Values larger than [`Compressible::CHUNK_SIZE`] are compressed in frames, see the `frames`
+//! module. Their tag is [`CHUNKED`], and each frame carries a tag of its own.
+//!
+//! Values written before compressed values were tagged don't start with the marker. They're
+//! decompressed with [`DEFAULT_METHOD`], since only one compressor could be enabled back then.
+
+use crate::layers::compressors::impls::frames;
+use crate::layers::compressors::{Compressible, CompressError, Compressor, DecompressError, Method};
+use crate::layers::core::Bytes;
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[cfg(feature = "compress-dictionaries")]
+use crate::layers::compressors::DictionaryBytes;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// The compressor that values are written with, unless another is selected with
+/// [`set_write_method`]. This is the first enabled compressor, in [`Method`] order.
+pub const DEFAULT_METHOD: Method = if cfg!(feature = "compress-brotli") {
+    Method::Brotli
+} else if cfg!(feature = "compress-bzip2") {
+    Method::Bzip2
+} else if cfg!(feature = "compress-deflate") {
+    Method::Deflate
+} else if cfg!(feature = "compress-gzip") {
+    Method::Gzip
+} else if cfg!(feature = "compress-lz4") {
+    Method::Lz4
+} else if cfg!(feature = "compress-zlib") {
+    Method::Zlib
+} else {
+    Method::Zstd
+};
+
+/// Tag of values that are stored uncompressed, because they were too small or didn't shrink when
+/// compressed. It's outside the range of `Method` discriminants.
+pub const STORED: u8 = u8::MAX;
+
+/// Tag of values that were compressed in frames, because they're larger than their type's
+/// [`Compressible::CHUNK_SIZE`]. It's outside the range of `Method` discriminants.
+pub const CHUNKED: u8 = u8::MAX - 1;
+
+/// Starts every tagged value, setting it apart from legacy values that were written without a tag.
+///
+/// Legacy values start with their compressor's own output. The marker isn't a `zstd`, `gzip`,
+/// `zlib`, or `bzip2` header, and as an `lz4` size prefix it would describe a value of nearly
+/// 4 GiB.
+pub const MARKER: [u8; 4] = [0xA7, 0x1A, 0xC0, 0xFF];
+
+/// The compressor that values are currently written with, as a `Method` discriminant.
+static WRITE_METHOD: AtomicU8 = AtomicU8::new(DEFAULT_METHOD as u8);
+
+// -------------------------------------------------------------------------------------------------
+//
+/// What a compressed value's tag says about how it was written.
+enum Tag {
+    /// The value is stored uncompressed.
+    Stored,
+
+    /// The value was compressed in frames, which are tagged individually.
+    Chunked,
+
+    /// The value was compressed with the given method.
+    Compressed(Method),
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Macros
+
+/// Evaluates `$call` with `$compressor` bound to the compressor type of `$method`, or evaluates
+/// `$unsupported` if that compressor's feature isn't enabled.
+macro_rules! dispatch {
+    ($method:expr, $compressor:ident => $call:expr, $unsupported:expr) => {
+        match $method {
+            #[cfg(feature = "compress-brotli")]
+            Method::Brotli => { type $compressor<V> = super::brotli::Brotli<V>; $call },
+            #[cfg(feature = "compress-bzip2")]
+            Method::Bzip2 => { type $compressor<V> = super::bzip2::Bzip2<V>; $call },
+            #[cfg(feature = "compress-deflate")]
+            Method::Deflate => { type $compressor<V> = super::flate2_deflate::Deflate<V>; $call },
+            #[cfg(feature = "compress-gzip")]
+            Method::Gzip => { type $compressor<V> = super::flate2_gzip::Gzip<V>; $call },
+            #[cfg(feature = "compress-lz4")]
+            Method::Lz4 => { type $compressor<V> = super::lz4_flex::Lz4Flex<V>; $call },
+            #[cfg(feature = "compress-zlib")]
+            Method::Zlib => { type $compressor<V> = super::flate2_zlib::Zlib<V>; $call },
+            #[cfg(feature = "compress-zstd")]
+            Method::Zstd => { type $compressor<V> = super::zstd::Zstd<V>; $call },
+            #[allow(unreachable_patterns)]
+            _ => $unsupported,
+        }
+    };
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Selects the compressor that values are written with from now on. Values that were written with
+/// other compressors can still be read, as long as their features are enabled.
+///
+/// # Errors
+///
+/// * Returns [`CompressError::Unsupported`] if the compressor's feature isn't enabled.
+pub fn set_write_method(method: Method) -> Result<(), CompressError> {
+    if method.is_enabled() {
+        WRITE_METHOD.store(method as u8, Ordering::Relaxed);
+        Ok(())
+    } else {
+        Err(CompressError::Unsupported { method })
+    }
+}
+
+/// Returns the compressor that values are currently written with.
+#[must_use]
+pub fn write_method() -> Method {
+    <&Method>::try_from(&WRITE_METHOD.load(Ordering::Relaxed))
+        .map_or(DEFAULT_METHOD, |method| *method)
+}
+
+/// Compresses a value with the current write compressor, and tags it with the compressor's method.
+/// Values that are too small, or that don't shrink, are stored as they are. Values larger than
+/// `V::CHUNK_SIZE` are compressed in frames.
+///
+/// # Errors
+///
+/// Consult the documentation of the compressor backend you are using for more detail on
+/// compression behavior and potential limitations.
+#[cfg(not(feature = "compress-dictionaries"))]
+pub(crate) fn compress_tagged<V: Compressible>(
+    uncompressed_bytes: Bytes<'_>
+) -> Result<Bytes<'_>, CompressError> {
+    let method = write_method();
+    compress_chunked::<V>(uncompressed_bytes, method, |data: &[u8]| dispatch!(
+        method,
+        Active => <Active<V> as Compressor<'_, V>>::compress(Bytes::from_slice(data))
+            .map(|compressed_bytes| compressed_bytes.into_bytes().into_owned()),
+        Err(CompressError::Unsupported { method })
+    )).map(mark)
+}
+
+/// Compresses a value with the current write compressor, and tags it with the compressor's method.
+/// Values that are too small, or that don't shrink, are stored as they are. Values larger than
+/// `V::CHUNK_SIZE` are compressed in frames, unless a dictionary is used.
+///
+/// # Errors
+///
+/// Consult the documentation of the compressor backend you are using for more detail on
+/// compression behavior and potential limitations.
+#[cfg(feature = "compress-dictionaries")]
+pub(crate) fn compress_tagged<'b, V: Compressible>(
+    uncompressed_bytes: Bytes<'b>,
+    dictionary: Option<DictionaryBytes<'_>>
+) -> Result<Bytes<'b>, CompressError> {
+    let method = write_method();
+    if dictionary.is_none() {
+        return compress_chunked::<V>(uncompressed_bytes, method, |data: &[u8]| dispatch!(
+            method,
+            Active => <Active<V> as Compressor<'_, '_, V>>::compress(Bytes::from_slice(data), None)
+                .map(|compressed_bytes| compressed_bytes.into_bytes().into_owned()),
+            Err(CompressError::Unsupported { method })
+        )).map(mark);
+    }
+
+    compress_or_store::<V>(uncompressed_bytes, method, |data| dispatch!(
+        method,
+        Active => <Active<V> as Compressor<'_, '_, V>>::compress(
+            Bytes::from_slice(data),
+            dictionary
+        ).map(|compressed_bytes| compressed_bytes.into_bytes().into_owned()),
+        Err(CompressError::Unsupported { method })
+    )).map(mark)
+}
+
+/// Decompresses a value with the compressor named by its method tag.
+///
+/// # Errors
+///
+/// * The value has no method tag, or the tag names an unknown or disabled compressor.
+///
+/// * Input bytes are corrupted or malformed.
+#[cfg(not(feature = "compress-dictionaries"))]
+pub(crate) fn decompress_tagged<V: Compressible>(
+    compressed_bytes: Bytes<'_>
+) -> Result<Bytes<'_>, DecompressError> {
+    match unmark(compressed_bytes) {
+        (true, compressed_bytes) => decompress_value::<V>(compressed_bytes),
+        (false, legacy_bytes) => dispatch!(
+            DEFAULT_METHOD,
+            Active => <Active<V> as Compressor<'_, V>>::decompress(legacy_bytes),
+            Err(DecompressError::Unsupported { method: DEFAULT_METHOD })
+        ),
+    }
+}
+
+/// Decompresses a value, or one of its frames, with the compressor named by its method tag.
+///
+/// # Errors
+///
+/// * See [`decompress_tagged`].
+#[cfg(not(feature = "compress-dictionaries"))]
+fn decompress_value<V: Compressible>(
+    compressed_bytes: Bytes<'_>
+) -> Result<Bytes<'_>, DecompressError> {
+    match untag(compressed_bytes)? {
+        (Tag::Stored, compressed_bytes) => Ok(compressed_bytes),
+        (Tag::Chunked, compressed_bytes) => decompress_chunked(compressed_bytes, |frame| {
+            decompress_value::<V>(Bytes::from_slice(frame))
+                .map(|decompressed_bytes| decompressed_bytes.into_bytes().into_owned())
+        }),
+        (Tag::Compressed(method), compressed_bytes) => dispatch!(
+            method,
+            Active => <Active<V> as Compressor<'_, V>>::decompress(compressed_bytes),
+            Err(DecompressError::Unsupported { method })
+        ),
+    }
+}
+
+/// Decompresses a value with the compressor named by its method tag.
+///
+/// # Errors
+///
+/// * The value has no method tag, or the tag names an unknown or disabled compressor.
+///
+/// * Input bytes are corrupted or malformed.
+///
+/// * Dictionary mismatch (if provided dictionary differs from compression-time dictionary).
+#[cfg(feature = "compress-dictionaries")]
+pub(crate) fn decompress_tagged<'b, V: Compressible>(
+    compressed_bytes: Bytes<'b>,
+    dictionary: Option<DictionaryBytes<'_>>
+) -> Result<Bytes<'b>, DecompressError> {
+    match unmark(compressed_bytes) {
+        (true, compressed_bytes) => decompress_value::<V>(compressed_bytes, dictionary),
+        (false, legacy_bytes) => dispatch!(
+            DEFAULT_METHOD,
+            Active => <Active<V> as Compressor<'b, '_, V>>::decompress(legacy_bytes, dictionary),
+            Err(DecompressError::Unsupported { method: DEFAULT_METHOD })
+        ),
+    }
+}
+
+/// Decompresses a value, or one of its frames, with the compressor named by its method tag.
+///
+/// # Errors
+///
+/// * See [`decompress_tagged`].
+#[cfg(feature = "compress-dictionaries")]
+fn decompress_value<'b, V: Compressible>(
+    compressed_bytes: Bytes<'b>,
+    dictionary: Option<DictionaryBytes<'_>>
+) -> Result<Bytes<'b>, DecompressError> {
+    match untag(compressed_bytes)? {
+        (Tag::Stored, compressed_bytes) => Ok(compressed_bytes),
+        (Tag::Chunked, compressed_bytes) => decompress_chunked(compressed_bytes, |frame| {
+            decompress_value::<V>(Bytes::from_slice(frame), None)
+                .map(|decompressed_bytes| decompressed_bytes.into_bytes().into_owned())
+        }),
+        (Tag::Compressed(method), compressed_bytes) => dispatch!(
+            method,
+            Active => <Active<V> as Compressor<'b, '_, V>>::decompress(
+                compressed_bytes,
+                dictionary
+            ),
+            Err(DecompressError::Unsupported { method })
+        ),
+    }
+}
+
+/// Compresses a value's bytes with `compress`, in frames of `V::CHUNK_SIZE` if the value is larger
+/// than that, and tags the output. Each frame is compressed or stored on its own.
+fn compress_chunked<V: Compressible>(
+    uncompressed_bytes: Bytes<'_>,
+    method: Method,
+    compress: impl Fn(&[u8]) -> Result<Vec<u8>, CompressError>
+) -> Result<Bytes<'_>, CompressError> {
+    match V::CHUNK_SIZE {
+        Some(chunk_size) if uncompressed_bytes.len() > chunk_size => {
+            let (metadata, data) = uncompressed_bytes.into_parts();
+            let frames = frames::compress_frames(&data, chunk_size, |frame| {
+                compress_or_store::<V>(Bytes::from_slice(frame), method, &compress)
+                    .map(|compressed_bytes| compressed_bytes.into_bytes().into_owned())
+            })?;
+            Ok(tag(Bytes::from_parts(metadata, frames.into()), CHUNKED))
+        },
+        _ => compress_or_store::<V>(uncompressed_bytes, method, compress),
+    }
+}
+
+/// Decompresses each of a chunked value's frames with `decompress_frame`, and joins them.
+fn decompress_chunked(
+    compressed_bytes: Bytes<'_>,
+    decompress_frame: impl FnMut(&[u8]) -> Result<Vec<u8>, DecompressError>
+) -> Result<Bytes<'_>, DecompressError> {
+    let (metadata, data) = compressed_bytes.into_parts();
+    let decompressed = frames::decompress_frames(&data, decompress_frame)?;
+    Ok(Bytes::from_parts(metadata, decompressed.into()))
+}
+
+/// Compresses a value's bytes with `compress`, and tags the output with `method`. If the value is
+/// shorter than `V::MIN_SIZE`, or compressing it doesn't make it smaller, the value is tagged as
+/// [`STORED`] and kept as it is instead.
+fn compress_or_store<V: Compressible>(
+    uncompressed_bytes: Bytes<'_>,
+    method: Method,
+    compress: impl FnOnce(&[u8]) -> Result<Vec<u8>, CompressError>
+) -> Result<Bytes<'_>, CompressError> {
+    if uncompressed_bytes.len() < V::MIN_SIZE {
+        return Ok(tag(uncompressed_bytes, STORED));
+    }
+
+    let (metadata, data) = uncompressed_bytes.into_parts();
+    let compressed = compress(&data)?;
+    if compressed.len() < data.len() {
+        Ok(tag(Bytes::from_parts(metadata, compressed.into()), method as u8))
+    } else {
+        Ok(tag(Bytes::from_parts(metadata, data), STORED))
+    }
+}
+
+/// Appends a method tag, or the [`STORED`] or [`CHUNKED`] tag, to compressed bytes.
+fn tag(compressed_bytes: Bytes<'_>, tag: u8) -> Bytes<'_> {
+    let (metadata, data) = compressed_bytes.into_parts();
+    let mut data = data.into_owned();
+    data.push(tag);
+    Bytes::from_parts(metadata, data.into())
+}
+
+/// Puts [`MARKER`] in front of a tagged value.
+fn mark(compressed_bytes: Bytes<'_>) -> Bytes<'_> {
+    let (metadata, data) = compressed_bytes.into_parts();
+    let mut marked = Vec::with_capacity(MARKER.len() + data.len());
+    marked.extend_from_slice(&MARKER);
+    marked.extend_from_slice(&data);
+    Bytes::from_parts(metadata, marked.into())
+}
+
+/// Removes the leading [`MARKER`] from compressed bytes. Returns `false`, and the bytes as they
+/// are, for legacy values that were written without it.
+fn unmark(compressed_bytes: Bytes<'_>) -> (bool, Bytes<'_>) {
+    if !compressed_bytes.as_ref().starts_with(&MARKER) {
+        return (false, compressed_bytes);
+    }
+    let (metadata, data) = compressed_bytes.into_parts();
+    let data = match data {
+        Cow::Borrowed(slice) => Cow::Borrowed(&slice[MARKER.len()..]),
+        Cow::Owned(mut vec) => {
+            vec.drain(..MARKER.len());
+            Cow::Owned(vec)
+        },
+    };
+    (true, Bytes::from_parts(metadata, data))
+}
+
+/// Removes the tag from compressed bytes, returning what it says about how they were written.
+fn untag(mut compressed_bytes: Bytes<'_>) -> Result<(Tag, Bytes<'_>), DecompressError> {
+    let tag = match *compressed_bytes.as_ref().last().ok_or(DecompressError::MissingMethod)? {
+        STORED => Tag::Stored,
+        CHUNKED => Tag::Chunked,
+        tag => Tag::Compressed(
+            *<&Method>::try_from(&tag).map_err(|_| DecompressError::UnrecognizedMethod(tag))?
+        ),
+    };
+    compressed_bytes.truncate(compressed_bytes.len() - 1);
+    Ok((tag, compressed_bytes))
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::compressors::Level;
+    use crate::layers::core::Direction;
+
+    struct Document;
+
+    impl Compressible for Document {
+        const DIRECTION: Direction = Direction::Both;
+        const LEVEL: Level = Level::Maximum;
+    }
+
+    struct Blob;
+
+    impl Compressible for Blob {
+        const DIRECTION: Direction = Direction::Both;
+        const LEVEL: Level = Level::Maximum;
+        const CHUNK_SIZE: Option<usize> = Some(1_024);
+    }
+
+    #[test]
+    fn values_are_tagged_with_their_method() {
+        let uncompressed = Bytes::from_vec(b"the quick brown fox ".repeat(16));
+
+        #[cfg(not(feature = "compress-dictionaries"))]
+        let compressed = compress_tagged::<Document>(uncompressed.clone()).unwrap();
+        #[cfg(feature = "compress-dictionaries")]
+        let compressed = compress_tagged::<Document>(uncompressed.clone(), None).unwrap();
+        assert_eq!(compressed.as_ref().last(), Some(&(write_method() as u8)));
+
+        #[cfg(not(feature = "compress-dictionaries"))]
+        let decompressed = decompress_tagged::<Document>(compressed).unwrap();
+        #[cfg(feature = "compress-dictionaries")]
+        let decompressed = decompress_tagged::<Document>(compressed, None).unwrap();
+        assert_eq!(decompressed.as_ref(), uncompressed.as_ref());
+    }
+
+    #[test]
+    fn unknown_and_disabled_methods_are_rejected() {
+        let untagged = Bytes::from_vec(vec![1, 2, 3, 99]);
+        assert!(matches!(untag(untagged), Err(DecompressError::UnrecognizedMethod(99))));
+        assert!(matches!(untag(Bytes::from_vec(Vec::new())), Err(DecompressError::MissingMethod)));
+
+        let disabled = [Method::Brotli, Method::Bzip2, Method::Zstd]
+            .into_iter()
+            .find(|method| !method.is_enabled());
+        if let Some(method) = disabled {
+            assert!(set_write_method(method).is_err());
+        }
+        assert!(set_write_method(DEFAULT_METHOD).is_ok());
+    }
+
+    #[test]
+    fn small_and_incompressible_values_are_stored() {
+        let small = Bytes::from_vec(b"tiny".to_vec());
+        let mut state = 0x2545_f491_u32;
+        let noise = Bytes::from_vec((0..512).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state.to_le_bytes()[0]
+        }).collect());
+
+        for uncompressed in [small, noise] {
+            #[cfg(not(feature = "compress-dictionaries"))]
+            let compressed = compress_tagged::<Document>(uncompressed.clone()).unwrap();
+            #[cfg(feature = "compress-dictionaries")]
+            let compressed = compress_tagged::<Document>(uncompressed.clone(), None).unwrap();
+            assert_eq!(compressed.as_ref().last(), Some(&STORED));
+            let data = &compressed.as_ref()[MARKER.len()..];
+            assert_eq!(&data[..uncompressed.len()], uncompressed.as_ref());
+
+            #[cfg(not(feature = "compress-dictionaries"))]
+            let decompressed = decompress_tagged::<Document>(compressed).unwrap();
+            #[cfg(feature = "compress-dictionaries")]
+            let decompressed = decompress_tagged::<Document>(compressed, None).unwrap();
+            assert_eq!(decompressed.as_ref(), uncompressed.as_ref());
+        }
+    }
+
+    #[test]
+    fn large_values_are_compressed_in_frames() {
+        let uncompressed = Bytes::from_vec(b"the quick brown fox ".repeat(256));
+
+        #[cfg(not(feature = "compress-dictionaries"))]
+        let compressed = compress_tagged::<Blob>(uncompressed.clone()).unwrap();
+        #[cfg(feature = "compress-dictionaries")]
+        let compressed = compress_tagged::<Blob>(uncompressed.clone(), None).unwrap();
+        assert_eq!(compressed.as_ref().last(), Some(&CHUNKED));
+        assert!(compressed.len() < uncompressed.len());
+
+        #[cfg(not(feature = "compress-dictionaries"))]
+        let decompressed = decompress_tagged::<Blob>(compressed).unwrap();
+        #[cfg(feature = "compress-dictionaries")]
+        let decompressed = decompress_tagged::<Blob>(compressed, None).unwrap();
+        assert_eq!(decompressed.as_ref(), uncompressed.as_ref());
+    }
+
+    /// A value that was compressed with `lz4` before values were tagged: `"the quick brown fox "`
+    /// four times over, behind `lz4`'s little-endian size prefix.
+    #[cfg(all(
+        feature = "compress-lz4",
+        not(any(
+            feature = "compress-brotli",
+            feature = "compress-bzip2",
+            feature = "compress-deflate",
+            feature = "compress-gzip"
+        ))
+    ))]
+    const LEGACY_LZ4: [u8; 36] = [
+        80, 0, 0, 0, 255, 5, 116, 104, 101, 32, 113, 117, 105, 99, 107, 32, 98, 114, 111, 119, 110,
+        32, 102, 111, 120, 32, 20, 0, 35, 96, 110, 32, 102, 111, 120, 32,
+    ];
+
+    #[cfg(all(
+        feature = "compress-lz4",
+        not(any(
+            feature = "compress-brotli",
+            feature = "compress-bzip2",
+            feature = "compress-deflate",
+            feature = "compress-gzip"
+        ))
+    ))]
+    #[test]
+    fn legacy_untagged_values_are_decompressed() {
+        let expected = b"the quick brown fox ".repeat(4);
+
+        for legacy in [Bytes::from_slice(&LEGACY_LZ4), Bytes::from_vec(LEGACY_LZ4.to_vec())] {
+            #[cfg(not(feature = "compress-dictionaries"))]
+            let decompressed = decompress_tagged::<Document>(legacy).unwrap();
+            #[cfg(feature = "compress-dictionaries")]
+            let decompressed = decompress_tagged::<Document>(legacy, None).unwrap();
+            assert_eq!(decompressed.as_ref(), expected.as_slice());
+        }
+    }
+
+    #[test]
+    fn tagged_values_start_with_the_marker() {
+        let uncompressed = Bytes::from_vec(b"the quick brown fox ".repeat(16));
+
+        #[cfg(not(feature = "compress-dictionaries"))]
+        let compressed = compress_tagged::<Blob>(uncompressed.clone()).unwrap();
+        #[cfg(feature = "compress-dictionaries")]
+        let compressed = compress_tagged::<Blob>(uncompressed.clone(), None).unwrap();
+        assert!(compressed.as_ref().starts_with(&MARKER));
+
+        let owned = Bytes::from_vec(compressed.as_ref().to_vec());
+        #[cfg(not(feature = "compress-dictionaries"))]
+        let decompressed = decompress_tagged::<Blob>(owned).unwrap();
+        #[cfg(feature = "compress-dictionaries")]
+        let decompressed = decompress_tagged::<Blob>(owned, None).unwrap();
+        assert_eq!(decompressed.as_ref(), uncompressed.as_ref());
+    }
+}
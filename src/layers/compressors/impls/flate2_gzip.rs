@@ -3,7 +3,9 @@
 //! crate's gzip implementation.
 
 use crate::layers::compressors::impls::RESERVATION_FACTOR;
-use crate::layers::compressors::{Compressible, Compressor, Level, Method};
+use crate::layers::compressors::{
+    Compressible, CompressError, Compressor, DecompressError, Level, Method
+};
 use crate::layers::core::Bytes;
 use flate2::Compression;
 use std::io::{Read, Write};
@@ -74,8 +76,8 @@ impl<'b, V: Compressible> Compressor<'b, V> for Gzip<V> {
             Vec::with_capacity(uncompressed_bytes.len()),
             compression_level::<V>()
         );
-        encoder.write_all(&uncompressed_bytes)?;
-        Ok(encoder.finish()?.into())
+        encoder.write_all(&uncompressed_bytes).map_err(|source| CompressError::Gzip { source })?;
+        Ok(encoder.finish().map_err(|source| CompressError::Gzip { source })?.into())
     }
 
     /// Restores compressed data to its original form, expanding the encoded data to the original
@@ -101,7 +103,8 @@ impl<'b, V: Compressible> Compressor<'b, V> for Gzip<V> {
         let mut decompressed_bytes = Vec::with_capacity(
             compressed_bytes.len().saturating_mul(RESERVATION_FACTOR)
         );
-        decoder.read_to_end(&mut decompressed_bytes)?;
+        decoder.read_to_end(&mut decompressed_bytes)
+            .map_err(|source| DecompressError::Gzip { source })?;
         Ok(decompressed_bytes.into())
     }
 }
\ No newline at end of file
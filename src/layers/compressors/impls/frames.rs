@@ -0,0 +1,145 @@
+//! Chunked compression of large values, in fixed-size frames.
+//!
+//! Compressing a multi-hundred-megabyte value as a single buffer makes the compressor hold its
+//! entire working state, and a second copy of the output, at once. Types that set
+//! [`Compressible::CHUNK_SIZE`] have values larger than the chunk size split into frames, which are
+//! compressed one at a time. Each frame is tagged on its own, so frames that don't shrink are
+//! stored as they are.
+//!
+//! The frame lengths are kept in the tail, after the frames:
+//!
+//! | `frames`     | `frame_lens`      | `frame_count` |
+//! |--------------|-------------------|---------------|
+//! | `[&[u8]; N]` | `[u32; N]` (LE)   | `u32` (LE)    |
+//!
+//! [`Compressible::CHUNK_SIZE`]: crate::layers::compressors::Compressible::CHUNK_SIZE
+
+use crate::layers::compressors::{CompressError, DecompressError};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// Size of each integer in the tail: every frame length, and the frame count.
+const LEN_SIZE: usize = size_of::<u32>();
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Splits `data` into frames of `chunk_size` bytes, compresses each frame with `compress_frame`,
+/// and appends the frame lengths and frame count.
+///
+/// # Errors
+///
+/// * A compressed frame, or the number of frames, doesn't fit in a `u32`.
+///
+/// * Errors returned by `compress_frame`.
+pub(super) fn compress_frames(
+    data: &[u8],
+    chunk_size: usize,
+    mut compress_frame: impl FnMut(&[u8]) -> Result<Vec<u8>, CompressError>
+) -> Result<Vec<u8>, CompressError> {
+    let mut frames = Vec::with_capacity(data.len());
+    let mut frame_lens = Vec::new();
+
+    for chunk in data.chunks(chunk_size.max(1)) {
+        let frame = compress_frame(chunk)?;
+        let frame_len = u32::try_from(frame.len())
+            .map_err(|_| CompressError::FrameTooLarge { len: frame.len() })?;
+        frame_lens.push(frame_len);
+        frames.extend_from_slice(&frame);
+    }
+
+    let frame_count = u32::try_from(frame_lens.len())
+        .map_err(|_| CompressError::FrameTooLarge { len: frame_lens.len() })?;
+    frames.reserve((frame_lens.len() + 1) * LEN_SIZE);
+    for frame_len in frame_lens {
+        frames.extend_from_slice(&frame_len.to_le_bytes());
+    }
+    frames.extend_from_slice(&frame_count.to_le_bytes());
+
+    Ok(frames)
+}
+
+/// Reads the frame lengths from the tail of `data`, decompresses each frame with
+/// `decompress_frame`, and joins the decompressed frames.
+///
+/// # Errors
+///
+/// * Returns [`DecompressError::MalformedFrames`] if the tail is truncated, or the frame lengths
+///   don't add up to the data's length.
+///
+/// * Errors returned by `decompress_frame`.
+pub(super) fn decompress_frames(
+    data: &[u8],
+    mut decompress_frame: impl FnMut(&[u8]) -> Result<Vec<u8>, DecompressError>
+) -> Result<Vec<u8>, DecompressError> {
+    let (rest, frame_count) = split_len(data)?;
+    let tail_len = usize::try_from(frame_count)
+        .ok()
+        .and_then(|frame_count| frame_count.checked_mul(LEN_SIZE))
+        .filter(|tail_len| *tail_len <= rest.len())
+        .ok_or(DecompressError::MalformedFrames)?;
+    let (frames, frame_lens) = rest.split_at(rest.len() - tail_len);
+
+    let mut decompressed = Vec::with_capacity(frames.len());
+    let mut offset = 0_usize;
+    for frame_len in frame_lens.chunks_exact(LEN_SIZE) {
+        let frame_len = u32::from_le_bytes(frame_len.try_into().unwrap_or_default()) as usize;
+        let frame = offset
+            .checked_add(frame_len)
+            .and_then(|end| frames.get(offset..end))
+            .ok_or(DecompressError::MalformedFrames)?;
+        decompressed.extend_from_slice(&decompress_frame(frame)?);
+        offset += frame_len;
+    }
+
+    if offset == frames.len() {
+        Ok(decompressed)
+    } else {
+        Err(DecompressError::MalformedFrames)
+    }
+}
+
+/// Splits the trailing little-endian `u32` off of `data`.
+fn split_len(data: &[u8]) -> Result<(&[u8], u32), DecompressError> {
+    let split = data.len().checked_sub(LEN_SIZE).ok_or(DecompressError::MalformedFrames)?;
+    let (rest, len) = data.split_at(split);
+    Ok((rest, u32::from_le_bytes(len.try_into().unwrap_or_default())))
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_round_trip() {
+        let data = (0..1_000_u16).flat_map(u16::to_le_bytes).collect::<Vec<u8>>();
+        let framed = compress_frames(&data, 300, |frame| Ok(frame.to_vec())).unwrap();
+        assert_eq!(framed.len(), data.len() + 8 * LEN_SIZE);
+
+        let mut frame_count = 0;
+        let restored = decompress_frames(&framed, |frame| {
+            frame_count += 1;
+            Ok(frame.to_vec())
+        }).unwrap();
+        assert_eq!(restored, data);
+        assert_eq!(frame_count, 7);
+    }
+
+    #[test]
+    fn malformed_frames_are_rejected() {
+        let framed = compress_frames(&[1, 2, 3, 4, 5], 2, |frame| Ok(frame.to_vec())).unwrap();
+        for len in [0, 3, framed.len() - 1] {
+            assert!(matches!(
+                decompress_frames(&framed[..len], |frame| Ok(frame.to_vec())),
+                Err(DecompressError::MalformedFrames)
+            ));
+        }
+    }
+}
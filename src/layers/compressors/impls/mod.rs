@@ -1,5 +1,9 @@
-//! `Compressor` data compression implementations. A single implementation is selected at
-//! compile-time in the host application's `Cargo.toml` file.
+//! `Compressor` data compression implementations. Any number of implementations may be enabled in
+//! the host application's `Cargo.toml` file. Values are tagged with the compressor that wrote them,
+//! so each value is decompressed with the right one, see the [`dispatch`] module.
+//!
+//! The first enabled compressor, in `Method` order, is the `ActiveCompressor` and the default
+//! compressor that values are written with.
 
 // -------------------------------------------------------------------------------------------------
 //
@@ -29,12 +33,14 @@ const RESERVATION_FACTOR: usize = 4; // `4` = reserve 4 times the compressed siz
 // Compressor Feature Guard
 
 /// Helper macro: counts how many of the listed features are turned on.
+#[cfg(feature = "compress-dictionaries")]
 macro_rules! count_features {
     ($($feat:literal),* $(,)?) => {
         0_usize $(+ cfg!(feature = $feat) as usize)*
     };
 }
 
+#[cfg(feature = "compress-dictionaries")]
 const _COMPRESSOR_FEATURE_COUNT: usize = count_features!(
     "compress-brotli",
     "compress-bzip2",
@@ -45,16 +51,16 @@ const _COMPRESSOR_FEATURE_COUNT: usize = count_features!(
     "compress-zstd",
 );
 
+#[cfg(feature = "compress-dictionaries")]
 const _: () = {
     assert!(
-        // Only one compressor feature can be enabled. To fix: 1. open `Cargo.toml` file, 2. find
-        // `[dependencies]` section and where `atlatl` is, 3. ensure only one compressor is enabled.
+        // Dictionaries are specific to a single compressor, so only one compressor feature can be
+        // enabled alongside `compress-dictionaries`. To fix: 1. open `Cargo.toml` file, 2. find
+        // `[dependencies]` section and where `atlatl` is, 3. ensure only one compressor is enabled,
+        // or disable `compress-dictionaries`.
         !(_COMPRESSOR_FEATURE_COUNT > 1),
-        "Multiple compressor features enabled! Please enable only one of: \
-        `compress-brotli`, \
-        `compress-bzip2`, \
-        `compress-deflate`, \
-        `compress-gzip`, \
+        "Multiple compressor features enabled with `compress-dictionaries`! Please enable only one \
+        of: \
         `compress-lz4`, \
         `compress-zlib`, or \
         `compress-zstd`",
@@ -75,41 +81,74 @@ pub use crate::layers::compressors::impls::brotli::Brotli as ActiveCompressor;
 #[cfg(feature = "compress-bzip2")]
 mod bzip2;
 
-#[cfg(feature = "compress-bzip2")]
+#[cfg(all(feature = "compress-bzip2", not(feature = "compress-brotli")))]
 /// `BZip2` has been selected as the `ActiveCompressor` using `Cargo.toml` feature.
 pub use crate::layers::compressors::impls::bzip2::Bzip2 as ActiveCompressor;
 
 #[cfg(feature = "compress-deflate")]
 mod flate2_deflate;
 
-#[cfg(feature = "compress-deflate")]
+#[cfg(all(feature = "compress-deflate", not(any(
+    feature = "compress-brotli",
+    feature = "compress-bzip2",
+))))]
 /// `Deflate` has been selected as the `ActiveCompressor` using `Cargo.toml` feature.
 pub use crate::layers::compressors::impls::flate2_deflate::Deflate as ActiveCompressor;
 
 #[cfg(feature = "compress-gzip")]
 mod flate2_gzip;
 
-#[cfg(feature = "compress-gzip")]
+#[cfg(all(feature = "compress-gzip", not(any(
+    feature = "compress-brotli",
+    feature = "compress-bzip2",
+    feature = "compress-deflate",
+))))]
 /// `Gzip` has been selected as the `ActiveCompressor` using `Cargo.toml` feature.
 pub use crate::layers::compressors::impls::flate2_gzip::Gzip as ActiveCompressor;
 
 #[cfg(feature = "compress-lz4")]
 mod lz4_flex;
 
-#[cfg(feature = "compress-lz4")]
+#[cfg(all(feature = "compress-lz4", not(any(
+    feature = "compress-brotli",
+    feature = "compress-bzip2",
+    feature = "compress-deflate",
+    feature = "compress-gzip",
+))))]
 /// `Lz4Flex` has been selected as the `ActiveCompressor` using `Cargo.toml` feature.
 pub use crate::layers::compressors::impls::lz4_flex::Lz4Flex as ActiveCompressor;
 
 #[cfg(feature = "compress-zlib")]
 mod flate2_zlib;
 
-#[cfg(feature = "compress-zlib")]
+#[cfg(all(feature = "compress-zlib", not(any(
+    feature = "compress-brotli",
+    feature = "compress-bzip2",
+    feature = "compress-deflate",
+    feature = "compress-gzip",
+    feature = "compress-lz4",
+))))]
 /// `Zlib` has been selected as the `ActiveCompressor` using `Cargo.toml` feature.
 pub use crate::layers::compressors::impls::flate2_zlib::Zlib as ActiveCompressor;
 
 #[cfg(feature = "compress-zstd")]
 pub(super) mod zstd;
 
-#[cfg(feature = "compress-zstd")]
+#[cfg(all(feature = "compress-zstd", not(any(
+    feature = "compress-brotli",
+    feature = "compress-bzip2",
+    feature = "compress-deflate",
+    feature = "compress-gzip",
+    feature = "compress-lz4",
+    feature = "compress-zlib",
+))))]
 /// `Zstd` has been selected as the `ActiveCompressor` using `Cargo.toml` feature.
-pub use crate::layers::compressors::impls::zstd::Zstd as ActiveCompressor;
\ No newline at end of file
+pub use crate::layers::compressors::impls::zstd::Zstd as ActiveCompressor;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Runtime Dispatch
+
+pub mod dispatch;
+
+mod frames;
@@ -1,7 +1,9 @@
 //! Compression implementation with support for dictionaries.
 
 use crate::layers::compressors::impls::zstd::{compression_level, MAX_CAPACITY, Zstd};
-use crate::layers::compressors::{Compressible, Compressor, DictionaryBytes, Method};
+use crate::layers::compressors::{
+    Compressible, CompressError, Compressor, DecompressError, DictionaryBytes, Method
+};
 use crate::layers::core::Bytes;
 
 // -------------------------------------------------------------------------------------------------
@@ -37,15 +39,18 @@ impl<'b, 'd, V: Compressible> Compressor<'b, 'd, V> for Zstd<V> {
         dictionary: Option<DictionaryBytes<'d>>
     ) -> Result<Bytes<'b>, crate::layers::compressors::CompressError> {
         if let Some(dictionary) = dictionary {
-            let mut compressor = zstd::bulk::Compressor::new(compression_level::<V>())?;
-            compressor.set_prepared_dictionary(dictionary.as_encoder_dict())?;
-            let compressed_bytes = compressor.compress(uncompressed_bytes.as_slice())?;
+            let mut compressor = zstd::bulk::Compressor::new(compression_level::<V>())
+                .map_err(|source| CompressError::Zstd { source })?;
+            compressor.set_prepared_dictionary(dictionary.as_encoder_dict())
+                .map_err(|source| CompressError::Zstd { source })?;
+            let compressed_bytes = compressor.compress(uncompressed_bytes.as_slice())
+                .map_err(|source| CompressError::Zstd { source })?;
             Ok(compressed_bytes.into())
         } else {
             let compressed_bytes = zstd::bulk::compress(
                 uncompressed_bytes.as_slice(),
                 compression_level::<V>()
-            )?;
+            ).map_err(|source| CompressError::Zstd { source })?;
             Ok(compressed_bytes.into())
         }
     }
@@ -78,14 +83,16 @@ impl<'b, 'd, V: Compressible> Compressor<'b, 'd, V> for Zstd<V> {
         if let Some(dictionary) = dictionary {
             let mut compressor = zstd::bulk::Decompressor::with_prepared_dictionary(
                 dictionary.as_decoder_dict()
-            )?;
+            ).map_err(|source| DecompressError::Zstd { source })?;
             let decompressed_bytes = compressor.decompress(
                 compressed_bytes.as_slice(),
                 MAX_CAPACITY
-            )?;
+            ).map_err(|source| DecompressError::Zstd { source })?;
             Ok(decompressed_bytes.into())
         } else {
-            Ok(zstd::bulk::decompress(compressed_bytes.as_ref(), MAX_CAPACITY)?.into())
+            let decompressed_bytes = zstd::bulk::decompress(compressed_bytes.as_ref(), MAX_CAPACITY)
+                .map_err(|source| DecompressError::Zstd { source })?;
+            Ok(decompressed_bytes.into())
         }
     }
 }
\ No newline at end of file
@@ -1,7 +1,7 @@
 //! Standard compression implementation with no support for dictionaries.
 
 use crate::layers::compressors::impls::zstd::{compression_level, MAX_CAPACITY, Zstd};
-use crate::layers::compressors::{Compressible, Compressor, Method};
+use crate::layers::compressors::{Compressible, CompressError, Compressor, DecompressError, Method};
 use crate::layers::core::Bytes;
 
 // -------------------------------------------------------------------------------------------------
@@ -34,7 +34,7 @@ impl<'b, V: Compressible> Compressor<'b, V> for Zstd<V> {
         let compressed_bytes = zstd::bulk::compress(
             uncompressed_bytes.as_slice(),
             compression_level::<V>()
-        )?;
+        ).map_err(|source| CompressError::Zstd { source })?;
         Ok(compressed_bytes.into())
     }
 
@@ -57,6 +57,8 @@ impl<'b, V: Compressible> Compressor<'b, V> for Zstd<V> {
     fn decompress(
         compressed_bytes: Bytes<'b>
     ) -> Result<Bytes<'b>, crate::layers::compressors::DecompressError> {
-        Ok(zstd::bulk::decompress(compressed_bytes.as_ref(), MAX_CAPACITY)?.into())
+        let decompressed_bytes = zstd::bulk::decompress(compressed_bytes.as_ref(), MAX_CAPACITY)
+            .map_err(|source| DecompressError::Zstd { source })?;
+        Ok(decompressed_bytes.into())
     }
 }
\ No newline at end of file
@@ -21,4 +21,6 @@ pub use crate::layers::compressors::core::DictionaryBytes;
 // Compressor Implementations
 
 mod impls;
-pub use crate::layers::compressors::impls::ActiveCompressor;
\ No newline at end of file
+pub use crate::layers::compressors::impls::ActiveCompressor;
+pub use crate::layers::compressors::impls::dispatch;
+pub use crate::layers::compressors::impls::dispatch::{set_write_method, write_method};
\ No newline at end of file
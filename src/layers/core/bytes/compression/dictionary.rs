@@ -1,6 +1,8 @@
-use crate::layers::compressors::{ActiveCompressor, DictionaryBytes};
-use crate::layers::core::{bytes::Error, Bytes};
-use crate::layers::{Compressible, Compressor};
+use crate::layers::compressors::dispatch::{compress_tagged, decompress_tagged};
+use crate::layers::compressors::DictionaryBytes;
+use crate::layers::core::bytes::{measure, Error};
+use crate::layers::core::{Bytes, Direction, Layer};
+use crate::layers::Compressible;
 
 // -------------------------------------------------------------------------------------------------
 //
@@ -38,7 +40,13 @@ impl Bytes<'_> {
         dictionary: Option<DictionaryBytes<'_>>
     ) -> Result<Self, Error> {
         if V::DIRECTION.is_write() {
-            Ok(ActiveCompressor::<V>::compress(self, dictionary)?)
+            Ok(measure(
+                Layer::Compression,
+                Direction::OnWrite,
+                self.len(),
+                || compress_tagged::<V>(self, dictionary),
+                Bytes::len,
+            )?)
         } else {
             Ok(self)
         }
@@ -73,7 +81,13 @@ impl Bytes<'_> {
         dictionary: Option<DictionaryBytes<'_>>
     ) -> Result<Self, Error> {
         if V::DIRECTION.is_read() {
-            Ok(ActiveCompressor::<V>::decompress(self, dictionary)?)
+            Ok(measure(
+                Layer::Compression,
+                Direction::OnRead,
+                self.len(),
+                || decompress_tagged::<V>(self, dictionary),
+                Bytes::len,
+            )?)
         } else {
             Ok(self)
         }
@@ -1,6 +1,7 @@
-use crate::layers::compressors::ActiveCompressor;
-use crate::layers::core::{bytes::Error, Bytes};
-use crate::layers::{Compressible, Compressor};
+use crate::layers::compressors::dispatch::{compress_tagged, decompress_tagged};
+use crate::layers::core::bytes::{measure, Error};
+use crate::layers::core::{Bytes, Direction, Layer};
+use crate::layers::Compressible;
 
 // -------------------------------------------------------------------------------------------------
 //
@@ -28,7 +29,13 @@ impl Bytes<'_> {
         self
     ) -> Result<Self, Error> {
         if V::DIRECTION.is_write() {
-            Ok(ActiveCompressor::<V>::compress(self)?)
+            Ok(measure(
+                Layer::Compression,
+                Direction::OnWrite,
+                self.len(),
+                || compress_tagged::<V>(self),
+                Bytes::len,
+            )?)
         } else {
             Ok(self)
         }
@@ -55,7 +62,13 @@ impl Bytes<'_> {
         self
     ) -> Result<Self, Error> {
         if V::DIRECTION.is_read() {
-            Ok(ActiveCompressor::<V>::decompress(self)?)
+            Ok(measure(
+                Layer::Compression,
+                Direction::OnRead,
+                self.len(),
+                || decompress_tagged::<V>(self),
+                Bytes::len,
+            )?)
         } else {
             Ok(self)
         }
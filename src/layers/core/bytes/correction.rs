@@ -1,4 +1,5 @@
-use crate::layers::core::{bytes::Error, Bytes};
+use crate::layers::core::bytes::{measure, Error};
+use crate::layers::core::{Bytes, Direction, Layer};
 use crate::layers::correctors::ActiveCorrector;
 use crate::layers::{Correctable, Corrector};
 
@@ -27,7 +28,13 @@ impl Bytes<'_> {
         self
     ) -> Result<Self, Error> {
         if V::DIRECTION.is_write() {
-            Ok(ActiveCorrector::<V>::protect(self)?)
+            Ok(measure(
+                Layer::Correction,
+                Direction::OnWrite,
+                self.len(),
+                || ActiveCorrector::<V>::protect(self),
+                Bytes::len,
+            )?)
         } else {
             Ok(self)
         }
@@ -37,7 +44,8 @@ impl Bytes<'_> {
     /// the embedded parity information, or returns the original data if no corruption is found.
     ///
     /// This method may return the original buffer untouched if no corruption is detected, or may
-    /// allocate a repaired version if any issues are found.
+    /// allocate a repaired version if any issues are found. Every repair is reported as a
+    /// [`RepairEvent`](crate::layers::correctors::RepairEvent).
     ///
     /// # Errors
     ///
@@ -57,7 +65,20 @@ impl Bytes<'_> {
         self
     ) -> Result<Self, Error> {
         if V::DIRECTION.is_read() {
-            Ok(ActiveCorrector::<V>::recover(self)?)
+            let bytes = measure(
+                Layer::Correction,
+                Direction::OnRead,
+                self.len(),
+                || ActiveCorrector::<V>::recover(self),
+                Bytes::len,
+            )?;
+            if let Some(shards_recovered) = bytes.shards_recovered() {
+                crate::layers::correctors::report_repair(
+                    shards_recovered,
+                    <ActiveCorrector::<V> as Corrector<V>>::METHOD
+                );
+            }
+            Ok(bytes)
         } else {
             Ok(self)
         }
@@ -0,0 +1,121 @@
+//! Per-layer diagnostics for a stored value that's read back through its read layers, used to
+//! salvage what's left of corrupted values.
+
+use crate::layers::core::Layer;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// What happened when a stored value was passed through one read layer.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LayerState {
+    /// The layer isn't applied on reads for this value type.
+    Skipped,
+
+    /// The layer was applied successfully.
+    Passed,
+
+    /// Error correction rebuilt corrupted shards, and the layer was then applied successfully.
+    Repaired {
+        /// The number of corrupted shards that were rebuilt.
+        shards_recovered: usize,
+    },
+
+    /// The layer failed.
+    Failed {
+        /// A description of the layer's error.
+        error: String,
+    },
+
+    /// An earlier layer failed, so this layer wasn't attempted.
+    NotReached,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The state of a single read layer, see [`Diagnostics`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LayerDiagnostic {
+    /// The read layer.
+    pub layer: Layer,
+
+    /// What happened when the value was passed through the layer.
+    pub state: LayerState,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Diagnostics for a stored value that was read back through its read layers, returned by
+/// [`Bytes::diagnose_read_layers`](crate::layers::core::Bytes::diagnose_read_layers).
+///
+/// Unlike a normal read, diagnosis never stops at an error: every layer's state is reported, and
+/// the bytes the failing layer was given are kept so they can be recovered by hand.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostics {
+    /// The state of every read layer, in the order they're applied during reads. This is the
+    /// reverse of the value type's [`LayerStack`](crate::layers::core::LayerStack) order, followed
+    /// by serialization. By default: correction, encryption, compression, then serialization.
+    pub layers: Vec<LayerDiagnostic>,
+
+    /// The checksum state of every error correction shard, as found on disk before any repair was
+    /// attempted. `None` if the value isn't protected by error correction.
+    #[cfg(feature = "correctors")]
+    pub shard_health: Option<crate::layers::correctors::ShardHealth>,
+
+    /// The bytes that were passed to the failing layer. For example, if decompression failed,
+    /// these are the decrypted bytes. If every layer passed, these are the serialized value's
+    /// bytes.
+    pub salvaged: Vec<u8>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Diagnostics {
+    /// Returns diagnostics where no layer has been reached yet, given the order that the layers
+    /// before deserialization are read in.
+    pub(crate) fn new(read_order: [Layer; 3]) -> Self {
+        Self {
+            layers: read_order
+                .into_iter()
+                .chain([Layer::Serialization])
+                .map(|layer| LayerDiagnostic { layer, state: LayerState::NotReached })
+                .collect(),
+            #[cfg(feature = "correctors")]
+            shard_health: None,
+            salvaged: Vec::new(),
+        }
+    }
+
+    /// Records the state of a read layer.
+    pub(crate) fn set(&mut self, layer: Layer, state: LayerState) {
+        if let Some(diagnostic) = self.layers.iter_mut().find(|diagnostic| diagnostic.layer == layer) {
+            diagnostic.state = state;
+        }
+    }
+
+    /// Returns the state of a read layer.
+    #[must_use]
+    pub fn state(&self, layer: Layer) -> Option<&LayerState> {
+        self.layers
+            .iter()
+            .find(|diagnostic| diagnostic.layer == layer)
+            .map(|diagnostic| &diagnostic.state)
+    }
+
+    /// Returns the read layer that failed, if any did.
+    #[must_use]
+    pub fn failed_layer(&self) -> Option<Layer> {
+        self.layers
+            .iter()
+            .find(|diagnostic| matches!(diagnostic.state, LayerState::Failed { .. }))
+            .map(|diagnostic| diagnostic.layer)
+    }
+
+    /// Returns `true` if every read layer passed or was skipped, meaning the value can be read
+    /// normally.
+    #[must_use]
+    pub fn is_readable(&self) -> bool {
+        self.failed_layer().is_none()
+    }
+}
@@ -1,6 +1,10 @@
-use crate::layers::core::{bytes::Error, Bytes};
-use crate::layers::encryptors::{ActiveEncryptor, KeyBytes, Nonce};
+use crate::layers::core::bytes::{measure, Error};
+use crate::layers::core::{Bytes, Direction, Layer};
+use crate::layers::encryptors::{ActiveEncryptor, AssociatedData, DecryptError, KeyBytes};
+use crate::layers::encryptors::{KeyId, KeyRingProvider, Nonce};
 use crate::layers::{Encryptable, Encryptor};
+use std::borrow::Cow;
+use zeroize::Zeroize;
 
 // -------------------------------------------------------------------------------------------------
 //
@@ -27,6 +31,9 @@ impl Bytes<'_> {
     ///
     /// * `key` · The secret encryption key used to transform the data.
     ///
+    /// * `associated_data` · The table and key the data is stored under. They're authenticated
+    ///   but not stored, so the same context must be given to decrypt the data.
+    ///
     /// # Errors
     ///
     /// Consult the documentation of the encryptor backend you are using for more detail on
@@ -42,10 +49,17 @@ impl Bytes<'_> {
     pub fn encrypt<V: Encryptable>(
         self,
         key: KeyBytes<'_>,
+        associated_data: &AssociatedData<'_>,
         nonce: Option<Nonce<'_>>
     ) -> Result<Self, Error> {
         if V::DIRECTION.is_write() {
-            Ok(ActiveEncryptor::<V>::encrypt(self, key, nonce)?)
+            Ok(measure(
+                Layer::Encryption,
+                Direction::OnWrite,
+                self.len(),
+                || ActiveEncryptor::<V>::encrypt(self, key, associated_data, nonce),
+                Bytes::len,
+            )?)
         } else {
             Ok(self)
         }
@@ -59,11 +73,14 @@ impl Bytes<'_> {
     /// * `key` · The same secret key used during encryption, required to reverse the
     ///   transformation.
     ///
+    /// * `associated_data` · The same table and key the data was encrypted with.
+    ///
     /// # Errors
     ///
     /// This method may fail for several reasons, including:
     ///
-    /// * Invalid key, or
+    /// * Invalid key,
+    /// * The data was encrypted for another table or key, or
     /// * Input bytes are corrupted or malformed.
     ///
     /// Consult the documentation of the encryptor backend you are using for more detail on
@@ -78,10 +95,59 @@ impl Bytes<'_> {
     #[inline]
     pub fn decrypt<V: Encryptable>(
         self,
-        key: KeyBytes<'_>
+        key: KeyBytes<'_>,
+        associated_data: &AssociatedData<'_>,
+    ) -> Result<Self, Error> {
+        if V::DIRECTION.is_read() {
+            Ok(measure(
+                Layer::Encryption,
+                Direction::OnRead,
+                self.len(),
+                || ActiveEncryptor::<V>::decrypt(self, key, associated_data),
+                Bytes::len,
+            )?)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Wipes the buffer's bytes from memory, if they're owned, and drops it. Used to wipe plain
+    /// text once it's been encrypted. Borrowed bytes are left to their owner.
+    pub(crate) fn wipe(self) {
+        if let (_, Cow::Owned(mut bytes)) = self.into_parts() {
+            bytes.zeroize();
+        }
+    }
+
+    /// Decrypts the data with whichever key of the key ring it was encrypted with, as recorded by
+    /// the key ID in its encryption layer parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `key_ring` · The keys the data may have been encrypted with.
+    ///
+    /// * `associated_data` · The same table and key the data was encrypted with.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DecryptError::UnknownKey`] if the data was encrypted with a key that isn't in the
+    /// key ring. Otherwise, see [`Bytes::decrypt`].
+    #[inline]
+    pub fn decrypt_with<V: Encryptable>(
+        self,
+        key_ring: &impl KeyRingProvider,
+        associated_data: &AssociatedData<'_>,
     ) -> Result<Self, Error> {
         if V::DIRECTION.is_read() {
-        	Ok(ActiveEncryptor::<V>::decrypt(self, key)?)
+            let key_id = KeyId::of_cipher_text(self.as_slice())?;
+            let key = key_ring.key(key_id).ok_or(DecryptError::UnknownKey { key_id })?;
+            Ok(measure(
+                Layer::Encryption,
+                Direction::OnRead,
+                self.len(),
+                || ActiveEncryptor::<V>::decrypt(self, key, associated_data),
+                Bytes::len,
+            )?)
         } else {
             Ok(self)
         }
@@ -228,6 +228,31 @@ pub enum Error {
         configured_encryptor: crate::layers::encryptors::Method
     },
 
+    /// Failed to sign data during the signing layer processing.
+    ///
+    /// This typically indicates:
+    /// * Invalid signing key: The provided key is malformed.
+    ///
+    /// Troubleshooting steps:
+    /// 1. Verify the signing key was generated for the configured signing method.
+    #[cfg(feature = "signers")]
+    #[error("signing failed")]
+    Sign { #[from] #[source] source: crate::layers::signers::SignError },
+
+    /// Failed to verify the signature of data during the signing layer processing.
+    ///
+    /// Common causes include:
+    /// * Wrong verifying key: The key doesn't belong to the signing key used by the writer.
+    /// * Modified data: The value was tampered with, or corrupted, after it was signed.
+    /// * Unsigned data: The value was written before signing was enabled for its type.
+    ///
+    /// Troubleshooting steps:
+    /// 1. Verify you're using the verifying key of the writer's signing key.
+    /// 2. Check that the signed data hasn't been modified or corrupted.
+    #[cfg(feature = "signers")]
+    #[error("signature verification failed")]
+    Verify { #[from] #[source] source: crate::layers::signers::VerifyError },
+
     /// Failed to protect data.
     ///
     /// This can occur due to:
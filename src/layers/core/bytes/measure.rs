@@ -0,0 +1,152 @@
+//! The single point where each layer's work on a value is measured.
+//!
+//! With the `metrics` feature, every layer that's applied to a value records its duration and
+//! sizes, see [`crate::layers::metrics`]. With the `tracing-spans` feature, it's also wrapped in an
+//! `atlatl.layer` span, which nests under the span of the table operation or query that read or
+//! wrote the value. Without either, layers are applied as they are.
+
+use crate::layers::core::{Direction, Layer};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Applies one layer to a value, measuring the time it takes and the size of its output.
+///
+/// # Errors
+///
+/// Returns the layer's error.
+#[inline]
+#[cfg_attr(
+    not(any(feature = "metrics", feature = "tracing-spans")),
+    allow(unused_variables, reason = "layers are only measured with `metrics` or `tracing-spans`")
+)]
+pub fn measure<T, E>(
+    layer: Layer,
+    direction: Direction,
+    input_len: usize,
+    apply: impl FnOnce() -> Result<T, E>,
+    output_len: impl FnOnce(&T) -> usize,
+) -> Result<T, E> {
+    #[cfg(feature = "tracing-spans")]
+    let span = tracing::trace_span!(
+        "atlatl.layer",
+        layer = %layer,
+        operation = if direction.is_write() { "write" } else { "read" },
+        input_len,
+        output_len = tracing::field::Empty,
+    )
+    .entered();
+
+    #[cfg(feature = "metrics")]
+    let started = crate::layers::core::Stopwatch::start();
+
+    let result = apply();
+
+    #[cfg(any(feature = "metrics", feature = "tracing-spans"))]
+    let output_len = result.as_ref().ok().map(output_len);
+
+    #[cfg(feature = "tracing-spans")]
+    if let Some(output_len) = output_len {
+        span.record("output_len", output_len);
+    }
+
+    #[cfg(feature = "metrics")]
+    crate::layers::metrics::record_layer(
+        layer,
+        direction,
+        input_len,
+        output_len,
+        started.elapsed(),
+    );
+
+    result
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    use crate::layers::metrics::*;
+    use metrics::{Counter, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit};
+    use std::sync::Mutex;
+
+    /// Remembers the name of every metric that's recorded, and discards their values.
+    #[derive(Default)]
+    struct Names(Mutex<Vec<String>>);
+
+    impl Names {
+        fn push(&self, key: &Key) {
+            self.0.lock().unwrap().push(key.name().to_string());
+        }
+
+        fn contains(&self, name: &str) -> bool {
+            self.0.lock().unwrap().iter().any(|recorded| recorded == name)
+        }
+    }
+
+    impl Recorder for Names {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            self.push(key);
+            Counter::noop()
+        }
+
+        fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+            self.push(key);
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+            self.push(key);
+            Histogram::noop()
+        }
+    }
+
+    #[test]
+    fn measured_layers_are_recorded() {
+        let names = Names::default();
+        metrics::with_local_recorder(&names, || {
+            let compressed = measure(
+                Layer::Compression,
+                Direction::OnWrite,
+                100,
+                || Ok::<_, ()>(vec![0_u8; 25]),
+                Vec::len,
+            );
+            assert_eq!(compressed.unwrap().len(), 25);
+        });
+
+        assert!(names.contains(LAYER_DURATION_SECONDS));
+        assert!(names.contains(LAYER_INPUT_BYTES));
+        assert!(names.contains(LAYER_OUTPUT_BYTES));
+        assert!(names.contains(COMPRESSION_RATIO));
+        assert!(!names.contains(LAYER_ERRORS));
+
+        metrics::with_local_recorder(&names, || {
+            measure(
+                Layer::Encryption,
+                Direction::OnRead,
+                100,
+                || Err::<Vec<u8>, _>(()),
+                Vec::len,
+            ).unwrap_err();
+        });
+
+        assert!(names.contains(LAYER_ERRORS));
+
+        let totals = layer_totals();
+        let compression = totals
+            .iter()
+            .find(|totals| totals.layer == "compression" && totals.operation == "write")
+            .unwrap();
+        assert!(compression.values >= 1);
+        assert!(compression.output_bytes >= 25);
+    }
+}
@@ -22,8 +22,22 @@ mod encryption;
 #[cfg(feature = "serializers")]
 mod serialization;
 
+#[cfg(feature = "signers")]
+mod signing;
+
+mod measure;
+pub use crate::layers::core::bytes::measure::measure;
+
 // mod tests;
 mod read;
+
+mod diagnostics;
+pub use crate::layers::core::bytes::diagnostics::{Diagnostics, LayerDiagnostic, LayerState};
+
+mod verify;
+pub use crate::layers::core::bytes::verify::{LayerFailure, Verified};
+
+#[cfg(feature = "writes")]
 mod write;
 
 // -------------------------------------------------------------------------------------------------
@@ -83,6 +97,20 @@ impl<'b> Bytes<'b> {
         self.data.as_ref()
     }
 
+    /// Returns `true` if error correction was used to repair the bytes while they were read.
+    #[must_use] pub fn was_recovered(&self) -> bool {
+        self.shards_recovered().is_some()
+    }
+
+    /// Returns the number of corrupted shards that were rebuilt, if error correction was used to
+    /// repair the bytes while they were read.
+    #[must_use] pub fn shards_recovered(&self) -> Option<usize> {
+        self.metadata.corrector
+            .as_ref()
+            .filter(|corrector| corrector.was_recovered())
+            .map(crate::layers::correctors::Metadata::shards_recovered)
+    }
+
     /// Unwraps a `Bytes` buffer into the underlying `Cow<[u8]>` bytes, discarding the metadata.
     #[must_use] pub fn into_bytes(self) -> Cow<'b, [u8]> {
         self.into()
@@ -167,9 +195,14 @@ impl<'b> Bytes<'b> {
 
     /// Instantiates a `Bytes` buffer from an owned `Vec<u8>` and automatically marks the data as
     /// “recovered.”
-    #[must_use] pub(crate) fn from_recovered_data(recovered_data: Vec<u8>) -> Self {
+    #[must_use] pub(crate) fn from_recovered_data(
+        recovered_data: Vec<u8>,
+        shards_recovered: usize
+    ) -> Self {
         Bytes {
-            metadata: Metadata::default(),
+            metadata: Metadata {
+                corrector: Some(crate::layers::correctors::Metadata::recovered(shards_recovered))
+            },
             data: recovered_data.into()
         }
     }
@@ -1,11 +1,16 @@
 use crate::layers::{
     Compressible,
+    core::{apply_in_order, read_order},
     core::Bytes,
-    core::Value,
+    core::Layer,
+    core::LayerStack,
     core::ValueOrBytes,
+    core::bytes::{Diagnostics, LayerFailure, LayerState, Verified},
+    correctors::ActiveCorrector,
+    Corrector,
     Correctable,
     Encryptable,
-    encryptors::KeyBytes,
+    encryptors::{AssociatedData, KeyBytes},
     Serializable,
     Serializer,
     core::bytes::Error,
@@ -18,6 +23,10 @@ use crate::layers::compressors::DictionaryBytes;
 //
 // Method Implementations
 
+#[cfg_attr(
+    not(feature = "compress-dictionaries"),
+    allow(clippy::extra_unused_lifetimes, reason = "`'d` is only used by dictionary methods")
+)]
 impl<'b, 'k, 'd> Bytes<'b> {
     /// # Generics & Lifetimes
     ///
@@ -28,23 +37,32 @@ impl<'b, 'k, 'd> Bytes<'b> {
     ///
     /// * 'd' lifetime represents a dictionary potentially being borrowed from a `Dictionary` or
     ///   `DictionaryProvider`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the read layers fail: ECC recovery, decryption, decompression, or
+    /// deserialization. The layers are removed in the reverse of `V`'s [`LayerStack`] order.
     #[cfg(feature = "compress-dictionaries")]
     pub fn apply_read_layers<V>(
         value_buf: Self,
         key: KeyBytes<'k>,
+        associated_data: &AssociatedData<'_>,
         dictionary: Option<DictionaryBytes<'d>>
     ) -> Result<ValueOrBytes<'b, V>, Error>
     where V:
         Correctable +
         Encryptable +
         Compressible +
+        LayerStack +
         Serializer::<'b, V> + Serializable,
     {
-        let value_or_bytes = value_buf
-            .recover::<V>()?
-            .decrypt::<V>(key)?
-            .decompress::<V>(dictionary)?
-            .deserialize::<V>()?;
+        let value_or_bytes = apply_in_order(
+            value_buf,
+            read_order::<V>(),
+            |bytes| bytes.decompress::<V>(dictionary),
+            |bytes| bytes.decrypt::<V>(key, associated_data),
+            Self::recover::<V>,
+        )?.deserialize::<V>()?;
 
         Ok(value_or_bytes)
     }
@@ -55,23 +73,645 @@ impl<'b, 'k, 'd> Bytes<'b> {
     ///
     /// * 'k' lifetime represents a key potentially being borrowed from a `KeyRing` or
     ///   `KeyProvider`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the read layers fail: ECC recovery, decryption, decompression, or
+    /// deserialization. The layers are removed in the reverse of `V`'s [`LayerStack`] order.
     #[cfg(not(feature = "compress-dictionaries"))]
     pub fn apply_read_layers<V>(
         value_buf: Self,
         key: KeyBytes<'k>,
+        associated_data: &AssociatedData<'_>,
     ) -> Result<ValueOrBytes<'b, V>, Error>
     where V:
         Correctable +
         Encryptable +
         Compressible +
+        LayerStack +
         Serializer::<'b, V> + Serializable,
     {
-        let value_or_bytes = value_buf
-            .recover::<V>()?
-            .decrypt::<V>(key)?
-            .decompress::<V>()?
-            .deserialize::<V>()?;
+        let value_or_bytes = apply_in_order(
+            value_buf,
+            read_order::<V>(),
+            Self::decompress::<V>,
+            |bytes| bytes.decrypt::<V>(key, associated_data),
+            Self::recover::<V>,
+        )?.deserialize::<V>()?;
+
+        Ok(value_or_bytes)
+    }
+
+    /// Applies the read layers of record type `V`, but deserializes into a lighter projection type
+    /// `P` rather than the full record.
+    ///
+    /// ECC recovery, decryption, and decompression are performed using `V`'s layer settings, since
+    /// that's how the bytes were written. Only the final deserialization step uses `P`. This lets
+    /// list endpoints decode, for example, a `UserSummary { id, name }` from a `User` record without
+    /// allocating its larger fields.
+    ///
+    /// `P` must be decodable from `V`'s serialized form. With `serde`-based serializers this is
+    /// typically a struct with the same fields as `V`, in the same order, where every field that
+    /// isn't needed is declared as [`serde::de::IgnoredAny`]. Ignored fields are skipped over
+    /// without being decoded or allocated:
+    ///
+    /// ```rust,ignore
+    /// #[derive(serde::Deserialize)]
+    /// struct UserSummary {
+    ///     id: u64,
+    ///     name: String,
+    ///     biography: serde::de::IgnoredAny,
+    /// }
+    /// ```
+    ///
+    /// # Generics & Lifetimes
+    ///
+    /// * `V` generic represents the user's value type, for example: `User`, `String`, etc.
+    ///
+    /// * `P` generic represents the projection type, for example: `UserSummary`.
+    ///
+    /// * 'k' lifetime represents a key potentially being borrowed from a `KeyRing` or
+    ///   `KeyProvider`.
+    ///
+    /// * 'd' lifetime represents a dictionary potentially being borrowed from a `Dictionary` or
+    ///   `DictionaryProvider`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the read layers fail: ECC recovery, decryption, decompression, or
+    /// deserialization into `P`.
+    #[cfg(feature = "compress-dictionaries")]
+    pub fn apply_read_layers_as<V, P>(
+        value_buf: Self,
+        key: KeyBytes<'k>,
+        associated_data: &AssociatedData<'_>,
+        dictionary: Option<DictionaryBytes<'d>>
+    ) -> Result<ValueOrBytes<'b, P>, Error>
+    where
+        V: Correctable + Encryptable + Compressible + LayerStack,
+        P: Serializer::<'b, P> + Serializable,
+    {
+        let value_or_bytes = apply_in_order(
+            value_buf,
+            read_order::<V>(),
+            |bytes| bytes.decompress::<V>(dictionary),
+            |bytes| bytes.decrypt::<V>(key, associated_data),
+            Self::recover::<V>,
+        )?.deserialize::<P>()?;
+
+        Ok(value_or_bytes)
+    }
+
+    /// Applies the read layers of record type `V`, but deserializes into a lighter projection type
+    /// `P` rather than the full record.
+    ///
+    /// ECC recovery, decryption, and decompression are performed using `V`'s layer settings, since
+    /// that's how the bytes were written. Only the final deserialization step uses `P`.
+    ///
+    /// `P` must be decodable from `V`'s serialized form. With `serde`-based serializers this is
+    /// typically a struct with the same fields as `V`, in the same order, where every field that
+    /// isn't needed is declared as [`serde::de::IgnoredAny`].
+    ///
+    /// # Generics & Lifetimes
+    ///
+    /// * `V` generic represents the user's value type, for example: `User`, `String`, etc.
+    ///
+    /// * `P` generic represents the projection type, for example: `UserSummary`.
+    ///
+    /// * 'k' lifetime represents a key potentially being borrowed from a `KeyRing` or
+    ///   `KeyProvider`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the read layers fail: ECC recovery, decryption, decompression, or
+    /// deserialization into `P`.
+    #[cfg(not(feature = "compress-dictionaries"))]
+    pub fn apply_read_layers_as<V, P>(
+        value_buf: Self,
+        key: KeyBytes<'k>,
+        associated_data: &AssociatedData<'_>,
+    ) -> Result<ValueOrBytes<'b, P>, Error>
+    where
+        V: Correctable + Encryptable + Compressible + LayerStack,
+        P: Serializer::<'b, P> + Serializable,
+    {
+        let value_or_bytes = apply_in_order(
+            value_buf,
+            read_order::<V>(),
+            Self::decompress::<V>,
+            |bytes| bytes.decrypt::<V>(key, associated_data),
+            Self::recover::<V>,
+        )?.deserialize::<P>()?;
 
         Ok(value_or_bytes)
     }
-}
\ No newline at end of file
+
+    /// Reads a stored value back through every read layer, as [`Bytes::apply_read_layers`] does,
+    /// but discards the value. This is used to check stored values for corruption without
+    /// mutating anything.
+    ///
+    /// # Generics & Lifetimes
+    ///
+    /// * `V` generic represents the user's value type, for example: `User`, `String`, etc.
+    ///
+    /// * 'k' lifetime represents a key potentially being borrowed from a `KeyRing` or
+    ///   `KeyProvider`.
+    ///
+    /// * 'd' lifetime represents a dictionary potentially being borrowed from a `Dictionary` or
+    ///   `DictionaryProvider`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`LayerFailure`] naming the first read layer that failed: ECC recovery,
+    /// decryption, decompression, or deserialization.
+    #[cfg(feature = "compress-dictionaries")]
+    pub fn verify_read_layers<V>(
+        value_buf: Self,
+        key: KeyBytes<'k>,
+        associated_data: &AssociatedData<'_>,
+        dictionary: Option<DictionaryBytes<'d>>
+    ) -> Result<Verified, LayerFailure>
+    where V:
+        Correctable +
+        Encryptable +
+        Compressible +
+        LayerStack +
+        Serializer::<'b, V> + Serializable + 'b,
+    {
+        let mut shards_recovered = None;
+        apply_in_order(
+            value_buf,
+            read_order::<V>(),
+            |bytes| bytes
+                .decompress::<V>(dictionary)
+                .map_err(LayerFailure::at(Layer::Compression)),
+            |bytes| bytes
+                .decrypt::<V>(key, associated_data)
+                .map_err(LayerFailure::at(Layer::Encryption)),
+            |bytes| {
+                let recovered =
+                    bytes.recover::<V>().map_err(LayerFailure::at(Layer::Correction))?;
+                shards_recovered = recovered.shards_recovered();
+                Ok(recovered)
+            },
+        )?
+            .deserialize::<V>()
+            .map_err(LayerFailure::at(Layer::Serialization))?;
+
+        Ok(Verified { shards_recovered })
+    }
+
+    /// Reads a stored value back through every read layer, as [`Bytes::apply_read_layers`] does,
+    /// but discards the value. This is used to check stored values for corruption without
+    /// mutating anything.
+    ///
+    /// # Generics & Lifetimes
+    ///
+    /// * `V` generic represents the user's value type, for example: `User`, `String`, etc.
+    ///
+    /// * 'k' lifetime represents a key potentially being borrowed from a `KeyRing` or
+    ///   `KeyProvider`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`LayerFailure`] naming the first read layer that failed: ECC recovery,
+    /// decryption, decompression, or deserialization.
+    #[cfg(not(feature = "compress-dictionaries"))]
+    pub fn verify_read_layers<V>(
+        value_buf: Self,
+        key: KeyBytes<'k>,
+        associated_data: &AssociatedData<'_>,
+    ) -> Result<Verified, LayerFailure>
+    where V:
+        Correctable +
+        Encryptable +
+        Compressible +
+        LayerStack +
+        Serializer::<'b, V> + Serializable + 'b,
+    {
+        let mut shards_recovered = None;
+        apply_in_order(
+            value_buf,
+            read_order::<V>(),
+            |bytes| bytes
+                .decompress::<V>()
+                .map_err(LayerFailure::at(Layer::Compression)),
+            |bytes| bytes
+                .decrypt::<V>(key, associated_data)
+                .map_err(LayerFailure::at(Layer::Encryption)),
+            |bytes| {
+                let recovered =
+                    bytes.recover::<V>().map_err(LayerFailure::at(Layer::Correction))?;
+                shards_recovered = recovered.shards_recovered();
+                Ok(recovered)
+            },
+        )?
+            .deserialize::<V>()
+            .map_err(LayerFailure::at(Layer::Serialization))?;
+
+        Ok(Verified { shards_recovered })
+    }
+
+    /// Reads a stored value back through every read layer, reporting the state of each layer
+    /// rather than stopping at the first error. Used to salvage what's left of corrupted values.
+    ///
+    /// The returned [`Diagnostics`] include the checksum state of every error correction shard, and
+    /// the bytes that were passed to the failing layer, which can be recovered by hand.
+    ///
+    /// # Generics & Lifetimes
+    ///
+    /// * `V` generic represents the user's value type, for example: `User`, `String`, etc.
+    ///
+    /// * 'k' lifetime represents a key potentially being borrowed from a `KeyRing` or
+    ///   `KeyProvider`.
+    ///
+    /// * 'd' lifetime represents a dictionary potentially being borrowed from a `Dictionary` or
+    ///   `DictionaryProvider`.
+    #[cfg(feature = "compress-dictionaries")]
+    #[must_use]
+    pub fn diagnose_read_layers<V>(
+        value_buf: Self,
+        key: KeyBytes<'k>,
+        associated_data: &AssociatedData<'_>,
+        dictionary: Option<DictionaryBytes<'d>>
+    ) -> Diagnostics
+    where V:
+        Correctable +
+        Encryptable +
+        Compressible +
+        LayerStack +
+        Serializer::<'b, V> + Serializable + 'b,
+    {
+        Self::diagnose::<V>(value_buf, key, associated_data, |bytes| {
+            bytes.decompress::<V>(dictionary)
+        })
+    }
+
+    /// Reads a stored value back through every read layer, reporting the state of each layer
+    /// rather than stopping at the first error. Used to salvage what's left of corrupted values.
+    ///
+    /// The returned [`Diagnostics`] include the checksum state of every error correction shard, and
+    /// the bytes that were passed to the failing layer, which can be recovered by hand.
+    ///
+    /// # Generics & Lifetimes
+    ///
+    /// * `V` generic represents the user's value type, for example: `User`, `String`, etc.
+    ///
+    /// * 'k' lifetime represents a key potentially being borrowed from a `KeyRing` or
+    ///   `KeyProvider`.
+    #[cfg(not(feature = "compress-dictionaries"))]
+    #[must_use]
+    pub fn diagnose_read_layers<V>(
+        value_buf: Self,
+        key: KeyBytes<'k>,
+        associated_data: &AssociatedData<'_>,
+    ) -> Diagnostics
+    where V:
+        Correctable +
+        Encryptable +
+        Compressible +
+        LayerStack +
+        Serializer::<'b, V> + Serializable + 'b,
+    {
+        Self::diagnose::<V>(value_buf, key, associated_data, Self::decompress::<V>)
+    }
+
+    /// Diagnoses each read layer in turn. See [`Bytes::diagnose_read_layers`].
+    fn diagnose<V>(
+        value_buf: Self,
+        key: KeyBytes<'k>,
+        associated_data: &AssociatedData<'_>,
+        decompress: impl FnOnce(Self) -> Result<Self, Error>,
+    ) -> Diagnostics
+    where V:
+        Correctable +
+        Encryptable +
+        Compressible +
+        LayerStack +
+        Serializer::<'b, V> + Serializable + 'b,
+    {
+        let mut diagnostics = Diagnostics::new(read_order::<V>());
+        let mut key = Some(key);
+        let mut decompress = Some(decompress);
+
+        let mut bytes = value_buf;
+        for layer in read_order::<V>() {
+            let output = match layer {
+                Layer::Correction => {
+                    let is_protected = <V as Correctable>::DIRECTION.is_read();
+                    if is_protected {
+                        diagnostics.shard_health =
+                            <ActiveCorrector<V> as Corrector<V>>::inspect(&bytes.data);
+                    }
+                    Self::diagnose_layer(
+                        &mut diagnostics,
+                        layer,
+                        is_protected,
+                        bytes,
+                        Self::recover::<V>,
+                    )
+                },
+                Layer::Encryption => match key.take() {
+                    Some(key) => Self::diagnose_layer(
+                        &mut diagnostics,
+                        layer,
+                        <V as Encryptable>::DIRECTION.is_read(),
+                        bytes,
+                        |bytes| bytes.decrypt::<V>(key, associated_data),
+                    ),
+                    None => Some(bytes),
+                },
+                Layer::Compression => match decompress.take() {
+                    Some(decompress) => Self::diagnose_layer(
+                        &mut diagnostics,
+                        layer,
+                        <V as Compressible>::DIRECTION.is_read(),
+                        bytes,
+                        decompress,
+                    ),
+                    None => Some(bytes),
+                },
+                _ => Some(bytes),
+            };
+            let Some(output) = output else { return diagnostics };
+            bytes = output;
+        }
+
+        let state = if <V as Serializable>::DIRECTION.is_read() {
+            match bytes.clone().deserialize::<V>() {
+                Ok(_value) => LayerState::Passed,
+                Err(error) => LayerState::Failed { error: describe(&error) },
+            }
+        } else {
+            LayerState::Skipped
+        };
+        diagnostics.set(Layer::Serialization, state);
+        diagnostics.salvaged = bytes.data.into_owned();
+        diagnostics
+    }
+
+    /// Applies a single read layer, recording its state. Returns `None` if the layer failed, in
+    /// which case the bytes it was given are kept as the salvaged bytes.
+    fn diagnose_layer(
+        diagnostics: &mut Diagnostics,
+        layer: Layer,
+        is_applied: bool,
+        bytes: Self,
+        apply: impl FnOnce(Self) -> Result<Self, Error>,
+    ) -> Option<Self> {
+        if !is_applied {
+            diagnostics.set(layer, LayerState::Skipped);
+            return Some(bytes);
+        }
+
+        let input = bytes.clone();
+        match apply(bytes) {
+            Ok(output) => {
+                let state = match output.shards_recovered() {
+                    Some(shards_recovered) if layer == Layer::Correction =>
+                        LayerState::Repaired { shards_recovered },
+                    _ => LayerState::Passed,
+                };
+                diagnostics.set(layer, state);
+                Some(output)
+            },
+            Err(error) => {
+                diagnostics.set(layer, LayerState::Failed { error: describe(&error) });
+                diagnostics.salvaged = input.data.into_owned();
+                None
+            },
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Describes a read layer's error along with the chain of errors that caused it, for example:
+/// `"decryption failed: aead::Error"`.
+fn describe(error: &Error) -> String {
+    let mut description = error.to_string();
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        description.push_str(": ");
+        description.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    description
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "serialize-messagepack", feature = "compress-dictionaries"))]
+mod tests {
+    use crate::layers::core::{Bytes, Direction, Layer, LayerState, Value};
+    use crate::layers::encryptors::{AssociatedData, KeyBytes};
+    use crate::layers::{Compressible, Correctable, Encryptable, LayerStack, Serializable};
+
+    const CONTEXT: AssociatedData<'static> = AssociatedData::new("users", b"7");
+
+    #[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+    struct User {
+        id: u64,
+        name: String,
+        biography: String,
+    }
+
+    #[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+    struct UserSummary {
+        id: u64,
+        name: String,
+        #[serde(skip_serializing)]
+        biography: serde::de::IgnoredAny,
+    }
+
+    #[cfg(feature = "serde-safety")]
+    unsafe impl crate::layers::serializers::SafeForSerde for User {}
+
+    #[cfg(feature = "serde-safety")]
+    unsafe impl crate::layers::serializers::SafeForSerde for UserSummary {}
+
+    impl Serializable for User {
+        const DIRECTION: Direction = Direction::Both;
+    }
+
+    impl Compressible for User {
+        const DIRECTION: Direction = Direction::Both;
+        const LEVEL: crate::layers::compressors::Level = crate::layers::compressors::Level::Maximum;
+    }
+
+    impl Encryptable for User {
+        const DIRECTION: Direction = Direction::Both;
+    }
+
+    impl Correctable for User {
+        const DIRECTION: Direction = Direction::Both;
+        const LEVEL: crate::layers::correctors::Level = crate::layers::correctors::Level::Maximum;
+    }
+
+    impl LayerStack for User {}
+
+    /// A user whose cipher text is protected, and encrypted again on top of its parity data.
+    #[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+    struct SealedUser(User);
+
+    #[cfg(feature = "serde-safety")]
+    unsafe impl crate::layers::serializers::SafeForSerde for SealedUser {}
+
+    impl Serializable for SealedUser {
+        const DIRECTION: Direction = Direction::Both;
+    }
+
+    impl Compressible for SealedUser {
+        const DIRECTION: Direction = Direction::Both;
+        const LEVEL: crate::layers::compressors::Level = crate::layers::compressors::Level::Maximum;
+    }
+
+    impl Encryptable for SealedUser {
+        const DIRECTION: Direction = Direction::Both;
+    }
+
+    impl Correctable for SealedUser {
+        const DIRECTION: Direction = Direction::Both;
+        const LEVEL: crate::layers::correctors::Level = crate::layers::correctors::Level::Maximum;
+    }
+
+    impl LayerStack for SealedUser {
+        const ORDER: [Layer; 3] = [Layer::Compression, Layer::Correction, Layer::Encryption];
+    }
+
+    impl Serializable for UserSummary {
+        const DIRECTION: Direction = Direction::Both;
+    }
+
+    #[cfg(feature = "writes")]
+    #[test]
+    fn layers_are_read_in_the_types_order() {
+        let key: KeyBytes<'static> = b"SECURE_32_BYTE_KEY______________".into();
+        let wrong_key: KeyBytes<'static> = b"WRONG_32_BYTE_KEY_______________".into();
+        let user =
+            SealedUser(User { id: 7, name: "Ariadne".to_string(), biography: String::new() });
+
+        let buf = Bytes::apply_write_layers(&user, (*key).into(), &CONTEXT, None, None).unwrap();
+        let decoded = Bytes::apply_read_layers::<SealedUser>(buf.clone(), key, &CONTEXT, None)
+            .unwrap()
+            .try_into_value()
+            .unwrap();
+        match decoded {
+            Value::Borrowed(decoded) => assert_eq!(decoded, &user),
+            Value::Owned(decoded) => assert_eq!(decoded, user),
+        }
+
+        let diagnostics = Bytes::diagnose_read_layers::<SealedUser>(buf, wrong_key, &CONTEXT, None);
+        let read_order = diagnostics.layers.iter().map(|diagnostic| diagnostic.layer);
+        assert!(read_order.eq([
+            Layer::Encryption,
+            Layer::Correction,
+            Layer::Compression,
+            Layer::Serialization,
+        ]));
+        assert_eq!(diagnostics.failed_layer(), Some(Layer::Encryption));
+        assert_eq!(diagnostics.state(Layer::Correction), Some(&LayerState::NotReached));
+    }
+
+    #[cfg(feature = "writes")]
+    #[test]
+    fn verification_names_the_failing_layer() {
+        let key: KeyBytes<'static> = b"SECURE_32_BYTE_KEY______________".into();
+        let wrong_key: KeyBytes<'static> = b"WRONG_32_BYTE_KEY_______________".into();
+        let user = User { id: 7, name: "Ariadne".to_string(), biography: String::new() };
+
+        let buf = Bytes::apply_write_layers(&user, (*key).into(), &CONTEXT, None, None).unwrap();
+        let verified = Bytes::verify_read_layers::<User>(buf.clone(), key, &CONTEXT, None).unwrap();
+        assert_eq!(verified.shards_recovered, None);
+
+        let failure =
+            Bytes::verify_read_layers::<User>(buf, wrong_key, &CONTEXT, None).unwrap_err();
+        assert_eq!(failure.layer, Layer::Encryption);
+    }
+
+    #[cfg(feature = "writes")]
+    #[test]
+    fn values_cannot_be_moved_to_another_record() {
+        let key: KeyBytes<'static> = b"SECURE_32_BYTE_KEY______________".into();
+        let user = User { id: 7, name: "Ariadne".to_string(), biography: String::new() };
+        let buf = Bytes::apply_write_layers(&user, (*key).into(), &CONTEXT, None, None).unwrap();
+
+        let other_record = AssociatedData::new("users", b"8");
+        let failure = Bytes::verify_read_layers::<User>(buf, key, &other_record, None).unwrap_err();
+        assert_eq!(failure.layer, Layer::Encryption);
+    }
+
+    #[cfg(feature = "writes")]
+    #[test]
+    fn reencryption_moves_values_to_the_new_key() {
+        let old_key: KeyBytes<'static> = b"SECURE_32_BYTE_KEY______________".into();
+        let new_key: KeyBytes<'static> = b"ROTATED_32_BYTE_KEY_____________".into();
+        let user = User { id: 7, name: "Ariadne".to_string(), biography: String::new() };
+        let buf = Bytes::apply_write_layers(&user, (*old_key).into(), &CONTEXT, None, None)
+            .unwrap();
+
+        let rotated =
+            Bytes::reencrypt::<User>(buf, (*old_key).into(), (*new_key).into(), &CONTEXT).unwrap();
+        let failure = Bytes::verify_read_layers::<User>(rotated.clone(), old_key, &CONTEXT, None)
+            .unwrap_err();
+        assert_eq!(failure.layer, Layer::Encryption);
+        let decoded = Bytes::apply_read_layers::<User>(rotated, new_key, &CONTEXT, None)
+            .unwrap()
+            .try_into_value()
+            .unwrap();
+        match decoded {
+            Value::Borrowed(decoded) => assert_eq!(decoded, &user),
+            Value::Owned(decoded) => assert_eq!(decoded, user),
+        }
+    }
+
+    #[cfg(feature = "writes")]
+    #[test]
+    fn diagnosis_salvages_the_failing_layers_input() {
+        let key: KeyBytes<'static> = b"SECURE_32_BYTE_KEY______________".into();
+        let user = User { id: 7, name: "Ariadne".to_string(), biography: "Navigator. ".repeat(50) };
+        let buf = Bytes::apply_write_layers(&user, (*key).into(), &CONTEXT, None, None).unwrap();
+
+        let diagnostics = Bytes::diagnose_read_layers::<User>(buf.clone(), key, &CONTEXT, None);
+        assert!(diagnostics.is_readable());
+        assert!(diagnostics.shard_health.unwrap().is_intact());
+
+        let wrong_key: KeyBytes<'static> = b"WRONG_32_BYTE_KEY_______________".into();
+        let diagnostics = Bytes::diagnose_read_layers::<User>(buf, wrong_key, &CONTEXT, None);
+        assert_eq!(diagnostics.failed_layer(), Some(Layer::Encryption));
+        assert_eq!(diagnostics.state(Layer::Correction), Some(&LayerState::Passed));
+        assert_eq!(diagnostics.state(Layer::Compression), Some(&LayerState::NotReached));
+        assert!(!diagnostics.salvaged.is_empty());
+    }
+
+    #[cfg(feature = "writes")]
+    #[test]
+    fn projection_skips_ignored_fields() {
+        let key: KeyBytes<'static> = b"SECURE_32_BYTE_KEY______________".into();
+        let user = User {
+            id: 7,
+            name: "Ariadne".to_string(),
+            biography: "Navigator of labyrinths. ".repeat(100),
+        };
+
+        let buf = Bytes::apply_write_layers(&user, (*key).into(), &CONTEXT, None, None).unwrap();
+        let summary = Bytes::apply_read_layers_as::<User, UserSummary>(buf, key, &CONTEXT, None)
+            .unwrap()
+            .try_into_value()
+            .unwrap();
+
+        let expected = UserSummary {
+            id: 7,
+            name: "Ariadne".to_string(),
+            biography: serde::de::IgnoredAny,
+        };
+        match summary {
+            Value::Borrowed(summary) => assert_eq!(summary, &expected),
+            Value::Owned(summary) => assert_eq!(summary, expected),
+        }
+    }
+}
@@ -1,4 +1,6 @@
-use crate::layers::core::{bytes::Error, Bytes, Value, ValueOrBytes};
+use crate::layers::core::bytes::{measure, Error};
+use crate::layers::core::{Bytes, Direction, Layer, ValueOrBytes};
+use crate::layers::serializers::impls::dispatch::{deserialize_tagged, serialize_tagged};
 use crate::layers::{Serializable, Serializer};
 
 // -------------------------------------------------------------------------------------------------
@@ -22,10 +24,14 @@ impl<'b> Bytes<'b> {
         value_or_bytes: ValueOrBytes<'b, V>
     ) -> Result<Self, Error> {
         if V::DIRECTION.is_write() {
-            match value_or_bytes.try_into_value()? {
-                Value::Borrowed(value_ref) => Ok(<V>::serialize_ref(value_ref)?),
-                Value::Owned(value) => Ok(<V>::serialize(value)?),
-            }
+            let value = value_or_bytes.try_into_value()?;
+            Ok(measure(
+                Layer::Serialization,
+                Direction::OnWrite,
+                0,
+                || serialize_tagged(value),
+                Bytes::len,
+            )?)
         } else {
             Ok(value_or_bytes.try_into_bytes()?)
         }
@@ -42,7 +48,13 @@ impl<'b> Bytes<'b> {
         self
     ) -> Result<ValueOrBytes<'b, V>, Error> {
         if V::DIRECTION.is_read() {
-            Ok(V::deserialize(self)?.into())
+            Ok(measure(
+                Layer::Serialization,
+                Direction::OnRead,
+                self.len(),
+                || deserialize_tagged::<V>(self),
+                |_| 0,
+            )?.into())
         } else {
             Ok(self.into())
         }
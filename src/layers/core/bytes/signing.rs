@@ -0,0 +1,57 @@
+use crate::layers::core::{bytes::Error, Bytes};
+use crate::layers::signers::{ActiveSigner, SigningKeyBytes, VerifyingKeyBytes};
+use crate::layers::{Signable, Signer};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Bytes<'_> {
+    /// Signs the bytes with a private signing key, appending the signature to their tail.
+    ///
+    /// Signing is applied after encryption and before error correction, so that the signature
+    /// covers the bytes exactly as they're stored, and corruption can be repaired before the
+    /// signature is checked.
+    ///
+    /// # Errors
+    ///
+    /// Consult the documentation of the signer backend you are using for more detail on signing
+    /// and potential limitations.
+    ///
+    /// # Generics & Lifetimes
+    ///
+    /// * `V` generic represents the user's value type, for example: `User`, `String`, etc.
+    #[inline]
+    pub fn sign<V: Signable>(self, key: SigningKeyBytes<'_>) -> Result<Self, Error> {
+        if V::DIRECTION.is_write() {
+            Ok(ActiveSigner::<V>::sign(self, key)?)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Checks the signature at the tail of the bytes against a public verifying key, and removes
+    /// it.
+    ///
+    /// Verification is applied after error correction and before decryption.
+    ///
+    /// # Errors
+    ///
+    /// This method may fail for several reasons, including:
+    ///
+    /// * The bytes weren't signed,
+    /// * Invalid verifying key, or
+    /// * The bytes were modified after they were signed, or were signed with another key.
+    ///
+    /// # Generics & Lifetimes
+    ///
+    /// * `V` generic represents the user's value type, for example: `User`, `String`, etc.
+    #[inline]
+    pub fn verify<V: Signable>(self, key: VerifyingKeyBytes<'_>) -> Result<Self, Error> {
+        if V::DIRECTION.is_read() {
+            Ok(ActiveSigner::<V>::verify(self, key)?)
+        } else {
+            Ok(self)
+        }
+    }
+}
@@ -0,0 +1,40 @@
+//! Outcomes of verifying a stored value by reading it back through its read layers.
+
+use crate::layers::core::{bytes::Error, Layer};
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A stored value that was read back through every read layer.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Verified {
+    /// The number of corrupted shards that error correction rebuilt while the value was read, or
+    /// `None` if the value was intact.
+    pub shards_recovered: Option<usize>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A stored value that couldn't be read back, and the read layer that rejected it.
+#[derive(thiserror::Error, Debug)]
+#[error("{layer} layer failed")]
+pub struct LayerFailure {
+    /// The read layer that failed. For example, [`Layer::Encryption`] if the value failed its
+    /// authentication check.
+    pub layer: Layer,
+
+    /// The error returned by the read layer.
+    #[source]
+    pub source: Error,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl LayerFailure {
+    /// Returns a closure that attributes a read layer's error to that layer, for use with
+    /// `map_err`.
+    pub(crate) fn at(layer: Layer) -> impl Fn(Error) -> Self {
+        move |source| Self { layer, source }
+    }
+}
@@ -1,10 +1,14 @@
 use crate::layers::{
     Compressible,
+    core::{apply_in_order, position, write_order},
     core::Bytes,
+    core::Layer,
+    core::LayerStack,
     core::ValueOrBytes,
+    core::bytes::LayerFailure,
     Correctable,
     Encryptable,
-    encryptors::{KeyBytes, Nonce},
+    encryptors::{AssociatedData, KeyBytes, Nonce},
     Serializable,
     Serializer,
     core::bytes::Error,
@@ -17,6 +21,10 @@ use crate::layers::compressors::DictionaryBytes;
 //
 // Method Implementations
 
+#[cfg_attr(
+    not(feature = "compress-dictionaries"),
+    allow(clippy::extra_unused_lifetimes, reason = "`'d` is only used by dictionary methods")
+)]
 impl<'b, 'k, 'd> Bytes<'b> {
     /// # Generics & Lifetimes
     ///
@@ -27,10 +35,16 @@ impl<'b, 'k, 'd> Bytes<'b> {
     ///
     /// * 'd' lifetime represents a dictionary potentially being borrowed from a `Dictionary` or
     ///   `DictionaryProvider`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the write layers fail: serialization, compression, encryption, or
+    /// ECC protection. The layers after serialization are applied in `V`'s [`LayerStack`] order.
     #[cfg(feature = "compress-dictionaries")]
     pub fn apply_write_layers<V>(
         value_or_bytes: impl Into<ValueOrBytes<'b, V>>,
         key: KeyBytes<'k>,
+        associated_data: &AssociatedData<'_>,
         nonce: Option<Nonce<'k>>,
         dictionary: Option<DictionaryBytes<'d>>
     ) -> Result<Self, Error>
@@ -38,14 +52,18 @@ impl<'b, 'k, 'd> Bytes<'b> {
         Serializer::<'b, V> + Serializable +
         Compressible +
         Encryptable +
-        Correctable + 'b
+        Correctable +
+        LayerStack + 'b
     {
         let value_or_bytes: ValueOrBytes<V> = value_or_bytes.into();
 
-        Self::serialize(value_or_bytes)?
-            .compress::<V>(dictionary)?
-            .encrypt::<V>(key, nonce)?
-            .protect::<V>()
+        apply_in_order(
+            Self::serialize(value_or_bytes)?,
+            write_order::<V>(),
+            |bytes| bytes.compress::<V>(dictionary),
+            |bytes| bytes.encrypt::<V>(key, associated_data, nonce),
+            Self::protect::<V>,
+        )
     }
 
     /// # Generics & Lifetimes
@@ -54,23 +72,92 @@ impl<'b, 'k, 'd> Bytes<'b> {
     ///
     /// * 'k' lifetime represents a key potentially being borrowed from a `KeyRing` or
     ///   `KeyProvider`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the write layers fail: serialization, compression, encryption, or
+    /// ECC protection. The layers after serialization are applied in `V`'s [`LayerStack`] order.
     #[cfg(not(feature = "compress-dictionaries"))]
     pub fn apply_write_layers<V>(
         value_or_bytes: impl Into<ValueOrBytes<'b, V>>,
         key: KeyBytes<'k>,
+        associated_data: &AssociatedData<'_>,
         nonce: Option<Nonce<'k>>,
     ) -> Result<Self, Error>
     where V:
         Serializer::<'b, V> + Serializable +
         Compressible +
         Encryptable +
-        Correctable + 'b
+        Correctable +
+        LayerStack + 'b
     {
         let value_or_bytes: ValueOrBytes<V> = value_or_bytes.into();
 
-        Self::serialize(value_or_bytes)?
-            .compress::<V>()?
-            .encrypt::<V>(key, nonce)?
-            .protect::<V>()
+        apply_in_order(
+            Self::serialize(value_or_bytes)?,
+            write_order::<V>(),
+            Self::compress::<V>,
+            |bytes| bytes.encrypt::<V>(key, associated_data, nonce),
+            Self::protect::<V>,
+        )
+    }
+
+    /// Moves a stored value from one encryption key to another. The value is recovered, decrypted
+    /// with `old_key`, encrypted with `new_key` under a fresh nonce, and protected again. It's
+    /// never decompressed or deserialized.
+    ///
+    /// Error correction is only removed and added again if `V`'s [`LayerStack`] applies it after
+    /// encryption. Types that compress after encrypting fail to compile, since their cipher text
+    /// can't be reached without decompressing.
+    ///
+    /// # Generics & Lifetimes
+    ///
+    /// * `V` generic represents the user's value type, for example: `User`, `String`, etc.
+    ///
+    /// * 'k' lifetime represents a key potentially being borrowed from a `KeyRing` or
+    ///   `KeyProvider`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`LayerFailure`] naming the layer that failed: ECC recovery, decryption with the
+    /// old key, encryption with the new key, or ECC protection.
+    pub fn reencrypt<V>(
+        value_buf: Self,
+        old_key: KeyBytes<'k>,
+        new_key: KeyBytes<'k>,
+        associated_data: &AssociatedData<'_>,
+    ) -> Result<Self, LayerFailure>
+    where V:
+        Encryptable +
+        Correctable +
+        LayerStack
+    {
+        const {
+            assert!(
+                position(V::ORDER, Layer::Compression) < position(V::ORDER, Layer::Encryption),
+                "values that are compressed after they're encrypted can't be re-encrypted"
+            );
+        };
+        let order = write_order::<V>();
+        let is_protected_outside =
+            position(order, Layer::Correction) > position(order, Layer::Encryption);
+
+        let value_buf = if is_protected_outside {
+            value_buf.recover::<V>().map_err(LayerFailure::at(Layer::Correction))?
+        } else {
+            value_buf
+        };
+
+        let reencrypted = value_buf
+            .decrypt::<V>(old_key, associated_data)
+            .map_err(LayerFailure::at(Layer::Encryption))?
+            .encrypt::<V>(new_key, associated_data, None)
+            .map_err(LayerFailure::at(Layer::Encryption))?;
+
+        if is_protected_outside {
+            reencrypted.protect::<V>().map_err(LayerFailure::at(Layer::Correction))
+        } else {
+            Ok(reencrypted)
+        }
     }
 }
\ No newline at end of file
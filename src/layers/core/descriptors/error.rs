@@ -66,6 +66,14 @@ pub enum Error {
     )]
     UnrecognizedEncryptor(u8),
 
+    /// When reading a value from the database, an unrecognized signing method was encountered.
+    /// This may indicate data corruption or database version incompatibility.
+    #[error(
+        "unrecognized signing method: {0:?}, expected: \
+        \"0\" for ed25519"
+    )]
+    UnrecognizedSigner(u8),
+
     /// When reading a value from the database, an unrecognized error correction method was
     /// encountered. This may indicate data corruption or database version incompatibility.
     #[error(
@@ -9,6 +9,9 @@
 /// * Write Order: Serialization → Compression → Encryption → Error Correction
 /// * Read Order: Error Correction → Encryption → Compression → Serialization
 ///
+/// Serialization always comes first. A value type may reorder the other layers with
+/// [`LayerStack`](crate::layers::core::LayerStack).
+///
 /// Each layer pushes its identifier to the end of the data buffer after processing, creating a
 /// "layer stack" that enables precise error diagnostics and proper reverse processing during reads.
 ///
@@ -77,6 +80,24 @@ pub enum Layer {
     Correction    = 4, // 0100b
 }
 
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Layer {
+    /// Returns the layer's name, in lowercase. For example, `"encryption"`.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Raw           => "raw",
+            Self::Serialization => "serialization",
+            Self::Compression   => "compression",
+            Self::Encryption    => "encryption",
+            Self::Correction    => "correction",
+        }
+    }
+}
+
 // -------------------------------------------------------------------------------------------------
 //
 // Trait Implementations
@@ -104,13 +125,7 @@ impl TryFrom<&u8> for &'static Layer {
 impl std::fmt::Display for Layer {
     /// Formats the `Layer` as a human-readable string.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Raw           => write!(f, "raw"),
-            Self::Serialization => write!(f, "serialization"),
-            Self::Compression   => write!(f, "compression"),
-            Self::Encryption    => write!(f, "encryption"),
-            Self::Correction    => write!(f, "correction"),
-        }
+        f.write_str(self.name())
     }
 }
 
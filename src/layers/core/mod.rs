@@ -1,10 +1,24 @@
 mod bytes;
 pub use crate::layers::core::bytes::Bytes;
+pub use crate::layers::core::bytes::{Diagnostics, LayerDiagnostic, LayerState};
+pub use crate::layers::core::bytes::{LayerFailure, Verified};
 
 pub(crate) mod descriptors;
 pub use crate::layers::core::descriptors::Direction;
 pub use crate::layers::core::descriptors::Layer;
 
+mod stack;
+pub use crate::layers::core::stack::{DEFAULT_ORDER, LayerStack};
+pub(crate) use crate::layers::core::stack::{apply_in_order, read_order, write_order};
+#[cfg(feature = "writes")]
+pub(crate) use crate::layers::core::stack::position;
+
+#[cfg(any(feature = "writes", feature = "metrics"))]
+mod stopwatch;
+#[cfg(any(feature = "writes", feature = "metrics"))]
+pub(crate) use crate::layers::core::stopwatch::Stopwatch;
+
+#[cfg(any(feature = "correctors", feature = "encryptors", feature = "signers"))]
 pub(crate) mod tail_readers;
 
 mod value;
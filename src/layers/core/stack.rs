@@ -0,0 +1,161 @@
+//! The order that a type's layers are applied in.
+//!
+//! Serialization always comes first on writes, and last on reads. The compression, encryption, and
+//! error correction layers that follow it are applied in the order given by the type's
+//! [`LayerStack::ORDER`], and peeled off in the reverse order on reads.
+
+use crate::layers::core::Layer;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// The write order of types that don't choose their own: compression, encryption, then error
+/// correction.
+///
+/// Compressing before encrypting keeps values small, since cipher text doesn't compress, and
+/// protecting last lets error correction repair the cipher text itself.
+pub const DEFAULT_ORDER: [Layer; 3] = [Layer::Compression, Layer::Encryption, Layer::Correction];
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Declares the order that a value type's layers are applied in.
+///
+/// Some deployments must encrypt before compressing for policy reasons, while others need error
+/// correction to be the outermost layer so that it can repair any corruption on disk. Each type
+/// picks its own order:
+///
+/// ```rust,ignore
+/// impl LayerStack for MedicalRecord {
+///     const ORDER: [Layer; 3] = [Layer::Encryption, Layer::Compression, Layer::Correction];
+/// }
+/// ```
+///
+/// The order is validated at compile time: it must list [`Layer::Compression`],
+/// [`Layer::Encryption`], and [`Layer::Correction`] once each. A type with any other order fails to
+/// compile as soon as it's written or read.
+///
+/// # Notes
+///
+/// * The order is part of the storage format. Changing a type's order makes its existing values
+///   unreadable, so a table must be rewritten after its order changes.
+///
+/// * Values that are compressed after they're encrypted can't be moved to a new encryption key,
+///   since re-encryption doesn't decompress values. Rotating the keys of such a type fails to
+///   compile.
+pub trait LayerStack {
+    /// The compression, encryption, and error correction layers, in the order they're applied
+    /// during writes. Defaults to [`DEFAULT_ORDER`].
+    const ORDER: [Layer; 3] = DEFAULT_ORDER;
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Returns `V`'s write order, failing to compile if it's invalid.
+pub const fn write_order<V: LayerStack + ?Sized>() -> [Layer; 3] {
+    const {
+        assert!(
+            is_valid_order(V::ORDER),
+            "`LayerStack::ORDER` must list the compression, encryption, and correction layers once \
+            each"
+        );
+    };
+    V::ORDER
+}
+
+/// Returns `V`'s read order, which is its write order reversed.
+pub const fn read_order<V: LayerStack + ?Sized>() -> [Layer; 3] {
+    let [first, second, third] = write_order::<V>();
+    [third, second, first]
+}
+
+/// Returns the position of `layer` in `order`, or the order's length if it's missing.
+pub const fn position(order: [Layer; 3], layer: Layer) -> usize {
+    let mut index = 0;
+    while index < order.len() {
+        if order[index] as u8 == layer as u8 {
+            return index;
+        }
+        index += 1;
+    }
+    index
+}
+
+/// Returns `true` if `order` lists the compression, encryption, and correction layers once each.
+pub const fn is_valid_order(order: [Layer; 3]) -> bool {
+    position(order, Layer::Compression) < order.len()
+        && position(order, Layer::Encryption) < order.len()
+        && position(order, Layer::Correction) < order.len()
+}
+
+/// Passes `value` through the compression, encryption, and correction steps, in the given order.
+///
+/// # Errors
+///
+/// Returns the first error returned by a step. Later steps aren't run.
+pub fn apply_in_order<T, E>(
+    value: T,
+    order: [Layer; 3],
+    compression: impl FnOnce(T) -> Result<T, E>,
+    encryption: impl FnOnce(T) -> Result<T, E>,
+    correction: impl FnOnce(T) -> Result<T, E>,
+) -> Result<T, E> {
+    let (mut compression, mut encryption, mut correction) =
+        (Some(compression), Some(encryption), Some(correction));
+
+    order.into_iter().try_fold(value, |value, layer| match layer {
+        Layer::Compression => run(compression.take(), value),
+        Layer::Encryption => run(encryption.take(), value),
+        Layer::Correction => run(correction.take(), value),
+        _ => Ok(value),
+    })
+}
+
+/// Runs a step that hasn't been run yet, or passes `value` through if it already has.
+fn run<T, E>(step: Option<impl FnOnce(T) -> Result<T, E>>, value: T) -> Result<T, E> {
+    match step {
+        Some(step) => step(value),
+        None => Ok(value),
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct EncryptFirst;
+
+    impl LayerStack for EncryptFirst {
+        const ORDER: [Layer; 3] = [Layer::Encryption, Layer::Compression, Layer::Correction];
+    }
+
+    #[test]
+    fn layers_are_applied_in_the_declared_order() {
+        assert_eq!(
+            read_order::<EncryptFirst>(),
+            [Layer::Correction, Layer::Compression, Layer::Encryption]
+        );
+
+        let applied = apply_in_order::<_, ()>(
+            Vec::new(),
+            write_order::<EncryptFirst>(),
+            |mut applied| { applied.push(Layer::Compression); Ok(applied) },
+            |mut applied| { applied.push(Layer::Encryption); Ok(applied) },
+            |mut applied| { applied.push(Layer::Correction); Ok(applied) },
+        ).unwrap();
+        assert_eq!(applied, EncryptFirst::ORDER);
+    }
+
+    #[test]
+    fn orders_must_list_every_layer_once() {
+        assert!(is_valid_order(DEFAULT_ORDER));
+        assert!(!is_valid_order([Layer::Compression, Layer::Compression, Layer::Correction]));
+        assert!(!is_valid_order([Layer::Serialization, Layer::Encryption, Layer::Correction]));
+    }
+}
@@ -0,0 +1,33 @@
+//! Timing of layer work, for dry runs and metrics.
+//!
+//! `std::time::Instant` panics on `wasm32-unknown-unknown`, which has no clock without JavaScript
+//! bindings. There, every stopwatch reads zero, so that timing never stops a layer from running.
+
+use std::time::Duration;
+
+/// Whether the target has no clock that the standard library can read.
+const NO_CLOCK: bool = cfg!(all(target_arch = "wasm32", target_os = "unknown"));
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Measures the time since it was started, or reads zero on targets without a clock.
+#[derive(Clone, Copy, Debug)]
+pub struct Stopwatch(Option<std::time::Instant>);
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Stopwatch {
+    /// Starts a stopwatch at the current time.
+    #[must_use]
+    pub fn start() -> Self {
+        Self(if NO_CLOCK { None } else { Some(std::time::Instant::now()) })
+    }
+
+    /// Returns the time since the stopwatch was started, or zero on targets without a clock.
+    #[must_use]
+    pub fn elapsed(self) -> Duration {
+        self.0.map_or(Duration::ZERO, |started| started.elapsed())
+    }
+}
@@ -65,6 +65,21 @@ impl TailReaderMut {
         }
     }
 
+    /// Reads `marker` from the end of the data buffer, if the buffer ends with it, moving the
+    /// `position` backwards. Returns whether the marker was read. The `position` isn't moved if
+    /// the buffer doesn't end with the marker.
+    ///
+    /// This is useful for metadata whose layout has changed, where a marker identifies the newer
+    /// layout.
+    pub fn read_marker<const SIZE: usize>(&mut self, marker: &[u8; SIZE]) -> bool {
+        let is_marked = self.position >= SIZE
+            && self.data[self.position - SIZE..self.position] == *marker;
+        if is_marked {
+            self.position -= SIZE;
+        }
+        is_marked
+    }
+
     /// Closes the reader and returns the remaining unread bytes.
     ///
     /// This consumes the reader and returns a slice containing only the bytes that were not read,
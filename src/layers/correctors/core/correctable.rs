@@ -62,4 +62,31 @@ pub trait Correctable {
     /// * `V` generic represents the user's value type, for example: `User`, `String`, etc.
     /// * `b` lifetime represents bytes potentially being borrowed from the `redb` database.
     const LEVEL: crate::layers::correctors::Level;
+
+    /// Returns the number of parity shards for this type, as a percentage of its data shards.
+    ///
+    /// This overrides [`Correctable::LEVEL`] when set, so that critical tables can carry more
+    /// redundancy than bulk tables without picking an exact shard count. The number of parity
+    /// shards is rounded up, is at least 1, and is limited so that data and parity shards don't
+    /// exceed 256 in total.
+    ///
+    /// # Returns
+    ///
+    /// The percentage of parity shards, for example `50` for one parity shard for every two data
+    /// shards, or `None` to use [`Correctable::LEVEL`]. Defaults to `None`.
+    const PARITY_PERCENT: Option<u8> = None;
+
+    /// Returns how values of this type are divided into shards.
+    ///
+    /// The shard size is recorded with every protected value, so it can be changed without
+    /// affecting values that were already written.
+    ///
+    /// # Returns
+    ///
+    /// The [`ShardSize`] strategy for this type. Defaults to [`ShardSize::Automatic`].
+    ///
+    /// [`ShardSize`]: crate::layers::correctors::ShardSize
+    /// [`ShardSize::Automatic`]: crate::layers::correctors::ShardSize::Automatic
+    const SHARD_SIZE: crate::layers::correctors::ShardSize =
+        crate::layers::correctors::ShardSize::Automatic;
 }
\ No newline at end of file
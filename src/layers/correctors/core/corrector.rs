@@ -91,4 +91,20 @@ pub trait Corrector<'b, V: crate::layers::correctors::Correctable> {
     fn recover(
         protected_bytes: Bytes<'b>
     ) -> Result<Bytes<'b>, crate::layers::correctors::RecoverError>;
+
+    /// Checks the integrity of protected data without repairing it, returning the checksum state
+    /// of every shard.
+    ///
+    /// Returns `None` if the data isn't protected, or if its protection parameters are unreadable.
+    /// Defaults to `None` for correctors that don't divide data into shards.
+    ///
+    /// # Arguments
+    ///
+    /// * `protected_bytes` · Data that has been previously protected and may contain corruption.
+    #[must_use]
+    fn inspect(
+        _protected_bytes: &[u8]
+    ) -> Option<crate::layers::correctors::ShardHealth> {
+        None
+    }
 }
\ No newline at end of file
@@ -6,4 +6,33 @@ pub struct Metadata {
     /// This flag is used to determine when values ought to be re-written or re-committed to a
     /// `redb` database table.
     recovered: bool,
+
+    /// The number of data and parity shards that were found to be corrupted and were rebuilt
+    /// during recovery.
+    shards_recovered: usize,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Metadata {
+    /// Instantiates metadata for a value that was repaired using error correction.
+    #[must_use]
+    pub(crate) const fn recovered(shards_recovered: usize) -> Self {
+        Self { recovered: true, shards_recovered }
+    }
+
+    /// Returns `true` if error correction was used to recover corrupted data, meaning the value
+    /// ought to be re-written to the database.
+    #[must_use]
+    pub const fn was_recovered(&self) -> bool {
+        self.recovered
+    }
+
+    /// Returns the number of corrupted shards that were rebuilt during recovery.
+    #[must_use]
+    pub const fn shards_recovered(&self) -> usize {
+        self.shards_recovered
+    }
 }
\ No newline at end of file
@@ -18,4 +18,17 @@ mod metadata;
 pub use crate::layers::correctors::core::metadata::Metadata;
 
 mod method;
-pub use crate::layers::correctors::core::method::Method;
\ No newline at end of file
+pub use crate::layers::correctors::core::method::Method;
+
+mod repair;
+pub use crate::layers::correctors::core::repair::{
+    clear_repair_observer, set_repair_observer, with_repair_context, RepairEvent, RepairObserver,
+    RepairStats, RepairTotals
+};
+pub use crate::layers::correctors::core::repair::report_repair;
+
+mod shard_health;
+pub use crate::layers::correctors::core::shard_health::ShardHealth;
+
+mod shard_size;
+pub use crate::layers::correctors::core::shard_size::ShardSize;
@@ -0,0 +1,293 @@
+//! Read-repair events and per-table repair statistics.
+//!
+//! When error correction rebuilds corrupted shards on read, the repaired value is returned to the
+//! caller as if nothing had happened. That's convenient, but a disk that's slowly going bad will
+//! only show up in the logs. This module surfaces every repair as a structured [`RepairEvent`] to
+//! an application-supplied [`RepairObserver`], and keeps running per-table [`RepairStats`] in each
+//! database's [`RepairTotals`] so that operators can alert on tables that are being repaired
+//! unusually often.
+
+use crate::layers::correctors::Method;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Describes a single value that was repaired using error correction while being read.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RepairEvent {
+    /// Name of the table the value was read from, if the read was performed within a
+    /// [`with_repair_context`] scope.
+    pub table: Option<&'static str>,
+
+    /// Serialized key of the repaired value, if the read was performed within a
+    /// [`with_repair_context`] scope.
+    pub key: Option<Vec<u8>>,
+
+    /// The number of corrupted shards that were rebuilt.
+    pub shards_recovered: usize,
+
+    /// The error correction method that performed the repair.
+    pub method: Method,
+}
+
+/// Receives [`RepairEvent`]s as they happen.
+///
+/// Observers are called synchronously on the thread that performed the read, so implementations
+/// should be quick. For example: increment a metric, or push the event onto a channel.
+///
+/// # Example
+///
+/// ```rust
+/// use atlatl::layers::correctors::{set_repair_observer, RepairEvent};
+///
+/// set_repair_observer(|event: &RepairEvent| {
+///     eprintln!("repaired {} shard(s) in {:?}", event.shards_recovered, event.table);
+/// });
+/// ```
+pub trait RepairObserver: Send + Sync {
+    /// Called once for every value that was repaired on read.
+    fn on_repair(&self, event: &RepairEvent);
+}
+
+impl<F> RepairObserver for F
+where
+    F: Fn(&RepairEvent) + Send + Sync
+{
+    /// Allows plain closures and functions to be used as repair observers.
+    fn on_repair(&self, event: &RepairEvent) {
+        self(event);
+    }
+}
+
+/// Running repair totals for a single table.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RepairStats {
+    /// The number of values that were repaired on read.
+    pub repairs: u64,
+
+    /// The total number of corrupted shards that were rebuilt across all repairs.
+    pub shards_recovered: u64,
+}
+
+/// Running repair totals for every table of one database, keyed by table name.
+///
+/// Each database keeps its own totals, so two databases with a table of the same name don't share
+/// counts. Repairs are counted in the totals given to the [`with_repair_context`] scope they
+/// happen in.
+#[derive(Debug, Default)]
+pub struct RepairTotals(Mutex<HashMap<&'static str, RepairStats>>);
+
+/// The table and key of the value being read, and the totals its repairs are counted in.
+type Context = (&'static str, Vec<u8>, Option<Arc<RepairTotals>>);
+
+/// Restores the previous repair context when a [`with_repair_context`] scope ends, even if it
+/// ends by panicking.
+struct RestoreContext(Option<Context>);
+
+// -------------------------------------------------------------------------------------------------
+//
+// Global State
+
+/// The application's repair observer, if one has been set.
+static OBSERVER: RwLock<Option<Arc<dyn RepairObserver>>> = RwLock::new(None);
+
+thread_local! {
+    /// The context of the value currently being read on this thread, if known.
+    static CONTEXT: RefCell<Option<Context>> = const { RefCell::new(None) };
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl RepairTotals {
+    /// Instantiates empty repair totals.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the running repair totals for the named table.
+    #[must_use]
+    pub fn get(&self, table: &str) -> RepairStats {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(table)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the running repair totals for every table that has had at least one repair.
+    #[must_use]
+    pub fn all(&self) -> Vec<(&'static str, RepairStats)> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|(table, stats)| (*table, *stats))
+            .collect()
+    }
+
+    /// Counts a repair against the named table.
+    fn record(&self, table: &'static str, shards_recovered: usize) {
+        let mut stats = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let entry = stats.entry(table).or_default();
+        entry.repairs += 1;
+        entry.shards_recovered += shards_recovered as u64;
+        drop(stats);
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl Drop for RestoreContext {
+    /// Puts the previous context back. Does nothing if the thread's locals are being torn down.
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        let _ = CONTEXT.try_with(|context| *context.borrow_mut() = previous);
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Sets the observer that receives every [`RepairEvent`], replacing any previous observer.
+pub fn set_repair_observer(observer: impl RepairObserver + 'static) {
+    *OBSERVER.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(observer));
+}
+
+/// Removes the repair observer. Repairs are still logged and counted in their [`RepairTotals`].
+pub fn clear_repair_observer() {
+    *OBSERVER.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// Runs `f` with the given table name and key attached to any repair that happens on this thread.
+/// Repairs are counted in `totals`, if given.
+///
+/// Contexts nest: the previous context is restored when `f` returns or panics.
+pub fn with_repair_context<R>(
+    totals: Option<&Arc<RepairTotals>>,
+    table: &'static str,
+    key: &[u8],
+    f: impl FnOnce() -> R,
+) -> R {
+    let context = (table, key.to_vec(), totals.map(Arc::clone));
+    let _restore = RestoreContext(CONTEXT.with(|current| current.replace(Some(context))));
+    f()
+}
+
+/// Records a repair: logs it, updates the table's totals, and notifies the observer.
+pub fn report_repair(shards_recovered: usize, method: Method) {
+    let (table, key, totals) = CONTEXT
+        .with(|context| context.borrow().clone())
+        .map_or((None, None, None), |(table, key, totals)| (Some(table), Some(key), totals));
+
+    tracing::warn!(
+        table = table.unwrap_or("<unknown>"),
+        shards_recovered,
+        "value repaired on read using {method} error correction"
+    );
+
+    #[cfg(feature = "metrics")]
+    crate::layers::metrics::record_repair(table, shards_recovered);
+
+    if let (Some(table), Some(totals)) = (table, totals) {
+        totals.record(table, shards_recovered);
+    }
+
+    let observer = OBSERVER.read().unwrap_or_else(PoisonError::into_inner).clone();
+    if let Some(observer) = observer {
+        observer.on_repair(&RepairEvent { table, key, shards_recovered, method });
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repairs_are_counted_per_table() {
+        let totals = Arc::new(RepairTotals::new());
+        with_repair_context(Some(&totals), "creatures", b"key", || {
+            report_repair(1, Method::ReedSolomon);
+            report_repair(2, Method::ReedSolomon);
+        });
+
+        assert_eq!(totals.get("creatures"), RepairStats { repairs: 2, shards_recovered: 3 });
+        assert_eq!(totals.all(), [("creatures", RepairStats { repairs: 2, shards_recovered: 3 })]);
+    }
+
+    #[test]
+    fn repairs_are_counted_per_database() {
+        let (first, second) = (Arc::new(RepairTotals::new()), Arc::new(RepairTotals::new()));
+        with_repair_context(Some(&first), "creatures", b"a", || {
+            report_repair(1, Method::ReedSolomon);
+        });
+        with_repair_context(None, "creatures", b"b", || report_repair(1, Method::ReedSolomon));
+
+        assert_eq!(first.get("creatures").repairs, 1);
+        assert_eq!(second.get("creatures"), RepairStats::default());
+    }
+
+    #[test]
+    fn contexts_nest() {
+        let totals = Arc::new(RepairTotals::new());
+        with_repair_context(Some(&totals), "outer", b"a", || {
+            with_repair_context(Some(&totals), "inner", b"b", || {
+                report_repair(1, Method::ReedSolomon);
+            });
+            report_repair(1, Method::ReedSolomon);
+        });
+
+        assert_eq!(totals.get("inner").repairs, 1);
+        assert_eq!(totals.get("outer").repairs, 1);
+    }
+
+    #[test]
+    fn contexts_are_restored_after_a_panic() {
+        let totals = Arc::new(RepairTotals::new());
+        with_repair_context(Some(&totals), "outer", b"a", || {
+            let panicked = std::panic::catch_unwind(|| {
+                with_repair_context(Some(&totals), "inner", b"b", || panic!("decoder panicked"));
+            });
+            assert!(panicked.is_err());
+            report_repair(1, Method::ReedSolomon);
+        });
+
+        assert_eq!(totals.get("outer").repairs, 1);
+        assert_eq!(totals.get("inner"), RepairStats::default());
+        assert!(CONTEXT.with(|context| context.borrow().is_none()));
+    }
+
+    #[test]
+    fn observer_receives_events() {
+        static SEEN: Mutex<Vec<RepairEvent>> = Mutex::new(Vec::new());
+        set_repair_observer(|event: &RepairEvent| {
+            if event.table == Some("repair_test_observed") {
+                SEEN.lock().unwrap().push(event.clone());
+            }
+        });
+
+        with_repair_context(None, "repair_test_observed", b"k1", || {
+            report_repair(2, Method::ReedSolomon);
+        });
+        clear_repair_observer();
+
+        assert_eq!(*SEEN.lock().unwrap(), vec![RepairEvent {
+            table: Some("repair_test_observed"),
+            key: Some(b"k1".to_vec()),
+            shards_recovered: 2,
+            method: Method::ReedSolomon,
+        }]);
+    }
+}
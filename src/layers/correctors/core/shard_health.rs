@@ -0,0 +1,36 @@
+//! The checksum states of a protected value's shards, used for diagnosing corrupted values.
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The checksum states of a protected value's data and parity shards, as found on disk before any
+/// repair was attempted.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ShardHealth {
+    /// The number of data shards, which hold the protected value.
+    pub data_shards: usize,
+
+    /// The number of parity shards, which hold the redundancy used to rebuild corrupted shards.
+    pub parity_shards: usize,
+
+    /// Indices of the shards that failed their checksums. Data shards come first, followed by
+    /// parity shards.
+    pub corrupted_shards: Vec<usize>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl ShardHealth {
+    /// Returns `true` if every shard passed its checksum.
+    #[must_use]
+    pub const fn is_intact(&self) -> bool {
+        self.corrupted_shards.is_empty()
+    }
+
+    /// Returns `true` if few enough shards are corrupted for error correction to rebuild them.
+    #[must_use]
+    pub const fn is_repairable(&self) -> bool {
+        self.corrupted_shards.len() <= self.parity_shards
+    }
+}
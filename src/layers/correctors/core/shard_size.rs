@@ -0,0 +1,22 @@
+//! An enumeration that allows the shard size strategy for each type to be set individually.
+
+// -------------------------------------------------------------------------------------------------
+//
+/// How a value is divided into shards for error correction.
+///
+/// Smaller shards spread corruption across more, smaller units, so a burst of damage costs fewer
+/// bytes of parity to repair. Larger shards mean fewer shards, and less work to encode and check.
+/// Whatever the strategy, values are always divided into at least 2 and at most 255 data shards.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum ShardSize {
+    /// Shards of roughly 1/4 to 1/8 of the value, between 16 and 65,536 bytes.
+    ///
+    /// * Data shards: 4 to 8, for most values
+    #[default]
+    Automatic,
+
+    /// Shards of the given size in bytes, rounded up to a power of two of at least 16 bytes.
+    ///
+    /// * Data shards: value size / shard size
+    Target(usize),
+}
@@ -68,4 +68,15 @@ impl<'b, V: Correctable> crate::layers::correctors::Corrector<'b, V> for ReedSol
     ) -> Result<Bytes<'b>, crate::layers::correctors::RecoverError> {
         Ok(Self::check_and_recover(protected_bytes)?)
     }
+
+    /// Checks the CRC-32 checksum of every data and parity shard, without repairing anything.
+    ///
+    /// Returns `None` if the data is too small or too large to have been protected, or if its
+    /// Reed-Solomon parameters are unreadable.
+    #[inline]
+    fn inspect(
+        protected_bytes: &[u8]
+    ) -> Option<crate::layers::correctors::ShardHealth> {
+        Self::shard_health(protected_bytes)
+    }
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 use crate::layers::core::Bytes;
 use crate::layers::correctors::impls::reed_solomon::{DATA_LEN_MIN, DATA_LEN_MAX, Error, Parameters};
-use crate::layers::correctors::{Correctable, Level};
+use crate::layers::correctors::{Correctable, Level, ShardHealth, ShardSize};
 use reed_solomon_erasure::Field;
 
 // -------------------------------------------------------------------------------------------------
@@ -239,13 +239,42 @@ impl<V: Correctable> ReedSolomon<V> {
                 // Mark the data was "recovered" so that it may be later recommitted to the
                 // database:
                 recovered_data.truncate(parameters.data_len);
-                Ok(Bytes::from_recovered_data(recovered_data))
+                Ok(Bytes::from_recovered_data(recovered_data, corrupted_shards.len()))
             }
         }
     }
 
+    /// Checks the integrity of every data and parity shard using their CRC-32 checksums, without
+    /// attempting any recovery.
+    ///
+    /// Returns `None` if the data is too small or too large to have been protected, or if its
+    /// parameters can't be parsed.
+    #[must_use] pub fn shard_health(data: &[u8]) -> Option<ShardHealth> {
+        if data.len() < DATA_LEN_MIN || data.len() > DATA_LEN_MAX - std::mem::size_of::<Parameters>() {
+            return None;
+        }
+
+        let mut data = Bytes::from_slice(data);
+        let parameters = Parameters::from_data_buffer(&mut data).ok()?;
+        Some(ShardHealth {
+            data_shards: parameters.num_data_shards,
+            parity_shards: parameters.total_num_shards - parameters.num_data_shards,
+            corrupted_shards: parameters.check_shards(data.as_slice()).to_vec(),
+        })
+    }
+
     /// Returns the number of parity shards that should be used to protect the value.
     #[must_use] fn num_parity_shards(num_data_shards: usize) -> usize {
+        if let Some(percent) = V::PARITY_PERCENT {
+            // Data and parity shards together can't exceed the Galois field order:
+            let max_parity_shards = reed_solomon_erasure::galois_8::Field::ORDER
+                .saturating_sub(num_data_shards)
+                .max(1);
+            return (num_data_shards * usize::from(percent))
+                .div_ceil(100)
+                .clamp(1, max_parity_shards);
+        }
+
         // Note: `max(1)` ensures that at least on parity shard will be used, regardless of the
         // math.
         match V::LEVEL {
@@ -270,7 +299,7 @@ impl<V: Correctable> ReedSolomon<V> {
     /// 4. `total_size` · The total size of the data, parity, and parameters blocks in bytes.
     #[must_use] fn buffer_measurements(data_len: usize) -> (usize, usize, usize, usize, usize) {
         // Figure out the size of a shard:
-        let shard_size = Self::shard_size(data_len);
+        let shard_size = Self::selected_shard_size(data_len);
 
         tracing::trace!(
             "data size is {data_len} bytes, \
@@ -302,6 +331,16 @@ impl<V: Correctable> ReedSolomon<V> {
             total_size
         )
     }
+
+    /// Returns a data shard size for the provided data length (in bytes), following the type's
+    /// [`ShardSize`] strategy.
+    #[must_use] fn selected_shard_size(data_len: usize) -> usize {
+        match V::SHARD_SIZE {
+            ShardSize::Automatic => Self::shard_size(data_len),
+            ShardSize::Target(target) =>
+                Self::constrained_shard_size(data_len, target.max(16).next_power_of_two()),
+        }
+    }
 }
 
 impl<V> ReedSolomon<V> {
@@ -337,13 +376,18 @@ impl<V> ReedSolomon<V> {
     /// * Aren't too few, and
     /// * Don't exceed the Galois field limit, currently `256` shards.
     #[must_use] fn shard_size(data_len: usize) -> usize {
+        // Get the recommended shard sized based on the data length:
+        Self::constrained_shard_size(data_len, Self::recommended_shard_size(data_len))
+    }
+
+    /// Adjusts a preferred shard size so that the number of data shards:
+    /// * Aren't too few, and
+    /// * Don't exceed the Galois field limit, currently `256` shards.
+    #[must_use] const fn constrained_shard_size(data_len: usize, mut shard_size: usize) -> usize {
         // Shard count constraints to be encorced:
         const MIN_SHARDS: usize = 2;
         const MAX_SHARDS: usize = reed_solomon_erasure::galois_8::Field::ORDER - 1;
 
-        // Get the recommended shard sized based on the data length:
-        let mut shard_size = Self::recommended_shard_size(data_len);
-
         // Ensure that the recommended shard size doesn't cause us to exceed the maximum number of
         // shards (Galois field limit):
         let min_shard_size_for_max = data_len.div_ceil(MAX_SHARDS);
@@ -6,7 +6,7 @@
 mod round_trip {
 	use crate::layers::core::{Bytes, Direction};
 	use crate::layers::correctors::impls::reed_solomon::*;
-	use crate::layers::correctors::{Correctable, Level};
+	use crate::layers::correctors::{Correctable, Level, ShardSize};
 
     // Mock type implementing `Correctable` for testing
     struct TestValue;
@@ -55,10 +55,30 @@ mod round_trip {
 	    // Decode and reconstruct:
 	    let recovered_data = ReedSolomon::<TestValue>::check_and_recover(protected_data.into()).unwrap();
 
+	    // Verify the repair was recorded in the metadata:
+	    assert_eq!(recovered_data.shards_recovered(), Some(1));
+
 	    // Verify round-trip:
 	    assert_eq!(recovered_data.as_slice(), original_data.as_slice());
 	}
 
+	#[test]
+	fn shard_health_reports_corrupted_shards() {
+	    let data = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+	    let protected_data = ReedSolomon::<TestValue>::add_parity((&data).into()).unwrap();
+
+	    let health = ReedSolomon::<TestValue>::shard_health(protected_data.as_slice()).unwrap();
+	    assert!(health.is_intact());
+
+	    // Corrupt the first data shard:
+	    let mut protected_data = protected_data.to_vec();
+	    protected_data[0] ^= 0xFF;
+
+	    let health = ReedSolomon::<TestValue>::shard_health(&protected_data).unwrap();
+	    assert_eq!(health.corrupted_shards, vec![0]);
+	    assert!(health.is_repairable());
+	}
+
     /// A parity shard is not expected to be generated for this data. The protectr and recoverr should
     /// skip error corrrection.
     #[test]
@@ -208,4 +228,33 @@ mod round_trip {
 	    	})
 	    ));
 	}
+
+	#[test]
+	fn parity_percent_and_shard_size_are_configurable() {
+	    struct Critical;
+	    impl Correctable for Critical {
+			const DIRECTION: Direction = Direction::Both;
+	        const LEVEL: Level = Level::Minimum; // overridden by `PARITY_PERCENT`
+	        const PARITY_PERCENT: Option<u8> = Some(100);
+	        const SHARD_SIZE: ShardSize = ShardSize::Target(10); // rounded up to 16 bytes
+	    }
+
+	    let data = vec![7u8; 128];
+	    let protected_data = ReedSolomon::<Critical>::add_parity((&data).into()).unwrap();
+
+	    let metadata = Parameters::from_data_buffer(&mut protected_data.clone()).unwrap();
+	    assert_eq!(metadata.shard_size, 16);
+	    assert_eq!(metadata.num_data_shards, 8);
+	    assert_eq!(metadata.total_num_shards, 16);
+
+	    // Corrupt half of the shards, which is OK with as many parity shards as data shards:
+	    let mut protected_data = protected_data.to_vec();
+	    for index in 0..8 {
+	        let start = index * 2 * metadata.shard_size;
+	        protected_data[start..start + metadata.shard_size].fill(0);
+	    }
+
+	    let recovered_data = ReedSolomon::<Critical>::check_and_recover(protected_data.into()).unwrap();
+	    assert_eq!(recovered_data.as_slice(), data.as_slice());
+	}
 }
\ No newline at end of file
@@ -9,6 +9,13 @@ pub use crate::layers::correctors::core::Metadata;
 pub use crate::layers::correctors::core::Method;
 pub use crate::layers::correctors::core::ProtectError;
 pub use crate::layers::correctors::core::RecoverError;
+pub use crate::layers::correctors::core::ShardHealth;
+pub use crate::layers::correctors::core::ShardSize;
+pub use crate::layers::correctors::core::{
+    clear_repair_observer, set_repair_observer, with_repair_context, RepairEvent, RepairObserver,
+    RepairStats, RepairTotals
+};
+pub(crate) use crate::layers::correctors::core::report_repair;
 
 mod impls;
 pub use crate::layers::correctors::impls::ActiveCorrector;
\ No newline at end of file
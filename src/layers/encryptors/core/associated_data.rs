@@ -0,0 +1,91 @@
+//! Associated data binds a ciphertext to the record it was written for, so that it can't be moved
+//! to another record.
+
+use crate::layers::core::Layer;
+use crate::layers::encryptors::Method;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The context a value is encrypted in: the table it's stored in and the key it's stored under.
+///
+/// The context is authenticated along with the ciphertext as AEAD (Authenticated Encryption with
+/// Associated Data) associated data, but isn't stored with it. Decrypting with a different context
+/// than the value was encrypted with fails, so that an attacker with write access to the database
+/// file can't splice a valid ciphertext from one record or table into another.
+///
+/// The encryption layer's descriptor, its layer type and encryption method, is authenticated as
+/// well, so that a ciphertext can't be reinterpreted by another encryption method.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let associated_data = AssociatedData::new("creatures", &primary_key_bytes);
+/// let bytes = Bytes::apply_write_layers(&creature, key, &associated_data, None, None)?;
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct AssociatedData<'a> {
+    /// Full name of the table the value is stored in, including its namespace prefix, if any.
+    table_name: &'a str,
+
+    /// The serialized key the value is stored under.
+    key: &'a [u8],
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<'a> AssociatedData<'a> {
+    /// Instantiates the context of a value stored in the given table, under the given serialized
+    /// key.
+    #[must_use]
+    pub const fn new(table_name: &'a str, key: &'a [u8]) -> Self {
+        Self { table_name, key }
+    }
+
+    /// Returns the name of the table the value is stored in.
+    #[must_use]
+    pub const fn table_name(&self) -> &'a str {
+        self.table_name
+    }
+
+    /// Returns the serialized key the value is stored under.
+    #[must_use]
+    pub const fn key(&self) -> &'a [u8] {
+        self.key
+    }
+
+    /// Encodes the associated data that's passed to an AEAD cipher.
+    ///
+    /// Layout: `layer (u8) | method (u8) | table name length (u32 little-endian) | table name |
+    /// key`. The length prefix keeps a table name and key from being re-split into another pair.
+    #[must_use]
+    pub(crate) fn to_bytes(self, method: Method) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(6 + self.table_name.len() + self.key.len());
+        bytes.push(Layer::Encryption as u8);
+        bytes.push(method as u8);
+        let length = u32::try_from(self.table_name.len()).unwrap_or(u32::MAX);
+        bytes.extend_from_slice(&length.to_le_bytes());
+        bytes.extend_from_slice(self.table_name.as_bytes());
+        bytes.extend_from_slice(self.key);
+        bytes
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context_is_unambiguous() {
+        let method = Method::AesGcm;
+        let bytes = AssociatedData::new("creatures", b"7").to_bytes(method);
+        assert_ne!(bytes, AssociatedData::new("creature", b"s7").to_bytes(method));
+        assert_ne!(bytes, AssociatedData::new("creatures", b"8").to_bytes(method));
+        assert_ne!(bytes, AssociatedData::new("creatures", b"7").to_bytes(Method::ChaCha20));
+    }
+}
@@ -0,0 +1,235 @@
+//! Blind indexes allow equality searches over fields that are encrypted at rest.
+//!
+//! Rather than storing a field's plain text in a secondary index, a keyed hash of the plain text (a
+//! "search token") is stored instead. The same plain text always produces the same token under the
+//! same key, so a look-up can be performed by hashing the search term and querying the index for
+//! the resulting token. The plain text itself never appears in any table.
+
+// Imports
+
+use crate::layers::encryptors::impls::KEY_SIZE;
+use crate::layers::encryptors::KeyBytes;
+use zeroize::Zeroize;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// Size of a blind index search token. Tokens are `32`-bytes or `256`-bits.
+pub const TOKEN_SIZE: usize = 32;
+
+/// Domain separation prefix used when deriving a per-field blind index key from a master key.
+///
+/// **Warning**: This prefix must never change. It is permanently bound to every search token that
+/// has been written to a secondary index. Changing it would render all existing blind indexes
+/// unsearchable until they are rebuilt.
+const CONTEXT: &str = "atlatl:blind-index:";
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Derives search tokens for a single encrypted field.
+///
+/// Each field is given its own sub-key, derived from the master key and the field's name. This
+/// ensures that the same plain text in two different fields (for example, a user's `email` and
+/// their `recovery_email`) produces unrelated tokens, which prevents correlating values across
+/// indexes.
+///
+/// # Example
+///
+/// ```rust
+/// use atlatl::layers::encryptors::{BlindIndex, KeyBytes};
+///
+/// let key = KeyBytes::from_array(&[7_u8; 32]);
+/// let email_index = BlindIndex::new(&key, "users_by_email");
+///
+/// let stored = email_index.token(b"jane@example.com");
+/// let search = email_index.token(b"jane@example.com");
+/// assert_eq!(stored, search);
+/// ```
+///
+/// # Notes
+///
+/// * Blind indexes only support exact-match (equality) look-ups. Range queries, prefix scans, and
+///   ordering are not possible over search tokens.
+///
+/// * Tokens are deterministic. An observer with access to the index table can tell when two records
+///   share the same value, even though they can't tell what that value is. Normalize values (case,
+///   whitespace, etc.) before hashing if equivalent inputs should match.
+#[derive(Clone)]
+pub struct BlindIndex {
+    /// Per-field key derived from the master key and the field name.
+    field_key: [u8; KEY_SIZE],
+}
+
+/// A search token produced by a [`BlindIndex`].
+///
+/// This is the value that's stored in a secondary index table in place of the field's plain text.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct BlindToken([u8; TOKEN_SIZE]);
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl BlindIndex {
+    /// Instantiates a new `BlindIndex` for the named field.
+    ///
+    /// The `field` name is used for domain separation, so it should be stable for the lifetime of
+    /// the index. Using the index table's name is a good choice.
+    #[must_use]
+    pub fn new(key: &KeyBytes<'_>, field: &str) -> Self {
+        Self { field_key: derive_field_key(key, CONTEXT, field) }
+    }
+
+    /// Produces the search token for the given plain text.
+    ///
+    /// The token is used both when writing the secondary index entry, and when querying it.
+    #[must_use]
+    pub fn token(&self, plain_text: &[u8]) -> BlindToken {
+        BlindToken(keyed_hash(&self.field_key, plain_text))
+    }
+}
+
+impl BlindToken {
+    /// Returns the token as a slice of bytes, ready for use as a secondary index key.
+    #[inline]
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8; TOKEN_SIZE] {
+        &self.0
+    }
+
+    /// Converts the token into an owned `Vec<u8>`.
+    #[inline]
+    #[must_use]
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl Drop for BlindIndex {
+    /// Wipes the derived key from memory.
+    fn drop(&mut self) {
+        self.field_key.zeroize();
+    }
+}
+
+impl std::fmt::Debug for BlindIndex {
+    /// Formats the `BlindIndex` without exposing the derived field key.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlindIndex").finish_non_exhaustive()
+    }
+}
+
+impl AsRef<[u8]> for BlindToken {
+    /// Returns a reference to the bytes in the token. Does not allocate.
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<BlindToken> for Vec<u8> {
+    /// Converts a `BlindToken` into an owned `Vec<u8>`.
+    #[inline]
+    fn from(token: BlindToken) -> Self {
+        token.0.to_vec()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Keyed Hash Implementations
+
+/// Derives a per-field key from the master key, for the given purpose `context`, using
+/// [Jack O'Connor](https://github.com/oconnor663)'s [blake3](https://crates.io/crates/blake3) crate
+/// in keyed-hash mode.
+#[cfg(feature = "kdf-blake3")]
+pub(super) fn derive_field_key(key: &KeyBytes<'_>, context: &str, field: &str) -> [u8; KEY_SIZE] {
+    let mut hasher = blake3::Hasher::new_keyed(key);
+    hasher.update(context.as_bytes());
+    hasher.update(field.as_bytes());
+    *hasher.finalize().as_bytes()
+}
+
+/// Hashes the plain text with the per-field key using
+/// [Jack O'Connor](https://github.com/oconnor663)'s [blake3](https://crates.io/crates/blake3) crate
+/// in keyed-hash mode.
+#[cfg(feature = "kdf-blake3")]
+pub(super) fn keyed_hash(field_key: &[u8; KEY_SIZE], plain_text: &[u8]) -> [u8; TOKEN_SIZE] {
+    *blake3::keyed_hash(field_key, plain_text).as_bytes()
+}
+
+/// Derives a per-field key from the master key, for the given purpose `context`, using HMAC-SHA256
+/// from [Brian Smith](https://github.com/briansmith)'s [ring](https://crates.io/crates/ring) crate.
+#[cfg(all(feature = "kdf-sha256", not(feature = "kdf-blake3")))]
+pub(super) fn derive_field_key(key: &KeyBytes<'_>, context: &str, field: &str) -> [u8; KEY_SIZE] {
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, key.as_ref());
+    let mut hmac = ring::hmac::Context::with_key(&key);
+    hmac.update(context.as_bytes());
+    hmac.update(field.as_bytes());
+    hmac.sign().as_ref().try_into().unwrap() // HMAC-SHA256 is always 32 bytes
+}
+
+/// Hashes the plain text with the per-field key using HMAC-SHA256 from
+/// [Brian Smith](https://github.com/briansmith)'s [ring](https://crates.io/crates/ring) crate.
+#[cfg(all(feature = "kdf-sha256", not(feature = "kdf-blake3")))]
+pub(super) fn keyed_hash(field_key: &[u8; KEY_SIZE], plain_text: &[u8]) -> [u8; TOKEN_SIZE] {
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, field_key);
+    ring::hmac::sign(&key, plain_text).as_ref().try_into().unwrap() // HMAC-SHA256 is always 32 bytes
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MASTER_KEY: [u8; KEY_SIZE] = [0x42; KEY_SIZE];
+    const OTHER_KEY: [u8; KEY_SIZE] = [0x24; KEY_SIZE];
+
+    #[test]
+    fn tokens_are_deterministic() {
+        let key = KeyBytes::from_array(&MASTER_KEY);
+        let index = BlindIndex::new(&key, "users_by_email");
+        assert_eq!(index.token(b"jane@example.com"), index.token(b"jane@example.com"));
+    }
+
+    #[test]
+    fn different_values_produce_different_tokens() {
+        let key = KeyBytes::from_array(&MASTER_KEY);
+        let index = BlindIndex::new(&key, "users_by_email");
+        assert_ne!(index.token(b"jane@example.com"), index.token(b"john@example.com"));
+    }
+
+    #[test]
+    fn fields_are_domain_separated() {
+        let key = KeyBytes::from_array(&MASTER_KEY);
+        let email = BlindIndex::new(&key, "users_by_email");
+        let recovery = BlindIndex::new(&key, "users_by_recovery_email");
+        assert_ne!(email.token(b"jane@example.com"), recovery.token(b"jane@example.com"));
+    }
+
+    #[test]
+    fn keys_are_domain_separated() {
+        let key = KeyBytes::from_array(&MASTER_KEY);
+        let other = KeyBytes::from_array(&OTHER_KEY);
+        let index = BlindIndex::new(&key, "users_by_email");
+        let other_index = BlindIndex::new(&other, "users_by_email");
+        assert_ne!(index.token(b"jane@example.com"), other_index.token(b"jane@example.com"));
+    }
+
+    #[test]
+    fn token_does_not_contain_plain_text() {
+        let key = KeyBytes::from_array(&MASTER_KEY);
+        let index = BlindIndex::new(&key, "users_by_email");
+        let token = index.token(b"jane@example.com");
+        assert_eq!(token.as_bytes().len(), TOKEN_SIZE);
+        assert!(!token.as_ref().windows(4).any(|window| window == b"jane"));
+    }
+}
@@ -0,0 +1,221 @@
+//! Deterministic encryption, which makes encrypted secondary keys searchable by equality while
+//! still letting them be decrypted.
+//!
+//! Values encrypted with a random nonce produce a different cipher text every time, so an encrypted
+//! secondary key could never be found again by encrypting the search term. A
+//! [`DeterministicCipher`] instead derives the nonce from a keyed hash of the plain text, in the
+//! style of SIV (Synthetic Initialization Vector) constructions:
+//!
+//! ```text
+//! nonce = keyed hash(field nonce key, plain text)[..NONCE_SIZE]
+//! ```
+//!
+//! The same plain text always encrypts to the same cipher text under the same key and field, and
+//! different plain texts are encrypted under different nonces. Unlike a [`BlindIndex`] token, the
+//! cipher text can be decrypted back into the secondary key, for example to list an index's keys.
+//!
+//! [`BlindIndex`]: crate::layers::encryptors::BlindIndex
+
+// Imports
+
+use crate::layers::core::{Bytes, Direction};
+use crate::layers::encryptors::core::blind_index::{derive_field_key, keyed_hash};
+use crate::layers::encryptors::core::{
+    AssociatedData,
+    DecryptError,
+    EncryptError,
+    Encryptable,
+    Encryptor,
+    KeyBytes,
+    KeyId,
+    Nonce,
+};
+use crate::layers::encryptors::impls::{ActiveEncryptor, KEY_SIZE, NONCE_SIZE};
+use zeroize::Zeroize;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// Domain separation prefix used when deriving a per-field nonce key from a master key.
+///
+/// **Warning**: This prefix must never change. Changing it would change every cipher text, and
+/// render existing deterministically encrypted indexes unsearchable until they are rebuilt.
+const NONCE_CONTEXT: &str = "atlatl:deterministic-nonce:";
+
+/// Domain separation prefix used when deriving a per-field encryption key from a master key.
+///
+/// **Warning**: This prefix must never change. Changing it would render existing deterministically
+/// encrypted indexes unreadable until they are rebuilt.
+const CIPHER_CONTEXT: &str = "atlatl:deterministic-cipher:";
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Deterministically encrypts the secondary keys of a single encrypted field.
+///
+/// Like a [`BlindIndex`], each field is given its own sub-keys, derived from the master key and
+/// the field's name, so the same plain text in two different fields produces unrelated cipher
+/// texts.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use atlatl::layers::encryptors::{DeterministicCipher, KeyBytes};
+///
+/// let key = KeyBytes::from_array(&[7_u8; 32]);
+/// let email_index = DeterministicCipher::new(&key, "users_by_email");
+///
+/// let stored = email_index.encrypt(b"jane@example.com")?;
+/// assert_eq!(stored, email_index.encrypt(b"jane@example.com")?);
+/// assert_eq!(email_index.decrypt(&stored)?, b"jane@example.com");
+/// ```
+///
+/// # Notes
+///
+/// * Deterministic encryption only supports exact-match (equality) look-ups. Range queries, prefix
+///   scans, and ordering are not possible over cipher texts.
+///
+/// * An observer with access to the index table can tell when two records share the same value,
+///   even though they can't tell what that value is. This is inherent to any searchable encryption.
+///
+/// [`BlindIndex`]: crate::layers::encryptors::BlindIndex
+#[derive(Clone)]
+pub struct DeterministicCipher {
+    /// Per-field key that nonces are derived with.
+    nonce_key: [u8; KEY_SIZE],
+
+    /// Per-field key that secondary keys are encrypted with.
+    cipher_key: [u8; KEY_SIZE],
+
+    /// ID of the master key the per-field keys were derived from.
+    key_id: KeyId,
+
+    /// Name of the field, which is authenticated as the cipher text's associated data.
+    field: String,
+}
+
+/// Marks secondary keys, which are always encrypted and decrypted.
+struct SecondaryKey;
+
+impl Encryptable for SecondaryKey {
+    const DIRECTION: Direction = Direction::Both;
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl DeterministicCipher {
+    /// Instantiates a new `DeterministicCipher` for the named field.
+    ///
+    /// The `field` name is used for domain separation, so it should be stable for the lifetime of
+    /// the index. Using the index table's name is a good choice.
+    #[must_use]
+    pub fn new(key: &KeyBytes<'_>, field: &str) -> Self {
+        Self {
+            nonce_key: derive_field_key(key, NONCE_CONTEXT, field),
+            cipher_key: derive_field_key(key, CIPHER_CONTEXT, field),
+            key_id: key.id(),
+            field: field.to_owned(),
+        }
+    }
+
+    /// Encrypts a secondary key. The same plain text always produces the same cipher text, which is
+    /// used both when writing the secondary index entry, and when querying it.
+    ///
+    /// # Errors
+    ///
+    /// Consult the documentation of the encryptor backend you are using for more detail on
+    /// encryption and potential limitations.
+    pub fn encrypt(&self, plain_text: &[u8]) -> Result<Vec<u8>, EncryptError> {
+        let mut nonce = [0; NONCE_SIZE];
+        nonce.copy_from_slice(&keyed_hash(&self.nonce_key, plain_text)[..NONCE_SIZE]);
+        let cipher_text = ActiveEncryptor::<SecondaryKey>::encrypt(
+            Bytes::from_slice(plain_text),
+            self.key(),
+            &AssociatedData::new(&self.field, &[]),
+            Some(Nonce::from_array(nonce)),
+        )?;
+        Ok(cipher_text.into_bytes().into_owned())
+    }
+
+    /// Decrypts a secondary key that was encrypted with [`DeterministicCipher::encrypt`].
+    ///
+    /// # Errors
+    ///
+    /// This method may fail for several reasons, including:
+    ///
+    /// * The secondary key was encrypted with another key, or for another field, or
+    /// * Input bytes are corrupted or malformed.
+    pub fn decrypt(&self, cipher_text: &[u8]) -> Result<Vec<u8>, DecryptError> {
+        let plain_text = ActiveEncryptor::<SecondaryKey>::decrypt(
+            Bytes::from_slice(cipher_text),
+            self.key(),
+            &AssociatedData::new(&self.field, &[]),
+        )?;
+        Ok(plain_text.into_bytes().into_owned())
+    }
+
+    /// Returns the per-field encryption key, carrying the master key's ID.
+    fn key(&self) -> KeyBytes<'_> {
+        KeyBytes::from_array(&self.cipher_key).with_id(self.key_id)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl Drop for DeterministicCipher {
+    /// Wipes the derived keys from memory.
+    fn drop(&mut self) {
+        self.nonce_key.zeroize();
+        self.cipher_key.zeroize();
+    }
+}
+
+impl std::fmt::Debug for DeterministicCipher {
+    /// Formats the `DeterministicCipher` without exposing the derived keys.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeterministicCipher")
+            .field("key_id", &self.key_id)
+            .field("field", &self.field)
+            .finish_non_exhaustive()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, any(
+    feature = "encrypt-aes-gcm",
+    feature = "encrypt-chacha20",
+    feature = "encrypt-aes-gcm-siv"
+)))]
+mod tests {
+    use super::*;
+
+    const MASTER_KEY: [u8; KEY_SIZE] = [0x42; KEY_SIZE];
+
+    #[test]
+    fn cipher_texts_are_deterministic_and_decryptable() {
+        let key = KeyBytes::from_array(&MASTER_KEY);
+        let email = DeterministicCipher::new(&key, "users_by_email");
+        let jane = email.encrypt(b"jane@example.com").unwrap();
+        assert_eq!(jane, email.encrypt(b"jane@example.com").unwrap());
+        assert_ne!(jane, email.encrypt(b"john@example.com").unwrap());
+        assert!(!jane.windows(4).any(|window| window == b"jane"));
+        assert_eq!(email.decrypt(&jane).unwrap(), b"jane@example.com");
+    }
+
+    #[test]
+    fn fields_are_domain_separated() {
+        let key = KeyBytes::from_array(&MASTER_KEY);
+        let email = DeterministicCipher::new(&key, "users_by_email");
+        let recovery = DeterministicCipher::new(&key, "users_by_recovery_email");
+        let jane = email.encrypt(b"jane@example.com").unwrap();
+        assert_ne!(jane, recovery.encrypt(b"jane@example.com").unwrap());
+        assert!(recovery.decrypt(&jane).is_err());
+    }
+}
@@ -30,4 +30,16 @@ pub trait Encryptable {
     /// The [`Direction`] configuration for this type. The same directional setting is used for all
     /// values of this type.
     const DIRECTION: crate::layers::core::descriptors::Direction;
+
+    /// How nonces are chosen when values of this type are encrypted.
+    ///
+    /// Defaults to [`NonceStrategy::Random`]. Select [`NonceStrategy::Counter`] to draw nonces from
+    /// a persisted [`NonceCounter`] instead, which guarantees they're unique across restarts
+    /// without relying on the random number generator alone.
+    ///
+    /// [`NonceStrategy::Random`]: crate::layers::encryptors::NonceStrategy::Random
+    /// [`NonceStrategy::Counter`]: crate::layers::encryptors::NonceStrategy::Counter
+    /// [`NonceCounter`]: crate::layers::encryptors::NonceCounter
+    const NONCE_STRATEGY: crate::layers::encryptors::NonceStrategy =
+        crate::layers::encryptors::NonceStrategy::Random;
 }
\ No newline at end of file
@@ -0,0 +1,276 @@
+//! Field-level encryption, for records where only a few fields need protection.
+//!
+//! Encrypting a whole record is costly when only a social security number or an API token needs
+//! protection, and it prevents the rest of the record from being indexed. Instead, individual
+//! fields can be marked as encrypted. A marked field is encrypted as the record is serialized, and
+//! decrypted as it's deserialized, while the remaining fields stay in plain text:
+//!
+//! ```rust,ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Customer {
+//!     name: String,
+//!     #[serde(with = "atlatl::layers::encryptors::encrypted")]
+//!     ssn: String,
+//! }
+//!
+//! let key = KeyBytes::from_array(&[7_u8; 32]);
+//! let bytes = encrypted::with_key(&key, || Customer::serialize(&customer))?;
+//! let customer = encrypted::with_key(&key, || Customer::deserialize(&bytes))?;
+//! ```
+//!
+//! The `#[serde(with = ...)]` attribute plays the role of an `#[encrypted]` field attribute. The
+//! key is supplied by [`with_key`] for the duration of a closure, since serde doesn't give field
+//! serializers any context of their own.
+//!
+//! # Notes
+//!
+//! * Encrypted fields use a random nonce, so they can't be indexed. Index the other fields, or use
+//!   a [`BlindIndex`] over the field's plain text.
+//!
+//! * Serializing or deserializing an encrypted field outside of [`with_key`] fails.
+//!
+//! [`BlindIndex`]: crate::layers::encryptors::BlindIndex
+
+// Imports
+
+use crate::layers::core::{Bytes, Direction};
+use crate::layers::encryptors::impls::{ActiveEncryptor, KEY_SIZE};
+use crate::layers::encryptors::{AssociatedData, Encryptable, Encryptor, KeyBytes, KeyId};
+use std::cell::RefCell;
+use zeroize::Zeroizing;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// Associated data of every encrypted field, which separates field cipher texts from those of
+/// whole records.
+///
+/// **Warning**: This context must never change. Changing it would render every encrypted field
+/// unreadable.
+const CONTEXT: &str = "atlatl:encrypted-field";
+
+thread_local! {
+    /// Key that fields are encrypted with on this thread, while inside [`with_key`].
+    static FIELD_KEY: RefCell<Option<(Zeroizing<[u8; KEY_SIZE]>, KeyId)>> =
+        const { RefCell::new(None) };
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A field type that can be encrypted. It's converted to and from bytes around encryption.
+pub trait EncryptedField: Sized {
+    /// Returns the field's plain text bytes.
+    fn to_plain_text(&self) -> Vec<u8>;
+
+    /// Converts decrypted plain text bytes back into the field.
+    ///
+    /// # Errors
+    ///
+    /// * The plain text isn't valid for the field's type.
+    fn from_plain_text(plain_text: Vec<u8>) -> Result<Self, String>;
+}
+
+/// Marks encrypted fields, which are always encrypted and decrypted.
+struct Field;
+
+impl Encryptable for Field {
+    const DIRECTION: Direction = Direction::Both;
+}
+
+/// Restores the previously scoped key when a [`with_key`] closure returns or panics.
+struct ScopeGuard(Option<(Zeroizing<[u8; KEY_SIZE]>, KeyId)>);
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Runs the closure with `key` as the key of encrypted fields on this thread. Records serialized
+/// or deserialized inside the closure have their encrypted fields encrypted or decrypted with it.
+///
+/// Scopes may be nested, in which case the innermost key is used.
+pub fn with_key<R>(key: &KeyBytes<'_>, f: impl FnOnce() -> R) -> R {
+    let scoped = (Zeroizing::new(**key), key.id());
+    let _guard = ScopeGuard(FIELD_KEY.with(|field_key| field_key.replace(Some(scoped))));
+    f()
+}
+
+/// Encrypts a field as it's serialized. Used through `#[serde(with = "...")]`.
+///
+/// # Errors
+///
+/// * The field was serialized outside of [`with_key`].
+///
+/// * Encryption errors. Consult the documentation of the encryptor backend you are using for more
+///   detail on encryption and potential limitations.
+pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: EncryptedField,
+    S: serde::Serializer,
+{
+    let cipher_text = scoped_key(|key| ActiveEncryptor::<Field>::encrypt(
+        Bytes::from_vec(value.to_plain_text()),
+        key,
+        &AssociatedData::new(CONTEXT, &[]),
+        None,
+    ).map_err(|error| error.to_string()))
+        .map_err(serde::ser::Error::custom)?;
+
+    serializer.serialize_bytes(cipher_text.as_slice())
+}
+
+/// Decrypts a field as it's deserialized. Used through `#[serde(with = "...")]`.
+///
+/// # Errors
+///
+/// * The field was deserialized outside of [`with_key`].
+///
+/// * Decryption errors, for example if the field was encrypted with another key.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: EncryptedField,
+    D: serde::Deserializer<'de>,
+{
+    let cipher_text = deserializer.deserialize_byte_buf(CipherTextVisitor)?;
+    let plain_text = scoped_key(|key| ActiveEncryptor::<Field>::decrypt(
+        Bytes::from_vec(cipher_text),
+        key,
+        &AssociatedData::new(CONTEXT, &[]),
+    ).map_err(|error| error.to_string()))
+        .map_err(serde::de::Error::custom)?;
+
+    T::from_plain_text(plain_text.into_bytes().into_owned()).map_err(serde::de::Error::custom)
+}
+
+/// Calls `f` with the key scoped by [`with_key`].
+fn scoped_key<R>(f: impl FnOnce(KeyBytes<'_>) -> Result<R, String>) -> Result<R, String> {
+    FIELD_KEY.with(|field_key| match &*field_key.borrow() {
+        Some((key, key_id)) => f(KeyBytes::from_array(key).with_id(*key_id)),
+        None => Err("an encrypted field was used outside of `encrypted::with_key`".to_string()),
+    })
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl Drop for ScopeGuard {
+    /// Restores the previously scoped key, if any.
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        FIELD_KEY.with(|field_key| field_key.replace(previous));
+    }
+}
+
+impl EncryptedField for String {
+    fn to_plain_text(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    fn from_plain_text(plain_text: Vec<u8>) -> Result<Self, String> {
+        Self::from_utf8(plain_text).map_err(|error| error.to_string())
+    }
+}
+
+impl EncryptedField for Vec<u8> {
+    fn to_plain_text(&self) -> Vec<u8> {
+        self.clone()
+    }
+
+    fn from_plain_text(plain_text: Vec<u8>) -> Result<Self, String> {
+        Ok(plain_text)
+    }
+}
+
+impl<T: EncryptedField> EncryptedField for Option<T> {
+    /// An absent value is encrypted as an empty plain text, and a present one with a `1` prefix,
+    /// so `None` and `Some` of an empty value remain distinguishable.
+    fn to_plain_text(&self) -> Vec<u8> {
+        self.as_ref().map_or_else(Vec::new, |value| {
+            let mut plain_text = vec![1];
+            plain_text.extend(value.to_plain_text());
+            plain_text
+        })
+    }
+
+    fn from_plain_text(mut plain_text: Vec<u8>) -> Result<Self, String> {
+        match plain_text.first() {
+            None => Ok(None),
+            Some(1) => {
+                plain_text.remove(0);
+                T::from_plain_text(plain_text).map(Some)
+            },
+            Some(tag) => Err(format!("invalid optional field tag {tag}")),
+        }
+    }
+}
+
+/// Accepts the cipher text in any of the forms a serialization format may hand back bytes in.
+struct CipherTextVisitor;
+
+impl<'de> serde::de::Visitor<'de> for CipherTextVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("an encrypted field's cipher text")
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+        Ok(bytes.to_vec())
+    }
+
+    fn visit_byte_buf<E: serde::de::Error>(self, bytes: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(bytes)
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "serialize-messagepack", any(
+    feature = "encrypt-aes-gcm",
+    feature = "encrypt-chacha20",
+    feature = "encrypt-aes-gcm-siv"
+)))]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Customer {
+        name: String,
+        #[serde(with = "crate::layers::encryptors::encrypted")]
+        ssn: String,
+        #[serde(with = "crate::layers::encryptors::encrypted")]
+        token: Option<Vec<u8>>,
+    }
+
+    #[test]
+    fn only_marked_fields_are_encrypted() {
+        let key = KeyBytes::from_array(&[0x42; KEY_SIZE]);
+        let customer = Customer {
+            name: "Wile E. Coyote".into(),
+            ssn: "078-05-1120".into(),
+            token: Some(vec![]),
+        };
+
+        let bytes = with_key(&key, || rmp_serde::to_vec(&customer)).unwrap();
+        assert!(bytes.windows(5).any(|window| window == b"Wile "));
+        assert!(!bytes.windows(6).any(|window| window == b"078-05"));
+
+        let decoded: Customer = with_key(&key, || rmp_serde::from_slice(&bytes)).unwrap();
+        assert_eq!(decoded, customer);
+
+        let other_key = KeyBytes::from_array(&[0x24; KEY_SIZE]);
+        assert!(with_key(&other_key, || rmp_serde::from_slice::<Customer>(&bytes)).is_err());
+        assert!(rmp_serde::from_slice::<Customer>(&bytes).is_err());
+    }
+}
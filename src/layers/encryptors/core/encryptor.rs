@@ -1,5 +1,5 @@
 use crate::layers::core::Bytes;
-use crate::layers::encryptors::{KeyBytes, Nonce};
+use crate::layers::encryptors::{AssociatedData, KeyBytes, Nonce};
 
 // -------------------------------------------------------------------------------------------------
 //
@@ -94,6 +94,9 @@ pub trait Encryptor<'b, 'k, V> {
     ///
     /// * `key` · The secret encryption key used to transform the data.
     ///
+    /// * `associated_data` · The table and key the data is stored under. It's authenticated, but
+    ///   not encrypted or stored, so the same context must be given to decrypt the data.
+    ///
     /// # Errors
     ///
     /// Consult the documentation of the encryptor backend you are using for more detail on
@@ -108,6 +111,7 @@ pub trait Encryptor<'b, 'k, V> {
     fn encrypt(
         plain_text: Bytes<'b>,
         key: KeyBytes<'k>,
+        associated_data: &AssociatedData<'_>,
         nonce: Option<Nonce<'k>>
     ) -> Result<Bytes<'b>, crate::layers::encryptors::EncryptError>;
 
@@ -121,11 +125,14 @@ pub trait Encryptor<'b, 'k, V> {
     /// * `key` · The same secret key used during encryption, required to reverse the
     ///   transformation.
     ///
+    /// * `associated_data` · The same table and key the data was encrypted with.
+    ///
     /// # Errors
     ///
     /// This method may fail for several reasons, including:
     ///
-    /// * Invalid key, or
+    /// * Invalid key,
+    /// * The data was encrypted for another table or key, or
     /// * Input bytes are corrupted or malformed.
     ///
     /// Consult the documentation of the encryptor backend you are using for more detail on
@@ -139,6 +146,7 @@ pub trait Encryptor<'b, 'k, V> {
     ///   `KeyProvider`.
     fn decrypt(
         cipher_text: Bytes<'b>,
-        key: KeyBytes<'k>
+        key: KeyBytes<'k>,
+        associated_data: &AssociatedData<'_>,
     ) -> Result<Bytes<'b>, crate::layers::encryptors::DecryptError>;
 }
\ No newline at end of file
@@ -66,8 +66,29 @@ pub enum Error {
     #[error("access denied: decryption failed")]
     AccessDenied { #[from] #[source] source: aes_gcm::Error },
 
+    /// Error returned from the [aes-gcm-siv](https://crates.io/crates/aes-gcm-siv) crate.
+    ///
+    /// This error typically means that the provided key was invalid for the given ciphertext.
+    /// However, it may also indicate that the encrypted data itself has been corrupted due to bit
+    /// rot, or tampering.
+    ///
+    /// Atlatl does not attempt to distinguish between these causes, and intentionally surfaces this
+    /// generic `AccessDenied` error to preserve abstraction boundaries and avoid leaking
+    /// information that could aid an attacker.
+    ///
+    /// To understand the possible errors this encryption may produce, please refer to the official
+    /// documentation: <https://docs.rs/aes-gcm-siv>
+    #[cfg(feature = "encrypt-aes-gcm-siv")]
+    #[error("access denied: decryption failed")]
+    AccessDenied { #[from] #[source] source: aes_gcm_siv::Error },
+
     /// Error parsing layer parameters. This may indicate data corruption or a database version
     /// mismatch.
     #[error("error parsing layer parameters")]
     Parameters { #[from] #[source] source: crate::layers::encryptors::core::parameters::Error },
+
+    /// The value was encrypted with a key that isn't in the key ring. The key may have been
+    /// retired while values encrypted with it remained.
+    #[error("{key_id} is not in the key ring")]
+    UnknownKey { key_id: crate::layers::encryptors::KeyId },
 }
\ No newline at end of file
@@ -24,6 +24,14 @@ pub enum Error {
     #[error("aes-gcm encryption failed")]
     AesGcm { #[from] #[source] source: aes_gcm::Error },
 
+    /// Error returned from the [aes-gcm-siv](https://crates.io/crates/aes-gcm-siv) crate.
+    ///
+    /// To understand the possible errors this encryption may produce, please refer to the official
+    /// documentation: <https://docs.rs/aes-gcm-siv>
+    #[cfg(feature = "encrypt-aes-gcm-siv")]
+    #[error("aes-gcm-siv encryption failed")]
+    AesGcmSiv { #[from] #[source] source: aes_gcm_siv::Error },
+
     /// Error processing layer parameters. This may indicate data corruption, a database version
     /// mismatch, or misconfiguration.
     #[error("error processing layer parameters")]
@@ -0,0 +1,27 @@
+//! Contains the error type returned while fetching keys from an external key provider.
+
+use crate::layers::encryptors::KeyId;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// An error returned while fetching keys from an [`ExternalKeyProvider`].
+///
+/// [`ExternalKeyProvider`]: crate::layers::encryptors::ExternalKeyProvider
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The provider doesn't hold a key that was asked for. The key may have been deleted from the
+    /// keychain, KMS, or HSM while values encrypted with it remained.
+    #[error("{key_id} was not found in the external key provider")]
+    MissingKey {
+        key_id: KeyId,
+    },
+
+    /// The provider failed to fetch a key. For example, because the KMS service couldn't be reached
+    /// or denied access.
+    #[error("external key provider failed: {source}")]
+    Provider {
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+    },
+}
@@ -0,0 +1,244 @@
+//! External key providers fetch encryption keys from outside the application, such as an OS
+//! keychain, a KMS service, or an HSM, so that keys don't have to be embedded in the binary.
+//!
+//! Keys are fetched once, when the database is opened, and cached in a [`FetchedKeys`] ring for the
+//! lifetime of the database. Fetched key material is wiped from memory when it's dropped.
+
+// Exports
+
+mod error;
+pub use crate::layers::encryptors::core::external_keys::error::Error;
+
+// Imports
+
+use crate::layers::encryptors::core::{KeyBytes, KeyId, KeyRingProvider};
+use crate::layers::encryptors::impls::KEY_SIZE;
+use std::collections::{BTreeMap, BTreeSet};
+use zeroize::Zeroizing;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Fetches encryption keys from outside the application, blocking until they're available.
+///
+/// Implementations should return keys wrapped in [`Zeroizing`], and avoid keeping other copies of
+/// them, so that key material is wiped from memory once it's no longer needed.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// struct Keychain;
+///
+/// impl ExternalKeyProvider for Keychain {
+///     type Error = keyring::Error;
+///
+///     fn active_key_id(&self) -> Result<KeyId, Self::Error> {
+///         Ok(KeyId::new(2))
+///     }
+///
+///     fn fetch_key(&self, key_id: KeyId) -> Result<Option<Zeroizing<[u8; 32]>>, Self::Error> {
+///         let entry = keyring::Entry::new("creatures-db", &key_id.get().to_string())?;
+///         let secret = Zeroizing::new(entry.get_secret()?);
+///         Ok(secret.as_slice().try_into().ok().map(Zeroizing::new))
+///     }
+/// }
+///
+/// let keys = FetchedKeys::fetch(&Keychain, [KeyId::new(1)])?;
+/// ```
+pub trait ExternalKeyProvider {
+    /// The error returned when a key can't be fetched.
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Returns the ID of the key that new values should be encrypted with.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider couldn't be reached, or denied access.
+    fn active_key_id(&self) -> Result<KeyId, Self::Error>;
+
+    /// Fetches the key with the given ID, or returns `None` if the provider doesn't hold it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider couldn't be reached, or denied access.
+    fn fetch_key(&self, key_id: KeyId) -> Result<Option<Zeroizing<[u8; KEY_SIZE]>>, Self::Error>;
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Fetches encryption keys from outside the application, without blocking. For providers that are
+/// reached over the network, such as a KMS service.
+///
+/// See [`ExternalKeyProvider`] for the blocking version.
+pub trait AsyncExternalKeyProvider {
+    /// The error returned when a key can't be fetched.
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Returns the ID of the key that new values should be encrypted with.
+    fn active_key_id(&self) -> impl Future<Output = Result<KeyId, Self::Error>> + Send;
+
+    /// Fetches the key with the given ID, or returns `None` if the provider doesn't hold it.
+    fn fetch_key(
+        &self,
+        key_id: KeyId,
+    ) -> impl Future<Output = Result<Option<Zeroizing<[u8; KEY_SIZE]>>, Self::Error>> + Send;
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Keys fetched from an external key provider, cached for the lifetime of the database.
+///
+/// The cached keys are wiped from memory when the `FetchedKeys` is dropped.
+pub struct FetchedKeys {
+    /// Every fetched key, by ID.
+    keys: BTreeMap<KeyId, Zeroizing<[u8; KEY_SIZE]>>,
+
+    /// ID of the key that new values are encrypted with.
+    active: KeyId,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl FetchedKeys {
+    /// Fetches the provider's active key, along with the keys of any other IDs that values may
+    /// still be encrypted with.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::MissingKey`] if the provider doesn't hold one of the keys.
+    ///
+    /// * Returns [`Error::Provider`] if the provider failed to fetch one of the keys.
+    pub fn fetch<P: ExternalKeyProvider>(
+        provider: &P,
+        key_ids: impl IntoIterator<Item = KeyId>,
+    ) -> Result<Self, Error> {
+        let active = provider.active_key_id().map_err(Error::provider)?;
+        let key_ids: BTreeSet<KeyId> = std::iter::once(active).chain(key_ids).collect();
+        let mut keys = BTreeMap::new();
+        for key_id in key_ids {
+            let key = provider.fetch_key(key_id).map_err(Error::provider)?;
+            keys.insert(key_id, key.ok_or(Error::MissingKey { key_id })?);
+        }
+        Ok(Self { keys, active })
+    }
+
+    /// Fetches the provider's active key, along with the keys of any other IDs that values may
+    /// still be encrypted with, without blocking.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::MissingKey`] if the provider doesn't hold one of the keys.
+    ///
+    /// * Returns [`Error::Provider`] if the provider failed to fetch one of the keys.
+    pub async fn fetch_async<P: AsyncExternalKeyProvider + Sync>(
+        provider: &P,
+        key_ids: impl IntoIterator<Item = KeyId> + Send,
+    ) -> Result<Self, Error> {
+        let active = provider.active_key_id().await.map_err(Error::provider)?;
+        let key_ids: BTreeSet<KeyId> = std::iter::once(active).chain(key_ids).collect();
+        let mut keys = BTreeMap::new();
+        for key_id in key_ids {
+            let key = provider.fetch_key(key_id).await.map_err(Error::provider)?;
+            keys.insert(key_id, key.ok_or(Error::MissingKey { key_id })?);
+        }
+        Ok(Self { keys, active })
+    }
+}
+
+impl Error {
+    /// Wraps an external key provider's error.
+    fn provider(source: impl std::error::Error + Send + Sync + 'static) -> Self {
+        Self::Provider { source: Box::new(source) }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl KeyRingProvider for FetchedKeys {
+    fn active_key(&self) -> KeyBytes<'_> {
+        // The active key is always fetched.
+        KeyBytes::from_array(&self.keys[&self.active]).with_id(self.active)
+    }
+
+    fn key(&self, key_id: KeyId) -> Option<KeyBytes<'_>> {
+        self.keys.get(&key_id).map(|key| KeyBytes::from_array(key).with_id(key_id))
+    }
+}
+
+impl std::fmt::Debug for FetchedKeys {
+    /// Formats the `FetchedKeys` without exposing its keys.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FetchedKeys")
+            .field("key_ids", &self.keys.keys().collect::<Vec<_>>())
+            .field("active", &self.active)
+            .finish()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct Vault(HashMap<u16, [u8; KEY_SIZE]>);
+
+    impl ExternalKeyProvider for Vault {
+        type Error = std::io::Error;
+
+        fn active_key_id(&self) -> Result<KeyId, Self::Error> {
+            Ok(KeyId::new(2))
+        }
+
+        fn fetch_key(
+            &self,
+            key_id: KeyId,
+        ) -> Result<Option<Zeroizing<[u8; KEY_SIZE]>>, Self::Error> {
+            Ok(self.0.get(&key_id.get()).copied().map(Zeroizing::new))
+        }
+    }
+
+    impl AsyncExternalKeyProvider for Vault {
+        type Error = std::io::Error;
+
+        async fn active_key_id(&self) -> Result<KeyId, Self::Error> {
+            ExternalKeyProvider::active_key_id(self)
+        }
+
+        async fn fetch_key(
+            &self,
+            key_id: KeyId,
+        ) -> Result<Option<Zeroizing<[u8; KEY_SIZE]>>, Self::Error> {
+            ExternalKeyProvider::fetch_key(self, key_id)
+        }
+    }
+
+    #[test]
+    fn fetches_the_active_and_retired_keys() {
+        let vault = Vault(HashMap::from([(1, [1; KEY_SIZE]), (2, [2; KEY_SIZE])]));
+        let keys = FetchedKeys::fetch(&vault, [KeyId::new(1)]).unwrap();
+        assert_eq!(keys.active_key().id(), KeyId::new(2));
+        assert_eq!(*keys.active_key(), [2; KEY_SIZE]);
+        assert_eq!(keys.key(KeyId::new(1)).map(|key| *key), Some([1; KEY_SIZE]));
+
+        let missing = FetchedKeys::fetch(&vault, [KeyId::new(3)]).unwrap_err();
+        assert!(matches!(missing, Error::MissingKey { key_id } if key_id == KeyId::new(3)));
+    }
+
+    #[test]
+    fn fetches_without_blocking() {
+        let vault = Vault(HashMap::from([(2, [2; KEY_SIZE])]));
+        let mut future = std::pin::pin!(FetchedKeys::fetch_async(&vault, []));
+        let mut context = std::task::Context::from_waker(std::task::Waker::noop());
+        let std::task::Poll::Ready(keys) = future.as_mut().poll(&mut context) else {
+            panic!("the vault answers immediately");
+        };
+        assert_eq!(*keys.unwrap().active_key(), [2; KEY_SIZE]);
+    }
+}
@@ -8,8 +8,10 @@ pub use crate::layers::encryptors::core::key_bytes::error::Error;
 
 // Imports
 
+use crate::layers::encryptors::core::KeyId;
 use crate::layers::encryptors::impls::KEY_SIZE;
 use std::borrow::Cow;
+use zeroize::Zeroize;
 
 // -------------------------------------------------------------------------------------------------
 //
@@ -17,8 +19,9 @@ use std::borrow::Cow;
 /// it secure from unauthorized access.
 ///
 /// This structure represents a previously validated key originating from a `KeyRing` struct or
-/// `KeyRingProvider` trait.
-pub struct KeyBytes<'k>(Cow<'k, [u8; KEY_SIZE]>);
+/// `KeyRingProvider` trait. It carries the key's [`KeyId`], which is stored alongside every value
+/// encrypted with it. Keys that don't come from a key ring have the default ID, `0`.
+pub struct KeyBytes<'k>(Cow<'k, [u8; KEY_SIZE]>, KeyId);
 
 // -------------------------------------------------------------------------------------------------
 //
@@ -57,6 +60,28 @@ impl<'k> KeyBytes<'k> {
     pub fn try_from_vec(owned_vec: Vec<u8>) -> Result<Self, Error> {
         owned_vec.try_into()
     }
+
+    /// Sets the ID of the key, which is recorded alongside every value encrypted with it.
+    #[inline]
+    #[must_use]
+    pub const fn with_id(mut self, key_id: KeyId) -> Self {
+        self.1 = key_id;
+        self
+    }
+
+    /// Returns the ID of the key.
+    #[inline]
+    #[must_use]
+    pub const fn id(&self) -> KeyId {
+        self.1
+    }
+
+    /// Returns a key that borrows this key's bytes, and has the same ID.
+    #[inline]
+    #[must_use]
+    pub fn reborrow(&self) -> KeyBytes<'_> {
+        KeyBytes::from_array(self).with_id(self.1)
+    }
 }
 
 // -------------------------------------------------------------------------------------------------
@@ -81,13 +106,29 @@ impl std::ops::Deref for KeyBytes<'_> {
     }
 }
 
+impl Drop for KeyBytes<'_> {
+    /// Wipes an owned key from memory. Borrowed keys are left to their owner.
+    fn drop(&mut self) {
+        if let Cow::Owned(key) = &mut self.0 {
+            key.zeroize();
+        }
+    }
+}
+
+impl std::fmt::Debug for KeyBytes<'_> {
+    /// Formats the `KeyBytes` with its ID, without exposing the key.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyBytes").field("id", &self.1).finish_non_exhaustive()
+    }
+}
+
 // Array Conversions
 
 impl<'k> From<&'k [u8; KEY_SIZE]> for KeyBytes<'k> {
     /// Converts a borrowed immutable `&[u8; KEY_SIZE]` fixed array of bytes into a `KeyBytes` type.
     #[inline]
     fn from(borrowed_fixed_array: &'k [u8; KEY_SIZE]) -> Self {
-        KeyBytes(Cow::Borrowed(borrowed_fixed_array))
+        KeyBytes(Cow::Borrowed(borrowed_fixed_array), KeyId::default())
     }
 }
 
@@ -95,7 +136,7 @@ impl<'k> From<&'k mut [u8; KEY_SIZE]> for KeyBytes<'k> {
     /// Converts a borrowed mutable `&[u8; KEY_SIZE]` fixed array of bytes into a `KeyBytes` type.
     #[inline]
     fn from(borrowed_fixed_array: &'k mut [u8; KEY_SIZE]) -> Self {
-        KeyBytes(Cow::Borrowed(&*borrowed_fixed_array))
+        KeyBytes(Cow::Borrowed(&*borrowed_fixed_array), KeyId::default())
     }
 }
 
@@ -103,7 +144,7 @@ impl From<[u8; KEY_SIZE]> for KeyBytes<'_> {
     /// Converts an owned `[u8; KEY_SIZE]` fixed array of bytes into a `KeyBytes` type.
     #[inline]
     fn from(owned_fixed_array: [u8; KEY_SIZE]) -> Self {
-        KeyBytes(Cow::Owned(owned_fixed_array))
+        KeyBytes(Cow::Owned(owned_fixed_array), KeyId::default())
     }
 }
 
@@ -125,7 +166,7 @@ impl<'k> TryFrom<&'k [u8]> for KeyBytes<'k> {
                 provided_size: borrowed_slice_of_bytes.len()
             })?;
 
-        Ok(KeyBytes(Cow::Owned(fixed_array)))
+        Ok(KeyBytes(Cow::Owned(fixed_array), KeyId::default()))
     }
 }
 
@@ -153,7 +194,7 @@ impl<'k> From<&'k Cow<'k, [u8; KEY_SIZE]>> for KeyBytes<'k> {
         KeyBytes(match borrowed_clone_on_write {
             Cow::Borrowed(fixed_array_ref) => Cow::Borrowed(fixed_array_ref),
             Cow::Owned(fixed_array) => Cow::Borrowed(fixed_array)
-        })
+        }, KeyId::default())
     }
 }
 
@@ -165,7 +206,7 @@ impl<'k> From<&'k mut Cow<'k, [u8; KEY_SIZE]>> for KeyBytes<'k> {
         KeyBytes(match borrowed_clone_on_write {
             Cow::Borrowed(fixed_array_ref) => Cow::Borrowed(fixed_array_ref),
             Cow::Owned(fixed_array) => Cow::Borrowed(fixed_array)
-        })
+        }, KeyId::default())
     }
 }
 
@@ -173,7 +214,7 @@ impl<'k> From<Cow<'k, [u8; KEY_SIZE]>> for KeyBytes<'k> {
     /// Converts an owned `Cow<'k, [u8; KEY_SIZE]>` collection of bytes into a `KeyBytes` type.
     #[inline]
     fn from(owned_clone_on_write: Cow<'k, [u8; KEY_SIZE]>) -> Self {
-        KeyBytes(owned_clone_on_write)
+        KeyBytes(owned_clone_on_write, KeyId::default())
     }
 }
 
@@ -196,7 +237,7 @@ impl<'k> TryFrom<&'k Vec<u8>> for KeyBytes<'k> {
                 provided_size: borrowed_vec_of_bytes.len()
             })?;
 
-        Ok(KeyBytes(Cow::Owned(fixed_array)))
+        Ok(KeyBytes(Cow::Owned(fixed_array), KeyId::default()))
     }
 }
 
@@ -222,15 +263,50 @@ impl TryFrom<Vec<u8>> for KeyBytes<'_> {
     /// # Errors
     /// This conversion can fail if:
     /// * The provided `Vec` is not `KEY_SIZE` length.
-    fn try_from(owned_vec_of_bytes: Vec<u8>) -> Result<Self, Self::Error> {
-        let fixed_array: [u8; KEY_SIZE] = owned_vec_of_bytes
-            .as_slice()
-            .try_into()
-            .map_err(|_| Error::InvalidKeyLength {
-                expected_size: KEY_SIZE,
-                provided_size: owned_vec_of_bytes.len()
-            })?;
+    fn try_from(mut owned_vec_of_bytes: Vec<u8>) -> Result<Self, Self::Error> {
+        let fixed_array: Result<[u8; KEY_SIZE], _> = owned_vec_of_bytes.as_slice().try_into();
+        let provided_size = owned_vec_of_bytes.len();
+        owned_vec_of_bytes.zeroize();
+
+        let fixed_array = fixed_array.map_err(|_| Error::InvalidKeyLength {
+            expected_size: KEY_SIZE,
+            provided_size,
+        })?;
 
-        Ok(KeyBytes(Cow::Owned(fixed_array)))
+        Ok(KeyBytes(Cow::Owned(fixed_array), KeyId::default()))
     }
-}
\ No newline at end of file
+}
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::MaybeUninit;
+
+    #[test]
+    fn dropping_an_owned_key_wipes_it() {
+        let mut slot = MaybeUninit::new(KeyBytes::try_from(vec![0xAB; KEY_SIZE]).unwrap());
+
+        // The owned key is stored inline, so its bytes are still readable after it's dropped:
+        // SAFETY: `slot` was initialized above, and the key is only read before it's dropped.
+        let key = unsafe { slot.assume_init_ref() }.as_ptr();
+        let offset = key as usize - slot.as_ptr() as usize;
+        // SAFETY: `slot` was initialized above and isn't used again afterwards.
+        unsafe { slot.assume_init_drop() };
+
+        // SAFETY: the key's bytes were initialized, and `zeroize` only overwrites them.
+        let wiped = unsafe {
+            std::slice::from_raw_parts(slot.as_ptr().cast::<u8>().add(offset), KEY_SIZE)
+        };
+        assert_eq!(wiped, [0; KEY_SIZE]);
+    }
+
+    #[test]
+    fn dropping_a_borrowed_key_leaves_it_to_its_owner() {
+        let array = [0xCD; KEY_SIZE];
+        drop(KeyBytes::from_array(&array));
+        assert_eq!(array, [0xCD; KEY_SIZE]);
+    }
+}
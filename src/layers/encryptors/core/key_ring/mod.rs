@@ -0,0 +1,226 @@
+//! Key rings hold several encryption keys at once, so that records encrypted under different keys
+//! can be read side by side.
+//!
+//! Every encrypted value records the ID of the key it was encrypted with in its encryption layer
+//! parameters. When the value is read back, the ID is used to look its key up in a
+//! [`KeyRingProvider`], such as a [`KeyRing`]. New values are always encrypted with the key ring's
+//! active key.
+
+// Imports
+
+use crate::layers::core::tail_readers::TailReader;
+use crate::layers::encryptors::core::{DecryptError, KeyBytes, Parameters};
+use crate::layers::encryptors::impls::KEY_SIZE;
+use std::collections::BTreeMap;
+use zeroize::Zeroize;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Identifies an encryption key within a [`KeyRing`].
+///
+/// Key IDs are stored alongside every encrypted value, so a key's ID must never be reused for a
+/// different key while values encrypted under the old key remain.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct KeyId(u16);
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Looks up encryption keys by their ID.
+///
+/// Implement this trait to keep keys somewhere other than a [`KeyRing`]. For example, in a secrets
+/// manager that's queried as keys are needed.
+pub trait KeyRingProvider {
+    /// Returns the key that new values are encrypted with. The key carries its own ID.
+    fn active_key(&self) -> KeyBytes<'_>;
+
+    /// Returns the key with the given ID, or `None` if the provider doesn't hold it.
+    fn key(&self, key_id: KeyId) -> Option<KeyBytes<'_>>;
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A set of encryption keys, one of which is active.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use atlatl::layers::encryptors::{KeyId, KeyRing, KeyRingProvider};
+///
+/// // Values written from now on are encrypted with key 2, and values written under key 1 can
+/// // still be read:
+/// let key_ring = KeyRing::new(KeyId::new(1), [1; 32]).rotate_to(KeyId::new(2), [2; 32]);
+/// assert_eq!(key_ring.active_key().id(), KeyId::new(2));
+/// assert!(key_ring.key(KeyId::new(1)).is_some());
+/// ```
+#[derive(Clone)]
+pub struct KeyRing {
+    /// Every key in the ring, by ID.
+    keys: BTreeMap<KeyId, [u8; KEY_SIZE]>,
+
+    /// ID of the key that new values are encrypted with.
+    active: KeyId,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl KeyId {
+    /// Instantiates a key ID from its number.
+    #[inline]
+    #[must_use]
+    pub const fn new(id: u16) -> Self {
+        Self(id)
+    }
+
+    /// Returns the key ID's number.
+    #[inline]
+    #[must_use]
+    pub const fn get(self) -> u16 {
+        self.0
+    }
+
+    /// Returns the ID of the key an encrypted value was encrypted with, without decrypting it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value is too short to hold the encryption layer's parameters.
+    pub fn of_cipher_text(cipher_text: &[u8]) -> Result<Self, DecryptError> {
+        Ok(Parameters::from_data_buffer(&mut TailReader::from_slice(cipher_text))?.key_id)
+    }
+}
+
+impl KeyRing {
+    /// Instantiates a key ring holding a single key, which is active.
+    #[must_use]
+    pub fn new(key_id: KeyId, key: [u8; KEY_SIZE]) -> Self {
+        Self { keys: BTreeMap::from([(key_id, key)]), active: key_id }
+    }
+
+    /// Adds a key that values can be decrypted with, without making it active. For example, a
+    /// retired key that some values are still encrypted with.
+    #[must_use]
+    pub fn with_key(mut self, key_id: KeyId, key: [u8; KEY_SIZE]) -> Self {
+        self.keys.insert(key_id, key);
+        self
+    }
+
+    /// Adds a key and makes it active, so that new values are encrypted with it. The previously
+    /// active key is kept, so that values encrypted with it can still be read.
+    #[must_use]
+    pub fn rotate_to(mut self, key_id: KeyId, key: [u8; KEY_SIZE]) -> Self {
+        self.keys.insert(key_id, key);
+        self.active = key_id;
+        self
+    }
+
+    /// Removes a retired key from the ring, once no values are encrypted with it. Returns `false`
+    /// if the key is active, or isn't in the ring.
+    pub fn retire(&mut self, key_id: KeyId) -> bool {
+        key_id != self.active && self.keys.remove(&key_id).map(|mut key| key.zeroize()).is_some()
+    }
+
+    /// Returns the IDs of every key in the ring, in ascending order.
+    pub fn key_ids(&self) -> impl Iterator<Item = KeyId> + '_ {
+        self.keys.keys().copied()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl KeyRingProvider for KeyRing {
+    fn active_key(&self) -> KeyBytes<'_> {
+        // The active key is always in the ring; it can't be retired.
+        KeyBytes::from_array(&self.keys[&self.active]).with_id(self.active)
+    }
+
+    fn key(&self, key_id: KeyId) -> Option<KeyBytes<'_>> {
+        self.keys.get(&key_id).map(|key| KeyBytes::from_array(key).with_id(key_id))
+    }
+}
+
+impl Drop for KeyRing {
+    /// Wipes every key in the ring from memory.
+    fn drop(&mut self) {
+        self.keys.values_mut().for_each(Zeroize::zeroize);
+    }
+}
+
+impl std::fmt::Debug for KeyRing {
+    /// Formats the `KeyRing` without exposing its keys.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyRing")
+            .field("key_ids", &self.keys.keys().collect::<Vec<_>>())
+            .field("active", &self.active)
+            .finish()
+    }
+}
+
+impl std::fmt::Display for KeyId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "key #{}", self.0)
+    }
+}
+
+impl From<u16> for KeyId {
+    #[inline]
+    fn from(id: u16) -> Self {
+        Self(id)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotation_keeps_retired_keys_readable() {
+        let mut key_ring = KeyRing::new(KeyId::new(1), [1; KEY_SIZE])
+            .rotate_to(KeyId::new(2), [2; KEY_SIZE]);
+        assert_eq!(key_ring.active_key().id(), KeyId::new(2));
+        assert_eq!(*key_ring.active_key(), [2; KEY_SIZE]);
+        assert_eq!(key_ring.key(KeyId::new(1)).map(|key| *key), Some([1; KEY_SIZE]));
+
+        assert!(!key_ring.retire(KeyId::new(2)));
+        assert!(key_ring.retire(KeyId::new(1)));
+        assert!(key_ring.key(KeyId::new(1)).is_none());
+        assert_eq!(key_ring.key_ids().collect::<Vec<_>>(), [KeyId::new(2)]);
+    }
+
+    #[cfg(any(
+        feature = "encrypt-aes-gcm",
+        feature = "encrypt-chacha20",
+        feature = "encrypt-aes-gcm-siv"
+    ))]
+    #[test]
+    fn values_record_their_key_id() {
+        use crate::layers::core::{Bytes, Direction};
+        use crate::layers::encryptors::{ActiveEncryptor, AssociatedData, Encryptable, Encryptor};
+
+        struct Record;
+        impl Encryptable for Record {
+            const DIRECTION: Direction = Direction::Both;
+        }
+
+        let old_ring = KeyRing::new(KeyId::new(1), [1; KEY_SIZE]);
+        let new_ring = old_ring.clone().rotate_to(KeyId::new(2), [2; KEY_SIZE]);
+
+        let context = AssociatedData::new("records", b"1");
+        let plain_text = Bytes::from(b"Wile E. Coyote".as_slice());
+        let cipher_text =
+            ActiveEncryptor::<Record>::encrypt(plain_text, old_ring.active_key(), &context, None)
+                .unwrap();
+        let key_id = KeyId::of_cipher_text(cipher_text.as_slice()).unwrap();
+        assert_eq!(key_id, KeyId::new(1));
+
+        let key = new_ring.key(key_id).unwrap();
+        let decrypted = ActiveEncryptor::<Record>::decrypt(cipher_text, key, &context).unwrap();
+        assert_eq!(decrypted.as_slice(), b"Wile E. Coyote");
+    }
+}
@@ -19,6 +19,11 @@ pub enum Method {
     /// `ChaCha20Poly1305` stream cipher offering high performance and resistance to timing attacks.
     /// Use when you need fast encryption with strong security guarantees on diverse hardware.
     ChaCha20 = 1,
+
+    /// `AES-GCM-SIV` nonce-misuse-resistant authenticated encryption. Use when nonces may repeat,
+    /// for example after a VM snapshot is restored, since a repeated nonce only reveals whether two
+    /// values are identical instead of compromising the key.
+    AesGcmSiv = 2,
 }
 
 // -------------------------------------------------------------------------------------------------
@@ -37,6 +42,7 @@ impl TryFrom<&u8> for &'static Method {
         match value {
             0 => Ok(&Method::AesGcm),
             1 => Ok(&Method::ChaCha20),
+            2 => Ok(&Method::AesGcmSiv),
             _ => Err(Self::Error::UnrecognizedEncryptor(*value)),
         }
     }
@@ -48,6 +54,7 @@ impl std::fmt::Display for Method {
         match self {
             Self::ChaCha20  => write!(f, "chacha20poly1305"),
             Self::AesGcm    => write!(f, "aes-gcm"),
+            Self::AesGcmSiv => write!(f, "aes-gcm-siv"),
         }
     }
 }
@@ -66,6 +73,7 @@ mod tests {
         let methods = [
             Method::AesGcm,
             Method::ChaCha20,
+            Method::AesGcmSiv,
         ];
 
         for method in methods {
@@ -90,12 +98,13 @@ mod tests {
     fn test_method_values() {
         assert_eq!(Method::AesGcm as u8,   0);
         assert_eq!(Method::ChaCha20 as u8, 1);
+        assert_eq!(Method::AesGcmSiv as u8, 2);
     }
 
     /// Test that invalid values return errors
     #[test]
     fn test_invalid_method() {
-        let invalid_values = [2, 3, 4, 5, 6, 7, 9, 15, 17, 23, 25, 31, 33, 39, 41, 47, 49, 255];
+        let invalid_values = [3, 4, 5, 6, 7, 9, 15, 17, 23, 25, 31, 33, 39, 41, 47, 49, 255];
 
         for invalid in invalid_values {
             assert!(
@@ -1,5 +1,21 @@
 //! Common types and traits that are used across the various encryption implementations.
 
+mod associated_data;
+pub use crate::layers::encryptors::core::associated_data::AssociatedData;
+
+#[cfg(any(feature = "kdf-blake3", feature = "kdf-sha256"))]
+mod blind_index;
+#[cfg(any(feature = "kdf-blake3", feature = "kdf-sha256"))]
+pub use crate::layers::encryptors::core::blind_index::{BlindIndex, BlindToken, TOKEN_SIZE};
+
+#[cfg(any(feature = "kdf-blake3", feature = "kdf-sha256"))]
+mod deterministic;
+#[cfg(any(feature = "kdf-blake3", feature = "kdf-sha256"))]
+pub use crate::layers::encryptors::core::deterministic::DeterministicCipher;
+
+#[cfg(feature = "serde")]
+pub mod encrypted;
+
 mod encryptable;
 pub use crate::layers::encryptors::core::encryptable::Encryptable;
 
@@ -11,14 +27,48 @@ pub use crate::layers::encryptors::core::errors::DecryptError;
 pub use crate::layers::encryptors::core::errors::EncryptError;
 pub use crate::layers::encryptors::core::errors::Error;
 
+#[cfg(feature = "external-keys")]
+mod external_keys;
+#[cfg(feature = "external-keys")]
+pub use crate::layers::encryptors::core::external_keys::{
+    AsyncExternalKeyProvider,
+    Error as ExternalKeyError,
+    ExternalKeyProvider,
+    FetchedKeys,
+};
+
 mod key_bytes;
 pub use crate::layers::encryptors::core::key_bytes::KeyBytes;
 
+mod key_ring;
+pub use crate::layers::encryptors::core::key_ring::{KeyId, KeyRing, KeyRingProvider};
+
 mod method;
 pub use crate::layers::encryptors::core::method::Method;
 
 mod nonce;
 pub use crate::layers::encryptors::core::nonce::Nonce;
 
+mod nonce_counter;
+pub use crate::layers::encryptors::core::nonce_counter::{
+    Error as NonceCounterError,
+    NonceCounter,
+    NonceStrategy,
+    PREFIX_SIZE as NONCE_PREFIX_SIZE,
+};
+
 mod parameters;
-pub(super) use crate::layers::encryptors::core::parameters::Parameters;
\ No newline at end of file
+pub(super) use crate::layers::encryptors::core::parameters::Parameters;
+pub use crate::layers::encryptors::core::parameters::FORMAT_MARKER;
+pub use crate::layers::encryptors::core::parameters::{
+    Error as ParametersError,
+    accepts_legacy_values,
+    set_accept_legacy_values,
+};
+#[cfg(all(test, any(feature = "encrypt-aes-gcm", feature = "encrypt-chacha20")))]
+pub use crate::layers::encryptors::core::parameters::LEGACY_VALUES_LOCK;
+
+#[cfg(any(feature = "kdf-blake3", feature = "kdf-sha256"))]
+mod tenant_key;
+#[cfg(any(feature = "kdf-blake3", feature = "kdf-sha256"))]
+pub use crate::layers::encryptors::core::tenant_key::TenantKey;
\ No newline at end of file
@@ -0,0 +1,13 @@
+//! Contains the error type returned while handing out counter-based nonces.
+
+// -------------------------------------------------------------------------------------------------
+//
+/// An error returned while handing out counter-based nonces.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Every counter value has been handed out, so no further unique nonce can be built. The key
+    /// must be rotated before anything else is encrypted with it.
+    #[error("nonce counter exhausted: rotate the encryption key")]
+    Exhausted,
+}
@@ -0,0 +1,182 @@
+//! Counter-based nonces, which are unique by construction instead of by chance.
+//!
+//! Randomly generated nonces are only unique as long as the random number generator never repeats
+//! itself, which it may after a VM snapshot is restored, or on a device with little entropy. A
+//! [`NonceCounter`] instead builds each nonce from a random prefix, chosen when the counter is
+//! created, followed by a 64-bit counter that's persisted in the database:
+//!
+//! ```text
+//! nonce = random prefix (4 bytes) | counter (u64 big-endian)
+//! ```
+//!
+//! Record types opt into counter-based nonces with [`Encryptable::NONCE_STRATEGY`].
+
+// Exports
+
+mod error;
+pub use crate::layers::encryptors::core::nonce_counter::error::Error;
+
+// Imports
+
+use crate::layers::encryptors::core::{Encryptable, Nonce};
+use crate::layers::encryptors::impls::NONCE_SIZE;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// Length of the random prefix that starts every counter-based nonce.
+pub const PREFIX_SIZE: usize = NONCE_SIZE - size_of::<u64>();
+
+// -------------------------------------------------------------------------------------------------
+//
+/// How nonces are chosen when a type's values are encrypted.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum NonceStrategy {
+    /// Every nonce is generated randomly by the encryptor. Simple, but uniqueness relies on the
+    /// random number generator never repeating itself.
+    #[default]
+    Random,
+
+    /// Every nonce is drawn from a [`NonceCounter`], which guarantees uniqueness across restarts
+    /// as long as the counter is persisted.
+    Counter,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Hands out unique nonces built from a random prefix and an increasing counter.
+///
+/// The counter's [`position`](Self::position) must be persisted before any value encrypted with
+/// its nonces is, and a new counter started from the persisted position when the database is
+/// reopened. The typed API does this with `Database::nonce_counter` and
+/// `WriteTransaction::next_nonce`.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use atlatl::layers::encryptors::NonceCounter;
+///
+/// let counter = NonceCounter::new(persisted_position);
+/// let first = counter.next()?;
+/// let second = counter.next()?;
+/// assert_ne!(first.into_bytes(), second.into_bytes());
+/// ```
+#[derive(Debug)]
+pub struct NonceCounter {
+    /// Random prefix chosen when the counter was created.
+    prefix: [u8; PREFIX_SIZE],
+
+    /// Counter value of the next nonce to be handed out.
+    next: AtomicU64,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl NonceCounter {
+    /// Instantiates a counter with a freshly generated random prefix, which hands out nonces
+    /// starting from the persisted `position`.
+    #[must_use]
+    pub fn new(position: u64) -> Self {
+        Self::with_prefix(rand::random(), position)
+    }
+
+    /// Instantiates a counter with the given prefix, which hands out nonces starting from
+    /// `position`.
+    ///
+    /// The prefix must never be reused with the same key and an overlapping range of positions.
+    /// Prefer [`NonceCounter::new`], unless the prefix is assigned elsewhere, for example one per
+    /// node in a cluster.
+    #[must_use]
+    pub const fn with_prefix(prefix: [u8; PREFIX_SIZE], position: u64) -> Self {
+        Self { prefix, next: AtomicU64::new(position) }
+    }
+
+    /// Returns the counter's random prefix.
+    #[must_use]
+    pub const fn prefix(&self) -> [u8; PREFIX_SIZE] {
+        self.prefix
+    }
+
+    /// Returns the counter value of the next nonce to be handed out. This is the value to persist.
+    #[must_use]
+    pub fn position(&self) -> u64 {
+        self.next.load(Ordering::Acquire)
+    }
+
+    /// Hands out the next nonce.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Exhausted`] once every counter value has been handed out. The key must
+    ///   be rotated before anything else is encrypted with it.
+    pub fn next(&self) -> Result<Nonce<'static>, Error> {
+        let count = self.next
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| count.checked_add(1))
+            .map_err(|_| Error::Exhausted)?;
+
+        let mut nonce = [0; NONCE_SIZE];
+        nonce[..PREFIX_SIZE].copy_from_slice(&self.prefix);
+        nonce[PREFIX_SIZE..].copy_from_slice(&count.to_be_bytes());
+        Ok(Nonce::from_array(nonce))
+    }
+
+    /// Hands out the next nonce if `V` uses [`NonceStrategy::Counter`], or returns `None` to have
+    /// the encryptor generate a random one.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Exhausted`] once every counter value has been handed out.
+    pub fn nonce_for<V: Encryptable>(&self) -> Result<Option<Nonce<'static>>, Error> {
+        match V::NONCE_STRATEGY {
+            NonceStrategy::Random => Ok(None),
+            NonceStrategy::Counter => self.next().map(Some),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::core::Direction;
+
+    #[test]
+    fn nonces_are_prefixed_and_counted() {
+        let counter = NonceCounter::with_prefix([7; PREFIX_SIZE], 41);
+        let first = counter.next().unwrap().into_bytes();
+        let second = counter.next().unwrap().into_bytes();
+        assert_eq!(first[..PREFIX_SIZE], [7; PREFIX_SIZE]);
+        assert_eq!(first[PREFIX_SIZE..], 41_u64.to_be_bytes());
+        assert_eq!(second[PREFIX_SIZE..], 42_u64.to_be_bytes());
+        assert_eq!(counter.position(), 43);
+
+        let exhausted = NonceCounter::with_prefix([7; PREFIX_SIZE], u64::MAX);
+        assert!(matches!(exhausted.next(), Err(Error::Exhausted)));
+    }
+
+    #[test]
+    fn only_counter_types_draw_from_the_counter() {
+        struct Random;
+        impl Encryptable for Random {
+            const DIRECTION: Direction = Direction::Both;
+        }
+
+        struct Counted;
+        impl Encryptable for Counted {
+            const DIRECTION: Direction = Direction::Both;
+            const NONCE_STRATEGY: NonceStrategy = NonceStrategy::Counter;
+        }
+
+        let counter = NonceCounter::new(0);
+        assert!(counter.nonce_for::<Random>().unwrap().is_none());
+        assert!(counter.nonce_for::<Counted>().unwrap().is_some());
+        assert_eq!(counter.position(), 1);
+    }
+}
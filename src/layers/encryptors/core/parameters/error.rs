@@ -39,4 +39,12 @@ pub enum Error {
         expected_size: usize,
         provided_size: usize,
     },
+
+    /// Legacy value rejected.
+    ///
+    /// The value is in the legacy format, which isn't bound to its table or key, and legacy
+    /// values are no longer accepted. See
+    /// [`set_accept_legacy_values`](super::set_accept_legacy_values).
+    #[error("the value is in the legacy encryption format, which is no longer accepted")]
+    LegacyRejected,
 }
\ No newline at end of file
@@ -9,8 +9,9 @@ pub use crate::layers::encryptors::core::parameters::error::Error;
 // Imports
 
 use crate::layers::core::tail_readers::{TailReader, TailReaderMut};
-use crate::layers::encryptors::core::Nonce;
+use crate::layers::encryptors::core::{AssociatedData, KeyId, Method, Nonce};
 use crate::layers::encryptors::impls::NONCE_SIZE;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 // -------------------------------------------------------------------------------------------------
 //
@@ -26,30 +27,139 @@ use crate::layers::encryptors::impls::NONCE_SIZE;
 ///
 /// # Parameters Structure
 ///
-/// | `nonce`              |
-/// |----------------------|
-/// | `&[u32; NONCE_SIZE]` |
+/// | `nonce`              | `key_id`   | `marker`        |
+/// |----------------------|------------|-----------------|
+/// | `&[u32; NONCE_SIZE]` | `u16` (LE) | `FORMAT_MARKER` |
+///
+/// Values encrypted before the table and key were authenticated as associated data have no key ID
+/// or marker, only a nonce. See [`Format::Legacy`].
 pub struct Parameters<'b> {
+    /// Identifies the key the data was encrypted with, so that it can be looked up in a `KeyRing`
+    /// when the data is decrypted.
+    pub key_id: KeyId,
+
     /// A nonce is a unique, random or pseudo-random number used only once to ensure security by
     /// preventing replay attacks and that identical plaintexts produce different ciphertexts.
     pub nonce: Nonce<'b>,
+
+    /// How the parameters were written, which determines the associated data the value is
+    /// decrypted with.
+    pub format: Format,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// How an encryption layer's parameters were written.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+    /// `data | nonce`. Written before the value's table and key were authenticated as associated
+    /// data, and before key IDs were recorded. These values are decrypted with empty associated
+    /// data, and their key ID is read as `0`.
+    Legacy,
+
+    /// `data | nonce | key_id | marker`. The value's table and key, and the encryption layer's
+    /// descriptor, are authenticated as associated data.
+    Authenticated,
 }
 
+/// Ends the parameters of values written in the [`Format::Authenticated`] format. Legacy values
+/// end with their nonce instead.
+pub const FORMAT_MARKER: [u8; 4] = [0xA7, 0x1A, 0x7E, 0x02];
+
+/// Whether values in the [`Format::Legacy`] format are decrypted. See
+/// [`set_accept_legacy_values`].
+static ACCEPT_LEGACY_VALUES: AtomicBool = AtomicBool::new(true);
+
+/// Held by tests that decrypt legacy values, or that stop accepting them, so that they don't see
+/// each other's changes.
+#[cfg(all(test, any(feature = "encrypt-aes-gcm", feature = "encrypt-chacha20")))]
+pub static LEGACY_VALUES_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
 // -------------------------------------------------------------------------------------------------
 //
 // Method Implementations
 
 impl<'b> Parameters<'b> {
-    /// Instantiates a new `Parameters` struct from a nonce.
+    /// Instantiates a new `Parameters` struct from a key ID and a nonce.
+    #[inline]
+    pub const fn new(key_id: KeyId, nonce: Nonce<'b>) -> Self {
+        Parameters { key_id, nonce, format: Format::Authenticated }
+    }
+
+    /// Returns the parameters with an owned copy of their nonce, so that they outlive the buffer
+    /// they were read from.
     #[inline]
-    pub const fn from_nonce(nonce: Nonce<'b>) -> Self {
-        Parameters { nonce }
+    #[must_use]
+    pub fn into_owned(self) -> Parameters<'static> {
+        Parameters {
+            key_id: self.key_id,
+            nonce: Nonce::from_array(self.nonce.into_bytes()),
+            format: self.format,
+        }
+    }
+
+    /// Reads a value's parameters again as [`Format::Legacy`] parameters, after decrypting it with
+    /// these [`Format::Authenticated`] parameters failed. Returns `None` if these parameters are
+    /// already legacy ones, or if legacy values aren't accepted.
+    ///
+    /// A legacy value's nonce is random, and ends with the [`FORMAT_MARKER`] about once in 2³²
+    /// values, so it's mistaken for an authenticated value's parameters.
+    ///
+    /// `data` is the whole value, including its parameters. Returns the legacy parameters and the
+    /// data they were read from.
+    #[must_use]
+    pub fn as_legacy<'d>(&self, data: &'d [u8]) -> Option<(Parameters<'d>, &'d [u8])> {
+        if self.format == Format::Legacy || !accepts_legacy_values() {
+            return None;
+        }
+        let mut tail_reader = TailReader::from_slice(data);
+        let array: &[u8; NONCE_SIZE] = tail_reader.read_array::<NONCE_SIZE>().ok()?;
+        let parameters = Parameters {
+            key_id: KeyId::default(),
+            nonce: Nonce::from(array),
+            format: Format::Legacy,
+        };
+        Some((parameters, tail_reader.close()))
+    }
+
+    /// Reads a value's parameters again as [`Format::Legacy`] parameters, like
+    /// [`Parameters::as_legacy`], for a value that's decrypted in place. `data` is the value
+    /// without these parameters, which are appended to it again.
+    #[must_use]
+    pub fn into_legacy(self, mut data: Vec<u8>) -> Option<(Parameters<'static>, Vec<u8>)> {
+        if self.format == Format::Legacy || !accepts_legacy_values() {
+            return None;
+        }
+        self.into_data_buffer(&mut data);
+        let mut tail_reader_mut = TailReaderMut::from_vec(data);
+        let array: [u8; NONCE_SIZE] = *tail_reader_mut.read_array::<NONCE_SIZE>().ok()?;
+        let parameters = Parameters {
+            key_id: KeyId::default(),
+            nonce: Nonce::from_array(array),
+            format: Format::Legacy,
+        };
+        Some((parameters, tail_reader_mut.close()))
+    }
+
+    /// Returns the associated data to decrypt the value with. Legacy values were encrypted
+    /// without associated data, so it's empty for them.
+    #[must_use]
+    pub fn associated_data(
+        &self,
+        associated_data: &AssociatedData<'_>,
+        method: Method,
+    ) -> Vec<u8> {
+        match self.format {
+            Format::Legacy => Vec::new(),
+            Format::Authenticated => associated_data.to_bytes(method),
+        }
     }
 
     /// Deserializes `Parameters` from the end of an immutable data buffer.
     ///
-    /// Reads the encryption method (u8) and nonce (size depends on method: `12` bytes for
-    /// `ChaCha20`, `12` bytes for `AesGcm`, etc.) in reverse order from the buffer’s end.
+    /// Reads the format marker, key ID (u16) and nonce (size depends on method: `12` bytes for
+    /// `ChaCha20`, `12` bytes for `AesGcm`, etc.) in reverse order from the buffer’s end. Legacy
+    /// values have no marker or key ID, so only their nonce is read.
     ///
     /// # Layer Structure
     ///
@@ -59,9 +169,9 @@ impl<'b> Parameters<'b> {
     ///
     /// # Parameters Structure
     ///
-    /// | `nonce`              |
-    /// |----------------------|
-    /// | `&[u32; NONCE_SIZE]` |
+    /// | `nonce`              | `key_id`   | `marker`        |
+    /// |----------------------|------------|-----------------|
+    /// | `&[u32; NONCE_SIZE]` | `u16` (LE) | `FORMAT_MARKER` |
     ///
     /// # Errors
     ///
@@ -71,16 +181,27 @@ impl<'b> Parameters<'b> {
     pub fn from_data_buffer(
         tail_reader: &mut TailReader<'b>
     ) -> Result<Self, Error> {
+        let (key_id, format) = if tail_reader.read_marker(&FORMAT_MARKER) {
+            let key_id: &[u8; 2] = tail_reader.read_array::<2>()
+                .map_err(|error| Error::InsufficientData { parameter: "key_id", error })?;
+            (KeyId::new(u16::from_le_bytes(*key_id)), Format::Authenticated)
+        } else if accepts_legacy_values() {
+            (KeyId::default(), Format::Legacy)
+        } else {
+            return Err(Error::LegacyRejected);
+        };
+
         let array: &[u8; NONCE_SIZE] = tail_reader.read_array::<NONCE_SIZE>()
             .map_err(|error| Error::InsufficientData { parameter: "nonce", error })?;
 
-        Ok(Parameters { nonce: Nonce::from(array) })
+        Ok(Parameters { key_id, nonce: Nonce::from(array), format })
     }
 
     /// Deserializes `Parameters` from the end of an mutable data buffer.
     ///
-    /// Reads the encryption method (u8) and nonce (size depends on method: `12` bytes for
-    /// `ChaCha20`, `12` bytes for `AesGcm`, etc.) in reverse order from the buffer’s end.
+    /// Reads the format marker, key ID (u16) and nonce (size depends on method: `12` bytes for
+    /// `ChaCha20`, `12` bytes for `AesGcm`, etc.) in reverse order from the buffer’s end. Legacy
+    /// values have no marker or key ID, so only their nonce is read.
     ///
     /// # Layer Structure
     ///
@@ -90,9 +211,9 @@ impl<'b> Parameters<'b> {
     ///
     /// # Parameters Structure
     ///
-    /// | `nonce`              |
-    /// |----------------------|
-    /// | `&[u32; NONCE_SIZE]` |
+    /// | `nonce`              | `key_id`   | `marker`        |
+    /// |----------------------|------------|-----------------|
+    /// | `&[u32; NONCE_SIZE]` | `u16` (LE) | `FORMAT_MARKER` |
     ///
     /// # Errors
     ///
@@ -102,16 +223,26 @@ impl<'b> Parameters<'b> {
     pub fn from_data_buffer_mut(
         tail_reader: &'b mut TailReaderMut
     ) -> Result<Self, Error> {
+        let (key_id, format) = if tail_reader.read_marker(&FORMAT_MARKER) {
+            let key_id: &[u8; 2] = tail_reader.read_array::<2>()
+                .map_err(|error| Error::InsufficientData { parameter: "key_id", error })?;
+            (KeyId::new(u16::from_le_bytes(*key_id)), Format::Authenticated)
+        } else if accepts_legacy_values() {
+            (KeyId::default(), Format::Legacy)
+        } else {
+            return Err(Error::LegacyRejected);
+        };
+
         let array: &[u8; NONCE_SIZE] = tail_reader.read_array::<NONCE_SIZE>()
             .map_err(|error| Error::InsufficientData { parameter: "nonce", error })?;
 
-        Ok(Parameters { nonce: Nonce::from(array) })
+        Ok(Parameters { key_id, nonce: Nonce::from(array), format })
     }
 
     /// Serializes `Parameters` to a data buffer, appending fields to the end.
     ///
-    /// Appends the nonce (12 bytes) and encryption method (u8) to the provided buffer, matching the
-    /// format expected by `from_data_buffer`.
+    /// Appends the nonce (12 bytes), key ID (u16) and format marker to the provided buffer,
+    /// matching the format expected by `from_data_buffer`.
     ///
     /// # Layer Structure
     ///
@@ -121,9 +252,9 @@ impl<'b> Parameters<'b> {
     ///
     /// # Parameters Structure
     ///
-    /// | `nonce`              |
-    /// |----------------------|
-    /// | `&[u32; NONCE_SIZE]` |
+    /// | `nonce`              | `key_id`   | `marker`        |
+    /// |----------------------|------------|-----------------|
+    /// | `&[u32; NONCE_SIZE]` | `u16` (LE) | `FORMAT_MARKER` |
     ///
     /// # Errors
     ///
@@ -131,5 +262,31 @@ impl<'b> Parameters<'b> {
     #[inline]
     pub fn into_data_buffer(self, buffer: &mut Vec<u8>) {
         buffer.extend(self.nonce.into_bytes());
+        if self.format == Format::Authenticated {
+            buffer.extend(self.key_id.get().to_le_bytes());
+            buffer.extend(FORMAT_MARKER);
+        }
     }
-}
\ No newline at end of file
+}
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Selects whether values in the [`Format::Legacy`] format, which were encrypted before their
+/// table and key were authenticated, are decrypted. They're accepted unless this is called with
+/// `false`.
+///
+/// Legacy values aren't bound to their table or key, so one can be copied into another row and
+/// still be decrypted. Once every legacy value has been re-encrypted, for example by a key
+/// rotation, stop accepting them: values that can't be decrypted in the authenticated format are
+/// then rejected with [`Error::LegacyRejected`], instead of being read as legacy values.
+pub fn set_accept_legacy_values(accept: bool) {
+    ACCEPT_LEGACY_VALUES.store(accept, Ordering::Relaxed);
+}
+
+/// Returns `true` if values in the [`Format::Legacy`] format are decrypted. See
+/// [`set_accept_legacy_values`].
+#[must_use]
+pub fn accepts_legacy_values() -> bool {
+    ACCEPT_LEGACY_VALUES.load(Ordering::Relaxed)
+}
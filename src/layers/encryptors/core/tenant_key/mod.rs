@@ -0,0 +1,167 @@
+//! Tenant keys give every tenant of a shared database its own encryption key.
+//!
+//! Each tenant's key is derived from a single master key and the tenant's name. Records encrypted
+//! under one tenant's key can't be decrypted with another tenant's key, even when both tenants'
+//! tables live in the same database file.
+
+// Imports
+
+use crate::layers::encryptors::impls::KEY_SIZE;
+use crate::layers::encryptors::KeyBytes;
+use zeroize::Zeroize;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// Domain separation prefix used when deriving a tenant key from a master key.
+///
+/// **Warning**: This prefix must never change. It is permanently bound to every record that has
+/// been encrypted with a tenant key. Changing it would render all existing tenant data unreadable.
+const CONTEXT: &str = "atlatl:tenant:";
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The encryption key of a single tenant, derived from a master key and the tenant's name.
+///
+/// # Example
+///
+/// ```rust
+/// use atlatl::layers::encryptors::{KeyBytes, TenantKey};
+///
+/// let master_key = KeyBytes::from_array(&[7_u8; 32]);
+/// let acme = TenantKey::new(&master_key, "acme");
+/// let globex = TenantKey::new(&master_key, "globex");
+/// assert_ne!(*acme.key_bytes(), *globex.key_bytes());
+/// ```
+///
+/// # Notes
+///
+/// * The tenant's name is used for domain separation, so it must be stable for the lifetime of the
+///   tenant's data. Renaming a tenant requires its records to be re-encrypted.
+///
+/// * Anyone holding the master key can derive every tenant's key. Hand out tenant keys, not the
+///   master key, to components that should only see one tenant's data.
+#[derive(Clone)]
+pub struct TenantKey {
+    /// Per-tenant key derived from the master key and the tenant's name.
+    tenant_key: [u8; KEY_SIZE],
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl TenantKey {
+    /// Derives the key of the named tenant from the master key.
+    #[must_use]
+    pub fn new(master_key: &KeyBytes<'_>, tenant: &str) -> Self {
+        Self { tenant_key: derive_tenant_key(master_key, tenant) }
+    }
+
+    /// Returns the tenant's key, ready to be handed to an [`Encryptor`].
+    ///
+    /// [`Encryptor`]: crate::layers::encryptors::Encryptor
+    #[inline]
+    #[must_use]
+    pub fn key_bytes(&self) -> KeyBytes<'_> {
+        KeyBytes::from_array(&self.tenant_key)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl Drop for TenantKey {
+    /// Wipes the derived key from memory.
+    fn drop(&mut self) {
+        self.tenant_key.zeroize();
+    }
+}
+
+impl std::fmt::Debug for TenantKey {
+    /// Formats the `TenantKey` without exposing the derived key.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TenantKey").finish_non_exhaustive()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Key Derivation Implementations
+
+/// Derives a tenant key from the master key using
+/// [Jack O'Connor](https://github.com/oconnor663)'s [blake3](https://crates.io/crates/blake3) crate
+/// in keyed-hash mode.
+#[cfg(feature = "kdf-blake3")]
+fn derive_tenant_key(key: &KeyBytes<'_>, tenant: &str) -> [u8; KEY_SIZE] {
+    let mut hasher = blake3::Hasher::new_keyed(key);
+    hasher.update(CONTEXT.as_bytes());
+    hasher.update(tenant.as_bytes());
+    *hasher.finalize().as_bytes()
+}
+
+/// Derives a tenant key from the master key using HMAC-SHA256 from
+/// [Brian Smith](https://github.com/briansmith)'s [ring](https://crates.io/crates/ring) crate.
+#[cfg(all(feature = "kdf-sha256", not(feature = "kdf-blake3")))]
+fn derive_tenant_key(key: &KeyBytes<'_>, tenant: &str) -> [u8; KEY_SIZE] {
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, key.as_ref());
+    let mut context = ring::hmac::Context::with_key(&key);
+    context.update(CONTEXT.as_bytes());
+    context.update(tenant.as_bytes());
+    context.sign().as_ref().try_into().unwrap() // HMAC-SHA256 is always 32 bytes
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MASTER_KEY: [u8; KEY_SIZE] = [0x42; KEY_SIZE];
+
+    #[test]
+    fn tenants_are_domain_separated() {
+        let master_key = KeyBytes::from_array(&MASTER_KEY);
+        let acme = TenantKey::new(&master_key, "acme");
+        assert_eq!(*acme.key_bytes(), *TenantKey::new(&master_key, "acme").key_bytes());
+        assert_ne!(*acme.key_bytes(), *TenantKey::new(&master_key, "globex").key_bytes());
+        assert_ne!(*acme.key_bytes(), MASTER_KEY);
+    }
+
+    #[cfg(any(
+        feature = "encrypt-aes-gcm",
+        feature = "encrypt-chacha20",
+        feature = "encrypt-aes-gcm-siv"
+    ))]
+    #[test]
+    fn tenants_cannot_decrypt_each_other() {
+        use crate::layers::core::{Bytes, Direction};
+        use crate::layers::encryptors::{ActiveEncryptor, AssociatedData, Encryptable, Encryptor};
+
+        struct Record;
+        impl Encryptable for Record {
+            const DIRECTION: Direction = Direction::Both;
+        }
+
+        let master_key = KeyBytes::from_array(&MASTER_KEY);
+        let acme = TenantKey::new(&master_key, "acme");
+        let globex = TenantKey::new(&master_key, "globex");
+
+        let context = AssociatedData::new("records", b"1");
+        let plain_text = Bytes::from(b"Wile E. Coyote".as_slice());
+        let cipher_text =
+            ActiveEncryptor::<Record>::encrypt(plain_text, acme.key_bytes(), &context, None)
+                .unwrap();
+
+        let stolen =
+            ActiveEncryptor::<Record>::decrypt(cipher_text.clone(), globex.key_bytes(), &context);
+        assert!(stolen.is_err());
+        let decrypted =
+            ActiveEncryptor::<Record>::decrypt(cipher_text, acme.key_bytes(), &context).unwrap();
+        assert_eq!(decrypted.as_slice(), b"Wile E. Coyote");
+    }
+}
@@ -1,9 +1,10 @@
 //! Support for [Artyom Pavlov](https://github.com/newpavlov)'s
 //! [chacha20poly1305](https://crates.io/crates/chacha20poly1305) crate.
 
-use aes_gcm::{aead::{Aead, AeadCore, AeadInPlace, KeyInit, OsRng}, Aes256Gcm};
+use aes_gcm::{aead::{Aead, AeadCore, AeadInPlace, KeyInit, OsRng, Payload}, Aes256Gcm};
 use crate::layers::core::{Bytes, tail_readers::{TailReader, TailReaderMut}};
 use crate::layers::encryptors::core::{
+    AssociatedData,
     Encryptable,
     EncryptError,
     Encryptor,
@@ -50,6 +51,9 @@ impl<'b, 'k, V: Encryptable> Encryptor<'b, 'k, V> for AesGcm<V> {
     ///
     /// * `key` · The secret encryption key used to transform the data.
     ///
+    /// * `associated_data` · The table and key the data is stored under, which are authenticated
+    ///   along with the encryption layer's descriptor.
+    ///
     /// # Errors
     ///
     /// Consult the documentation of the encryptor backend you are using for more detail on
@@ -65,19 +69,23 @@ impl<'b, 'k, V: Encryptable> Encryptor<'b, 'k, V> for AesGcm<V> {
     fn encrypt(
         plain_text: Bytes<'b>,
         key: KeyBytes<'k>,
+        associated_data: &AssociatedData<'_>,
         nonce: Option<Nonce<'k>>,
     ) -> Result<Bytes<'b>, EncryptError> {
         let cipher = Aes256Gcm::new(key.as_ref().into());
-    	if let Some(nonce) = nonce {
-            let mut cipher_text = cipher.encrypt(nonce.as_ref().into(), plain_text.as_slice())?;
-            Parameters::from_nonce(nonce).into_data_buffer(&mut cipher_text);
-            Ok(cipher_text.into())
-        } else {
-            let nonce = Nonce::from_array(Aes256Gcm::generate_nonce(&mut OsRng).into());
-            let mut cipher_text = cipher.encrypt(nonce.as_ref().into(), plain_text.as_slice())?;
-            Parameters::from_nonce(nonce).into_data_buffer(&mut cipher_text);
-            Ok(cipher_text.into())
-        }
+        let aad = associated_data.to_bytes(Method::AesGcm);
+        let payload = Payload { msg: plain_text.as_slice(), aad: &aad };
+        let nonce = nonce.unwrap_or_else(|| {
+            Nonce::from_array(Aes256Gcm::generate_nonce(&mut OsRng).into())
+        });
+        let cipher_text = cipher.encrypt(nonce.as_ref().into(), payload);
+
+        // The plain text is no longer needed, whether or not encryption succeeded:
+        plain_text.wipe();
+
+        let mut cipher_text = cipher_text?;
+        Parameters::new(key.id(), nonce).into_data_buffer(&mut cipher_text);
+        Ok(cipher_text.into())
     }
 
     /// Reverses the encryption process using the same secret key, restoring the encrypted bytes
@@ -90,11 +98,14 @@ impl<'b, 'k, V: Encryptable> Encryptor<'b, 'k, V> for AesGcm<V> {
     /// * `key` · The same secret key used during encryption, required to reverse the
     ///   transformation.
     ///
+    /// * `associated_data` · The same table and key the data was encrypted with.
+    ///
     /// # Errors
     ///
     /// This method may fail for several reasons, including:
     ///
-    /// * Invalid key, or
+    /// * Invalid key,
+    /// * The data was encrypted for another table or key, or
     /// * Input bytes are corrupted or malformed.
     ///
     /// Consult the documentation of the encryptor backend you are using for more detail on
@@ -109,7 +120,8 @@ impl<'b, 'k, V: Encryptable> Encryptor<'b, 'k, V> for AesGcm<V> {
     #[inline]
     fn decrypt(
     	cipher_text: Bytes<'b>,
-    	key: KeyBytes<'k>
+    	key: KeyBytes<'k>,
+    	associated_data: &AssociatedData<'_>,
     ) -> Result<Bytes<'b>, crate::layers::encryptors::DecryptError> {
         let (metadata, cipher_text) = cipher_text.into_parts();
         let cipher = Aes256Gcm::new(key.as_ref().into());
@@ -119,15 +131,33 @@ impl<'b, 'k, V: Encryptable> Encryptor<'b, 'k, V> for AesGcm<V> {
                 // Borrowed: use out-of-place decryption (allocates only for plaintext)
                 let mut tail_reader = TailReader::from_slice(slice);
                 let parameters = Parameters::from_data_buffer(&mut tail_reader)?;
-                let plain_text = cipher.decrypt(parameters.nonce.as_ref().into(), tail_reader.close())?;
+                let aad = parameters.associated_data(associated_data, Method::AesGcm);
+                let payload = Payload { msg: tail_reader.close(), aad: &aad };
+                let plain_text = cipher
+                    .decrypt(parameters.nonce.as_ref().into(), payload)
+                    .or_else(|error| {
+                        // A legacy value whose nonce happens to end with the format marker:
+                        let (legacy, msg) = parameters.as_legacy(slice).ok_or(error)?;
+                        cipher.decrypt(legacy.nonce.as_ref().into(), Payload { msg, aad: &[] })
+                    })?;
                 Ok(Bytes::from_parts(metadata, plain_text.into()))
             }
             Cow::Owned(vec) => {
                 // Owned: decrypt in-place (no extra allocation)
                 let mut tail_reader_mut = TailReaderMut::from_vec(vec);
-                let nonce = *Parameters::from_data_buffer_mut(&mut tail_reader_mut)?.nonce;
+                let parameters = Parameters::from_data_buffer_mut(&mut tail_reader_mut)?
+                    .into_owned();
+                let aad = parameters.associated_data(associated_data, Method::AesGcm);
                 let mut bytes_buf: Vec<u8> = tail_reader_mut.close();
-                cipher.decrypt_in_place(nonce.as_ref().into(), &[], &mut bytes_buf)?;
+                let decrypted =
+                    cipher.decrypt_in_place(parameters.nonce.as_ref().into(), &aad, &mut bytes_buf);
+                if let Err(error) = decrypted {
+                    // A legacy value whose nonce happens to end with the format marker. Failed
+                    // decryption leaves the cipher text as it was:
+                    let (legacy, legacy_buf) = parameters.into_legacy(bytes_buf).ok_or(error)?;
+                    bytes_buf = legacy_buf;
+                    cipher.decrypt_in_place(legacy.nonce.as_ref().into(), &[], &mut bytes_buf)?;
+                }
                 Ok(Bytes::from_parts(metadata, bytes_buf.into()))
             }
         }
@@ -142,6 +172,9 @@ impl<'b, 'k, V: Encryptable> Encryptor<'b, 'k, V> for AesGcm<V> {
 mod tests {
     use super::*;
     use crate::layers::core::Direction;
+    use crate::layers::encryptors::core::LEGACY_VALUES_LOCK;
+    use crate::layers::encryptors::core::{FORMAT_MARKER, set_accept_legacy_values};
+    use crate::layers::encryptors::impls::NONCE_SIZE;
 
     // Test types implementing Encryptable with different directions
     struct AlwaysEncrypt;
@@ -149,6 +182,8 @@ mod tests {
         const DIRECTION: Direction = Direction::Both;
     }
 
+    const CONTEXT: AssociatedData<'static> = AssociatedData::new("creatures", b"7");
+
     #[test]
     fn test_symmetric_encryption_both_encryption_direction() {
         let key = b"an example very very secret key."; // 32 bytes
@@ -156,14 +191,14 @@ mod tests {
         let bytes = Bytes::from(original_data.as_slice());
 
         // Encrypt
-        let encrypted = AesGcm::<AlwaysEncrypt>::encrypt(bytes, key.into(), None)
+        let encrypted = AesGcm::<AlwaysEncrypt>::encrypt(bytes, key.into(), &CONTEXT, None)
             .expect("Encryption should succeed");
 
         // Verify data is actually encrypted (different from original)
         assert_ne!(encrypted.as_slice(), original_data);
 
         // Decrypt
-        let decrypted = AesGcm::<AlwaysEncrypt>::decrypt(encrypted, key.into())
+        let decrypted = AesGcm::<AlwaysEncrypt>::decrypt(encrypted, key.into(), &CONTEXT)
             .expect("Decryption should succeed");
 
         // Verify decrypted data matches original
@@ -177,19 +212,19 @@ mod tests {
         let bytes = Bytes::from(original_data.as_slice());
 
         // Encrypt the same data multiple times
-        let encrypted1 = AesGcm::<AlwaysEncrypt>::encrypt(bytes.clone(), key.into(), None)
+        let encrypted1 = AesGcm::<AlwaysEncrypt>::encrypt(bytes.clone(), key.into(), &CONTEXT, None)
             .expect("First encryption should succeed");
-        let encrypted2 = AesGcm::<AlwaysEncrypt>::encrypt(bytes.clone(), key.into(), None)
+        let encrypted2 = AesGcm::<AlwaysEncrypt>::encrypt(bytes.clone(), key.into(), &CONTEXT, None)
             .expect("Second encryption should succeed");
 
         // Encrypted data should be different due to different nonces
         assert_ne!(encrypted1.as_slice(), encrypted2.as_slice());
 
         // But both should decrypt to the same original data
-        let decrypted1 = AesGcm::<AlwaysEncrypt>::decrypt(encrypted1, key.into())
+        let decrypted1 = AesGcm::<AlwaysEncrypt>::decrypt(encrypted1, key.into(), &CONTEXT)
             .inspect_err(|error| println!("{error:#?}"))
             .expect("First decryption should succeed");
-        let decrypted2 = AesGcm::<AlwaysEncrypt>::decrypt(encrypted2, key.into())
+        let decrypted2 = AesGcm::<AlwaysEncrypt>::decrypt(encrypted2, key.into(), &CONTEXT)
             .inspect_err(|error| println!("{error:#?}"))
             .expect("Second decryption should succeed");
 
@@ -203,11 +238,11 @@ mod tests {
         let empty_data = b"";
         let bytes = Bytes::from(empty_data.as_slice());
 
-        let encrypted = AesGcm::<AlwaysEncrypt>::encrypt(bytes, key.into(), None)
+        let encrypted = AesGcm::<AlwaysEncrypt>::encrypt(bytes, key.into(), &CONTEXT, None)
             .inspect_err(|error| println!("{error:#?}"))
             .expect("Encryption of empty data should succeed");
 
-        let decrypted = AesGcm::<AlwaysEncrypt>::decrypt(encrypted, key.into())
+        let decrypted = AesGcm::<AlwaysEncrypt>::decrypt(encrypted, key.into(), &CONTEXT)
             .inspect_err(|error| println!("{error:?}"))
             .expect("Decryption of empty data should succeed");
 
@@ -220,10 +255,10 @@ mod tests {
         let large_data = vec![0x42u8; 10000]; // 10KB of data
         let bytes = Bytes::from(large_data.as_slice());
 
-        let encrypted = AesGcm::<AlwaysEncrypt>::encrypt(bytes, key.into(), None)
+        let encrypted = AesGcm::<AlwaysEncrypt>::encrypt(bytes, key.into(), &CONTEXT, None)
             .expect("Encryption of large data should succeed");
 
-        let decrypted = AesGcm::<AlwaysEncrypt>::decrypt(encrypted, key.into())
+        let decrypted = AesGcm::<AlwaysEncrypt>::decrypt(encrypted, key.into(), &CONTEXT)
             .expect("Decryption of large data should succeed");
 
         assert_eq!(decrypted.as_slice(), large_data.as_slice());
@@ -237,11 +272,11 @@ mod tests {
         let bytes = Bytes::from(original_data.as_slice());
 
         // Encrypt with key1
-        let encrypted = AesGcm::<AlwaysEncrypt>::encrypt(bytes, key1, None)
+        let encrypted = AesGcm::<AlwaysEncrypt>::encrypt(bytes, key1, &CONTEXT, None)
             .expect("Encryption should succeed");
 
         // Try to decrypt with key2 (should fail)
-        let result = AesGcm::<AlwaysEncrypt>::decrypt(encrypted, key2);
+        let result = AesGcm::<AlwaysEncrypt>::decrypt(encrypted, key2, &CONTEXT);
         assert!(result.is_err(), "Decryption with wrong key should fail");
     }
 
@@ -252,7 +287,7 @@ mod tests {
         let bytes = Bytes::from(original_data.as_slice());
 
         // Encrypt data
-        let mut encrypted = AesGcm::<AlwaysEncrypt>::encrypt(bytes, key.into(), None)
+        let mut encrypted = AesGcm::<AlwaysEncrypt>::encrypt(bytes, key.into(), &CONTEXT, None)
             .expect("Encryption should succeed")
             .to_vec();
 
@@ -262,10 +297,100 @@ mod tests {
         }
 
         // Try to decrypt corrupted data (should fail)
-        let result = AesGcm::<AlwaysEncrypt>::decrypt(encrypted.into(), key.into());
+        let result = AesGcm::<AlwaysEncrypt>::decrypt(encrypted.into(), key.into(), &CONTEXT);
         assert!(result.is_err(), "Decryption of corrupted data should fail");
     }
 
+    #[test]
+    fn test_spliced_cipher_text_fails() {
+        let key = b"an example very very secret key."; // 32 bytes
+        let bytes = Bytes::from(b"Hello, World!".as_slice());
+
+        let encrypted = AesGcm::<AlwaysEncrypt>::encrypt(bytes, key.into(), &CONTEXT, None)
+            .expect("Encryption should succeed");
+
+        // Moving the cipher text to another key or table must fail authentication
+        let other_key = AssociatedData::new("creatures", b"8");
+        let other_table = AssociatedData::new("tenant42.creatures", b"7");
+        for context in [other_key, other_table] {
+            let result = AesGcm::<AlwaysEncrypt>::decrypt(encrypted.clone(), key.into(), &context);
+            assert!(result.is_err(), "Decryption in another context should fail");
+        }
+    }
+
+    #[test]
+    fn test_legacy_cipher_text_decrypts() {
+        // Written before associated data was authenticated: the cipher text and its nonce, with
+        // no key ID or format marker.
+        const LEGACY: [u8; 41] = [
+            245, 148, 236, 24, 22, 54, 33, 218, 147, 187, 232, 161,
+            116, 50, 81, 194, 220, 131, 50, 31, 228, 204, 131, 95,
+            143, 75, 83, 238, 54,
+            7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+        ];
+        let _lock = LEGACY_VALUES_LOCK.lock().unwrap();
+        let key = b"an example very very secret key."; // 32 bytes
+
+        // Borrowed and owned bytes are decrypted differently:
+        for bytes in [Bytes::from(LEGACY.as_slice()), Bytes::from(LEGACY.to_vec())] {
+            let decrypted = AesGcm::<AlwaysEncrypt>::decrypt(bytes, key.into(), &CONTEXT)
+                .expect("Decryption of a legacy value should succeed");
+            assert_eq!(decrypted.as_slice(), b"Hello, World!");
+        }
+    }
+
+    #[test]
+    fn test_legacy_nonce_ending_with_the_format_marker_decrypts() {
+        let _lock = LEGACY_VALUES_LOCK.lock().unwrap();
+        let key = b"an example very very secret key."; // 32 bytes
+
+        // One in 2³² legacy nonces ends with the format marker:
+        let mut nonce = [7; NONCE_SIZE];
+        nonce[NONCE_SIZE - FORMAT_MARKER.len()..].copy_from_slice(&FORMAT_MARKER);
+        let mut legacy = Aes256Gcm::new(key.into())
+            .encrypt((&nonce).into(), b"Hello, World!".as_slice())
+            .unwrap();
+        legacy.extend(nonce);
+
+        for bytes in [Bytes::from(legacy.as_slice()), Bytes::from(legacy.clone())] {
+            let decrypted = AesGcm::<AlwaysEncrypt>::decrypt(bytes, key.into(), &CONTEXT)
+                .expect("Decryption of a legacy value should succeed");
+            assert_eq!(decrypted.as_slice(), b"Hello, World!");
+        }
+    }
+
+    #[test]
+    fn test_legacy_values_can_be_rejected() {
+        let _lock = LEGACY_VALUES_LOCK.lock().unwrap();
+        let key = b"an example very very secret key."; // 32 bytes
+        let mut legacy = Aes256Gcm::new(key.into())
+            .encrypt((&[7; NONCE_SIZE]).into(), b"Hello, World!".as_slice())
+            .unwrap();
+        legacy.extend([7; NONCE_SIZE]);
+        let authenticated = AesGcm::<AlwaysEncrypt>::encrypt(
+            Bytes::from(b"Hello, World!".as_slice()),
+            key.into(),
+            &CONTEXT,
+            None,
+        ).unwrap();
+
+        set_accept_legacy_values(false);
+        let rejected = [Bytes::from(legacy.as_slice()), Bytes::from(legacy.clone())]
+            .map(|bytes| AesGcm::<AlwaysEncrypt>::decrypt(bytes, key.into(), &CONTEXT));
+        let accepted = AesGcm::<AlwaysEncrypt>::decrypt(authenticated, key.into(), &CONTEXT);
+        set_accept_legacy_values(true);
+
+        for result in rejected {
+            assert!(matches!(
+                result,
+                Err(crate::layers::encryptors::DecryptError::Parameters {
+                    source: crate::layers::encryptors::ParametersError::LegacyRejected
+                })
+            ));
+        }
+        assert_eq!(accepted.unwrap().as_slice(), b"Hello, World!");
+    }
+
     #[test]
     fn test_method_returns_correct_value() {
         assert_eq!(AesGcm::<AlwaysEncrypt>::METHOD, Method::AesGcm);
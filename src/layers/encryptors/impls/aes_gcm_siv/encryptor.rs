@@ -0,0 +1,335 @@
+//! Support for [Tony Arcieri](https://github.com/tarcieri)'s
+//! [aes-gcm-siv](https://crates.io/crates/aes-gcm-siv) crate.
+
+use aes_gcm_siv::{aead::{Aead, AeadCore, AeadInPlace, KeyInit, OsRng, Payload}, Aes256GcmSiv};
+use crate::layers::core::{Bytes, tail_readers::{TailReader, TailReaderMut}};
+use crate::layers::encryptors::core::{
+    AssociatedData,
+    Encryptable,
+    EncryptError,
+    Encryptor,
+    KeyBytes,
+    Method,
+    Nonce,
+    Parameters
+};
+use crate::layers::encryptors::impls::aes_gcm_siv::AesGcmSiv;
+use std::borrow::Cow;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl<'b, 'k, V: Encryptable> Encryptor<'b, 'k, V> for AesGcmSiv<V> {
+    /// Returns the encryption method that the current `Encryptor` trait implements.
+    ///
+    /// This enables runtime identification of the encryption algorithm in use, allowing
+    /// applications to log compression details, or store metadata about how data was processed in
+    /// the data pipeline.
+    const METHOD: Method = Method::AesGcmSiv;
+
+    /// Transforms readable data into an unreadable form using a secret key and unique nonce,
+    /// ensuring only authorized parties can access the original information.
+    ///
+    /// # Arguments
+    ///
+    /// * `plain_text` · The original data to be encrypted, wrapped in a `Bytes` that may reference
+    ///   borrowed application bytes.
+    ///
+    /// * `nonce` · A unique value used once per encryption operation to ensure the same `plaintext`
+    ///   produces different `ciphertext`.
+    ///
+    ///   If no nonce is provided, one will be randomly generated. Unlike AES-GCM, reusing a nonce
+    ///   with the same key doesn't compromise the key or allow forgeries. It only reveals whether
+    ///   two values encrypted with that nonce are identical.
+    ///
+    /// * `key` · The secret encryption key used to transform the data.
+    ///
+    /// * `associated_data` · The table and key the data is stored under, which are authenticated
+    ///   along with the encryption layer's descriptor.
+    ///
+    /// # Errors
+    ///
+    /// Consult the documentation of the encryptor backend you are using for more detail on
+    /// recovery behavior and potential limitations: <https://docs.rs/aes-gcm-siv>
+    ///
+    /// # Generics & Lifetimes
+    ///
+    /// * `V` generic represents the user's value type, for example: `User`, `String`, etc.
+    /// * `b` lifetime represents bytes potentially being borrowed from the host application.
+    /// * 'k' lifetime represents a key potentially being borrowed from a `KeyRing` or
+    ///   `KeyProvider`.
+    #[inline]
+    fn encrypt(
+        plain_text: Bytes<'b>,
+        key: KeyBytes<'k>,
+        associated_data: &AssociatedData<'_>,
+        nonce: Option<Nonce<'k>>,
+    ) -> Result<Bytes<'b>, EncryptError> {
+        let cipher = Aes256GcmSiv::new(key.as_ref().into());
+        let aad = associated_data.to_bytes(Method::AesGcmSiv);
+        let payload = Payload { msg: plain_text.as_slice(), aad: &aad };
+        let nonce = nonce.unwrap_or_else(|| {
+            Nonce::from_array(Aes256GcmSiv::generate_nonce(&mut OsRng).into())
+        });
+        let cipher_text = cipher.encrypt(nonce.as_ref().into(), payload);
+
+        // The plain text is no longer needed, whether or not encryption succeeded:
+        plain_text.wipe();
+
+        let mut cipher_text = cipher_text?;
+        Parameters::new(key.id(), nonce).into_data_buffer(&mut cipher_text);
+        Ok(cipher_text.into())
+    }
+
+    /// Reverses the encryption process using the same secret key, restoring the encrypted bytes
+    /// back to their original readable form.
+    ///
+    /// # Arguments
+    ///
+    /// * `cipher_text` · The encrypted data to be decrypted, wrapped in a `Bytes`.
+    ///
+    /// * `key` · The same secret key used during encryption, required to reverse the
+    ///   transformation.
+    ///
+    /// * `associated_data` · The same table and key the data was encrypted with.
+    ///
+    /// # Errors
+    ///
+    /// This method may fail for several reasons, including:
+    ///
+    /// * Invalid key,
+    /// * The data was encrypted for another table or key, or
+    /// * Input bytes are corrupted or malformed.
+    ///
+    /// Consult the documentation of the encryptor backend you are using for more detail on
+    /// recovery behavior and potential limitations: <https://docs.rs/aes-gcm-siv>
+    ///
+    /// # Generics & Lifetimes
+    ///
+    /// * `V` generic represents the user's value type, for example: `User`, `String`, etc.
+    /// * `b` lifetime represents bytes potentially being borrowed from the `redb` database.
+    /// * 'k' lifetime represents a key potentially being borrowed from a `KeyRing` or
+    ///   `KeyProvider`.
+    #[inline]
+    fn decrypt(
+    	cipher_text: Bytes<'b>,
+    	key: KeyBytes<'k>,
+    	associated_data: &AssociatedData<'_>,
+    ) -> Result<Bytes<'b>, crate::layers::encryptors::DecryptError> {
+        let (metadata, cipher_text) = cipher_text.into_parts();
+        let cipher = Aes256GcmSiv::new(key.as_ref().into());
+
+        match cipher_text {
+            Cow::Borrowed(slice) => {
+                // Borrowed: use out-of-place decryption (allocates only for plaintext)
+                let mut tail_reader = TailReader::from_slice(slice);
+                let parameters = Parameters::from_data_buffer(&mut tail_reader)?;
+                let aad = parameters.associated_data(associated_data, Method::AesGcmSiv);
+                let payload = Payload { msg: tail_reader.close(), aad: &aad };
+                let plain_text = cipher
+                    .decrypt(parameters.nonce.as_ref().into(), payload)
+                    .or_else(|error| {
+                        // A legacy value whose nonce happens to end with the format marker:
+                        let (legacy, msg) = parameters.as_legacy(slice).ok_or(error)?;
+                        cipher.decrypt(legacy.nonce.as_ref().into(), Payload { msg, aad: &[] })
+                    })?;
+                Ok(Bytes::from_parts(metadata, plain_text.into()))
+            }
+            Cow::Owned(vec) => {
+                // Owned: decrypt in-place (no extra allocation)
+                let mut tail_reader_mut = TailReaderMut::from_vec(vec);
+                let parameters = Parameters::from_data_buffer_mut(&mut tail_reader_mut)?
+                    .into_owned();
+                let aad = parameters.associated_data(associated_data, Method::AesGcmSiv);
+                let mut bytes_buf: Vec<u8> = tail_reader_mut.close();
+                let decrypted =
+                    cipher.decrypt_in_place(parameters.nonce.as_ref().into(), &aad, &mut bytes_buf);
+                if let Err(error) = decrypted {
+                    // A legacy value whose nonce happens to end with the format marker. Failed
+                    // decryption leaves the cipher text as it was:
+                    let (legacy, legacy_buf) = parameters.into_legacy(bytes_buf).ok_or(error)?;
+                    bytes_buf = legacy_buf;
+                    cipher.decrypt_in_place(legacy.nonce.as_ref().into(), &[], &mut bytes_buf)?;
+                }
+                Ok(Bytes::from_parts(metadata, bytes_buf.into()))
+            }
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::core::Direction;
+
+    // Test types implementing Encryptable with different directions
+    struct AlwaysEncrypt;
+    impl Encryptable for AlwaysEncrypt {
+        const DIRECTION: Direction = Direction::Both;
+    }
+
+    const CONTEXT: AssociatedData<'static> = AssociatedData::new("creatures", b"7");
+
+    #[test]
+    fn test_symmetric_encryption_both_encryption_direction() {
+        let key = b"an example very very secret key."; // 32 bytes
+        let original_data = b"Hello, World! This is a test message.";
+        let bytes = Bytes::from(original_data.as_slice());
+
+        // Encrypt
+        let encrypted = AesGcmSiv::<AlwaysEncrypt>::encrypt(bytes, key.into(), &CONTEXT, None)
+            .expect("Encryption should succeed");
+
+        // Verify data is actually encrypted (different from original)
+        assert_ne!(encrypted.as_slice(), original_data);
+
+        // Decrypt
+        let decrypted = AesGcmSiv::<AlwaysEncrypt>::decrypt(encrypted, key.into(), &CONTEXT)
+            .expect("Decryption should succeed");
+
+        // Verify decrypted data matches original
+        assert_eq!(decrypted.as_slice(), original_data);
+    }
+
+    #[test]
+    fn test_nonce_uniqueness() {
+        let key = b"an example very very secret key."; // 32 bytes
+        let original_data = b"Hello, World! This is a test message.";
+        let bytes = Bytes::from(original_data.as_slice());
+
+        // Encrypt the same data multiple times
+        let encrypted1 = AesGcmSiv::<AlwaysEncrypt>::encrypt(bytes.clone(), key.into(), &CONTEXT, None)
+            .expect("First encryption should succeed");
+        let encrypted2 = AesGcmSiv::<AlwaysEncrypt>::encrypt(bytes.clone(), key.into(), &CONTEXT, None)
+            .expect("Second encryption should succeed");
+
+        // Encrypted data should be different due to different nonces
+        assert_ne!(encrypted1.as_slice(), encrypted2.as_slice());
+
+        // But both should decrypt to the same original data
+        let decrypted1 = AesGcmSiv::<AlwaysEncrypt>::decrypt(encrypted1, key.into(), &CONTEXT)
+            .inspect_err(|error| println!("{error:#?}"))
+            .expect("First decryption should succeed");
+        let decrypted2 = AesGcmSiv::<AlwaysEncrypt>::decrypt(encrypted2, key.into(), &CONTEXT)
+            .inspect_err(|error| println!("{error:#?}"))
+            .expect("Second decryption should succeed");
+
+        assert_eq!(decrypted1.as_slice(), original_data);
+        assert_eq!(decrypted2.as_slice(), original_data);
+    }
+
+    #[test]
+    fn test_empty_data() {
+        let key = b"an example very very secret key."; // 32 bytes
+        let empty_data = b"";
+        let bytes = Bytes::from(empty_data.as_slice());
+
+        let encrypted = AesGcmSiv::<AlwaysEncrypt>::encrypt(bytes, key.into(), &CONTEXT, None)
+            .inspect_err(|error| println!("{error:#?}"))
+            .expect("Encryption of empty data should succeed");
+
+        let decrypted = AesGcmSiv::<AlwaysEncrypt>::decrypt(encrypted, key.into(), &CONTEXT)
+            .inspect_err(|error| println!("{error:?}"))
+            .expect("Decryption of empty data should succeed");
+
+        assert_eq!(decrypted.as_slice(), empty_data);
+    }
+
+    #[test]
+    fn test_large_data() {
+        let key = b"an example very very secret key."; // 32 bytes
+        let large_data = vec![0x42u8; 10000]; // 10KB of data
+        let bytes = Bytes::from(large_data.as_slice());
+
+        let encrypted = AesGcmSiv::<AlwaysEncrypt>::encrypt(bytes, key.into(), &CONTEXT, None)
+            .expect("Encryption of large data should succeed");
+
+        let decrypted = AesGcmSiv::<AlwaysEncrypt>::decrypt(encrypted, key.into(), &CONTEXT)
+            .expect("Decryption of large data should succeed");
+
+        assert_eq!(decrypted.as_slice(), large_data.as_slice());
+    }
+
+    #[test]
+    fn test_wrong_key_fails() {
+        let key1: KeyBytes = b"an example very very secret key.".into(); // 32 bytes
+        let key2: KeyBytes = b"another example very secret key.".into(); // 32 bytes
+        let original_data = b"Hello, World! This is a test message.";
+        let bytes = Bytes::from(original_data.as_slice());
+
+        // Encrypt with key1
+        let encrypted = AesGcmSiv::<AlwaysEncrypt>::encrypt(bytes, key1, &CONTEXT, None)
+            .expect("Encryption should succeed");
+
+        // Try to decrypt with key2 (should fail)
+        let result = AesGcmSiv::<AlwaysEncrypt>::decrypt(encrypted, key2, &CONTEXT);
+        assert!(result.is_err(), "Decryption with wrong key should fail");
+    }
+
+    #[test]
+    fn test_corrupted_data_fails() {
+        let key = b"an example very very secret key."; // 32 bytes
+        let original_data = b"Hello, World! This is a test message.";
+        let bytes = Bytes::from(original_data.as_slice());
+
+        // Encrypt data
+        let mut encrypted = AesGcmSiv::<AlwaysEncrypt>::encrypt(bytes, key.into(), &CONTEXT, None)
+            .expect("Encryption should succeed")
+            .to_vec();
+
+        // Corrupt the encrypted data
+        if let Some(byte) = encrypted.get_mut(0) {
+            *byte = byte.wrapping_add(1);
+        }
+
+        // Try to decrypt corrupted data (should fail)
+        let result = AesGcmSiv::<AlwaysEncrypt>::decrypt(encrypted.into(), key.into(), &CONTEXT);
+        assert!(result.is_err(), "Decryption of corrupted data should fail");
+    }
+
+    #[test]
+    fn test_spliced_cipher_text_fails() {
+        let key = b"an example very very secret key."; // 32 bytes
+        let bytes = Bytes::from(b"Hello, World!".as_slice());
+
+        let encrypted = AesGcmSiv::<AlwaysEncrypt>::encrypt(bytes, key.into(), &CONTEXT, None)
+            .expect("Encryption should succeed");
+
+        // Moving the cipher text to another key or table must fail authentication
+        let other_key = AssociatedData::new("creatures", b"8");
+        let other_table = AssociatedData::new("tenant42.creatures", b"7");
+        for context in [other_key, other_table] {
+            let result = AesGcmSiv::<AlwaysEncrypt>::decrypt(encrypted.clone(), key.into(), &context);
+            assert!(result.is_err(), "Decryption in another context should fail");
+        }
+    }
+
+    #[test]
+    fn test_reused_nonce_only_reveals_equality() {
+        let key = b"an example very very secret key."; // 32 bytes
+        let encrypt = |data: &'static [u8]| {
+            let nonce = Nonce::from_array([7; crate::layers::encryptors::impls::NONCE_SIZE]);
+            AesGcmSiv::<AlwaysEncrypt>::encrypt(data.into(), key.into(), &CONTEXT, Some(nonce))
+                .expect("Encryption should succeed")
+        };
+
+        // With a repeated nonce, identical values encrypt identically, and others don't:
+        let wile = encrypt(b"Wile E. Coyote");
+        assert_eq!(wile.as_slice(), encrypt(b"Wile E. Coyote").as_slice());
+        assert_ne!(wile.as_slice(), encrypt(b"Road Runner").as_slice());
+
+        let decrypted = AesGcmSiv::<AlwaysEncrypt>::decrypt(wile, key.into(), &CONTEXT)
+            .expect("Decryption should succeed");
+        assert_eq!(decrypted.as_slice(), b"Wile E. Coyote");
+    }
+
+    #[test]
+    fn test_method_returns_correct_value() {
+        assert_eq!(AesGcmSiv::<AlwaysEncrypt>::METHOD, Method::AesGcmSiv);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,48 @@
+//! AES-GCM-SIV encryption using [Tony Arcieri](https://github.com/tarcieri)'s
+//! [aes-gcm-siv](https://crates.io/crates/aes-gcm-siv) crate.
+
+mod encryptor;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// `AesGcmSiv`'s key size is `32`-bytes or `256`-bits.
+pub const KEY_SIZE: usize = 32;
+
+/// `AesGcmSiv`'s nonce size is `12`-bytes or `96`-bits.
+pub const NONCE_SIZE: usize = 12;
+
+/// The digest to be used when working with the `ring` crate for SHA (Secure Hash Algorithm).
+#[cfg(feature = "kdf-sha256")]
+pub const RING_SHA256_DIGEST: &'static ring::digest::Algorithm = &ring::digest::SHA256;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// AES-GCM-SIV (Advanced Encryption Standard - Galois/Counter Mode - Synthetic Initialization
+/// Vector) is a nonce-misuse-resistant authenticated encryption algorithm.
+///
+/// With AES-GCM or ChaCha20-Poly1305, encrypting two values with the same key and nonce reveals
+/// the difference between the values, and can allow messages to be forged. This can happen without
+/// any bug in the application, for example when a VM snapshot is restored and its random number
+/// generator replays the same state. The benefits and features of AES-GCM-SIV include:
+///
+/// * Nonce Misuse Resistance: The authentication tag is derived from the plain text and used as the
+///   counter mode's initialization vector. A repeated nonce only reveals whether two values are
+///   identical, and doesn't compromise the key or allow forgeries.
+///
+/// * Authenticated Encryption: AES-GCM-SIV belongs to the class of authenticated encryption with
+///   associated data (AEAD) methods, and produces the same sized output as AES-GCM.
+///
+/// * Performance: AES-GCM-SIV uses the same AES and Galois field hardware acceleration as AES-GCM.
+///   Encryption makes two passes over the plain text, so it's somewhat slower than AES-GCM to
+///   encrypt, while decryption runs at about the same speed.
+///
+/// AES-GCM-SIV was designed by Shay Gueron, Adam Langley, and Yehuda Lindell, and was standardized
+/// in [RFC 8452](https://www.rfc-editor.org/rfc/rfc8452) in April 2019.
+#[allow(clippy::doc_markdown, reason = "respect the designers' names")]
+pub struct AesGcmSiv<V> {
+    /// A marker to tie this `AesGcmSiv` structure to a specific type `V` without storing any actual
+    /// data.
+    phantom_data: std::marker::PhantomData<V>,
+}
@@ -1,9 +1,13 @@
 //! Support for [Artyom Pavlov](https://github.com/newpavlov)'s
 //! [chacha20poly1305](https://crates.io/crates/chacha20poly1305) crate.
 
-use chacha20poly1305::{aead::{Aead, AeadCore, AeadInPlace, KeyInit, OsRng}, ChaCha20Poly1305};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, AeadInPlace, KeyInit, OsRng, Payload},
+    ChaCha20Poly1305,
+};
 use crate::layers::core::{Bytes, tail_readers::{TailReader, TailReaderMut}};
 use crate::layers::encryptors::core::{
+    AssociatedData,
     Encryptable,
     EncryptError,
     Encryptor,
@@ -50,6 +54,9 @@ impl<'b, 'k, V: Encryptable> Encryptor<'b, 'k, V> for ChaCha20<V> {
     ///
     /// * `key` · The secret encryption key used to transform the data.
     ///
+    /// * `associated_data` · The table and key the data is stored under, which are authenticated
+    ///   along with the encryption layer's descriptor.
+    ///
     /// # Errors
     ///
     /// Consult the documentation of the encryptor backend you are using for more detail on
@@ -65,19 +72,23 @@ impl<'b, 'k, V: Encryptable> Encryptor<'b, 'k, V> for ChaCha20<V> {
     fn encrypt(
         plain_text: Bytes<'b>,
         key: KeyBytes<'k>,
+        associated_data: &AssociatedData<'_>,
         nonce: Option<Nonce<'k>>,
     ) -> Result<Bytes<'b>, EncryptError> {
         let cipher = ChaCha20Poly1305::new(key.as_ref().into());
-        if let Some(nonce) = nonce {
-            let mut cipher_text = cipher.encrypt(nonce.as_ref().into(), plain_text.as_slice())?;
-            Parameters::from_nonce(nonce).into_data_buffer(&mut cipher_text);
-            Ok(cipher_text.into())
-        } else {
-            let nonce = Nonce::from_array(ChaCha20Poly1305::generate_nonce(&mut OsRng).into());
-            let mut cipher_text = cipher.encrypt(nonce.as_ref().into(), plain_text.as_slice())?;
-            Parameters::from_nonce(nonce).into_data_buffer(&mut cipher_text);
-            Ok(cipher_text.into())
-        }
+        let aad = associated_data.to_bytes(Method::ChaCha20);
+        let payload = Payload { msg: plain_text.as_slice(), aad: &aad };
+        let nonce = nonce.unwrap_or_else(|| {
+            Nonce::from_array(ChaCha20Poly1305::generate_nonce(&mut OsRng).into())
+        });
+        let cipher_text = cipher.encrypt(nonce.as_ref().into(), payload);
+
+        // The plain text is no longer needed, whether or not encryption succeeded:
+        plain_text.wipe();
+
+        let mut cipher_text = cipher_text?;
+        Parameters::new(key.id(), nonce).into_data_buffer(&mut cipher_text);
+        Ok(cipher_text.into())
     }
 
     /// Reverses the encryption process using the same secret key, restoring the encrypted bytes
@@ -90,11 +101,14 @@ impl<'b, 'k, V: Encryptable> Encryptor<'b, 'k, V> for ChaCha20<V> {
     /// * `key` · The same secret key used during encryption, required to reverse the
     ///   transformation.
     ///
+    /// * `associated_data` · The same table and key the data was encrypted with.
+    ///
     /// # Errors
     ///
     /// This method may fail for several reasons, including:
     ///
-    /// * Invalid key, or
+    /// * Invalid key,
+    /// * The data was encrypted for another table or key, or
     /// * Input bytes are corrupted or malformed.
     ///
     /// Consult the documentation of the encryptor backend you are using for more detail on
@@ -109,7 +123,8 @@ impl<'b, 'k, V: Encryptable> Encryptor<'b, 'k, V> for ChaCha20<V> {
     #[inline]
     fn decrypt(
         cipher_text: Bytes<'b>,
-        key: KeyBytes<'k>
+        key: KeyBytes<'k>,
+        associated_data: &AssociatedData<'_>,
     ) -> Result<Bytes<'b>, crate::layers::encryptors::DecryptError> {
         let (metadata, cipher_text) = cipher_text.into_parts();
         let cipher = ChaCha20Poly1305::new(key.as_ref().into());
@@ -119,15 +134,33 @@ impl<'b, 'k, V: Encryptable> Encryptor<'b, 'k, V> for ChaCha20<V> {
                 // Borrowed: use out-of-place decryption (allocates only for plaintext)
                 let mut tail_reader = TailReader::from_slice(slice);
                 let parameters = Parameters::from_data_buffer(&mut tail_reader)?;
-                let plain_text = cipher.decrypt(parameters.nonce.as_ref().into(), tail_reader.close())?;
+                let aad = parameters.associated_data(associated_data, Method::ChaCha20);
+                let payload = Payload { msg: tail_reader.close(), aad: &aad };
+                let plain_text = cipher
+                    .decrypt(parameters.nonce.as_ref().into(), payload)
+                    .or_else(|error| {
+                        // A legacy value whose nonce happens to end with the format marker:
+                        let (legacy, msg) = parameters.as_legacy(slice).ok_or(error)?;
+                        cipher.decrypt(legacy.nonce.as_ref().into(), Payload { msg, aad: &[] })
+                    })?;
                 Ok(Bytes::from_parts(metadata, plain_text.into()))
             }
             Cow::Owned(vec) => {
                 // Owned: decrypt in-place (no extra allocation)
                 let mut tail_reader_mut = TailReaderMut::from_vec(vec);
-                let nonce = *Parameters::from_data_buffer_mut(&mut tail_reader_mut)?.nonce;
+                let parameters = Parameters::from_data_buffer_mut(&mut tail_reader_mut)?
+                    .into_owned();
+                let aad = parameters.associated_data(associated_data, Method::ChaCha20);
                 let mut bytes_buf: Vec<u8> = tail_reader_mut.close();
-                cipher.decrypt_in_place(nonce.as_ref().into(), &[], &mut bytes_buf)?;
+                let decrypted =
+                    cipher.decrypt_in_place(parameters.nonce.as_ref().into(), &aad, &mut bytes_buf);
+                if let Err(error) = decrypted {
+                    // A legacy value whose nonce happens to end with the format marker. Failed
+                    // decryption leaves the cipher text as it was:
+                    let (legacy, legacy_buf) = parameters.into_legacy(bytes_buf).ok_or(error)?;
+                    bytes_buf = legacy_buf;
+                    cipher.decrypt_in_place(legacy.nonce.as_ref().into(), &[], &mut bytes_buf)?;
+                }
                 Ok(Bytes::from_parts(metadata, bytes_buf.into()))
             }
         }
@@ -142,6 +175,7 @@ impl<'b, 'k, V: Encryptable> Encryptor<'b, 'k, V> for ChaCha20<V> {
 mod tests {
     use super::*;
     use crate::layers::core::Direction;
+    use crate::layers::encryptors::core::LEGACY_VALUES_LOCK;
 
     // Test types implementing Encryptable with different directions
     struct AlwaysEncrypt;
@@ -149,6 +183,8 @@ mod tests {
         const DIRECTION: Direction = Direction::Both;
     }
 
+    const CONTEXT: AssociatedData<'static> = AssociatedData::new("creatures", b"7");
+
     #[test]
     fn test_symmetric_encryption_both_encryption_direction() {
         let key = b"an example very very secret key."; // 32 bytes
@@ -156,14 +192,14 @@ mod tests {
         let value_buf = Bytes::from(original_data.as_slice());
 
         // Encrypt
-        let encrypted = ChaCha20::<AlwaysEncrypt>::encrypt(value_buf, key.into(), None)
+        let encrypted = ChaCha20::<AlwaysEncrypt>::encrypt(value_buf, key.into(), &CONTEXT, None)
             .expect("Encryption should succeed");
 
         // Verify data is actually encrypted (different from original)
         assert_ne!(encrypted.as_slice(), original_data);
 
         // Decrypt
-        let decrypted = ChaCha20::<AlwaysEncrypt>::decrypt(encrypted, key.into())
+        let decrypted = ChaCha20::<AlwaysEncrypt>::decrypt(encrypted, key.into(), &CONTEXT)
             .expect("Decryption should succeed");
 
         // Verify decrypted data matches original
@@ -177,19 +213,21 @@ mod tests {
         let value_buf = Bytes::from(original_data.as_slice());
 
         // Encrypt the same data multiple times
-        let encrypted1 = ChaCha20::<AlwaysEncrypt>::encrypt(value_buf.clone(), key.into(), None)
-            .expect("First encryption should succeed");
-        let encrypted2 = ChaCha20::<AlwaysEncrypt>::encrypt(value_buf.clone(), key.into(), None)
-            .expect("Second encryption should succeed");
+        let encrypted1 =
+            ChaCha20::<AlwaysEncrypt>::encrypt(value_buf.clone(), key.into(), &CONTEXT, None)
+                .expect("First encryption should succeed");
+        let encrypted2 =
+            ChaCha20::<AlwaysEncrypt>::encrypt(value_buf.clone(), key.into(), &CONTEXT, None)
+                .expect("Second encryption should succeed");
 
         // Encrypted data should be different due to different nonces
         assert_ne!(encrypted1.as_slice(), encrypted2.as_slice());
 
         // But both should decrypt to the same original data
-        let decrypted1 = ChaCha20::<AlwaysEncrypt>::decrypt(encrypted1, key.into())
+        let decrypted1 = ChaCha20::<AlwaysEncrypt>::decrypt(encrypted1, key.into(), &CONTEXT)
             .inspect_err(|error| println!("{error:#?}"))
             .expect("First decryption should succeed");
-        let decrypted2 = ChaCha20::<AlwaysEncrypt>::decrypt(encrypted2, key.into())
+        let decrypted2 = ChaCha20::<AlwaysEncrypt>::decrypt(encrypted2, key.into(), &CONTEXT)
             .inspect_err(|error| println!("{error:#?}"))
             .expect("Second decryption should succeed");
 
@@ -203,11 +241,11 @@ mod tests {
         let empty_data = b"";
         let value_buf = Bytes::from(empty_data.as_slice());
 
-        let encrypted = ChaCha20::<AlwaysEncrypt>::encrypt(value_buf, key.into(), None)
+        let encrypted = ChaCha20::<AlwaysEncrypt>::encrypt(value_buf, key.into(), &CONTEXT, None)
             .inspect_err(|error| println!("{error:#?}"))
             .expect("Encryption of empty data should succeed");
 
-        let decrypted = ChaCha20::<AlwaysEncrypt>::decrypt(encrypted, key.into())
+        let decrypted = ChaCha20::<AlwaysEncrypt>::decrypt(encrypted, key.into(), &CONTEXT)
             .inspect_err(|error| println!("{error:?}"))
             .expect("Decryption of empty data should succeed");
 
@@ -220,10 +258,10 @@ mod tests {
         let large_data = vec![0x42u8; 10000]; // 10KB of data
         let value_buf = Bytes::from(large_data.as_slice());
 
-        let encrypted = ChaCha20::<AlwaysEncrypt>::encrypt(value_buf, key.into(), None)
+        let encrypted = ChaCha20::<AlwaysEncrypt>::encrypt(value_buf, key.into(), &CONTEXT, None)
             .expect("Encryption of large data should succeed");
 
-        let decrypted = ChaCha20::<AlwaysEncrypt>::decrypt(encrypted, key.into())
+        let decrypted = ChaCha20::<AlwaysEncrypt>::decrypt(encrypted, key.into(), &CONTEXT)
             .expect("Decryption of large data should succeed");
 
         assert_eq!(decrypted.as_slice(), large_data.as_slice());
@@ -237,11 +275,11 @@ mod tests {
         let value_buf = Bytes::from(original_data.as_slice());
 
         // Encrypt with key1
-        let encrypted = ChaCha20::<AlwaysEncrypt>::encrypt(value_buf, key1, None)
+        let encrypted = ChaCha20::<AlwaysEncrypt>::encrypt(value_buf, key1, &CONTEXT, None)
             .expect("Encryption should succeed");
 
         // Try to decrypt with key2 (should fail)
-        let result = ChaCha20::<AlwaysEncrypt>::decrypt(encrypted, key2);
+        let result = ChaCha20::<AlwaysEncrypt>::decrypt(encrypted, key2, &CONTEXT);
         assert!(result.is_err(), "Decryption with wrong key should fail");
     }
 
@@ -252,9 +290,10 @@ mod tests {
         let value_buf = Bytes::from(original_data.as_slice());
 
         // Encrypt data
-        let mut encrypted = ChaCha20::<AlwaysEncrypt>::encrypt(value_buf, key.into(), None)
-            .expect("Encryption should succeed")
-            .to_vec();
+        let mut encrypted =
+            ChaCha20::<AlwaysEncrypt>::encrypt(value_buf, key.into(), &CONTEXT, None)
+                .expect("Encryption should succeed")
+                .to_vec();
 
         // Corrupt the encrypted data
         if let Some(byte) = encrypted.get_mut(0) {
@@ -262,10 +301,49 @@ mod tests {
         }
 
         // Try to decrypt corrupted data (should fail)
-        let result = ChaCha20::<AlwaysEncrypt>::decrypt(encrypted.into(), key.into());
+        let result = ChaCha20::<AlwaysEncrypt>::decrypt(encrypted.into(), key.into(), &CONTEXT);
         assert!(result.is_err(), "Decryption of corrupted data should fail");
     }
 
+    #[test]
+    fn test_spliced_cipher_text_fails() {
+        let key = b"an example very very secret key."; // 32 bytes
+        let value_buf = Bytes::from(b"Hello, World!".as_slice());
+
+        let encrypted = ChaCha20::<AlwaysEncrypt>::encrypt(value_buf, key.into(), &CONTEXT, None)
+            .expect("Encryption should succeed");
+
+        // Moving the cipher text to another key or table must fail authentication
+        let other_key = AssociatedData::new("creatures", b"8");
+        let other_table = AssociatedData::new("tenant42.creatures", b"7");
+        for context in [other_key, other_table] {
+            let result =
+                ChaCha20::<AlwaysEncrypt>::decrypt(encrypted.clone(), key.into(), &context);
+            assert!(result.is_err(), "Decryption in another context should fail");
+        }
+    }
+
+    #[test]
+    fn test_legacy_cipher_text_decrypts() {
+        // Written before associated data was authenticated: the cipher text and its nonce, with
+        // no key ID or format marker.
+        const LEGACY: [u8; 41] = [
+            211, 72, 220, 131, 173, 64, 238, 8, 149, 133, 219, 100,
+            195, 246, 231, 96, 0, 23, 135, 1, 240, 7, 197, 239,
+            189, 84, 210, 3, 141,
+            7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+        ];
+        let _lock = LEGACY_VALUES_LOCK.lock().unwrap();
+        let key = b"an example very very secret key."; // 32 bytes
+
+        // Borrowed and owned bytes are decrypted differently:
+        for bytes in [Bytes::from(LEGACY.as_slice()), Bytes::from(LEGACY.to_vec())] {
+            let decrypted = ChaCha20::<AlwaysEncrypt>::decrypt(bytes, key.into(), &CONTEXT)
+                .expect("Decryption of a legacy value should succeed");
+            assert_eq!(decrypted.as_slice(), b"Hello, World!");
+        }
+    }
+
     #[test]
     fn test_method_returns_correct_value() {
         assert_eq!(ChaCha20::<AlwaysEncrypt>::METHOD, Method::ChaCha20);
@@ -15,6 +15,7 @@ macro_rules! count_features {
 const _ENCRYPTOR_FEATURE_COUNT: usize = count_features!(
     "encrypt-aes-gcm",
     "encrypt-chacha20",
+    "encrypt-aes-gcm-siv",
 );
 
 const _: () = {
@@ -23,8 +24,9 @@ const _: () = {
         // `[dependencies]` section where `atlatl` is declared, 3. ensure only one serializer is enabled.
         !(_ENCRYPTOR_FEATURE_COUNT > 1),
         "Multiple encryptor features enabled! Enable only one of: \
-	    `encrypt-aes-gcm`, or \
-	    `encrypt-chacha20`",
+	    `encrypt-aes-gcm`, \
+	    `encrypt-chacha20`, or \
+	    `encrypt-aes-gcm-siv`",
     );
 };
 
@@ -68,4 +70,23 @@ pub use crate::layers::encryptors::impls::aes_gcm::NONCE_SIZE;
 
 #[cfg(all(feature = "encrypt-aes-gcm", feature = "kdf-sha256"))]
 /// Digest to be used for hashing text passwords when working with the `ring` crate.
-pub use crate::layers::encryptors::impls::aes_gcm::RING_SHA256_DIGEST;
\ No newline at end of file
+pub use crate::layers::encryptors::impls::aes_gcm::RING_SHA256_DIGEST;
+
+#[cfg(feature = "encrypt-aes-gcm-siv")]
+mod aes_gcm_siv;
+
+#[cfg(feature = "encrypt-aes-gcm-siv")]
+/// `AesGcmSiv` has been selected as the `ActiveEncryptor` using `Cargo.toml` feature.
+pub use crate::layers::encryptors::impls::aes_gcm_siv::AesGcmSiv as ActiveEncryptor;
+
+#[cfg(feature = "encrypt-aes-gcm-siv")]
+/// Key size for the active encryptor. `AesGcmSiv`'s key size is `32`-bytes or `256`-bits.
+pub use crate::layers::encryptors::impls::aes_gcm_siv::KEY_SIZE;
+
+#[cfg(feature = "encrypt-aes-gcm-siv")]
+/// Nonce size for the active encryptor. `AesGcmSiv`'s nonce size is `12`-bytes or `96`-bits.
+pub use crate::layers::encryptors::impls::aes_gcm_siv::NONCE_SIZE;
+
+#[cfg(all(feature = "encrypt-aes-gcm-siv", feature = "kdf-sha256"))]
+/// Digest to be used for hashing text passwords when working with the `ring` crate.
+pub use crate::layers::encryptors::impls::aes_gcm_siv::RING_SHA256_DIGEST;
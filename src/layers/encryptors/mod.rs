@@ -1,14 +1,49 @@
 //! Encryption algorithms for securing stored data.
 
 mod core;
+pub use crate::layers::encryptors::core::AssociatedData;
+#[cfg(any(feature = "kdf-blake3", feature = "kdf-sha256"))]
+pub use crate::layers::encryptors::core::{BlindIndex, BlindToken, TOKEN_SIZE};
 pub use crate::layers::encryptors::core::DecryptError;
+#[cfg(any(feature = "kdf-blake3", feature = "kdf-sha256"))]
+pub use crate::layers::encryptors::core::DeterministicCipher;
 pub use crate::layers::encryptors::core::EncryptError;
+#[cfg(feature = "serde")]
+pub use crate::layers::encryptors::core::encrypted;
+#[cfg(feature = "serde")]
+pub use crate::layers::encryptors::core::encrypted::EncryptedField;
 pub use crate::layers::encryptors::core::Encryptable;
 pub use crate::layers::encryptors::core::Encryptor;
 pub use crate::layers::encryptors::core::Error;
+#[cfg(feature = "external-keys")]
+pub use crate::layers::encryptors::core::{
+    AsyncExternalKeyProvider,
+    ExternalKeyError,
+    ExternalKeyProvider,
+    FetchedKeys,
+};
+#[cfg(feature = "external-keys")]
+pub use zeroize::Zeroizing;
 pub use crate::layers::encryptors::core::KeyBytes;
+pub use crate::layers::encryptors::core::{KeyId, KeyRing, KeyRingProvider};
 pub use crate::layers::encryptors::core::Method;
 pub use crate::layers::encryptors::core::Nonce;
+pub use crate::layers::encryptors::core::{
+    NONCE_PREFIX_SIZE,
+    NonceCounter,
+    NonceCounterError,
+    NonceStrategy,
+};
+#[cfg(any(feature = "kdf-blake3", feature = "kdf-sha256"))]
+pub use crate::layers::encryptors::core::TenantKey;
 
 mod impls;
-pub use crate::layers::encryptors::impls::ActiveEncryptor;
\ No newline at end of file
+pub use crate::layers::encryptors::impls::ActiveEncryptor;
+pub use crate::layers::encryptors::impls::KEY_SIZE;
+pub use crate::layers::encryptors::impls::NONCE_SIZE;
+pub(crate) use crate::layers::encryptors::core::FORMAT_MARKER;
+pub use crate::layers::encryptors::core::{
+    ParametersError,
+    accepts_legacy_values,
+    set_accept_legacy_values,
+};
\ No newline at end of file
@@ -16,6 +16,11 @@ pub enum Error {
     #[error("encryption failure")]
     Encryption(#[from] crate::layers::encryptors::Error),
 
+    /// A signing or signature verification failure.
+    #[cfg(feature = "signers")]
+    #[error("signing failure")]
+    Signing(#[from] crate::layers::signers::Error),
+
     /// A compression or decompression failure.
     #[error("compression failure")]
     Compression(#[from] crate::layers::compressors::Error),
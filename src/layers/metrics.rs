@@ -0,0 +1,242 @@
+//! Per-layer timings, sizes, and error correction repairs, recorded through the
+//! [metrics](https://crates.io/crates/metrics) crate.
+//!
+//! Every layer that's applied to a value records how long it took, how many bytes went in and came
+//! out, and whether it failed. Install any `metrics` recorder or exporter, such as
+//! `metrics-exporter-prometheus`, to collect them:
+//!
+//! ```rust,ignore
+//! metrics_exporter_prometheus::PrometheusBuilder::new().install()?;
+//! atlatl::layers::metrics::describe_metrics();
+//! ```
+//!
+//! Layer metrics carry a `layer` label (`serialization`, `compression`, `encryption`, or
+//! `correction`) and an `operation` label (`read` or `write`). Nothing is sent anywhere while no
+//! recorder is installed, and nothing is compiled in without the `metrics` feature.
+//!
+//! Running totals of the same measurements are also kept in-process, and can be read back with
+//! [`layer_totals`] without installing a recorder.
+
+use crate::layers::core::{Direction, Layer};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Running totals of one layer's work in one direction, since the process started.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LayerTotals {
+    /// The layer's `layer` label. For example: `compression`.
+    pub layer: &'static str,
+
+    /// The `operation` label: `read` or `write`.
+    pub operation: &'static str,
+
+    /// The number of values the layer processed, including failures.
+    pub values: u64,
+
+    /// The number of values the layer failed to process.
+    pub errors: u64,
+
+    /// The bytes passed into the layer by values it processed successfully.
+    pub input_bytes: u64,
+
+    /// The bytes that came out of the layer.
+    pub output_bytes: u64,
+
+    /// The total time spent in the layer, in seconds.
+    pub seconds: f64,
+}
+
+/// Atomic running totals of one layer's work in one direction.
+struct Totals {
+    values: AtomicU64,
+    errors: AtomicU64,
+    input_bytes: AtomicU64,
+    output_bytes: AtomicU64,
+    nanos: AtomicU64,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// Histogram of the time spent in a layer, in seconds.
+pub const LAYER_DURATION_SECONDS: &str = "atlatl_layer_duration_seconds";
+
+/// Counter of the bytes passed into a layer. Values passed into the serialization layer on writes
+/// aren't bytes yet, and aren't counted.
+pub const LAYER_INPUT_BYTES: &str = "atlatl_layer_input_bytes_total";
+
+/// Counter of the bytes that came out of a layer. Values that came out of the serialization layer
+/// on reads aren't bytes anymore, and aren't counted.
+pub const LAYER_OUTPUT_BYTES: &str = "atlatl_layer_output_bytes_total";
+
+/// Counter of the values that a layer failed to process.
+pub const LAYER_ERRORS: &str = "atlatl_layer_errors_total";
+
+/// Histogram of compressed size divided by uncompressed size, for every compressed write. Values
+/// below `1.0` shrank, and values above it grew by their tag.
+pub const COMPRESSION_RATIO: &str = "atlatl_compression_ratio";
+
+/// Counter of the values that error correction repaired on read, labelled by `table` when known.
+pub const ECC_REPAIRS: &str = "atlatl_ecc_repairs_total";
+
+/// Counter of the corrupted shards that error correction rebuilt, labelled by `table` when known.
+pub const ECC_SHARDS_RECOVERED: &str = "atlatl_ecc_shards_recovered_total";
+
+// -------------------------------------------------------------------------------------------------
+//
+// Global State
+
+/// The layers that are measured, in the order their totals are kept.
+const MEASURED: [Layer; 4] =
+    [Layer::Serialization, Layer::Compression, Layer::Encryption, Layer::Correction];
+
+/// Running totals, indexed by the layer's position in [`MEASURED`], then write `0` or read `1`.
+static TOTALS: [[Totals; 2]; 4] = [const { [const { Totals::new() }; 2] }; 4];
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Totals {
+    /// Instantiates zero totals.
+    const fn new() -> Self {
+        Self {
+            values: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            input_bytes: AtomicU64::new(0),
+            output_bytes: AtomicU64::new(0),
+            nanos: AtomicU64::new(0),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Registers a description and unit for every metric with the installed recorder. Call it once,
+/// after the recorder is installed, so that exporters can show help text.
+pub fn describe_metrics() {
+    metrics::describe_histogram!(
+        LAYER_DURATION_SECONDS,
+        metrics::Unit::Seconds,
+        "Time spent in a storage layer"
+    );
+    metrics::describe_counter!(
+        LAYER_INPUT_BYTES,
+        metrics::Unit::Bytes,
+        "Bytes passed into a storage layer"
+    );
+    metrics::describe_counter!(
+        LAYER_OUTPUT_BYTES,
+        metrics::Unit::Bytes,
+        "Bytes that came out of a storage layer"
+    );
+    metrics::describe_counter!(LAYER_ERRORS, "Values that a storage layer failed to process");
+    metrics::describe_histogram!(COMPRESSION_RATIO, "Compressed size over uncompressed size");
+    metrics::describe_counter!(ECC_REPAIRS, "Values repaired by error correction on read");
+    metrics::describe_counter!(ECC_SHARDS_RECOVERED, "Shards rebuilt by error correction");
+}
+
+/// Records one layer's work on a value. `output_len` is `None` if the layer failed.
+pub(crate) fn record_layer(
+    layer: Layer,
+    direction: Direction,
+    input_len: usize,
+    output_len: Option<usize>,
+    elapsed: Duration,
+) {
+    let labels = [("layer", layer_label(layer)), ("operation", operation_label(direction))];
+    metrics::histogram!(LAYER_DURATION_SECONDS, &labels).record(elapsed.as_secs_f64());
+
+    let totals = totals_of(layer, direction);
+    if let Some(totals) = totals {
+        totals.values.fetch_add(1, Ordering::Relaxed);
+        let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+        totals.nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    let Some(output_len) = output_len else {
+        metrics::counter!(LAYER_ERRORS, &labels).increment(1);
+        if let Some(totals) = totals {
+            totals.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        return;
+    };
+
+    if let Some(totals) = totals {
+        totals.input_bytes.fetch_add(input_len as u64, Ordering::Relaxed);
+        totals.output_bytes.fetch_add(output_len as u64, Ordering::Relaxed);
+    }
+
+    metrics::counter!(LAYER_INPUT_BYTES, &labels).increment(input_len as u64);
+    metrics::counter!(LAYER_OUTPUT_BYTES, &labels).increment(output_len as u64);
+    if layer == Layer::Compression && direction.is_write() && input_len > 0 {
+        #[allow(clippy::cast_precision_loss, reason = "ratios don't need every bit")]
+        let ratio = output_len as f64 / input_len as f64;
+        metrics::histogram!(COMPRESSION_RATIO).record(ratio);
+    }
+}
+
+/// Returns the running totals of every measured layer, in each direction, since the process
+/// started. Layers that haven't processed a value yet are included, with zero totals.
+#[must_use]
+pub fn layer_totals() -> Vec<LayerTotals> {
+    MEASURED
+        .into_iter()
+        .zip(&TOTALS)
+        .flat_map(|(layer, totals)| {
+            [Direction::OnWrite, Direction::OnRead].into_iter().zip(totals).map(
+                move |(direction, totals)| LayerTotals {
+                    layer: layer_label(layer),
+                    operation: operation_label(direction),
+                    values: totals.values.load(Ordering::Relaxed),
+                    errors: totals.errors.load(Ordering::Relaxed),
+                    input_bytes: totals.input_bytes.load(Ordering::Relaxed),
+                    output_bytes: totals.output_bytes.load(Ordering::Relaxed),
+                    seconds: Duration::from_nanos(totals.nanos.load(Ordering::Relaxed))
+                        .as_secs_f64(),
+                },
+            )
+        })
+        .collect()
+}
+
+/// Records a value repaired by error correction.
+#[cfg(feature = "correctors")]
+pub(crate) fn record_repair(table: Option<&'static str>, shards_recovered: usize) {
+    if let Some(table) = table {
+        metrics::counter!(ECC_REPAIRS, "table" => table).increment(1);
+        metrics::counter!(ECC_SHARDS_RECOVERED, "table" => table)
+            .increment(shards_recovered as u64);
+    } else {
+        metrics::counter!(ECC_REPAIRS).increment(1);
+        metrics::counter!(ECC_SHARDS_RECOVERED).increment(shards_recovered as u64);
+    }
+}
+
+/// Returns the running totals of a layer in a direction, or `None` if the layer isn't measured.
+fn totals_of(layer: Layer, direction: Direction) -> Option<&'static Totals> {
+    let position = MEASURED.iter().position(|measured| *measured == layer)?;
+    Some(&TOTALS[position][usize::from(!direction.is_write())])
+}
+
+/// Returns the `layer` label of a layer.
+const fn layer_label(layer: Layer) -> &'static str {
+    match layer {
+        Layer::Raw => "raw",
+        Layer::Serialization => "serialization",
+        Layer::Compression => "compression",
+        Layer::Encryption => "encryption",
+        Layer::Correction => "correction",
+    }
+}
+
+/// Returns the `operation` label of a direction.
+const fn operation_label(direction: Direction) -> &'static str {
+    if direction.is_write() { "write" } else { "read" }
+}
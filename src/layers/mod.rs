@@ -1,6 +1,8 @@
-//! Support for data transformation layers: serialization, compression, correction, and encryption.
+//! Support for data transformation layers: serialization, compression, correction, encryption, and
+//! signing.
 
 pub mod core;
+pub use crate::layers::core::LayerStack;
 
 mod error;
 pub use crate::layers::error::Error;
@@ -64,4 +66,32 @@ pub use crate::layers::encryptors::ActiveEncryptor;
 pub use crate::layers::encryptors::Encryptor;
 
 #[cfg(feature = "encryptors")]
-pub use crate::layers::encryptors::Encryptable;
\ No newline at end of file
+pub use crate::layers::encryptors::Encryptable;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Signing Layer
+
+#[cfg(feature = "signers")]
+pub mod signers;
+
+#[cfg(feature = "signers")]
+pub use crate::layers::signers::ActiveSigner;
+
+#[cfg(feature = "signers")]
+pub use crate::layers::signers::Signer;
+
+#[cfg(feature = "signers")]
+pub use crate::layers::signers::Signable;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Pipeline Introspection & Metrics
+
+#[cfg(feature = "metrics")]
+pub mod metrics;
+
+mod pipeline;
+pub use crate::layers::pipeline::{
+    DryRun, Layers, Overhead, PipelineDescription, StageDescription, StageReport
+};
@@ -0,0 +1,476 @@
+//! Introspection of the layer pipeline, for debugging why stored values are larger or slower than
+//! expected.
+//!
+//! [`Layers::describe`] reports which layers a value type runs through, in which order, and how
+//! many bytes each is expected to add, without touching any data. [`Layers::dry_run`] passes an
+//! actual value through the write layers and reports its size and the time taken after each one.
+
+use crate::layers::core::{Bytes, Direction, Layer, LayerStack};
+use crate::layers::correctors::{ActiveCorrector, Level};
+use crate::layers::encryptors::{ActiveEncryptor, AssociatedData, KEY_SIZE, KeyBytes};
+use crate::layers::{Compressible, Correctable, Corrector, Encryptable, Encryptor, Serializable};
+use std::time::Duration;
+
+#[cfg(feature = "writes")]
+use crate::layers::core::{LayerFailure, ValueOrBytes};
+
+#[cfg(feature = "writes")]
+use crate::layers::Serializer;
+
+#[cfg(feature = "writes")]
+use crate::layers::core::Stopwatch;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// Key that dry runs and overhead estimates are encrypted with. Sizes don't depend on the key.
+const PROBE_KEY: [u8; KEY_SIZE] = [0; KEY_SIZE];
+
+/// Context that dry runs and overhead estimates are encrypted for.
+const PROBE_CONTEXT: AssociatedData<'static> = AssociatedData::new("__atlatl_dry_run", b"");
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Entry point for inspecting the layer pipeline of a value type.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// println!("{}", Layers::describe::<Creature>());
+///
+/// let dry_run = Layers::dry_run(&creature)?;
+/// println!("{dry_run}");
+/// assert!(dry_run.stored_len() < 4_096);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Layers;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// How many bytes a layer is expected to add to a value.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Overhead {
+    /// The layer isn't applied on writes, so it adds nothing.
+    None,
+
+    /// The layer always adds this many bytes. For example, the serializer's method tag, or the
+    /// encryptor's nonce and authentication tag.
+    Fixed(usize),
+
+    /// The layer adds about this percentage of the value's size. For example, error correction
+    /// parity shards.
+    Proportional(u8),
+
+    /// The layer's overhead depends on the value. For example, compression may shrink a value
+    /// considerably, or add a single tag byte if the value doesn't shrink.
+    Variable,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// One stage of a value type's write pipeline, see [`PipelineDescription`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StageDescription {
+    /// The layer.
+    pub layer: Layer,
+
+    /// When the layer is applied, as declared by the value type.
+    pub direction: Direction,
+
+    /// The algorithm that values are currently written with, for example `"zstd"`. `None` if the
+    /// layer isn't applied on writes.
+    pub method: Option<String>,
+
+    /// How many bytes the layer is expected to add.
+    pub overhead: Overhead,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The layers that a value type's writes run through, in order, returned by
+/// [`Layers::describe`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PipelineDescription {
+    /// Every layer, in the order it's applied during writes. Reads apply them in reverse.
+    pub stages: Vec<StageDescription>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The outcome of passing a value through one write layer, see [`DryRun`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StageReport {
+    /// The layer.
+    pub layer: Layer,
+
+    /// Whether the layer is applied on writes. Skipped layers pass the value through unchanged.
+    pub applied: bool,
+
+    /// The value's size after the layer, in bytes.
+    pub len: usize,
+
+    /// Time spent in the layer.
+    pub elapsed: Duration,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The sizes and timings of a value passed through its write layers, returned by
+/// [`Layers::dry_run`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DryRun {
+    /// Every layer, in the order it was applied.
+    pub stages: Vec<StageReport>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Layers {
+    /// Describes the layers that `V`'s values are written through, in order, along with the
+    /// algorithm each uses and how many bytes each is expected to add.
+    #[must_use]
+    pub fn describe<V>() -> PipelineDescription
+    where
+        V: Serializable + Compressible + Encryptable + Correctable + LayerStack,
+    {
+        let mut stages = vec![stage(
+            Layer::Serialization,
+            <V as Serializable>::DIRECTION,
+            || crate::layers::serializers::write_method().to_string(),
+            || Overhead::Fixed(1),
+        )];
+
+        stages.extend(crate::layers::core::write_order::<V>().map(|layer| match layer {
+            Layer::Compression => stage(
+                layer,
+                <V as Compressible>::DIRECTION,
+                || crate::layers::compressors::write_method().to_string(),
+                || Overhead::Variable,
+            ),
+            Layer::Encryption => stage(
+                layer,
+                <V as Encryptable>::DIRECTION,
+                || <ActiveEncryptor<V> as Encryptor<V>>::METHOD.to_string(),
+                encryption_overhead::<V>,
+            ),
+            _ => stage(
+                layer,
+                <V as Correctable>::DIRECTION,
+                || <ActiveCorrector<V> as Corrector<V>>::METHOD.to_string(),
+                correction_overhead::<V>,
+            ),
+        }));
+
+        PipelineDescription { stages }
+    }
+
+    /// Passes a value through `V`'s write layers, without storing it, and reports the value's size
+    /// and the time taken after each layer.
+    ///
+    /// The value is encrypted with a throwaway key, since the key doesn't affect sizes. Values of
+    /// types that use compression dictionaries are compressed without one, so they may come out
+    /// larger than they would be when stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`LayerFailure`] naming the write layer that failed.
+    #[cfg(feature = "writes")]
+    pub fn dry_run<'b, V>(
+        value_or_bytes: impl Into<ValueOrBytes<'b, V>>
+    ) -> Result<DryRun, LayerFailure>
+    where
+        V: Serializer<'b, V>
+            + Serializable
+            + Compressible
+            + Encryptable
+            + Correctable
+            + LayerStack
+            + 'b,
+    {
+        let started = Stopwatch::start();
+        let mut bytes = Bytes::serialize(value_or_bytes.into())
+            .map_err(LayerFailure::at(Layer::Serialization))?;
+        let mut stages = vec![StageReport {
+            layer: Layer::Serialization,
+            applied: <V as Serializable>::DIRECTION.is_write(),
+            len: bytes.len(),
+            elapsed: started.elapsed(),
+        }];
+
+        for layer in crate::layers::core::write_order::<V>() {
+            let started = Stopwatch::start();
+            let (applied, output) = match layer {
+                Layer::Compression => (
+                    <V as Compressible>::DIRECTION.is_write(),
+                    compress::<V>(bytes),
+                ),
+                Layer::Encryption => (
+                    <V as Encryptable>::DIRECTION.is_write(),
+                    bytes
+                        .encrypt::<V>(KeyBytes::from_array(&PROBE_KEY), &PROBE_CONTEXT, None)
+                        .map_err(LayerFailure::at(layer)),
+                ),
+                _ => (
+                    <V as Correctable>::DIRECTION.is_write(),
+                    bytes.protect::<V>().map_err(LayerFailure::at(layer)),
+                ),
+            };
+            bytes = output?;
+            stages.push(StageReport { layer, applied, len: bytes.len(), elapsed: started.elapsed() });
+        }
+
+        Ok(DryRun { stages })
+    }
+}
+
+impl PipelineDescription {
+    /// Returns the layers that are applied on writes, in order.
+    pub fn applied(&self) -> impl Iterator<Item = Layer> + '_ {
+        self.stages
+            .iter()
+            .filter(|stage| stage.direction.is_write())
+            .map(|stage| stage.layer)
+    }
+
+    /// Returns the description of a layer.
+    #[must_use]
+    pub fn stage(&self, layer: Layer) -> Option<&StageDescription> {
+        self.stages.iter().find(|stage| stage.layer == layer)
+    }
+
+    /// Estimates how many bytes of a stored value are layer tails and parity, rather than the
+    /// value itself, by peeling the layers' expected overheads off in read order.
+    ///
+    /// Compression's overhead depends on the value, and isn't counted.
+    #[must_use]
+    pub fn tail_overhead(&self, stored_len: usize) -> usize {
+        let inner_len = self.stages.iter().rev().fold(stored_len, |len, stage| {
+            match stage.overhead {
+                Overhead::Fixed(bytes) => len.saturating_sub(bytes),
+                Overhead::Proportional(percent) => len * 100 / (100 + usize::from(percent)),
+                Overhead::None | Overhead::Variable => len,
+            }
+        });
+        stored_len - inner_len
+    }
+}
+
+impl DryRun {
+    /// Returns the size of the serialized value, before any other layer was applied.
+    #[must_use]
+    pub fn serialized_len(&self) -> usize {
+        self.stage(Layer::Serialization).map_or(0, |stage| stage.len)
+    }
+
+    /// Returns the size of the value as it would be stored, after every layer was applied.
+    #[must_use]
+    pub fn stored_len(&self) -> usize {
+        self.stages.last().map_or(0, |stage| stage.len)
+    }
+
+    /// Returns the total time spent in the write layers.
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.stages.iter().map(|stage| stage.elapsed).sum()
+    }
+
+    /// Returns the report of a layer.
+    #[must_use]
+    pub fn stage(&self, layer: Layer) -> Option<&StageReport> {
+        self.stages.iter().find(|stage| stage.layer == layer)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl std::fmt::Display for Overhead {
+    /// Formats the `Overhead` as a human-readable string, for example: `+28 bytes`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Fixed(len) => write!(f, "+{len} bytes"),
+            Self::Proportional(percent) => write!(f, "~{percent}%"),
+            Self::Variable => write!(f, "varies"),
+        }
+    }
+}
+
+impl std::fmt::Display for PipelineDescription {
+    /// Formats the pipeline with one layer per line, for example:
+    /// `encryption: aes-gcm (+34 bytes)`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for stage in &self.stages {
+            match &stage.method {
+                Some(method) => writeln!(f, "{}: {method} ({})", stage.layer, stage.overhead)?,
+                None => writeln!(f, "{}: skipped", stage.layer)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for DryRun {
+    /// Formats the dry run with one layer per line, for example:
+    /// `compression: 1024 → 311 bytes in 42µs`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut previous_len = None;
+        for stage in &self.stages {
+            if !stage.applied {
+                writeln!(f, "{}: skipped", stage.layer)?;
+            } else if let Some(previous_len) = previous_len {
+                writeln!(
+                    f,
+                    "{}: {previous_len} → {} bytes in {:?}",
+                    stage.layer,
+                    stage.len,
+                    stage.elapsed
+                )?;
+            } else {
+                writeln!(f, "{}: {} bytes in {:?}", stage.layer, stage.len, stage.elapsed)?;
+            }
+            previous_len = Some(stage.len);
+        }
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Describes a single stage, only computing its method and overhead if it's applied on writes.
+fn stage(
+    layer: Layer,
+    direction: Direction,
+    method: impl FnOnce() -> String,
+    overhead: impl FnOnce() -> Overhead,
+) -> StageDescription {
+    if direction.is_write() {
+        StageDescription { layer, direction, method: Some(method()), overhead: overhead() }
+    } else {
+        StageDescription { layer, direction, method: None, overhead: Overhead::None }
+    }
+}
+
+/// Measures the encryptor's overhead by encrypting an empty value. The nonce, authentication tag,
+/// and metadata don't depend on the value's size.
+fn encryption_overhead<V: Encryptable>() -> Overhead {
+    Bytes::from_slice(&[])
+        .encrypt::<V>(KeyBytes::from_array(&PROBE_KEY), &PROBE_CONTEXT, None)
+        .map_or(Overhead::Variable, |cipher_text| Overhead::Fixed(cipher_text.len()))
+}
+
+/// Estimates the share of parity data that error correction adds, from the type's parity
+/// percentage or correction level.
+const fn correction_overhead<V: Correctable>() -> Overhead {
+    match (V::PARITY_PERCENT, V::LEVEL) {
+        (Some(percent), _) => Overhead::Proportional(percent),
+        (None, Level::Medium) => Overhead::Proportional(25),
+        (None, Level::Maximum) => Overhead::Proportional(50),
+        (None, _) => Overhead::Variable,
+    }
+}
+
+/// Compresses a dry run's value, without a dictionary.
+#[cfg(all(feature = "writes", feature = "compress-dictionaries"))]
+fn compress<V: Compressible>(bytes: Bytes<'_>) -> Result<Bytes<'_>, LayerFailure> {
+    bytes.compress::<V>(None).map_err(LayerFailure::at(Layer::Compression))
+}
+
+/// Compresses a dry run's value.
+#[cfg(all(feature = "writes", not(feature = "compress-dictionaries")))]
+fn compress<V: Compressible>(bytes: Bytes<'_>) -> Result<Bytes<'_>, LayerFailure> {
+    bytes.compress::<V>().map_err(LayerFailure::at(Layer::Compression))
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "serialize-messagepack"))]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+    struct Note {
+        text: String,
+    }
+
+    #[cfg(feature = "serde-safety")]
+    unsafe impl crate::layers::serializers::SafeForSerde for Note {}
+
+    impl Serializable for Note {
+        const DIRECTION: Direction = Direction::Both;
+    }
+
+    impl Compressible for Note {
+        const DIRECTION: Direction = Direction::Both;
+        const LEVEL: crate::layers::compressors::Level = crate::layers::compressors::Level::Maximum;
+    }
+
+    impl Encryptable for Note {
+        const DIRECTION: Direction = Direction::Both;
+    }
+
+    impl Correctable for Note {
+        const DIRECTION: Direction = Direction::None;
+        const LEVEL: Level = Level::Medium;
+    }
+
+    impl LayerStack for Note {}
+
+    #[test]
+    fn descriptions_list_the_layers_in_write_order() {
+        let description = Layers::describe::<Note>();
+        assert!(description.applied().eq([
+            Layer::Serialization,
+            Layer::Compression,
+            Layer::Encryption,
+        ]));
+        assert_eq!(description.stage(Layer::Correction).unwrap().overhead, Overhead::None);
+        assert!(matches!(
+            description.stage(Layer::Encryption).unwrap().overhead,
+            Overhead::Fixed(len) if len > 0
+        ));
+    }
+
+    #[test]
+    fn tail_overheads_are_peeled_off_in_read_order() {
+        let stage = |layer, overhead| StageDescription {
+            layer,
+            direction: Direction::Both,
+            method: None,
+            overhead,
+        };
+        let description = PipelineDescription {
+            stages: vec![
+                stage(Layer::Serialization, Overhead::Fixed(1)),
+                stage(Layer::Compression, Overhead::Variable),
+                stage(Layer::Encryption, Overhead::Fixed(28)),
+                stage(Layer::Correction, Overhead::Proportional(25)),
+            ],
+        };
+
+        // 160 → 128 without parity → 100 without the cipher's tail → 99 without the tag:
+        assert_eq!(description.tail_overhead(160), 61);
+        assert_eq!(description.tail_overhead(0), 0);
+    }
+
+    #[cfg(feature = "writes")]
+    #[test]
+    fn dry_runs_report_the_size_after_each_layer() {
+        let note = Note { text: "Meep meep! ".repeat(100) };
+        let dry_run = Layers::dry_run(&note).unwrap();
+
+        let compressed = dry_run.stage(Layer::Compression).unwrap();
+        assert!(compressed.len < dry_run.serialized_len());
+        assert!(dry_run.stored_len() > compressed.len);
+        assert!(!dry_run.stage(Layer::Correction).unwrap().applied);
+        assert_eq!(dry_run.to_string().lines().count(), 4);
+    }
+}
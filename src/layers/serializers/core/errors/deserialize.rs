@@ -96,4 +96,25 @@ pub enum Error {
     #[cfg(feature = "serialize-zerocopy")]
     #[error("source was improperly aligned, was incorrect size, or contained invalid data")]
     Zerocopy,
+
+    /// Error returned while decoding `atlatl`'s order-preserving key encoding.
+    ///
+    /// This is returned for truncated or malformed keys, for example if a key was written by a
+    /// different serializer, or with a different key type.
+    #[error("ordered key decoding failed: {reason}")]
+    Ordered { reason: &'static str },
+
+    /// The value was written with a serialization method whose feature isn't enabled in
+    /// `Cargo.toml`, or that the value's type doesn't support.
+    #[error("value was serialized with {method}, which is not enabled")]
+    Unsupported { method: crate::layers::serializers::Method },
+
+    /// The value is empty, so it doesn't carry the serialization method it was written with.
+    #[error("serialized value is missing its serialization method")]
+    MissingMethod,
+
+    /// The value's serialization method tag isn't recognized, which usually indicates data
+    /// corruption.
+    #[error("unrecognized serialization method {0}")]
+    UnrecognizedMethod(u8),
 }
\ No newline at end of file
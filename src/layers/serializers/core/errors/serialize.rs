@@ -87,4 +87,9 @@ pub enum Error {
     #[cfg(feature = "serialize-zerocopy")]
     #[error("source was improperly aligned, was incorrect size, or contained invalid data")]
     Zerocopy,
+
+    /// The selected serialization method's feature isn't enabled in `Cargo.toml`, or the value's
+    /// type doesn't support it.
+    #[error("{method} serialization is not enabled")]
+    Unsupported { method: crate::layers::serializers::Method },
 }
\ No newline at end of file
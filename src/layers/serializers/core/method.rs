@@ -60,7 +60,43 @@ pub enum Method {
 
     /// Zerocopy enabling safe zero-copy parsing with compile-time layout verification. Use when you
     /// need maximum performance for reading structured data without deserialization overhead.
-    Zerocopy         = 12
+    Zerocopy         = 12,
+
+    /// Atlatl's built-in order-preserving key encoding, used by [`Ordered`] keys. Use when keys
+    /// such as signed integers, floats, or tuples must sort correctly in range queries.
+    ///
+    /// Built-in encodings are numbered from `64`, leaving room below for serializer backends.
+    ///
+    /// [`Ordered`]: crate::layers::serializers::Ordered
+    Ordered          = 64,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Method {
+    /// Returns `true` if this serialization method's feature is enabled in `Cargo.toml`. The
+    /// built-in `Ordered` key encoding is always enabled.
+    #[must_use]
+    pub const fn is_enabled(self) -> bool {
+        match self {
+            Self::BincodeNative    => cfg!(feature = "serialize-bincode-native"),
+            Self::BincodeSerde     => cfg!(feature = "serialize-bincode-serde"),
+            Self::BitcodeNative    => cfg!(feature = "serialize-bitcode-native"),
+            Self::BitcodeSerde     => cfg!(feature = "serialize-bitcode-serde"),
+            Self::Borsh            => cfg!(feature = "serialize-borsh"),
+            Self::MessagePack      => cfg!(feature = "serialize-messagepack"),
+            Self::MusliDescriptive => cfg!(feature = "serialize-musli-descriptive"),
+            Self::MusliStorage     => cfg!(feature = "serialize-musli-storage"),
+            Self::MusliWire        => cfg!(feature = "serialize-musli-wire"),
+            Self::MusliZeroCopy    => cfg!(feature = "serialize-musli-zerocopy"),
+            Self::PostcardSerde    => cfg!(feature = "serialize-postcard-serde"),
+            Self::Rkyv             => cfg!(feature = "serialize-rkyv"),
+            Self::Zerocopy         => cfg!(feature = "serialize-zerocopy"),
+            Self::Ordered          => true,
+        }
+    }
 }
 
 // -------------------------------------------------------------------------------------------------
@@ -90,6 +126,7 @@ impl TryFrom<&u8> for &'static Method {
             10 => Ok(&Method::PostcardSerde),
             11 => Ok(&Method::Rkyv),
             12 => Ok(&Method::Zerocopy),
+            64 => Ok(&Method::Ordered),
             _  => Err(Self::Error::UnrecognizedSerializer(*value)),
         }
     }
@@ -112,6 +149,7 @@ impl std::fmt::Display for Method {
             Self::PostcardSerde    => write!(f, "postcard serde"),
             Self::Rkyv             => write!(f, "rkyv"),
             Self::Zerocopy         => write!(f, "zerocopy"),
+            Self::Ordered          => write!(f, "ordered"),
         }
     }
 }
@@ -141,6 +179,7 @@ mod tests {
             Method::PostcardSerde,
             Method::Rkyv,
             Method::Zerocopy,
+            Method::Ordered,
         ];
 
         for method in &methods {
@@ -176,6 +215,7 @@ mod tests {
         assert_eq!(Method::PostcardSerde as u8,    10);
         assert_eq!(Method::Rkyv as u8,             11);
         assert_eq!(Method::Zerocopy as u8,         12);
+        assert_eq!(Method::Ordered as u8,          64);
     }
 
     /// Test that invalid values return errors
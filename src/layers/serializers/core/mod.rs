@@ -18,18 +18,3 @@ pub use crate::layers::serializers::core::serializable::Serializable;
 mod traits;
 pub use crate::layers::serializers::core::traits::OrderedWhenSerialized;
 pub use crate::layers::serializers::core::traits::Serializer;
-
-// -------------------------------------------------------------------------------------------------
-//
-// Serde Safety
-
-#[cfg(all(
-    feature = "serde-safety",
-    any(
-        feature = "serialize-bincode-serde",
-        feature = "serialize-bitcode-serde",
-        feature = "serialize-messagepack",
-        feature = "serialize-postcard-serde"
-    )
-))]
-pub use crate::layers::serializers::impls::SafeForSerde;
\ No newline at end of file
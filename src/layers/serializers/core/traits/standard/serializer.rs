@@ -118,4 +118,39 @@ pub trait StandardSerializer<'b, T> {
     /// applications to log serialization details, or store metadata about how data was processed in
     /// the data pipeline.
     fn method() -> &'static crate::layers::serializers::Method;
+
+    /// Serializes a value with a serialization method other than the one returned by
+    /// [`method`](Self::method). This is used while several serializers are enabled, for example
+    /// while migrating a database from one format to another.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`SerializeError::Unsupported`] if this implementation can't serialize with the
+    ///   given method. This is the default.
+    ///
+    /// Consult the documentation of the serializer backend you are using for more detail on
+    /// serialization behavior and potential limitations.
+    fn serialize_as(
+        &self,
+        method: crate::layers::serializers::Method
+    ) -> Result<Bytes<'b>, SerializeError> {
+        Err(SerializeError::Unsupported { method })
+    }
+
+    /// Deserializes a value that was serialized with a serialization method other than the one
+    /// returned by [`method`](Self::method). This is what allows values written in an older format
+    /// to be read after the write format changes.
+    ///
+    /// # Errors
+    ///
+    /// * Returns `DeserializeError::Unsupported` if this implementation can't deserialize the given
+    ///   method. This is the default.
+    ///
+    /// * Input bytes are corrupted or malformed.
+    fn deserialize_as(
+        _serialized_bytes: Bytes<'b>,
+        method: crate::layers::serializers::Method
+    ) -> Result<crate::layers::core::Value<'b, T>, crate::layers::serializers::DeserializeError> {
+        Err(crate::layers::serializers::DeserializeError::Unsupported { method })
+    }
 }
\ No newline at end of file
@@ -3,6 +3,8 @@
 //! [bincode](https://crates.io/crates/bincode) crate's [serde](https://serde.rs/) implementation.
 
 mod serializer;
+pub(crate) use crate::layers::serializers::impls::bincode_serde::serializer::{decode, encode};
+
 mod ordered_when_serialized;
 
 #[cfg(feature = "serde-safety")]
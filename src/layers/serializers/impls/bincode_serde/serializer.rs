@@ -2,6 +2,7 @@
 
 use crate::layers::core::{Bytes, Value};
 use crate::layers::serializers::{DeserializeError, Method, SerializeError};
+use crate::layers::serializers::impls::dispatch::{deserialize_serde, serialize_serde};
 
 // -------------------------------------------------------------------------------------------------
 
@@ -75,6 +76,33 @@ where T: serde::de::DeserializeOwned + serde::Serialize + crate::layers::seriali
     fn method() -> &'static Method {
         &Method::BincodeSerde
     }
+
+    /// Serializes a value with another enabled `serde` serializer, while several are enabled.
+    ///
+    /// # Errors
+    ///
+    /// * Returns `SerializeError::Unsupported` if the method isn't an enabled `serde` serializer.
+    #[inline]
+    fn serialize_as(
+        &self,
+        method: Method
+    ) -> Result<Bytes<'b>, SerializeError> {
+        Ok(serialize_serde(self, method)?.into())
+    }
+
+    /// Deserializes a value that was written by another enabled `serde` serializer, while several
+    /// are enabled.
+    ///
+    /// # Errors
+    ///
+    /// * Returns `DeserializeError::Unsupported` if the method isn't an enabled `serde` serializer.
+    #[inline]
+    fn deserialize_as(
+        serialized_bytes: Bytes<'b>,
+        method: Method
+    ) -> Result<Value<'b, T>, DeserializeError> {
+        Ok(deserialize_serde::<T>(&serialized_bytes, method)?.into())
+    }
 }
 
 #[cfg(not(feature = "serde-safety"))]
@@ -143,4 +171,59 @@ where T: serde::de::DeserializeOwned + serde::Serialize {
     fn method() -> &'static Method {
         &Method::BincodeSerde
     }
-}
\ No newline at end of file
+
+    /// Serializes a value with another enabled `serde` serializer, while several are enabled.
+    ///
+    /// # Errors
+    ///
+    /// * Returns `SerializeError::Unsupported` if the method isn't an enabled `serde` serializer.
+    #[inline]
+    fn serialize_as(
+        &self,
+        method: Method
+    ) -> Result<Bytes<'b>, SerializeError> {
+        Ok(serialize_serde(self, method)?.into())
+    }
+
+    /// Deserializes a value that was written by another enabled `serde` serializer, while several
+    /// are enabled.
+    ///
+    /// # Errors
+    ///
+    /// * Returns `DeserializeError::Unsupported` if the method isn't an enabled `serde` serializer.
+    #[inline]
+    fn deserialize_as(
+        serialized_bytes: Bytes<'b>,
+        method: Method
+    ) -> Result<Value<'b, T>, DeserializeError> {
+        Ok(deserialize_serde::<T>(&serialized_bytes, method)?.into())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Serializes a value into this format's binary representation.
+///
+/// # Errors
+///
+/// * To understand the possible errors this serializer may produce, please refer to the official
+///   documentation: <https://docs.rs/bincode>
+pub fn encode<T: serde::Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, SerializeError> {
+    Ok(bincode::serde::encode_to_vec(value, bincode::config::standard())?)
+}
+
+/// Deserializes a value from this format's binary representation.
+///
+/// # Errors
+///
+/// * To understand the possible errors this deserializer may produce, please refer to the official
+///   documentation: <https://docs.rs/bincode>
+pub fn decode<T: serde::de::DeserializeOwned>(
+    serialized_bytes: &[u8]
+) -> Result<T, DeserializeError> {
+    let (value, _bytes_read): (T, usize) =
+        bincode::serde::decode_from_slice(serialized_bytes, bincode::config::standard())?;
+    Ok(value)
+}
@@ -3,6 +3,10 @@
 //! [serde](https://serde.rs/) implementation.
 
 mod serializer;
+pub(crate) use crate::layers::serializers::impls::bitcode_serde::serializer::{decode, encode};
+
+// Key ordering follows the serializer that implements `Serializer`, see `impls::dispatch`.
+#[cfg(not(feature = "serialize-bincode-serde"))]
 mod ordered_when_serialized;
 
 #[cfg(feature = "serde-safety")]
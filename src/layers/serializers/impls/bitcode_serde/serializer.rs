@@ -1,11 +1,16 @@
 //! Trait implementation that tells the system how to serialize & deserialize types.
 
+#[cfg(not(feature = "serialize-bincode-serde"))]
 use crate::layers::core::{Bytes, Value};
-use crate::layers::serializers::{DeserializeError, Method, SerializeError};
+use crate::layers::serializers::{DeserializeError, SerializeError};
+#[cfg(not(feature = "serialize-bincode-serde"))]
+use crate::layers::serializers::Method;
+#[cfg(not(feature = "serialize-bincode-serde"))]
+use crate::layers::serializers::impls::dispatch::{deserialize_serde, serialize_serde};
 
 // -------------------------------------------------------------------------------------------------
 
-#[cfg(feature = "serde-safety")]
+#[cfg(all(feature = "serde-safety", not(feature = "serialize-bincode-serde")))]
 impl<'b, T> crate::layers::Serializer<'b, T> for T
 where T: serde::de::DeserializeOwned + serde::Serialize + crate::layers::serializers::impls::bitcode_serde::serde_safety::SafeForBitcodeSerde {
     /// Serializes an owned value into its binary representation.
@@ -71,9 +76,36 @@ where T: serde::de::DeserializeOwned + serde::Serialize + crate::layers::seriali
     fn method() -> &'static Method {
         &Method::BitcodeSerde
     }
+
+    /// Serializes a value with another enabled `serde` serializer, while several are enabled.
+    ///
+    /// # Errors
+    ///
+    /// * Returns `SerializeError::Unsupported` if the method isn't an enabled `serde` serializer.
+    #[inline]
+    fn serialize_as(
+        &self,
+        method: Method
+    ) -> Result<Bytes<'b>, SerializeError> {
+        Ok(serialize_serde(self, method)?.into())
+    }
+
+    /// Deserializes a value that was written by another enabled `serde` serializer, while several
+    /// are enabled.
+    ///
+    /// # Errors
+    ///
+    /// * Returns `DeserializeError::Unsupported` if the method isn't an enabled `serde` serializer.
+    #[inline]
+    fn deserialize_as(
+        serialized_bytes: Bytes<'b>,
+        method: Method
+    ) -> Result<Value<'b, T>, DeserializeError> {
+        Ok(deserialize_serde::<T>(&serialized_bytes, method)?.into())
+    }
 }
 
-#[cfg(not(feature = "serde-safety"))]
+#[cfg(all(not(feature = "serde-safety"), not(feature = "serialize-bincode-serde")))]
 impl<'b, T> crate::layers::Serializer<'b, T> for T
 where T: serde::de::DeserializeOwned + serde::Serialize {
     /// Serializes an owned value into its binary representation.
@@ -139,4 +171,57 @@ where T: serde::de::DeserializeOwned + serde::Serialize {
     fn method() -> &'static Method {
         &Method::BitcodeSerde
     }
-}
\ No newline at end of file
+
+    /// Serializes a value with another enabled `serde` serializer, while several are enabled.
+    ///
+    /// # Errors
+    ///
+    /// * Returns `SerializeError::Unsupported` if the method isn't an enabled `serde` serializer.
+    #[inline]
+    fn serialize_as(
+        &self,
+        method: Method
+    ) -> Result<Bytes<'b>, SerializeError> {
+        Ok(serialize_serde(self, method)?.into())
+    }
+
+    /// Deserializes a value that was written by another enabled `serde` serializer, while several
+    /// are enabled.
+    ///
+    /// # Errors
+    ///
+    /// * Returns `DeserializeError::Unsupported` if the method isn't an enabled `serde` serializer.
+    #[inline]
+    fn deserialize_as(
+        serialized_bytes: Bytes<'b>,
+        method: Method
+    ) -> Result<Value<'b, T>, DeserializeError> {
+        Ok(deserialize_serde::<T>(&serialized_bytes, method)?.into())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Serializes a value into this format's binary representation.
+///
+/// # Errors
+///
+/// * To understand the possible errors this serializer may produce, please refer to the official
+///   documentation: <https://docs.rs/bitcode>
+pub fn encode<T: serde::Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, SerializeError> {
+    Ok(bitcode::serialize(value)?)
+}
+
+/// Deserializes a value from this format's binary representation.
+///
+/// # Errors
+///
+/// * To understand the possible errors this deserializer may produce, please refer to the official
+///   documentation: <https://docs.rs/bitcode>
+pub fn decode<T: serde::de::DeserializeOwned>(
+    serialized_bytes: &[u8]
+) -> Result<T, DeserializeError> {
+    Ok(bitcode::deserialize::<T>(serialized_bytes)?)
+}
@@ -0,0 +1,251 @@
+//! Runtime dispatch between the compiled-in serializers.
+//!
+//! Every serialized value ends with a single byte that identifies the serialization [`Method`] it
+//! was written with:
+//!
+//! | `data`  | `method` |
+//! |---------|----------|
+//! | `&[u8]` | `u8`     |
+//!
+//! Values are deserialized with the serializer named by their tag, rather than with the one that's
+//! currently used for writing. Several `serde` serializers may be enabled at once, which allows a
+//! database to be migrated between formats while it's live: new values are written in the new
+//! format, and values in the old format can still be read.
+//!
+//! While several `serde` serializers are enabled, the first in [`Method`] order implements the
+//! `Serializer` trait. Keys are always serialized by it without a tag, so that their ordering and
+//! look-ups are unaffected by the write format.
+//!
+//! Types whose `Serializer` implementation supports a single format, such as `Ordered` keys, are
+//! always written in that format.
+
+use crate::layers::core::{Bytes, Value};
+use crate::layers::serializers::{DeserializeError, Method, SerializeError, Serializer};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// The serializer that values are written with, unless another is selected with
+/// [`set_write_method`]. This is the first enabled serializer, in [`Method`] order.
+pub const DEFAULT_METHOD: Method = if cfg!(feature = "serialize-bincode-native") {
+    Method::BincodeNative
+} else if cfg!(feature = "serialize-bincode-serde") {
+    Method::BincodeSerde
+} else if cfg!(feature = "serialize-bitcode-native") {
+    Method::BitcodeNative
+} else if cfg!(feature = "serialize-bitcode-serde") {
+    Method::BitcodeSerde
+} else if cfg!(feature = "serialize-borsh") {
+    Method::Borsh
+} else if cfg!(feature = "serialize-messagepack") {
+    Method::MessagePack
+} else if cfg!(feature = "serialize-musli-descriptive") {
+    Method::MusliDescriptive
+} else if cfg!(feature = "serialize-musli-storage") {
+    Method::MusliStorage
+} else if cfg!(feature = "serialize-musli-wire") {
+    Method::MusliWire
+} else if cfg!(feature = "serialize-musli-zerocopy") {
+    Method::MusliZeroCopy
+} else if cfg!(feature = "serialize-postcard-serde") {
+    Method::PostcardSerde
+} else if cfg!(feature = "serialize-rkyv") {
+    Method::Rkyv
+} else {
+    Method::Zerocopy
+};
+
+/// The serializer that values are currently written with, as a `Method` discriminant.
+static WRITE_METHOD: AtomicU8 = AtomicU8::new(DEFAULT_METHOD as u8);
+
+/// Held by tests that change the write method, so that they don't see each other's changes.
+#[cfg(test)]
+pub(crate) static WRITE_METHOD_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Selects the serializer that values are written with from now on. Values that were written with
+/// other serializers can still be read, as long as their features are enabled.
+///
+/// # Errors
+///
+/// * Returns [`SerializeError::Unsupported`] if the serializer's feature isn't enabled, or for the
+///   `Ordered` key encoding, which can't serialize values.
+pub fn set_write_method(method: Method) -> Result<(), SerializeError> {
+    if method.is_enabled() && method != Method::Ordered {
+        WRITE_METHOD.store(method as u8, Ordering::Relaxed);
+        Ok(())
+    } else {
+        Err(SerializeError::Unsupported { method })
+    }
+}
+
+/// Returns the serializer that values are currently written with.
+#[must_use]
+pub fn write_method() -> Method {
+    <&Method>::try_from(&WRITE_METHOD.load(Ordering::Relaxed))
+        .map_or(DEFAULT_METHOD, |method| *method)
+}
+
+/// Serializes a value with the current write serializer, and tags it with the serializer's method.
+///
+/// # Errors
+///
+/// Consult the documentation of the serializer backend you are using for more detail on
+/// serialization behavior and potential limitations.
+pub(crate) fn serialize_tagged<'b, V: Serializer<'b, V>>(
+    value: Value<'b, V>
+) -> Result<Bytes<'b>, SerializeError> {
+    let method = write_method();
+    if method != *V::method() {
+        match value.as_ref().serialize_as(method) {
+            Err(SerializeError::Unsupported { .. }) => {},
+            serialized_bytes => return Ok(tag(serialized_bytes?, method)),
+        }
+    }
+
+    let serialized_bytes = match value {
+        Value::Borrowed(value_ref) => V::serialize_ref(value_ref)?,
+        Value::Owned(value) => V::serialize(value)?,
+    };
+    Ok(tag(serialized_bytes, *V::method()))
+}
+
+/// Deserializes a value with the serializer named by its method tag.
+///
+/// # Errors
+///
+/// * The value has no method tag, or the tag names an unknown or disabled serializer.
+///
+/// * Input bytes are corrupted or malformed.
+pub(crate) fn deserialize_tagged<'b, V: Serializer<'b, V>>(
+    serialized_bytes: Bytes<'b>
+) -> Result<Value<'b, V>, DeserializeError> {
+    let (method, serialized_bytes) = untag(serialized_bytes)?;
+    if method == *V::method() {
+        V::deserialize(serialized_bytes)
+    } else {
+        V::deserialize_as(serialized_bytes, method)
+    }
+}
+
+/// Serializes a value with any of the enabled `serde` serializers.
+///
+/// # Errors
+///
+/// * Returns [`SerializeError::Unsupported`] if the method isn't an enabled `serde` serializer.
+///
+/// Consult the documentation of the serializer backend for more detail on serialization behavior
+/// and potential limitations.
+#[cfg(any(
+    feature = "serialize-bincode-serde",
+    feature = "serialize-bitcode-serde",
+    feature = "serialize-messagepack",
+    feature = "serialize-postcard-serde"
+))]
+pub(crate) fn serialize_serde<T: serde::Serialize + ?Sized>(
+    value: &T,
+    method: Method
+) -> Result<Vec<u8>, SerializeError> {
+    match method {
+        #[cfg(feature = "serialize-bincode-serde")]
+        Method::BincodeSerde => super::bincode_serde::encode(value),
+        #[cfg(feature = "serialize-bitcode-serde")]
+        Method::BitcodeSerde => super::bitcode_serde::encode(value),
+        #[cfg(feature = "serialize-messagepack")]
+        Method::MessagePack => super::rmp_serde::encode(value),
+        #[cfg(feature = "serialize-postcard-serde")]
+        Method::PostcardSerde => super::postcard_serde::encode(value),
+        _ => Err(SerializeError::Unsupported { method }),
+    }
+}
+
+/// Deserializes a value with any of the enabled `serde` serializers.
+///
+/// # Errors
+///
+/// * Returns [`DeserializeError::Unsupported`] if the method isn't an enabled `serde` serializer.
+///
+/// * Input bytes are corrupted or malformed.
+#[cfg(any(
+    feature = "serialize-bincode-serde",
+    feature = "serialize-bitcode-serde",
+    feature = "serialize-messagepack",
+    feature = "serialize-postcard-serde"
+))]
+pub(crate) fn deserialize_serde<T: serde::de::DeserializeOwned>(
+    serialized_bytes: &[u8],
+    method: Method
+) -> Result<T, DeserializeError> {
+    match method {
+        #[cfg(feature = "serialize-bincode-serde")]
+        Method::BincodeSerde => super::bincode_serde::decode(serialized_bytes),
+        #[cfg(feature = "serialize-bitcode-serde")]
+        Method::BitcodeSerde => super::bitcode_serde::decode(serialized_bytes),
+        #[cfg(feature = "serialize-messagepack")]
+        Method::MessagePack => super::rmp_serde::decode(serialized_bytes),
+        #[cfg(feature = "serialize-postcard-serde")]
+        Method::PostcardSerde => super::postcard_serde::decode(serialized_bytes),
+        _ => Err(DeserializeError::Unsupported { method }),
+    }
+}
+
+/// Appends the method tag to serialized bytes.
+fn tag(serialized_bytes: Bytes<'_>, method: Method) -> Bytes<'_> {
+    let (metadata, data) = serialized_bytes.into_parts();
+    let mut data = data.into_owned();
+    data.push(method as u8);
+    Bytes::from_parts(metadata, data.into())
+}
+
+/// Removes the method tag from serialized bytes, returning the method it names.
+fn untag(mut serialized_bytes: Bytes<'_>) -> Result<(Method, Bytes<'_>), DeserializeError> {
+    let tag = *serialized_bytes.as_ref().last().ok_or(DeserializeError::MissingMethod)?;
+    let method = *<&Method>::try_from(&tag)
+        .map_err(|_| DeserializeError::UnrecognizedMethod(tag))?;
+    serialized_bytes.truncate(serialized_bytes.len() - 1);
+    Ok((method, serialized_bytes))
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "serialize-messagepack"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_tagged_with_their_method() {
+        let _lock = WRITE_METHOD_LOCK.lock().unwrap();
+        let value = String::from("Wile E. Coyote");
+
+        let serialized = serialize_tagged::<String>(Value::Borrowed(&value)).unwrap();
+        assert_eq!(serialized.as_ref().last(), Some(&(write_method() as u8)));
+
+        let deserialized = deserialize_tagged::<String>(serialized).unwrap();
+        assert_eq!(deserialized.as_ref(), &value);
+    }
+
+    #[test]
+    fn values_are_read_in_the_format_they_were_written_in() {
+        let _lock = WRITE_METHOD_LOCK.lock().unwrap();
+        let mut serialized = serialize_serde("Road Runner", Method::MessagePack).unwrap();
+        serialized.push(Method::MessagePack as u8);
+
+        let deserialized = deserialize_tagged::<String>(Bytes::from_vec(serialized)).unwrap();
+        assert_eq!(deserialized.as_ref(), "Road Runner");
+
+        let untagged = Bytes::from_vec(vec![1, 2, 3, 99]);
+        assert!(matches!(
+            deserialize_tagged::<String>(untagged),
+            Err(DeserializeError::UnrecognizedMethod(99))
+        ));
+        assert!(set_write_method(Method::Ordered).is_err());
+        assert!(set_write_method(DEFAULT_METHOD).is_ok());
+    }
+}
@@ -27,11 +27,21 @@ const _SERIALIZER_FEATURE_COUNT: usize = count_features!(
     "serialize-zerocopy"
 );
 
+/// Serializers built on `serde`. Any number of these may be enabled together.
+const _SERDE_SERIALIZER_FEATURE_COUNT: usize = count_features!(
+    "serialize-bincode-serde",
+    "serialize-bitcode-serde",
+    "serialize-messagepack",
+    "serialize-postcard-serde"
+);
+
 const _: () = {
     assert!(
-        // Only one serializer feature can be enabled. To fix: 1. open the `Cargo.toml` file, 2. find the
-        // `[dependencies]` section where `atlatl` is declared, 3. ensure only one serializer is enabled.
-        !(_SERIALIZER_FEATURE_COUNT > 1),
+        // Only one serializer feature can be enabled, unless every enabled serializer is built on
+        // `serde`. To fix: 1. open the `Cargo.toml` file, 2. find the `[dependencies]` section
+        // where `atlatl` is declared, 3. ensure only one serializer is enabled.
+        !(_SERIALIZER_FEATURE_COUNT > 1
+            && _SERIALIZER_FEATURE_COUNT > _SERDE_SERIALIZER_FEATURE_COUNT),
         "Multiple serializer features enabled! Enable only one of: \
         `serialize-bincode-native`, \
         `serialize-bincode-serde`, \
@@ -45,7 +55,9 @@ const _: () = {
         `serialize-musli-zerocopy`, \
         `serialize-postcard-serde`, \
         `serialize-rkyv`, or \
-        `serialize-zerocopy`",
+        `serialize-zerocopy`. \
+        Several of `serialize-bincode-serde`, `serialize-bitcode-serde`, `serialize-messagepack`, \
+        and `serialize-postcard-serde` may be enabled together.",
     );
 };
 
@@ -92,18 +104,43 @@ pub mod rkyv;
 #[cfg(feature = "serialize-zerocopy")]
 pub mod zerocopy;
 
+// -------------------------------------------------------------------------------------------------
+//
+// Runtime Dispatch
+
+pub mod dispatch;
+
 // -------------------------------------------------------------------------------------------------
 //
 // Serde Safety
+//
+// While several `serde` serializers are enabled, the first in `Method` order implements
+// `Serializer` and its safety marker is the one that's required.
 
 #[cfg(all(feature = "serialize-bincode-serde", feature = "serde-safety"))]
 pub use crate::layers::serializers::impls::bincode_serde::serde_safety::SafeForBincodeSerde as SafeForSerde;
 
-#[cfg(all(feature = "serialize-bitcode-serde", feature = "serde-safety"))]
+#[cfg(all(
+    feature = "serialize-bitcode-serde",
+    feature = "serde-safety",
+    not(feature = "serialize-bincode-serde")
+))]
 pub use crate::layers::serializers::impls::bitcode_serde::serde_safety::SafeForBitcodeSerde as SafeForSerde;
 
-#[cfg(all(feature = "serialize-postcard-serde", feature = "serde-safety"))]
+#[cfg(all(
+    feature = "serialize-postcard-serde",
+    feature = "serde-safety",
+    not(any(
+        feature = "serialize-bincode-serde",
+        feature = "serialize-bitcode-serde",
+        feature = "serialize-messagepack"
+    ))
+))]
 pub use crate::layers::serializers::impls::postcard_serde::serde_safety::SafeForPostcardSerde as SafeForSerde;
 
-#[cfg(all(feature = "serialize-messagepack", feature = "serde-safety"))]
+#[cfg(all(
+    feature = "serialize-messagepack",
+    feature = "serde-safety",
+    not(any(feature = "serialize-bincode-serde", feature = "serialize-bitcode-serde"))
+))]
 pub use crate::layers::serializers::impls::rmp_serde::serde_safety::SafeForMessagePack as SafeForSerde;
\ No newline at end of file
@@ -2,6 +2,14 @@
 //! [postcard](https://crates.io/crates/postcard) crate's [serde](https://serde.rs/) implementation.
 
 mod serializer;
+pub(crate) use crate::layers::serializers::impls::postcard_serde::serializer::{decode, encode};
+
+// Key ordering follows the serializer that implements `Serializer`, see `impls::dispatch`.
+#[cfg(not(any(
+    feature = "serialize-bincode-serde",
+    feature = "serialize-bitcode-serde",
+    feature = "serialize-messagepack",
+)))]
 mod ordered_when_serialized;
 
 #[cfg(feature = "serde-safety")]
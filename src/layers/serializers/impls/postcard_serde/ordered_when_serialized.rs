@@ -3,4 +3,4 @@
 
 /// Marker trait indicating that when `u8` types are serialized by `postcard-serde`, they remain in
 /// lexographical order.
-impl crate::layers::serializers::OrderedWhenSerialized for u8 {}
\ No newline at end of file
+impl crate::layers::serializers::OrderedWhenSerialized<'_> for u8 {}
\ No newline at end of file
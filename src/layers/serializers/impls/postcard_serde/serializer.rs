@@ -1,33 +1,86 @@
-//! Trait implementations that tell the system how to serialize & deserialize types.
+//! Trait implementation that tells the system how to serialize & deserialize types.
 
-use crate::layers::{serializers::{Error, Method}, ValueBuf};
-use musli::{Decode, Encode, alloc::System, mode::Binary};
+#[cfg(not(any(
+    feature = "serialize-bincode-serde",
+    feature = "serialize-bitcode-serde",
+    feature = "serialize-messagepack",
+)))]
+use crate::layers::core::{Bytes, Value};
+use crate::layers::serializers::{DeserializeError, SerializeError};
+#[cfg(not(any(
+    feature = "serialize-bincode-serde",
+    feature = "serialize-bitcode-serde",
+    feature = "serialize-messagepack",
+)))]
+use crate::layers::serializers::Method;
+#[cfg(not(any(
+    feature = "serialize-bincode-serde",
+    feature = "serialize-bitcode-serde",
+    feature = "serialize-messagepack",
+)))]
+use crate::layers::serializers::impls::dispatch::{deserialize_serde, serialize_serde};
 
 // -------------------------------------------------------------------------------------------------
 
-#[cfg(feature = "serde-safety")]
+#[cfg(all(feature = "serde-safety", not(any(
+    feature = "serialize-bincode-serde",
+    feature = "serialize-bitcode-serde",
+    feature = "serialize-messagepack",
+))))]
 impl<'b, T> crate::layers::Serializer<'b, T> for T
-where T: serde::de::DeserializeOwned + serde::Serialize + crate::layers::serializers::SafeForPostcardSerde {
-    /// Serialize a value into its binary representation.
+where T: serde::de::DeserializeOwned + serde::Serialize + crate::layers::serializers::impls::postcard_serde::serde_safety::SafeForPostcardSerde {
+    /// Serializes an owned value into its binary representation.
     ///
     /// # Errors
     ///
     /// * To understand the possible errors this serializer may produce, please refer to the
     ///   official documentation: <https://docs.rs/postcard>
+    ///
+    /// # Generics & Lifetimes
+    ///
+    /// * `T` generic represents the user's value type, for example: `User`, `String`, etc.
+    /// * `b` lifetime represents bytes potentially being borrowed from the host application.
     #[inline]
-    fn serialize(&self) -> Result<ValueBuf<'b>, Error> {
+    fn serialize(
+        self
+    ) -> Result<Bytes<'b>, SerializeError> {
+        Ok(postcard::to_stdvec(&self)?.into())
+    }
+
+    /// Serializes a borrowed value into its binary representation.
+    ///
+    /// # Errors
+    ///
+    /// * To understand the possible errors this serializer may produce, please refer to the
+    ///   official documentation: <https://docs.rs/postcard>
+    ///
+    /// # Generics & Lifetimes
+    ///
+    /// * `T` generic represents the user's value type, for example: `User`, `String`, etc.
+    /// * `b` lifetime represents bytes potentially being borrowed from the host application.
+    #[inline]
+    fn serialize_ref(
+        &'b self
+    ) -> Result<Bytes<'b>, SerializeError> {
         Ok(postcard::to_stdvec(self)?.into())
     }
 
-    /// Deserialize a value into its binary representation.
+    /// Deserializes a series of bytes into a `T` native value.
     ///
     /// # Errors
     ///
     /// * To understand the possible errors this deserializer may produce, please refer to the
     ///   official documentation: <https://docs.rs/postcard>
+    ///
+    /// # Generics & Lifetimes
+    ///
+    /// * `T` generic represents the user's value type, for example: `User`, `String`, etc.
+    /// * `b` lifetime represents bytes potentially being borrowed from the `redb` database.
     #[inline]
-    fn deserialize(serialized_bytes: ValueBuf<'b>) -> Result<T, Error> {
-        Ok(postcard::from_bytes(&serialized_bytes)?)
+    fn deserialize(
+        serialized_bytes: Bytes<'b>
+    ) -> Result<Value<'b, T>, DeserializeError> {
+        Ok(postcard::from_bytes::<T>(&serialized_bytes)?.into())
     }
 
     /// Returns the serialization method that the current `Serializer` trait implements.
@@ -39,31 +92,94 @@ where T: serde::de::DeserializeOwned + serde::Serialize + crate::layers::seriali
     fn method() -> &'static Method {
         &Method::PostcardSerde
     }
+
+    /// Serializes a value with another enabled `serde` serializer, while several are enabled.
+    ///
+    /// # Errors
+    ///
+    /// * Returns `SerializeError::Unsupported` if the method isn't an enabled `serde` serializer.
+    #[inline]
+    fn serialize_as(
+        &self,
+        method: Method
+    ) -> Result<Bytes<'b>, SerializeError> {
+        Ok(serialize_serde(self, method)?.into())
+    }
+
+    /// Deserializes a value that was written by another enabled `serde` serializer, while several
+    /// are enabled.
+    ///
+    /// # Errors
+    ///
+    /// * Returns `DeserializeError::Unsupported` if the method isn't an enabled `serde` serializer.
+    #[inline]
+    fn deserialize_as(
+        serialized_bytes: Bytes<'b>,
+        method: Method
+    ) -> Result<Value<'b, T>, DeserializeError> {
+        Ok(deserialize_serde::<T>(&serialized_bytes, method)?.into())
+    }
 }
 
-#[cfg(not(feature = "serde-safety"))]
-impl<T> crate::layers::Serializer<T> for T
+#[cfg(all(not(feature = "serde-safety"), not(any(
+    feature = "serialize-bincode-serde",
+    feature = "serialize-bitcode-serde",
+    feature = "serialize-messagepack",
+))))]
+impl<'b, T> crate::layers::Serializer<'b, T> for T
 where T: serde::de::DeserializeOwned + serde::Serialize {
-    /// Serialize a value into its binary representation.
+    /// Serializes an owned value into its binary representation.
+    ///
+    /// # Errors
+    ///
+    /// * To understand the possible errors this serializer may produce, please refer to the
+    ///   official documentation: <https://docs.rs/postcard>
+    ///
+    /// # Generics & Lifetimes
+    ///
+    /// * `T` generic represents the user's value type, for example: `User`, `String`, etc.
+    /// * `b` lifetime represents bytes potentially being borrowed from the host application.
+    #[inline]
+    fn serialize(
+        self
+    ) -> Result<Bytes<'b>, SerializeError> {
+        Ok(postcard::to_stdvec(&self)?.into())
+    }
+
+    /// Serializes a borrowed value into its binary representation.
     ///
     /// # Errors
     ///
     /// * To understand the possible errors this serializer may produce, please refer to the
     ///   official documentation: <https://docs.rs/postcard>
+    ///
+    /// # Generics & Lifetimes
+    ///
+    /// * `T` generic represents the user's value type, for example: `User`, `String`, etc.
+    /// * `b` lifetime represents bytes potentially being borrowed from the host application.
     #[inline]
-    fn serialize(&self) -> Result<ValueBuf<'b>, Error> {
+    fn serialize_ref(
+        &'b self
+    ) -> Result<Bytes<'b>, SerializeError> {
         Ok(postcard::to_stdvec(self)?.into())
     }
 
-    /// Deserialize a value into its binary representation.
+    /// Deserializes a series of bytes into a `T` native value.
     ///
     /// # Errors
     ///
     /// * To understand the possible errors this deserializer may produce, please refer to the
     ///   official documentation: <https://docs.rs/postcard>
+    ///
+    /// # Generics & Lifetimes
+    ///
+    /// * `T` generic represents the user's value type, for example: `User`, `String`, etc.
+    /// * `b` lifetime represents bytes potentially being borrowed from the `redb` database.
     #[inline]
-    fn deserialize(serialized_bytes: ValueBuf<'b>) -> Result<T, Error> {
-        Ok(postcard::from_bytes(&serialized_bytes)?)
+    fn deserialize(
+        serialized_bytes: Bytes<'b>
+    ) -> Result<Value<'b, T>, DeserializeError> {
+        Ok(postcard::from_bytes::<T>(&serialized_bytes)?.into())
     }
 
     /// Returns the serialization method that the current `Serializer` trait implements.
@@ -75,4 +191,57 @@ where T: serde::de::DeserializeOwned + serde::Serialize {
     fn method() -> &'static Method {
         &Method::PostcardSerde
     }
-}
\ No newline at end of file
+
+    /// Serializes a value with another enabled `serde` serializer, while several are enabled.
+    ///
+    /// # Errors
+    ///
+    /// * Returns `SerializeError::Unsupported` if the method isn't an enabled `serde` serializer.
+    #[inline]
+    fn serialize_as(
+        &self,
+        method: Method
+    ) -> Result<Bytes<'b>, SerializeError> {
+        Ok(serialize_serde(self, method)?.into())
+    }
+
+    /// Deserializes a value that was written by another enabled `serde` serializer, while several
+    /// are enabled.
+    ///
+    /// # Errors
+    ///
+    /// * Returns `DeserializeError::Unsupported` if the method isn't an enabled `serde` serializer.
+    #[inline]
+    fn deserialize_as(
+        serialized_bytes: Bytes<'b>,
+        method: Method
+    ) -> Result<Value<'b, T>, DeserializeError> {
+        Ok(deserialize_serde::<T>(&serialized_bytes, method)?.into())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Serializes a value into this format's binary representation.
+///
+/// # Errors
+///
+/// * To understand the possible errors this serializer may produce, please refer to the official
+///   documentation: <https://docs.rs/postcard>
+pub fn encode<T: serde::Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, SerializeError> {
+    Ok(postcard::to_stdvec(value)?)
+}
+
+/// Deserializes a value from this format's binary representation.
+///
+/// # Errors
+///
+/// * To understand the possible errors this deserializer may produce, please refer to the official
+///   documentation: <https://docs.rs/postcard>
+pub fn decode<T: serde::de::DeserializeOwned>(
+    serialized_bytes: &[u8]
+) -> Result<T, DeserializeError> {
+    Ok(postcard::from_bytes::<T>(serialized_bytes)?)
+}
@@ -4,8 +4,12 @@
 //! [Evgeny Safronov](https://github.com/3Hren)'s
 //! [rmp-serde](https://crates.io/crates/rmp-serde) crate.
 
+// Key ordering follows the serializer that implements `Serializer`, see `impls::dispatch`.
+#[cfg(not(any(feature = "serialize-bincode-serde", feature = "serialize-bitcode-serde")))]
 mod ordered_when_serialized;
+
 mod serializer;
+pub(crate) use crate::layers::serializers::impls::rmp_serde::serializer::{decode, encode};
 
 #[cfg(feature = "serde-safety")]
 pub mod serde_safety;
@@ -13,11 +13,22 @@ pub use crate::layers::serializers::core::SerializeError;
 pub use crate::layers::serializers::core::OrderedWhenSerialized;
 pub use crate::layers::serializers::core::Serializer;
 
+// -------------------------------------------------------------------------------------------------
+//
+// Order-Preserving Keys
+
+pub mod ordered;
+pub use crate::layers::serializers::ordered::KeyTuple;
+pub use crate::layers::serializers::ordered::Ordered;
+pub use crate::layers::serializers::ordered::OrderedEncoding;
+
 // -------------------------------------------------------------------------------------------------
 //
 // Serializer Implementations
 
 pub mod impls;
+pub use crate::layers::serializers::impls::dispatch;
+pub use crate::layers::serializers::impls::dispatch::{set_write_method, write_method};
 
 // -------------------------------------------------------------------------------------------------
 //
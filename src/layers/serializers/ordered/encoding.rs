@@ -0,0 +1,307 @@
+//! The `OrderedEncoding` trait, and its implementations for primitive, string, and tuple types.
+
+use crate::layers::serializers::DeserializeError;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A canonical binary encoding whose byte-wise order matches the type's natural order.
+///
+/// For any two values `a` and `b`, `a < b` if and only if `a`'s encoding sorts before `b`'s. This
+/// makes the encoding safe to use for `redb` keys in range queries and ordered indexes.
+///
+/// # Encodings
+///
+/// * Unsigned integers are stored big-endian.
+/// * Signed integers are stored big-endian with the sign bit flipped, so that negative numbers
+///   sort before positive ones.
+/// * Floats are stored in IEEE 754 total order, the same order as [`f64::total_cmp`]: negative
+///   numbers have every bit flipped, and positive numbers have only the sign bit flipped.
+/// * `bool` is stored as a single `0` or `1` byte, and `char` as a big-endian `u32`.
+/// * Strings and byte vectors have each `0x00` byte escaped as `0x00 0xFF`, and are terminated by
+///   `0x00 0x00`. A length prefix would sort `"b"` after `"aa"`, which this avoids.
+/// * `Option<T>` is stored as `0x00` for `None`, or `0x01` followed by the value.
+/// * Tuples are stored as the concatenation of their components. Each component's encoding is
+///   self-delimiting, so tuples sort by their first component, then their second, and so on.
+pub trait OrderedEncoding: Sized {
+    /// Appends the value's order-preserving encoding to the buffer.
+    fn encode_ordered(&self, buffer: &mut Vec<u8>);
+
+    /// Decodes a value from the front of the bytes, advancing past it.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`DeserializeError::Ordered`] if the bytes are truncated or malformed.
+    fn decode_ordered(bytes: &mut &[u8]) -> Result<Self, DeserializeError>;
+
+    /// Returns the value's order-preserving encoding.
+    #[must_use]
+    fn to_ordered_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        self.encode_ordered(&mut buffer);
+        buffer
+    }
+
+    /// Decodes a value from its order-preserving encoding.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`DeserializeError::Ordered`] if the bytes are truncated or malformed, or if any
+    ///   bytes are left over after the value.
+    fn from_ordered_bytes(mut bytes: &[u8]) -> Result<Self, DeserializeError> {
+        let value = Self::decode_ordered(&mut bytes)?;
+        if bytes.is_empty() {
+            Ok(value)
+        } else {
+            Err(DeserializeError::Ordered { reason: "trailing bytes after key" })
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+/// Implements `OrderedEncoding` for unsigned integers, stored big-endian.
+macro_rules! impl_unsigned {
+    ($($unsigned:ty),*) => {$(
+        impl OrderedEncoding for $unsigned {
+            #[inline]
+            fn encode_ordered(&self, buffer: &mut Vec<u8>) {
+                buffer.extend_from_slice(&self.to_be_bytes());
+            }
+
+            #[inline]
+            fn decode_ordered(bytes: &mut &[u8]) -> Result<Self, DeserializeError> {
+                Ok(Self::from_be_bytes(take_array(bytes)?))
+            }
+        }
+    )*};
+}
+
+impl_unsigned!(u8, u16, u32, u64, u128);
+
+/// Implements `OrderedEncoding` for signed integers, stored big-endian with the sign bit flipped.
+macro_rules! impl_signed {
+    ($($signed:ty => $unsigned:ty),*) => {$(
+        impl OrderedEncoding for $signed {
+            #[inline]
+            fn encode_ordered(&self, buffer: &mut Vec<u8>) {
+                (self.cast_unsigned() ^ !(<$unsigned>::MAX >> 1)).encode_ordered(buffer);
+            }
+
+            #[inline]
+            fn decode_ordered(bytes: &mut &[u8]) -> Result<Self, DeserializeError> {
+                let flipped = <$unsigned>::decode_ordered(bytes)?;
+                Ok((flipped ^ !(<$unsigned>::MAX >> 1)).cast_signed())
+            }
+        }
+    )*};
+}
+
+impl_signed!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128);
+
+/// Implements `OrderedEncoding` for floats, stored in IEEE 754 total order.
+macro_rules! impl_float {
+    ($($float:ty => $unsigned:ty),*) => {$(
+        impl OrderedEncoding for $float {
+            #[inline]
+            fn encode_ordered(&self, buffer: &mut Vec<u8>) {
+                let bits = self.to_bits();
+                let sign = !(<$unsigned>::MAX >> 1);
+                let ordered = if bits & sign == 0 { bits ^ sign } else { !bits };
+                ordered.encode_ordered(buffer);
+            }
+
+            #[inline]
+            fn decode_ordered(bytes: &mut &[u8]) -> Result<Self, DeserializeError> {
+                let ordered = <$unsigned>::decode_ordered(bytes)?;
+                let sign = !(<$unsigned>::MAX >> 1);
+                let bits = if ordered & sign == 0 { !ordered } else { ordered ^ sign };
+                Ok(Self::from_bits(bits))
+            }
+        }
+    )*};
+}
+
+impl_float!(f32 => u32, f64 => u64);
+
+impl OrderedEncoding for bool {
+    #[inline]
+    fn encode_ordered(&self, buffer: &mut Vec<u8>) {
+        buffer.push(u8::from(*self));
+    }
+
+    #[inline]
+    fn decode_ordered(bytes: &mut &[u8]) -> Result<Self, DeserializeError> {
+        match u8::decode_ordered(bytes)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DeserializeError::Ordered { reason: "invalid bool" }),
+        }
+    }
+}
+
+impl OrderedEncoding for char {
+    #[inline]
+    fn encode_ordered(&self, buffer: &mut Vec<u8>) {
+        u32::from(*self).encode_ordered(buffer);
+    }
+
+    #[inline]
+    fn decode_ordered(bytes: &mut &[u8]) -> Result<Self, DeserializeError> {
+        Self::from_u32(u32::decode_ordered(bytes)?)
+            .ok_or(DeserializeError::Ordered { reason: "invalid char" })
+    }
+}
+
+impl OrderedEncoding for Vec<u8> {
+    fn encode_ordered(&self, buffer: &mut Vec<u8>) {
+        encode_escaped(self, buffer);
+    }
+
+    fn decode_ordered(bytes: &mut &[u8]) -> Result<Self, DeserializeError> {
+        decode_escaped(bytes)
+    }
+}
+
+impl OrderedEncoding for String {
+    fn encode_ordered(&self, buffer: &mut Vec<u8>) {
+        encode_escaped(self.as_bytes(), buffer);
+    }
+
+    fn decode_ordered(bytes: &mut &[u8]) -> Result<Self, DeserializeError> {
+        Self::from_utf8(decode_escaped(bytes)?)
+            .map_err(|_error| DeserializeError::Ordered { reason: "string isn't valid UTF-8" })
+    }
+}
+
+impl<T: OrderedEncoding> OrderedEncoding for Option<T> {
+    fn encode_ordered(&self, buffer: &mut Vec<u8>) {
+        match self {
+            None => buffer.push(0),
+            Some(value) => {
+                buffer.push(1);
+                value.encode_ordered(buffer);
+            }
+        }
+    }
+
+    fn decode_ordered(bytes: &mut &[u8]) -> Result<Self, DeserializeError> {
+        match u8::decode_ordered(bytes)? {
+            0 => Ok(None),
+            1 => Ok(Some(T::decode_ordered(bytes)?)),
+            _ => Err(DeserializeError::Ordered { reason: "invalid option tag" }),
+        }
+    }
+}
+
+/// Implements `OrderedEncoding` for tuples, stored as the concatenation of their components.
+macro_rules! impl_tuple {
+    ($($component:ident),+) => {
+        impl<$($component: OrderedEncoding),+> OrderedEncoding for ($($component,)+) {
+            #[allow(non_snake_case, reason = "components are named after their type parameters")]
+            fn encode_ordered(&self, buffer: &mut Vec<u8>) {
+                let ($($component,)+) = self;
+                $($component.encode_ordered(buffer);)+
+            }
+
+            fn decode_ordered(bytes: &mut &[u8]) -> Result<Self, DeserializeError> {
+                Ok(($($component::decode_ordered(bytes)?,)+))
+            }
+        }
+    };
+}
+
+impl_tuple!(A);
+impl_tuple!(A, B);
+impl_tuple!(A, B, C);
+impl_tuple!(A, B, C, D);
+impl_tuple!(A, B, C, D, E);
+impl_tuple!(A, B, C, D, E, F);
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Splits a fixed-size array off the front of the bytes.
+fn take_array<const N: usize>(bytes: &mut &[u8]) -> Result<[u8; N], DeserializeError> {
+    let (head, tail) = bytes
+        .split_first_chunk::<N>()
+        .ok_or(DeserializeError::Ordered { reason: "key is truncated" })?;
+    *bytes = tail;
+    Ok(*head)
+}
+
+/// Appends bytes with each `0x00` escaped as `0x00 0xFF`, followed by the `0x00 0x00` terminator.
+fn encode_escaped(value: &[u8], buffer: &mut Vec<u8>) {
+    for byte in value {
+        buffer.push(*byte);
+        if *byte == 0x00 {
+            buffer.push(0xFF);
+        }
+    }
+    buffer.extend_from_slice(&[0x00, 0x00]);
+}
+
+/// Decodes escaped bytes from the front of the bytes, advancing past the terminator.
+fn decode_escaped(bytes: &mut &[u8]) -> Result<Vec<u8>, DeserializeError> {
+    let mut value = Vec::new();
+    loop {
+        match take_array::<1>(bytes)? {
+            [0x00] => match take_array::<1>(bytes)? {
+                [0x00] => return Ok(value),
+                [0xFF] => value.push(0x00),
+                _ => return Err(DeserializeError::Ordered { reason: "invalid escape sequence" }),
+            },
+            [byte] => value.push(byte),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Asserts that the values' encodings sort in the same order as the values.
+    fn assert_sorted<T: OrderedEncoding + std::fmt::Debug + PartialEq>(values: &[T]) {
+        for pair in values.windows(2) {
+            assert!(pair[0].to_ordered_bytes() < pair[1].to_ordered_bytes(), "{pair:?}");
+        }
+        for value in values {
+            assert_eq!(&T::from_ordered_bytes(&value.to_ordered_bytes()).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn integers_sort_naturally() {
+        assert_sorted(&[i64::MIN, -300, -1, 0, 1, 255, 256, i64::MAX]);
+        assert_sorted(&[i8::MIN, -1, 0, i8::MAX]);
+        assert_sorted(&[0_u32, 1, 256, u32::MAX]);
+    }
+
+    #[test]
+    fn floats_sort_in_total_order() {
+        assert_sorted(&[f64::NEG_INFINITY, -1.5e10, -1.0, -0.0, 0.0, 1e-300, 2.5, f64::INFINITY]);
+        assert_sorted(&[-1.0_f32, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn strings_and_tuples_sort_naturally() {
+        assert_sorted(&[String::new(), "a".into(), "a\0".into(), "a\0b".into(), "aa".into(), "b".into()]);
+        assert_sorted(&[
+            ("Desert".to_string(), -5_i32),
+            ("Desert".to_string(), 3),
+            ("Desert\0".to_string(), i32::MIN),
+            ("Tundra".to_string(), 0),
+        ]);
+        assert_sorted(&[None, Some(false), Some(true)]);
+
+        assert!(u64::from_ordered_bytes(&[0; 7]).is_err());
+        assert!(String::from_ordered_bytes(&[b'a', 0x00, 0x01]).is_err());
+        assert!(u8::from_ordered_bytes(&[1, 2]).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+//! The `Ordered` key wrapper, which stores a key using its order-preserving encoding.
+
+use crate::layers::serializers::ordered::OrderedEncoding;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Stores a key using its [`OrderedEncoding`], instead of the configured serializer.
+///
+/// This makes range queries and ordered indexes work correctly for keys that the configured
+/// serializer wouldn't keep in order, such as signed integers, floats, and tuples. For example,
+/// `Ordered(-1_i64)` sorts before `Ordered(0_i64)`, and `Ordered(("Desert", 3))` sorts before
+/// `Ordered(("Tundra", 1))`.
+///
+/// # Notes
+///
+/// * Keys are only compared byte-wise by `redb`, so changing a table's key type to or from
+///   `Ordered` requires its keys to be rewritten.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Ordered<T>(pub T);
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<T> Ordered<T> {
+    /// Returns the wrapped key.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl<T> From<T> for Ordered<T> {
+    /// Wraps a key so that it's stored using its order-preserving encoding.
+    #[inline]
+    fn from(key: T) -> Self {
+        Self(key)
+    }
+}
+
+impl<T> std::ops::Deref for Ordered<T> {
+    type Target = T;
+
+    /// Dereferences an `Ordered` key into the wrapped key.
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(not(feature = "serialize-rkyv"))]
+impl<'b, T: OrderedEncoding> crate::layers::Serializer<'b, Self> for Ordered<T> {
+    /// Serializes an owned key into its order-preserving encoding.
+    ///
+    /// # Errors
+    ///
+    /// * This encoding can't fail.
+    #[inline]
+    fn serialize(
+        self
+    ) -> Result<crate::layers::core::Bytes<'b>, crate::layers::serializers::SerializeError> {
+        Ok(self.0.to_ordered_bytes().into())
+    }
+
+    /// Serializes a borrowed key into its order-preserving encoding.
+    ///
+    /// # Errors
+    ///
+    /// * This encoding can't fail.
+    #[inline]
+    fn serialize_ref(
+        &'b self
+    ) -> Result<crate::layers::core::Bytes<'b>, crate::layers::serializers::SerializeError> {
+        Ok(self.0.to_ordered_bytes().into())
+    }
+
+    /// Deserializes a key from its order-preserving encoding.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`DeserializeError::Ordered`] if the bytes are truncated or malformed.
+    ///
+    /// [`DeserializeError::Ordered`]: crate::layers::serializers::DeserializeError::Ordered
+    #[inline]
+    fn deserialize(
+        serialized_bytes: crate::layers::core::Bytes<'b>
+    ) -> Result<crate::layers::core::Value<'b, Self>, crate::layers::serializers::DeserializeError> {
+        let key = T::from_ordered_bytes(&serialized_bytes.into_bytes())?;
+        Ok(Self(key).into())
+    }
+
+    /// Returns [`Method::Ordered`].
+    ///
+    /// [`Method::Ordered`]: crate::layers::serializers::Method::Ordered
+    #[inline]
+    fn method() -> &'static crate::layers::serializers::Method {
+        &crate::layers::serializers::Method::Ordered
+    }
+}
+
+/// Marker trait indicating that `Ordered` keys remain in their natural order when serialized.
+#[cfg(not(feature = "serialize-rkyv"))]
+impl<T: OrderedEncoding> crate::layers::serializers::OrderedWhenSerialized<'_> for Ordered<T> {}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, not(feature = "serialize-rkyv")))]
+mod tests {
+    use super::*;
+    use crate::layers::Serializer;
+
+    #[test]
+    fn serializes_in_order() {
+        let low = Ordered((String::from("Desert"), -3_i64)).serialize().unwrap();
+        let high = Ordered((String::from("Desert"), 2_i64)).serialize().unwrap();
+        assert!(low.as_slice() < high.as_slice());
+
+        let key = <Ordered<(String, i64)>>::deserialize(high).unwrap().into_owned().unwrap();
+        assert_eq!(key, Ordered((String::from("Desert"), 2)));
+    }
+}
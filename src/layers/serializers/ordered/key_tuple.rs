@@ -0,0 +1,192 @@
+//! The `KeyTuple` composite key, which concatenates the order-preserving encodings of its
+//! components and supports prefix scans.
+
+use crate::layers::serializers::DeserializeError;
+use crate::layers::serializers::ordered::OrderedEncoding;
+use std::ops::Bound;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A composite key made up of a tuple of components, for "partition key + sort key" style modeling.
+///
+/// The key is stored as the concatenation of its components' [`OrderedEncoding`]s, so keys sort by
+/// their first component, then their second, and so on. Every key that shares its leading
+/// components also shares a byte prefix, which lets a range scan visit one partition at a time.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// // Sightings are partitioned by habitat, and sorted by time within each habitat:
+/// type SightingKey = KeyTuple<(String, i64)>;
+///
+/// // Every desert sighting, in time order:
+/// let (start, end) = SightingKey::prefix_range(&("Desert".to_string(),));
+/// let bounds = (start.as_ref().map(Vec::as_slice), end.as_ref().map(Vec::as_slice));
+/// for entry in raw_sightings_table.range::<&[u8]>(bounds)? {
+///     let (key, sighting) = entry?;
+///     let (habitat, time) = SightingKey::deserialize(key.value().into())?.into_owned()?.into_inner();
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct KeyTuple<T>(pub T);
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<T: OrderedEncoding> KeyTuple<T> {
+    /// Returns the wrapped tuple.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+
+    /// Returns the byte prefix shared by every key that starts with the given leading components.
+    ///
+    /// The prefix is given as a tuple of the key's first components, in order. For example, the
+    /// prefix of a `KeyTuple<(String, i64)>` would be a `(String,)`.
+    #[must_use]
+    pub fn prefix<P: OrderedEncoding>(prefix: &P) -> Vec<u8> {
+        prefix.to_ordered_bytes()
+    }
+
+    /// Returns the byte range holding every key that starts with the given leading components.
+    ///
+    /// The range can be handed to a byte-wise range scan to visit one partition of the table.
+    #[must_use]
+    pub fn prefix_range<P: OrderedEncoding>(prefix: &P) -> (Bound<Vec<u8>>, Bound<Vec<u8>>) {
+        let start = Self::prefix(prefix);
+        let end = prefix_successor(&start).map_or(Bound::Unbounded, Bound::Excluded);
+        (Bound::Included(start), end)
+    }
+
+    /// Decodes only the leading components of a serialized key, ignoring the rest.
+    ///
+    /// For example, the partition key can be read from a `KeyTuple<(String, i64)>` by extracting a
+    /// `(String,)`, without decoding the sort key.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`DeserializeError::Ordered`] if the leading components are truncated or
+    ///   malformed.
+    pub fn extract_prefix<P: OrderedEncoding>(mut serialized_key: &[u8]) -> Result<P, DeserializeError> {
+        P::decode_ordered(&mut serialized_key)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl<T> From<T> for KeyTuple<T> {
+    /// Wraps a tuple as a composite key.
+    #[inline]
+    fn from(components: T) -> Self {
+        Self(components)
+    }
+}
+
+impl<T> std::ops::Deref for KeyTuple<T> {
+    type Target = T;
+
+    /// Dereferences a `KeyTuple` into its tuple of components.
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(not(feature = "serialize-rkyv"))]
+impl<'b, T: OrderedEncoding> crate::layers::Serializer<'b, Self> for KeyTuple<T> {
+    /// Serializes an owned key by concatenating its components' order-preserving encodings.
+    ///
+    /// # Errors
+    ///
+    /// * This encoding can't fail.
+    #[inline]
+    fn serialize(
+        self
+    ) -> Result<crate::layers::core::Bytes<'b>, crate::layers::serializers::SerializeError> {
+        Ok(self.0.to_ordered_bytes().into())
+    }
+
+    /// Serializes a borrowed key by concatenating its components' order-preserving encodings.
+    ///
+    /// # Errors
+    ///
+    /// * This encoding can't fail.
+    #[inline]
+    fn serialize_ref(
+        &'b self
+    ) -> Result<crate::layers::core::Bytes<'b>, crate::layers::serializers::SerializeError> {
+        Ok(self.0.to_ordered_bytes().into())
+    }
+
+    /// Deserializes a key from its components' order-preserving encodings.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`DeserializeError::Ordered`] if the bytes are truncated or malformed.
+    #[inline]
+    fn deserialize(
+        serialized_bytes: crate::layers::core::Bytes<'b>
+    ) -> Result<crate::layers::core::Value<'b, Self>, DeserializeError> {
+        let components = T::from_ordered_bytes(&serialized_bytes.into_bytes())?;
+        Ok(Self(components).into())
+    }
+
+    /// Returns [`Method::Ordered`].
+    ///
+    /// [`Method::Ordered`]: crate::layers::serializers::Method::Ordered
+    #[inline]
+    fn method() -> &'static crate::layers::serializers::Method {
+        &crate::layers::serializers::Method::Ordered
+    }
+}
+
+/// Marker trait indicating that `KeyTuple` keys remain in their natural order when serialized.
+#[cfg(not(feature = "serialize-rkyv"))]
+impl<T: OrderedEncoding> crate::layers::serializers::OrderedWhenSerialized<'_> for KeyTuple<T> {}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Returns the smallest byte string that sorts after every string starting with `prefix`, or
+/// `None` if there isn't one because the prefix is all `0xFF` bytes.
+fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let last = prefix.iter().rposition(|byte| *byte != 0xFF)?;
+    let mut successor = prefix[..=last].to_vec();
+    successor[last] += 1;
+    Some(successor)
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ops::RangeBounds;
+
+    type SightingKey = KeyTuple<(String, i64)>;
+
+    #[test]
+    fn prefix_range_covers_one_partition() {
+        let range = SightingKey::prefix_range(&("Desert".to_string(),));
+        let key = |habitat: &str, time: i64| (habitat.to_string(), time).to_ordered_bytes();
+
+        assert!(range.contains(&key("Desert", i64::MIN)));
+        assert!(range.contains(&key("Desert", i64::MAX)));
+        assert!(!range.contains(&key("Deser", 0)));
+        assert!(!range.contains(&key("Desert\0", 0)));
+        assert!(!range.contains(&key("Desertification", 0)));
+
+        let habitat: (String,) = SightingKey::extract_prefix(&key("Desert", -7)).unwrap();
+        assert_eq!(habitat.0, "Desert");
+
+        assert_eq!(prefix_successor(&[1, 0xFF]), Some(vec![2]));
+        assert_eq!(prefix_successor(&[0xFF]), None);
+    }
+}
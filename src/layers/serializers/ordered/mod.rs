@@ -0,0 +1,23 @@
+//! Order-preserving key encodings, so that keys sort correctly in range queries and ordered
+//! indexes.
+//!
+//! `redb` compares keys as raw bytes. Most serializers don't preserve a type's natural order in
+//! their output: a little-endian or two's complement integer, an IEEE 754 float, or a tuple of
+//! strings will all sort incorrectly. [`OrderedEncoding`] provides a canonical encoding for these
+//! types whose byte order matches their natural order, and [`Ordered`] wraps a key so that it's
+//! stored using it.
+//!
+//! ```rust,ignore
+//! // Temperatures sort correctly, including below zero:
+//! let readings = txn.open_table::<Ordered<f64>, Reading>("readings")?;
+//! let freezing = readings.range(Ordered(-40.0)..Ordered(0.0))?;
+//! ```
+
+mod encoding;
+pub use crate::layers::serializers::ordered::encoding::OrderedEncoding;
+
+mod key;
+pub use crate::layers::serializers::ordered::key::Ordered;
+
+mod key_tuple;
+pub use crate::layers::serializers::ordered::key_tuple::KeyTuple;
@@ -0,0 +1,10 @@
+//! Error types used across the various signing implementations.
+
+mod sign;
+pub use crate::layers::signers::core::errors::sign::Error as SignError;
+
+mod signing;
+pub use crate::layers::signers::core::errors::signing::Error;
+
+mod verify;
+pub use crate::layers::signers::core::errors::verify::Error as VerifyError;
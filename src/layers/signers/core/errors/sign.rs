@@ -0,0 +1,16 @@
+//! Contains the error type returned from the signing implementation while signing data.
+
+// -------------------------------------------------------------------------------------------------
+//
+/// An error returned from the signing implementation while signing data.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Error returned from the [ed25519-dalek](https://crates.io/crates/ed25519-dalek) crate.
+    ///
+    /// To understand the possible errors this signer may produce, please refer to the official
+    /// documentation: <https://docs.rs/ed25519-dalek>
+    #[cfg(feature = "sign-ed25519")]
+    #[error("ed25519 signing failed")]
+    Ed25519 { #[from] #[source] source: ed25519_dalek::SignatureError },
+}
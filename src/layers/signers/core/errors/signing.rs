@@ -0,0 +1,18 @@
+//! Contains the error type returned from the signing layer.
+
+// -------------------------------------------------------------------------------------------------
+//
+/// An error returned from the signing layer.
+///
+/// This includes errors for unsigned, forged, or corrupted data, etc.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An error was encountered while signing data.
+    #[error("signing of data failed")]
+    Sign { #[from] #[source] source: crate::layers::signers::SignError },
+
+    /// An error was encountered while verifying data.
+    #[error("verification of data failed")]
+    Verify { #[from] #[source] source: crate::layers::signers::VerifyError },
+}
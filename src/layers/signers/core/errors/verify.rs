@@ -0,0 +1,42 @@
+//! Contains the error type returned from the signing implementation while verifying data.
+
+// -------------------------------------------------------------------------------------------------
+//
+/// An error returned from the signing implementation while verifying data.
+///
+/// This includes errors for unsigned, forged, or corrupted data, etc.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The data is too short to hold a signature.
+    ///
+    /// This typically means that the value was written before signing was enabled for its type, or
+    /// that it was truncated.
+    #[error("data is not signed")]
+    Unsigned { #[source] source: crate::layers::core::tail_readers::Error },
+
+    /// The verifying key isn't a valid key for this signature algorithm.
+    #[cfg(feature = "sign-ed25519")]
+    #[error("invalid ed25519 verifying key")]
+    InvalidKey { #[source] source: ed25519_dalek::SignatureError },
+
+    /// The signature doesn't match the data and the verifying key.
+    ///
+    /// Atlatl does not attempt to distinguish between the possible causes, and intentionally
+    /// surfaces this generic `Forged` error to avoid leaking information that could aid an
+    /// attacker.
+    ///
+    /// # Common Causes
+    ///
+    /// * The value was modified after it was signed, whether by tampering or by data corruption.
+    /// * The value was signed with a signing key that doesn't belong to the verifying key.
+    ///
+    /// # Suggestions
+    ///
+    /// * Ensure that the verifying key belongs to the signing key used by the writer.
+    /// * Enable an error correction layer if the storage medium is unreliable, so that corruption
+    ///   is repaired before signatures are verified.
+    #[cfg(feature = "sign-ed25519")]
+    #[error("signature verification failed")]
+    Forged { #[source] source: ed25519_dalek::SignatureError },
+}
@@ -0,0 +1,84 @@
+//! Signing and verifying keys. A signing key is kept private by the writer and is used to sign
+//! data. Its verifying key is public and is used by readers to check the signatures.
+
+use crate::layers::signers::impls::{SIGNING_KEY_SIZE, VERIFYING_KEY_SIZE};
+use std::borrow::Cow;
+use zeroize::Zeroize;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A private signing key, used to sign data as it's written.
+///
+/// The key is sent directly to the signing backend with no additional processing. Ensure it was
+/// generated with a cryptographically secure random number generator.
+pub struct SigningKeyBytes<'k>(Cow<'k, [u8; SIGNING_KEY_SIZE]>);
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A public verifying key, used to check the signatures of data as it's read.
+pub struct VerifyingKeyBytes<'k>(Cow<'k, [u8; VERIFYING_KEY_SIZE]>);
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl AsRef<[u8; SIGNING_KEY_SIZE]> for SigningKeyBytes<'_> {
+    /// Returns a reference to the bytes of the key. Does not allocate.
+    #[inline]
+    fn as_ref(&self) -> &[u8; SIGNING_KEY_SIZE] {
+        self.0.as_ref()
+    }
+}
+
+impl Drop for SigningKeyBytes<'_> {
+    /// Wipes an owned signing key from memory. Borrowed keys are left to their owner.
+    fn drop(&mut self) {
+        if let Cow::Owned(key) = &mut self.0 {
+            key.zeroize();
+        }
+    }
+}
+
+impl<'k> From<&'k [u8; SIGNING_KEY_SIZE]> for SigningKeyBytes<'k> {
+    /// Converts a borrowed `&[u8; SIGNING_KEY_SIZE]` fixed array of bytes into a `SigningKeyBytes`
+    /// type.
+    #[inline]
+    fn from(borrowed_fixed_array: &'k [u8; SIGNING_KEY_SIZE]) -> Self {
+        SigningKeyBytes(Cow::Borrowed(borrowed_fixed_array))
+    }
+}
+
+impl From<[u8; SIGNING_KEY_SIZE]> for SigningKeyBytes<'_> {
+    /// Converts an owned `[u8; SIGNING_KEY_SIZE]` fixed array of bytes into a `SigningKeyBytes`
+    /// type.
+    #[inline]
+    fn from(owned_fixed_array: [u8; SIGNING_KEY_SIZE]) -> Self {
+        SigningKeyBytes(Cow::Owned(owned_fixed_array))
+    }
+}
+
+impl AsRef<[u8; VERIFYING_KEY_SIZE]> for VerifyingKeyBytes<'_> {
+    /// Returns a reference to the bytes of the key. Does not allocate.
+    #[inline]
+    fn as_ref(&self) -> &[u8; VERIFYING_KEY_SIZE] {
+        self.0.as_ref()
+    }
+}
+
+impl<'k> From<&'k [u8; VERIFYING_KEY_SIZE]> for VerifyingKeyBytes<'k> {
+    /// Converts a borrowed `&[u8; VERIFYING_KEY_SIZE]` fixed array of bytes into a
+    /// `VerifyingKeyBytes` type.
+    #[inline]
+    fn from(borrowed_fixed_array: &'k [u8; VERIFYING_KEY_SIZE]) -> Self {
+        VerifyingKeyBytes(Cow::Borrowed(borrowed_fixed_array))
+    }
+}
+
+impl From<[u8; VERIFYING_KEY_SIZE]> for VerifyingKeyBytes<'_> {
+    /// Converts an owned `[u8; VERIFYING_KEY_SIZE]` fixed array of bytes into a
+    /// `VerifyingKeyBytes` type.
+    #[inline]
+    fn from(owned_fixed_array: [u8; VERIFYING_KEY_SIZE]) -> Self {
+        VerifyingKeyBytes(Cow::Owned(owned_fixed_array))
+    }
+}
@@ -0,0 +1,83 @@
+//! An enumeration that lists all available signing methods.
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Helps provide runtime identification of the signature algorithm in use, allowing applications
+/// to log signing details, or store metadata about how data was processed in the data pipeline.
+///
+/// This type is returned by the `Signer` trait.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+#[repr(u8)]
+#[non_exhaustive]
+pub enum Method {
+    /// `Ed25519` Edwards-curve signatures over Curve25519. Use when you need small signatures and
+    /// fast verification, and deterministic signing that doesn't depend on a random number
+    /// generator.
+    Ed25519 = 0,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl TryFrom<&u8> for &'static Method {
+    type Error = crate::layers::core::descriptors::Error;
+
+    /// Converts a `&u8` word into a signing `&Method` enum.
+    ///
+    /// # Errors
+    /// Returns an error for unrecognized values.
+    #[inline]
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(&Method::Ed25519),
+            _ => Err(Self::Error::UnrecognizedSigner(*value)),
+        }
+    }
+}
+
+impl std::fmt::Display for Method {
+    /// Formats the signing `Method` as a human-readable string.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ed25519 => write!(f, "ed25519"),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_method_roundtrip() {
+        let as_u8: u8 = Method::Ed25519 as u8;
+        let recovered = <&Method>::try_from(&as_u8)
+            .unwrap_or_else(|_| panic!("failed to convert back from u8: {as_u8}"));
+        assert_eq!(&Method::Ed25519, recovered);
+    }
+
+    /// Test that invalid values return errors
+    #[test]
+    fn test_invalid_method() {
+        for invalid in [1, 2, 7, 31, 255] {
+            assert!(
+                <&Method>::try_from(&invalid).is_err(),
+                "expected error for invalid value: {invalid}"
+            );
+        }
+    }
+
+    /// Test that error messages are helpful
+    #[test]
+    fn test_method_error_message() {
+        let error_msg = <&Method>::try_from(&99).unwrap_err().to_string();
+        assert!(error_msg.contains("unrecognized signing method"));
+        assert!(error_msg.contains("99"));
+    }
+}
@@ -0,0 +1,18 @@
+//! Common types and traits that are used across the various signing implementations.
+
+mod errors;
+pub use crate::layers::signers::core::errors::Error;
+pub use crate::layers::signers::core::errors::SignError;
+pub use crate::layers::signers::core::errors::VerifyError;
+
+mod key_bytes;
+pub use crate::layers::signers::core::key_bytes::{SigningKeyBytes, VerifyingKeyBytes};
+
+mod method;
+pub use crate::layers::signers::core::method::Method;
+
+mod signable;
+pub use crate::layers::signers::core::signable::Signable;
+
+mod signer;
+pub use crate::layers::signers::core::signer::Signer;
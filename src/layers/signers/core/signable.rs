@@ -0,0 +1,35 @@
+//! Signing configuration trait for database storage.
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Configures signing for a specific type.
+///
+/// This trait determines if values of this type should be signed when they're stored, and if their
+/// signatures should be verified when they're retrieved.
+///
+/// # Implementation
+///
+/// This trait is typically implemented automatically via derive macros, but can also be implemented
+/// manually for custom signing strategies.
+pub trait Signable {
+    /// Returns the signing conditions for this type.
+    ///
+    /// # Example Strategies
+    ///
+    /// This method determines when signing or verification should be applied to a type. For
+    /// example:
+    /// * `None` · Never sign or verify this type.
+    /// * `OnRead` · Use this when the data being written was already signed elsewhere, for
+    ///   example by the device that produced it. Signatures will be verified on read.
+    /// * `OnWrite` · Sign on write, and return signed data for furtherance, for example, to another
+    ///   node that verifies it.
+    /// * `Both` · Sign on write and verify on read.
+    ///
+    /// # Returns
+    ///
+    /// The [`Direction`] configuration for this type. The same directional setting is used for all
+    /// values of this type.
+    ///
+    /// [`Direction`]: crate::layers::core::Direction
+    const DIRECTION: crate::layers::core::descriptors::Direction;
+}
@@ -0,0 +1,81 @@
+use crate::layers::core::Bytes;
+use crate::layers::signers::{SigningKeyBytes, VerifyingKeyBytes};
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The `Signer` trait provides digital signatures as part of a data processing pipeline.
+///
+/// On write, the value's bytes are signed with a private signing key, and the signature is appended
+/// to the value's tail, the same way the encryption layer appends its nonce. On read, the signature
+/// is removed from the tail and checked against the public verifying key.
+///
+/// Unlike encryption, which proves that a value was written by _someone_ holding the shared secret
+/// key, a signature proves that it was written by the holder of a specific signing key. Readers
+/// only need the verifying key, which can't be used to forge signatures, so it can be handed out to
+/// replicas, auditors, or clients freely.
+///
+/// Signing doesn't hide the value. Enable an encryptor as well when the value must stay private.
+///
+/// # Generics & Lifetimes
+///
+/// * `V` generic represents the user's value type, for example: `Creature`, `User`, `String`, etc.
+/// * `b` lifetime represents bytes potentially being borrowed from the host application or the
+///   `redb` database.
+/// * 'k' lifetime represents a key potentially being borrowed from the host application.
+///
+/// # Considerations
+///
+/// ## Key Management
+///
+/// * Generate signing keys securely: Use cryptographically secure random number generators.
+/// * Keep signing keys on the writers: Only processes that write values need the signing key.
+/// * Distribute verifying keys out-of-band: A verifying key read from the same database that it
+///   protects proves nothing.
+pub trait Signer<'b, 'k, V> {
+    /// Returns the signing method that the current `Signer` trait implements.
+    ///
+    /// This enables runtime identification of the signature algorithm in use, allowing
+    /// applications to log signing details, or store metadata about how data was processed in the
+    /// data pipeline.
+    const METHOD: crate::layers::signers::Method;
+
+    /// Signs data with a private signing key, and appends the signature to the data's tail.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` · The data to be signed, wrapped in a `Bytes` that may reference borrowed
+    ///   application bytes.
+    ///
+    /// * `key` · The private signing key.
+    ///
+    /// # Errors
+    ///
+    /// Consult the documentation of the signer backend you are using for more detail on signing
+    /// and potential limitations.
+    fn sign(
+        data: Bytes<'b>,
+        key: SigningKeyBytes<'k>,
+    ) -> Result<Bytes<'b>, crate::layers::signers::SignError>;
+
+    /// Removes the signature from the data's tail and checks it against a public verifying key,
+    /// returning the data as it was before it was signed.
+    ///
+    /// # Arguments
+    ///
+    /// * `signed_data` · The signed data, wrapped in a `Bytes`.
+    ///
+    /// * `key` · The public verifying key that belongs to the signing key used during signing.
+    ///
+    /// # Errors
+    ///
+    /// This method may fail for several reasons, including:
+    ///
+    /// * The data wasn't signed, or is too short to hold a signature,
+    /// * The verifying key is invalid, or
+    /// * The signature doesn't match the data and the verifying key, because the data was modified
+    ///   or was signed by someone else.
+    fn verify(
+        signed_data: Bytes<'b>,
+        key: VerifyingKeyBytes<'k>,
+    ) -> Result<Bytes<'b>, crate::layers::signers::VerifyError>;
+}
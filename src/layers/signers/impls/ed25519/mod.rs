@@ -0,0 +1,43 @@
+//! Ed25519 signatures using the [dalek-cryptography](https://github.com/dalek-cryptography)
+//! project's [ed25519-dalek](https://crates.io/crates/ed25519-dalek) crate.
+
+mod signer;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// `Ed25519`'s signing key size is `32`-bytes or `256`-bits.
+pub const SIGNING_KEY_SIZE: usize = ed25519_dalek::SECRET_KEY_LENGTH;
+
+/// `Ed25519`'s verifying key size is `32`-bytes or `256`-bits.
+pub const VERIFYING_KEY_SIZE: usize = ed25519_dalek::PUBLIC_KEY_LENGTH;
+
+/// `Ed25519`'s signature size is `64`-bytes or `512`-bits.
+pub const SIGNATURE_SIZE: usize = ed25519_dalek::SIGNATURE_LENGTH;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Ed25519 is a public-key signature scheme using the Edwards form of Curve25519.
+///
+/// It proves that data was produced by the holder of a private signing key, and that it hasn't
+/// been modified since. The benefits and features of Ed25519 include:
+///
+/// * Small keys and signatures: Keys are 32 bytes, and signatures are 64 bytes, which keeps the
+///   per-value overhead low.
+///
+/// * Performance: Signing and verification are fast, and don't require hardware acceleration.
+///
+/// * Deterministic signing: Signatures don't depend on a random number generator, so a weak or
+///   misconfigured random number generator can't leak the signing key.
+///
+/// Verification uses the strict rules of RFC 8032, which reject signatures that other
+/// implementations may accept, so a value can't be given several valid signatures.
+///
+/// Ed25519 was designed by Daniel J. Bernstein, Niels Duif, Tanja Lange, Peter Schwabe, and Bo-Yin
+/// Yang, and was standardized in RFC 8032 in January 2017.
+pub struct Ed25519<V> {
+    /// A marker to tie this `Ed25519` structure to a specific type `V` without storing any actual
+    /// data.
+    phantom_data: std::marker::PhantomData<V>,
+}
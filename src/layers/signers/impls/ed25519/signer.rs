@@ -0,0 +1,190 @@
+//! Support for the [dalek-cryptography](https://github.com/dalek-cryptography) project's
+//! [ed25519-dalek](https://crates.io/crates/ed25519-dalek) crate.
+
+use crate::layers::core::{Bytes, tail_readers::{TailReader, TailReaderMut}};
+use crate::layers::signers::core::{
+    Method,
+    Signable,
+    SignError,
+    Signer,
+    SigningKeyBytes,
+    VerifyError,
+    VerifyingKeyBytes,
+};
+use crate::layers::signers::impls::ed25519::{Ed25519, SIGNATURE_SIZE};
+use ed25519_dalek::{Signature, Signer as _, SigningKey, VerifyingKey};
+use std::borrow::Cow;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl<'b, 'k, V: Signable> Signer<'b, 'k, V> for Ed25519<V> {
+    /// Returns the signing method that the current `Signer` trait implements.
+    ///
+    /// This enables runtime identification of the signature algorithm in use, allowing
+    /// applications to log signing details, or store metadata about how data was processed in the
+    /// data pipeline.
+    const METHOD: Method = Method::Ed25519;
+
+    /// Signs data with a private signing key, and appends the `64`-byte signature to the data's
+    /// tail.
+    ///
+    /// # Errors
+    ///
+    /// Consult the documentation of the signer backend you are using for more detail on signing
+    /// and potential limitations: <https://docs.rs/ed25519-dalek>
+    ///
+    /// # Generics & Lifetimes
+    ///
+    /// * `V` generic represents the user's value type, for example: `User`, `String`, etc.
+    /// * `b` lifetime represents bytes potentially being borrowed from the host application.
+    /// * 'k' lifetime represents a key potentially being borrowed from the host application.
+    #[inline]
+    fn sign(
+        data: Bytes<'b>,
+        key: SigningKeyBytes<'k>,
+    ) -> Result<Bytes<'b>, SignError> {
+        let signature = SigningKey::from_bytes(key.as_ref()).try_sign(data.as_ref())?;
+        let (metadata, data) = data.into_parts();
+        let mut signed_data = data.into_owned();
+        signed_data.extend_from_slice(&signature.to_bytes());
+        Ok(Bytes::from_parts(metadata, signed_data.into()))
+    }
+
+    /// Removes the signature from the data's tail and checks it against a public verifying key,
+    /// returning the data as it was before it was signed.
+    ///
+    /// # Errors
+    ///
+    /// This method may fail for several reasons, including:
+    ///
+    /// * The data wasn't signed, or is too short to hold a signature,
+    /// * The verifying key is invalid, or
+    /// * The signature doesn't match the data and the verifying key.
+    ///
+    /// # Generics & Lifetimes
+    ///
+    /// * `V` generic represents the user's value type, for example: `User`, `String`, etc.
+    /// * `b` lifetime represents bytes potentially being borrowed from the `redb` database.
+    /// * 'k' lifetime represents a key potentially being borrowed from the host application.
+    #[inline]
+    fn verify(
+        signed_data: Bytes<'b>,
+        key: VerifyingKeyBytes<'k>,
+    ) -> Result<Bytes<'b>, VerifyError> {
+        let verifying_key = VerifyingKey::from_bytes(key.as_ref())
+            .map_err(|source| VerifyError::InvalidKey { source })?;
+        let (metadata, signed_data) = signed_data.into_parts();
+
+        match signed_data {
+            Cow::Borrowed(slice) => {
+                // Borrowed: the data is a sub-slice, no allocation
+                let mut tail_reader = TailReader::from_slice(slice);
+                let signature = tail_reader.read_array::<SIGNATURE_SIZE>()
+                    .map_err(|source| VerifyError::Unsigned { source })?;
+                let data = tail_reader.close();
+                verifying_key
+                    .verify_strict(data, &Signature::from_bytes(signature))
+                    .map_err(|source| VerifyError::Forged { source })?;
+                Ok(Bytes::from_parts(metadata, data.into()))
+            }
+            Cow::Owned(vec) => {
+                // Owned: the signature is truncated off, no allocation
+                let mut tail_reader_mut = TailReaderMut::from_vec(vec);
+                let signature = Signature::from_bytes(
+                    tail_reader_mut.read_array::<SIGNATURE_SIZE>()
+                        .map_err(|source| VerifyError::Unsigned { source })?
+                );
+                let data = tail_reader_mut.close();
+                verifying_key
+                    .verify_strict(&data, &signature)
+                    .map_err(|source| VerifyError::Forged { source })?;
+                Ok(Bytes::from_parts(metadata, data.into()))
+            }
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::core::Direction;
+
+    struct AlwaysSign;
+    impl Signable for AlwaysSign {
+        const DIRECTION: Direction = Direction::Both;
+    }
+
+    const SIGNING_KEY: &[u8; 32] = b"an example very very secret key.";
+
+    fn verifying_key() -> [u8; 32] {
+        SigningKey::from_bytes(SIGNING_KEY).verifying_key().to_bytes()
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let original_data = b"Hello, World! This is a test message.";
+        let bytes = Bytes::from(original_data.as_slice());
+        let signed = Ed25519::<AlwaysSign>::sign(bytes, SIGNING_KEY.into())
+            .expect("Signing should succeed");
+        assert_eq!(signed.len(), original_data.len() + SIGNATURE_SIZE);
+
+        // Owned and borrowed signed data verify alike:
+        let borrowed = Bytes::from_slice(signed.as_ref());
+        let verified = Ed25519::<AlwaysSign>::verify(borrowed, verifying_key().into())
+            .expect("Verification should succeed");
+        assert_eq!(verified.as_ref(), original_data);
+
+        let verified = Ed25519::<AlwaysSign>::verify(signed, verifying_key().into())
+            .expect("Verification should succeed");
+        assert_eq!(verified.as_ref(), original_data);
+    }
+
+    #[test]
+    fn test_signatures_are_deterministic() {
+        let bytes = Bytes::from(b"kelp".as_slice());
+        let signed1 = Ed25519::<AlwaysSign>::sign(bytes.clone(), SIGNING_KEY.into()).unwrap();
+        let signed2 = Ed25519::<AlwaysSign>::sign(bytes, SIGNING_KEY.into()).unwrap();
+        assert_eq!(signed1, signed2);
+    }
+
+    #[test]
+    fn test_modified_data_fails() {
+        let mut signed = Ed25519::<AlwaysSign>::sign(b"kelp".as_slice().into(), SIGNING_KEY.into())
+            .expect("Signing should succeed")
+            .to_vec();
+        signed[0] ^= 1;
+
+        let result = Ed25519::<AlwaysSign>::verify(signed.into(), verifying_key().into());
+        assert!(matches!(result, Err(VerifyError::Forged { .. })));
+    }
+
+    #[test]
+    fn test_wrong_key_fails() {
+        let signed = Ed25519::<AlwaysSign>::sign(b"kelp".as_slice().into(), SIGNING_KEY.into())
+            .expect("Signing should succeed");
+        let other_key = SigningKey::from_bytes(b"another example very secret key.")
+            .verifying_key()
+            .to_bytes();
+
+        let result = Ed25519::<AlwaysSign>::verify(signed, other_key.into());
+        assert!(matches!(result, Err(VerifyError::Forged { .. })));
+    }
+
+    #[test]
+    fn test_unsigned_data_fails() {
+        let bytes = Bytes::from(b"kelp".as_slice());
+        let result = Ed25519::<AlwaysSign>::verify(bytes, verifying_key().into());
+        assert!(matches!(result, Err(VerifyError::Unsigned { .. })));
+    }
+
+    #[test]
+    fn test_method_returns_correct_value() {
+        assert_eq!(Ed25519::<AlwaysSign>::METHOD, Method::Ed25519);
+    }
+}
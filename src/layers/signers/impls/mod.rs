@@ -0,0 +1,25 @@
+//! `Signer` signing implementations. A single implementation is selected at compile-time in the
+//! host application's `Cargo.toml` file.
+
+// -------------------------------------------------------------------------------------------------
+//
+// Signing Implementations
+
+#[cfg(feature = "sign-ed25519")]
+mod ed25519;
+
+#[cfg(feature = "sign-ed25519")]
+/// `Ed25519` has been selected as the `ActiveSigner` using `Cargo.toml` feature.
+pub use crate::layers::signers::impls::ed25519::Ed25519 as ActiveSigner;
+
+#[cfg(feature = "sign-ed25519")]
+/// Signing key size for the active signer. `Ed25519`'s signing key size is `32`-bytes.
+pub use crate::layers::signers::impls::ed25519::SIGNING_KEY_SIZE;
+
+#[cfg(feature = "sign-ed25519")]
+/// Verifying key size for the active signer. `Ed25519`'s verifying key size is `32`-bytes.
+pub use crate::layers::signers::impls::ed25519::VERIFYING_KEY_SIZE;
+
+#[cfg(feature = "sign-ed25519")]
+/// Signature size for the active signer. `Ed25519`'s signature size is `64`-bytes.
+pub use crate::layers::signers::impls::ed25519::SIGNATURE_SIZE;
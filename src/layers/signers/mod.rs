@@ -0,0 +1,15 @@
+//! Digital signature algorithms for proving who wrote stored data.
+
+mod core;
+pub use crate::layers::signers::core::Error;
+pub use crate::layers::signers::core::Method;
+pub use crate::layers::signers::core::Signable;
+pub use crate::layers::signers::core::SignError;
+pub use crate::layers::signers::core::Signer;
+pub use crate::layers::signers::core::SigningKeyBytes;
+pub use crate::layers::signers::core::VerifyError;
+pub use crate::layers::signers::core::VerifyingKeyBytes;
+
+mod impls;
+pub use crate::layers::signers::impls::ActiveSigner;
+pub use crate::layers::signers::impls::SIGNATURE_SIZE;
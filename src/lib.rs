@@ -39,6 +39,13 @@
     clippy::multiple_crate_versions, // Due to upstream crates, can't do much about this
 )]
 
+// `wasm32-unknown-unknown` has no threads, sockets, or C toolchain for `ring`:
+#[cfg(all(target_arch = "wasm32", target_os = "unknown", feature = "kdf-sha256"))]
+compile_error!("`kdf-sha256` isn't supported on `wasm32-unknown-unknown`. Use `kdf-blake3`.");
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown", feature = "server"))]
+compile_error!("the embedded `server` isn't supported on `wasm32-unknown-unknown`.");
+
 // #[cfg(debug_assertions)]
 // debug_assert_eq!(cfg!(target_endian = "little"), true, "Atlatl only supports little-endian targets.");
 
@@ -53,12 +60,24 @@ pub mod layers;
 // #[cfg(feature = "redb-pass-through")]
 // pub mod redb;
 
+pub mod backends;
+
+#[cfg(feature = "serializers")]
+mod codec;
+#[cfg(feature = "serializers")]
+pub use crate::codec::Codec;
+
 // pub mod db;
 
 mod error;
-pub use crate::error::Error;
+pub use crate::error::{DisplayKey, Error, ErrorContext};
+
+pub mod clock;
+pub mod defaults;
+pub mod redaction;
+pub mod validation;
 
-// pub mod indexing;
-// pub mod querying;
+pub mod indexing;
+pub mod querying;
 
-// pub mod typed;
\ No newline at end of file
+pub mod typed;
\ No newline at end of file
@@ -0,0 +1,255 @@
+//! Aggregations over query results: group-by, count, sum, min, max, and average.
+//!
+//! Aggregates are folded one record at a time, so the matching records are never all held in
+//! memory. Counts that are grouped by an index and don't need any record fields are computed from
+//! the index's key sets alone, without reading the primary table.
+
+use crate::indexing::{HasTable, Index};
+use crate::querying::Query;
+use crate::{Codec, Error};
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A numeric field extracted from a record, for example `|creature| creature.weight`.
+pub type DynMeasure<V> = dyn Fn(&V) -> f64 + Send + Sync;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A single aggregation step.
+pub enum Measure<V> {
+    Count,
+    Sum(Box<DynMeasure<V>>),
+    Min(Box<DynMeasure<V>>),
+    Max(Box<DynMeasure<V>>),
+    Average(Box<DynMeasure<V>>),
+}
+
+impl<V> Measure<V> {
+    /// Returns the numeric field this measure reads from each record, if any.
+    pub(crate) fn field(&self) -> Option<&DynMeasure<V>> {
+        match self {
+            Self::Count => None,
+            Self::Sum(field) | Self::Min(field) | Self::Max(field) | Self::Average(field) =>
+                Some(field.as_ref()),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// An aggregation over the records of a table, optionally filtered by a query and grouped by an
+/// index.
+///
+/// Built using [`Query::aggregate`] and executed with the read transaction's `aggregate` method.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let aggregate = Query::<Creature>::aggregate()
+///     .filter(Habitat("Savannah".into()))
+///     .group_by(DietIndex)
+///     .count()
+///     .sum(|creature| creature.weight);
+///
+/// for group in txn.aggregate::<u64, Creature>(aggregate)? {
+///     let diet: String = group.key()?.unwrap_or_default();
+///     println!("{diet}: {:?}", group.values());
+/// }
+/// ```
+pub struct Aggregate<V: HasTable> {
+    pub(crate) filter: Option<Query<V>>,
+    pub(crate) group_by: Option<&'static str>,
+    pub(crate) measures: Vec<Measure<V>>,
+}
+
+impl<V: HasTable> Aggregate<V> {
+    /// Aggregates only the records matching a query. By default, every record in the table is
+    /// aggregated.
+    #[must_use]
+    pub fn filter(mut self, query: impl Into<Query<V>>) -> Self {
+        self.filter = Some(query.into());
+        self
+    }
+
+    /// Groups the records by a non-unique secondary index, producing one [`AggregateGroup`] per
+    /// index entry, keyed by the entry's secondary key. By default, all records form a single
+    /// group.
+    #[must_use]
+    pub fn group_by<I: Index<Record = V>>(mut self, _index: I) -> Self {
+        self.group_by = Some(I::index_name());
+        self
+    }
+
+    /// Counts the records in each group.
+    #[must_use]
+    pub fn count(mut self) -> Self {
+        self.measures.push(Measure::Count);
+        self
+    }
+
+    /// Sums a numeric field over the records in each group.
+    #[must_use]
+    pub fn sum(mut self, field: impl Fn(&V) -> f64 + Send + Sync + 'static) -> Self {
+        self.measures.push(Measure::Sum(Box::new(field)));
+        self
+    }
+
+    /// Finds the smallest value of a numeric field in each group.
+    #[must_use]
+    pub fn min(mut self, field: impl Fn(&V) -> f64 + Send + Sync + 'static) -> Self {
+        self.measures.push(Measure::Min(Box::new(field)));
+        self
+    }
+
+    /// Finds the largest value of a numeric field in each group.
+    #[must_use]
+    pub fn max(mut self, field: impl Fn(&V) -> f64 + Send + Sync + 'static) -> Self {
+        self.measures.push(Measure::Max(Box::new(field)));
+        self
+    }
+
+    /// Averages a numeric field over the records in each group.
+    #[must_use]
+    pub fn average(mut self, field: impl Fn(&V) -> f64 + Send + Sync + 'static) -> Self {
+        self.measures.push(Measure::Average(Box::new(field)));
+        self
+    }
+}
+
+impl<V: HasTable> Query<V> {
+    /// Starts building an [`Aggregate`] over the table's records.
+    #[must_use]
+    pub const fn aggregate() -> Aggregate<V> {
+        Aggregate { filter: None, group_by: None, measures: Vec::new() }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The result of a single aggregation step, in the order the steps were added.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AggregateValue {
+    /// The number of records in the group.
+    Count(u64),
+
+    /// The sum of the field. `0.0` for an empty group.
+    Sum(f64),
+
+    /// The smallest value of the field. `None` for an empty group.
+    Min(Option<f64>),
+
+    /// The largest value of the field. `None` for an empty group.
+    Max(Option<f64>),
+
+    /// The average value of the field. `None` for an empty group.
+    Average(Option<f64>),
+}
+
+impl AggregateValue {
+    /// Returns the value as a number, or `None` if the group was empty.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss, reason = "counts above 2^53 are approximated")]
+    pub const fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Self::Count(count) => Some(count as f64),
+            Self::Sum(sum) => Some(sum),
+            Self::Min(value) | Self::Max(value) | Self::Average(value) => value,
+        }
+    }
+}
+
+/// The aggregated values of a single group.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AggregateGroup {
+    /// The group's serialized secondary key. `None` if the aggregate wasn't grouped.
+    pub(crate) key_bytes: Option<Vec<u8>>,
+
+    /// One value per aggregation step.
+    pub(crate) values: Vec<AggregateValue>,
+}
+
+impl AggregateGroup {
+    /// Decodes the group's secondary key. Returns `None` if the aggregate wasn't grouped.
+    ///
+    /// # Errors
+    ///
+    /// * Returns an error if the key could not be deserialized into `G`.
+    pub fn key<G: Codec<G>>(&self) -> Result<Option<G>, Error> {
+        self.key_bytes.as_deref().map(G::deserialize).transpose()
+    }
+
+    /// Returns the group's serialized secondary key. `None` if the aggregate wasn't grouped.
+    #[must_use]
+    pub fn key_bytes(&self) -> Option<&[u8]> {
+        self.key_bytes.as_deref()
+    }
+
+    /// Returns one value per aggregation step, in the order the steps were added.
+    #[must_use]
+    pub fn values(&self) -> &[AggregateValue] {
+        &self.values
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Folds records into the values of a single group.
+pub struct Accumulator {
+    count: u64,
+    sums: Vec<f64>,
+    mins: Vec<Option<f64>>,
+    maxes: Vec<Option<f64>>,
+}
+
+impl Accumulator {
+    /// Instantiates an empty accumulator for the given number of aggregation steps.
+    pub(crate) fn new(measures: usize) -> Self {
+        Self {
+            count: 0,
+            sums: vec![0.0; measures],
+            mins: vec![None; measures],
+            maxes: vec![None; measures],
+        }
+    }
+
+    /// Instantiates an accumulator for a group whose records were counted but not read.
+    pub(crate) fn counted(measures: usize, count: u64) -> Self {
+        Self { count, ..Self::new(measures) }
+    }
+
+    /// Folds a single record into the group.
+    pub(crate) fn push<V>(&mut self, measures: &[Measure<V>], record: &V) {
+        self.count += 1;
+        for (index, measure) in measures.iter().enumerate() {
+            let Some(field) = measure.field() else { continue };
+            let value = field(record);
+            self.sums[index] += value;
+            self.mins[index] = Some(self.mins[index].map_or(value, |min| min.min(value)));
+            self.maxes[index] = Some(self.maxes[index].map_or(value, |max| max.max(value)));
+        }
+    }
+
+    /// Completes the group.
+    #[allow(clippy::cast_precision_loss, reason = "counts above 2^53 are approximated")]
+    pub(crate) fn finish<V>(
+        self,
+        measures: &[Measure<V>],
+        key_bytes: Option<Vec<u8>>
+    ) -> AggregateGroup {
+        let values = measures
+            .iter()
+            .enumerate()
+            .map(|(index, measure)| match measure {
+                Measure::Count => AggregateValue::Count(self.count),
+                Measure::Sum(_) => AggregateValue::Sum(self.sums[index]),
+                Measure::Min(_) => AggregateValue::Min(self.mins[index]),
+                Measure::Max(_) => AggregateValue::Max(self.maxes[index]),
+                Measure::Average(_) => AggregateValue::Average(
+                    (self.count > 0).then(|| self.sums[index] / self.count as f64)
+                ),
+            })
+            .collect();
+
+        AggregateGroup { key_bytes, values }
+    }
+}
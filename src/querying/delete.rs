@@ -0,0 +1,64 @@
+//! Index-aware deletion of the records matching a query.
+
+use crate::indexing::{HasTable, Indexable};
+use crate::querying::Query;
+use crate::typed::transaction::WriteTransaction;
+use crate::{Codec, Error};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<V: HasTable> Query<V> {
+    /// Deletes every record matching the query, along with their secondary index entries, inside
+    /// the given write transaction. Returns the number of records deleted.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let mut txn = db.write()?;
+    /// let deleted = Query::lookup(Habitat("Lunar Lagoon".into())).delete::<u64>(&mut txn)?;
+    /// txn.commit()?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * See [`WriteTransaction::delete_matching`].
+    pub fn delete<K>(self, txn: &mut WriteTransaction) -> Result<u64, Error>
+    where
+        K: Codec<K>,
+        V: for<'i> Indexable<'i> + Codec<V>,
+    {
+        txn.delete_matching::<K, V>(self, None)
+    }
+
+    /// Deletes up to `limit` records matching the query, in ascending primary key order, along
+    /// with their secondary index entries. Returns the number of records deleted.
+    ///
+    /// Use this to delete large result sets in batches, committing between batches to keep each
+    /// write transaction small. Fewer than `limit` deletions means the last batch was reached.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let prepared = Query::lookup(Habitat("Lunar Lagoon".into())).prepare();
+    ///
+    /// loop {
+    ///     let mut txn = db.write()?;
+    ///     let deleted = prepared.to_query()?.delete_up_to::<u64>(&mut txn, 1_000)?;
+    ///     txn.commit()?;
+    ///     if deleted < 1_000 { break; }
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * See [`WriteTransaction::delete_matching`].
+    pub fn delete_up_to<K>(self, txn: &mut WriteTransaction, limit: usize) -> Result<u64, Error>
+    where
+        K: Codec<K>,
+        V: for<'i> Indexable<'i> + Codec<V>,
+    {
+        txn.delete_matching::<K, V>(self, Some(limit))
+    }
+}
@@ -0,0 +1,289 @@
+//! `EXPLAIN`-style output that describes how a [`Query`] will be evaluated.
+
+use crate::indexing::{AlignedBytes, ArchivedKeySet, HasTable, ReadableKeySet};
+use crate::querying::{DynLookup, DynMultiLookup, Query};
+use crate::typed::transaction::{QuerySource, ReadTransaction};
+use crate::Error;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// How a single step of a query plan is evaluated.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Strategy {
+    /// The step is answered directly from a secondary index table.
+    IndexAccelerated,
+
+    /// The step requires every record in the primary table to be read, or every record that
+    /// survived the previous steps, and checked one at a time.
+    PostFilterScan,
+}
+
+/// A single step of a [`QueryPlan`].
+#[derive(Clone, Debug)]
+pub struct PlanStep {
+    /// Nesting depth of the step in the query tree. The root is `0`.
+    pub depth: usize,
+
+    /// The operation performed at this step. For example: `LOOKUP`, `AND`, `NOT_IN`.
+    pub operation: &'static str,
+
+    /// Name of the secondary index table consulted by this step, if any.
+    pub index_name: Option<&'static str>,
+
+    /// Human-readable description of the look-up. For example: `Habitat("Desert")`.
+    pub description: String,
+
+    /// Number of primary keys the index holds for this step's key, if it could be estimated.
+    ///
+    /// This is `None` for steps whose size depends on the whole primary table, such as `NOT`.
+    pub estimated_keys: Option<usize>,
+
+    /// Whether the step is index-accelerated or a post-filter scan.
+    pub strategy: Strategy,
+
+    /// A performance warning for the step, if any. For example, a substring match must check every
+    /// key in its index.
+    pub warning: Option<&'static str>,
+}
+
+/// A description of how a [`Query`] will be evaluated, produced by [`Query::explain`].
+///
+/// Steps are listed in evaluation order: the left-hand side of each binary operation is evaluated
+/// before the operation's right-hand index is applied.
+#[derive(Clone, Debug, Default)]
+pub struct QueryPlan {
+    /// The steps of the plan, in evaluation order.
+    pub steps: Vec<PlanStep>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl QueryPlan {
+    /// Returns `true` if every step of the plan can be answered from secondary indexes.
+    #[must_use]
+    pub fn is_fully_indexed(&self) -> bool {
+        self.steps.iter().all(|step| step.strategy == Strategy::IndexAccelerated)
+    }
+}
+
+impl<V: HasTable> Query<V> {
+    /// Describes how this query will be evaluated against the given read transaction.
+    ///
+    /// For each step, the plan reports the index consulted, the number of primary keys stored for
+    /// the step's key (read from the index, without loading any records), and whether the step is
+    /// index-accelerated or a post-filter scan.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///
+    /// * Serialization errors when encoding a look-up's secondary key.
+    ///
+    /// * Deserialization errors when instantiating a `&ArchivedKeySet` from an index entry.
+    pub fn explain(&self, txn: &ReadTransaction) -> Result<QueryPlan, Error> {
+        let mut plan = QueryPlan::default();
+        self.explain_into(txn, 0, &mut plan)?;
+        Ok(plan)
+    }
+
+    /// Recursively appends this query's steps to the plan, in evaluation order.
+    fn explain_into(
+        &self,
+        txn: &ReadTransaction,
+        depth: usize,
+        plan: &mut QueryPlan,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Lookup(lookup) => plan.steps.push(lookup_step(txn, depth, "LOOKUP", lookup.as_ref())?),
+            Self::Not(lookup) => {
+                let mut step = lookup_step(txn, depth, "NOT", lookup.as_ref())?;
+                step.estimated_keys = None;
+                plan.steps.push(step);
+            },
+            Self::And(lhs, rhs) => binary_step(txn, depth, plan, "AND", lhs, rhs.as_ref())?,
+            Self::Difference(lhs, rhs) =>
+                binary_step(txn, depth, plan, "WITHOUT", lhs, rhs.as_ref())?,
+            Self::Or(lhs, rhs) => binary_step(txn, depth, plan, "OR", lhs, rhs.as_ref())?,
+            Self::Xor(lhs, rhs) => binary_step(txn, depth, plan, "XOR", lhs, rhs.as_ref())?,
+            Self::Group(inner) => inner.explain_into(txn, depth + 1, plan)?,
+            Self::AnyOf(multi) => plan.steps.push(multi_step(txn, depth, "ANY_OF", multi.as_ref())?),
+            Self::NotIn(multi) => {
+                let mut step = multi_step(txn, depth, "NOT_IN", multi.as_ref())?;
+                step.estimated_keys = None;
+                plan.steps.push(step);
+            },
+            #[cfg(feature = "custom-queries")]
+            Self::Custom(_) => plan.steps.push(PlanStep {
+                depth,
+                operation: "CUSTOM",
+                index_name: None,
+                description: "custom predicate".to_string(),
+                estimated_keys: None,
+                strategy: Strategy::PostFilterScan,
+                warning: Some("custom predicates are evaluated against every loaded record"),
+            }),
+            Self::StartsWith(string_match) => plan.steps.push(PlanStep {
+                depth,
+                operation: "STARTS_WITH",
+                index_name: Some(string_match.index_name),
+                description: format!("{:?}", string_match.pattern),
+                estimated_keys: None,
+                strategy: Strategy::IndexAccelerated,
+                warning: None,
+            }),
+            Self::Contains(string_match) => plan.steps.push(PlanStep {
+                depth,
+                operation: "CONTAINS",
+                index_name: Some(string_match.index_name),
+                description: format!("{:?}", string_match.pattern),
+                // Every key is checked, so at most every entry in the index can match:
+                estimated_keys: txn
+                    .index_stats_by_name(string_match.index_name)?
+                    .map(|stats| usize::try_from(stats.total_entries).unwrap_or(usize::MAX)),
+                strategy: Strategy::PostFilterScan,
+                warning: Some("substring matches decode and check every key in the index"),
+            }),
+            Self::TopK(top_k) => plan.steps.push(PlanStep {
+                depth,
+                operation: "TOP_K",
+                index_name: Some(top_k.index_name),
+                description: format!("{} {:?}", top_k.k, top_k.direction),
+                estimated_keys: Some(top_k.k),
+                strategy: Strategy::IndexAccelerated,
+                warning: None,
+            }),
+        }
+
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl std::fmt::Display for QueryPlan {
+    /// Formats the plan as an indented list of steps, one per line. For example:
+    ///
+    /// ```text
+    /// LOOKUP creatures_by_habitat Habitat("Tide Pool") ~14 keys [index]
+    /// WITHOUT creatures_by_species Species("Mantis Shrimp") ~3 keys [index]
+    /// ```
+    ///
+    /// Steps with a performance warning have it appended:
+    ///
+    /// ```text
+    /// CONTAINS creatures_by_diet "fish" ~? keys [scan] -- warning: substring matches decode and check every key in the index
+    /// ```
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for step in &self.steps {
+            write!(f, "{:indent$}{}", "", step.operation, indent = step.depth * 2)?;
+            if let Some(index_name) = step.index_name {
+                write!(f, " {index_name}")?;
+            }
+            write!(f, " {}", step.description)?;
+            match step.estimated_keys {
+                Some(count) => write!(f, " ~{count} keys")?,
+                None => write!(f, " ~? keys")?,
+            }
+            match step.strategy {
+                Strategy::IndexAccelerated => write!(f, " [index]")?,
+                Strategy::PostFilterScan => write!(f, " [scan]")?,
+            }
+            match step.warning {
+                Some(warning) => writeln!(f, " -- warning: {warning}")?,
+                None => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Helpers
+
+/// Appends the left-hand side's steps, followed by the binary operation's right-hand look-up.
+fn binary_step<V: HasTable>(
+    txn: &ReadTransaction,
+    depth: usize,
+    plan: &mut QueryPlan,
+    operation: &'static str,
+    lhs: &Query<V>,
+    rhs: &DynLookup<V>,
+) -> Result<(), Error> {
+    lhs.explain_into(txn, depth + 1, plan)?;
+    plan.steps.push(lookup_step(txn, depth, operation, rhs)?);
+    Ok(())
+}
+
+/// Describes a single-value index look-up.
+fn lookup_step<V: HasTable>(
+    txn: &ReadTransaction,
+    depth: usize,
+    operation: &'static str,
+    lookup: &DynLookup<V>,
+) -> Result<PlanStep, Error> {
+    let index_name = lookup.index_name();
+    let estimated_keys = count_keys(txn, index_name, &lookup.index_key_bytes()?)?;
+    Ok(PlanStep {
+        depth,
+        operation,
+        index_name: Some(index_name),
+        description: super::DisplayLookup(lookup).to_string(),
+        estimated_keys: Some(estimated_keys),
+        strategy: Strategy::IndexAccelerated,
+        warning: None,
+    })
+}
+
+/// Describes a multi-value index look-up. The estimate is the sum of the individual key-sets, so
+/// it may over-count records that appear under more than one value.
+fn multi_step<V: HasTable>(
+    txn: &ReadTransaction,
+    depth: usize,
+    operation: &'static str,
+    multi: &DynMultiLookup<V>,
+) -> Result<PlanStep, Error> {
+    let index_name = multi.index_name().ok_or(Error::MissingIndexTableName)?;
+    let mut estimated_keys = 0;
+    for index_key_bytes in multi.to_key_set()? {
+        estimated_keys += count_keys(txn, index_name, &index_key_bytes)?;
+    }
+    Ok(PlanStep {
+        depth,
+        operation,
+        index_name: Some(index_name),
+        description: super::DisplayMultiLookup(multi).to_string(),
+        estimated_keys: Some(estimated_keys),
+        strategy: Strategy::IndexAccelerated,
+        warning: None,
+    })
+}
+
+/// Returns the number of primary keys stored in the index for the given secondary key. A missing
+/// index table or index entry counts as zero. The secondary key of a protected index is looked-up
+/// by its search token.
+fn count_keys(txn: &ReadTransaction, index_name: &str, index_key_bytes: &[u8]) -> Result<usize, Error> {
+    let Some(index_table) = txn.open_raw_index_table(index_name)? else {
+        return Ok(0);
+    };
+
+    let index_key_bytes = txn.stored_index_key(index_name, index_key_bytes.to_vec());
+    let first_shard_len = match index_table.get(&*index_key_bytes)? {
+        Some(key_set_guard) => {
+            let key_set_bytes =
+                txn.open_index_value(index_name, &index_key_bytes, key_set_guard.value())?;
+            ArchivedKeySet::from_bytes(&AlignedBytes::new(&key_set_bytes))?.len()
+        },
+        None => return Ok(0),
+    };
+
+    // A very large key set is sharded across several rows:
+    let overflow_len = txn
+        .overflow_keys(index_name, &index_key_bytes, first_shard_len)?
+        .map_or(0, |overflow| overflow.len());
+    Ok(first_shard_len + overflow_len)
+}
@@ -0,0 +1,183 @@
+//! The `query!` macro: a small declarative DSL for composing [`Query`] trees.
+//!
+//! [`Query`]: crate::querying::Query
+
+/// Composes a [`Query`] from a boolean expression over index look-ups.
+///
+/// ```rust,ignore
+/// let query = query!(Habitat == "Tide Pool" && !(Species == "Mantis Shrimp"));
+///
+/// // Expands to:
+/// let query = Query::lookup(Habitat("Tide Pool".into()))
+///     .without(Species("Mantis Shrimp".into()));
+/// ```
+///
+/// # Grammar
+///
+/// | Syntax                     | Expands to                                          |
+/// |----------------------------|-----------------------------------------------------|
+/// | `Index == value`           | `Query::lookup(Index(value.into()))`                |
+/// | `Index != value`           | `Query::negate(Index(value.into()))`                |
+/// | `!(Index == value)`        | `Query::negate(Index(value.into()))`                |
+/// | `Index in [a, b, c]`       | `Query::AnyOf` over `Index(a.into())`, …            |
+/// | `(…)`                      | `Query::group(query!(…))`                           |
+/// | `lhs && Index == value`    | `lhs.and(Index(value.into()))`                      |
+/// | `lhs && !(Index == value)` | `lhs.without(Index(value.into()))`                  |
+/// | `lhs && Index != value`    | `lhs.without(Index(value.into()))`                  |
+/// | `lhs \|\| Index == value`  | `lhs.or_else(Index(value.into()))`                  |
+/// | `lhs ^ Index == value`     | `lhs.xor(Index(value.into()))`                      |
+///
+/// # Notes
+///
+/// * Operators are evaluated strictly left-to-right with no precedence, mirroring how a `Query`
+///   tree is built: `A || B && C` means `(A || B) && C`. Use a parenthesized group on the left-hand
+///   side to make intent explicit.
+///
+/// * The right-hand side of a binary operator must be a single comparison, because the right-hand
+///   side of each `Query` operation is an index look-up rather than a sub-query.
+///
+/// * Each value must be a single token tree: a literal, an identifier, or a parenthesized
+///   expression. For example: `Habitat == (format!("{prefix} Pool"))`.
+///
+/// [`Query`]: crate::querying::Query
+#[macro_export]
+macro_rules! query {
+    // Terms ---------------------------------------------------------------------------------------
+
+    (@term $index:ident == $value:tt) => {
+        $index(::core::convert::Into::into($value))
+    };
+
+    // Leading expression --------------------------------------------------------------------------
+
+    (@start ! ( $index:ident == $value:tt ) $($rest:tt)*) => {
+        $crate::query!(@ops
+            [$crate::querying::Query::negate($crate::query!(@term $index == $value))]
+            $($rest)*
+        )
+    };
+
+    (@start ! $index:ident == $value:tt $($rest:tt)*) => {
+        $crate::query!(@ops
+            [$crate::querying::Query::negate($crate::query!(@term $index == $value))]
+            $($rest)*
+        )
+    };
+
+    (@start $index:ident != $value:tt $($rest:tt)*) => {
+        $crate::query!(@ops
+            [$crate::querying::Query::negate($crate::query!(@term $index == $value))]
+            $($rest)*
+        )
+    };
+
+    (@start $index:ident in [ $($value:tt),+ $(,)? ] $($rest:tt)*) => {
+        $crate::query!(@ops
+            [$crate::querying::Query::AnyOf(::std::boxed::Box::new(
+                ::std::vec![$($crate::query!(@term $index == $value)),+]
+            ))]
+            $($rest)*
+        )
+    };
+
+    (@start $index:ident == $value:tt $($rest:tt)*) => {
+        $crate::query!(@ops
+            [$crate::querying::Query::lookup($crate::query!(@term $index == $value))]
+            $($rest)*
+        )
+    };
+
+    (@start ( $($inner:tt)+ ) $($rest:tt)*) => {
+        $crate::query!(@ops
+            [$crate::querying::Query::group($crate::query!(@start $($inner)+))]
+            $($rest)*
+        )
+    };
+
+    // Chained operators ---------------------------------------------------------------------------
+
+    (@ops [$acc:expr]) => {
+        $acc
+    };
+
+    (@ops [$acc:expr] && ! ( $index:ident == $value:tt ) $($rest:tt)*) => {
+        $crate::query!(@ops [$acc.without($crate::query!(@term $index == $value))] $($rest)*)
+    };
+
+    (@ops [$acc:expr] && ! $index:ident == $value:tt $($rest:tt)*) => {
+        $crate::query!(@ops [$acc.without($crate::query!(@term $index == $value))] $($rest)*)
+    };
+
+    (@ops [$acc:expr] && $index:ident != $value:tt $($rest:tt)*) => {
+        $crate::query!(@ops [$acc.without($crate::query!(@term $index == $value))] $($rest)*)
+    };
+
+    (@ops [$acc:expr] && $index:ident == $value:tt $($rest:tt)*) => {
+        $crate::query!(@ops [$acc.and($crate::query!(@term $index == $value))] $($rest)*)
+    };
+
+    (@ops [$acc:expr] || $index:ident == $value:tt $($rest:tt)*) => {
+        $crate::query!(@ops [$acc.or_else($crate::query!(@term $index == $value))] $($rest)*)
+    };
+
+    (@ops [$acc:expr] ^ $index:ident == $value:tt $($rest:tt)*) => {
+        $crate::query!(@ops [$acc.xor($crate::query!(@term $index == $value))] $($rest)*)
+    };
+
+    // Entry point ---------------------------------------------------------------------------------
+
+    ($($tokens:tt)+) => {
+        $crate::query!(@start $($tokens)+)
+    };
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use crate::indexing::{Creature, Habitat, Species};
+    use crate::querying::Query;
+
+    #[test]
+    fn comparisons_expand_to_lookups_and_negations() {
+        let query: Query<Creature> = query!(Habitat == "Tide Pool");
+        assert!(matches!(query, Query::Lookup(..)));
+
+        let query: Query<Creature> = query!(Habitat != "Tide Pool");
+        assert!(matches!(query, Query::Not(..)));
+
+        let query: Query<Creature> = query!(!(Species == "Mantis Shrimp"));
+        assert!(matches!(query, Query::Not(..)));
+
+        let query: Query<Creature> = query!(Species in ["Clownfish", "Parrotfish"]);
+        assert!(matches!(query, Query::AnyOf(..)));
+    }
+
+    #[test]
+    fn operators_chain_left_to_right() {
+        let query: Query<Creature> =
+            query!(Habitat == "Tide Pool" || Habitat == "Reef" && Species != "Mantis Shrimp");
+        let Query::Difference(lhs, _) = query else {
+            panic!("expected the last operator at the root");
+        };
+        assert!(matches!(*lhs, Query::Or(..)));
+
+        let query: Query<Creature> = query!(Habitat == "Reef" ^ Species == "Clownfish");
+        assert!(matches!(query, Query::Xor(..)));
+    }
+
+    #[test]
+    fn parentheses_group_the_left_hand_side() {
+        let query: Query<Creature> =
+            query!((Habitat == "Reef" || Habitat == "Tide Pool") && Species == "Clownfish");
+        let Query::And(lhs, _) = query else {
+            panic!("expected an and at the root");
+        };
+        let Query::Group(inner) = *lhs else {
+            panic!("expected a group on the left-hand side");
+        };
+        assert!(matches!(*inner, Query::Or(..)));
+    }
+}
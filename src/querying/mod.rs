@@ -1,10 +1,46 @@
+mod aggregate;
+#[cfg(feature = "writes")]
+mod delete;
+mod explain;
+mod macros;
+
+pub use crate::querying::aggregate::{Aggregate, AggregateGroup, AggregateValue, DynMeasure};
+pub(crate) use crate::querying::aggregate::Accumulator;
+
+pub use crate::querying::explain::{PlanStep, QueryPlan, Strategy};
+
+mod prepared;
+pub use crate::querying::prepared::{Param, PreparedQuery};
+
+mod string_match;
+pub use crate::querying::string_match::StringMatch;
+
+mod top_k;
+pub use crate::querying::top_k::{SortDirection, TopK};
+
+#[cfg(feature = "writes")]
+mod update;
+#[cfg(feature = "writes")]
+pub use crate::querying::update::{UpdateReport, Versioned};
+
+#[cfg(feature = "query-parser")]
+pub mod parser;
+#[cfg(feature = "query-parser")]
+pub use crate::querying::parser::QueryParser;
+
 use crate::indexing::HasTable;
 use crate::indexing::IndexLookup;
 use crate::indexing::IndexMultiLookup;
+use crate::indexing::ReadableKeySet;
 
 pub type DynLookup<V> = dyn IndexLookup<Record = V>;
 pub type DynMultiLookup<V> = dyn IndexMultiLookup<Record = V>;
 
+/// A custom predicate over records. It may capture state, and it may fail, for example when a
+/// captured validation rule rejects a record outright.
+#[cfg(feature = "custom-queries")]
+pub type DynPredicate<V> = dyn Fn(&V) -> Result<bool, crate::Error> + Send + Sync;
+
 /// A composable, recursive query structure used to express logical operations over indexed fields.
 ///
 /// This enum represents a logical tree of operations that can be evaluated against a record table,
@@ -13,7 +49,7 @@ pub type DynMultiLookup<V> = dyn IndexMultiLookup<Record = V>;
 ///
 /// # Examples
 ///
-/// ```rust
+/// ```rust,ignore
 /// use atlatl::querying::Query;
 /// use atlatl::indexing::{Habitat, Species};
 ///
@@ -27,7 +63,7 @@ pub type DynMultiLookup<V> = dyn IndexMultiLookup<Record = V>;
 pub enum Query<V: HasTable> {
     // Atomic lookup -------------------------------------------------------------------------------
 
-    /// Lookup starts a query by matching an index (e.g., Habitat("Tide Pool")), returning a KeySet
+    /// Lookup starts a query by matching an index (e.g., Habitat("Tide Pool")), returning a `KeySet`
     /// of keys.
     ///
     /// A base query that performs a direct lookup using a field index.
@@ -59,20 +95,20 @@ pub enum Query<V: HasTable> {
     ///
     /// Prefer placing the more selective query on the left-hand side, as it will be evaluated 
     /// first.
-    And(Box<Query<V>>, Box<DynLookup<V>>),
+    And(Box<Self>, Box<DynLookup<V>>),
 
     /// Set difference (e.g., Tide Pool WITHOUT Mantis Shrimp).
-    Difference(Box<Query<V>>, Box<DynLookup<V>>),
+    Difference(Box<Self>, Box<DynLookup<V>>),
 
     /// Performs a logical `OR` between two subqueries.
     ///
     /// Records matching *either* query will be returned.
     ///
     /// This is equivalent to set union in terms of result evaluation.
-    Or(Box<Query<V>>, Box<DynLookup<V>>),
+    Or(Box<Self>, Box<DynLookup<V>>),
 
     /// Performs a logical `XOR` between two subqueries.
-    Xor(Box<Query<V>>, Box<DynLookup<V>>),
+    Xor(Box<Self>, Box<DynLookup<V>>),
 
     // Grouping ------------------------------------------------------------------------------------
 
@@ -88,17 +124,33 @@ pub enum Query<V: HasTable> {
     /// ```text
     /// A AND (B OR C)
     /// ```
-    Group(Box<Query<V>>),
+    Group(Box<Self>),
 
     // Multi-value lookups -------------------------------------------------------------------------
 
-    /// Internal: Multi-value IN lookup (e.g., Species IN ["Clownfish", "Parrotfish"]). Use
+    /// Internal: Multi-value IN lookup (e.g., `Species IN ["Clownfish", "Parrotfish"]`). Use
     /// `Query::any_of`.
     AnyOf(Box<DynMultiLookup<V>>),
 
     /// Internal: Multi-value NOT IN lookup. Use `Query::not_in`.
     NotIn(Box<DynMultiLookup<V>>),
 
+    // String pattern lookups ----------------------------------------------------------------------
+
+    /// Internal: Prefix match over an index's string keys (e.g., Species STARTS WITH "Sea "). Use
+    /// `Query::starts_with`.
+    StartsWith(StringMatch),
+
+    /// Internal: Substring match over an index's string keys (e.g., Species CONTAINS "Shrimp").
+    /// Use `Query::contains`.
+    Contains(StringMatch),
+
+    // Ordered selection ---------------------------------------------------------------------------
+
+    /// Internal: The first `k` records in the order of a secondary index (e.g., the 10 newest
+    /// posts). Use `Query::top_k`.
+    TopK(TopK),
+
     // Custom predicate ----------------------------------------------------------------------------
 
     /// A custom predicate-based query over records.
@@ -110,14 +162,17 @@ pub enum Query<V: HasTable> {
     /// **Warning**: This query cannot be accelerated by index traversal and will be applied *after* 
     /// indexed filtering or on a full scan. Use with care.
     ///
+    /// The predicate may capture configuration, and any error it returns aborts the query and is
+    /// returned to the caller, rather than the record being silently filtered out.
+    ///
     /// # Example
     ///
-    /// ```rust
-    /// let predicate = |record: &User| record.age > 30 && record.name.starts_with("A");
-    /// let custom_query = Query::Custom(predicate);
-    /// ```    
+    /// ```rust,ignore
+    /// let minimum_age = settings.minimum_age;
+    /// let custom_query = Query::custom(move |record: &User| Ok(record.age > minimum_age));
+    /// ```
     #[cfg(feature = "custom-queries")]
-    Custom(fn(&V) -> bool),
+    Custom(Box<DynPredicate<V>>),
 }
 
 impl<T, V> From<T> for Query<V>
@@ -129,25 +184,69 @@ where
         Self::Lookup(Box::new(index_lookup))
     }
 }
-/*
+/// Formats a boxed index look-up using its [`IndexLookup::fmt`] implementation.
+struct DisplayLookup<'q, V>(&'q DynLookup<V>);
+
+impl<V: HasTable> std::fmt::Display for DisplayLookup<'_, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        IndexLookup::fmt(self.0, f)
+    }
+}
+
+/// Formats a boxed multi-value index look-up as its index name and the number of values.
+struct DisplayMultiLookup<'q, V>(&'q DynMultiLookup<V>);
+
+impl<V: HasTable> std::fmt::Display for DisplayMultiLookup<'_, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let index_name = self.0.index_name().unwrap_or("?");
+        match self.0.to_key_set() {
+            Ok(key_set) => write!(f, "{index_name}[{} values]", key_set.len()),
+            Err(_) => write!(f, "{index_name}[?]"),
+        }
+    }
+}
+
 impl<V: HasTable> std::fmt::Display for Query<V> {
+    /// Formats the query as a human-readable boolean expression. For example:
+    /// `(Habitat("Tide Pool") WITHOUT Species("Mantis Shrimp"))`.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Query::Lookup(index) => write!(f, "{:?}", index),
-            Query::And(lhs, rhs) => write!(f, "({} AND {:?})", lhs, rhs),
-            Query::Or(lhs, rhs) => write!(f, "({} OR {:?})", lhs, rhs),
-            Query::Xor(lhs, rhs) => write!(f, "({} XOR {:?})", lhs, rhs),
-            Query::Difference(lhs, rhs) => write!(f, "({} WITHOUT {:?})", lhs, rhs),
-            Query::Not(inner) => write!(f, "(NOT {:?})", inner),
-            Query::Group(inner) => write!(f, "({})", inner),
-            Query::AnyOf(multi) => write!(f, "(ANY_OF {:?})", multi),
-            Query::NotIn(multi) => write!(f, "(NOT_IN {:?})", multi),
+            Self::Lookup(index) => write!(f, "{}", DisplayLookup(index.as_ref())),
+            Self::Not(index) => write!(f, "(NOT {})", DisplayLookup(index.as_ref())),
+            Self::And(lhs, rhs) => write!(f, "({lhs} AND {})", DisplayLookup(rhs.as_ref())),
+            Self::Or(lhs, rhs) => write!(f, "({lhs} OR {})", DisplayLookup(rhs.as_ref())),
+            Self::Xor(lhs, rhs) => write!(f, "({lhs} XOR {})", DisplayLookup(rhs.as_ref())),
+            Self::Difference(lhs, rhs) =>
+                write!(f, "({lhs} WITHOUT {})", DisplayLookup(rhs.as_ref())),
+            Self::Group(inner) => write!(f, "({inner})"),
+            Self::AnyOf(multi) => write!(f, "(ANY_OF {})", DisplayMultiLookup(multi.as_ref())),
+            Self::NotIn(multi) => write!(f, "(NOT_IN {})", DisplayMultiLookup(multi.as_ref())),
+            Self::StartsWith(string_match) =>
+                write!(f, "{}[STARTS_WITH {:?}]", string_match.index_name, string_match.pattern),
+            Self::Contains(string_match) =>
+                write!(f, "{}[CONTAINS {:?}]", string_match.index_name, string_match.pattern),
+            Self::TopK(top_k) => write!(
+                f,
+                "{}[TOP_K {} {}]",
+                top_k.index_name,
+                top_k.k,
+                match top_k.direction {
+                    SortDirection::Ascending => "ASC",
+                    SortDirection::Descending => "DESC",
+                }
+            ),
             #[cfg(feature = "custom-queries")]
-            Query::Custom(_) => write!(f, "(CUSTOM PREDICATE)"),
+            Self::Custom(_) => write!(f, "(CUSTOM PREDICATE)"),
         }
     }
 }
-*/
+
+impl<V: HasTable> std::fmt::Debug for Query<V> {
+    /// Formats the query the same way as its `Display` implementation.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Query({self})")
+    }
+}
 
 /// A composable query expression tree for filtering records of type `V`.
 ///
@@ -164,57 +263,94 @@ impl<V: HasTable> Query<V> {
     /// example `Habitat("Desert")`, `Species("Mantis Shrimp")`.
     ///
     /// The `IndexLookup` trait defines how to locate the index table and serialize the key.
-    fn lookup<I>(index_lookup: I) -> Self 
+    pub fn lookup<I>(index_lookup: I) -> Self 
     where 
         I: IndexLookup<Record = V> + 'static
     {
         index_lookup.into()
     }
 
+    /// Constructs a base query that looks-up an encrypted field by its blind index search token.
+    ///
+    /// The search term is hashed with the field's [`BlindIndex`] before it's used to query the
+    /// index table, so only the token is ever compared. See [`BlindLookup`] for more details.
+    ///
+    /// [`BlindIndex`]: crate::layers::encryptors::BlindIndex
+    /// [`BlindLookup`]: crate::indexing::BlindLookup
+    #[cfg(any(feature = "kdf-blake3", feature = "kdf-sha256"))]
+    pub fn blind<I>(index_lookup: I, blind_index: &crate::layers::encryptors::BlindIndex) -> Self
+    where
+        I: IndexLookup<Record = V> + 'static
+    {
+        crate::indexing::BlindLookup::new(index_lookup, blind_index).into()
+    }
+
+    /// Constructs a base query that looks-up an encrypted field by its deterministic cipher text.
+    ///
+    /// The search term is encrypted with the field's [`DeterministicCipher`] before it's used to
+    /// query the index table. See [`DeterministicLookup`] for more details.
+    ///
+    /// [`DeterministicCipher`]: crate::layers::encryptors::DeterministicCipher
+    /// [`DeterministicLookup`]: crate::indexing::DeterministicLookup
+    #[cfg(any(feature = "kdf-blake3", feature = "kdf-sha256"))]
+    pub fn deterministic<I>(
+        index_lookup: I,
+        cipher: &crate::layers::encryptors::DeterministicCipher,
+    ) -> Self
+    where
+        I: IndexLookup<Record = V> + 'static
+    {
+        crate::indexing::DeterministicLookup::new(index_lookup, cipher).into()
+    }
+
     // Chainable binary operations -----------------------------------------------------------------
 
     /// Combines two queries with a logical `AND`.
     ///
     /// Both `self` and `filter` must evaluate to `true` for the overall query to match a record.
     /// Use this to narrow results by requiring multiple conditions.
-    fn and<I>(self, filter: I) -> Self
-    where 
+    #[must_use]
+    pub fn and<I>(self, filter: I) -> Self
+    where
         I: IndexLookup<Record = V> + 'static
-    {    
-        Query::And(Box::new(self), Box::new(filter))
-    }    
+    {
+        Self::And(Box::new(self), Box::new(filter))
+    }
 
     /// Combines two queries with a logical `OR`.
     ///
     /// Either `self` or `extender` must evaluate to `true` for the overall query to match a record.
     /// Use this to broaden the result set by accepting multiple possibilities.
-    fn or_else<I>(self, extender: I) -> Self
+    #[must_use]
+    pub fn or_else<I>(self, extender: I) -> Self
     where
         I: IndexLookup<Record = V> + 'static
     {
-        Query::Or(Box::new(self), Box::new(extender))
+        Self::Or(Box::new(self), Box::new(extender))
     }
 
     /// Excludes records matching `filter` from those matching `self`.
     ///
     /// Only records satisfying `self` but not `filter` are included. Use this to filter out
     /// specific conditions from a broader query result.
-    fn without<I>(self, filter: I) -> Self
+    #[must_use]
+    pub fn without<I>(self, filter: I) -> Self
     where
         I: IndexLookup<Record = V> + 'static
     {
-        Query::Difference(Box::new(self), Box::new(filter))
+        Self::Difference(Box::new(self), Box::new(filter))
     }
 
     /// Combines two queries with a logical `XOR`.
     ///
     /// Records matching exactly one of `self` or `filter`, but not both, are included. Use this to
     /// find records exclusive to one condition or the other.
-    fn xor<I>(self, filter: I) -> Self
+    #[must_use]
+    pub fn xor<I>(self, filter: I) -> Self
     where
         I: IndexLookup<Record = V> + 'static
     {
-        Query::Xor(Box::new(self), Box::new(filter))
+        Self::Xor(Box::new(self), Box::new(filter))
     }
 }
 
@@ -226,48 +362,48 @@ impl<V: HasTable> Query<V> {
     ///
     /// Both `a` and `b` must evaluate to `true` for the overall query to match a record. Use this
     /// to narrow results by requiring multiple conditions.
-    fn and_with<Q, I>(a: Q, b: I) -> Self
+    pub fn and_with<Q, I>(a: Q, b: I) -> Self
     where
-        Q: Into<Query<V>>,
+        Q: Into<Self>,
         I: IndexLookup<Record = V> + 'static
     {
-        Query::And(Box::new(a.into()), Box::new(b))
+        Self::And(Box::new(a.into()), Box::new(b))
     }
 
     /// Excludes records matching `b` from those matching `a`.
     ///
     /// Only records satisfying `a` but not `b` are included. Use this to filter out specific
     /// conditions from a broader query result.
-    fn difference<Q, I>(a: Q, b: I) -> Self
+    pub fn difference<Q, I>(a: Q, b: I) -> Self
     where
-        Q: Into<Query<V>>,
+        Q: Into<Self>,
         I: IndexLookup<Record = V> + 'static
     {
-        Query::Difference(Box::new(a.into()), Box::new(b))
+        Self::Difference(Box::new(a.into()), Box::new(b))
     }
 
     /// Combines two queries with a logical `OR`.
     ///
     /// Either `a` or `b` must evaluate to `true` for the overall query to match a record. Use this
     /// to broaden the result set by accepting multiple possibilities.
-    fn or_else_with<Q, I>(a: Q, b: I) -> Self
+    pub fn or_else_with<Q, I>(a: Q, b: I) -> Self
     where
-        Q: Into<Query<V>>,
+        Q: Into<Self>,
         I: IndexLookup<Record = V> + 'static
     {
-        Query::Or(Box::new(a.into()), Box::new(b))
+        Self::Or(Box::new(a.into()), Box::new(b))
     }
 
     /// Combines two queries with a logical `XOR`.
     ///
     /// Records matching exactly one of `a` or `b`, but not both, are included. Use this to find
     /// records exclusive to one condition or the other.
-    fn xor_with<Q, I>(a: Q, b: I) -> Self
+    pub fn xor_with<Q, I>(a: Q, b: I) -> Self
     where
-        Q: Into<Query<V>>,
+        Q: Into<Self>,
         I: IndexLookup<Record = V> + 'static
     {
-        Query::Xor(Box::new(a.into()), Box::new(b))
+        Self::Xor(Box::new(a.into()), Box::new(b))
     }
 }
 
@@ -278,11 +414,11 @@ impl<V: HasTable> Query<V> {
     ///
     /// If the inner query matches a record, this query will exclude it. Useful for excluding 
     /// specific matches from a broader set.
-    fn negate<I>(index_lookup: I) -> Self
+    pub fn negate<I>(index_lookup: I) -> Self
     where 
         I: IndexLookup<Record = V> + 'static
     {
-        index_lookup.into()
+        Self::Not(Box::new(index_lookup))
     }
 
     /// Groups a subquery to explicitly define precedence.
@@ -290,11 +426,11 @@ impl<V: HasTable> Query<V> {
     /// While the `Query` structure already defines a tree of operations, this method allows 
     /// semantically grouping a subexpression. This is useful for debugging, display, or evaluation 
     /// strategies that care about grouping.
-    fn group<Q>(query: Q) -> Self
+    pub fn group<Q>(query: Q) -> Self
     where 
-        Q: Into<Query<V>>
+        Q: Into<Self>
     {    
-        Query::Group(Box::new(query.into()))
+        Self::Group(Box::new(query.into()))
     }    
 
     // Multi-value lookups -------------------------------------------------------------------------
@@ -325,23 +461,36 @@ impl<V: HasTable> Query<V> {
 
     // Custom predicate ----------------------------------------------------------------------------
 
-    /// Creates a custom query using a raw function that evaluates a record.
+    /// Creates a custom query using a closure that evaluates a record.
     ///
     /// This allows for arbitrary user-defined logic, typically used when no index is available or 
-    /// when a more complex in-memory filter is required.
+    /// when a more complex in-memory filter is required. The closure may capture state, and may
+    /// return an error to abort the query.
     ///
     /// This variant bypasses the index and is evaluated *after* data is loaded, so use it sparingly 
     /// for performance-critical paths.
     #[cfg(feature = "custom-queries")]
-    fn custom(f: fn(&V) -> bool) -> Self {
-        Query::Custom(f)
+    pub fn custom<F>(predicate: F) -> Self
+    where
+        F: Fn(&V) -> Result<bool, crate::Error> + Send + Sync + 'static
+    {
+        Self::Custom(Box::new(predicate))
     }
 }
 
-fn example() {
-    use crate::indexing::Habitat;
-    use crate::indexing::Species;
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::Query;
+    use crate::indexing::{Habitat, Species};
 
-    let habitat_query = Query::lookup(Habitat("Tide Pool".to_string()));
-    let complex_query = Query::and(habitat_query, Species("Mantis Shrimp".to_string()));
+    #[test]
+    fn lookups_compose() {
+        let habitat_query = Query::lookup(Habitat("Tide Pool".to_string()));
+        let complex_query = Query::and(habitat_query, Species("Mantis Shrimp".to_string()));
+        assert!(matches!(complex_query, Query::And(..)));
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,53 @@
+//! Contains the error type returned from the text query parser.
+
+// -------------------------------------------------------------------------------------------------
+//
+/// An error returned when a text filter could not be parsed into a `Query`.
+///
+/// Positions are byte offsets into the filter string, so they can be used to point at the problem
+/// in an admin tool or REPL.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The filter string was empty, or ended where another token was expected.
+    #[error("unexpected end of filter, expected {expected}")]
+    UnexpectedEnd {
+        expected: &'static str,
+    },
+
+    /// A token appeared where it wasn't expected.
+    #[error("unexpected `{found}` at position {position}, expected {expected}")]
+    UnexpectedToken {
+        found: String,
+        position: usize,
+        expected: &'static str,
+    },
+
+    /// A quoted string was opened but never closed.
+    #[error("unterminated string starting at position {position}")]
+    UnterminatedString {
+        position: usize,
+    },
+
+    /// A field name was used that isn't in the parser's registry.
+    #[error("unknown field `{name}` at position {position}")]
+    UnknownField {
+        name: String,
+        position: usize,
+    },
+
+    /// A parenthesized group appeared on the right-hand side of an operator. The right-hand side of
+    /// each `Query` operation is an index look-up rather than a sub-query, so groups are only
+    /// allowed at the start of an expression.
+    #[error("parenthesized group at position {position} must appear at the start of an expression")]
+    GroupOnRightHandSide {
+        position: usize,
+    },
+
+    /// The look-up for a field could not be prepared. For example, its key failed to serialize.
+    #[error("field `{name}` could not be prepared for look-up")]
+    Lookup {
+        name: String,
+        #[source] source: Box<crate::Error>,
+    },
+}
@@ -0,0 +1,573 @@
+//! A parser for a small SQL-like filter language that produces [`Query`] trees.
+//!
+//! This allows user-supplied filters in admin tools and REPLs built on `atlatl`, for example:
+//!
+//! ```text
+//! habitat = 'Desert' AND diet != 'Carnivore'
+//! species IN ('Clownfish', 'Parrotfish') OR habitat = 'Reef'
+//! (habitat = 'Tide Pool' OR habitat = 'Reef') AND NOT species = 'Mantis Shrimp'
+//! ```
+//!
+//! Field names are resolved against a registry of index look-ups supplied by the application, so
+//! only indexed fields can be queried.
+
+mod error;
+pub use crate::querying::parser::error::Error;
+
+use crate::indexing::{HasTable, IndexLookup, PreparedIndexLookup};
+use crate::querying::{DynLookup, Query};
+use std::collections::HashMap;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Type Aliases
+
+/// Builds an index look-up for a field from the text value supplied in a filter.
+type FieldConstructor<V> = Box<dyn Fn(String) -> Box<DynLookup<V>> + Send + Sync>;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Parses text filters into [`Query`] trees for record type `V`.
+///
+/// # Grammar
+///
+/// | Syntax                          | Meaning                                        |
+/// |---------------------------------|------------------------------------------------|
+/// | `field = 'value'`               | Index look-up                                  |
+/// | `field != 'value'`              | Exclusion (`NOT`)                              |
+/// | `NOT field = 'value'`           | Exclusion (`NOT`)                              |
+/// | `field IN ('a', 'b')`           | Multi-value look-up (`ANY_OF`)                 |
+/// | `( … )`                         | Group; only allowed at the start of a filter   |
+/// | `… AND field = 'value'`         | Intersection                                   |
+/// | `… AND field != 'value'`        | Difference                                     |
+/// | `… AND NOT field = 'value'`     | Difference                                     |
+/// | `… OR field = 'value'`          | Union                                          |
+/// | `… XOR field = 'value'`         | Symmetric difference                           |
+///
+/// Keywords and field names are case-insensitive. Values are single- or double-quoted strings;
+/// a quote is escaped by doubling it (`'O''Brien'`). Operators are applied strictly left-to-right
+/// with no precedence, mirroring the shape of a `Query` tree.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let parser = QueryParser::<Creature>::new()
+///     .field("habitat", Habitat)
+///     .field("species", Species)
+///     .field("diet", Diet);
+///
+/// let query = parser.parse("habitat = 'Desert' AND diet != 'Carnivore'")?;
+/// ```
+pub struct QueryParser<V: HasTable> {
+    /// Registered fields, keyed by lower-case field name.
+    fields: HashMap<String, FieldConstructor<V>>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<V: HasTable + 'static> QueryParser<V> {
+    /// Instantiates a parser with no registered fields.
+    #[must_use]
+    pub fn new() -> Self {
+        Self { fields: HashMap::new() }
+    }
+
+    /// Registers a queryable field. The `constructor` turns a value from the filter text into the
+    /// field's index look-up, for example `Habitat` for `habitat = 'Desert'`.
+    #[must_use]
+    pub fn field<I, F>(mut self, name: &str, constructor: F) -> Self
+    where
+        I: IndexLookup<Record = V> + 'static,
+        F: Fn(String) -> I + Send + Sync + 'static
+    {
+        self.fields.insert(
+            name.to_ascii_lowercase(),
+            Box::new(move |value| Box::new(constructor(value)))
+        );
+        self
+    }
+
+    /// Parses a filter string into a `Query`.
+    ///
+    /// # Errors
+    ///
+    /// * Returns an error if the filter is malformed, references a field that isn't registered, or
+    ///   if a multi-value look-up's keys could not be serialized.
+    pub fn parse(&self, filter: &str) -> Result<Query<V>, Error> {
+        let tokens = tokenize(filter)?;
+        let mut cursor = Cursor { tokens: &tokens, position: 0, end: filter.len() };
+        let query = self.parse_expression(&mut cursor)?;
+        match cursor.next() {
+            None => Ok(query),
+            Some((token, position)) => Err(Error::UnexpectedToken {
+                found: token.to_string(),
+                position,
+                expected: "`AND`, `OR`, `XOR`, or end of filter",
+            }),
+        }
+    }
+
+    /// Parses a leading term followed by any number of chained operators. Stops at the end of the
+    /// filter or at a closing parenthesis, which is left for the caller.
+    fn parse_expression(&self, cursor: &mut Cursor<'_>) -> Result<Query<V>, Error> {
+        let mut query = self.parse_leading(cursor)?;
+
+        while let Some((token, position)) = cursor.peek() {
+            query = match token {
+                Token::And => {
+                    cursor.next();
+                    match self.parse_comparison(cursor)? {
+                        (lookup, false) => Query::And(Box::new(query), lookup),
+                        (lookup, true) => Query::Difference(Box::new(query), lookup),
+                    }
+                },
+                Token::Or | Token::Xor => {
+                    cursor.next();
+                    let (lookup, negated) = self.parse_comparison(cursor)?;
+                    if negated {
+                        return Err(Error::UnexpectedToken {
+                            found: "NOT".to_string(),
+                            position,
+                            expected: "`AND` before a negated comparison",
+                        });
+                    }
+                    if matches!(token, Token::Or) {
+                        Query::Or(Box::new(query), lookup)
+                    } else {
+                        Query::Xor(Box::new(query), lookup)
+                    }
+                },
+                Token::RParen => break,
+                _ => return Err(Error::UnexpectedToken {
+                    found: token.to_string(),
+                    position,
+                    expected: "`AND`, `OR`, or `XOR`",
+                }),
+            };
+        }
+
+        Ok(query)
+    }
+
+    /// Parses the first term of an expression, which may also be a group or a multi-value look-up.
+    fn parse_leading(&self, cursor: &mut Cursor<'_>) -> Result<Query<V>, Error> {
+        match cursor.peek() {
+            Some((Token::LParen, _)) => {
+                cursor.next();
+                let inner = self.parse_expression(cursor)?;
+                cursor.expect(&Token::RParen, "`)`")?;
+                Ok(Query::Group(Box::new(inner)))
+            },
+            Some((Token::Ident(_), _)) if cursor.peek_at(1).is_some_and(|(token, _)| token == Token::In) => {
+                let (name, position) = cursor.ident()?;
+                cursor.next(); // IN
+                cursor.expect(&Token::LParen, "`(`")?;
+                let mut lookups = Vec::new();
+                loop {
+                    let value = cursor.string()?;
+                    let lookup = self.construct(&name, position, value)?;
+                    lookups.push(prepare(&name, lookup.as_ref())?);
+                    match cursor.next() {
+                        Some((Token::Comma, _)) => {},
+                        Some((Token::RParen, _)) => break,
+                        Some((token, position)) => return Err(Error::UnexpectedToken {
+                            found: token.to_string(),
+                            position,
+                            expected: "`,` or `)`",
+                        }),
+                        None => return Err(Error::UnexpectedEnd { expected: "`,` or `)`" }),
+                    }
+                }
+                Ok(Query::AnyOf(Box::new(lookups)))
+            },
+            _ => match self.parse_comparison(cursor)? {
+                (lookup, false) => Ok(Query::Lookup(lookup)),
+                (lookup, true) => Ok(Query::Not(lookup)),
+            },
+        }
+    }
+
+    /// Parses a single comparison, returning its look-up and whether it was negated.
+    fn parse_comparison(&self, cursor: &mut Cursor<'_>) -> Result<(Box<DynLookup<V>>, bool), Error> {
+        let mut negated = matches!(cursor.peek(), Some((Token::Not, _)));
+        if negated {
+            cursor.next();
+        }
+
+        match cursor.peek() {
+            Some((Token::LParen, position)) if !negated => {
+                return Err(Error::GroupOnRightHandSide { position });
+            },
+            Some((Token::LParen, _)) => {
+                // `NOT (field = 'value')`
+                cursor.next();
+                let (lookup, inner_negated) = self.parse_comparison(cursor)?;
+                cursor.expect(&Token::RParen, "`)`")?;
+                return Ok((lookup, !inner_negated));
+            },
+            _ => {},
+        }
+
+        let (name, position) = cursor.ident()?;
+        match cursor.next() {
+            Some((Token::Eq, _)) => {},
+            Some((Token::NotEq, _)) => negated = !negated,
+            Some((token, position)) => return Err(Error::UnexpectedToken {
+                found: token.to_string(),
+                position,
+                expected: "`=` or `!=`",
+            }),
+            None => return Err(Error::UnexpectedEnd { expected: "`=` or `!=`" }),
+        }
+
+        let value = cursor.string()?;
+        Ok((self.construct(&name, position, value)?, negated))
+    }
+
+    /// Builds the look-up for a registered field.
+    fn construct(
+        &self,
+        name: &str,
+        position: usize,
+        value: String,
+    ) -> Result<Box<DynLookup<V>>, Error> {
+        self.fields
+            .get(&name.to_ascii_lowercase())
+            .map(|constructor| constructor(value))
+            .ok_or_else(|| Error::UnknownField { name: name.to_string(), position })
+    }
+}
+
+impl<V: HasTable + 'static> Default for QueryParser<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Copies a look-up into a [`PreparedIndexLookup`] so it can be collected into a multi-value
+/// look-up.
+fn prepare<V: HasTable>(
+    name: &str,
+    lookup: &DynLookup<V>,
+) -> Result<PreparedIndexLookup<V>, Error> {
+    let index_key_bytes = lookup
+        .index_key_bytes()
+        .map_err(|source| Error::Lookup { name: name.to_string(), source: Box::new(source) })?;
+    Ok(PreparedIndexLookup::new(lookup.index_name(), *lookup.index_kind(), index_key_bytes))
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tokenizer
+
+/// A lexical token in a filter string.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Eq,
+    NotEq,
+    LParen,
+    RParen,
+    Comma,
+    And,
+    Or,
+    Xor,
+    Not,
+    In,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ident(name) => write!(f, "{name}"),
+            Self::Str(value) => write!(f, "'{value}'"),
+            Self::Eq => write!(f, "="),
+            Self::NotEq => write!(f, "!="),
+            Self::LParen => write!(f, "("),
+            Self::RParen => write!(f, ")"),
+            Self::Comma => write!(f, ","),
+            Self::And => write!(f, "AND"),
+            Self::Or => write!(f, "OR"),
+            Self::Xor => write!(f, "XOR"),
+            Self::Not => write!(f, "NOT"),
+            Self::In => write!(f, "IN"),
+        }
+    }
+}
+
+/// Splits a filter string into tokens, each paired with its byte offset.
+fn tokenize(filter: &str) -> Result<Vec<(Token, usize)>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars = filter.char_indices().peekable();
+
+    while let Some((position, ch)) = chars.next() {
+        let token = match ch {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            '=' => {
+                if chars.peek().is_some_and(|(_, c)| *c == '=') { chars.next(); }
+                Token::Eq
+            },
+            '!' | '<' => {
+                let expected = if ch == '!' { '=' } else { '>' };
+                match chars.next() {
+                    Some((_, c)) if c == expected => Token::NotEq,
+                    Some((position, c)) => return Err(Error::UnexpectedToken {
+                        found: c.to_string(),
+                        position,
+                        expected: "`!=` or `<>`",
+                    }),
+                    None => return Err(Error::UnexpectedEnd { expected: "`!=` or `<>`" }),
+                }
+            },
+            '\'' | '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, c)) if c == ch => {
+                            // A doubled quote is an escaped quote
+                            if chars.peek().is_some_and(|(_, next)| *next == ch) {
+                                chars.next();
+                                value.push(ch);
+                            } else {
+                                break;
+                            }
+                        },
+                        Some((_, c)) => value.push(c),
+                        None => return Err(Error::UnterminatedString { position }),
+                    }
+                }
+                Token::Str(value)
+            },
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut word = String::from(c);
+                while let Some((_, c)) = chars.peek().copied() {
+                    if c.is_alphanumeric() || c == '_' { word.push(c); chars.next(); } else { break; }
+                }
+                match word.to_ascii_uppercase().as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "XOR" => Token::Xor,
+                    "NOT" => Token::Not,
+                    "IN" => Token::In,
+                    _ => Token::Ident(word),
+                }
+            },
+            c => return Err(Error::UnexpectedToken {
+                found: c.to_string(),
+                position,
+                expected: "a field, value, operator, or parenthesis",
+            }),
+        };
+        tokens.push((token, position));
+    }
+
+    Ok(tokens)
+}
+
+/// A read position in a token stream.
+struct Cursor<'t> {
+    tokens: &'t [(Token, usize)],
+    position: usize,
+    end: usize,
+}
+
+impl Cursor<'_> {
+    fn peek(&self) -> Option<(Token, usize)> {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<(Token, usize)> {
+        self.tokens.get(self.position + offset).cloned()
+    }
+
+    fn next(&mut self) -> Option<(Token, usize)> {
+        let token = self.peek();
+        if token.is_some() { self.position += 1; }
+        token
+    }
+
+    fn expect(&mut self, expected_token: &Token, expected: &'static str) -> Result<(), Error> {
+        match self.next() {
+            Some((token, _)) if token == *expected_token => Ok(()),
+            Some((token, position)) => Err(Error::UnexpectedToken {
+                found: token.to_string(),
+                position,
+                expected,
+            }),
+            None => Err(Error::UnexpectedEnd { expected }),
+        }
+    }
+
+    fn ident(&mut self) -> Result<(String, usize), Error> {
+        match self.next() {
+            Some((Token::Ident(name), position)) => Ok((name, position)),
+            Some((token, position)) => Err(Error::UnexpectedToken {
+                found: token.to_string(),
+                position,
+                expected: "a field name",
+            }),
+            None => Err(Error::UnexpectedEnd { expected: "a field name" }),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        match self.next() {
+            Some((Token::Str(value), _)) => Ok(value),
+            Some((token, position)) => Err(Error::UnexpectedToken {
+                found: token.to_string(),
+                position,
+                expected: "a quoted value",
+            }),
+            None => Err(Error::UnexpectedToken {
+                found: "end of filter".to_string(),
+                position: self.end,
+                expected: "a quoted value",
+            }),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexing::{Creature, IndexKind};
+
+    /// A look-up that formats as `field="value"`, so parsed queries can be compared as text.
+    struct Field(&'static str, String);
+
+    impl IndexLookup for Field {
+        type Record = Creature;
+
+        fn index_name(&self) -> &'static str {
+            self.0
+        }
+
+        fn index_kind(&self) -> &IndexKind {
+            &IndexKind::NonUnique
+        }
+
+        fn index_key_bytes(&self) -> Result<Vec<u8>, crate::Error> {
+            Ok(self.1.clone().into_bytes())
+        }
+
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}={:?}", self.0, self.1)
+        }
+    }
+
+    fn parser() -> QueryParser<Creature> {
+        QueryParser::new()
+            .field("habitat", |value| Field("habitat", value))
+            .field("species", |value| Field("species", value))
+            .field("diet", |value| Field("diet", value))
+    }
+
+    /// Parses each filter, and compares the query, or the error, as text.
+    fn check(cases: &[(&str, Result<&str, &str>)]) {
+        let parser = parser();
+        for (filter, expected) in cases {
+            let parsed = parser
+                .parse(filter)
+                .map(|query| query.to_string())
+                .map_err(|error| error.to_string());
+            let expected = expected.map(ToString::to_string).map_err(ToString::to_string);
+            assert_eq!(parsed, expected, "filter: {filter}");
+        }
+    }
+
+    #[test]
+    fn operators_apply_left_to_right() {
+        check(&[
+            ("habitat = 'Reef'", Ok(r#"habitat="Reef""#)),
+            (
+                "habitat = 'Reef' OR species = 'Eel' AND diet = 'Fish'",
+                Ok(r#"((habitat="Reef" OR species="Eel") AND diet="Fish")"#),
+            ),
+            (
+                "habitat = 'Reef' AND species = 'Eel' OR diet = 'Fish'",
+                Ok(r#"((habitat="Reef" AND species="Eel") OR diet="Fish")"#),
+            ),
+            (
+                "habitat = 'Reef' XOR species = 'Eel' AND NOT diet = 'Fish'",
+                Ok(r#"((habitat="Reef" XOR species="Eel") WITHOUT diet="Fish")"#),
+            ),
+            (
+                "(habitat = 'Reef' OR habitat = 'Kelp') AND diet != 'Fish'",
+                Ok(r#"(((habitat="Reef" OR habitat="Kelp")) WITHOUT diet="Fish")"#),
+            ),
+            ("habitat != 'Reef'", Ok(r#"(NOT habitat="Reef")"#)),
+            ("NOT habitat != 'Reef'", Ok(r#"habitat="Reef""#)),
+            ("NOT (habitat = 'Reef')", Ok(r#"(NOT habitat="Reef")"#)),
+            ("habitat IN ('Reef', 'Kelp')", Ok("(ANY_OF habitat[2 values])")),
+            (
+                "HABITAT == 'Reef' and Diet <> 'Fish'",
+                Ok(r#"(habitat="Reef" WITHOUT diet="Fish")"#),
+            ),
+        ]);
+    }
+
+    #[test]
+    fn quotes_are_escaped_by_doubling() {
+        check(&[
+            ("species = 'O''Brien'", Ok(r#"species="O'Brien""#)),
+            (r#"species = "say ""hi""""#, Ok(r#"species="say \"hi\"""#)),
+            (r#"species = 'say "hi"'"#, Ok(r#"species="say \"hi\"""#)),
+            ("species = ''", Ok(r#"species="""#)),
+            ("species = 'AND OR ()'", Ok(r#"species="AND OR ()""#)),
+        ]);
+    }
+
+    #[test]
+    fn malformed_filters_report_their_position() {
+        check(&[
+            ("", Err("unexpected end of filter, expected a field name")),
+            ("habitat = 'Reef", Err("unterminated string starting at position 10")),
+            ("habitat 'Reef'", Err("unexpected `'Reef'` at position 8, expected `=` or `!=`")),
+            ("habitat =", Err("unexpected `end of filter` at position 9, expected a quoted value")),
+            ("habitat ! 'Reef'", Err("unexpected ` ` at position 9, expected `!=` or `<>`")),
+            (
+                "habitat = 'Reef' ; diet = 'Fish'",
+                Err(
+                    "unexpected `;` at position 17, expected a field, value, operator, or \
+                    parenthesis",
+                ),
+            ),
+            (
+                "habitat = 'Reef' diet = 'Fish'",
+                Err("unexpected `diet` at position 17, expected `AND`, `OR`, or `XOR`"),
+            ),
+            (
+                "habitat = 'Reef' OR NOT diet = 'Fish'",
+                Err("unexpected `NOT` at position 17, expected `AND` before a negated comparison"),
+            ),
+            (
+                "habitat = 'Reef' AND (diet = 'Fish')",
+                Err("parenthesized group at position 21 must appear at the start of an expression"),
+            ),
+            ("(habitat = 'Reef'", Err("unexpected end of filter, expected `)`")),
+            (
+                "habitat IN ('Reef' 'Kelp')",
+                Err("unexpected `'Kelp'` at position 19, expected `,` or `)`"),
+            ),
+        ]);
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        check(&[
+            ("colour = 'Red'", Err("unknown field `colour` at position 0")),
+            ("habitat = 'Reef' AND colour = 'Red'", Err("unknown field `colour` at position 21")),
+            ("colour IN ('Red', 'Blue')", Err("unknown field `colour` at position 0")),
+        ]);
+    }
+}
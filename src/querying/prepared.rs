@@ -0,0 +1,396 @@
+//! Parameterized query templates that are planned once and bound to different values at execution
+//! time.
+
+use crate::indexing::{HasTable, IndexKind, IndexLookup, PreparedIndexLookup};
+use crate::querying::{DynLookup, DynMultiLookup, Query};
+#[cfg(feature = "custom-queries")]
+use crate::querying::DynPredicate;
+use crate::Error;
+use std::collections::HashMap;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A named placeholder for an index look-up in a query template.
+///
+/// A `Param` stands in for a value that isn't known when the query is built. For example, a query
+/// for "all creatures in habitat `h` that are not species `s`" can be planned once and then bound
+/// to `Habitat("Desert")` and `Species("Scorpion")` later.
+///
+/// Placeholders are created using [`Query::param`] or [`Param::new`], and bound using
+/// [`PreparedQuery::bind`]. Executing a query with an unbound placeholder returns an
+/// [`Error::UnboundParameter`] error.
+pub struct Param<V> {
+    /// The placeholder's name. For example: `"h"`.
+    name: &'static str,
+
+    /// The type name of the index look-up this placeholder expects. For example: `Habitat`.
+    lookup_type: &'static str,
+
+    /// Used to track the type of the primary record `V` value.
+    phantom_data: std::marker::PhantomData<V>,
+}
+
+impl<V> Param<V> {
+    /// Instantiates a new placeholder named `name` that expects an `I` index look-up.
+    #[must_use]
+    pub fn new<I: IndexLookup<Record = V>>(name: &'static str) -> Self {
+        Self {
+            name,
+            lookup_type: std::any::type_name::<I>(),
+            phantom_data: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<V: HasTable> IndexLookup for Param<V> {
+    type Record = V;
+
+    /// Placeholders don't know their index until they're bound. Returns the expected look-up type.
+    fn index_name(&self) -> &'static str {
+        self.lookup_type
+    }
+
+    /// Placeholders don't know their index until they're bound.
+    fn index_kind(&self) -> &IndexKind {
+        &IndexKind::NonUnique
+    }
+
+    /// Always fails. An unbound placeholder has no key to look-up.
+    fn index_key_bytes(&self) -> Result<Vec<u8>, Error> {
+        Err(Error::UnboundParameter { name: self.name })
+    }
+
+    /// Formats the placeholder as `$name`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "${}", self.name)
+    }
+
+    /// Returns the placeholder's name and expected look-up type.
+    fn parameter(&self) -> Option<(&'static str, &'static str)> {
+        Some((self.name, self.lookup_type))
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A bound value for a placeholder, stored in serialized form so that it can be reused for every
+/// execution of the query.
+#[allow(clippy::struct_field_names, reason = "mirrors the fields of `PreparedIndexLookup`")]
+struct Binding {
+    index_name: &'static str,
+    index_kind: IndexKind,
+    index_key_bytes: Vec<u8>,
+}
+
+impl Binding {
+    /// Creates a fresh index look-up from the binding.
+    fn to_lookup<V: HasTable + 'static>(&self) -> Box<DynLookup<V>> {
+        Box::new(PreparedIndexLookup::<V>::new(
+            self.index_name,
+            self.index_kind,
+            self.index_key_bytes.clone(),
+        ))
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A query plan that's built once and can be executed many times with different parameter values.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let mut prepared = Query::param::<Habitat>("h")
+///     .without(Param::new::<Species>("s"))
+///     .prepare();
+///
+/// prepared
+///     .bind("h", Habitat("Desert".into()))?
+///     .bind("s", Species("Scorpion".into()))?;
+///
+/// let keys = txn.query::<u64, Creature>(prepared.to_query()?)?;
+///
+/// prepared.bind("h", Habitat("Tide Pool".into()))?;
+/// let keys = txn.query::<u64, Creature>(prepared.to_query()?)?;
+/// ```
+pub struct PreparedQuery<V: HasTable> {
+    /// The query template, possibly containing [`Param`] placeholders.
+    plan: Query<V>,
+
+    /// Values bound to the template's placeholders, keyed by placeholder name.
+    bindings: HashMap<&'static str, Binding>,
+
+    /// The template's custom predicate, if any, shared so that every executable query instantiated
+    /// from the template can evaluate it.
+    #[cfg(feature = "custom-queries")]
+    predicate: Option<std::sync::Arc<DynPredicate<V>>>,
+}
+
+impl<V: HasTable + 'static> PreparedQuery<V> {
+    /// Instantiates a new `PreparedQuery` from a query template.
+    #[must_use]
+    pub fn new(plan: Query<V>) -> Self {
+        #[cfg(feature = "custom-queries")]
+        let (plan, predicate) = share_predicate(plan);
+
+        Self {
+            plan,
+            bindings: HashMap::new(),
+            #[cfg(feature = "custom-queries")]
+            predicate,
+        }
+    }
+
+    /// Binds an index look-up to the named placeholder, replacing any previous binding.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::UnknownParameter`] if the plan has no placeholder with the given name.
+    ///
+    /// * Returns [`Error::ParameterTypeMismatch`] if the placeholder was declared with a different
+    ///   look-up type.
+    ///
+    /// * Returns an error if the look-up's key could not be serialized.
+    #[allow(clippy::needless_pass_by_value, reason = "look-ups are usually built in the call")]
+    pub fn bind<I>(&mut self, name: &'static str, index_lookup: I) -> Result<&mut Self, Error>
+    where
+        I: IndexLookup<Record = V>
+    {
+        let provided = std::any::type_name::<I>();
+        let Some(expected) = find_parameter(&self.plan, name) else {
+            return Err(Error::UnknownParameter { name });
+        };
+        if expected != provided {
+            return Err(Error::ParameterTypeMismatch { name, expected, provided });
+        }
+
+        self.bindings.insert(name, Binding {
+            index_name: index_lookup.index_name(),
+            index_kind: *index_lookup.index_kind(),
+            index_key_bytes: index_lookup.index_key_bytes()?,
+        });
+
+        Ok(self)
+    }
+
+    /// Removes all bound values. The query plan is retained.
+    pub fn clear_bindings(&mut self) {
+        self.bindings.clear();
+    }
+
+    /// Instantiates an executable query from the plan, substituting every placeholder with its
+    /// bound value.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::UnboundParameter`] if a placeholder in the plan has no bound value.
+    ///
+    /// * Returns `Error::UnsharedPredicate` if the plan's custom predicate wasn't shared when the
+    ///   query was prepared.
+    ///
+    /// * Returns an error if a fixed look-up's key in the plan could not be serialized.
+    pub fn to_query(&self) -> Result<Query<V>, Error> {
+        self.instantiate(&self.plan)
+    }
+
+    /// Recursively copies the query tree, resolving placeholders along the way.
+    fn instantiate(&self, query: &Query<V>) -> Result<Query<V>, Error> {
+        Ok(match query {
+            Query::Lookup(lookup) => Query::Lookup(self.resolve(lookup.as_ref())?),
+            Query::Not(lookup) => Query::Not(self.resolve(lookup.as_ref())?),
+            Query::And(lhs, rhs) =>
+                Query::And(Box::new(self.instantiate(lhs)?), self.resolve(rhs.as_ref())?),
+            Query::Difference(lhs, rhs) =>
+                Query::Difference(Box::new(self.instantiate(lhs)?), self.resolve(rhs.as_ref())?),
+            Query::Or(lhs, rhs) =>
+                Query::Or(Box::new(self.instantiate(lhs)?), self.resolve(rhs.as_ref())?),
+            Query::Xor(lhs, rhs) =>
+                Query::Xor(Box::new(self.instantiate(lhs)?), self.resolve(rhs.as_ref())?),
+            Query::Group(inner) => Query::Group(Box::new(self.instantiate(inner)?)),
+            Query::AnyOf(multi) => Query::AnyOf(Box::new(copy_multi_lookup(multi.as_ref())?)),
+            Query::NotIn(multi) => Query::NotIn(Box::new(copy_multi_lookup(multi.as_ref())?)),
+            Query::StartsWith(string_match) => Query::StartsWith(string_match.clone()),
+            Query::Contains(string_match) => Query::Contains(string_match.clone()),
+            Query::TopK(top_k) => Query::TopK(top_k.clone()),
+            #[cfg(feature = "custom-queries")]
+            Query::Custom(_) => {
+                let predicate =
+                    std::sync::Arc::clone(self.predicate.as_ref().ok_or(Error::UnsharedPredicate)?);
+                Query::Custom(Box::new(move |record: &V| predicate(record)))
+            },
+        })
+    }
+
+    /// Resolves a single look-up. Placeholders are replaced with their bound values, and fixed
+    /// look-ups are copied into a [`PreparedIndexLookup`].
+    fn resolve(&self, lookup: &DynLookup<V>) -> Result<Box<DynLookup<V>>, Error> {
+        match lookup.parameter() {
+            Some((name, _lookup_type)) => self.bindings
+                .get(name)
+                .map(Binding::to_lookup)
+                .ok_or(Error::UnboundParameter { name }),
+            None => Ok(Box::new(PreparedIndexLookup::<V>::new(
+                lookup.index_name(),
+                *lookup.index_kind(),
+                lookup.index_key_bytes()?,
+            ))),
+        }
+    }
+}
+
+impl<V: HasTable> Query<V> {
+    /// Starts a query template with a named placeholder for an `I` index look-up.
+    ///
+    /// The placeholder is bound to a value later using [`PreparedQuery::bind`].
+    #[must_use]
+    pub fn param<I>(name: &'static str) -> Self
+    where
+        I: IndexLookup<Record = V>,
+        V: 'static
+    {
+        Self::Lookup(Box::new(Param::<V>::new::<I>(name)))
+    }
+
+    /// Converts this query into a reusable [`PreparedQuery`] template.
+    #[must_use]
+    pub fn prepare(self) -> PreparedQuery<V>
+    where
+        V: 'static
+    {
+        PreparedQuery::new(self)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Helpers
+
+/// Finds the expected look-up type of the named placeholder, if it's present in the query tree.
+fn find_parameter<V: HasTable>(query: &Query<V>, name: &str) -> Option<&'static str> {
+    let matches = |lookup: &DynLookup<V>| lookup
+        .parameter()
+        .filter(|(param_name, _)| *param_name == name)
+        .map(|(_, lookup_type)| lookup_type);
+
+    match query {
+        Query::Lookup(lookup) | Query::Not(lookup) => matches(lookup.as_ref()),
+        Query::And(lhs, rhs)
+        | Query::Difference(lhs, rhs)
+        | Query::Or(lhs, rhs)
+        | Query::Xor(lhs, rhs) => find_parameter(lhs, name).or_else(|| matches(rhs.as_ref())),
+        Query::Group(inner) => find_parameter(inner, name),
+        _ => None,
+    }
+}
+
+/// Moves the query tree's custom predicate, if any, behind an `Arc` so that it can be shared by
+/// every query instantiated from a [`PreparedQuery`].
+///
+/// A query tree has exactly one leaf query, so there is at most one custom predicate.
+#[cfg(feature = "custom-queries")]
+fn share_predicate<V: HasTable + 'static>(
+    query: Query<V>
+) -> (Query<V>, Option<std::sync::Arc<DynPredicate<V>>>) {
+    match query {
+        Query::Custom(predicate) => {
+            let predicate: std::sync::Arc<DynPredicate<V>> = predicate.into();
+            let shared = std::sync::Arc::clone(&predicate);
+            (Query::Custom(Box::new(move |record: &V| shared(record))), Some(predicate))
+        },
+        Query::And(lhs, rhs) => {
+            let (lhs, predicate) = share_predicate(*lhs);
+            (Query::And(Box::new(lhs), rhs), predicate)
+        },
+        Query::Difference(lhs, rhs) => {
+            let (lhs, predicate) = share_predicate(*lhs);
+            (Query::Difference(Box::new(lhs), rhs), predicate)
+        },
+        Query::Or(lhs, rhs) => {
+            let (lhs, predicate) = share_predicate(*lhs);
+            (Query::Or(Box::new(lhs), rhs), predicate)
+        },
+        Query::Xor(lhs, rhs) => {
+            let (lhs, predicate) = share_predicate(*lhs);
+            (Query::Xor(Box::new(lhs), rhs), predicate)
+        },
+        Query::Group(inner) => {
+            let (inner, predicate) = share_predicate(*inner);
+            (Query::Group(Box::new(inner)), predicate)
+        },
+        query => (query, None),
+    }
+}
+
+/// Copies a multi-value look-up into a `Vec` of [`PreparedIndexLookup`]s.
+fn copy_multi_lookup<V: HasTable + 'static>(
+    multi: &DynMultiLookup<V>
+) -> Result<Vec<PreparedIndexLookup<V>>, Error> {
+    let index_name = multi.index_name().ok_or(Error::MissingIndexTableName)?;
+    let index_kind = *multi.index_kind().ok_or(Error::MissingIndexKind)?;
+    Ok(multi
+        .to_key_set()?
+        .into_iter()
+        .map(|index_key_bytes| PreparedIndexLookup::new(index_name, index_kind, index_key_bytes))
+        .collect())
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexing::{Creature, Habitat, Species};
+
+    fn prepared() -> PreparedQuery<Creature> {
+        Query::param::<Habitat>("h").without(Param::new::<Species>("s")).prepare()
+    }
+
+    #[test]
+    fn binding_an_unknown_name_fails() {
+        let mut prepared = prepared();
+        let result = prepared.bind("x", Habitat("Desert".into())).map(drop);
+        assert!(matches!(result, Err(Error::UnknownParameter { name: "x" })));
+    }
+
+    #[test]
+    fn binding_the_wrong_type_fails() {
+        let mut prepared = prepared();
+        let result = prepared.bind("h", Species("Scorpion".into())).map(drop);
+        assert!(matches!(result, Err(Error::ParameterTypeMismatch { name: "h", .. })));
+        assert!(matches!(prepared.to_query(), Err(Error::UnboundParameter { .. })));
+    }
+
+    #[test]
+    fn rebinding_replaces_the_value() {
+        let mut prepared = prepared();
+        prepared
+            .bind("h", Habitat("Desert".into())).unwrap()
+            .bind("s", Species("Scorpion".into())).unwrap()
+            .bind("h", Habitat("Tide Pool".into())).unwrap();
+
+        let Query::Difference(lhs, _) = prepared.to_query().unwrap() else {
+            panic!("expected a difference");
+        };
+        let Query::Lookup(habitat) = *lhs else { panic!("expected a look-up") };
+        assert_eq!(
+            habitat.index_key_bytes().unwrap(),
+            Habitat("Tide Pool".into()).index_key_bytes().unwrap(),
+        );
+    }
+
+    #[cfg(feature = "custom-queries")]
+    #[test]
+    fn custom_predicates_are_shared_by_every_query() {
+        let prepared = Query::<Creature>::custom(|_| Ok(true))
+            .and(Habitat("Desert".into()))
+            .prepare();
+        for _ in 0..2 {
+            let Query::And(lhs, _) = prepared.to_query().unwrap() else {
+                panic!("expected an intersection");
+            };
+            assert!(matches!(*lhs, Query::Custom(_)));
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! String pattern look-ups over the keys of a secondary index: prefix matches and substring
+//! matches.
+
+use crate::indexing::{HasTable, KeyNormalization};
+use crate::querying::Query;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A string pattern to be matched against the keys of a secondary index.
+///
+/// For example, `StringMatch { index_name: "creatures_by_species", pattern: "Sea ".into() }`
+/// could be used to find `"Sea Star"`, `"Sea Otter"`, and `"Sea Urchin"`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StringMatch {
+    /// Name of the secondary index table whose keys are matched.
+    pub index_name: &'static str,
+
+    /// The prefix or substring to match. Matching is case-sensitive, unless the index's keys and
+    /// the pattern are normalized. See [`StringMatch::normalized`].
+    pub pattern: String,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl StringMatch {
+    /// Instantiates a new `StringMatch`.
+    #[must_use]
+    pub fn new(index_name: &'static str, pattern: impl Into<String>) -> Self {
+        Self { index_name, pattern: pattern.into() }
+    }
+
+    /// Normalizes the pattern the same way as the index's keys. For example, if the index's keys
+    /// are lowercased, `"Sea "` becomes `"sea "` and matches a stored `"sea star"`.
+    #[must_use]
+    pub fn normalized(mut self, normalization: KeyNormalization) -> Self {
+        self.pattern = normalization.apply(&self.pattern).into_owned();
+        self
+    }
+
+    /// Returns `true` if the index key starts with the pattern.
+    #[must_use]
+    pub fn is_prefix_of(&self, index_key: &str) -> bool {
+        index_key.starts_with(self.pattern.as_str())
+    }
+
+    /// Returns `true` if the index key contains the pattern anywhere.
+    #[must_use]
+    pub fn is_substring_of(&self, index_key: &str) -> bool {
+        index_key.contains(self.pattern.as_str())
+    }
+}
+
+impl<V: HasTable> Query<V> {
+    /// Matches records whose indexed string field starts with `prefix`.
+    ///
+    /// This query is index-accelerated: only the keys of the `index_name` index table are
+    /// examined, and primary records are never loaded. For example,
+    /// `Query::starts_with("creatures_by_species", "Sea ")` would match `"Sea Star"` and
+    /// `"Sea Otter"`.
+    #[must_use]
+    pub fn starts_with(index_name: &'static str, prefix: impl Into<String>) -> Self {
+        Self::StartsWith(StringMatch::new(index_name, prefix))
+    }
+
+    /// Matches records whose indexed string field contains `substring` anywhere.
+    ///
+    /// **Warning**: A substring can't be located using the index's ordering, so every key in the
+    /// `index_name` index table is decoded and checked. This is reported as a post-filter scan by
+    /// [`Query::explain`]. Prefer [`Query::starts_with`] where possible.
+    #[must_use]
+    pub fn contains(index_name: &'static str, substring: impl Into<String>) -> Self {
+        Self::Contains(StringMatch::new(index_name, substring))
+    }
+}
@@ -0,0 +1,62 @@
+//! Top-K selection: the first `k` records in the order of a secondary index, found by walking the
+//! index from one end rather than scanning and sorting the whole table.
+
+use crate::indexing::HasTable;
+use crate::querying::Query;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The end of an index that a top-K selection starts walking from.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SortDirection {
+    /// Smallest secondary keys first. For example: the oldest records by a timestamp index.
+    #[default]
+    Ascending,
+
+    /// Largest secondary keys first. For example: the newest records by a timestamp index.
+    Descending,
+}
+
+/// Selects the first `k` primary keys in the order of a non-unique secondary index.
+///
+/// Primary keys that share a secondary key are taken in primary key order, in the same direction,
+/// so the selection is deterministic. Each primary key is counted once, even if the record appears
+/// under more than one secondary key.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TopK {
+    /// Name of the non-unique secondary index table to walk. For example: `"posts_by_created_at"`.
+    pub index_name: &'static str,
+
+    /// The maximum number of primary keys to select.
+    pub k: usize,
+
+    /// The end of the index to start from.
+    pub direction: SortDirection,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl TopK {
+    /// Instantiates a new `TopK` selection.
+    #[must_use]
+    pub const fn new(index_name: &'static str, k: usize, direction: SortDirection) -> Self {
+        Self { index_name, k, direction }
+    }
+}
+
+impl<V: HasTable> Query<V> {
+    /// Matches the first `k` records in the order of a secondary index.
+    ///
+    /// The index is walked from one end and iteration stops once `k` distinct primary keys have
+    /// been found, so `Query::top_k("posts_by_created_at", 10, SortDirection::Descending)` finds
+    /// the ten newest posts without a full scan.
+    ///
+    /// Like every query, this returns an unordered set of primary keys. Use the read
+    /// transaction's `top_k` method to get the records back in index order.
+    #[must_use]
+    pub const fn top_k(index_name: &'static str, k: usize, direction: SortDirection) -> Self {
+        Self::TopK(TopK::new(index_name, k, direction))
+    }
+}
@@ -0,0 +1,141 @@
+//! Index-aware bulk updates of the records matching a query.
+
+use crate::defaults::Defaults;
+use crate::indexing::{HasTable, Indexable};
+use crate::querying::Query;
+use crate::typed::transaction::WriteTransaction;
+use crate::validation::Validate;
+use crate::{Codec, Error};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A record that carries a generation number, which is incremented every time it's updated.
+///
+/// Generations are used for optimistic concurrency: a caller remembers the generation of each
+/// record it read, and an update is refused if the record has since moved on to a newer
+/// generation.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// impl Versioned for Creature {
+///     fn generation(&self) -> u64 { self.generation }
+///     fn set_generation(&mut self, generation: u64) { self.generation = generation; }
+/// }
+/// ```
+pub trait Versioned {
+    /// Returns the record's current generation.
+    fn generation(&self) -> u64;
+
+    /// Sets the record's generation.
+    fn set_generation(&mut self, generation: u64);
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The outcome of an `update_each` call.
+#[derive(Debug)]
+pub struct UpdateReport<K> {
+    /// The number of records that were written back.
+    pub updated: u64,
+
+    /// The records that were left unchanged because their update failed, with the reason. For
+    /// example, the closure returned an error, the updated record failed validation, or its
+    /// generation didn't match.
+    pub failures: Vec<(K, Error)>,
+}
+
+impl<K> UpdateReport<K> {
+    /// Returns `true` if every matching record was updated.
+    #[must_use]
+    pub const fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<V: HasTable> Query<V> {
+    /// Applies a closure to every record matching the query, inside the given write transaction,
+    /// and writes each changed record back.
+    ///
+    /// Each updated record has its [`Defaults`] applied and its [`Validate`] rules checked, and
+    /// only the secondary index entries whose keys changed are rewritten. A record whose update
+    /// fails is left unchanged and reported in [`UpdateReport::failures`], while the remaining
+    /// records are still updated.
+    ///
+    /// Changes to a record's primary key field are ignored: the record is written back under the
+    /// primary key it was read from.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let mut txn = db.write()?;
+    /// let report = Query::lookup(Habitat("Tide Pool".into())).update_each::<u64>(
+    ///     &mut txn,
+    ///     |creature| { creature.habitat = "Rock Pool".into(); Ok(()) }
+    /// )?;
+    /// txn.commit()?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * See [`WriteTransaction::update_matching`]. Per-record failures are returned in the report
+    ///   rather than as an error.
+    pub fn update_each<K>(
+        self,
+        txn: &mut WriteTransaction,
+        update: impl FnMut(&mut V) -> Result<(), Error>,
+    ) -> Result<UpdateReport<K>, Error>
+    where
+        K: Codec<K>,
+        V: for<'i> Indexable<'i> + Defaults + Validate + Codec<V>,
+    {
+        txn.update_matching::<K, V>(self, update, |_key, _record| Ok(()))
+    }
+
+    /// Like [`Query::update_each`], but each record is only updated if its generation still
+    /// matches the generation the caller expects, and its generation is incremented when it's
+    /// written back.
+    ///
+    /// Records with no expected generation are updated unconditionally. Records whose generation
+    /// doesn't match are reported with [`Error::GenerationMismatch`].
+    ///
+    /// # Errors
+    ///
+    /// * See [`WriteTransaction::update_matching`]. Per-record failures are returned in the report
+    ///   rather than as an error.
+    pub fn update_each_checked<K>(
+        self,
+        txn: &mut WriteTransaction,
+        expected_generations: &HashMap<K, u64>,
+        mut update: impl FnMut(&mut V) -> Result<(), Error>,
+    ) -> Result<UpdateReport<K>, Error>
+    where
+        K: Codec<K> + Eq + Hash,
+        V: for<'i> Indexable<'i> + Defaults + Validate + Versioned + Codec<V>,
+    {
+        txn.update_matching::<K, V>(
+            self,
+            |record| {
+                update(record)?;
+                record.set_generation(record.generation().wrapping_add(1));
+                Ok(())
+            },
+            |key, record| match expected_generations.get(key) {
+                Some(&expected) if expected != record.generation() =>
+                    Err(Error::GenerationMismatch {
+                        table: V::table_name(),
+                        key: K::serialize(key)?,
+                        expected,
+                        found: record.generation(),
+                    }),
+                _ => Ok(()),
+            },
+        )
+    }
+}
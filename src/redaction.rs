@@ -0,0 +1,447 @@
+//! Redaction hooks that mask or drop sensitive fields from records before they leave the database
+//! through an export, backup, or dump.
+//!
+//! A [`Redactor`] is written once per record type and registered with a [`RedactionPolicy`]. The
+//! policy is set once on the database with `Database::set_redaction_policy`, and applied by every
+//! export path: `Database::backup_to`, and the read transaction's `export` and `export_jsonl`
+//! methods. Operational dumps can then be shared with support or analytics without each call site
+//! having to remember which fields contain personal information.
+//!
+//! The policy also covers `Database::backup_incremental`, and so the server's `/changes` endpoint,
+//! whose changes are redacted as they're written, and the server's `/query` endpoint.
+
+use crate::indexing::{HasTable, IndexKeyBytes, Indexable, shard_table_name};
+use crate::typed::Namespace;
+use crate::typed::audit::AUDIT_LOG_TABLE_NAME;
+use crate::typed::change_log::{CHANGE_LOG_TABLE_NAME, Change};
+use crate::typed::record_layers::RecordContext;
+use crate::{Codec, Error};
+use std::any::{Any, TypeId};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Redacts one stored record of a table, given its primary key, returning the stored result. The
+/// full names of the secondary index tables the record appears in are added to the set.
+type TableRedactor = Box<
+    dyn Fn(&RecordContext, &[u8], &[u8], &mut BTreeSet<String>) -> Result<Redacted<Vec<u8>>, Error>
+        + Send
+        + Sync
+>;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The outcome of redacting a single record.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Redacted<V> {
+    /// The record should be exported. Its sensitive fields may have been masked.
+    Keep(V),
+
+    /// The record should be omitted from the export entirely.
+    Drop,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Masks or drops sensitive fields from a record of type `V` before it's exported.
+///
+/// # Example
+///
+/// ```rust
+/// use atlatl::redaction::{mask_email, Redacted, Redactor};
+///
+/// struct User { email: String, is_test_account: bool }
+///
+/// struct UserRedactor;
+///
+/// impl Redactor<User> for UserRedactor {
+///     fn redact(&self, mut user: User) -> Redacted<User> {
+///         if user.is_test_account { return Redacted::Drop; }
+///         user.email = mask_email(&user.email);
+///         Redacted::Keep(user)
+///     }
+/// }
+/// ```
+pub trait Redactor<V>: Send + Sync {
+    /// Redacts a single record.
+    fn redact(&self, record: V) -> Redacted<V>;
+}
+
+impl<V, F> Redactor<V> for F
+where
+    F: Fn(V) -> Redacted<V> + Send + Sync
+{
+    /// Allows plain closures and functions to be used as redactors.
+    fn redact(&self, record: V) -> Redacted<V> {
+        self(record)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A central registry of [`Redactor`]s, one per record type.
+///
+/// Record types without a registered redactor are passed through unchanged, unless the policy was
+/// created with [`RedactionPolicy::deny_unregistered`], in which case they're dropped.
+///
+/// Backups and archives copy tables without knowing their record types, so they only redact the
+/// tables of redactors registered with [`RedactionPolicy::with_table`]. Every other table is copied
+/// as it is, or left out under `deny_unregistered`.
+///
+/// Secondary index, reverse index, and history tables hold copies of record fields, so they're
+/// left out of redacted backups and archives for every table registered with `with_table`. Restore
+/// them with `repair_indexes` or `rebuild_index` after restoring the backup. The audit log is left
+/// out too, since it names who wrote each record. The change log is kept, with each change
+/// redacted like the record it wrote, so that incremental backups can still be replayed on top.
+#[derive(Default)]
+pub struct RedactionPolicy {
+    /// Registered redactors. Each value is an `Arc<dyn Redactor<V>>` for the `V` of its `TypeId`.
+    redactors: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+
+    /// Redactors registered with `with_table`, by unqualified primary table name.
+    tables: HashMap<&'static str, TableRedactor>,
+
+    /// History and reverse index tables of the tables registered with `with_table`, by unqualified
+    /// name.
+    derived_tables: HashSet<&'static str>,
+
+    /// Drop records of types that have no registered redactor.
+    deny_unregistered: bool,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl RedactionPolicy {
+    /// Instantiates an empty policy that passes through unregistered record types.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Instantiates an empty policy that drops records of every type that has no registered
+    /// redactor.
+    ///
+    /// This is the safer choice for dumps leaving the organization: a newly added record type will
+    /// not be exported until someone has decided how it should be redacted.
+    #[must_use]
+    pub fn deny_unregistered() -> Self {
+        Self { deny_unregistered: true, ..Self::default() }
+    }
+
+    /// Registers the redactor for record type `V`, replacing any previously registered redactor.
+    #[must_use]
+    pub fn with<V: 'static>(mut self, redactor: impl Redactor<V> + 'static) -> Self {
+        self.register(redactor);
+        self
+    }
+
+    /// Registers the redactor for record type `V`, replacing any previously registered redactor.
+    pub fn register<V: 'static>(&mut self, redactor: impl Redactor<V> + 'static) {
+        let redactor: Arc<dyn Redactor<V>> = Arc::new(redactor);
+        self.redactors.insert(TypeId::of::<V>(), Box::new(redactor));
+    }
+
+    /// Registers the redactor for record type `V`, and for the records of its primary table as
+    /// they're copied by backups and archives.
+    #[must_use]
+    pub fn with_table<V>(mut self, redactor: impl Redactor<V> + 'static) -> Self
+    where
+        V: for<'i> Indexable<'i> + Codec<V> + HasTable + 'static,
+    {
+        self.register_table(redactor);
+        self
+    }
+
+    /// Registers the redactor for record type `V`, and for the records of its primary table as
+    /// they're copied by backups and archives.
+    pub fn register_table<V>(&mut self, redactor: impl Redactor<V> + 'static)
+    where
+        V: for<'i> Indexable<'i> + Codec<V> + HasTable + 'static,
+    {
+        let redactor: Arc<dyn Redactor<V>> = Arc::new(redactor);
+        let table_redactor = Arc::clone(&redactor);
+        let table_redactor = move |
+            context: &RecordContext,
+            primary_key: &[u8],
+            bytes: &[u8],
+            index_tables: &mut BTreeSet<String>,
+        | {
+            let record: V = context.open(primary_key, bytes)?;
+
+            // Index tables are named without the namespace prefix of the primary table:
+            let prefix = context.table_name().strip_suffix(V::table_name()).unwrap_or_default();
+            for index_key in IndexKeyBytes::of(&record)? {
+                index_tables.insert(format!("{prefix}{}", index_key.index_name));
+                index_tables.insert(format!("{prefix}{}", shard_table_name(index_key.index_name)));
+                if let Some(covering) = index_key.covering {
+                    index_tables.insert(format!("{prefix}{}", covering.table_name));
+                }
+            }
+
+            match table_redactor.redact(record) {
+                Redacted::Keep(record) =>
+                    Ok(Redacted::Keep(context.seal(primary_key, &record, None)?)),
+                Redacted::Drop => Ok(Redacted::Drop),
+            }
+        };
+
+        self.tables.insert(V::table_name(), Box::new(table_redactor));
+        self.derived_tables.extend(V::history_table_name());
+        self.derived_tables.extend(V::reverse_index_name());
+        self.redactors.insert(TypeId::of::<V>(), Box::new(redactor));
+    }
+
+    /// Returns `true` if a redactor has been registered for record type `V`.
+    #[must_use]
+    pub fn is_registered<V: 'static>(&self) -> bool {
+        self.redactors.contains_key(&TypeId::of::<V>())
+    }
+
+    /// Applies the registered redactor for record type `V` to a record.
+    #[must_use]
+    pub fn apply<V: 'static>(&self, record: V) -> Redacted<V> {
+        match self
+            .redactors
+            .get(&TypeId::of::<V>())
+            .and_then(|redactor| redactor.downcast_ref::<Arc<dyn Redactor<V>>>())
+        {
+            Some(redactor) => redactor.redact(record),
+            None if self.deny_unregistered => Redacted::Drop,
+            None => Redacted::Keep(record),
+        }
+    }
+
+    /// Returns `true` if backups and archives should copy the table with the given name, which may
+    /// be qualified by a namespace. For example, `tenant42.creatures`.
+    ///
+    /// The audit log, the change log, and the history and reverse index tables of redacted tables
+    /// aren't copied. Backups copy the change log with [`RedactionPolicy::redact_change`] instead.
+    /// Secondary index tables are only known once their records have been redacted, see
+    /// [`RedactionPolicy::apply_to_table`].
+    #[must_use]
+    pub(crate) fn keeps_table(&self, table_name: &str) -> bool {
+        if table_name == AUDIT_LOG_TABLE_NAME || table_name == CHANGE_LOG_TABLE_NAME {
+            return false;
+        }
+        if self.derived_tables.iter().any(|name| names_table(table_name, name)) {
+            return false;
+        }
+        !self.deny_unregistered || self.table_redactor(table_name).is_some()
+    }
+
+    /// Returns `true` if a redactor was registered with `with_table` for the table with the given
+    /// name, which may be qualified by a namespace. Backups and archives copy these tables first,
+    /// so that the secondary index tables of their records are known before any other table is
+    /// copied.
+    #[must_use]
+    pub(crate) fn redacts_table(&self, table_name: &str) -> bool {
+        self.table_redactor(table_name).is_some()
+    }
+
+    /// Applies the redactor registered for a table to one of its stored records, as it's copied
+    /// by a backup or archive. Records of tables without a registered redactor are kept as they
+    /// are, unless the policy denies unregistered types.
+    ///
+    /// Records with record layers are decoded in the given context, and the redacted copy is
+    /// encrypted again with a random nonce. The full names of the secondary index tables the record
+    /// appears in are added to `index_tables`, and must be left out of the copy.
+    ///
+    /// # Errors
+    ///
+    /// * Decoding the record, or encoding the redacted record, fails.
+    pub(crate) fn apply_to_table<'v>(
+        &self,
+        context: &RecordContext,
+        primary_key: &[u8],
+        value: &'v [u8],
+        index_tables: &mut BTreeSet<String>,
+    ) -> Result<Redacted<Cow<'v, [u8]>>, Error> {
+        match self.table_redactor(context.table_name()) {
+            Some(redactor) => Ok(match redactor(context, primary_key, value, index_tables)? {
+                Redacted::Keep(value) => Redacted::Keep(Cow::Owned(value)),
+                Redacted::Drop => Redacted::Drop,
+            }),
+            None if self.deny_unregistered => Ok(Redacted::Drop),
+            None => Ok(Redacted::Keep(Cow::Borrowed(value))),
+        }
+    }
+
+    /// Redacts a change from the change log, as it's copied by a backup or written to an
+    /// incremental backup. `records` is the context of the database's records, for any table.
+    ///
+    /// A written record is redacted like [`RedactionPolicy::apply_to_table`] redacts it, and a
+    /// record the policy drops becomes a deletion. A change to a table the policy leaves out
+    /// becomes a deletion without a key, so that the change's sequence number is still accounted
+    /// for. The change loses its link in the log's hash chain, since its value may no longer match
+    /// it.
+    ///
+    /// # Errors
+    ///
+    /// * Decoding the record, or encoding the redacted record, fails.
+    pub(crate) fn redact_change(
+        &self,
+        records: &RecordContext,
+        change: Change,
+    ) -> Result<Change, Error> {
+        if !self.keeps_table(&change.table) {
+            return Ok(Change { key: Vec::new(), value: None, chain: None, ..change });
+        }
+
+        let context = records.for_table(change.table.as_str());
+        let value = match &change.value {
+            Some(value) => match self.apply_to_table(
+                &context,
+                &change.key,
+                value,
+                &mut BTreeSet::new(),
+            )? {
+                Redacted::Keep(value) => Some(value.into_owned()),
+                Redacted::Drop => None,
+            },
+            None => None,
+        };
+        Ok(Change { value, chain: None, ..change })
+    }
+
+    /// Returns the redactor registered for a table, whose name may be qualified by a namespace.
+    fn table_redactor(&self, table_name: &str) -> Option<&TableRedactor> {
+        self.tables.get(table_name).or_else(|| {
+            self.tables
+                .iter()
+                .find_map(|(name, redactor)| names_table(table_name, name).then_some(redactor))
+        })
+    }
+}
+
+impl std::fmt::Debug for RedactionPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedactionPolicy")
+            .field("registered", &self.redactors.len())
+            .field("tables", &self.tables.len())
+            .field("derived_tables", &self.derived_tables)
+            .field("deny_unregistered", &self.deny_unregistered)
+            .finish()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Returns `true` if a table name, which may be qualified by a namespace, names the table with
+/// the given unqualified name.
+fn names_table(table_name: &str, unqualified: &str) -> bool {
+    table_name.strip_suffix(unqualified).is_some_and(|prefix| {
+        prefix.is_empty() || prefix.ends_with(Namespace::SEPARATOR)
+    })
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Masking Helpers
+
+/// Replaces every character in a string with `*`, preserving its length in characters.
+#[must_use]
+pub fn mask(value: &str) -> String {
+    "*".repeat(value.chars().count())
+}
+
+/// Masks the local part of an email address, keeping its first character and the domain. For
+/// example, `jane@example.com` becomes `j***@example.com`.
+///
+/// Strings without an `@` are masked entirely.
+#[must_use]
+pub fn mask_email(email: &str) -> String {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            let mut chars = local.chars();
+            chars.next().map_or_else(
+                || format!("@{domain}"),
+                |first| format!("{first}{}@{domain}", "*".repeat(chars.count())),
+            )
+        },
+        None => mask(email),
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct User {
+        name: String,
+        email: String,
+        internal: bool,
+    }
+
+    struct UserRedactor;
+
+    impl Redactor<User> for UserRedactor {
+        fn redact(&self, mut user: User) -> Redacted<User> {
+            if user.internal {
+                return Redacted::Drop;
+            }
+            user.email = mask_email(&user.email);
+            Redacted::Keep(user)
+        }
+    }
+
+    fn user(internal: bool) -> User {
+        User { name: "Jane".into(), email: "jane@example.com".into(), internal }
+    }
+
+    #[test]
+    fn registered_redactor_masks_fields() {
+        let policy = RedactionPolicy::new().with(UserRedactor);
+        assert_eq!(
+            policy.apply(user(false)),
+            Redacted::Keep(User {
+                name: "Jane".into(),
+                email: "j***@example.com".into(),
+                internal: false
+            })
+        );
+    }
+
+    #[test]
+    fn registered_redactor_drops_records() {
+        let policy = RedactionPolicy::new().with(UserRedactor);
+        assert_eq!(policy.apply(user(true)), Redacted::Drop);
+    }
+
+    #[test]
+    fn closures_are_redactors() {
+        let policy = RedactionPolicy::new().with(|_: u64| Redacted::Keep(0_u64));
+        assert_eq!(policy.apply(42_u64), Redacted::Keep(0));
+    }
+
+    #[test]
+    fn unregistered_types_pass_through_by_default() {
+        let policy = RedactionPolicy::new();
+        assert!(!policy.is_registered::<User>());
+        assert_eq!(policy.apply(user(false)), Redacted::Keep(user(false)));
+    }
+
+    #[test]
+    fn unregistered_types_are_dropped_when_denied() {
+        let policy = RedactionPolicy::deny_unregistered();
+        assert_eq!(policy.apply(user(false)), Redacted::Drop);
+    }
+
+    #[test]
+    fn masking_helpers() {
+        assert_eq!(mask("secret"), "******");
+        assert_eq!(mask_email("jane@example.com"), "j***@example.com");
+        assert_eq!(mask_email("@example.com"), "@example.com");
+        assert_eq!(mask_email("not-an-email"), "************");
+    }
+}
@@ -55,4 +55,5 @@ pub use redb::UntypedMultimapTableHandle;
 pub use redb::UntypedTableHandle;
 pub use redb::UpgradeError;
 pub use redb::Value;
-pub use redb::WriteTransaction;
\ No newline at end of file
+pub use redb::WriteTransaction;
+pub use redb::backends;
\ No newline at end of file
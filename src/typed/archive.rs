@@ -0,0 +1,408 @@
+//! A portable archive format for exporting and importing tables, independent of `redb`'s on-disk
+//! format.
+//!
+//! Archives let data be moved between `redb` versions, or a database be rebuilt from scratch. An
+//! archive is written with [`ReadTransaction::export`] and read back with
+//! [`WriteTransaction::import`].
+//!
+//! # Format
+//!
+//! All integers are little-endian, and all strings and byte strings are prefixed by their length
+//! as a `u32`.
+//!
+//! ```text
+//! header:  magic "ATLARCHV" | format version (u8) | atlatl version (string) | layer count (u32)
+//!          | layer feature (string) ...
+//! table:   TABLE (u8) | table name (string) | key kind (u8)
+//! entry:   ENTRY (u8) | key (bytes) | value (bytes)
+//! end:     END (u8)
+//! ```
+//!
+//! Each table is followed by its entries. The header's layer features record how the record bytes
+//! were serialized, compressed, encrypted, and protected: records are archived exactly as they're
+//! stored, so they can only be imported by a build with the same layers.
+//!
+//! [`ReadTransaction::export`]: crate::typed::transaction::ReadTransaction::export
+//! [`WriteTransaction::import`]: crate::typed::transaction::WriteTransaction::import
+
+use crate::Error;
+use std::io::Write;
+#[cfg(feature = "writes")]
+use std::io::Read;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Identifies an archive file.
+const MAGIC: &[u8; 8] = b"ATLARCHV";
+
+/// Version of the archive format.
+const FORMAT_VERSION: u8 = 1;
+
+/// Marks the start of a table.
+const TAG_TABLE: u8 = 1;
+
+/// Marks an entry of the current table.
+const TAG_ENTRY: u8 = 2;
+
+/// Marks the end of the archive.
+const TAG_END: u8 = 0;
+
+/// Helper macro: lists which of the given features are turned on.
+macro_rules! enabled_features {
+    ($($feat:literal),* $(,)?) => {
+        [$( cfg!(feature = $feat).then_some($feat) ),*]
+    };
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Selects the tables written to an archive by [`ReadTransaction::export`].
+///
+/// [`ReadTransaction::export`]: crate::typed::transaction::ReadTransaction::export
+#[derive(Clone, Copy, Debug)]
+pub enum ExportScope<'t> {
+    /// Only the named record tables. For example, the `TABLE_NAMES` generated by [`tables!`].
+    ///
+    /// Secondary indexes aren't archived, and must be rebuilt with `rebuild_index` after the
+    /// archive is imported. This keeps archives small, and independent of the index layout.
+    ///
+    /// [`tables!`]: crate::tables
+    Tables(&'t [&'t str]),
+
+    /// Every table in the transaction's namespace, including secondary indexes and `atlatl`'s
+    /// internal tables. The imported database is usable immediately, without rebuilding indexes.
+    Everything,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A summary of an archive, returned by both [`ReadTransaction::export`] and
+/// [`WriteTransaction::import`].
+///
+/// [`ReadTransaction::export`]: crate::typed::transaction::ReadTransaction::export
+/// [`WriteTransaction::import`]: crate::typed::transaction::WriteTransaction::import
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ArchiveSummary {
+    /// Version of `atlatl` that wrote the archive. For example: `"0.1.0"`.
+    pub atlatl_version: String,
+
+    /// Names of the archived tables, without their namespace prefix, in archive order.
+    pub tables: Vec<String>,
+
+    /// Number of archived entries, across all tables.
+    pub entries: u64,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// How a table's keys are typed in `redb`. Record and index tables have byte keys, but some of
+/// `atlatl`'s internal tables don't.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u8)]
+pub(crate) enum KeyKind {
+    /// `&[u8]` keys.
+    Bytes = 0,
+
+    /// `&str` keys.
+    Str = 1,
+
+    /// `u64` keys, archived as 8 little-endian bytes.
+    U64 = 2,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Writes an archive. Tables must be written one at a time: a table's entries follow it.
+pub(crate) struct ArchiveWriter<W: Write> {
+    writer: W,
+    summary: ArchiveSummary,
+}
+
+/// One item read from an archive.
+#[cfg(feature = "writes")]
+pub(crate) enum ArchiveItem {
+    /// The start of a table, whose entries follow.
+    Table { name: String, key_kind: KeyKind },
+
+    /// An entry of the most recent table.
+    Entry { key: Vec<u8>, value: Vec<u8> },
+}
+
+/// Reads an archive, one item at a time.
+#[cfg(feature = "writes")]
+pub(crate) struct ArchiveReader<R: Read> {
+    reader: R,
+    summary: ArchiveSummary,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<W: Write> ArchiveWriter<W> {
+    /// Writes the archive's header.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Io`] if the archive could not be written.
+    pub(crate) fn new(mut writer: W) -> Result<Self, Error> {
+        let atlatl_version = env!("CARGO_PKG_VERSION");
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[FORMAT_VERSION])?;
+        put_prefixed(&mut writer, atlatl_version.as_bytes())?;
+
+        let layers = layer_features();
+        writer.write_all(&len_u32(layers.len())?.to_le_bytes())?;
+        for layer in layers {
+            put_prefixed(&mut writer, layer.as_bytes())?;
+        }
+
+        let summary = ArchiveSummary {
+            atlatl_version: atlatl_version.to_string(),
+            ..Default::default()
+        };
+        Ok(Self { writer, summary })
+    }
+
+    /// Starts a table. The entries written next belong to it.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Io`] if the archive could not be written.
+    pub(crate) fn table(&mut self, name: &str, key_kind: KeyKind) -> Result<(), Error> {
+        self.writer.write_all(&[TAG_TABLE])?;
+        put_prefixed(&mut self.writer, name.as_bytes())?;
+        self.writer.write_all(&[key_kind as u8])?;
+        self.summary.tables.push(name.to_string());
+        Ok(())
+    }
+
+    /// Writes an entry of the current table.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Io`] if the archive could not be written.
+    pub(crate) fn entry(&mut self, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        self.writer.write_all(&[TAG_ENTRY])?;
+        put_prefixed(&mut self.writer, key)?;
+        put_prefixed(&mut self.writer, value)?;
+        self.summary.entries += 1;
+        Ok(())
+    }
+
+    /// Ends the archive, and returns its summary.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Io`] if the archive could not be written.
+    pub(crate) fn finish(mut self) -> Result<ArchiveSummary, Error> {
+        self.writer.write_all(&[TAG_END])?;
+        self.writer.flush()?;
+        Ok(self.summary)
+    }
+}
+
+#[cfg(feature = "writes")]
+impl<R: Read> ArchiveReader<R> {
+    /// Reads and checks the archive's header.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::MalformedArchive`] if this isn't an archive, or it's truncated.
+    ///
+    /// * Returns [`Error::ArchiveLayerMismatch`] if the archive was written by a build with
+    ///   different layers, whose record bytes this build can't read.
+    ///
+    /// * Returns [`Error::Io`] if the archive could not be read.
+    pub(crate) fn new(mut reader: R) -> Result<Self, Error> {
+        let mut magic = [0; MAGIC.len() + 1];
+        read_exact(&mut reader, &mut magic)?;
+        if magic[..MAGIC.len()] != *MAGIC || magic[MAGIC.len()] != FORMAT_VERSION {
+            return Err(Error::MalformedArchive {
+                reason: "not an archive, or an unsupported version",
+            });
+        }
+
+        let atlatl_version = take_string(&mut reader)?;
+        let mut layer_count = [0; 4];
+        read_exact(&mut reader, &mut layer_count)?;
+        let archived_layers = (0..u32::from_le_bytes(layer_count))
+            .map(|_| take_string(&mut reader))
+            .collect::<Result<Vec<String>, Error>>()?;
+
+        let active_layers = layer_features();
+        if archived_layers != active_layers {
+            return Err(Error::ArchiveLayerMismatch {
+                archived: archived_layers.join(", "),
+                active: active_layers.join(", "),
+            });
+        }
+
+        let summary = ArchiveSummary { atlatl_version, ..Default::default() };
+        Ok(Self { reader, summary })
+    }
+
+    /// Reads the next table or entry, or returns `None` at the end of the archive.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::MalformedArchive`] if the archive is truncated or malformed.
+    ///
+    /// * Returns [`Error::Io`] if the archive could not be read.
+    pub(crate) fn next_item(&mut self) -> Result<Option<ArchiveItem>, Error> {
+        let mut tag = [0];
+        read_exact(&mut self.reader, &mut tag)?;
+        match tag[0] {
+            TAG_TABLE => {
+                let name = take_string(&mut self.reader)?;
+                let mut key_kind = [0];
+                read_exact(&mut self.reader, &mut key_kind)?;
+                let key_kind = match key_kind[0] {
+                    0 => KeyKind::Bytes,
+                    1 => KeyKind::Str,
+                    2 => KeyKind::U64,
+                    _ => return Err(Error::MalformedArchive { reason: "unrecognized key kind" }),
+                };
+                self.summary.tables.push(name.clone());
+                Ok(Some(ArchiveItem::Table { name, key_kind }))
+            },
+            TAG_ENTRY => {
+                if self.summary.tables.is_empty() {
+                    return Err(Error::MalformedArchive { reason: "entry precedes its table" });
+                }
+                let key = take_prefixed(&mut self.reader)?;
+                let value = take_prefixed(&mut self.reader)?;
+                self.summary.entries += 1;
+                Ok(Some(ArchiveItem::Entry { key, value }))
+            },
+            TAG_END => Ok(None),
+            _ => Err(Error::MalformedArchive { reason: "unrecognized tag" }),
+        }
+    }
+
+    /// Returns the summary of everything read so far.
+    pub(crate) fn into_summary(self) -> ArchiveSummary {
+        self.summary
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Returns the enabled features that determine how stored bytes are encoded: the serializer,
+/// compressor, encryptor, error corrector, and key set implementation.
+pub(crate) fn layer_features() -> Vec<String> {
+    enabled_features!(
+        "serialize-bincode-native",
+        "serialize-bincode-serde",
+        "serialize-bitcode-native",
+        "serialize-bitcode-serde",
+        "serialize-borsh",
+        "serialize-musli-descriptive",
+        "serialize-musli-storage",
+        "serialize-musli-wire",
+        "serialize-musli-zerocopy",
+        "serialize-postcard-serde",
+        "serialize-rkyv",
+        "serialize-messagepack",
+        "serialize-zerocopy",
+        "compress-brotli",
+        "compress-bzip2",
+        "compress-deflate",
+        "compress-gzip",
+        "compress-lz4",
+        "compress-zlib",
+        "compress-zstd",
+        "encrypt-aes-gcm",
+        "encrypt-chacha20",
+        "encrypt-aes-gcm-siv",
+        "ecc-reed-solomon",
+        "key-set-ahash",
+        "key-set-hash",
+        "key-set-btree",
+        "key-set-vec",
+        "roaring-key-set",
+    )
+    .into_iter()
+    .flatten()
+    .map(String::from)
+    .collect()
+}
+
+/// Converts a length into the `u32` used by the archive format.
+fn len_u32(len: usize) -> Result<u32, Error> {
+    u32::try_from(len).map_err(|_| Error::MalformedArchive { reason: "field is larger than 4 GiB" })
+}
+
+/// Writes a length-prefixed byte string.
+fn put_prefixed(writer: &mut impl Write, field: &[u8]) -> Result<(), Error> {
+    writer.write_all(&len_u32(field.len())?.to_le_bytes())?;
+    writer.write_all(field)?;
+    Ok(())
+}
+
+/// Reads a length-prefixed byte string.
+#[cfg(feature = "writes")]
+fn take_prefixed(reader: &mut impl Read) -> Result<Vec<u8>, Error> {
+    let mut len = [0; 4];
+    read_exact(reader, &mut len)?;
+    let mut field = vec![0; u32::from_le_bytes(len) as usize];
+    read_exact(reader, &mut field)?;
+    Ok(field)
+}
+
+/// Reads a length-prefixed UTF-8 string.
+#[cfg(feature = "writes")]
+fn take_string(reader: &mut impl Read) -> Result<String, Error> {
+    String::from_utf8(take_prefixed(reader)?)
+        .map_err(|_| Error::MalformedArchive { reason: "string is not UTF-8" })
+}
+
+/// Fills `buffer`, reporting a premature end of the archive as [`Error::MalformedArchive`].
+#[cfg(feature = "writes")]
+fn read_exact(reader: &mut impl Read, buffer: &mut [u8]) -> Result<(), Error> {
+    reader.read_exact(buffer).map_err(|error| match error.kind() {
+        std::io::ErrorKind::UnexpectedEof =>
+            Error::MalformedArchive { reason: "archive is truncated" },
+        _ => Error::Io(error),
+    })
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "writes"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archives_round_trip() {
+        let mut bytes = Vec::new();
+        let mut archive = ArchiveWriter::new(&mut bytes).unwrap();
+        archive.table("creatures", KeyKind::Bytes).unwrap();
+        archive.entry(b"1", b"Coyote").unwrap();
+        archive.entry(b"2", b"Road Runner").unwrap();
+        archive.table("__atlatl_change_log", KeyKind::U64).unwrap();
+        let written = archive.finish().unwrap();
+        assert_eq!(written.tables, ["creatures", "__atlatl_change_log"]);
+        assert_eq!(written.entries, 2);
+
+        let mut archive = ArchiveReader::new(bytes.as_slice()).unwrap();
+        let mut entries = Vec::new();
+        while let Some(item) = archive.next_item().unwrap() {
+            if let ArchiveItem::Entry { key, value } = item {
+                entries.push((key, value));
+            }
+        }
+        assert_eq!(entries[1], (b"2".to_vec(), b"Road Runner".to_vec()));
+        assert_eq!(archive.into_summary(), written);
+
+        // An archive without its end tag is truncated:
+        let truncated = &bytes[..bytes.len() - 1];
+        let mut archive = ArchiveReader::new(truncated).unwrap();
+        while let Ok(Some(_)) = archive.next_item() {}
+        assert!(matches!(archive.next_item(), Err(Error::MalformedArchive { .. })));
+    }
+}
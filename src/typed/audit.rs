@@ -0,0 +1,310 @@
+//! The audit log, which records who wrote or deleted which record, and when.
+//!
+//! Once enabled with [`Database::enable_audit_log`], every index-aware record write and deletion
+//! appends an [`AuditEntry`] to an internal, append-only table. Entries name the [`Actor`] the
+//! write transaction was begun for, if any, with [`WriteTransaction::acting_as`]. The log is read
+//! back for compliance review with [`ReadTransaction::audit_log`].
+//!
+//! Unlike the change log, the audit log doesn't record the records' values, so it can be retained
+//! for longer, or exported, without exposing their contents.
+//!
+//! [`Database::enable_audit_log`]: crate::typed::database::Database::enable_audit_log
+//! [`WriteTransaction::acting_as`]: crate::typed::transaction::WriteTransaction::acting_as
+//! [`ReadTransaction::audit_log`]: crate::typed::transaction::ReadTransaction::audit_log
+
+use crate::Error;
+use crate::typed::change_log::put_prefixed;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Name of the internal table that stores the audit log, keyed by sequence number.
+///
+/// The audit log is shared by every namespace. Each entry records the full, namespaced name of the
+/// table it applies to.
+pub const AUDIT_LOG_TABLE_NAME: &str = "__atlatl_audit_log";
+
+/// Definition of the audit log table: sequence number → encoded [`AuditEntry`].
+pub(crate) const AUDIT_LOG_TABLE: redb::TableDefinition<u64, &[u8]> =
+    redb::TableDefinition::new(AUDIT_LOG_TABLE_NAME);
+
+/// Marks an entry without an actor.
+const TAG_NO_ACTOR: u8 = 0;
+
+/// Marks an entry made by a user.
+const TAG_USER: u8 = 1;
+
+/// Marks an entry made by a service.
+const TAG_SERVICE: u8 = 2;
+
+/// Set in an entry's operation byte if the entry is linked into the log's hash chain.
+const FLAG_CHAINED: u8 = 2;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Who made a change: a user, or a service acting on its own behalf.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Actor {
+    /// A user, identified by their user ID. For example: `"u-1042"`.
+    User(String),
+
+    /// A service, identified by its name. For example: `"nightly-import"`.
+    Service(String),
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// What a change did to a record.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Operation {
+    /// The record was inserted or replaced.
+    Write,
+
+    /// The record was deleted.
+    Delete,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A single entry in the audit log: who wrote or deleted a record, and when.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AuditEntry {
+    /// Position of the entry in the log. Sequence numbers start at `1`, and increase by one for
+    /// every entry.
+    pub sequence: u64,
+
+    /// Who made the change, or `None` if the write transaction wasn't begun for an actor.
+    pub actor: Option<Actor>,
+
+    /// Full name of the table the change applies to, including its namespace prefix. For example:
+    /// `"tenant42.creatures"`.
+    pub table: String,
+
+    /// The record's serialized primary key.
+    pub key: Vec<u8>,
+
+    /// What the change did to the record.
+    pub operation: Operation,
+
+    /// When the change was made, to the millisecond. Changes are recorded when they're made, not
+    /// when their transaction is committed.
+    pub timestamp: SystemTime,
+
+    /// The entry's link in the log's hash chain, or `None` if it was recorded without the
+    /// `hash-chain` feature. See the [`chain`](crate::typed::chain) module.
+    pub chain: Option<[u8; 32]>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// An iterator over audit log entries, oldest first, returned by
+/// [`ReadTransaction::audit_log`](crate::typed::transaction::ReadTransaction::audit_log).
+pub struct AuditEntries(Option<redb::Range<'static, u64, &'static [u8]>>);
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Actor {
+    /// Returns the actor's user ID or service name.
+    #[must_use]
+    pub fn id(&self) -> &str {
+        match self {
+            Self::User(id) | Self::Service(id) => id,
+        }
+    }
+}
+
+impl AuditEntry {
+    /// Encodes the entry for storage in the audit log. The sequence number is the log's key, so it
+    /// isn't included.
+    ///
+    /// Layout: `timestamp millis (u64) | operation (u8) | [chain (32 bytes)] | actor tag (u8) |
+    /// [actor length (u32) | actor] | table length (u32) | table | key length (u32) | key`. The
+    /// chain is only present if the operation byte says so.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let millis = self.timestamp
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX));
+        encode_audit_entry(
+            self.actor.as_ref(),
+            &self.table,
+            &self.key,
+            self.operation,
+            millis,
+            self.chain.as_ref(),
+        )
+    }
+
+    /// Decodes an entry that was stored in the audit log under the given sequence number.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::MalformedAuditLog`] if the bytes are truncated or malformed.
+    pub fn decode(sequence: u64, bytes: &[u8]) -> Result<Self, Error> {
+        let truncated = || Error::MalformedAuditLog { reason: "entry is truncated" };
+        let (millis, bytes) = bytes.split_first_chunk::<8>().ok_or_else(truncated)?;
+        let timestamp = UNIX_EPOCH + Duration::from_millis(u64::from_le_bytes(*millis));
+        let (&operation, bytes) = bytes.split_first().ok_or_else(truncated)?;
+        let (chain, bytes) = if operation & FLAG_CHAINED == 0 {
+            (None, bytes)
+        } else {
+            let (chain, bytes) = bytes.split_first_chunk::<32>().ok_or_else(truncated)?;
+            (Some(*chain), bytes)
+        };
+        let [actor_tag, rest @ ..] = bytes else {
+            return Err(truncated());
+        };
+        let mut bytes = rest;
+
+        let operation = match operation & !FLAG_CHAINED {
+            0 => Operation::Delete,
+            1 => Operation::Write,
+            _ => return Err(Error::MalformedAuditLog { reason: "invalid operation" }),
+        };
+        let actor = match *actor_tag {
+            TAG_NO_ACTOR => None,
+            TAG_USER => Some(Actor::User(take_string(&mut bytes)?)),
+            TAG_SERVICE => Some(Actor::Service(take_string(&mut bytes)?)),
+            _ => return Err(Error::MalformedAuditLog { reason: "invalid actor" }),
+        };
+        let table = take_string(&mut bytes)?;
+        let key = take_prefixed(&mut bytes)?.to_vec();
+
+        Ok(Self { sequence, actor, table, key, operation, timestamp, chain })
+    }
+}
+
+impl AuditEntries {
+    /// Wraps a range of the audit log, or `None` if the audit log doesn't exist.
+    pub(crate) const fn new(range: Option<redb::Range<'static, u64, &'static [u8]>>) -> Self {
+        Self(range)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl Iterator for AuditEntries {
+    type Item = Result<AuditEntry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.0.as_mut()?.next()?;
+        Some(entry.map_err(Error::from).and_then(|(sequence, bytes)| {
+            AuditEntry::decode(sequence.value(), bytes.value())
+        }))
+    }
+}
+
+impl std::fmt::Display for Actor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::User(id) => write!(f, "user `{id}`"),
+            Self::Service(name) => write!(f, "service `{name}`"),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Encodes an audit entry without first copying its parts into an [`AuditEntry`].
+pub(crate) fn encode_audit_entry(
+    actor: Option<&Actor>,
+    table: &str,
+    key: &[u8],
+    operation: Operation,
+    millis: u64,
+    chain: Option<&[u8; 32]>,
+) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(
+        54 + actor.map_or(0, |actor| actor.id().len()) + table.len() + key.len()
+    );
+    bytes.extend_from_slice(&millis.to_le_bytes());
+    let operation = match operation {
+        Operation::Delete => 0,
+        Operation::Write => 1,
+    };
+    match chain {
+        Some(chain) => {
+            bytes.push(operation | FLAG_CHAINED);
+            bytes.extend_from_slice(chain);
+        },
+        None => bytes.push(operation),
+    }
+    match actor {
+        None => bytes.push(TAG_NO_ACTOR),
+        Some(actor) => {
+            bytes.push(match actor {
+                Actor::User(_) => TAG_USER,
+                Actor::Service(_) => TAG_SERVICE,
+            });
+            put_prefixed(&mut bytes, actor.id().as_bytes());
+        },
+    }
+    put_prefixed(&mut bytes, table.as_bytes());
+    put_prefixed(&mut bytes, key);
+    bytes
+}
+
+/// Returns an encoded entry's link in the log's hash chain, without decoding the rest of it.
+/// Returns `None` if the entry isn't chained, or is malformed.
+#[cfg(any(test, feature = "hash-chain"))]
+pub(crate) fn audit_chain(bytes: &[u8]) -> Option<[u8; 32]> {
+    match bytes.get(8..)?.split_first()? {
+        (operation, rest) if operation & FLAG_CHAINED != 0 => Some(*rest.first_chunk::<32>()?),
+        _ => None,
+    }
+}
+
+/// Takes a length-prefixed byte string from the front of `bytes`.
+fn take_prefixed<'b>(bytes: &mut &'b [u8]) -> Result<&'b [u8], Error> {
+    let truncated = || Error::MalformedAuditLog { reason: "entry is truncated" };
+    let (len, rest) = bytes.split_first_chunk::<4>().ok_or_else(truncated)?;
+    let len = u32::from_le_bytes(*len) as usize;
+    let field = rest.get(..len).ok_or_else(truncated)?;
+    *bytes = &rest[len..];
+    Ok(field)
+}
+
+/// Takes a length-prefixed UTF-8 string from the front of `bytes`.
+fn take_string(bytes: &mut &[u8]) -> Result<String, Error> {
+    String::from_utf8(take_prefixed(bytes)?.to_vec())
+        .map_err(|_| Error::MalformedAuditLog { reason: "name is not UTF-8" })
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_round_trip() {
+        let write = AuditEntry {
+            sequence: 3,
+            actor: Some(Actor::User("u-1042".to_string())),
+            table: "tenant42.creatures".to_string(),
+            key: b"7".to_vec(),
+            operation: Operation::Write,
+            timestamp: UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+            chain: None,
+        };
+        assert_eq!(AuditEntry::decode(3, &write.encode()).unwrap(), write);
+        assert_eq!(audit_chain(&write.encode()), None);
+
+        let delete = AuditEntry { actor: None, operation: Operation::Delete, ..write };
+        assert_eq!(AuditEntry::decode(3, &delete.encode()).unwrap(), delete);
+
+        assert!(AuditEntry::decode(3, &delete.encode()[..12]).is_err());
+
+        let chained = AuditEntry { chain: Some([9; 32]), ..delete };
+        assert_eq!(AuditEntry::decode(3, &chained.encode()).unwrap(), chained);
+        assert_eq!(audit_chain(&chained.encode()), Some([9; 32]));
+    }
+}
@@ -0,0 +1,661 @@
+//! Hot backups, which copy an open database into a fresh file while writers continue, and
+//! incremental backups, which capture only the changes made since a previous backup.
+
+use crate::Error;
+use crate::redaction::{Redacted, RedactionPolicy};
+use crate::typed::change_log::{CHANGE_LOG_TABLE, CHANGE_LOG_TABLE_NAME, Change, last_sequence};
+use crate::typed::record_layers::RecordContext;
+use redb::{ReadableMultimapTable, ReadableTable};
+use redb::{MultimapTableDefinition, MultimapTableHandle, TableDefinition, TableHandle};
+use std::collections::BTreeSet;
+use std::io::Write;
+#[cfg(feature = "writes")]
+use std::io::{BufRead, Read};
+
+// -------------------------------------------------------------------------------------------------
+
+/// The progress callback is invoked after every `PROGRESS_INTERVAL` entries are copied, in
+/// addition to once after each table is finished.
+const PROGRESS_INTERVAL: u64 = 10_000;
+
+/// Identifies an incremental backup file.
+const DELTA_MAGIC: &[u8; 8] = b"ATLDELTA";
+
+/// Version of the incremental backup file format.
+const DELTA_VERSION: u8 = 1;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The progress of a backup, passed to the callback given to [`Database::backup_to`].
+///
+/// [`Database::backup_to`]: crate::typed::database::Database::backup_to
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BackupProgress {
+    /// Name of the table currently being copied. For example: `"creatures"`.
+    pub table: String,
+
+    /// Number of tables that have been completely copied.
+    pub tables_copied: usize,
+
+    /// Total number of tables in the backup.
+    pub tables_total: usize,
+
+    /// Number of entries copied so far, across all tables.
+    pub entries_copied: u64,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A summary of an incremental backup, returned when it's written with
+/// [`Database::backup_incremental`] and when it's replayed with [`Database::restore_incremental`].
+///
+/// [`Database::backup_incremental`]: crate::typed::database::Database::backup_incremental
+/// [`Database::restore_incremental`]: crate::typed::database::Database::restore_incremental
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct DeltaSummary {
+    /// Sequence number the backup starts after. Changes up to and including this sequence number
+    /// must already be present in the base backup.
+    pub since: u64,
+
+    /// Sequence number of the last change in the backup. Pass this as `since_sequence` to take the
+    /// next incremental backup.
+    pub until: u64,
+
+    /// Number of changes written to, or replayed from, the backup.
+    pub changes: u64,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl BackupProgress {
+    /// Returns an estimate of the backup's progress, from `0.0` to `1.0`, based on the number of
+    /// tables copied.
+    #[must_use]
+    pub fn fraction(&self) -> f64 {
+        if self.tables_total == 0 {
+            1.0
+        } else {
+            #[allow(clippy::cast_precision_loss, reason = "table counts are small")]
+            let fraction = self.tables_copied as f64 / self.tables_total as f64;
+            fraction
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Copies every table and multimap table visible to the `source` read transaction into the
+/// `target` database, in a single write transaction that's committed once everything is copied.
+///
+/// Because `source` is a read transaction, the copy is a consistent snapshot of the database as it
+/// was when the transaction began, regardless of any writes committed since.
+///
+/// Tables must have byte values, and byte, string, or `u64` keys, as every table created by
+/// `atlatl` does. Multimap tables must have byte keys and values.
+///
+/// If a redaction policy is given, tables it leaves out aren't copied, and records are redacted
+/// as they're copied. Records with record layers are decoded in the context of `records`, for the
+/// table they're stored in. The secondary index tables of redacted records aren't copied, and the
+/// change log is copied with each of its changes redacted.
+///
+/// # Errors
+///
+/// * Returns [`redb::TableError::TableTypeMismatch`] if a table has other key or value types.
+///
+/// * Decoding a record, or encoding its redacted copy, fails.
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+pub(crate) fn copy_tables(
+    source: &redb::ReadTransaction,
+    target: &redb::Database,
+    redaction: Option<&RedactionPolicy>,
+    records: &RecordContext,
+    mut progress: impl FnMut(&BackupProgress),
+) -> Result<BackupProgress, Error> {
+    let keeps_table = |name: &str| redaction.is_none_or(|policy| policy.keeps_table(name));
+
+    let mut tables: Vec<String> = source
+        .list_tables()?
+        .map(|handle| handle.name().to_string())
+        .filter(|name| name == CHANGE_LOG_TABLE_NAME || keeps_table(name))
+        .collect();
+
+    // Redacted tables are copied first, so that their records' index tables are known in time:
+    if let Some(policy) = redaction {
+        tables.sort_by_key(|name| !policy.redacts_table(name));
+    }
+
+    let multimap_tables: Vec<String> = source
+        .list_multimap_tables()?
+        .map(|handle| handle.name().to_string())
+        .filter(|name| keeps_table(name))
+        .collect();
+
+    let mut status = BackupProgress {
+        tables_total: tables.len() + multimap_tables.len(),
+        ..BackupProgress::default()
+    };
+
+    let txn = target.begin_write().map_err(Box::new)?;
+    let mut index_tables = BTreeSet::new();
+
+    for name in tables {
+        if index_tables.contains(&name) {
+            status.tables_total -= 1;
+            continue;
+        }
+        status.table.clone_from(&name);
+
+        match redaction {
+            Some(policy) if name == CHANGE_LOG_TABLE_NAME =>
+                copy_change_log(source, &txn, policy, records, &mut status, &mut progress)?,
+            _ => {
+                // Record and index tables have byte keys, but some internal tables don't:
+                let context = records.for_table(name.as_str());
+                let redaction = redaction.map(|policy| (policy, &context));
+                let index_tables = &mut index_tables;
+                copy_table::<&[u8]>(
+                    source, &txn, &name, redaction, index_tables, &mut status, &mut progress,
+                )
+                .or_else(|error| retry_on_mismatch(error, || copy_table::<&str>(
+                    source, &txn, &name, redaction, index_tables, &mut status, &mut progress,
+                )))
+                .or_else(|error| retry_on_mismatch(error, || copy_table::<u64>(
+                    source, &txn, &name, redaction, index_tables, &mut status, &mut progress,
+                )))?;
+            },
+        }
+
+        status.tables_copied += 1;
+        progress(&status);
+    }
+
+    for name in multimap_tables {
+        if index_tables.contains(&name) {
+            status.tables_total -= 1;
+            continue;
+        }
+        let definition = MultimapTableDefinition::<&[u8], &[u8]>::new(&name);
+        let source_table = source.open_multimap_table(definition)?;
+        let mut target_table = txn.open_multimap_table(definition)?;
+        status.table.clone_from(&name);
+
+        for entry in source_table.iter()? {
+            let (key, values) = entry?;
+            for value in values {
+                target_table.insert(key.value(), value?.value())?;
+                status.entries_copied += 1;
+                if status.entries_copied.is_multiple_of(PROGRESS_INTERVAL) {
+                    progress(&status);
+                }
+            }
+        }
+
+        status.tables_copied += 1;
+        progress(&status);
+    }
+
+    txn.commit()?;
+    Ok(status)
+}
+
+/// Copies a table whose keys are of type `K`, and whose values are bytes, redacting its records
+/// if a redaction policy is given, along with the table's record context. The index tables of
+/// redacted records are added to `index_tables`.
+///
+/// # Errors
+///
+/// * Returns [`redb::TableError::TableTypeMismatch`] if the table's keys aren't of type `K`.
+///
+/// * Decoding a record, or encoding its redacted copy, fails.
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+fn copy_table<K: redb::Key + 'static>(
+    source: &redb::ReadTransaction,
+    target: &redb::WriteTransaction,
+    name: &str,
+    redaction: Option<(&RedactionPolicy, &RecordContext)>,
+    index_tables: &mut BTreeSet<String>,
+    status: &mut BackupProgress,
+    progress: &mut impl FnMut(&BackupProgress),
+) -> Result<(), Error> {
+    let definition = TableDefinition::<K, &[u8]>::new(name);
+    let source_table = source.open_table(definition)?;
+    let mut target_table = target.open_table(definition)?;
+
+    for entry in source_table.iter()? {
+        let (key, value) = entry?;
+        match redaction {
+            Some((policy, context)) => match policy.apply_to_table(
+                context,
+                K::as_bytes(&key.value()).as_ref(),
+                value.value(),
+                index_tables,
+            )? {
+                Redacted::Keep(value) => { target_table.insert(key.value(), &*value)?; },
+                Redacted::Drop => continue,
+            },
+            None => { target_table.insert(key.value(), value.value())?; },
+        }
+        status.entries_copied += 1;
+        if status.entries_copied.is_multiple_of(PROGRESS_INTERVAL) {
+            progress(status);
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies the change log, redacting each of its changes with `policy`. Records with record layers
+/// are decoded in the context of `records`, for the table the change applies to.
+///
+/// # Errors
+///
+/// * Returns [`Error::MalformedChangeLog`] if a change is malformed.
+///
+/// * Decoding a record, or encoding its redacted copy, fails.
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+fn copy_change_log(
+    source: &redb::ReadTransaction,
+    target: &redb::WriteTransaction,
+    policy: &RedactionPolicy,
+    records: &RecordContext,
+    status: &mut BackupProgress,
+    progress: &mut impl FnMut(&BackupProgress),
+) -> Result<(), Error> {
+    let source_table = source.open_table(CHANGE_LOG_TABLE)?;
+    let mut target_table = target.open_table(CHANGE_LOG_TABLE)?;
+
+    for entry in source_table.iter()? {
+        let (sequence, change) = entry?;
+        let change = Change::decode(sequence.value(), change.value())?;
+        target_table.insert(sequence.value(), &*policy.redact_change(records, change)?.encode())?;
+        status.entries_copied += 1;
+        if status.entries_copied.is_multiple_of(PROGRESS_INTERVAL) {
+            progress(status);
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs `retry` if `error` reports that a table was opened with the wrong key or value types, and
+/// returns the original error otherwise.
+fn retry_on_mismatch(
+    error: Error,
+    retry: impl FnOnce() -> Result<(), Error>,
+) -> Result<(), Error> {
+    match error {
+        Error::RedbTable(redb::TableError::TableTypeMismatch { .. }) => retry(),
+        error => Err(error),
+    }
+}
+
+/// Writes every change in the change log after `since` to `writer`, as an incremental backup.
+///
+/// Layout: `magic | version (u8) | since (u64) | until (u64)`, followed by one
+/// `sequence (u64) | length (u32) | encoded change` entry per change. Integers are little-endian.
+///
+/// If a redaction policy is given, along with the context of the database's records, each change
+/// is redacted as it's written. See [`RedactionPolicy::redact_change`].
+///
+/// # Errors
+///
+/// * Returns [`Error::ChangeLogNotEnabled`] if the database has no change log.
+///
+/// * Returns [`Error::Io`] if the backup could not be written.
+///
+/// * Returns [`Error::MalformedChangeLog`] if a change to be redacted is malformed.
+///
+/// * Decoding a record, or encoding its redacted copy, fails.
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+pub(crate) fn write_delta(
+    source: &redb::ReadTransaction,
+    since: u64,
+    redaction: Option<(&RedactionPolicy, &RecordContext)>,
+    mut writer: impl Write,
+) -> Result<DeltaSummary, Error> {
+    let change_log = match source.open_table(CHANGE_LOG_TABLE) {
+        Ok(change_log) => change_log,
+        Err(redb::TableError::TableDoesNotExist(_)) => return Err(Error::ChangeLogNotEnabled),
+        Err(error) => return Err(error.into()),
+    };
+
+    let until = last_sequence(&change_log)?.max(since);
+    let mut summary = DeltaSummary { since, until, changes: 0 };
+
+    writer.write_all(DELTA_MAGIC)?;
+    writer.write_all(&[DELTA_VERSION])?;
+    writer.write_all(&summary.since.to_le_bytes())?;
+    writer.write_all(&summary.until.to_le_bytes())?;
+
+    for entry in change_log.range(since.saturating_add(1)..)? {
+        let (sequence, change) = entry?;
+        let redacted = match redaction {
+            Some((policy, records)) => Some(policy
+                .redact_change(records, Change::decode(sequence.value(), change.value())?)?
+                .encode()),
+            None => None,
+        };
+        let change = redacted.as_deref().unwrap_or_else(|| change.value());
+        let len = u32::try_from(change.len())
+            .map_err(|_| Error::MalformedChangeLog { reason: "change is larger than 4 GiB" })?;
+        writer.write_all(&sequence.value().to_le_bytes())?;
+        writer.write_all(&len.to_le_bytes())?;
+        writer.write_all(change)?;
+        summary.changes += 1;
+    }
+
+    writer.flush()?;
+    Ok(summary)
+}
+
+/// Replays an incremental backup onto the `target` database, in a single write transaction.
+///
+/// Each change is applied to its table, and appended to the target's own change log under its
+/// original sequence number. Changes the target already has are skipped, so replaying the same
+/// backup twice is harmless.
+///
+/// # Errors
+///
+/// * Returns [`Error::IncrementalBackupGap`] if the target is missing changes that were made
+///   before the backup starts.
+///
+/// * Returns [`Error::MalformedChangeLog`] if the backup is truncated or malformed.
+///
+/// * Returns [`Error::Io`] if the backup could not be read.
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+#[cfg(feature = "writes")]
+pub(crate) fn apply_delta(
+    target: &redb::Database,
+    reader: impl Read,
+) -> Result<DeltaSummary, Error> {
+    let mut reader = std::io::BufReader::new(reader);
+
+    let mut header = [0; DELTA_MAGIC.len() + 1 + 16];
+    reader.read_exact(&mut header)?;
+    let (magic, header) = header.split_at(DELTA_MAGIC.len());
+    if magic != DELTA_MAGIC || header[0] != DELTA_VERSION {
+        return Err(Error::MalformedChangeLog { reason: "not an incremental backup" });
+    }
+    let since = u64::from_le_bytes(header[1..9].try_into().unwrap()); // Always 8 bytes
+    let until = u64::from_le_bytes(header[9..17].try_into().unwrap()); // Always 8 bytes
+
+    let txn = target.begin_write().map_err(Box::new)?;
+    let mut change_log = txn.open_table(CHANGE_LOG_TABLE)?;
+    let applied = last_sequence(&change_log)?;
+    if applied < since {
+        return Err(Error::IncrementalBackupGap { expected: since, found: applied });
+    }
+
+    let mut summary = DeltaSummary { since, until, changes: 0 };
+    while !reader.fill_buf()?.is_empty() {
+        let mut entry_header = [0; 12];
+        reader.read_exact(&mut entry_header)?;
+        let sequence = u64::from_le_bytes(entry_header[..8].try_into().unwrap()); // Always 8 bytes
+        let len = u32::from_le_bytes(entry_header[8..].try_into().unwrap()); // Always 4 bytes
+        let mut bytes = vec![0; len as usize];
+        reader.read_exact(&mut bytes)?;
+
+        if sequence <= applied {
+            continue;
+        }
+
+        let change = Change::decode(sequence, &bytes)?;
+        let mut table = txn.open_table(TableDefinition::<&[u8], &[u8]>::new(&change.table))?;
+        match &change.value {
+            Some(value) => { table.insert(&*change.key, &**value)?; },
+            None => { table.remove(&*change.key)?; },
+        }
+        change_log.insert(sequence, &*bytes)?;
+        summary.changes += 1;
+    }
+
+    drop(change_log);
+    txn.commit()?;
+    Ok(summary)
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CREATURES: TableDefinition<&[u8], &[u8]> = TableDefinition::new("creatures");
+    const HABITATS: MultimapTableDefinition<&[u8], &[u8]> = MultimapTableDefinition::new("habitats");
+
+    #[test]
+    fn copies_a_consistent_snapshot() {
+        let directory = std::env::temp_dir().join(format!("atlatl-backup-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let source = redb::Database::create(directory.join("source.redb")).unwrap();
+
+        let txn = source.begin_write().unwrap();
+        txn.open_table(CREATURES).unwrap().insert(b"1".as_slice(), b"Coyote".as_slice()).unwrap();
+        let mut habitats = txn.open_multimap_table(HABITATS).unwrap();
+        habitats.insert(b"Desert".as_slice(), b"1".as_slice()).unwrap();
+        habitats.insert(b"Desert".as_slice(), b"2".as_slice()).unwrap();
+        drop(habitats);
+        txn.commit().unwrap();
+
+        let snapshot = source.begin_read().unwrap();
+
+        // Written after the snapshot began, so it must not appear in the backup:
+        let txn = source.begin_write().unwrap();
+        txn.open_table(CREATURES).unwrap().insert(b"2".as_slice(), b"Road Runner".as_slice()).unwrap();
+        txn.commit().unwrap();
+
+        let target = redb::Database::create(directory.join("target.redb")).unwrap();
+        let mut calls = 0;
+        let records = RecordContext::new(None, "");
+        let status = copy_tables(&snapshot, &target, None, &records, |_| calls += 1).unwrap();
+        assert_eq!(status.tables_copied, 2);
+        assert_eq!(status.entries_copied, 3);
+        assert_eq!(calls, 2);
+
+        let txn = target.begin_read().unwrap();
+        let creatures = txn.open_table(CREATURES).unwrap();
+        assert_eq!(creatures.get(b"1".as_slice()).unwrap().unwrap().value(), b"Coyote");
+        assert!(creatures.get(b"2".as_slice()).unwrap().is_none());
+        assert_eq!(txn.open_multimap_table(HABITATS).unwrap().get(b"Desert".as_slice()).unwrap().count(), 2);
+
+        drop((creatures, txn, target, snapshot, source));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(feature = "writes")]
+    #[test]
+    fn replays_incremental_backups() {
+        use crate::typed::change_log::encode_change;
+
+        let directory = std::env::temp_dir().join(format!("atlatl-delta-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let source = redb::Database::create(directory.join("source.redb")).unwrap();
+
+        // Writes a record and logs the change, as an index-aware write would:
+        let write = |key: &[u8], value: Option<&[u8]>| {
+            let txn = source.begin_write().unwrap();
+            let mut creatures = txn.open_table(CREATURES).unwrap();
+            match value {
+                Some(value) => { creatures.insert(key, value).unwrap(); },
+                None => { creatures.remove(key).unwrap(); },
+            }
+            let mut change_log = txn.open_table(CHANGE_LOG_TABLE).unwrap();
+            let sequence = last_sequence(&change_log).unwrap() + 1;
+            change_log.insert(sequence, &*encode_change("creatures", key, value, None)).unwrap();
+            drop((creatures, change_log));
+            txn.commit().unwrap();
+        };
+
+        write(b"1", Some(b"Coyote"));
+        let base = redb::Database::create(directory.join("base.redb")).unwrap();
+        let records = RecordContext::new(None, "");
+        copy_tables(&source.begin_read().unwrap(), &base, None, &records, |_| {}).unwrap();
+
+        write(b"2", Some(b"Road Runner"));
+        write(b"1", None);
+        let mut delta = Vec::new();
+        let written = write_delta(&source.begin_read().unwrap(), 1, None, &mut delta).unwrap();
+        assert_eq!(written, DeltaSummary { since: 1, until: 3, changes: 2 });
+
+        assert_eq!(apply_delta(&base, delta.as_slice()).unwrap(), written);
+        assert_eq!(apply_delta(&base, delta.as_slice()).unwrap().changes, 0);
+
+        let txn = base.begin_read().unwrap();
+        let creatures = txn.open_table(CREATURES).unwrap();
+        assert!(creatures.get(b"1".as_slice()).unwrap().is_none());
+        assert_eq!(creatures.get(b"2".as_slice()).unwrap().unwrap().value(), b"Road Runner");
+
+        // A backup starting after a change the base doesn't have is refused:
+        write(b"3", Some(b"Roadrunner"));
+        write(b"4", Some(b"Acme"));
+        let mut gap = Vec::new();
+        write_delta(&source.begin_read().unwrap(), 4, None, &mut gap).unwrap();
+        drop((creatures, txn));
+        assert!(matches!(
+            apply_delta(&base, gap.as_slice()),
+            Err(Error::IncrementalBackupGap { expected: 4, found: 3 })
+        ));
+
+        drop((base, source));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(feature = "serialize-messagepack")]
+    #[test]
+    fn redacts_records_as_they_are_copied() {
+        use crate::Codec;
+        use crate::redaction::mask;
+        use crate::typed::test_records::Animal;
+
+        const ANIMALS: TableDefinition<&[u8], &[u8]> = TableDefinition::new("tenant42.animals");
+
+        let directory = std::env::temp_dir().join(format!("atlatl-redact-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let source = redb::Database::create(directory.join("source.redb")).unwrap();
+
+        let animal = Animal::new(1, "Coyote", "Desert");
+        let txn = source.begin_write().unwrap();
+        let animal = Animal::serialize(&animal).unwrap();
+        txn.open_table(ANIMALS).unwrap().insert(b"1".as_slice(), &*animal).unwrap();
+        txn.open_table(CREATURES).unwrap().insert(b"1".as_slice(), b"Coyote".as_slice()).unwrap();
+        txn.commit().unwrap();
+
+        let policy = RedactionPolicy::deny_unregistered().with_table(|mut animal: Animal| {
+            animal.enclosure = mask(&animal.enclosure);
+            Redacted::Keep(animal)
+        });
+        let target = redb::Database::create(directory.join("target.redb")).unwrap();
+        let status = copy_tables(
+            &source.begin_read().unwrap(),
+            &target,
+            Some(&policy),
+            &RecordContext::new(None, ""),
+            |_| {},
+        ).unwrap();
+        assert_eq!(status.tables_copied, 1);
+
+        let txn = target.begin_read().unwrap();
+        let animals = txn.open_table(ANIMALS).unwrap();
+        let copied = animals.get(b"1".as_slice()).unwrap().unwrap();
+        let copied = Animal::deserialize(copied.value()).unwrap();
+        assert_eq!(copied, Animal::new(1, "Coyote", "******"));
+        assert!(txn.open_table(CREATURES).is_err());
+
+        drop((animals, txn, target, source));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(all(feature = "writes", feature = "serialize-messagepack"))]
+    #[test]
+    fn redacted_fields_are_not_copied_to_any_table() {
+        use crate::redaction::mask;
+        use crate::typed::archive::ExportScope;
+        use crate::typed::database::Database;
+        use crate::typed::test_records::Animal;
+
+        const ENCLOSURE: &[u8] = b"Painted Desert";
+        let contains_enclosure =
+            |bytes: &[u8]| bytes.windows(ENCLOSURE.len()).any(|window| window == ENCLOSURE);
+
+        let mut db = Database::in_memory().unwrap();
+        db.enable_change_log().unwrap();
+        db.enable_audit_log().unwrap();
+        db.set_redaction_policy(RedactionPolicy::new().with_table(|mut animal: Animal| {
+            animal.enclosure = mask(&animal.enclosure);
+            Redacted::Keep(animal)
+        }));
+        let mut txn = db.write().unwrap();
+        txn.bulk_insert::<u64, Animal>([
+            Animal::new(1, "Coyote", "Painted Desert"),
+            Animal::new(2, "Roadrunner", "Painted Desert"),
+        ]).unwrap();
+        txn.commit().unwrap();
+
+        let directory =
+            std::env::temp_dir().join(format!("atlatl-redact-all-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        db.backup_to(directory.join("backup.redb"), |_| {}).unwrap();
+
+        // Every table of the backup, whatever its key type, is searched for the enclosure:
+        let backup = redb::Database::open(directory.join("backup.redb")).unwrap();
+        let txn = backup.begin_read().unwrap();
+        let mut table_names = Vec::new();
+        for table in txn.list_tables().unwrap() {
+            let name = table.name().to_string();
+            let mut bytes = Vec::new();
+            if let Ok(table) = txn.open_table(TableDefinition::<&[u8], &[u8]>::new(&name)) {
+                for entry in table.iter().unwrap() {
+                    let (key, value) = entry.unwrap();
+                    bytes.extend_from_slice(key.value());
+                    bytes.extend_from_slice(value.value());
+                }
+            } else if let Ok(table) = txn.open_table(TableDefinition::<u64, &[u8]>::new(&name)) {
+                for entry in table.iter().unwrap() {
+                    bytes.extend_from_slice(entry.unwrap().1.value());
+                }
+            } else if let Ok(table) = txn.open_table(TableDefinition::<&str, &[u8]>::new(&name)) {
+                for entry in table.iter().unwrap() {
+                    let (key, value) = entry.unwrap();
+                    bytes.extend_from_slice(key.value().as_bytes());
+                    bytes.extend_from_slice(value.value());
+                }
+            }
+            assert!(!contains_enclosure(&bytes), "`{name}` holds a redacted field");
+            table_names.push(name);
+        }
+        assert!(table_names.iter().any(|name| name == "animals"));
+        assert!(table_names.iter().any(|name| name == CHANGE_LOG_TABLE_NAME));
+        assert!(!table_names.iter().any(|name| name == "animals_by_enclosure"));
+        assert!(!table_names.iter().any(|name| name == crate::typed::audit::AUDIT_LOG_TABLE_NAME));
+        assert_eq!(last_sequence(&txn.open_table(CHANGE_LOG_TABLE).unwrap()).unwrap(), 2);
+        drop((txn, backup));
+
+        let restored = Database::open(directory.join("backup.redb")).unwrap();
+        assert_eq!(
+            restored.read().unwrap().get::<u64, Animal>(&1).unwrap(),
+            Some(Animal::new(1, "Coyote", "**************")),
+        );
+
+        // Incremental backups, and so the server's `/changes`, and archives are redacted too:
+        let mut delta = Vec::new();
+        assert_eq!(db.backup_incremental(0, &mut delta).unwrap().changes, 2);
+        assert!(!contains_enclosure(&delta));
+        let mut archive = Vec::new();
+        db.read().unwrap().export(ExportScope::Everything, &mut archive).unwrap();
+        assert!(!contains_enclosure(&archive));
+
+        drop(restored);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
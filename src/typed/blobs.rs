@@ -0,0 +1,516 @@
+//! Large-object storage, for byte payloads too big to be stored as a single `redb` value.
+//!
+//! A blob is split into content-defined chunks, which are stored in a chunk table shared by every
+//! blob in a namespace, keyed by their BLAKE3 hash. Each blob has a manifest that lists its chunks
+//! in order. Because chunk boundaries depend on the content rather than on offsets, inserting a
+//! few bytes into a blob only changes the chunks around the insertion, and blobs that share
+//! content share chunks. Chunks are reference counted, and removed once no manifest lists them.
+//!
+//! Blobs are written with a [`BlobWriter`], from the write transaction's `blob_writer` method, and
+//! read with a [`BlobReader`], from the read transaction's `blob_reader` method. Both stream the
+//! blob, so only about one chunk is held in memory at a time:
+//!
+//! ```rust,ignore
+//! let mut txn = db.write()?;
+//! let mut writer = txn.blob_writer("videos/coyote.mp4");
+//! std::io::copy(&mut std::fs::File::open("coyote.mp4")?, &mut writer)?;
+//! let info = writer.finish()?;
+//! txn.commit()?;
+//!
+//! let txn = db.read()?;
+//! let mut reader = txn.blob_reader("videos/coyote.mp4")?;
+//! std::io::copy(&mut reader, &mut std::fs::File::create("copy.mp4")?)?;
+//! ```
+//!
+//! # Notes
+//!
+//! * Chunks are stored as they are: they aren't serialized, compressed, encrypted, or protected by
+//!   error correction like records are.
+//!
+//! * Chunks are verified against their hash as they're read.
+//!
+//! # Format
+//!
+//! All integers are little-endian.
+//!
+//! ```text
+//! manifest: blob length (u64) | chunk count (u32) | chunk hash ([u8; 32]) | chunk length (u32) ...
+//! chunk:    reference count (u64) | chunk data
+//! ```
+
+use crate::Error;
+
+#[cfg(feature = "writes")]
+use crate::typed::transaction::WriteTransaction;
+use crate::typed::transaction::ReadTransaction;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Name of the internal table that stores blob manifests, keyed by blob name. The table is opened
+/// in the transaction's namespace.
+pub const BLOB_MANIFESTS_TABLE_NAME: &str = "__atlatl_blob_manifests";
+
+/// Name of the internal table that stores blob chunks, keyed by their BLAKE3 hash. The table is
+/// opened in the transaction's namespace.
+pub const BLOB_CHUNKS_TABLE_NAME: &str = "__atlatl_blob_chunks";
+
+/// Chunks are never cut shorter than this, except for a blob's last chunk.
+const MIN_CHUNK_SIZE: usize = 16 * 1_024;
+
+/// Chunks are always cut at this length, if no content-defined boundary was found before it.
+const MAX_CHUNK_SIZE: usize = 256 * 1_024;
+
+/// A chunk boundary is found where the rolling hash's masked bits are all zero, which happens
+/// every 64 KiB on average.
+const BOUNDARY_MASK: u64 = (64 * 1_024) - 1;
+
+/// Size of a chunk hash.
+pub(crate) const CHUNK_HASH_SIZE: usize = 32;
+
+/// Size of a manifest's fixed header: the blob length and the chunk count.
+const MANIFEST_HEADER_SIZE: usize = size_of::<u64>() + size_of::<u32>();
+
+/// Size of each chunk in a manifest: the chunk hash and the chunk length.
+const MANIFEST_CHUNK_SIZE: usize = CHUNK_HASH_SIZE + size_of::<u32>();
+
+/// Random values mixed into the rolling hash for every byte value.
+const GEAR: [u64; 256] = gear_table();
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A summary of a blob that was written, returned by [`BlobWriter::finish`] and the write
+/// transaction's `put_blob` method.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct BlobInfo {
+    /// Length of the blob, in bytes.
+    pub len: u64,
+
+    /// Number of chunks the blob was split into.
+    pub chunks: u64,
+
+    /// Number of the blob's chunks that weren't already stored, and were written. The remaining
+    /// chunks are shared with other blobs, or repeated within this one.
+    pub chunks_written: u64,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The list of chunks that make up a blob.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct Manifest {
+    /// Length of the blob, in bytes.
+    pub(crate) len: u64,
+
+    /// Hash and length of every chunk, in blob order.
+    pub(crate) chunks: Vec<([u8; CHUNK_HASH_SIZE], u32)>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Finds content-defined chunk boundaries with a gear rolling hash.
+#[derive(Clone, Copy, Debug, Default)]
+struct Chunker {
+    /// The rolling hash of the current chunk's bytes, as far as they've been scanned.
+    hash: u64,
+
+    /// Number of the current chunk's bytes that have been scanned.
+    scanned: usize,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Writes a blob in a write transaction, implementing [`std::io::Write`]. Chunks are stored as
+/// they're cut, and the blob's manifest is stored by [`BlobWriter::finish`].
+///
+/// Dropping a writer without finishing it releases the chunks it stored, and leaves any existing
+/// blob with the same name as it was.
+#[cfg(feature = "writes")]
+pub struct BlobWriter<'txn> {
+    /// The transaction that chunks and the manifest are written to.
+    txn: &'txn mut WriteTransaction,
+
+    /// Name of the blob.
+    name: String,
+
+    /// Bytes written since the last chunk was cut.
+    buffer: Vec<u8>,
+
+    /// Finds the end of the chunk in `buffer`.
+    chunker: Chunker,
+
+    /// The chunks stored so far.
+    manifest: Manifest,
+
+    /// Number of chunks that weren't already stored.
+    chunks_written: u64,
+
+    /// Whether the manifest was stored, after which the stored chunks belong to it.
+    finished: bool,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Reads a blob in a read transaction, implementing [`std::io::Read`]. Chunks are loaded one at a
+/// time, as the previous chunk is used up.
+pub struct BlobReader<'txn> {
+    /// The transaction that chunks are read from.
+    txn: &'txn ReadTransaction,
+
+    /// Name of the blob.
+    name: String,
+
+    /// The blob's chunks.
+    manifest: Manifest,
+
+    /// Index of the next chunk to load.
+    next_chunk: usize,
+
+    /// The current chunk's data.
+    chunk: Vec<u8>,
+
+    /// Number of the current chunk's bytes that have been read.
+    position: usize,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Manifest {
+    /// Encodes the manifest for the manifest table.
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(
+            MANIFEST_HEADER_SIZE + self.chunks.len() * MANIFEST_CHUNK_SIZE
+        );
+        bytes.extend_from_slice(&self.len.to_le_bytes());
+        let chunk_count = u32::try_from(self.chunks.len()).unwrap_or(u32::MAX);
+        bytes.extend_from_slice(&chunk_count.to_le_bytes());
+        for (hash, len) in &self.chunks {
+            bytes.extend_from_slice(hash);
+            bytes.extend_from_slice(&len.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Decodes a manifest from the manifest table.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::MalformedBlob`] if the manifest is truncated, or its chunk lengths don't
+    ///   add up to the blob length.
+    pub(crate) fn decode(name: &str, bytes: &[u8]) -> Result<Self, Error> {
+        let malformed = |reason| Error::MalformedBlob { name: name.to_string(), reason };
+
+        let (header, chunks) = bytes
+            .split_at_checked(MANIFEST_HEADER_SIZE)
+            .ok_or_else(|| malformed("manifest is truncated"))?;
+        let (len, chunk_count) = header.split_at(size_of::<u64>());
+        let len = u64::from_le_bytes(len.try_into().unwrap_or_default());
+        let chunk_count = u32::from_le_bytes(chunk_count.try_into().unwrap_or_default()) as usize;
+        if chunks.len() != chunk_count.saturating_mul(MANIFEST_CHUNK_SIZE) {
+            return Err(malformed("manifest chunk count doesn't match its length"));
+        }
+
+        let chunks = chunks
+            .chunks_exact(MANIFEST_CHUNK_SIZE)
+            .map(|chunk| {
+                let (hash, chunk_len) = chunk.split_at(CHUNK_HASH_SIZE);
+                (
+                    hash.try_into().unwrap_or_default(),
+                    u32::from_le_bytes(chunk_len.try_into().unwrap_or_default()),
+                )
+            })
+            .collect::<Vec<_>>();
+        if chunks.iter().map(|(_, chunk_len)| u64::from(*chunk_len)).sum::<u64>() != len {
+            return Err(malformed("manifest chunk lengths don't add up to the blob length"));
+        }
+
+        Ok(Self { len, chunks })
+    }
+}
+
+impl Chunker {
+    /// Scans `data`, the bytes of the current chunk so far, for the end of the chunk, and returns
+    /// the chunk's length once it's found. Bytes scanned by earlier calls aren't scanned again.
+    fn next_boundary(&mut self, data: &[u8]) -> Option<usize> {
+        let start = self.scanned.max(MIN_CHUNK_SIZE);
+        for (offset, byte) in data.iter().enumerate().skip(start) {
+            self.hash = (self.hash << 1).wrapping_add(GEAR[usize::from(*byte)]);
+            if self.hash & BOUNDARY_MASK == 0 || offset + 1 >= MAX_CHUNK_SIZE {
+                *self = Self::default();
+                return Some(offset + 1);
+            }
+        }
+
+        self.scanned = data.len();
+        None
+    }
+}
+
+#[cfg(feature = "writes")]
+impl<'txn> BlobWriter<'txn> {
+    /// Starts writing the named blob in the given transaction.
+    pub(crate) fn new(txn: &'txn mut WriteTransaction, name: &str) -> Self {
+        Self {
+            txn,
+            name: name.to_string(),
+            buffer: Vec::new(),
+            chunker: Chunker::default(),
+            manifest: Manifest::default(),
+            chunks_written: 0,
+            finished: false,
+        }
+    }
+
+    /// Stores the last chunk and the blob's manifest, replacing any existing blob with the same
+    /// name. The chunks of the replaced blob are released.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::MalformedBlob`] if the replaced blob's manifest can't be decoded.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn finish(mut self) -> Result<BlobInfo, Error> {
+        if !self.buffer.is_empty() {
+            let chunk = std::mem::take(&mut self.buffer);
+            self.store_chunk(&chunk)?;
+        }
+
+        let replaced = self.txn.put_blob_manifest(&self.name, &self.manifest)?;
+        self.finished = true;
+        if let Some(replaced) = replaced {
+            self.txn.release_blob_chunks(&replaced)?;
+        }
+
+        Ok(BlobInfo {
+            len: self.manifest.len,
+            chunks: self.manifest.chunks.len() as u64,
+            chunks_written: self.chunks_written,
+        })
+    }
+
+    /// Stores a chunk, or adds a reference to it if it's already stored, and lists it in the
+    /// manifest.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    fn store_chunk(&mut self, chunk: &[u8]) -> Result<(), Error> {
+        let hash = *blake3::hash(chunk).as_bytes();
+        if self.txn.retain_blob_chunk(&hash, chunk)? {
+            self.chunks_written += 1;
+        }
+
+        let chunk_len = u32::try_from(chunk.len()).unwrap_or(u32::MAX);
+        self.manifest.chunks.push((hash, chunk_len));
+        self.manifest.len += u64::from(chunk_len);
+        Ok(())
+    }
+}
+
+impl<'txn> BlobReader<'txn> {
+    /// Starts reading a blob with the given manifest.
+    pub(crate) fn new(txn: &'txn ReadTransaction, name: &str, manifest: Manifest) -> Self {
+        Self {
+            txn,
+            name: name.to_string(),
+            manifest,
+            next_chunk: 0,
+            chunk: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Returns the length of the blob, in bytes.
+    #[must_use]
+    pub const fn len(&self) -> u64 {
+        self.manifest.len
+    }
+
+    /// Returns `true` if the blob is empty.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.manifest.len == 0
+    }
+
+    /// Loads the next chunk, and verifies it against its hash. Returns `false` once every chunk was
+    /// read.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::MalformedBlob`] if the chunk is missing, or doesn't match its hash or
+    ///   length.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    fn load_next_chunk(&mut self) -> Result<bool, Error> {
+        let Some((hash, chunk_len)) = self.manifest.chunks.get(self.next_chunk) else {
+            return Ok(false);
+        };
+
+        let malformed = |reason| Error::MalformedBlob { name: self.name.clone(), reason };
+        let chunk = self.txn
+            .read_blob_chunk(hash)?
+            .ok_or_else(|| malformed("chunk is missing"))?;
+        if chunk.len() != *chunk_len as usize || blake3::hash(&chunk).as_bytes() != hash {
+            return Err(malformed("chunk doesn't match its hash"));
+        }
+
+        self.chunk = chunk;
+        self.position = 0;
+        self.next_chunk += 1;
+        Ok(true)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+#[cfg(feature = "writes")]
+impl std::io::Write for BlobWriter<'_> {
+    /// Buffers the bytes, and stores every chunk that's cut from them.
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+
+        let mut start = 0;
+        while let Some(chunk_len) = self.chunker.next_boundary(&self.buffer[start..]) {
+            let chunk = self.buffer[start..start + chunk_len].to_vec();
+            self.store_chunk(&chunk).map_err(std::io::Error::other)?;
+            start += chunk_len;
+        }
+        self.buffer.drain(..start);
+
+        Ok(buf.len())
+    }
+
+    /// Chunks are cut by content, so there's nothing to flush until the writer is finished.
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "writes")]
+impl Drop for BlobWriter<'_> {
+    /// Releases the chunks stored by an unfinished writer.
+    fn drop(&mut self) {
+        if !self.finished {
+            // Errors can't be returned from `drop`. A failing storage backend fails the
+            // transaction's commit as well:
+            let _ = self.txn.release_blob_chunks(&self.manifest);
+        }
+    }
+}
+
+impl std::io::Read for BlobReader<'_> {
+    /// Copies bytes from the current chunk, loading the next chunk once it's used up.
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.position == self.chunk.len()
+            && !self.load_next_chunk().map_err(std::io::Error::other)?
+        {
+            return Ok(0);
+        }
+
+        let available = &self.chunk[self.position..];
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Builds the gear table with the `SplitMix64` generator, so that chunk boundaries are the same
+/// in every build.
+const fn gear_table() -> [u64; 256] {
+    let mut table = [0_u64; 256];
+    let mut state = 0x9E37_79B9_7F4A_7C15_u64;
+    let mut index = 0;
+    while index < table.len() {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut value = state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[index] = value ^ (value >> 31);
+        index += 1;
+    }
+    table
+}
+
+/// Encodes a chunk row for the chunk table: its reference count, followed by its data.
+#[cfg(feature = "writes")]
+pub(crate) fn encode_chunk(references: u64, chunk: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(size_of::<u64>() + chunk.len());
+    bytes.extend_from_slice(&references.to_le_bytes());
+    bytes.extend_from_slice(chunk);
+    bytes
+}
+
+/// Splits a chunk row into its reference count and its data, or returns `None` if it's truncated.
+pub(crate) fn decode_chunk(bytes: &[u8]) -> Option<(u64, &[u8])> {
+    let (references, chunk) = bytes.split_at_checked(size_of::<u64>())?;
+    Some((u64::from_le_bytes(references.try_into().ok()?), chunk))
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cuts `data` into chunks, feeding it to the chunker `step` bytes at a time.
+    fn chunk_lens(data: &[u8], step: usize) -> Vec<usize> {
+        let mut chunker = Chunker::default();
+        let (mut start, mut end, mut lens) = (0, 0, Vec::new());
+        while end < data.len() {
+            end = (end + step).min(data.len());
+            while let Some(len) = chunker.next_boundary(&data[start..end]) {
+                lens.push(len);
+                start += len;
+            }
+        }
+        lens.push(data.len() - start);
+        lens
+    }
+
+    #[test]
+    fn chunk_boundaries_depend_on_content() {
+        let mut state = 0x2545_f491_u32;
+        let data = (0..1_000_000).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state.to_le_bytes()[0]
+        }).collect::<Vec<u8>>();
+
+        let lens = chunk_lens(&data, data.len());
+        assert_eq!(lens, chunk_lens(&data, 4_096));
+        assert_eq!(lens.iter().sum::<usize>(), data.len());
+        assert!(lens[..lens.len() - 1]
+            .iter()
+            .all(|len| (MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(len)));
+
+        // Prepending bytes only changes the first chunk:
+        let mut shifted = vec![7; 100];
+        shifted.extend_from_slice(&data);
+        let shifted_lens = chunk_lens(&shifted, shifted.len());
+        assert_eq!(shifted_lens[1..], lens[1..]);
+    }
+
+    #[test]
+    fn manifests_round_trip() {
+        let manifest = Manifest { len: 300, chunks: vec![([1; 32], 100), ([2; 32], 200)] };
+        assert_eq!(Manifest::decode("blob", &manifest.encode()).unwrap(), manifest);
+
+        let mut truncated = manifest.encode();
+        truncated.pop();
+        assert!(matches!(
+            Manifest::decode("blob", &truncated),
+            Err(Error::MalformedBlob { .. })
+        ));
+    }
+}
@@ -0,0 +1,276 @@
+//! Streaming bulk loads, for initial imports of millions of records.
+//!
+//! A [`BulkLoader`] buffers records and writes each full batch in a write transaction of its own,
+//! so that no single transaction grows to the size of the whole import. Batches are committed with
+//! relaxed durability: they're visible once committed, but only persisted by the final commit.
+//! Only primary table rows are written per batch. Secondary indexes registered with
+//! [`BulkLoader::index`] are built from scratch in a final pass, which is much cheaper than
+//! updating every index entry once per record.
+//!
+//! A load that fails part-way leaves the batches committed so far in the table, without their index
+//! entries, and not persisted if the process then exits. Run the load again, or rebuild the
+//! indexes with the write transaction's `rebuild_index` method.
+
+use crate::defaults::Defaults;
+use crate::indexing::{HasPrimaryKey, HasTable, Index, Indexable, References};
+use crate::typed::Namespace;
+use crate::typed::database::Database;
+use crate::typed::transaction::WriteTransaction;
+use crate::validation::Validate;
+use crate::{Codec, Error};
+use std::marker::PhantomData;
+use std::time::{Duration, SystemTime};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// Number of records written in each write transaction, unless changed with
+/// [`BulkLoader::batch_size`].
+const DEFAULT_BATCH_SIZE: usize = 10_000;
+
+/// Builds an index from every record in its primary table, returning the number of distinct
+/// secondary keys written.
+type BuildIndex = fn(&mut WriteTransaction) -> Result<u64, Error>;
+
+/// Called with the loader's progress after each batch is committed.
+type ProgressCallback<'db> = Box<dyn FnMut(&LoadProgress) + 'db>;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Loads records of type `V` into their table across many write transactions.
+///
+/// Each record has its [`Defaults`] applied, and is checked against its [`Validate`] rules and
+/// references, like [`WriteTransaction::bulk_insert`]. Records replace any existing record with the
+/// same primary key. Quotas, history, and the change log are bypassed, as with other direct table
+/// writes.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let mut loader = BulkLoader::<u64, Creature>::new(&db)
+///     .batch_size(50_000)
+///     .index::<HabitatIndex>()
+///     .on_progress(|progress| println!("{} records loaded", progress.records));
+///
+/// loader.extend(read_creatures("creatures.csv"))?;
+/// let status = loader.finish()?;
+/// ```
+pub struct BulkLoader<'db, K, V> {
+    database: &'db Database,
+    namespace: Namespace,
+    batch_size: usize,
+    indexes: Vec<BuildIndex>,
+    progress: Option<ProgressCallback<'db>>,
+    pending: Vec<V>,
+    status: LoadProgress,
+    started: SystemTime,
+    _phantom: PhantomData<K>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The progress of a bulk load, passed to the callback given to [`BulkLoader::on_progress`] after
+/// each batch and after the final pass, and returned by [`BulkLoader::finish`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LoadProgress {
+    /// Number of records written.
+    pub records: u64,
+
+    /// Number of write transactions committed.
+    pub batches_committed: u64,
+
+    /// Number of indexes built by the final pass. Zero until the final pass is done.
+    pub indexes_built: u64,
+
+    /// Number of distinct secondary keys written by the final pass.
+    pub index_keys_written: u64,
+
+    /// Time since the loader was created.
+    pub elapsed: Duration,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<'db, K, V> BulkLoader<'db, K, V>
+where
+    K: Codec<K>,
+    V: for<'v> HasPrimaryKey<'v, K>
+        + for<'i> Indexable<'i>
+        + References
+        + Defaults
+        + Validate
+        + Codec<V>
+        + HasTable,
+{
+    /// Instantiates a loader into the default namespace, in batches of `10000`, that doesn't build
+    /// any indexes.
+    #[must_use]
+    pub fn new(database: &'db Database) -> Self {
+        Self {
+            database,
+            namespace: Namespace::default(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            indexes: Vec::new(),
+            progress: None,
+            pending: Vec::new(),
+            status: LoadProgress::default(),
+            started: crate::clock::now(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Loads into the table in the given namespace, rather than the default namespace.
+    #[must_use]
+    pub fn in_namespace(mut self, namespace: Namespace) -> Self {
+        self.namespace = namespace;
+        self
+    }
+
+    /// Sets the number of records written in each write transaction (defaults to `10000`).
+    /// Larger batches commit less often, but hold more records in memory.
+    #[must_use]
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Registers an index of the record type, to be built from scratch once every record has been
+    /// written. Indexes that aren't registered aren't updated by the load.
+    #[must_use]
+    pub fn index<I: Index<Record = V>>(mut self) -> Self {
+        self.indexes.push(WriteTransaction::rebuild_index::<V, I>);
+        self
+    }
+
+    /// Sets a callback that's invoked after each batch is committed, and after the final pass.
+    #[must_use]
+    pub fn on_progress(mut self, progress: impl FnMut(&LoadProgress) + 'db) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Adds a record to the load. The batch is written once it's full.
+    ///
+    /// # Errors
+    ///
+    /// * See [`BulkLoader::finish`].
+    pub fn push(&mut self, record: V) -> Result<(), Error> {
+        self.pending.push(record);
+        if self.pending.len() >= self.batch_size {
+            self.write_batch()?;
+        }
+        Ok(())
+    }
+
+    /// Adds every record of an iterator to the load, writing batches as they fill up. The iterator
+    /// is consumed lazily, so it can yield more records than fit in memory.
+    ///
+    /// # Errors
+    ///
+    /// * See [`BulkLoader::finish`].
+    pub fn extend(&mut self, records: impl IntoIterator<Item = V>) -> Result<(), Error> {
+        records.into_iter().try_for_each(|record| self.push(record))
+    }
+
+    /// Writes the last batch, builds the registered indexes, and commits durably. Returns the
+    /// load's final progress.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::ConstraintViolations`] if a record is invalid, or
+    ///   [`Error::ForeignKeyViolation`] if it refers to a primary key that doesn't exist. The
+    ///   batch it's in isn't written.
+    ///
+    /// * Returns [`Error::IndexCollision`] if more than one record has the same key in a `Unique`
+    ///   index. The index is left as it was before the load.
+    ///
+    /// * Encoding a primary key, record, secondary key, or key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn finish(mut self) -> Result<LoadProgress, Error> {
+        if !self.pending.is_empty() {
+            self.write_batch()?;
+        }
+
+        // The final transaction is committed with the default durability, which also persists
+        // every batch before it:
+        let mut txn = self.database.write_in(&self.namespace)?;
+        for build_index in &self.indexes {
+            self.status.index_keys_written += build_index(&mut txn)?;
+            self.status.indexes_built += 1;
+        }
+        txn.commit()?;
+        self.status.batches_committed += 1;
+
+        self.report();
+        Ok(self.status)
+    }
+
+    /// Writes and commits the pending records, with relaxed durability.
+    fn write_batch(&mut self) -> Result<(), Error> {
+        let mut txn = self.database.write_in(&self.namespace)?;
+        txn.load_batch::<K, V>(&self.pending)?;
+        txn.commit()?;
+
+        self.status.records += self.pending.len() as u64;
+        self.status.batches_committed += 1;
+        self.pending.clear();
+        self.report();
+        Ok(())
+    }
+
+    /// Updates the elapsed time, and passes the progress to the callback.
+    fn report(&mut self) {
+        self.status.elapsed = crate::clock::since(self.started);
+        if let Some(progress) = &mut self.progress {
+            progress(&self.status);
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexing::ReadableKeySet;
+    use crate::querying::Query;
+    use crate::typed::test_records::{Animal, Enclosure, EnclosureIndex};
+
+    #[test]
+    fn loads_in_batches_and_builds_indexes_last() {
+        let db = Database::in_memory().unwrap();
+        let mut reported = Vec::new();
+        let mut loader = BulkLoader::<u64, Animal>::new(&db)
+            .batch_size(2)
+            .index::<EnclosureIndex>()
+            .on_progress(|progress| reported.push(progress.records));
+
+        loader.extend([
+            Animal::new(1, "Lion", "Savannah"),
+            Animal::new(2, "Penguin", "Arctic"),
+            Animal::new(3, "Zebra", "Savannah"),
+            Animal::new(4, "Walrus", "Arctic"),
+        ]).unwrap();
+        loader.push(Animal::new(5, "Giraffe", "Savannah")).unwrap();
+        let status = loader.finish().unwrap();
+
+        assert_eq!(status.records, 5);
+        assert_eq!(status.batches_committed, 4);
+        assert_eq!(status.indexes_built, 1);
+        assert_eq!(status.index_keys_written, 2);
+        assert_eq!(reported, [2, 4, 5, 5]);
+
+        let txn = db.read().unwrap();
+        let savannah = txn.query::<u64, Animal>(Query::lookup(Enclosure("Savannah".into())));
+        assert_eq!(savannah.unwrap().len(), 3);
+        let giraffe = txn.get::<u64, Animal>(&5).unwrap();
+        assert_eq!(giraffe, Some(Animal::new(5, "Giraffe", "Savannah")));
+    }
+}
@@ -0,0 +1,224 @@
+//! Tamper-evident hash chains over the change log and the audit log.
+//!
+//! With the `hash-chain` feature, every entry appended to either log is linked to the entry
+//! before it by a running BLAKE3 hash, which is stored in the entry:
+//!
+//! ```text
+//! link = BLAKE3(previous link | sequence number (u64 little-endian) | entry without its link)
+//! ```
+//!
+//! The first chained entry links to [`GENESIS`]. Modifying, removing, or reordering any chained
+//! entry changes every link after it, which [`ReadTransaction::verify_chain`] detects.
+//!
+//! Removing entries from the end of a log leaves a valid, shorter chain. To detect that, keep a
+//! copy of a recent [`ChainHead`] outside of the database, for example in another system or
+//! signed, and pass it to `verify_chain` as an anchor: the chain must still pass through it.
+//!
+//! Entries recorded before the feature was enabled aren't chained, and aren't protected.
+//!
+//! [`ReadTransaction::verify_chain`]: crate::typed::transaction::ReadTransaction::verify_chain
+
+use crate::Error;
+use crate::typed::audit::{AuditEntry, audit_chain};
+use crate::typed::change_log::{Change, change_chain, encode_change};
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The link that the first chained entry of a log is chained to.
+pub const GENESIS: [u8; 32] = [0; 32];
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A log that entries are chained in.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Log {
+    /// The change-data-capture log. See the [`change_log`](crate::typed::change_log) module.
+    Changes,
+
+    /// The audit log. See the [`audit`](crate::typed::audit) module.
+    Audit,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The latest link of a log's hash chain.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ChainHead {
+    /// Sequence number of the entry the link belongs to.
+    pub sequence: u64,
+
+    /// The entry's link.
+    pub link: [u8; 32],
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The outcome of verifying a log's hash chain with
+/// [`ReadTransaction::verify_chain`](crate::typed::transaction::ReadTransaction::verify_chain).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ChainReport {
+    /// The number of chained entries that were verified.
+    pub chained: u64,
+
+    /// The number of entries at the start of the log that were recorded before chaining was
+    /// enabled, and aren't protected.
+    pub unchained: u64,
+
+    /// The log's latest link, or `None` if no entries are chained.
+    pub head: Option<ChainHead>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Log {
+    /// Returns the log's name, for errors.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Changes => "change log",
+            Self::Audit => "audit log",
+        }
+    }
+
+    /// Returns an encoded entry's link, or `None` if the entry isn't chained.
+    pub(crate) fn link_of(self, entry: &[u8]) -> Option<[u8; 32]> {
+        match self {
+            Self::Changes => change_chain(entry),
+            Self::Audit => audit_chain(entry),
+        }
+    }
+
+    /// Decodes an entry, and returns its link along with its encoding without the link.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::MalformedChangeLog`] or [`Error::MalformedAuditLog`] if the entry is
+    ///   truncated or malformed.
+    pub(crate) fn unlink(
+        self,
+        sequence: u64,
+        entry: &[u8],
+    ) -> Result<(Option<[u8; 32]>, Vec<u8>), Error> {
+        match self {
+            Self::Changes => {
+                let Change { table, key, value, chain, .. } = Change::decode(sequence, entry)?;
+                Ok((chain, encode_change(&table, &key, value.as_deref(), None)))
+            },
+            Self::Audit => {
+                let entry = AuditEntry::decode(sequence, entry)?;
+                Ok((entry.chain, AuditEntry { chain: None, ..entry }.encode()))
+            },
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Computes an entry's link from the previous entry's link, its sequence number, and its encoding
+/// without its link.
+#[must_use]
+pub(crate) fn link(previous: &[u8; 32], sequence: u64, unlinked: &[u8]) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(previous);
+    hasher.update(&sequence.to_le_bytes());
+    hasher.update(unlinked);
+    *hasher.finalize().as_bytes()
+}
+
+/// Computes the link of the next entry to be appended to a log, from the log's last entry.
+///
+/// # Errors
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+pub(crate) fn next_link(
+    log: Log,
+    table: &impl redb::ReadableTable<u64, &'static [u8]>,
+    sequence: u64,
+    unlinked: &[u8],
+) -> Result<[u8; 32], Error> {
+    let previous = table
+        .last()?
+        .and_then(|(_, entry)| log.link_of(entry.value()))
+        .unwrap_or(GENESIS);
+    Ok(link(&previous, sequence, unlinked))
+}
+
+/// Verifies every link of a log's hash chain, and that the chain passes through `anchor`, if one
+/// is given.
+///
+/// # Errors
+///
+/// * Returns [`Error::ChainBroken`] if an entry was modified, removed, or reordered, if an
+///   unchained entry follows a chained one, or if the chain doesn't pass through `anchor`.
+///
+/// * Returns [`Error::MalformedChangeLog`] or [`Error::MalformedAuditLog`] if an entry is
+///   truncated or malformed.
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+pub(crate) fn verify_log(
+    log: Log,
+    table: &impl redb::ReadableTable<u64, &'static [u8]>,
+    anchor: Option<&ChainHead>,
+) -> Result<ChainReport, Error> {
+    let broken = |sequence, reason| Error::ChainBroken { log: log.name(), sequence, reason };
+
+    let mut report = ChainReport::default();
+    let mut previous = GENESIS;
+    let mut expected_sequence = 1;
+    for entry in table.iter()? {
+        let (sequence, entry) = entry?;
+        let sequence = sequence.value();
+        if sequence != expected_sequence {
+            return Err(broken(expected_sequence, "entries are missing"));
+        }
+        expected_sequence += 1;
+
+        match log.unlink(sequence, entry.value())? {
+            (None, _) if report.head.is_none() => report.unchained += 1,
+            (None, _) => return Err(broken(sequence, "entry isn't chained")),
+            (Some(stored), unlinked) => {
+                if stored != link(&previous, sequence, &unlinked) {
+                    return Err(broken(sequence, "entry was modified"));
+                }
+                let misses_anchor = anchor
+                    .is_some_and(|anchor| anchor.sequence == sequence && anchor.link != stored);
+                if misses_anchor {
+                    return Err(broken(sequence, "chain doesn't pass through the anchor"));
+                }
+                previous = stored;
+                report.chained += 1;
+                report.head = Some(ChainHead { sequence, link: stored });
+            },
+        }
+    }
+
+    match anchor {
+        Some(anchor) if anchor.sequence >= expected_sequence => {
+            Err(broken(anchor.sequence, "entries were truncated"))
+        },
+        _ => Ok(report),
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_depend_on_everything_before_them() {
+        let first = link(&GENESIS, 1, b"kelp");
+        let second = link(&first, 2, b"urchin");
+        assert_ne!(second, link(&GENESIS, 2, b"urchin"));
+        assert_ne!(second, link(&first, 3, b"urchin"));
+        assert_ne!(second, link(&first, 2, b"urchins"));
+        assert_eq!(second, link(&link(&GENESIS, 1, b"kelp"), 2, b"urchin"));
+    }
+}
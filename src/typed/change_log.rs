@@ -0,0 +1,199 @@
+//! The change-data-capture (CDC) log, which records every record written or deleted through a
+//! write transaction in commit order.
+
+use crate::Error;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Name of the internal table that stores the change log, keyed by sequence number.
+///
+/// The change log is shared by every namespace. Each change records the full, namespaced name of
+/// the table it applies to.
+pub const CHANGE_LOG_TABLE_NAME: &str = "__atlatl_change_log";
+
+/// Definition of the change log table: sequence number → encoded [`Change`].
+pub(crate) const CHANGE_LOG_TABLE: redb::TableDefinition<u64, &[u8]> =
+    redb::TableDefinition::new(CHANGE_LOG_TABLE_NAME);
+
+/// Marks a change that wrote a record.
+const TAG_WRITE: u8 = 1;
+
+/// Marks a change that deleted a record.
+const TAG_DELETE: u8 = 0;
+
+/// Marks a change that wrote a record, and is linked into the log's hash chain.
+const TAG_WRITE_CHAINED: u8 = 3;
+
+/// Marks a change that deleted a record, and is linked into the log's hash chain.
+const TAG_DELETE_CHAINED: u8 = 2;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A single entry in the change log: a record that was written or deleted.
+///
+/// Changes are recorded at the primary table level, so a change's key and value are the record's
+/// serialized primary key and record bytes. Secondary index tables aren't logged, since they can
+/// be rebuilt from their primary tables.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Change {
+    /// Position of the change in the log. Sequence numbers start at `1`, and increase by one for
+    /// every change.
+    pub sequence: u64,
+
+    /// Full name of the table the change applies to, including its namespace prefix. For example:
+    /// `"tenant42.creatures"`.
+    pub table: String,
+
+    /// The record's serialized primary key.
+    pub key: Vec<u8>,
+
+    /// The record's serialized bytes after the change, or `None` if the record was deleted.
+    pub value: Option<Vec<u8>>,
+
+    /// The change's link in the log's hash chain, or `None` if it was recorded without the
+    /// `hash-chain` feature. See the [`chain`](crate::typed::chain) module.
+    pub chain: Option<[u8; 32]>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Change {
+    /// Encodes the change for storage in the change log. The sequence number is the log's key, so
+    /// it isn't included.
+    ///
+    /// Layout: `table length (u32) | table | key length (u32) | key | tag (u8) | [chain (32 bytes)]
+    /// | value`. The chain is only present if the tag says so.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        encode_change(&self.table, &self.key, self.value.as_deref(), self.chain.as_ref())
+    }
+
+    /// Decodes a change that was stored in the change log under the given sequence number.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::MalformedChangeLog`] if the bytes are truncated or malformed.
+    pub fn decode(sequence: u64, mut bytes: &[u8]) -> Result<Self, Error> {
+        let table = take_prefixed(&mut bytes)?;
+        let table = String::from_utf8(table.to_vec())
+            .map_err(|_| Error::MalformedChangeLog { reason: "table name is not UTF-8" })?;
+        let key = take_prefixed(&mut bytes)?.to_vec();
+        let (value, chain) = match bytes.split_first() {
+            Some((&TAG_WRITE, value)) => (Some(value.to_vec()), None),
+            Some((&TAG_DELETE, [])) => (None, None),
+            Some((&TAG_WRITE_CHAINED, rest)) => match rest.split_first_chunk::<32>() {
+                Some((chain, value)) => (Some(value.to_vec()), Some(*chain)),
+                None => return Err(Error::MalformedChangeLog { reason: "change is truncated" }),
+            },
+            Some((&TAG_DELETE_CHAINED, rest)) => match rest.split_first_chunk::<32>() {
+                Some((chain, [])) => (None, Some(*chain)),
+                _ => return Err(Error::MalformedChangeLog { reason: "invalid change tag" }),
+            },
+            _ => return Err(Error::MalformedChangeLog { reason: "invalid change tag" }),
+        };
+        Ok(Self { sequence, table, key, value, chain })
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Encodes a change without first copying its parts into a [`Change`].
+pub(crate) fn encode_change(
+    table: &str,
+    key: &[u8],
+    value: Option<&[u8]>,
+    chain: Option<&[u8; 32]>,
+) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(
+        41 + table.len() + key.len() + value.map_or(0, <[u8]>::len)
+    );
+    put_prefixed(&mut bytes, table.as_bytes());
+    put_prefixed(&mut bytes, key);
+    bytes.push(match (value, chain) {
+        (Some(_), None) => TAG_WRITE,
+        (None, None) => TAG_DELETE,
+        (Some(_), Some(_)) => TAG_WRITE_CHAINED,
+        (None, Some(_)) => TAG_DELETE_CHAINED,
+    });
+    if let Some(chain) = chain {
+        bytes.extend_from_slice(chain);
+    }
+    bytes.extend_from_slice(value.unwrap_or_default());
+    bytes
+}
+
+/// Returns an encoded change's link in the log's hash chain, without decoding the rest of it.
+/// Returns `None` if the change isn't chained, or is malformed.
+#[cfg(any(test, feature = "hash-chain"))]
+pub(crate) fn change_chain(mut bytes: &[u8]) -> Option<[u8; 32]> {
+    take_prefixed(&mut bytes).ok()?;
+    take_prefixed(&mut bytes).ok()?;
+    match bytes.split_first()? {
+        (&(TAG_WRITE_CHAINED | TAG_DELETE_CHAINED), rest) => Some(*rest.first_chunk::<32>()?),
+        _ => None,
+    }
+}
+
+/// Returns the sequence number of the last change in the log, or `0` if the log is empty or
+/// doesn't exist.
+///
+/// # Errors
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+pub(crate) fn last_sequence(
+    change_log: &impl redb::ReadableTable<u64, &'static [u8]>
+) -> Result<u64, Error> {
+    Ok(change_log.last()?.map_or(0, |(sequence, _)| sequence.value()))
+}
+
+/// Appends a length-prefixed byte string.
+pub(crate) fn put_prefixed(bytes: &mut Vec<u8>, field: &[u8]) {
+    let len = u32::try_from(field.len()).expect("change log fields are smaller than 4 GiB");
+    bytes.extend_from_slice(&len.to_le_bytes());
+    bytes.extend_from_slice(field);
+}
+
+/// Takes a length-prefixed byte string from the front of `bytes`.
+fn take_prefixed<'b>(bytes: &mut &'b [u8]) -> Result<&'b [u8], Error> {
+    let truncated = || Error::MalformedChangeLog { reason: "change is truncated" };
+    let (len, rest) = bytes.split_first_chunk::<4>().ok_or_else(truncated)?;
+    let len = u32::from_le_bytes(*len) as usize;
+    let field = rest.get(..len).ok_or_else(truncated)?;
+    *bytes = &rest[len..];
+    Ok(field)
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_round_trip() {
+        let write = Change {
+            sequence: 7,
+            table: "tenant42.creatures".to_string(),
+            key: b"1".to_vec(),
+            value: Some(b"Coyote".to_vec()),
+            chain: None,
+        };
+        assert_eq!(Change::decode(7, &write.encode()).unwrap(), write);
+        assert_eq!(change_chain(&write.encode()), None);
+
+        let delete = Change { value: None, ..write };
+        assert_eq!(Change::decode(7, &delete.encode()).unwrap(), delete);
+
+        assert!(Change::decode(7, &delete.encode()[..5]).is_err());
+
+        let chained = Change { chain: Some([9; 32]), ..delete };
+        assert_eq!(Change::decode(7, &chained.encode()).unwrap(), chained);
+        assert_eq!(change_chain(&chained.encode()), Some([9; 32]));
+    }
+}
@@ -0,0 +1,114 @@
+//! Field mappings for building records from the rows of a CSV file with
+//! [`WriteTransaction::import_csv`].
+//!
+//! [`WriteTransaction::import_csv`]: crate::typed::transaction::WriteTransaction::import_csv
+
+// -------------------------------------------------------------------------------------------------
+
+/// Number of records validated and written together by
+/// [`WriteTransaction::import_csv`](crate::typed::transaction::WriteTransaction::import_csv).
+pub const CSV_BATCH_SIZE: usize = 1_000;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Maps the columns of a CSV file to the fields of a record.
+///
+/// Rows are deserialized with `serde`, using each column's header as the field name. A mapping
+/// renames headers that don't match their record fields, for example a spreadsheet's `"Common
+/// Name"` column to a `name` field. Columns without a mapping keep their header as their field
+/// name. Columns with no matching field are ignored, unless the record is marked with
+/// `#[serde(deny_unknown_fields)]`.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let mapping = CsvMapping::new()
+///     .column("Common Name", "name")
+///     .column("Habitat", "habitat");
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CsvMapping {
+    /// Column header → record field name.
+    columns: Vec<(String, String)>,
+
+    /// The field delimiter, such as `b','` or `b'\t'`.
+    delimiter: u8,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl CsvMapping {
+    /// Returns a mapping that uses every column's header as its field name, for comma-delimited
+    /// files.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { columns: Vec::new(), delimiter: b',' }
+    }
+
+    /// Maps the column with the given header to a record field.
+    #[must_use]
+    pub fn column(mut self, header: impl Into<String>, field: impl Into<String>) -> Self {
+        self.columns.push((header.into(), field.into()));
+        self
+    }
+
+    /// Sets the field delimiter, for example `b'\t'` for tab-separated files. Defaults to `b','`.
+    #[must_use]
+    pub const fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Returns a CSV reader over the given input, configured for this mapping.
+    pub(crate) fn reader<R: std::io::Read>(&self, reader: R) -> csv::Reader<R> {
+        csv::ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .trim(csv::Trim::Headers)
+            .from_reader(reader)
+    }
+
+    /// Renames a CSV file's headers to their mapped record field names.
+    pub(crate) fn fields(&self, headers: &csv::StringRecord) -> csv::StringRecord {
+        headers
+            .iter()
+            .map(|header| self
+                .columns
+                .iter()
+                .find(|(column, _field)| column == header)
+                .map_or(header, |(_column, field)| field.as_str()))
+            .collect()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl Default for CsvMapping {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renames_mapped_headers() {
+        let mapping = CsvMapping::new().column("Common Name", "name").delimiter(b';');
+        let mut reader = mapping.reader("Common Name ; habitat\nCoyote;Desert\n".as_bytes());
+
+        let fields = mapping.fields(reader.headers().unwrap());
+        assert_eq!(fields, vec!["name", "habitat"]);
+
+        let row = reader.records().next().unwrap().unwrap();
+        assert_eq!(row.deserialize::<(String, String)>(Some(&fields)).unwrap().0, "Coyote");
+    }
+}
@@ -0,0 +1,332 @@
+//! A cursor that seeks to a key, and steps forward and backward from it.
+
+use crate::typed::{RedbRange, ResultEntry};
+use crate::{Codec, Error};
+use redb::ReadableTable;
+use std::marker::PhantomData;
+use std::ops::Bound;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Where a cursor is, between steps.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Position {
+    /// Before the first entry when stepping forward, and after the last when stepping backward.
+    Unset,
+
+    /// Just before the first entry at or after a key, after a seek.
+    Before(Vec<u8>),
+
+    /// At the entry with a key, after a step.
+    At(Vec<u8>),
+}
+
+/// Which way a cursor's range is being walked.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Direction {
+    Forward,
+    Backward,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A cursor over a table's decoded key-value pairs, which seeks to a key and steps forward and
+/// backward from it.
+///
+/// The cursor keeps the `redb` range it's walking, so that consecutive steps in one direction are
+/// as cheap as iterating. Changing direction, or seeking, starts a new range from the cursor's
+/// position. This suits stateful iteration such as resumable scans and merge joins, which would
+/// otherwise recreate a range for every step.
+///
+/// The cursor is returned by `OrderedTable::cursor`, and is only available for keys that implement
+/// [`OrderedWhenSerialized`].
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let mut cursor = table.cursor();
+/// let first_at_or_after = cursor.seek(&1_000)?;
+/// let following = cursor.next().transpose()?;
+/// let preceding = cursor.prev().transpose()?;
+/// ```
+///
+/// [`OrderedWhenSerialized`]: crate::layers::serializers::OrderedWhenSerialized
+pub struct Cursor<'c, K, V, T>
+where
+    T: ReadableTable<&'static [u8], &'static [u8]>,
+{
+    table: &'c T,
+    lower: Bound<Vec<u8>>,
+    upper: Bound<Vec<u8>>,
+    position: Position,
+    steps: Option<(Direction, RedbRange<'c>)>,
+    _phantom: PhantomData<(K, V)>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<'c, K, V, T> Cursor<'c, K, V, T>
+where
+    K: Codec<K>,
+    V: Codec<V>,
+    T: ReadableTable<&'static [u8], &'static [u8]>,
+{
+    /// Instantiates a cursor over every entry of a table. The first step forward returns the first
+    /// entry, and the first step backward returns the last.
+    #[must_use]
+    pub const fn new(table: &'c T) -> Self {
+        Self {
+            table,
+            lower: Bound::Unbounded,
+            upper: Bound::Unbounded,
+            position: Position::Unset,
+            steps: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Moves the cursor to the first entry whose key is at or after `key`, and returns it. Returns
+    /// `None` if there's no such entry, in which case stepping backward returns the last entry
+    /// before `key`.
+    ///
+    /// Any prefix set by [`Cursor::seek_prefix`] is cleared.
+    ///
+    /// # Errors
+    ///
+    /// * Encoding the key, or decoding the entry fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn seek(&mut self, key: &K) -> Result<Option<(K, V)>, Error> {
+        self.lower = Bound::Unbounded;
+        self.upper = Bound::Unbounded;
+        self.seek_bytes(K::serialize(key)?)
+    }
+
+    /// Moves the cursor to the first entry whose serialized key starts with `prefix`, and returns
+    /// it. Until the next seek, the cursor only steps over entries with the prefix.
+    ///
+    /// The prefix is compared with keys as they're stored, after serialization, so it's most
+    /// useful for composite keys whose serialized fields are laid out in order.
+    ///
+    /// # Errors
+    ///
+    /// * Decoding the entry fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn seek_prefix(&mut self, prefix: impl AsRef<[u8]>) -> Result<Option<(K, V)>, Error> {
+        let prefix = prefix.as_ref().to_vec();
+        self.upper = prefix_end(&prefix).map_or(Bound::Unbounded, Bound::Excluded);
+        self.lower = Bound::Included(prefix.clone());
+        self.seek_bytes(prefix)
+    }
+
+    /// Moves the cursor to the previous entry, and returns it. Returns `None`, and leaves the
+    /// cursor where it is, if there's no previous entry.
+    ///
+    /// # Errors
+    ///
+    /// * Returns an error if decoding the key or value fails. The cursor still moves past the
+    ///   entry, so that stepping can continue.
+    pub fn prev(&mut self) -> Option<ResultEntry<K, V>> {
+        self.step(Direction::Backward)
+    }
+
+    /// Returns the serialized key of the entry the cursor is at, or `None` if it isn't at an
+    /// entry.
+    #[must_use]
+    pub fn key_bytes(&self) -> Option<&[u8]> {
+        match &self.position {
+            Position::At(key) => Some(key),
+            Position::Unset | Position::Before(_) => None,
+        }
+    }
+
+    /// Positions the cursor just before `key`, then steps forward.
+    fn seek_bytes(&mut self, key: Vec<u8>) -> Result<Option<(K, V)>, Error> {
+        self.position = Position::Before(key);
+        self.steps = None;
+        self.step(Direction::Forward).transpose()
+    }
+
+    /// Steps in a direction, starting a new range from the cursor's position if the current one
+    /// doesn't go that way.
+    fn step(&mut self, direction: Direction) -> Option<ResultEntry<K, V>> {
+        if self.steps.as_ref().is_none_or(|(walking, _)| *walking != direction) {
+            match self.range(direction) {
+                Ok(range) => self.steps = Some((direction, range)),
+                Err(error) => return Some(Err(error)),
+            }
+        }
+
+        let (_, range) = self.steps.as_mut()?;
+        let entry = match direction {
+            Direction::Forward => range.next(),
+            Direction::Backward => range.next_back(),
+        }?;
+
+        Some(entry.map_err(Into::into).and_then(|(k_guard, v_guard)| {
+            self.position = Position::At(k_guard.value().to_vec());
+            let key   = K::deserialize(k_guard.value())?;
+            let value = V::deserialize(v_guard.value())?;
+            Ok((key, value))
+        }))
+    }
+
+    /// Returns the range of entries from the cursor's position to the end of its bounds, in a
+    /// direction.
+    fn range(&self, direction: Direction) -> Result<RedbRange<'c>, Error> {
+        let table: &'c T = self.table;
+        let lower = self.lower.as_ref().map(Vec::as_slice);
+        let upper = self.upper.as_ref().map(Vec::as_slice);
+        let range = match (&self.position, direction) {
+            (Position::Unset, _) => table.range::<&[u8]>((lower, upper))?,
+            (Position::Before(key), Direction::Forward) =>
+                table.range::<&[u8]>((Bound::Included(key.as_slice()), upper))?,
+            (Position::At(key), Direction::Forward) =>
+                table.range::<&[u8]>((Bound::Excluded(key.as_slice()), upper))?,
+            (Position::Before(key) | Position::At(key), Direction::Backward) =>
+                table.range::<&[u8]>((lower, Bound::Excluded(key.as_slice())))?,
+        };
+        Ok(range)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl<K, V, T> Iterator for Cursor<'_, K, V, T>
+where
+    K: Codec<K>,
+    V: Codec<V>,
+    T: ReadableTable<&'static [u8], &'static [u8]>,
+{
+    type Item = ResultEntry<K, V>;
+
+    /// Moves the cursor to the next entry, and returns it. Returns `None`, and leaves the cursor
+    /// where it is, if there's no next entry.
+    ///
+    /// # Errors
+    ///
+    /// * Returns an error if decoding the key or value fails. The cursor still moves past the
+    ///   entry, so that stepping can continue.
+    fn next(&mut self) -> Option<Self::Item> {
+        self.step(Direction::Forward)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Returns the smallest key that's after every key starting with `prefix`, or `None` if there's no
+/// such key because the prefix is empty or all `0xFF` bytes.
+fn prefix_end(prefix: &[u8]) -> Option<Vec<u8>> {
+    let last = prefix.iter().rposition(|byte| *byte != u8::MAX)?;
+    let mut end = prefix[..=last].to_vec();
+    end[last] += 1;
+    Some(end)
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use redb::TableDefinition;
+
+    const TABLE: TableDefinition<&[u8], &[u8]> = TableDefinition::new("keepers");
+
+    /// Returns a table of equal-length string keys, which sort the same serialized as they do
+    /// decoded, each mapped to its position.
+    fn table() -> redb::ReadOnlyTable<&'static [u8], &'static [u8]> {
+        let db = redb::Database::builder()
+            .create_with_backend(redb::backends::InMemoryBackend::new())
+            .unwrap();
+        let txn = db.begin_write().unwrap();
+        {
+            let mut table = txn.open_table(TABLE).unwrap();
+            for (position, key) in ["a1", "a2", "b1", "b2", "c1"].into_iter().enumerate() {
+                let key = String::serialize(&key.to_string()).unwrap();
+                let value = u64::serialize(&(position as u64)).unwrap();
+                table.insert(&*key, &*value).unwrap();
+            }
+        }
+        txn.commit().unwrap();
+        db.begin_read().unwrap().open_table(TABLE).unwrap()
+    }
+
+    fn entry(key: &str, position: u64) -> (String, u64) {
+        (key.to_string(), position)
+    }
+
+    #[test]
+    fn seeks_and_steps_both_ways() {
+        let table = table();
+        let mut cursor = Cursor::<String, u64, _>::new(&table);
+        assert_eq!(cursor.seek(&"b0".to_string()).unwrap(), Some(entry("b1", 2)));
+        assert_eq!(cursor.next().transpose().unwrap(), Some(entry("b2", 3)));
+        assert_eq!(cursor.next().transpose().unwrap(), Some(entry("c1", 4)));
+
+        // Changing direction returns the entries before the cursor, not the one it's at:
+        assert_eq!(cursor.prev().transpose().unwrap(), Some(entry("b2", 3)));
+        assert_eq!(cursor.prev().transpose().unwrap(), Some(entry("b1", 2)));
+        assert_eq!(cursor.next().transpose().unwrap(), Some(entry("b2", 3)));
+        assert_eq!(cursor.key_bytes(), Some(&*String::serialize(&"b2".to_string()).unwrap()));
+
+        // A seek to an existing key lands on it:
+        assert_eq!(cursor.seek(&"a2".to_string()).unwrap(), Some(entry("a2", 1)));
+        assert_eq!(cursor.prev().transpose().unwrap(), Some(entry("a1", 0)));
+    }
+
+    #[test]
+    fn stops_at_the_ends_of_the_table() {
+        let table = table();
+        let mut cursor = Cursor::<String, u64, _>::new(&table);
+        assert_eq!(cursor.next().transpose().unwrap(), Some(entry("a1", 0)));
+        assert!(cursor.prev().is_none());
+        assert_eq!(cursor.key_bytes(), Some(&*String::serialize(&"a1".to_string()).unwrap()));
+        assert_eq!(cursor.next().transpose().unwrap(), Some(entry("a2", 1)));
+
+        let mut cursor = Cursor::<String, u64, _>::new(&table);
+        assert_eq!(cursor.prev().transpose().unwrap(), Some(entry("c1", 4)));
+        assert!(cursor.next().is_none());
+
+        // Seeking past the last key finds nothing, but the last entry is still behind the cursor:
+        assert_eq!(cursor.seek(&"d1".to_string()).unwrap(), None);
+        assert_eq!(cursor.key_bytes(), None);
+        assert_eq!(cursor.prev().transpose().unwrap(), Some(entry("c1", 4)));
+    }
+
+    #[test]
+    fn seek_prefix_stays_within_the_prefix() {
+        let table = table();
+        let prefix = &String::serialize(&"b1".to_string()).unwrap()[..2];
+        let mut cursor = Cursor::<String, u64, _>::new(&table);
+        assert_eq!(cursor.seek_prefix(prefix).unwrap(), Some(entry("b1", 2)));
+        assert_eq!(cursor.next().transpose().unwrap(), Some(entry("b2", 3)));
+        assert!(cursor.next().is_none());
+        assert_eq!(cursor.prev().transpose().unwrap(), Some(entry("b1", 2)));
+        assert!(cursor.prev().is_none());
+
+        // A later seek clears the prefix:
+        assert_eq!(cursor.seek(&"b2".to_string()).unwrap(), Some(entry("b2", 3)));
+        assert_eq!(cursor.next().transpose().unwrap(), Some(entry("c1", 4)));
+
+        let missing = &String::serialize(&"z1".to_string()).unwrap()[..2];
+        assert_eq!(cursor.seek_prefix(missing).unwrap(), None);
+        assert!(cursor.prev().is_none());
+    }
+
+    #[test]
+    fn prefix_end_is_after_every_prefixed_key() {
+        assert_eq!(prefix_end(b"ab"), Some(b"ac".to_vec()));
+        assert_eq!(prefix_end(&[0x01, 0xFF, 0xFF]), Some(vec![0x02]));
+        assert_eq!(prefix_end(&[0xFF, 0xFF]), None);
+        assert_eq!(prefix_end(&[]), None);
+    }
+}
@@ -22,8 +22,37 @@
 
 
 use crate::Error;
+use crate::layers::correctors::RepairTotals;
+use crate::layers::encryptors::{KeyBytes, NonceCounter};
+use crate::redaction::RedactionPolicy;
+use crate::typed::audit::AUDIT_LOG_TABLE_NAME;
+use crate::typed::backup::{BackupProgress, DeltaSummary, copy_tables, write_delta};
+#[cfg(feature = "writes")]
+use crate::typed::backup::apply_delta;
+use crate::typed::change_log::{CHANGE_LOG_TABLE, CHANGE_LOG_TABLE_NAME, last_sequence};
+use crate::typed::nonce_counter::{NONCE_COUNTER_TABLE, persisted_position};
+use crate::typed::repair::{Integrity, repair_error};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use crate::typed::repair::RepairSession;
+#[cfg(feature = "writes")]
+use crate::typed::reserialization::{Reserialization, ReserializationProgress, reserialize_table};
+#[cfg(feature = "writes")]
+use crate::typed::rotation::{KeyRotation, RotationProgress, rotate_tables};
+use crate::typed::{Namespace, Tenant};
+use crate::typed::record_layers::{RecordContext, owned_key};
+use crate::typed::shared_cache::SharedCache;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use crate::typed::read_only::ReadOnlyDatabase;
+use crate::typed::snapshot::{ReadPoint, SNAPSHOT_TABLE, Snapshot, SnapshotId, SnapshotView};
+use crate::typed::stats_report::{StatsReport, gather};
 use crate::typed::transaction::ReadTransaction;
+use crate::typed::usage::{Usage, UsageOptions, measure_usage};
+#[cfg(feature = "writes")]
 use crate::typed::transaction::WriteTransaction;
+use redb::{ReadableTable, TableHandle};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::path::Path;
+use std::sync::Arc;
 
 /// The entry point for working with a redb database using typed keys and values.
 ///
@@ -31,22 +60,936 @@ use crate::typed::transaction::WriteTransaction;
 /// leveraging the `Codec` trait for automatic encoding and decoding.
 ///
 /// For ordered operations, use tables with key types that also implement [`OrderedWhenSerialized`].
-pub struct Database(redb::Database);
+pub struct Database {
+    /// The wrapped `redb` database.
+    redb: redb::Database,
+
+    /// Whether the database keeps a change log. Detected when the database is opened.
+    change_log: bool,
+
+    /// This peer's merge clock node ID, if one was set with `enable_merge_clock`.
+    #[cfg_attr(not(feature = "sync"), allow(dead_code, reason = "only read by anti-entropy sync"))]
+    node_id: Option<u16>,
+
+    /// Whether the database keeps an audit log. Detected when the database is opened.
+    #[cfg_attr(not(feature = "writes"), allow(dead_code, reason = "only read by write transactions"))]
+    audit_log: bool,
+
+    /// The read cache shared by its transactions, if one was set with `enable_shared_cache`.
+    shared_cache: Option<Arc<SharedCache>>,
+
+    /// The redaction policy applied to backups and exports, if one was set with
+    /// `set_redaction_policy`.
+    redaction_policy: Option<Arc<RedactionPolicy>>,
+
+    /// Counts the values its read transactions have repaired, see [`Database::repair_totals`].
+    repair_totals: Arc<RepairTotals>,
+
+    /// The key that records with record layers are encrypted with, if one was set with
+    /// `set_record_key`. Tenants' transactions use the tenant's key instead.
+    record_key: Option<Arc<KeyBytes<'static>>>,
+
+    /// The counter that write transactions draw counter-based nonces from. Started from the
+    /// persisted position when the database is opened.
+    #[cfg_attr(not(feature = "writes"), allow(dead_code, reason = "only read by write transactions"))]
+    nonce_counter: Arc<NonceCounter>,
+}
 
 impl Database {
     /// Opens or creates a database at the given file path.
-    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self, Error> {
-        let redb = redb::Database::open(path)?;
-        Ok(Self(redb))
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::DatabaseLocked`] if the database is already open elsewhere.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let redb = redb::Database::open(path).map_err(|error| open_error(path, error))?;
+        Self::from_redb(redb)
+    }
+
+    /// Opens an existing database for reading only. The returned [`ReadOnlyDatabase`] has no way
+    /// to begin a write transaction, so a reporting job can't modify the database by mistake.
+    ///
+    /// `redb` locks a database file for as long as any handle to it is open, so a read-only
+    /// handle still excludes every other handle, in this process and others. It restricts what
+    /// this handle can do, not who else can open the file.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let db = Database::open_read_only("creatures.redb")?;
+    /// let txn = db.read()?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::DatabaseLocked`] if the database is already open elsewhere.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///   A missing file is reported as an input/output failure, rather than created.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn open_read_only(path: impl AsRef<Path>) -> Result<ReadOnlyDatabase, Error> {
+        Self::open(path).map(ReadOnlyDatabase::new)
+    }
+
+    /// Opens or creates a database at the given file path, holding it exclusively until the handle
+    /// is dropped. Fails immediately, rather than waiting, if another handle holds it.
+    ///
+    /// Unlike [`Database::open`], a missing file is created, and unlike
+    /// [`Database::open_with_repair`], a database that wasn't shut down cleanly is repaired
+    /// without reporting progress. Use this to make sure only one writer, such as a single
+    /// deployed service instance, ever has the file open.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// match Database::open_exclusive("creatures.redb") {
+    ///     Ok(db) => serve(db),
+    ///     Err(Error::DatabaseLocked { path }) => eprintln!("{} is in use", path.display()),
+    ///     Err(error) => return Err(error),
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::DatabaseLocked`] if the database is already open elsewhere.
+    ///
+    /// * [`Error::Corrupted`] if the database file could not be repaired.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn open_exclusive(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let redb = redb::Database::create(path).map_err(|error| open_error(path, error))?;
+        Self::from_redb(redb)
+    }
+
+    /// Opens or creates a database stored by a custom `redb` storage backend, rather than a file.
+    ///
+    /// The database supports everything a file-backed one does: typed tables, indexes, queries,
+    /// and layers. Use it for in-memory databases, backends tuned for a particular platform, or
+    /// storage of your own, such as a backend that encrypts or replicates pages. A database that
+    /// wasn't shut down cleanly is repaired while opening.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// use atlatl::redb::backends::InMemoryBackend;
+    ///
+    /// let db = Database::with_backend(InMemoryBackend::new())?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Corrupted`] if the stored database could not be repaired.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn with_backend(backend: impl redb::StorageBackend) -> Result<Self, Error> {
+        let redb = redb::Builder::new().create_with_backend(backend).map_err(repair_error)?;
+        Self::from_redb(redb)
+    }
+
+    /// Creates an empty database held in memory, which is discarded when the handle is dropped.
+    /// Useful for tests and caches.
+    ///
+    /// # Errors
+    ///
+    /// * See [`Database::with_backend`].
+    pub fn in_memory() -> Result<Self, Error> {
+        Self::with_backend(redb::backends::InMemoryBackend::new())
+    }
+
+    /// Wraps an opened `redb` database, detecting whether it keeps a change log and an audit log,
+    /// and starting its nonce counter.
+    fn from_redb(redb: redb::Database) -> Result<Self, Error> {
+        let (mut change_log, mut audit_log) = (false, false);
+        for table in redb.begin_read().map_err(Box::new)?.list_tables()? {
+            change_log |= table.name() == CHANGE_LOG_TABLE_NAME;
+            audit_log |= table.name() == AUDIT_LOG_TABLE_NAME;
+        }
+        let nonce_counter = Arc::new(start_nonce_counter(&redb)?);
+        Ok(Self {
+            redb,
+            change_log,
+            node_id: None,
+            audit_log,
+            shared_cache: None,
+            redaction_policy: None,
+            repair_totals: Arc::default(),
+            record_key: None,
+            nonce_counter,
+        })
+    }
+
+    /// Opens or creates a database at the given file path, reporting the progress of any repair.
+    ///
+    /// If the database wasn't shut down cleanly, its file is repaired while opening and `callback`
+    /// is invoked periodically with the repair's progress. The callback may abort the repair.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let db = Database::open_with_repair("creatures.redb", |session| {
+    ///     println!("repairing: {:.0}%", session.progress() * 100.0);
+    /// })?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::DatabaseLocked`] if the database is already open elsewhere.
+    ///
+    /// * [`Error::RepairAborted`] if the callback aborted the repair.
+    ///
+    /// * [`Error::Corrupted`] if the database file could not be repaired.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn open_with_repair(
+        path: impl AsRef<Path>,
+        callback: impl Fn(&mut RepairSession) + 'static,
+    ) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let redb = redb::Builder::new()
+            .set_repair_callback(move |session| callback(&mut RepairSession::new(session)))
+            .create(path)
+            .map_err(|error| open_error(path, error))?;
+        Self::from_redb(redb)
+    }
+
+    /// Forces a full integrity check of the database file, and repairs it if possible.
+    ///
+    /// This is unnecessary during normal operation: crashes and unclean shutdowns are recovered
+    /// from automatically when the database is opened. It's slow, and only worth running when the
+    /// file may have been modified externally or damaged. No transactions may be open.
+    ///
+    /// `redb` doesn't report progress for an explicit check. To monitor a repair, reopen the
+    /// database with [`Database::open_with_repair`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Corrupted`] if the check failed and the database file could not be repaired.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn check_integrity(&mut self) -> Result<Integrity, Error> {
+        if self.redb.check_integrity().map_err(repair_error)? {
+            Ok(Integrity::Intact)
+        } else {
+            Ok(Integrity::Repaired)
+        }
     }
 
     /// Begins a read-only transaction.
+    ///
+    /// # Errors
+    ///
+    /// * The transaction couldn't be started, for example because of an input/output failure or
+    ///   because the database was left in a failed state by an earlier operation.
     pub fn read(&self) -> Result<ReadTransaction, Error> {
-        Ok(ReadTransaction::new(self.0.begin_read().map_err(Box::new)?))
+        // The cache's epoch is taken first, so that the transaction's snapshot is at least as new:
+        let cache = self.shared_cache.as_ref().map(|cache| (Arc::clone(cache), cache.epoch()));
+        let txn = ReadTransaction::new(self.redb.begin_read().map_err(Box::new)?)
+            .with_shared_cache(cache)
+            .with_shared_record_key(self.record_key.clone())
+            .with_repair_totals(Arc::clone(&self.repair_totals));
+        Ok(match &self.redaction_policy {
+            Some(policy) => txn.with_redaction_policy(Arc::clone(policy)),
+            None => txn,
+        })
     }
 
-    /// Begins a writable transaction.
+    /// Takes a snapshot: a read-only transaction that can be held open across many queries, and
+    /// knows when it was taken. See [`Snapshot`].
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn snapshot(&self) -> Result<Snapshot, Error> {
+        let redb = self.redb.begin_read().map_err(Box::new)?;
+        let sequence = if self.change_log {
+            Some(last_sequence(&redb.open_table(CHANGE_LOG_TABLE)?)?)
+        } else {
+            None
+        };
+        let txn = ReadTransaction::new(redb)
+            .with_shared_record_key(self.record_key.clone())
+            .with_repair_totals(Arc::clone(&self.repair_totals));
+        Ok(Snapshot::new(txn, sequence))
+    }
+
+    /// Begins a writable transaction. Only one write transaction can be open at a time, so this
+    /// waits for any other writer to commit or abort.
+    ///
+    /// # Errors
+    ///
+    /// * The transaction couldn't be started, for example because of an input/output failure or
+    ///   because the database was left in a failed state by an earlier operation.
+    #[cfg(feature = "writes")]
     pub fn write(&self) -> Result<WriteTransaction, Error> {
-        Ok(WriteTransaction::new(self.0.begin_write().map_err(Box::new)?))
+        let mut txn = WriteTransaction::new(self.redb.begin_write().map_err(Box::new)?);
+        txn.set_change_log(self.change_log);
+        txn.set_audit_log(self.audit_log);
+        #[cfg(feature = "sync")]
+        txn.set_merge_clock(self.node_id);
+        txn.set_shared_cache(self.shared_cache.clone());
+        txn.set_record_key(self.record_key.clone());
+        txn.set_nonce_counter(Some(Arc::clone(&self.nonce_counter)));
+        Ok(txn)
+    }
+
+    /// Begins a read-only transaction whose tables are opened in the given namespace.
+    ///
+    /// # Errors
+    ///
+    /// * The transaction couldn't be started, for example because of an input/output failure or
+    ///   because the database was left in a failed state by an earlier operation.
+    pub fn read_in(&self, namespace: &Namespace) -> Result<ReadTransaction, Error> {
+        Ok(self.read()?.in_namespace(namespace.clone()))
+    }
+
+    /// Begins a writable transaction whose tables are opened in the given namespace.
+    ///
+    /// # Errors
+    ///
+    /// * The transaction couldn't be started, for example because of an input/output failure or
+    ///   because the database was left in a failed state by an earlier operation.
+    #[cfg(feature = "writes")]
+    pub fn write_in(&self, namespace: &Namespace) -> Result<WriteTransaction, Error> {
+        Ok(self.write()?.in_namespace(namespace.clone()))
+    }
+
+    /// Begins a read-only transaction for the given tenant. Tables are opened in the tenant's
+    /// namespace, and the transaction carries the tenant's encryption key. Records with
+    /// [`RecordLayers`](crate::typed::record_layers::RecordLayers) are decrypted with it.
+    ///
+    /// # Errors
+    ///
+    /// * The transaction couldn't be started, for example because of an input/output failure or
+    ///   because the database was left in a failed state by an earlier operation.
+    pub fn read_as(&self, tenant: &Tenant) -> Result<ReadTransaction, Error> {
+        Ok(self.read()?.for_tenant(tenant))
+    }
+
+    /// Begins a writable transaction for the given tenant. Tables are opened in the tenant's
+    /// namespace, and the transaction carries the tenant's encryption key. Records with
+    /// [`RecordLayers`](crate::typed::record_layers::RecordLayers) are encrypted with it.
+    ///
+    /// # Errors
+    ///
+    /// * The transaction couldn't be started, for example because of an input/output failure or
+    ///   because the database was left in a failed state by an earlier operation.
+    #[cfg(feature = "writes")]
+    pub fn write_as(&self, tenant: &Tenant) -> Result<WriteTransaction, Error> {
+        Ok(self.write()?.for_tenant(tenant))
+    }
+
+    /// Copies every table into a fresh database file at the given path, while the database stays
+    /// open and writers continue.
+    ///
+    /// The backup is taken from a read transaction, so it's a consistent snapshot of the database
+    /// as it was when the backup began: writes committed while the backup is running aren't
+    /// included. `progress` is invoked after each table is copied, and periodically while large
+    /// tables are being copied.
+    ///
+    /// Records are redacted by the database's [`RedactionPolicy`], if one was set with
+    /// [`Database::set_redaction_policy`]. A redacted backup leaves out the audit log, and the
+    /// secondary index, reverse index, and history tables of redacted tables, and redacts each
+    /// change in the change log.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let status = db.backup_to("backups/creatures.redb", |progress| {
+    ///     println!("backing up `{}`: {:.0}%", progress.table, progress.fraction() * 100.0);
+    /// })?;
+    /// println!("{} entries backed up", status.entries_copied);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::BackupTargetExists`] if a file already exists at the given path.
+    ///
+    /// * Decoding a record, or encoding its redacted copy, fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///
+    /// # Notes
+    ///
+    /// * A read transaction is held open for the duration of the backup. Pages freed by writers in
+    ///   the meantime can't be reused until it ends, so the database file may grow while a large
+    ///   backup is running.
+    ///
+    /// * If the backup fails, the partially written target file is left in place and should be
+    ///   deleted.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn backup_to(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        progress: impl FnMut(&BackupProgress),
+    ) -> Result<BackupProgress, Error> {
+        let path = path.as_ref();
+        if path.exists() {
+            return Err(Error::BackupTargetExists { path: path.to_path_buf() });
+        }
+
+        let source = self.redb.begin_read().map_err(Box::new)?;
+        let target = redb::Database::create(path)?;
+        let records = RecordContext::new(self.record_key.clone(), "");
+        copy_tables(&source, &target, self.redaction_policy(), &records, progress)
+    }
+
+    /// Re-encrypts every record of the tables registered with `rotation`, moving them from its old
+    /// key to its new key, while the database stays open and writers continue.
+    ///
+    /// Records are rotated in batches, each committed in its own write transaction along with the
+    /// rotation's progress. If the rotation is interrupted, by an error or a crash, call this again
+    /// with the same keys to resume it. `progress` is invoked after each batch is committed.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let rotation = KeyRotation::new(retired_key, new_key).record::<Creature>().batch_size(500);
+    /// let status = db.rotate_keys(&rotation, |progress| {
+    ///     println!("rotating `{}`: {} records", progress.table, progress.entries_rotated);
+    /// })?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::KeyRotation`] if a record can't be decrypted with either key. Batches committed
+    ///   before the error stay rotated.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///
+    /// # Notes
+    ///
+    /// * Until the rotation finishes, some records are encrypted with each key, so readers need
+    ///   both keys. Retire the old key once this returns.
+    ///
+    /// * Rotated records aren't recorded in the change log, the audit log, or the merge clock,
+    ///   since their values don't change. Replicas and sync peers must be rotated separately, and
+    ///   incremental backups taken before the rotation still need the old key.
+    #[cfg(feature = "writes")]
+    pub fn rotate_keys(
+        &self,
+        rotation: &KeyRotation<'_>,
+        progress: impl FnMut(&RotationProgress),
+    ) -> Result<RotationProgress, Error> {
+        rotate_tables(&self.redb, rotation, progress)
+    }
+
+    /// Rewrites every record of `V`'s table in the current write format, in batches of write
+    /// transactions, calling `progress` after each batch. This completes a serializer migration
+    /// started with [`set_write_method`]: once it returns, the old serializer's feature can be
+    /// removed.
+    ///
+    /// If the migration is interrupted, by an error or a crash, call this again to resume it.
+    /// Records already in the current format are left as they are. Records whose primary key
+    /// encodes differently in the current format are moved to the new key, and the table's
+    /// secondary indexes are repaired once every record is rewritten. Rewritten records have their
+    /// [`Defaults`](crate::defaults::Defaults) applied.
+    ///
+    /// [`set_write_method`]: crate::layers::serializers::set_write_method
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let status = db.reserialize_table::<CreatureId, Creature>(
+    ///     &Reserialization::new(),
+    ///     |progress| println!("{} records rewritten", progress.entries_rewritten),
+    /// )?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Decoding a record or primary key fails, or encoding one in the current format fails.
+    ///   Batches committed before the error stay rewritten.
+    ///
+    /// * [`Error::IndexCollision`] if a moved record collides with a `Unique` index entry.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///
+    /// # Notes
+    ///
+    /// * Projections of covering indexes aren't re-encoded. Rebuild covering indexes with
+    ///   `rebuild_index` afterwards.
+    #[cfg(feature = "writes")]
+    pub fn reserialize_table<K, V>(
+        &self,
+        reserialization: &Reserialization,
+        progress: impl FnMut(&ReserializationProgress),
+    ) -> Result<ReserializationProgress, Error>
+    where
+        K: crate::Codec<K>,
+        V: for<'i> crate::indexing::Indexable<'i>
+            + crate::defaults::Defaults
+            + crate::Codec<V>
+            + crate::indexing::HasTable,
+    {
+        reserialize_table::<K, V>(self, reserialization, progress)
+    }
+
+    /// Starts a [`NonceCounter`] from the database's persisted counter position, with a fresh
+    /// random prefix. Draw nonces from it with [`WriteTransaction::next_nonce`], for record types
+    /// that encrypt with [`NonceStrategy::Counter`].
+    ///
+    /// Start one counter when the database is opened, and share it between transactions.
+    ///
+    /// [`NonceStrategy::Counter`]: crate::layers::encryptors::NonceStrategy::Counter
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn nonce_counter(&self) -> Result<NonceCounter, Error> {
+        start_nonce_counter(&self.redb)
+    }
+
+    /// Sets the key that records with [`RecordLayers`] are encrypted with, in every transaction
+    /// begun from now on that isn't a tenant's. Tenants' transactions use the tenant's key.
+    ///
+    /// The key isn't stored in the database. It must be set again whenever the database is
+    /// opened, before any layered record is read or written.
+    ///
+    /// [`RecordLayers`]: crate::typed::record_layers::RecordLayers
+    pub fn set_record_key(&mut self, key: &KeyBytes<'_>) {
+        self.record_key = Some(owned_key(key));
+    }
+
+    /// Starts recording every record write and deletion in the change log, which is what
+    /// incremental backups are taken from. Does nothing if the change log is already enabled.
+    ///
+    /// The change log is stored in the database, so it stays enabled when the database is reopened.
+    /// See [`WriteTransaction::set_change_log`] for which writes are logged.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    #[cfg(feature = "writes")]
+    pub fn enable_change_log(&mut self) -> Result<(), Error> {
+        if !self.change_log {
+            let txn = self.redb.begin_write().map_err(Box::new)?;
+            txn.open_table(crate::typed::change_log::CHANGE_LOG_TABLE)?;
+            txn.commit()?;
+            self.change_log = true;
+        }
+        Ok(())
+    }
+
+    /// Starts recording who wrote or deleted every record, and when, in the audit log. Does nothing
+    /// if the audit log is already enabled. See the [`audit`](crate::typed::audit) module.
+    ///
+    /// The audit log is stored in the database, so it stays enabled when the database is reopened.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    #[cfg(feature = "writes")]
+    pub fn enable_audit_log(&mut self) -> Result<(), Error> {
+        if !self.audit_log {
+            let txn = self.redb.begin_write().map_err(Box::new)?;
+            txn.open_table(crate::typed::audit::AUDIT_LOG_TABLE)?;
+            txn.commit()?;
+            self.audit_log = true;
+        }
+        Ok(())
+    }
+
+    /// Shares a cache of decoded records between every transaction begun from now on. Read
+    /// transactions read through it with `get_cached`, and write transactions clear it when they
+    /// commit. See the [`shared_cache`](crate::typed::shared_cache) module.
+    ///
+    /// Each database needs a cache of its own. Records are keyed by table name only, so a cache
+    /// enabled on two databases would serve one database's records to the other.
+    pub fn enable_shared_cache(&mut self, cache: Arc<SharedCache>) {
+        self.shared_cache = Some(cache);
+    }
+
+    /// Sets the redaction policy applied by [`Database::backup_to`] and
+    /// [`Database::backup_incremental`], and by the `export` and `export_jsonl` methods of every
+    /// read transaction begun from now on. See the [`redaction`](crate::redaction) module.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// db.set_redaction_policy(RedactionPolicy::new().with_table(|mut user: User| {
+    ///     user.email = mask_email(&user.email);
+    ///     Redacted::Keep(user)
+    /// }));
+    /// ```
+    pub fn set_redaction_policy(&mut self, policy: RedactionPolicy) {
+        self.redaction_policy = Some(Arc::new(policy));
+    }
+
+    /// Returns the redaction policy applied to backups and exports, if any.
+    #[must_use]
+    pub fn redaction_policy(&self) -> Option<&RedactionPolicy> {
+        self.redaction_policy.as_deref()
+    }
+
+    /// Returns the read cache shared by this database's transactions, if any.
+    #[must_use]
+    pub const fn shared_cache(&self) -> Option<&Arc<SharedCache>> {
+        self.shared_cache.as_ref()
+    }
+
+    /// Starts stamping every record write and deletion with a hybrid logical clock, for
+    /// conflict resolution during bidirectional sync. See the [`merge`](crate::typed::merge)
+    /// module.
+    ///
+    /// `node` must be unique among the peers that are synchronized with each other. It isn't
+    /// stored in the database, so this must be called again whenever the database is opened.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    #[cfg(feature = "sync")]
+    pub fn enable_merge_clock(&mut self, node: u16) -> Result<(), Error> {
+        let txn = self.redb.begin_write().map_err(Box::new)?;
+        txn.open_table(crate::typed::merge::CLOCK_TABLE)?;
+        txn.commit()?;
+        self.node_id = Some(node);
+        Ok(())
+    }
+
+    /// Writes every change made after `since_sequence` to `writer`, as a compact incremental
+    /// backup. Returns a summary whose `until` field is the `since_sequence` to use for the next
+    /// incremental backup.
+    ///
+    /// Incremental backups are restored with [`Database::restore_incremental`], on top of a full
+    /// backup taken with [`Database::backup_to`]. A full backup includes the change log, so the
+    /// first incremental backup after it can start from the backup's last change.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// db.enable_change_log()?;
+    /// db.backup_to("backups/base.redb", |_| {})?;
+    /// let mut since = 0;
+    ///
+    /// // Later, and as often as needed:
+    /// let file = std::fs::File::create(format!("backups/delta-{since}.bin"))?;
+    /// since = db.backup_incremental(since, file)?.until;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::ChangeLogNotEnabled`] if the change log isn't enabled.
+    ///
+    /// * [`Error::Io`] if the backup could not be written.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///
+    /// # Notes
+    ///
+    /// * The change log grows with every write. Only records' primary tables are logged: restore
+    ///   secondary indexes with `repair_indexes` after replaying incremental backups.
+    ///
+    /// * If a [redaction policy](Database::set_redaction_policy) is set, each change is redacted
+    ///   like the record it wrote, and changes to tables the policy leaves out are written without
+    ///   their keys or values.
+    pub fn backup_incremental(
+        &self,
+        since_sequence: u64,
+        writer: impl std::io::Write,
+    ) -> Result<DeltaSummary, Error> {
+        let source = self.redb.begin_read().map_err(Box::new)?;
+        let records = RecordContext::new(self.record_key.clone(), "");
+        let redaction = self.redaction_policy().map(|policy| (policy, &records));
+        write_delta(&source, since_sequence, redaction, writer)
+    }
+
+    /// Replays an incremental backup onto the base backup at the given path. Incremental backups
+    /// must be replayed in the order they were taken.
+    ///
+    /// Changes that the base backup already has are skipped, so replaying the same incremental
+    /// backup twice is harmless.
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::IncrementalBackupGap`] if an earlier incremental backup hasn't been replayed yet.
+    ///
+    /// * [`Error::MalformedChangeLog`] if the incremental backup is truncated or malformed.
+    ///
+    /// * [`Error::Io`] if the incremental backup could not be read.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    #[cfg(feature = "writes")]
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn restore_incremental(
+        base: impl AsRef<std::path::Path>,
+        reader: impl std::io::Read,
+    ) -> Result<DeltaSummary, Error> {
+        let target = redb::Database::open(base)?;
+        apply_delta(&target, reader)
+    }
+
+    /// Replays an incremental backup, or the changes streamed by a server's `/changes` endpoint,
+    /// onto this database in a single write transaction. Changes the database already has are
+    /// skipped.
+    ///
+    /// # Errors
+    ///
+    /// * See [`Database::restore_incremental`].
+    #[cfg(feature = "writes")]
+    pub fn apply_changes(&self, reader: impl std::io::Read) -> Result<DeltaSummary, Error> {
+        let _invalidation = self.shared_cache.as_deref().map(SharedCache::begin_commit);
+        apply_delta(&self.redb, reader)
+    }
+
+    /// Retains the current state of the database as a snapshot that can be read later with
+    /// [`Database::read_at`].
+    ///
+    /// A snapshot is a named position in the change log, so it costs no space of its own and
+    /// survives restarts. Release snapshots that are no longer needed with
+    /// [`Database::release_snapshot`].
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::ChangeLogNotEnabled`] if the database doesn't keep a change log.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    #[cfg(feature = "writes")]
+    pub fn retain_snapshot(&self) -> Result<SnapshotId, Error> {
+        if !self.change_log {
+            return Err(Error::ChangeLogNotEnabled);
+        }
+        let txn = self.redb.begin_write().map_err(Box::new)?;
+        let sequence = last_sequence(&txn.open_table(CHANGE_LOG_TABLE)?)?;
+        let mut snapshots = txn.open_table(SNAPSHOT_TABLE)?;
+        let id = snapshots.last()?.map_or(1, |(id, _)| id.value() + 1);
+        snapshots.insert(id, sequence)?;
+        drop(snapshots);
+        txn.commit()?;
+        Ok(SnapshotId(id))
+    }
+
+    /// Releases a retained snapshot. Returns `true` if the snapshot existed.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    #[cfg(feature = "writes")]
+    pub fn release_snapshot(&self, snapshot_id: SnapshotId) -> Result<bool, Error> {
+        let txn = self.redb.begin_write().map_err(Box::new)?;
+        let existed = txn.open_table(SNAPSHOT_TABLE)?.remove(snapshot_id.0)?.is_some();
+        txn.commit()?;
+        Ok(existed)
+    }
+
+    /// Lists all retained snapshots, oldest first.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn snapshots(&self) -> Result<Vec<SnapshotId>, Error> {
+        let txn = self.redb.begin_read().map_err(Box::new)?;
+        let snapshots = match txn.open_table(SNAPSHOT_TABLE) {
+            Ok(snapshots) => snapshots,
+            Err(redb::TableError::TableDoesNotExist(_)) => return Ok(Vec::new()),
+            Err(error) => return Err(error.into()),
+        };
+        snapshots
+            .iter()?
+            .map(|entry| Ok(SnapshotId(entry?.0.value())))
+            .collect()
+    }
+
+    /// Opens a read-only view of the database as it was at a retained snapshot, or just after the
+    /// change with the given sequence number was committed.
+    ///
+    /// This enables "compare today vs. last week" style reporting without restoring a backup
+    /// elsewhere. The view is backed by a read transaction, so writers aren't blocked while it's
+    /// open. See [`SnapshotView`] for its costs.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::ChangeLogNotEnabled`] if the database doesn't keep a change log.
+    ///
+    /// * Returns [`Error::SnapshotNotFound`] if the snapshot was never retained, or was released.
+    ///
+    /// * Returns [`Error::SequenceNotReached`] if the change log doesn't reach the sequence yet.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn read_at(&self, point: impl Into<ReadPoint>) -> Result<SnapshotView, Error> {
+        if !self.change_log {
+            return Err(Error::ChangeLogNotEnabled);
+        }
+        let txn = self.redb.begin_read().map_err(Box::new)?;
+        let last = last_sequence(&txn.open_table(CHANGE_LOG_TABLE)?)?;
+        let sequence = match point.into() {
+            ReadPoint::Sequence(sequence) => sequence,
+            ReadPoint::Snapshot(snapshot_id) => {
+                let not_found = || Error::SnapshotNotFound { snapshot: snapshot_id.0 };
+                match txn.open_table(SNAPSHOT_TABLE) {
+                    Ok(snapshots) => snapshots.get(snapshot_id.0)?.ok_or_else(not_found)?.value(),
+                    Err(redb::TableError::TableDoesNotExist(_)) => return Err(not_found()),
+                    Err(error) => return Err(error.into()),
+                }
+            },
+        };
+        if sequence > last {
+            return Err(Error::SequenceNotReached { sequence, last });
+        }
+        Ok(SnapshotView::new(txn, sequence).with_shared_record_key(self.record_key.clone()))
+    }
+
+    /// Returns the running totals of values repaired by error correction as this database's read
+    /// transactions decoded them, by table. They're kept in memory, and start from zero whenever
+    /// the database is opened.
+    #[inline]
+    #[must_use]
+    pub const fn repair_totals(&self) -> &Arc<RepairTotals> {
+        &self.repair_totals
+    }
+
+    /// Gathers storage, table, index, cache, repair, and layer statistics into one
+    /// [`StatsReport`], for example to serve on a `/metrics` endpoint.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let report = db.stats_report()?;
+    /// for table in &report.tables {
+    ///     println!("{}: {} entries, {} bytes", table.name, table.entries, table.stored_bytes);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Corrupted`] if an index's stored statistics can't be decoded.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn stats_report(&self) -> Result<StatsReport, Error> {
+        gather(&self.redb, &self.repair_totals)
+    }
+
+    /// Returns the disk usage of every table: its entries, stored bytes, `redb` overhead, and
+    /// average key and value sizes, sampled from its first entries.
+    ///
+    /// Layer overhead and index sizes can only be attributed to tables whose record types are
+    /// registered. Use [`Database::usage_with`] for those.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let usage = db.usage()?;
+    /// println!("{} bytes in total", usage.total_bytes());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn usage(&self) -> Result<Usage, Error> {
+        self.usage_with(&UsageOptions::new())
+    }
+
+    /// Returns the disk usage of every table, with the layer overhead of each registered record
+    /// table, and its registered indexes' sizes reported under it, in every namespace.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let options = UsageOptions::new().record::<Creature>().index::<HabitatIndex>();
+    /// let creatures = db.usage_with(&options)?.table("creatures").cloned();
+    /// if let Some(creatures) = creatures {
+    ///     println!("{:?} bytes of layer tails", creatures.layer_overhead_bytes);
+    ///     println!("{} bytes of indexes", creatures.index_bytes());
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn usage_with(&self, options: &UsageOptions) -> Result<Usage, Error> {
+        measure_usage(&self.redb, options)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Converts a `redb` error raised while opening the database at `path`, reporting a file that's
+/// held by another handle as [`Error::DatabaseLocked`].
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn open_error(path: &Path, error: redb::DatabaseError) -> Error {
+    match error {
+        redb::DatabaseError::DatabaseAlreadyOpen =>
+            Error::DatabaseLocked { path: path.to_path_buf() },
+        error => repair_error(error),
+    }
+}
+
+/// Starts a [`NonceCounter`] from the database's persisted counter position, with a fresh random
+/// prefix.
+///
+/// # Errors
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+fn start_nonce_counter(redb: &redb::Database) -> Result<NonceCounter, Error> {
+    let txn = redb.begin_read().map_err(Box::new)?;
+    let position = match txn.open_table(NONCE_COUNTER_TABLE) {
+        Ok(table) => persisted_position(&table)?,
+        Err(redb::TableError::TableDoesNotExist(_)) => 0,
+        Err(error) => return Err(error.into()),
+    };
+    Ok(NonceCounter::new(position))
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "writes"))]
+mod tests {
+    use super::*;
+    use crate::typed::test_records::Animal;
+    use redb::backends::InMemoryBackend;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// An in-memory backend that counts the writes it's asked to make.
+    #[derive(Debug)]
+    struct CountingBackend(InMemoryBackend, Arc<AtomicUsize>);
+
+    impl redb::StorageBackend for CountingBackend {
+        fn len(&self) -> Result<u64, std::io::Error> {
+            self.0.len()
+        }
+
+        fn read(&self, offset: u64, len: usize) -> Result<Vec<u8>, std::io::Error> {
+            self.0.read(offset, len)
+        }
+
+        fn set_len(&self, len: u64) -> Result<(), std::io::Error> {
+            self.0.set_len(len)
+        }
+
+        fn sync_data(&self, eventual: bool) -> Result<(), std::io::Error> {
+            self.0.sync_data(eventual)
+        }
+
+        fn write(&self, offset: u64, data: &[u8]) -> Result<(), std::io::Error> {
+            self.1.fetch_add(1, Ordering::Relaxed);
+            self.0.write(offset, data)
+        }
+    }
+
+    #[test]
+    fn stores_records_through_a_custom_backend() {
+        let writes = Arc::new(AtomicUsize::new(0));
+        let backend = CountingBackend(InMemoryBackend::new(), Arc::clone(&writes));
+        let db = Database::with_backend(backend).unwrap();
+
+        let writes_before = writes.load(Ordering::Relaxed);
+        let mut txn = db.write().unwrap();
+        txn.insert::<u64, Animal>(&Animal::new(1, "Lion", "Savannah")).unwrap();
+        txn.commit().unwrap();
+        assert!(writes.load(Ordering::Relaxed) > writes_before);
+
+        let lion = db.read().unwrap().get::<u64, Animal>(&1).unwrap();
+        assert_eq!(lion, Some(Animal::new(1, "Lion", "Savannah")));
     }
 }
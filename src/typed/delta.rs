@@ -0,0 +1,194 @@
+//! Delta encoding, which stores large records that are updated often in small ways as a base
+//! version and a small diff, to reduce write volume and compaction pressure.
+//!
+//! Record types opt in by implementing [`DeltaEncoded`]. Their records are kept in a table of
+//! their own, `"{table}#deltas"`, rather than their primary table, and are written and read with
+//! the transactions' `put_delta_encoded` and `get_delta_encoded` methods. Each record has two
+//! rows:
+//!
+//! * Its base: the record's full serialized form, as it was when the base was last written.
+//!
+//! * Its delta: how the current version differs from the base, if it does.
+//!
+//! An update only rewrites the small delta row, and leaves the large base row alone. Deltas are
+//! always taken against the base, so a record is reconstructed by applying one delta, however many
+//! times it's been updated. Once the delta grows past the record type's
+//! [`DeltaEncoded::consolidation_ratio`], the update is consolidated: the new version becomes the
+//! base, and the delta row is removed.
+//!
+//! A delta keeps the bytes shared by the start and end of both versions, and replaces the bytes in
+//! between. This suits updates that change one region of a value, such as a counter, a timestamp,
+//! or an appended entry.
+//!
+//! # Scope
+//!
+//! Delta-encoded records are a separate, minimal store. Bases and deltas are taken from the
+//! record's plain [`Codec`](crate::Codec) serialization, with no layers: they aren't compressed,
+//! encrypted, signed, or protected by error correction, so `put_delta_encoded` refuses record
+//! types that declare [`HasTable::layers`]. Delta-encoded records also aren't indexed, and aren't
+//! recorded in the change log, the audit log, or the history table, so they can't be queried,
+//! followed, or read as of an earlier time. Records in the primary table are unaffected, and are
+//! read and written with the regular methods.
+
+use crate::Error;
+use crate::indexing::HasTable;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// The default size of a delta, as a fraction of the record's size, above which an update is
+/// consolidated into a new base.
+pub const DEFAULT_CONSOLIDATION_RATIO: f64 = 0.5;
+
+/// Suffix of a delta-encoded row's key that marks its base.
+pub const BASE_ROW: u8 = 0;
+
+/// Suffix of a delta-encoded row's key that marks its delta.
+pub const DELTA_ROW: u8 = 1;
+
+/// Size of an encoded delta's header: the lengths of the shared prefix and suffix.
+const HEADER_LEN: usize = 2 * size_of::<u32>();
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Opts a record type in to delta encoding. See the [module documentation](self).
+///
+/// # Example
+///
+/// ```rust,ignore
+/// impl DeltaEncoded for Document {
+///     fn consolidation_ratio() -> f64 { 0.25 }
+/// }
+///
+/// let mut txn = db.write()?;
+/// txn.put_delta_encoded::<DocumentId, Document>(&id, &document)?;
+/// txn.commit()?;
+/// ```
+pub trait DeltaEncoded: HasTable {
+    /// The size of a delta, as a fraction of the record's size, above which an update is
+    /// consolidated into a new base. Defaults to [`DEFAULT_CONSOLIDATION_RATIO`].
+    #[must_use]
+    fn consolidation_ratio() -> f64 {
+        DEFAULT_CONSOLIDATION_RATIO
+    }
+}
+
+/// How a delta-encoded record was stored by `put_delta_encoded`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Stored {
+    /// The record was written in full, as a new base.
+    Base {
+        /// Size of the base, in bytes.
+        bytes: u64,
+    },
+
+    /// The record was written as a delta against its base.
+    Delta {
+        /// Size of the delta, in bytes.
+        bytes: u64,
+    },
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Returns the name of the table that holds a record type's delta-encoded records. For example:
+/// `"documents#deltas"`.
+#[must_use]
+pub fn delta_table_name(table_name: &str) -> String {
+    format!("{table_name}#deltas")
+}
+
+/// Returns the key of a delta-encoded record's base or delta row: the primary key followed by
+/// [`BASE_ROW`] or [`DELTA_ROW`].
+#[must_use]
+pub fn row_key(primary_key_bytes: &[u8], row: u8) -> Vec<u8> {
+    let mut key = Vec::with_capacity(primary_key_bytes.len() + 1);
+    key.extend_from_slice(primary_key_bytes);
+    key.push(row);
+    key
+}
+
+/// Encodes how `target` differs from `base`: the length of their shared prefix and suffix, as
+/// little-endian `u32`s, followed by the bytes of `target` in between.
+#[must_use]
+pub fn encode_delta(base: &[u8], target: &[u8]) -> Vec<u8> {
+    let prefix = base.iter().zip(target).take_while(|(a, b)| a == b).count();
+    let suffix = base[prefix..]
+        .iter()
+        .rev()
+        .zip(target[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    // Shared lengths past `u32::MAX` can't be encoded, so nothing is shared:
+    let Some((prefix_len, suffix_len)) = u32::try_from(prefix).ok().zip(u32::try_from(suffix).ok())
+    else {
+        return [&[0; HEADER_LEN][..], target].concat();
+    };
+
+    let middle = &target[prefix..target.len() - suffix];
+    let mut delta = Vec::with_capacity(HEADER_LEN + middle.len());
+    delta.extend_from_slice(&prefix_len.to_le_bytes());
+    delta.extend_from_slice(&suffix_len.to_le_bytes());
+    delta.extend_from_slice(middle);
+    delta
+}
+
+/// Reconstructs a value from its base and a delta encoded with [`encode_delta`].
+///
+/// # Errors
+///
+/// * Returns [`Error::Corrupted`] if the delta is truncated, or shares more bytes with the base
+///   than the base has.
+pub fn apply_delta(base: &[u8], delta: &[u8]) -> Result<Vec<u8>, Error> {
+    let corrupted = |message: &str| Error::Corrupted { message: message.to_string() };
+
+    let (header, middle) = delta
+        .split_at_checked(HEADER_LEN)
+        .ok_or_else(|| corrupted("delta is truncated"))?;
+    let (prefix, suffix) = header.split_at(size_of::<u32>());
+    let prefix = u32::from_le_bytes(prefix.try_into().unwrap_or_default()) as usize;
+    let suffix = u32::from_le_bytes(suffix.try_into().unwrap_or_default()) as usize;
+    if prefix.saturating_add(suffix) > base.len() {
+        return Err(corrupted("delta doesn't match its base"));
+    }
+
+    let mut value = Vec::with_capacity(prefix + middle.len() + suffix);
+    value.extend_from_slice(&base[..prefix]);
+    value.extend_from_slice(middle);
+    value.extend_from_slice(&base[base.len() - suffix..]);
+    Ok(value)
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_changes_make_small_deltas() {
+        let base = b"{\"name\":\"Wile E.\",\"visits\":41,\"notes\":\"...\"}".repeat(8);
+        let mut target = base.clone();
+        target[28] = b'2';
+
+        let delta = encode_delta(&base, &target);
+        assert_eq!(delta.len(), HEADER_LEN + 1);
+        assert_eq!(apply_delta(&base, &delta).unwrap(), target);
+    }
+
+    #[test]
+    fn round_trips_growth_and_shrinkage() {
+        let pairs = [(&b"abcdef"[..], &b"abXYZcdef"[..]), (b"abcdef", b"af"), (b"", b"new")];
+        for (base, target) in pairs {
+            assert_eq!(apply_delta(base, &encode_delta(base, target)).unwrap(), target);
+        }
+        assert!(apply_delta(b"ab", &encode_delta(b"abcdef", b"abcdef")).is_err());
+    }
+}
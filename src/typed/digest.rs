@@ -0,0 +1,170 @@
+//! Merkle tree digests of table contents, used to cheaply compare replicas of a table before
+//! synchronizing them.
+//!
+//! A digest is a Merkle root over a table's entries in key order. Each leaf hashes an entry's key
+//! together with a hash of its stored value bytes:
+//!
+//! ```text
+//! leaf:  BLAKE3(0x00 | key length (u32) | key | BLAKE3(value))
+//! node:  BLAKE3(0x01 | left | right)
+//! ```
+//!
+//! Two tables, or two key ranges, with the same digest hold the same entries. Digests compare the
+//! stored bytes, so replicas must be copied byte-for-byte, for example with a backup, an archive,
+//! or a sync, rather than re-encrypted.
+
+use crate::Error;
+use redb::ReadableTable;
+use std::ops::RangeBounds;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Domain separator for leaf hashes.
+const LEAF: u8 = 0x00;
+
+/// Domain separator for interior node hashes.
+const NODE: u8 = 0x01;
+
+/// The Merkle root of an empty table or key range.
+pub const EMPTY_ROOT: [u8; 32] = [0; 32];
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A Merkle digest of a table, or of a range of its keys.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct TableDigest {
+    /// The Merkle root over every entry. [`EMPTY_ROOT`] if there are no entries.
+    pub root: [u8; 32],
+
+    /// The number of entries.
+    pub entries: u64,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Builds a Merkle root from leaves pushed in key order, holding only one hash per tree level.
+#[derive(Debug, Default)]
+pub(crate) struct MerkleBuilder {
+    /// Roots of completed subtrees, with their heights, from tallest to shortest.
+    subtrees: Vec<(u32, [u8; 32])>,
+
+    /// The number of leaves pushed.
+    entries: u64,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl TableDigest {
+    /// Returns the digest of an empty table or key range.
+    #[must_use]
+    pub const fn empty() -> Self {
+        Self { root: EMPTY_ROOT, entries: 0 }
+    }
+
+    /// Returns the Merkle root as a lowercase hexadecimal string, for logging and display.
+    #[must_use]
+    pub fn to_hex(&self) -> String {
+        use std::fmt::Write;
+        self.root.iter().fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+    }
+}
+
+impl MerkleBuilder {
+    /// Adds an entry. Entries must be pushed in key order.
+    pub(crate) fn push(&mut self, key: &[u8], value: &[u8]) {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&[LEAF]);
+        hasher.update(&u32::try_from(key.len()).unwrap_or(u32::MAX).to_le_bytes());
+        hasher.update(key);
+        hasher.update(blake3::hash(value).as_bytes());
+
+        // Subtrees of equal height are merged, like carries when counting in binary:
+        let mut subtree = (0, *hasher.finalize().as_bytes());
+        while let Some(&(height, left)) = self.subtrees.last() {
+            if height != subtree.0 {
+                break;
+            }
+            self.subtrees.pop();
+            subtree = (height + 1, node_hash(&left, &subtree.1));
+        }
+        self.subtrees.push(subtree);
+        self.entries += 1;
+    }
+
+    /// Returns the digest of every entry pushed.
+    pub(crate) fn finish(mut self) -> TableDigest {
+        let Some((_height, mut root)) = self.subtrees.pop() else {
+            return TableDigest::empty();
+        };
+        while let Some((_height, left)) = self.subtrees.pop() {
+            root = node_hash(&left, &root);
+        }
+        TableDigest { root, entries: self.entries }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Computes the digest of the entries of a byte-keyed table whose keys are within `range`.
+///
+/// # Errors
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+pub(crate) fn digest_range<'r>(
+    table: &impl ReadableTable<&'static [u8], &'static [u8]>,
+    range: impl RangeBounds<&'r [u8]> + 'r,
+) -> Result<TableDigest, Error> {
+    let mut builder = MerkleBuilder::default();
+    for entry in table.range(range)? {
+        let (key_guard, value_guard) = entry?;
+        builder.push(key_guard.value(), value_guard.value());
+    }
+    Ok(builder.finish())
+}
+
+/// Hashes two child nodes into their parent.
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[NODE]);
+    hasher.update(left);
+    hasher.update(right);
+    *hasher.finalize().as_bytes()
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(entries: &[(&[u8], &[u8])]) -> TableDigest {
+        let mut builder = MerkleBuilder::default();
+        for (key, value) in entries {
+            builder.push(key, value);
+        }
+        builder.finish()
+    }
+
+    #[test]
+    fn digests_detect_differences() {
+        assert_eq!(digest(&[]), TableDigest::empty());
+
+        let entries: &[(&[u8], &[u8])] = &[(b"a", b"1"), (b"b", b"2"), (b"c", b"3")];
+        assert_eq!(digest(entries), digest(entries));
+        assert_eq!(digest(entries).entries, 3);
+
+        // A changed value, a missing entry, and a key/value boundary shift all change the root:
+        assert_ne!(digest(entries).root, digest(&[(b"a", b"1"), (b"b", b"X"), (b"c", b"3")]).root);
+        assert_ne!(digest(entries).root, digest(&[(b"a", b"1"), (b"c", b"3")]).root);
+        assert_ne!(digest(&[(b"ab", b"c")]).root, digest(&[(b"a", b"bc")]).root);
+    }
+}
@@ -0,0 +1,105 @@
+//! Approximate counts of the keys within a range, without iterating over the whole range.
+
+use crate::Error;
+use redb::ReadableTable;
+use std::ops::{Bound, RangeBounds};
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Ranges with up to this many keys are counted exactly. Larger ranges are estimated.
+pub const EXACT_COUNT_LIMIT: usize = 256;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// An approximate number of keys within a range, returned by `estimate_count`.
+///
+/// Small ranges are counted exactly. For larger ranges, the count is interpolated from the table's
+/// length and the position of the range's bounds between the table's first and last keys. The
+/// estimate is most accurate when keys are spread evenly across the key space, for example
+/// sequential or random integer keys, and least accurate for heavily clustered keys.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RangeEstimate {
+    /// The estimated number of keys within the range.
+    pub count: u64,
+
+    /// `true` if the range was small enough to be counted exactly.
+    pub is_exact: bool,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Estimates the number of keys within a range of a raw `redb` table.
+///
+/// At most [`EXACT_COUNT_LIMIT`] + 1 entries are visited, regardless of the size of the range.
+///
+/// # Errors
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+pub(crate) fn estimate_count<T, KR>(
+    table: &T,
+    range: impl RangeBounds<KR> + Clone,
+) -> Result<RangeEstimate, Error>
+where
+    T: ReadableTable<&'static [u8], &'static [u8]>,
+    KR: for<'a> std::borrow::Borrow<&'a [u8]>
+{
+    // Small ranges are counted exactly. This also handles empty ranges and empty tables:
+    let mut visited: u64 = 0;
+    for entry in table.range(range.clone())?.take(EXACT_COUNT_LIMIT + 1) {
+        entry?;
+        visited += 1;
+    }
+    if visited <= EXACT_COUNT_LIMIT as u64 {
+        return Ok(RangeEstimate { count: visited, is_exact: true });
+    }
+
+    let total = table.len()?;
+    let (Some((first, _)), Some((last, _))) = (table.first()?, table.last()?) else {
+        return Ok(RangeEstimate { count: visited, is_exact: true });
+    };
+    let (first, last) = (first.value().to_vec(), last.value().to_vec());
+
+    // Keys that share a common prefix (for example, a tenant or namespace prefix) would otherwise
+    // all map to the same position:
+    let prefix_len = first.iter().zip(&last).take_while(|(a, b)| a == b).count();
+    let position = |key: &[u8]| key_position(key, prefix_len);
+
+    let lower = match range.start_bound() {
+        Bound::Included(key) | Bound::Excluded(key) => position(key.borrow()),
+        Bound::Unbounded => position(&first),
+    };
+    let upper = match range.end_bound() {
+        Bound::Included(key) | Bound::Excluded(key) => position(key.borrow()),
+        Bound::Unbounded => position(&last),
+    };
+    let span = position(&last) - position(&first);
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss,
+        reason = "the result is an estimate, and is clamped to the table's length"
+    )]
+    let count = if span > 0.0 {
+        ((upper - lower) / span * total as f64).round() as u64
+    } else {
+        total
+    };
+
+    Ok(RangeEstimate { count: count.clamp(visited, total), is_exact: false })
+}
+
+/// Maps a key to its approximate position in the key space, from `0.0` to `1.0`, using the eight
+/// bytes that follow the table's common key prefix.
+fn key_position(key: &[u8], prefix_len: usize) -> f64 {
+    let mut bytes = [0_u8; 8];
+    let significant = key.get(prefix_len..).unwrap_or_default();
+    let len = significant.len().min(8);
+    bytes[..len].copy_from_slice(&significant[..len]);
+
+    #[allow(clippy::cast_precision_loss, reason = "the result is an estimate")]
+    let position = u64::from_be_bytes(bytes) as f64 / u64::MAX as f64;
+    position
+}
@@ -0,0 +1,156 @@
+//! Executes the same query across several open databases, for example one database per year of a
+//! time-partitioned dataset, and merges the results.
+
+use crate::indexing::HasTable;
+use crate::querying::PreparedQuery;
+use crate::typed::database::Database;
+use crate::{Codec, Error};
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A record returned from a federated query, tagged with the member database it came from.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FederatedRecord<K, V> {
+    /// Name of the member database the record was read from. For example: `"2024"`.
+    pub member: String,
+
+    /// The record's primary key.
+    pub key: K,
+
+    /// The record.
+    pub value: V,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A group of open databases that are queried together.
+///
+/// Members are queried in the order they were registered. Each member is read in its own read
+/// transaction, so results are consistent within a member but not across members.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let federation = Federation::new()
+///     .with("2023", Database::open("sightings-2023.redb")?)
+///     .with("2024", Database::open("sightings-2024.redb")?);
+///
+/// let mut prepared = Query::param::<Habitat>("h").prepare();
+/// prepared.bind("h", Habitat("Tide Pool".into()))?;
+///
+/// let sightings = federation.query_ordered_by::<u64, Sighting, _>(
+///     &prepared,
+///     |record| record.value.observed_at
+/// )?;
+/// ```
+#[derive(Default)]
+pub struct Federation {
+    /// Member databases, keyed by name, in registration order.
+    members: Vec<(String, Database)>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Federation {
+    /// Instantiates an empty federation.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a member database under a name, replacing any member with the same name.
+    #[must_use]
+    pub fn with(mut self, name: impl Into<String>, database: Database) -> Self {
+        self.register(name, database);
+        self
+    }
+
+    /// Registers a member database under a name, replacing any member with the same name. Returns
+    /// the replaced database, if any.
+    pub fn register(&mut self, name: impl Into<String>, database: Database) -> Option<Database> {
+        let name = name.into();
+        let previous = self.remove(&name);
+        self.members.push((name, database));
+        previous
+    }
+
+    /// Removes and returns the named member database.
+    pub fn remove(&mut self, name: &str) -> Option<Database> {
+        let position = self.members.iter().position(|(member, _)| member == name)?;
+        Some(self.members.remove(position).1)
+    }
+
+    /// Returns the named member database.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Database> {
+        self.members.iter().find(|(member, _)| member == name).map(|(_, database)| database)
+    }
+
+    /// Returns the names of the member databases, in registration order.
+    pub fn members(&self) -> impl Iterator<Item = &str> {
+        self.members.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Executes a prepared query against every member database, returning the matching records
+    /// grouped by member, in registration order.
+    ///
+    /// # Errors
+    ///
+    /// * Returns an error if the prepared query has unbound parameters.
+    ///
+    /// * Returns the first error encountered by any member, such as a `redb`
+    ///   [storage error](crate::Error#storage-errors).
+    ///
+    /// * Deserialization errors when decoding a primary key or record.
+    pub fn query<K, V>(
+        &self,
+        prepared: &PreparedQuery<V>,
+    ) -> Result<Vec<FederatedRecord<K, V>>, Error>
+    where
+        K: Codec<K>,
+        V: Codec<V> + HasTable + 'static,
+    {
+        let mut records = Vec::new();
+
+        for (member, database) in &self.members {
+            let txn = database.read()?;
+            let key_set = txn.query::<K, V>(prepared.to_query()?)?;
+            let table = txn.open_table::<K, V>(V::table_name())?;
+
+            for key_bytes in key_set {
+                records.push(FederatedRecord {
+                    member: member.clone(),
+                    key: K::deserialize(&key_bytes)?,
+                    value: table.get_by_key_bytes(&key_bytes)?,
+                });
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// Executes a prepared query against every member database, returning the matching records
+    /// from all members merged into a single list ordered by `sort_key`.
+    ///
+    /// The sort is stable, so records with equal sort keys remain in member registration order.
+    ///
+    /// # Errors
+    ///
+    /// * See [`Federation::query`].
+    pub fn query_ordered_by<K, V, O>(
+        &self,
+        prepared: &PreparedQuery<V>,
+        mut sort_key: impl FnMut(&FederatedRecord<K, V>) -> O,
+    ) -> Result<Vec<FederatedRecord<K, V>>, Error>
+    where
+        K: Codec<K>,
+        V: Codec<V> + HasTable + 'static,
+        O: Ord,
+    {
+        let mut records = self.query::<K, V>(prepared)?;
+        records.sort_by_key(|record| sort_key(record));
+        Ok(records)
+    }
+}
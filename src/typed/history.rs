@@ -0,0 +1,330 @@
+//! Record history: every version of a record that's been replaced or deleted, and when it was
+//! current.
+//!
+//! History is kept for record types that declare a history table with
+//! [`HasTable::history_table_name`]. Whenever an index-aware write replaces or deletes a record,
+//! the version it supersedes is appended to the history table along with the period it was
+//! current. Writing a record that didn't exist also appends an entry, recording that the record
+//! was absent until then, so that [`Historied::get_as_of`] can tell when a record was created.
+//!
+//! History is never pruned automatically. Writes made directly to a [`TableMut`] or a `redb` table
+//! bypass it.
+//!
+//! [`TableMut`]: crate::typed::TableMut
+
+use crate::indexing::HasTable;
+use crate::typed::record_layers::RecordContext;
+use crate::{Codec, Error};
+use std::marker::PhantomData;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Length of a history row's header: the version's start and end times.
+const ROW_HEADER_LEN: usize = 16;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Describes one historical version of a record.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Version {
+    /// Position of the version in the record's history, starting at `1` for the oldest.
+    pub number: u64,
+
+    /// When the version became current. This is the Unix epoch for the oldest version, since
+    /// history doesn't know when a record was first written.
+    pub valid_from: SystemTime,
+
+    /// When the version was replaced or deleted.
+    pub valid_until: SystemTime,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A view of a record type's current records and their history, opened with
+/// [`Transaction::historied`].
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let creatures = db.read()?.historied::<u64, Creature>()?;
+/// for (version, creature) in creatures.history(&7)? {
+///     println!("version {} ended at {:?}: {creature:?}", version.number, version.valid_until);
+/// }
+/// let last_week = SystemTime::now() - Duration::from_secs(7 * 24 * 60 * 60);
+/// let then = creatures.get_as_of(&7, last_week)?;
+/// ```
+///
+/// [`Transaction::historied`]: crate::typed::transaction::ReadTransaction::historied
+pub struct Historied<K, V> {
+    primary_table: Option<redb::ReadOnlyTable<&'static [u8], &'static [u8]>>,
+    history_table: Option<redb::ReadOnlyTable<&'static [u8], &'static [u8]>>,
+    context: RecordContext,
+    phantom_data: PhantomData<(K, V)>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<K, V> Historied<K, V>
+where
+    K: Codec<K>,
+    V: Codec<V> + HasTable,
+{
+    /// Wraps a record type's primary and history tables. Either may be `None` if it hasn't been
+    /// created yet. Versions are copied from the primary table as they're stored, so both are
+    /// decoded in the primary table's context.
+    pub(crate) const fn new(
+        primary_table: Option<redb::ReadOnlyTable<&'static [u8], &'static [u8]>>,
+        history_table: Option<redb::ReadOnlyTable<&'static [u8], &'static [u8]>>,
+        context: RecordContext,
+    ) -> Self {
+        Self { primary_table, history_table, context, phantom_data: PhantomData }
+    }
+
+    /// Returns the current version of a record, or `None` if it doesn't exist.
+    ///
+    /// # Errors
+    ///
+    /// * Encoding the primary key or decoding the record fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn get(&self, primary_key: &K) -> Result<Option<V>, Error> {
+        let Some(primary_table) = &self.primary_table else {
+            return Ok(None);
+        };
+        let primary_key_bytes = K::serialize(primary_key)?;
+        primary_table
+            .get(&*primary_key_bytes)?
+            .map(|value| self.context.open(&primary_key_bytes, value.value()))
+            .transpose()
+    }
+
+    /// Returns every replaced or deleted version of a record, oldest first. The current version
+    /// isn't included; use [`Historied::get`] for it.
+    ///
+    /// # Errors
+    ///
+    /// * Encoding the primary key or decoding a version fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn history(&self, primary_key: &K) -> Result<impl Iterator<Item = (Version, V)>, Error> {
+        let primary_key_bytes = K::serialize(primary_key)?;
+        let mut versions = Vec::new();
+        for (version, value_bytes) in self.versions(&primary_key_bytes)? {
+            if let Some(value_bytes) = value_bytes {
+                versions.push((version, self.context.open(&primary_key_bytes, &value_bytes)?));
+            }
+        }
+        Ok(versions.into_iter())
+    }
+
+    /// Returns a record as it was at the given time, or `None` if it didn't exist then.
+    ///
+    /// # Errors
+    ///
+    /// * Encoding the primary key or decoding the record fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn get_as_of(&self, primary_key: &K, timestamp: SystemTime) -> Result<Option<V>, Error> {
+        let primary_key_bytes = K::serialize(primary_key)?;
+        let millis = to_millis(timestamp);
+
+        // Versions are contiguous, so the first one to end after the timestamp was current then:
+        for (version, value_bytes) in self.versions(&primary_key_bytes)? {
+            if to_millis(version.valid_until) > millis {
+                return value_bytes
+                    .map(|value_bytes| self.context.open(&primary_key_bytes, &value_bytes))
+                    .transpose();
+            }
+        }
+
+        self.get(primary_key)
+    }
+
+    /// Returns every historical version of a record, oldest first, including periods in which the
+    /// record didn't exist (`None`).
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::HistoryRowInvalid`] if a history row is too short.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    #[allow(clippy::type_complexity, reason = "a version and its optional value bytes")]
+    fn versions(
+        &self,
+        primary_key_bytes: &[u8],
+    ) -> Result<Vec<(Version, Option<Vec<u8>>)>, Error> {
+        let Some(history_table) = &self.history_table else {
+            return Ok(Vec::new());
+        };
+
+        let start = history_key(primary_key_bytes, 0);
+        let end = history_key(primary_key_bytes, u64::MAX);
+        history_table
+            .range::<&[u8]>(&*start..=&*end)?
+            .map(|entry| {
+                let (key, row) = entry?;
+                decode_history_row(key.value(), row.value())
+                    .ok_or(Error::HistoryRowInvalid { table: V::table_name() })
+            })
+            .collect()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Returns the current time, in milliseconds since the Unix epoch.
+#[cfg(feature = "writes")]
+pub(crate) fn now_millis() -> u64 {
+    to_millis(crate::clock::now())
+}
+
+/// Converts a time to milliseconds since the Unix epoch. Times before the epoch are clamped to it.
+pub(crate) fn to_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX))
+}
+
+/// Returns a history table key: `u32 big-endian primary key length | primary key | u64 big-endian
+/// version number`. The length prefix keeps one record's versions together, and apart from those
+/// of records whose primary keys start with its primary key.
+pub(crate) fn history_key(primary_key_bytes: &[u8], number: u64) -> Vec<u8> {
+    let mut key = Vec::with_capacity(4 + primary_key_bytes.len() + 8);
+    let length = u32::try_from(primary_key_bytes.len()).unwrap_or(u32::MAX);
+    key.extend_from_slice(&length.to_be_bytes());
+    key.extend_from_slice(primary_key_bytes);
+    key.extend_from_slice(&number.to_be_bytes());
+    key
+}
+
+/// Encodes a history row: `u64 big-endian valid-from millis | u64 big-endian valid-until millis |
+/// presence flag | value bytes`.
+#[cfg(feature = "writes")]
+pub(crate) fn encode_history_row(
+    valid_from: u64,
+    valid_until: u64,
+    value: Option<&[u8]>,
+) -> Vec<u8> {
+    let mut row = Vec::with_capacity(ROW_HEADER_LEN + 1 + value.map_or(0, <[u8]>::len));
+    row.extend_from_slice(&valid_from.to_be_bytes());
+    row.extend_from_slice(&valid_until.to_be_bytes());
+    row.push(u8::from(value.is_some()));
+    row.extend_from_slice(value.unwrap_or_default());
+    row
+}
+
+/// Decodes a history key and row into the version they describe, and the version's value bytes.
+/// Returns `None` if either is too short.
+pub(crate) fn decode_history_row(key: &[u8], row: &[u8]) -> Option<(Version, Option<Vec<u8>>)> {
+    let number = u64::from_be_bytes(key.get(key.len().checked_sub(8)?..)?.try_into().ok()?);
+    let valid_from = u64::from_be_bytes(row.get(..8)?.try_into().ok()?);
+    let valid_until = u64::from_be_bytes(row.get(8..ROW_HEADER_LEN)?.try_into().ok()?);
+    let value = match row.get(ROW_HEADER_LEN)? {
+        0 => None,
+        _ => Some(row[ROW_HEADER_LEN + 1..].to_vec()),
+    };
+    let version = Version {
+        number,
+        valid_from: UNIX_EPOCH + Duration::from_millis(valid_from),
+        valid_until: UNIX_EPOCH + Duration::from_millis(valid_until),
+    };
+    Some((version, value))
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "writes"))]
+mod tests {
+    use super::*;
+    use crate::layers::encryptors::{KEY_SIZE, KeyBytes};
+    use crate::typed::database::Database;
+    use crate::typed::test_records::{Animal, Letter};
+
+    /// Returns the current time, a little after the last write and a little before the next, so
+    /// that every write lands in a millisecond of its own.
+    fn pause() -> SystemTime {
+        std::thread::sleep(Duration::from_millis(3));
+        let now = crate::clock::now();
+        std::thread::sleep(Duration::from_millis(3));
+        now
+    }
+
+    #[test]
+    fn reads_versions_across_updates_and_deletes() {
+        let mut db = Database::in_memory().unwrap();
+        db.set_record_key(&KeyBytes::from_array(&[5; KEY_SIZE]));
+        let draft = Letter::new(1, "Ada", "Draft");
+        let revised = Letter::new(1, "Ada", "Revised");
+        let resent = Letter::new(1, "Ada", "Resent");
+
+        let before_creation = pause();
+        let write = |letter: Option<&Letter>| {
+            let mut txn = db.write().unwrap();
+            match letter {
+                Some(letter) => { txn.insert::<u64, Letter>(letter).unwrap(); },
+                None => { txn.remove::<u64, Letter>(&1).unwrap().unwrap(); },
+            }
+            txn.commit().unwrap();
+        };
+        write(Some(&draft));
+        let while_drafted = pause();
+        write(Some(&revised));
+        let while_revised = pause();
+        write(None);
+        let while_deleted = pause();
+        write(Some(&resent));
+        let while_resent = pause();
+
+        let letters = db.read().unwrap().historied::<u64, Letter>().unwrap();
+        let history: Vec<(Version, Letter)> = letters.history(&1).unwrap().collect();
+        let texts: Vec<&str> = history.iter().map(|(_, letter)| letter.text.as_str()).collect();
+        assert_eq!(texts, ["Draft", "Revised"]);
+        assert_eq!(history.iter().map(|(version, _)| version.number).collect::<Vec<_>>(), [2, 3]);
+        assert!(history[0].0.valid_from < while_drafted);
+        assert!(while_drafted < history[0].0.valid_until);
+        assert_eq!(history[0].0.valid_until, history[1].0.valid_from);
+
+        let as_of = |time| letters.get_as_of(&1, time).unwrap().map(|letter| letter.text);
+        assert_eq!(as_of(before_creation), None);
+        assert_eq!(as_of(while_drafted).as_deref(), Some("Draft"));
+        assert_eq!(as_of(while_revised).as_deref(), Some("Revised"));
+        assert_eq!(as_of(while_deleted), None);
+        assert_eq!(as_of(while_resent).as_deref(), Some("Resent"));
+        assert_eq!(letters.get(&1).unwrap(), Some(resent));
+
+        assert_eq!(letters.history(&2).unwrap().count(), 0);
+        assert_eq!(letters.get_as_of(&2, while_resent).unwrap(), None);
+    }
+
+    #[test]
+    fn record_types_without_history_are_refused() {
+        let db = Database::in_memory().unwrap();
+        let result = db.read().unwrap().historied::<u64, Animal>();
+        assert!(matches!(result, Err(Error::HistoryNotDeclared { table: "animals" })));
+    }
+
+    #[test]
+    fn history_rows_round_trip() {
+        let key = history_key(b"kelp", 3);
+        let (version, value) = decode_history_row(&key, &encode_history_row(5, 9, Some(b"v2")))
+            .unwrap();
+        assert_eq!(version.number, 3);
+        assert_eq!(version.valid_from, UNIX_EPOCH + Duration::from_millis(5));
+        assert_eq!(version.valid_until, UNIX_EPOCH + Duration::from_millis(9));
+        assert_eq!(value.as_deref(), Some(&b"v2"[..]));
+
+        let (_, absent) = decode_history_row(&key, &encode_history_row(0, 5, None)).unwrap();
+        assert_eq!(absent, None);
+        assert_eq!(decode_history_row(&key, &[0; 4]), None);
+
+        // A record's versions sort together, apart from records with longer primary keys:
+        assert!(history_key(b"kelp", u64::MAX) < history_key(b"kelp forest", 0));
+        assert!(history_key(b"kelp", 1) < history_key(b"kelp", 2));
+    }
+}
@@ -0,0 +1,390 @@
+//! Conflict resolution for bidirectional sync, where both peers may have written the same record
+//! since they last synchronized.
+//!
+//! Every record write and deletion is stamped with a [`HybridTimestamp`] once merge clocks are
+//! enabled with [`Database::enable_merge_clock`]. Stored values are opaque once they've passed
+//! through the serialization, compression, encryption, and error correction layers, so stamps are
+//! kept beside them in the [`CLOCK_TABLE_NAME`] table. A deletion leaves a stamped tombstone
+//! there, so that it isn't undone by a peer that still has the record.
+//!
+//! When a record differs between two peers, its [`MergePolicy`] chooses the version both peers
+//! keep. [`LastWriterWins`] keeps the version with the later stamp, and [`CrdtMerge`] combines the
+//! two versions with a user-supplied function, for record types that are conflict-free replicated
+//! data types.
+//!
+//! [`Database::enable_merge_clock`]: crate::typed::database::Database::enable_merge_clock
+
+use crate::defaults::Defaults;
+use crate::indexing::{HasTable, Indexable};
+use crate::typed::transaction::{QuerySource, WriteTransaction};
+use crate::{Codec, Error};
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Name of the internal table that stores merge clock stamps.
+///
+/// The clock table is shared by every namespace. The row with an empty key holds the latest stamp
+/// this peer issued or received. Every other row is keyed by `full table name | 0x00 | primary
+/// key`, and holds the stamp of the record's latest write or deletion, followed by a deletion flag
+/// byte. Deletion rows are kept forever, so that a deletion always wins over older writes.
+pub const CLOCK_TABLE_NAME: &str = "__atlatl_clocks";
+
+/// Definition of the clock table: clock key → encoded stamp and deletion flag.
+pub(crate) const CLOCK_TABLE: redb::TableDefinition<&[u8], &[u8]> =
+    redb::TableDefinition::new(CLOCK_TABLE_NAME);
+
+/// Length of an encoded [`HybridTimestamp`].
+pub(crate) const STAMP_LEN: usize = 12;
+
+/// Function that writes a resolved version of a record to its primary table, maintaining the
+/// record's secondary index entries. Monomorphized per registered record type.
+type Applier = fn(&mut WriteTransaction, &[u8], Option<&[u8]>) -> Result<(), Error>;
+
+/// Function that converts a record, stored under the given primary key, between its stored bytes
+/// and the bytes its [`Codec`] serializes it to. Monomorphized per registered record type.
+type Recoder = fn(&WriteTransaction, &[u8], &[u8]) -> Result<Vec<u8>, Error>;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A hybrid logical clock timestamp: wall-clock milliseconds, a logical counter that orders
+/// events within the same millisecond, and the ID of the node that issued it.
+///
+/// Timestamps are ordered by their fields in that order, and a peer never issues a timestamp
+/// earlier than one it has already issued or received, even if its wall clock goes backwards.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct HybridTimestamp {
+    /// Milliseconds since the Unix epoch.
+    pub millis: u64,
+
+    /// Logical counter, for events in the same millisecond.
+    pub counter: u16,
+
+    /// ID of the node that issued the timestamp, which breaks ties between peers.
+    pub node: u16,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// One peer's version of a record, given to a [`MergePolicy`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Version<'v> {
+    /// The record's bytes, as its [`Codec`] serializes them, or `None` if the record was deleted.
+    /// Records with [`RecordLayers`] have already been passed back through them.
+    ///
+    /// [`RecordLayers`]: crate::typed::record_layers::RecordLayers
+    pub value: Option<&'v [u8]>,
+
+    /// When the record was last written or deleted. Records written before merge clocks were
+    /// enabled have the default, earliest, timestamp.
+    pub stamp: HybridTimestamp,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The version of a record chosen by a [`MergePolicy`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Resolution {
+    /// Keep the local version.
+    Local,
+
+    /// Keep the remote version.
+    Remote,
+
+    /// Keep a new version, with these bytes, that combines both. The bytes are encoded as the
+    /// record's [`Codec`] serializes it, and passed through its record layers before they're
+    /// stored.
+    Merged(Vec<u8>),
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Chooses which version of a record both peers keep, when the record differs between them.
+///
+/// Policies must be deterministic, so that the same two versions always resolve the same way.
+pub trait MergePolicy: Send + Sync {
+    /// Resolves a conflict between the local and remote versions of a record.
+    ///
+    /// # Errors
+    ///
+    /// * The versions couldn't be decoded or combined. The sync is stopped.
+    fn resolve(&self, local: &Version<'_>, remote: &Version<'_>) -> Result<Resolution, Error>;
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Keeps whichever version of a record was written or deleted last, by [`HybridTimestamp`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LastWriterWins;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Combines two versions of a record with a user-supplied merge function, for record types that are
+/// conflict-free replicated data types (CRDTs), such as counters or sets that only grow.
+///
+/// The function must be commutative, associative, and idempotent. Deletions can't be merged, so if
+/// either version is a deletion, the conflict is resolved with [`LastWriterWins`].
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let merger = Merger::new().record::<PageViews>(CrdtMerge::new(|local, remote| PageViews {
+///     count: local.count.max(remote.count),
+///     ..local
+/// }));
+/// ```
+pub struct CrdtMerge<V, F> {
+    merge: F,
+    record: PhantomData<fn() -> V>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The record types to merge during a bidirectional sync, and how each resolves conflicts.
+///
+/// Stored values don't record their type, so every primary table to be synchronized must have its
+/// record type registered with [`Merger::record`]. Both peers must register the same record types.
+/// Secondary indexes aren't compared: they're updated as each merged record is written, after its
+/// [`Defaults`] are applied.
+#[derive(Default)]
+pub struct Merger {
+    tables: BTreeMap<&'static str, Registered>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A record type registered with a [`Merger`].
+pub(crate) struct Registered {
+    /// Resolves conflicts between the record type's versions.
+    pub(crate) policy: Box<dyn MergePolicy>,
+
+    /// Writes a resolved version, given its stored bytes.
+    pub(crate) apply: Applier,
+
+    /// Converts stored bytes to serialized bytes, for the policy.
+    pub(crate) open: Recoder,
+
+    /// Converts serialized bytes, merged by the policy, to stored bytes.
+    pub(crate) seal: Recoder,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl HybridTimestamp {
+    /// Returns the next timestamp for this node: the current time if the clock has moved past
+    /// `last`, otherwise `last` with its counter advanced.
+    #[must_use]
+    pub fn next(last: Self, node: u16) -> Self {
+        let now = crate::clock::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX));
+        Self::next_at(last, now, node)
+    }
+
+    /// Returns the next timestamp for this node, given the current wall-clock time.
+    #[must_use]
+    pub(crate) const fn next_at(last: Self, now: u64, node: u16) -> Self {
+        if now > last.millis {
+            Self { millis: now, counter: 0, node }
+        } else if last.counter == u16::MAX {
+            Self { millis: last.millis + 1, counter: 0, node }
+        } else {
+            Self { millis: last.millis, counter: last.counter + 1, node }
+        }
+    }
+
+    /// Encodes the timestamp so that encoded timestamps sort in the same order as timestamps.
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; STAMP_LEN] {
+        let mut bytes = [0; STAMP_LEN];
+        bytes[..8].copy_from_slice(&self.millis.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.counter.to_be_bytes());
+        bytes[10..].copy_from_slice(&self.node.to_be_bytes());
+        bytes
+    }
+
+    /// Decodes a timestamp encoded by [`HybridTimestamp::to_bytes`]. Returns `None` if `bytes` is
+    /// too short.
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Some(Self {
+            millis: u64::from_be_bytes(bytes.get(..8)?.try_into().ok()?),
+            counter: u16::from_be_bytes(bytes.get(8..10)?.try_into().ok()?),
+            node: u16::from_be_bytes(bytes.get(10..STAMP_LEN)?.try_into().ok()?),
+        })
+    }
+}
+
+impl<V, F> CrdtMerge<V, F>
+where
+    V: Codec<V>,
+    F: Fn(V, V) -> V + Send + Sync,
+{
+    /// Creates a policy that merges two versions of a record with `merge(local, remote)`.
+    pub const fn new(merge: F) -> Self {
+        Self { merge, record: PhantomData }
+    }
+}
+
+impl Merger {
+    /// Creates a merger with no record types registered.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a record type, and the policy that resolves conflicts between its versions.
+    #[must_use]
+    pub fn record<V>(mut self, policy: impl MergePolicy + 'static) -> Self
+    where
+        V: for<'i> Indexable<'i> + Defaults + Codec<V> + HasTable,
+    {
+        self.tables.insert(V::table_name(), Registered {
+            policy: Box::new(policy),
+            apply: apply::<V>,
+            open: open::<V>,
+            seal: seal::<V>,
+        });
+        self
+    }
+
+    /// Returns the registered table names, in order.
+    pub(crate) fn table_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.tables.keys().copied()
+    }
+
+    /// Returns a registered table's record type, or `None` if it isn't registered.
+    pub(crate) fn table(&self, table_name: &str) -> Option<&Registered> {
+        self.tables.get(table_name)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl MergePolicy for LastWriterWins {
+    fn resolve(&self, local: &Version<'_>, remote: &Version<'_>) -> Result<Resolution, Error> {
+        Ok(if remote.stamp > local.stamp { Resolution::Remote } else { Resolution::Local })
+    }
+}
+
+impl<V, F> MergePolicy for CrdtMerge<V, F>
+where
+    V: Codec<V>,
+    F: Fn(V, V) -> V + Send + Sync,
+{
+    fn resolve(&self, local: &Version<'_>, remote: &Version<'_>) -> Result<Resolution, Error> {
+        let (Some(local_bytes), Some(remote_bytes)) = (local.value, remote.value) else {
+            return LastWriterWins.resolve(local, remote);
+        };
+        let merged = (self.merge)(V::deserialize(local_bytes)?, V::deserialize(remote_bytes)?);
+        Ok(Resolution::Merged(V::serialize(&merged)?))
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Returns the clock table key of a record: `full table name | 0x00 | primary key`.
+pub(crate) fn clock_key(full_table_name: &str, primary_key_bytes: &[u8]) -> Vec<u8> {
+    let mut key = Vec::with_capacity(full_table_name.len() + 1 + primary_key_bytes.len());
+    key.extend_from_slice(full_table_name.as_bytes());
+    key.push(0);
+    key.extend_from_slice(primary_key_bytes);
+    key
+}
+
+/// Encodes a record's clock row: its stamp, and whether it was deleted.
+pub(crate) fn encode_clock_row(stamp: HybridTimestamp, deleted: bool) -> [u8; STAMP_LEN + 1] {
+    let mut row = [0; STAMP_LEN + 1];
+    row[..STAMP_LEN].copy_from_slice(&stamp.to_bytes());
+    row[STAMP_LEN] = u8::from(deleted);
+    row
+}
+
+/// Decodes a record's clock row into its stamp, and whether it was deleted.
+pub(crate) fn decode_clock_row(row: &[u8]) -> Option<(HybridTimestamp, bool)> {
+    Some((HybridTimestamp::from_bytes(row)?, *row.get(STAMP_LEN)? != 0))
+}
+
+/// Writes or deletes a record, given its stored bytes, through the index-aware write paths.
+fn apply<V>(txn: &mut WriteTransaction, key: &[u8], value: Option<&[u8]>) -> Result<(), Error>
+where
+    V: for<'i> Indexable<'i> + Defaults + Codec<V> + HasTable,
+{
+    match value {
+        Some(value) => {
+            let record: V = txn.open_record(key, value)?;
+            txn.write_indexed(key, &record, |_| Ok(()))
+        },
+        None => txn.delete_by_key_bytes::<V>(key).map(drop),
+    }
+}
+
+/// Converts a record's stored bytes to the bytes its [`Codec`] serializes it to.
+fn open<V: Codec<V> + HasTable>(
+    txn: &WriteTransaction,
+    key: &[u8],
+    value: &[u8],
+) -> Result<Vec<u8>, Error> {
+    if V::layers().is_none() {
+        return Ok(value.to_vec());
+    }
+    V::serialize(&txn.open_record::<V>(key, value)?)
+}
+
+/// Converts the bytes a record's [`Codec`] serializes it to into its stored bytes.
+fn seal<V: Codec<V> + HasTable>(
+    txn: &WriteTransaction,
+    key: &[u8],
+    value: &[u8],
+) -> Result<Vec<u8>, Error> {
+    if V::layers().is_none() {
+        return Ok(value.to_vec());
+    }
+    txn.seal_record(key, &V::deserialize(value)?)
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clocks_never_go_backwards() {
+        let stamp = |millis, counter, node| HybridTimestamp { millis, counter, node };
+        let last = stamp(1_000, 4, 1);
+
+        // The wall clock moved forward, fell behind, or is about to overflow the counter:
+        assert_eq!(HybridTimestamp::next_at(last, 2_000, 2), stamp(2_000, 0, 2));
+        assert_eq!(HybridTimestamp::next_at(last, 500, 2), stamp(1_000, 5, 2));
+        let saturated = HybridTimestamp { counter: u16::MAX, ..last };
+        assert!(HybridTimestamp::next_at(saturated, 500, 1) > saturated);
+
+        // Encoded timestamps sort like timestamps:
+        let later = HybridTimestamp::next_at(last, 500, 0);
+        assert!(later > last && later.to_bytes() > last.to_bytes());
+        assert_eq!(HybridTimestamp::from_bytes(&later.to_bytes()), Some(later));
+    }
+
+    #[test]
+    fn last_writer_wins() {
+        let earlier = Version {
+            value: Some(b"Coyote"),
+            stamp: HybridTimestamp { millis: 1, counter: 0, node: 1 },
+        };
+
+        // Same millisecond and counter, so the node ID breaks the tie:
+        let later = Version { value: None, stamp: HybridTimestamp { node: 2, ..earlier.stamp } };
+        assert_eq!(LastWriterWins.resolve(&earlier, &later).unwrap(), Resolution::Remote);
+        assert_eq!(LastWriterWins.resolve(&later, &earlier).unwrap(), Resolution::Local);
+    }
+}
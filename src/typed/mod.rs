@@ -6,10 +6,14 @@
 //! Range queries and prefix scans are disabled by default and only available when the key type also
 //! implements [`crate::layers::serializers::OrderedWhenSerialized`].
 
+#[cfg(feature = "writes")]
 mod table_mut;
 
+#[cfg(feature = "writes")]
 pub use crate::typed::table_mut::TableMut;
+#[cfg(feature = "writes")]
 pub use crate::typed::table_mut::RawTable;
+#[cfg(feature = "writes")]
 pub use crate::typed::table_mut::OrderedTable as OrderedTableMut;
 
 mod table_ref;
@@ -18,8 +22,68 @@ pub use crate::typed::table_ref::TableRef;
 pub use crate::typed::table_ref::RawReadOnlyTable;
 pub use crate::typed::table_ref::OrderedTable as OrderedTableRef;
 
+pub mod archive;
+pub mod audit;
+pub mod backup;
+#[cfg(feature = "blobs")]
+pub mod blobs;
+#[cfg(feature = "writes")]
+pub mod bulk;
+pub mod change_log;
+#[cfg(feature = "hash-chain")]
+pub mod chain;
+#[cfg(feature = "csv-import")]
+pub mod csv_import;
+pub mod cursor;
 pub mod database;
+pub mod delta;
+#[cfg(feature = "digest")]
+pub mod digest;
+pub mod estimate;
+pub mod federation;
+pub mod history;
+pub mod nonce_counter;
+#[cfg(feature = "sync")]
+pub mod merge;
+pub mod projection;
+pub mod quota;
+pub mod record_layers;
+pub mod read_only;
+pub mod repair;
+#[cfg(feature = "writes")]
+pub mod reserialization;
+#[cfg(feature = "writes")]
+pub mod retention;
+#[cfg(feature = "writes")]
+pub mod rotation;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod scan;
+pub mod scrub;
+pub mod shared_cache;
+pub mod snapshot;
+pub mod stats_report;
+#[cfg(feature = "sync")]
+pub mod sync;
 pub mod transaction;
+pub mod usage;
+
+mod namespace;
+pub use crate::typed::namespace::Namespace;
+
+#[cfg(feature = "tracing-spans")]
+mod spans;
+#[cfg(feature = "tracing-spans")]
+pub(crate) use crate::typed::spans::KeyField;
+
+mod tables;
+pub use crate::typed::tables::TableHandle;
+
+mod tenant;
+pub use crate::typed::tenant::Tenant;
+
+#[cfg(test)]
+pub(crate) mod test_records;
 
 // -------------------------------------------------------------------------------------------------
 //
@@ -0,0 +1,117 @@
+//! Namespaces, which prefix table names so that several groups of tables can share one database.
+
+use std::borrow::Cow;
+use std::sync::Arc;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A prefix applied to every table name opened through a transaction, so that a single `redb` file
+/// can host several tenants or environments without their table names colliding.
+///
+/// Record tables, index tables, and every internal table (reverse indexes, covering tables, key
+/// set shards, and index statistics) are all prefixed. For example, in the `tenant42` namespace,
+/// the `creatures` table is stored as `tenant42.creatures`, and its `creatures_by_habitat` index
+/// as `tenant42.creatures_by_habitat`.
+///
+/// The default namespace has no prefix, and is the one used by transactions unless another is
+/// selected with `in_namespace`.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let tenant = Namespace::new("tenant42");
+/// let txn = db.write()?.in_namespace(tenant);
+/// txn.insert(&creature)?; // Written to `tenant42.creatures`.
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Namespace(Option<Arc<str>>);
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Namespace {
+    /// The separator placed between a namespace and a table name.
+    pub const SEPARATOR: char = '.';
+
+    /// Creates a namespace with the given name. For example: `"tenant42"` or `"staging"`.
+    ///
+    /// An empty name is the default namespace, which has no prefix.
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self(Some(name).filter(|name| !name.is_empty()).map(Arc::from))
+    }
+
+    /// Returns the namespace's name, or `None` for the default namespace.
+    #[must_use]
+    pub fn name(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+
+    /// Returns a nested namespace. For example, `Namespace::new("tenant42").child("staging")` is
+    /// the `tenant42.staging` namespace.
+    #[must_use]
+    pub fn child(&self, name: &str) -> Self {
+        Self::new(&self.table_name(name))
+    }
+
+    /// Returns the full name of a table in this namespace. For example, `creatures` becomes
+    /// `tenant42.creatures`. Table names in the default namespace are returned as-is.
+    #[must_use]
+    pub fn table_name<'n>(&self, name: &'n str) -> Cow<'n, str> {
+        self.0.as_ref().map_or(Cow::Borrowed(name), |prefix| {
+            Cow::Owned(format!("{prefix}{}{name}", Self::SEPARATOR))
+        })
+    }
+
+    /// Returns the table name without this namespace's prefix, or `None` if the full table name
+    /// isn't in this namespace. Useful for filtering the output of `list_tables`.
+    #[must_use]
+    pub fn strip<'n>(&self, full_name: &'n str) -> Option<&'n str> {
+        match &self.0 {
+            Some(prefix) => full_name
+                .strip_prefix(&**prefix)?
+                .strip_prefix(Self::SEPARATOR),
+            None => Some(full_name),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl From<&str> for Namespace {
+    /// Creates a namespace with the given name.
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl std::fmt::Display for Namespace {
+    /// Formats the namespace's name, which is empty for the default namespace.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name().unwrap_or_default())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefixes_table_names() {
+        let tenant = Namespace::new("tenant42");
+        assert_eq!(tenant.table_name("creatures"), "tenant42.creatures");
+        assert_eq!(tenant.child("staging").table_name("creatures"), "tenant42.staging.creatures");
+        assert_eq!(tenant.strip("tenant42.creatures"), Some("creatures"));
+        assert_eq!(tenant.strip("tenant420.creatures"), None);
+
+        assert_eq!(Namespace::default().table_name("creatures"), "creatures");
+        assert_eq!(Namespace::new(""), Namespace::default());
+    }
+}
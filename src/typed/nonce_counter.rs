@@ -0,0 +1,44 @@
+//! The persisted position of the database's nonce counter, for record types that encrypt with
+//! [`NonceStrategy::Counter`].
+//!
+//! A [`NonceCounter`] is started from the persisted position with [`Database::nonce_counter`], and
+//! every nonce drawn from it with [`WriteTransaction::next_nonce`] advances the persisted position
+//! in the same transaction as the value it encrypts. A value can't be committed without its
+//! nonce's position being committed too, so the counter never hands out a committed nonce again
+//! after a restart. Each counter also starts with a fresh random prefix, so nonces drawn in a
+//! transaction that was later aborted aren't repeated either.
+//!
+//! [`NonceStrategy::Counter`]: crate::layers::encryptors::NonceStrategy::Counter
+//! [`NonceCounter`]: crate::layers::encryptors::NonceCounter
+//! [`Database::nonce_counter`]: crate::typed::database::Database::nonce_counter
+//! [`WriteTransaction::next_nonce`]: crate::typed::transaction::WriteTransaction::next_nonce
+
+use crate::Error;
+use redb::ReadableTable;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Name of the internal table that stores the nonce counter's position.
+///
+/// The table has a single row, with an empty key, that holds the counter value of the next nonce
+/// to be handed out.
+pub const NONCE_COUNTER_TABLE_NAME: &str = "__atlatl_nonce_counter";
+
+/// Definition of the nonce counter table: empty key → next counter value.
+pub(crate) const NONCE_COUNTER_TABLE: redb::TableDefinition<&str, u64> =
+    redb::TableDefinition::new(NONCE_COUNTER_TABLE_NAME);
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Returns the persisted position of the nonce counter, or `0` if no nonce has been drawn yet.
+///
+/// # Errors
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+pub(crate) fn persisted_position(
+    table: &impl ReadableTable<&'static str, u64>,
+) -> Result<u64, Error> {
+    Ok(table.get("")?.map_or(0, |position| position.value()))
+}
@@ -0,0 +1,110 @@
+//! Partial decoding of records into lighter "view" types while iterating over a table.
+
+use crate::{Codec, typed::{RedbRange, ResultEntry}};
+use std::marker::PhantomData;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A lighter view of record type `V` that can be decoded from `V`'s stored bytes.
+///
+/// Projections let list endpoints stream large tables while decoding only the fields they display.
+/// For example, a `UserSummary { id, name }` can be decoded from a stored `User` without
+/// allocating the user's biography or avatar.
+///
+/// Implementations typically forward to
+/// [`Bytes::apply_read_layers_as`](crate::layers::core::Bytes::apply_read_layers_as), so that the
+/// record's own error correction, encryption, and compression settings are honoured and only the
+/// final deserialization step uses the projection type.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// #[derive(serde::Deserialize)]
+/// struct UserSummary {
+///     id: u64,
+///     name: String,
+///     biography: serde::de::IgnoredAny,
+/// }
+///
+/// for entry in table.range(..)?.map_while_deserializing::<UserSummary>() {
+///     let (user_id, summary) = entry?;
+/// }
+/// ```
+pub trait Projection<V>: Sized {
+    /// Decodes the projection from the stored bytes of a `V` record.
+    ///
+    /// # Errors
+    ///
+    /// * Returns an error if any of `V`'s read layers fail, or if the bytes can't be decoded into
+    ///   the projection.
+    fn deserialize_projection(value_bytes: &[u8]) -> Result<Self, crate::Error>;
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A double-ended iterator over a range of entries in a `V` table, where each value is decoded
+/// into the projection type `P` rather than the full record.
+///
+/// This is returned by [`Range::map_while_deserializing`](crate::typed::table_ref::Range).
+pub struct Projected<'r, K, V, P> {
+    inner: RedbRange<'r>,
+    _phantom: PhantomData<(K, V, P)>,
+}
+
+impl<'r, K, V, P> Projected<'r, K, V, P> {
+    /// Instantiates a new `Projected` iterator over a raw `redb` range.
+    pub(crate) const fn new(inner: RedbRange<'r>) -> Self {
+        Self { inner, _phantom: PhantomData }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl<K, V, P> Iterator for Projected<'_, K, V, P>
+where
+    K: Codec<K>,
+    P: Projection<V>
+{
+    type Item = ResultEntry<K, P>;
+
+    /// Advances the iterator and returns the next key and projection in ascending key order.
+    ///
+    /// # Errors
+    ///
+    /// * Returns an error if decoding the key or the projection fails.
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| entry
+            .map_err(Into::into)
+            .and_then(|(k_guard, v_guard)| {
+                let key        = K::deserialize(k_guard.value())?;
+                let projection = P::deserialize_projection(v_guard.value())?;
+                Ok((key, projection))
+            })
+        )
+    }
+}
+
+impl<K, V, P> DoubleEndedIterator for Projected<'_, K, V, P>
+where
+    K: Codec<K>,
+    P: Projection<V>
+{
+    /// Advances the iterator from the end and returns the next key and projection in descending
+    /// key order.
+    ///
+    /// # Errors
+    ///
+    /// * Returns an error if decoding the key or the projection fails.
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|entry| entry
+            .map_err(Into::into)
+            .and_then(|(k_guard, v_guard)| {
+                let key        = K::deserialize(k_guard.value())?;
+                let projection = P::deserialize_projection(v_guard.value())?;
+                Ok((key, projection))
+            })
+        )
+    }
+}
@@ -0,0 +1,214 @@
+//! Per-table limits on the number of records and the bytes they occupy, for multi-tenant
+//! embedding.
+//!
+//! A table's [`Quota`] is stored in an internal table of its namespace, along with running totals
+//! of the table's records and bytes. Every tenant's namespace therefore has quotas of its own. The
+//! totals are measured when the quota is set, and kept up to date by `atlatl`'s index-aware record
+//! writes and deletions, which check them before anything is written.
+
+use crate::Error;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// Name of the internal table that stores each table's quota and usage.
+///
+/// The quota table is shared by every namespace. Each row is keyed by the full, namespaced name
+/// of the table it applies to, so tenants in different namespaces have separate quotas.
+pub const QUOTA_TABLE_NAME: &str = "__atlatl_quotas";
+
+/// Definition of the quota table: full table name → encoded [`QuotaUsage`].
+#[cfg(feature = "writes")]
+pub(crate) const QUOTA_TABLE: redb::TableDefinition<&str, &[u8]> =
+    redb::TableDefinition::new(QUOTA_TABLE_NAME);
+
+/// Size of an encoded quota row: the two limits, then the two running totals.
+const ENCODED_LEN: usize = 4 * size_of::<u64>();
+
+/// Stands in for a missing limit in an encoded quota row.
+const UNLIMITED: u64 = u64::MAX;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Limits on how many records a table may hold, and how many bytes of keys and stored values.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let mut txn = db.write()?.for_tenant(&tenant);
+/// txn.set_quota("creatures", Quota::new().max_entries(10_000).max_bytes(64 * 1024 * 1024))?;
+/// txn.commit()?;
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Quota {
+    /// The maximum number of records, or `None` for no limit.
+    pub max_entries: Option<u64>,
+
+    /// The maximum bytes of keys and stored values, after every layer has been applied, or `None`
+    /// for no limit.
+    pub max_bytes: Option<u64>,
+}
+
+/// A table's quota, and how much of it is used.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct QuotaUsage {
+    /// The table's limits.
+    pub quota: Quota,
+
+    /// The number of records in the table.
+    pub entries: u64,
+
+    /// The bytes of keys and stored values in the table.
+    pub bytes: u64,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Quota {
+    /// Instantiates a quota without any limits.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { max_entries: None, max_bytes: None }
+    }
+
+    /// Limits the number of records in the table.
+    #[must_use]
+    pub const fn max_entries(mut self, max_entries: u64) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    /// Limits the bytes of keys and stored values in the table.
+    #[must_use]
+    pub const fn max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+}
+
+impl QuotaUsage {
+    /// Checks that a table may change from this usage to `after`. Usage that shrinks, or stays the
+    /// same, is always allowed, even if it's still over a limit that was lowered.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::QuotaExceeded`] if the records or bytes would grow past their limit.
+    pub fn check(&self, table_name: &str, after: &Self) -> Result<(), Error> {
+        let limits = [
+            ("entries", self.quota.max_entries, self.entries, after.entries),
+            ("bytes", self.quota.max_bytes, self.bytes, after.bytes),
+        ];
+
+        for (resource, limit, current, requested) in limits {
+            let Some(limit) = limit else { continue };
+            if requested > current && requested > limit {
+                return Err(Error::QuotaExceeded {
+                    table: table_name.to_string(),
+                    resource,
+                    current,
+                    requested,
+                    limit,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Encodes the quota and usage for the quota table, as little-endian `u64`s.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(ENCODED_LEN);
+        bytes.extend_from_slice(&self.quota.max_entries.unwrap_or(UNLIMITED).to_le_bytes());
+        bytes.extend_from_slice(&self.quota.max_bytes.unwrap_or(UNLIMITED).to_le_bytes());
+        bytes.extend_from_slice(&self.entries.to_le_bytes());
+        bytes.extend_from_slice(&self.bytes.to_le_bytes());
+        bytes
+    }
+
+    /// Decodes a quota and usage from the quota table.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Corrupted`] if the row isn't the expected length.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != ENCODED_LEN {
+            return Err(Error::Corrupted {
+                message: format!("quota row is {} bytes, expected {ENCODED_LEN}", bytes.len()),
+            });
+        }
+
+        let mut words = bytes
+            .chunks_exact(size_of::<u64>())
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap_or_default()));
+        let mut limit = || words.next().filter(|word| *word != UNLIMITED);
+
+        let quota = Quota { max_entries: limit(), max_bytes: limit() };
+        Ok(Self {
+            quota,
+            entries: words.next().unwrap_or_default(),
+            bytes: words.next().unwrap_or_default(),
+        })
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_growth_past_a_limit_is_refused() {
+        let usage = QuotaUsage { quota: Quota::new().max_entries(2), entries: 2, bytes: 100 };
+
+        let growing = QuotaUsage { entries: 3, ..usage };
+        assert!(matches!(
+            usage.check("creatures", &growing),
+            Err(Error::QuotaExceeded { resource: "entries", current: 2, requested: 3, .. })
+        ));
+
+        let replacing = QuotaUsage { bytes: 1_000, ..usage };
+        assert!(usage.check("creatures", &replacing).is_ok());
+
+        let over = QuotaUsage { entries: 5, ..usage };
+        assert!(over.check("creatures", &QuotaUsage { entries: 4, ..usage }).is_ok());
+    }
+
+    #[cfg(feature = "writes")]
+    #[test]
+    fn namespaces_have_separate_quotas() {
+        use crate::typed::Namespace;
+
+        let db = crate::typed::database::Database::in_memory().unwrap();
+        let mut txn = db.write().unwrap().in_namespace(Namespace::new("tenant42"));
+        txn.set_quota("creatures", Quota::new().max_entries(1)).unwrap();
+        let table_name = txn.namespace().table_name("creatures").into_owned();
+        txn.charge_quota(&table_name, &[(b"1", Some(b"Coyote"))]).unwrap();
+        assert!(matches!(
+            txn.charge_quota(&table_name, &[(b"2", Some(b"Road Runner"))]),
+            Err(Error::QuotaExceeded { table, .. }) if table == "tenant42.creatures"
+        ));
+
+        let txn = txn.in_namespace(Namespace::new("tenant7"));
+        assert!(txn.quota_usage("creatures").unwrap().is_none());
+        let table_name = txn.namespace().table_name("creatures").into_owned();
+        txn.charge_quota(&table_name, &[(b"2", Some(b"Road Runner"))]).unwrap();
+        let txn = txn.in_namespace(Namespace::new("tenant42"));
+        assert_eq!(txn.quota_usage("creatures").unwrap().unwrap().entries, 1);
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let usage = QuotaUsage { quota: Quota::new().max_bytes(4_096), entries: 7, bytes: 900 };
+        assert_eq!(QuotaUsage::from_bytes(&usage.to_bytes()).unwrap(), usage);
+        assert!(QuotaUsage::from_bytes(&[0; 3]).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+//! A database handle that can only begin read transactions.
+
+use crate::Error;
+use crate::typed::database::Database;
+use crate::typed::snapshot::Snapshot;
+use crate::typed::stats_report::StatsReport;
+use crate::typed::transaction::ReadTransaction;
+use crate::typed::usage::{Usage, UsageOptions};
+use crate::typed::{Namespace, Tenant};
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A database opened with [`Database::open_read_only`].
+///
+/// It offers the read-only half of [`Database`]'s methods. There is no way to begin a write
+/// transaction from it, or to change its settings, so code that's handed one can't modify the
+/// database.
+pub struct ReadOnlyDatabase(Database);
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl ReadOnlyDatabase {
+    /// Wraps an opened database, hiding its writable methods.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub(crate) const fn new(database: Database) -> Self {
+        Self(database)
+    }
+
+    /// Begins a read-only transaction.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn read(&self) -> Result<ReadTransaction, Error> {
+        self.0.read()
+    }
+
+    /// Begins a read-only transaction whose tables are opened in the given namespace.
+    ///
+    /// # Errors
+    ///
+    /// * See [`ReadOnlyDatabase::read`].
+    pub fn read_in(&self, namespace: &Namespace) -> Result<ReadTransaction, Error> {
+        self.0.read_in(namespace)
+    }
+
+    /// Begins a read-only transaction for the given tenant. Tables are opened in the tenant's
+    /// namespace, and the transaction carries the tenant's encryption key.
+    ///
+    /// # Errors
+    ///
+    /// * See [`ReadOnlyDatabase::read`].
+    pub fn read_as(&self, tenant: &Tenant) -> Result<ReadTransaction, Error> {
+        self.0.read_as(tenant)
+    }
+
+    /// Takes a snapshot. See [`Database::snapshot`].
+    ///
+    /// # Errors
+    ///
+    /// * See [`ReadOnlyDatabase::read`].
+    pub fn snapshot(&self) -> Result<Snapshot, Error> {
+        self.0.snapshot()
+    }
+
+    /// Gathers the database's statistics. See [`Database::stats_report`].
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Corrupted`] if an index's stored statistics can't be decoded.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn stats_report(&self) -> Result<StatsReport, Error> {
+        self.0.stats_report()
+    }
+
+    /// Returns the disk usage of every table. See [`Database::usage`].
+    ///
+    /// # Errors
+    ///
+    /// * See [`ReadOnlyDatabase::read`].
+    pub fn usage(&self) -> Result<Usage, Error> {
+        self.0.usage()
+    }
+
+    /// Returns the disk usage of every table, with the details of registered record types. See
+    /// [`Database::usage_with`].
+    ///
+    /// # Errors
+    ///
+    /// * See [`ReadOnlyDatabase::read`].
+    pub fn usage_with(&self, options: &UsageOptions) -> Result<Usage, Error> {
+        self.0.usage_with(options)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_open_database_locks_out_other_handles() {
+        let directory = std::env::temp_dir().join(format!("atlatl-locked-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("zoo.redb");
+
+        // A read-only handle never creates the file:
+        assert!(Database::open_read_only(&path).is_err());
+        assert!(!path.exists());
+
+        let exclusive = Database::open_exclusive(&path).unwrap();
+        assert!(matches!(
+            Database::open_read_only(&path),
+            Err(Error::DatabaseLocked { path: locked }) if locked == path
+        ));
+        assert!(matches!(Database::open_exclusive(&path), Err(Error::DatabaseLocked { .. })));
+        drop(exclusive);
+
+        let read_only = Database::open_read_only(&path).unwrap();
+        read_only.read().unwrap();
+        assert!(matches!(Database::open(&path), Err(Error::DatabaseLocked { .. })));
+
+        drop(read_only);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,461 @@
+//! Record layers: the compression, encryption, and error correction that a record type's values
+//! are stored with.
+//!
+//! A record type opts in by returning [`RecordLayers::of`] from [`HasTable::layers`]. Every
+//! transaction method that reads or writes the type's primary table then passes its records
+//! through the type's [`LayerStack`]: the value is serialized and tagged with its serialization
+//! method, then compressed, encrypted, and protected in the declared order. Encryption is bound to
+//! the full table name and the primary key, see [`AssociatedData`], so a stored value can't be
+//! moved to another row or table.
+//!
+//! Records are encrypted with the tenant's key in a tenant's transactions, and with the key set
+//! by [`Database::set_record_key`] otherwise. Record types that encrypt with
+//! [`NonceStrategy::Counter`] draw their nonces from the database's nonce counter.
+//!
+//! Record types without layers are stored as their [`Codec`] serializes them. So is every value
+//! written through the raw [`TableMut`] and [`TableRef`] wrappers, which don't know the record
+//! type of the table they're opened on.
+//!
+//! [`HasTable::layers`]: crate::indexing::HasTable::layers
+//! [`Database::set_record_key`]: crate::typed::database::Database::set_record_key
+//! [`TableMut`]: crate::typed::TableMut
+//! [`TableRef`]: crate::typed::TableRef
+
+use crate::indexing::HasTable;
+use crate::layers::core::{Bytes, Direction, Layer, LayerFailure, ValueOrBytes, apply_in_order};
+use crate::layers::core::{read_order, write_order};
+use crate::layers::encryptors::{AssociatedData, KEY_SIZE, KeyBytes, Nonce, NonceStrategy};
+use crate::layers::serializers::Method;
+use crate::layers::{Compressible, Correctable, Encryptable, LayerStack, Serializable, Serializer};
+use crate::{Codec, Error};
+use std::sync::Arc;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// Key handed to the encryption layer of record types that don't encrypt. It's never used.
+const UNUSED_KEY: [u8; KEY_SIZE] = [0; KEY_SIZE];
+
+// -------------------------------------------------------------------------------------------------
+
+/// Passes a record through its write layers.
+type Seal<V> = fn(
+    &V,
+    KeyBytes<'_>,
+    &AssociatedData<'_>,
+    Option<Nonce<'_>>,
+) -> Result<Vec<u8>, Error>;
+
+/// Passes a stored value back through its read layers, and returns the serialization method it
+/// was tagged with.
+type Open<V> = fn(
+    &[u8],
+    KeyBytes<'_>,
+    &AssociatedData<'_>,
+) -> Result<(V, Option<Method>), Error>;
+
+/// Returns the serialization method a record would be tagged with if it were written now.
+#[cfg(feature = "writes")]
+type WriteMethod<V> = fn(&V) -> Result<Option<Method>, Error>;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The layers that a record type's values are stored with, returned by
+/// [`HasTable::layers`](crate::indexing::HasTable::layers).
+///
+/// # Example
+///
+/// ```rust,ignore
+/// impl HasTable for Creature {
+///     fn table_name() -> &'static str { "creatures" }
+///     fn layers() -> Option<RecordLayers<Self>> { Some(RecordLayers::of::<Self>()) }
+/// }
+///
+/// db.set_record_key(&key);
+/// let txn = db.write()?;
+/// txn.insert(&creature)?; // Compressed, encrypted, and protected as `Creature` declares.
+/// txn.commit()?;
+/// ```
+pub struct RecordLayers<V> {
+    /// Passes a record through `V`'s write layers.
+    seal: Seal<V>,
+
+    /// Passes a stored value back through `V`'s read layers.
+    open: Open<V>,
+
+    /// Returns the serialization method a `V` record would be written with now.
+    #[cfg(feature = "writes")]
+    write_method: WriteMethod<V>,
+
+    /// Whether `V` is encrypted in either direction, so that a key is needed.
+    encrypted: bool,
+
+    /// How nonces are chosen when `V` is encrypted.
+    nonce_strategy: NonceStrategy,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Where a record is stored, and the key its layers are encrypted with. Both are bound to the
+/// stored value: it can only be read back in the same context it was written in.
+///
+/// Transactions hand one to the [`Dependent`](crate::indexing::Dependent) callbacks that decode
+/// records of another type.
+#[derive(Clone)]
+pub struct RecordContext {
+    /// The key records are encrypted with, if the transaction has one.
+    key: Option<Arc<KeyBytes<'static>>>,
+
+    /// The full name of the record's primary table, including its namespace prefix.
+    table_name: String,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<V> RecordLayers<V> {
+    /// Returns the layers that `V` declares with its [`Serializable`], [`Compressible`],
+    /// [`Encryptable`], [`Correctable`], and [`LayerStack`] implementations.
+    #[must_use]
+    pub fn of() -> Self
+    where
+        V: for<'b> Serializer<'b, V>
+            + Serializable
+            + Compressible
+            + Encryptable
+            + Correctable
+            + LayerStack
+            + 'static,
+    {
+        Self {
+            seal: seal::<V>,
+            open: open::<V>,
+            #[cfg(feature = "writes")]
+            write_method: write_method::<V>,
+            encrypted: <V as Encryptable>::DIRECTION != Direction::None,
+            nonce_strategy: <V as Encryptable>::NONCE_STRATEGY,
+        }
+    }
+
+    /// Returns how nonces are chosen when `V` is encrypted.
+    pub(crate) const fn nonce_strategy(&self) -> NonceStrategy {
+        self.nonce_strategy
+    }
+}
+
+impl RecordContext {
+    /// Returns the context of records stored in the named table, which must be the full table
+    /// name, encrypted with `key`.
+    pub(crate) fn new(key: Option<Arc<KeyBytes<'static>>>, table_name: impl Into<String>) -> Self {
+        Self { key, table_name: table_name.into() }
+    }
+
+    /// Returns the same context, for another table.
+    #[must_use]
+    pub(crate) fn for_table(&self, table_name: impl Into<String>) -> Self {
+        Self { key: self.key.clone(), table_name: table_name.into() }
+    }
+
+    /// Returns the full name of the table this context is for.
+    #[must_use]
+    pub fn table_name(&self) -> &str {
+        &self.table_name
+    }
+
+    /// Encodes a record to be stored under the given primary key. Record types with
+    /// [`RecordLayers`] are passed through them, using `nonce` if it's given, and other record
+    /// types are serialized with their [`Codec`].
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::RecordKeyMissing`] if the record type is encrypted, but there's no key.
+    ///
+    /// * Returns [`Error::Layer`] if a write layer fails, or a [`Codec`] error.
+    pub fn seal<V: HasTable + Codec<V>>(
+        &self,
+        primary_key: &[u8],
+        record: &V,
+        nonce: Option<Nonce<'_>>,
+    ) -> Result<Vec<u8>, Error> {
+        let Some(layers) = V::layers() else {
+            return V::serialize(record);
+        };
+        let key = self.key_for(&layers)?;
+        (layers.seal)(record, key, &AssociatedData::new(&self.table_name, primary_key), nonce)
+    }
+
+    /// Decodes a record stored under the given primary key. Record types with [`RecordLayers`]
+    /// are passed back through them, and other record types are deserialized with their
+    /// [`Codec`].
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::RecordKeyMissing`] if the record type is encrypted, but there's no key.
+    ///
+    /// * Returns [`Error::Layer`] if a read layer fails. For example, if the value was encrypted
+    ///   with another key, or was moved from another row.
+    ///
+    /// * A [`Codec`] error.
+    pub fn open<V: HasTable + Codec<V>>(
+        &self,
+        primary_key: &[u8],
+        value_bytes: &[u8],
+    ) -> Result<V, Error> {
+        let Some(layers) = V::layers() else {
+            return V::deserialize(value_bytes);
+        };
+        let key = self.key_for(&layers)?;
+        (layers.open)(value_bytes, key, &AssociatedData::new(&self.table_name, primary_key))
+            .map(|(record, _)| record)
+    }
+
+    /// Decodes a record stored under the given primary key, like [`RecordContext::open`], and
+    /// reports whether it's stored in the format it would be written in now.
+    ///
+    /// Record types with [`RecordLayers`] are tagged with their serialization method, which is
+    /// compared with the method selected by
+    /// [`set_write_method`](crate::layers::serializers::set_write_method). Other record types are
+    /// stored untagged, in their [`Codec`]'s only format, and are compared byte for byte with the
+    /// record serialized again.
+    ///
+    /// # Errors
+    ///
+    /// * See [`RecordContext::open`].
+    #[cfg(feature = "writes")]
+    pub(crate) fn open_in_current_format<V: HasTable + Codec<V>>(
+        &self,
+        primary_key: &[u8],
+        value_bytes: &[u8],
+    ) -> Result<(V, bool), Error> {
+        let Some(layers) = V::layers() else {
+            let record = V::deserialize(value_bytes)?;
+            let current = V::serialize(&record)? == value_bytes;
+            return Ok((record, current));
+        };
+        let key = self.key_for(&layers)?;
+        let associated_data = AssociatedData::new(&self.table_name, primary_key);
+        let (record, stored_method) = (layers.open)(value_bytes, key, &associated_data)?;
+        let current = stored_method == (layers.write_method)(&record)?;
+        Ok((record, current))
+    }
+
+    /// Returns the key to hand to `layers`' encryption layer.
+    fn key_for<V>(&self, layers: &RecordLayers<V>) -> Result<KeyBytes<'_>, Error> {
+        match &self.key {
+            Some(key) => Ok(key.reborrow()),
+            None if !layers.encrypted => Ok(KeyBytes::from_array(&UNUSED_KEY)),
+            None => Err(Error::RecordKeyMissing { table: self.table_name.clone() }),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl<V> Clone for RecordLayers<V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<V> Copy for RecordLayers<V> {}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Copies a key into an owned key that transactions can share. Its ID is kept.
+pub(crate) fn owned_key(key: &KeyBytes<'_>) -> Arc<KeyBytes<'static>> {
+    Arc::new(KeyBytes::from(**key).with_id(key.id()))
+}
+
+/// Passes a record through `V`'s write layers: serialization, then compression, encryption, and
+/// error correction in `V`'s [`LayerStack`] order.
+fn seal<V>(
+    record: &V,
+    key: KeyBytes<'_>,
+    associated_data: &AssociatedData<'_>,
+    nonce: Option<Nonce<'_>>,
+) -> Result<Vec<u8>, Error>
+where
+    V: for<'b> Serializer<'b, V>
+        + Serializable
+        + Compressible
+        + Encryptable
+        + Correctable
+        + LayerStack,
+{
+    let bytes = Bytes::serialize::<V>(ValueOrBytes::from_value_ref(record))
+        .map_err(LayerFailure::at(Layer::Serialization))?;
+
+    let bytes = apply_in_order(
+        bytes,
+        write_order::<V>(),
+        compress::<V>,
+        |bytes| bytes
+            .encrypt::<V>(key, associated_data, nonce)
+            .map_err(LayerFailure::at(Layer::Encryption)),
+        |bytes| bytes.protect::<V>().map_err(LayerFailure::at(Layer::Correction)),
+    )?;
+
+    Ok(bytes.into_bytes().into_owned())
+}
+
+/// Passes a stored value back through `V`'s read layers, in the reverse of `V`'s [`LayerStack`]
+/// order, and deserializes it. Returns the serialization method it was tagged with, if `V` is
+/// serialized by its layers.
+fn open<V>(
+    value_bytes: &[u8],
+    key: KeyBytes<'_>,
+    associated_data: &AssociatedData<'_>,
+) -> Result<(V, Option<Method>), Error>
+where
+    V: for<'b> Serializer<'b, V>
+        + Serializable
+        + Compressible
+        + Encryptable
+        + Correctable
+        + LayerStack,
+{
+    let bytes = apply_in_order(
+        Bytes::from_slice(value_bytes),
+        read_order::<V>(),
+        decompress::<V>,
+        |bytes| bytes
+            .decrypt::<V>(key, associated_data)
+            .map_err(LayerFailure::at(Layer::Encryption)),
+        |bytes| bytes.recover::<V>().map_err(LayerFailure::at(Layer::Correction)),
+    )?;
+
+    let method = tagged_method::<V>(bytes.as_ref());
+    let record = bytes
+        .deserialize::<V>()
+        .and_then(|value_or_bytes| Ok(value_or_bytes.try_into_value()?))
+        .map_err(LayerFailure::at(Layer::Serialization))?
+        .into_owned()
+        .ok_or_else(|| Error::Corrupted {
+            message: "deserializer returned a borrowed value".to_string(),
+        })?;
+    Ok((record, method))
+}
+
+/// Returns the serialization method a `V` record would be tagged with if it were written now,
+/// which is the write method if `V`'s serializer supports it.
+#[cfg(feature = "writes")]
+fn write_method<V>(record: &V) -> Result<Option<Method>, Error>
+where
+    V: for<'b> Serializer<'b, V> + Serializable,
+{
+    let bytes = Bytes::serialize::<V>(ValueOrBytes::from_value_ref(record))
+        .map_err(LayerFailure::at(Layer::Serialization))?;
+    Ok(tagged_method::<V>(bytes.as_ref()))
+}
+
+/// Returns the serialization method named by the tag at the end of `V`'s serialized bytes, or
+/// `None` if `V`'s layers don't serialize it.
+fn tagged_method<V: Serializable>(serialized_bytes: &[u8]) -> Option<Method> {
+    if !<V as Serializable>::DIRECTION.is_read() {
+        return None;
+    }
+    serialized_bytes.last().and_then(|tag| <&Method>::try_from(tag).ok()).copied()
+}
+
+/// Compresses a value with `V`'s compressor, without a dictionary.
+#[cfg(feature = "compress-dictionaries")]
+fn compress<V: Compressible>(bytes: Bytes<'_>) -> Result<Bytes<'_>, LayerFailure> {
+    bytes.compress::<V>(None).map_err(LayerFailure::at(Layer::Compression))
+}
+
+/// Compresses a value with `V`'s compressor.
+#[cfg(not(feature = "compress-dictionaries"))]
+fn compress<V: Compressible>(bytes: Bytes<'_>) -> Result<Bytes<'_>, LayerFailure> {
+    bytes.compress::<V>().map_err(LayerFailure::at(Layer::Compression))
+}
+
+/// Decompresses a value with `V`'s compressor, without a dictionary.
+#[cfg(feature = "compress-dictionaries")]
+fn decompress<V: Compressible>(bytes: Bytes<'_>) -> Result<Bytes<'_>, LayerFailure> {
+    bytes.decompress::<V>(None).map_err(LayerFailure::at(Layer::Compression))
+}
+
+/// Decompresses a value with `V`'s compressor.
+#[cfg(not(feature = "compress-dictionaries"))]
+fn decompress<V: Compressible>(bytes: Bytes<'_>) -> Result<Bytes<'_>, LayerFailure> {
+    bytes.decompress::<V>().map_err(LayerFailure::at(Layer::Compression))
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "writes"))]
+mod tests {
+    use super::*;
+    use crate::indexing::ReadableKeySet;
+    use crate::typed::database::Database;
+    use crate::typed::test_records::{Letter, Sender};
+    use crate::typed::transaction::QuerySource;
+
+    const TEXT: &str = "Meet me by the old oak at midnight.";
+
+    fn primary_key(id: u64) -> Vec<u8> {
+        <u64 as Codec<u64>>::serialize(&id).unwrap()
+    }
+
+    fn database(key: &KeyBytes<'_>) -> Database {
+        let mut db = Database::in_memory().unwrap();
+        db.set_record_key(key);
+        let mut txn = db.write().unwrap();
+        txn.bulk_insert::<u64, Letter>([
+            Letter::new(1, "Wile", TEXT),
+            Letter::new(2, "Wile", "Acme order, please."),
+        ]).unwrap();
+        txn.commit().unwrap();
+        db
+    }
+
+    #[test]
+    fn layered_records_are_stored_sealed_and_read_back() {
+        let key = KeyBytes::from_array(&[3; KEY_SIZE]);
+        let db = database(&key);
+        let txn = db.read().unwrap();
+
+        assert_eq!(txn.get::<u64, Letter>(&1).unwrap(), Some(Letter::new(1, "Wile", TEXT)));
+        let sent = txn.query::<u64, Letter>(Sender("Wile".into())).unwrap();
+        assert_eq!(sent.len(), 2);
+
+        let table = txn.open_raw_index_table("letters").unwrap().unwrap();
+        let stored = table.get(&*primary_key(1)).unwrap().unwrap();
+        let stored = stored.value();
+        assert!(!stored.windows(TEXT.len()).any(|window| window == TEXT.as_bytes()));
+        assert!(<Letter as Codec<Letter>>::deserialize(stored).is_err());
+    }
+
+    #[test]
+    fn layered_records_are_bound_to_their_key_and_row() {
+        let key = KeyBytes::from_array(&[3; KEY_SIZE]);
+        let db = database(&key);
+
+        let other_key = KeyBytes::from_array(&[4; KEY_SIZE]);
+        let txn = db.read().unwrap().with_record_key(&other_key);
+        assert!(matches!(txn.get::<u64, Letter>(&1), Err(Error::Layer(_))));
+
+        // A value moved to another row doesn't decrypt:
+        let txn = db.read().unwrap();
+        let table = txn.open_raw_index_table("letters").unwrap().unwrap();
+        let stored = table.get(&*primary_key(1)).unwrap().unwrap();
+        let moved = txn.open_record::<Letter>(&primary_key(2), stored.value());
+        assert!(matches!(moved, Err(Error::Layer(_))));
+    }
+
+    #[test]
+    fn encrypted_records_need_a_key() {
+        let db = Database::in_memory().unwrap();
+        let mut txn = db.write().unwrap();
+        let inserted = txn.insert(&Letter::new(1, "Wile", TEXT));
+        assert!(matches!(inserted, Err(Error::RecordKeyMissing { table }) if table == "letters"));
+    }
+}
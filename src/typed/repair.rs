@@ -0,0 +1,75 @@
+//! Integrity checks and repair of the underlying database file.
+
+use crate::Error;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The outcome of a successful [`Database::check_integrity`] call.
+///
+/// A database that failed its integrity check and could not be repaired is reported with
+/// [`Error::Corrupted`] instead.
+///
+/// [`Database::check_integrity`]: crate::typed::database::Database::check_integrity
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Integrity {
+    /// The database file passed all integrity checks.
+    Intact,
+
+    /// The database file failed its integrity checks, and was repaired.
+    Repaired,
+}
+
+impl Integrity {
+    /// Returns `true` if the database file had to be repaired.
+    #[must_use]
+    pub const fn was_repaired(self) -> bool {
+        matches!(self, Self::Repaired)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A repair in progress, passed to the callback given to [`Database::open_with_repair`].
+///
+/// The callback is invoked periodically while the database file is being repaired, and can report
+/// progress to the user or give up on the repair.
+///
+/// [`Database::open_with_repair`]: crate::typed::database::Database::open_with_repair
+pub struct RepairSession<'r>(&'r mut redb::RepairSession);
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<'r> RepairSession<'r> {
+    /// Wraps `redb`'s repair session.
+    pub(crate) const fn new(session: &'r mut redb::RepairSession) -> Self {
+        Self(session)
+    }
+
+    /// Returns an estimate of the repair's progress, from `0.0` up to (but not including) `1.0`.
+    #[must_use]
+    pub fn progress(&self) -> f64 {
+        self.0.progress()
+    }
+
+    /// Aborts the repair. Opening the database will fail with [`Error::RepairAborted`].
+    pub fn abort(&mut self) {
+        self.0.abort();
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Converts a `redb` database error raised while opening or checking a database into `atlatl`'s
+/// error model, surfacing aborted repairs and unrepairable corruption as their own variants.
+pub(crate) fn repair_error(error: redb::DatabaseError) -> Error {
+    match error {
+        redb::DatabaseError::RepairAborted => Error::RepairAborted,
+        redb::DatabaseError::Storage(redb::StorageError::Corrupted(message)) =>
+            Error::Corrupted { message },
+        error => Error::RedbDatabase(error),
+    }
+}
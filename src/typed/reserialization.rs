@@ -0,0 +1,251 @@
+//! Serializer migration, which rewrites every record of a table in the current write format while
+//! the database stays open.
+//!
+//! Values are tagged with the serializer that wrote them, so a database keeps working while a new
+//! serializer is being adopted: new records are written in the new format, and old records are
+//! still read in theirs. Reserializing a table completes the migration, after which the old
+//! serializer's feature can be removed.
+//!
+//! Only records passed through [`RecordLayers`] are tagged, and written with the method selected
+//! by [`set_write_method`]. Record types without layers are stored as their [`Codec`] serializes
+//! them, in a single format, so reserializing their tables only moves records whose primary key
+//! encodes differently.
+//!
+//! Records are rewritten in batches, each in its own write transaction, so that writers are only
+//! held up for one batch at a time. Every batch records how far it got in an internal table, in the
+//! same transaction, so an interrupted migration picks up where it left off when
+//! [`Database::reserialize_table`] is called again.
+//!
+//! [`Database::reserialize_table`]: crate::typed::database::Database::reserialize_table
+//! [`RecordLayers`]: crate::typed::record_layers::RecordLayers
+//! [`set_write_method`]: crate::layers::serializers::set_write_method
+
+use crate::defaults::Defaults;
+use crate::indexing::{HasTable, Indexable};
+use crate::typed::Namespace;
+use crate::typed::database::Database;
+use crate::typed::rotation::Checkpoint;
+use crate::{Codec, Error};
+use redb::TableDefinition;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Name of the internal table that records the progress of unfinished migrations, keyed by full
+/// table name. A table's row is removed once its migration finishes.
+pub const RESERIALIZATION_TABLE_NAME: &str = "__atlatl_reserialization";
+
+/// Definition of the migration progress table: full table name → encoded checkpoint.
+pub(crate) const RESERIALIZATION_TABLE: TableDefinition<&str, &[u8]> =
+    TableDefinition::new(RESERIALIZATION_TABLE_NAME);
+
+/// Number of records rewritten in each write transaction, unless changed with
+/// [`Reserialization::batch_size`].
+const DEFAULT_BATCH_SIZE: usize = 1_000;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Options for rewriting a table's records in the current write format.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// atlatl::layers::serializers::set_write_method(Method::BitcodeSerde)?;
+///
+/// let reserialization = Reserialization::new().batch_size(500);
+/// let status = db.reserialize_table::<CreatureId, Creature>(&reserialization, |progress| {
+///     println!("{} records rewritten", progress.entries_rewritten);
+/// })?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct Reserialization {
+    /// The namespace whose table is rewritten.
+    namespace: Namespace,
+
+    /// Number of records rewritten in each write transaction.
+    batch_size: usize,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The progress of a serializer migration, passed to the callback given to
+/// [`Database::reserialize_table`].
+///
+/// [`Database::reserialize_table`]: crate::typed::database::Database::reserialize_table
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ReserializationProgress {
+    /// Name of the table being rewritten, without its namespace prefix. For example: `"creatures"`.
+    pub table: String,
+
+    /// Number of records rewritten, because their value or primary key was encoded differently.
+    pub entries_rewritten: u64,
+
+    /// Number of records that were already in the current format, and were left as they are.
+    pub entries_unchanged: u64,
+
+    /// Number of rewritten records whose primary key encoding changed, and which were moved to
+    /// their new primary key.
+    pub keys_moved: u64,
+
+    /// Number of secondary index entries repaired once every record was rewritten, because their
+    /// secondary or primary key encoding changed.
+    pub index_entries_repaired: u64,
+
+    /// Number of write transactions committed.
+    pub batches_committed: u64,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Reserialization {
+    /// Returns options that rewrite a table in the default namespace, in batches of `1000`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self { namespace: Namespace::default(), batch_size: DEFAULT_BATCH_SIZE }
+    }
+
+    /// Rewrites the table in the given namespace, rather than the default namespace.
+    #[must_use]
+    pub fn in_namespace(mut self, namespace: Namespace) -> Self {
+        self.namespace = namespace;
+        self
+    }
+
+    /// Sets the number of records rewritten in each write transaction (defaults to `1000`).
+    /// Smaller batches hold up other writers for less time, but commit more often.
+    #[must_use]
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl Default for Reserialization {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Rewrites every record of `V`'s table in the current write format, in batches of write
+/// transactions, resuming from the progress left by an interrupted migration of the same table.
+///
+/// # Errors
+///
+/// * Decoding a record or primary key fails, or encoding one in the current format fails.
+///
+/// * Returns [`Error::IndexCollision`] if moving a record to its re-encoded primary key collides
+///   with a `Unique` index entry.
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+pub(crate) fn reserialize_table<K, V>(
+    db: &Database,
+    reserialization: &Reserialization,
+    mut progress: impl FnMut(&ReserializationProgress),
+) -> Result<ReserializationProgress, Error>
+where
+    K: Codec<K>,
+    V: for<'i> Indexable<'i> + Defaults + Codec<V> + HasTable,
+{
+    let mut status = ReserializationProgress {
+        table: V::table_name().to_string(),
+        ..ReserializationProgress::default()
+    };
+
+    loop {
+        let mut txn = db.write_in(&reserialization.namespace)?;
+        let checkpoint =
+            txn.reserialize_batch::<K, V>(reserialization.batch_size, &mut status)?;
+        txn.commit()?;
+        status.batches_committed += 1;
+        progress(&status);
+
+        if checkpoint == Checkpoint::Done {
+            return Ok(status);
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batch_size_is_at_least_one() {
+        assert_eq!(Reserialization::new().batch_size, DEFAULT_BATCH_SIZE);
+        assert_eq!(Reserialization::new().batch_size(0).batch_size, 1);
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "writes",
+        feature = "serialize-messagepack",
+        feature = "serialize-postcard-serde"
+    ))]
+    fn layered_records_are_rewritten_in_the_write_format() {
+        use crate::layers::encryptors::{KEY_SIZE, KeyBytes};
+        use crate::layers::serializers::Method;
+        use crate::layers::serializers::dispatch::{DEFAULT_METHOD, WRITE_METHOD_LOCK};
+        use crate::layers::serializers::set_write_method;
+        use crate::typed::test_records::{Letter, Sender};
+        use redb::ReadableTable;
+
+        let _lock = WRITE_METHOD_LOCK.lock().unwrap();
+        let mut db = Database::in_memory().unwrap();
+        db.set_record_key(&KeyBytes::from_array(&[5; KEY_SIZE]));
+        let letters = [
+            Letter::new(1, "Wile", "Acme order, please."),
+            Letter::new(2, "Wile", "The rocket skates arrived broken."),
+            Letter::new(3, "Road Runner", "Meep meep."),
+        ];
+        let mut txn = db.write().unwrap();
+        txn.bulk_insert::<u64, Letter>(letters.clone()).unwrap();
+        txn.commit().unwrap();
+        let stored = |db: &Database| {
+            let txn = db.read().unwrap();
+            let table = txn.open_raw_index_table("letters").unwrap().unwrap();
+            table
+                .iter()
+                .unwrap()
+                .map(|entry| entry.unwrap().1.value().to_vec())
+                .collect::<Vec<_>>()
+        };
+        let written_in_message_pack = stored(&db);
+
+        let reserialization = Reserialization::new().batch_size(2);
+        let unchanged = db.reserialize_table::<u64, Letter>(&reserialization, |_| {}).unwrap();
+        assert_eq!((unchanged.entries_rewritten, unchanged.entries_unchanged), (0, 3));
+        assert_eq!(stored(&db), written_in_message_pack);
+
+        set_write_method(Method::PostcardSerde).unwrap();
+        let rewritten = db.reserialize_table::<u64, Letter>(&reserialization, |_| {});
+        let again = db.reserialize_table::<u64, Letter>(&reserialization, |_| {});
+        set_write_method(DEFAULT_METHOD).unwrap();
+
+        let rewritten = rewritten.unwrap();
+        assert_eq!((rewritten.entries_rewritten, rewritten.entries_unchanged), (3, 0));
+        assert_eq!(rewritten.batches_committed, 2);
+        let again = again.unwrap();
+        assert_eq!((again.entries_rewritten, again.entries_unchanged), (0, 3));
+
+        let written_in_postcard = stored(&db);
+        assert!(written_in_postcard.iter().zip(&written_in_message_pack).all(|(a, b)| a != b));
+        let txn = db.read().unwrap();
+        for letter in &letters {
+            assert_eq!(txn.get::<u64, Letter>(&letter.id).unwrap().as_ref(), Some(letter));
+        }
+        assert_eq!(txn.query::<u64, Letter>(Sender("Wile".into())).unwrap().len(), 2);
+    }
+}
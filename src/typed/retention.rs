@@ -0,0 +1,312 @@
+//! Retention policies for time-keyed tables, such as logs, metrics, and events.
+//!
+//! A [`Retention`] lists the tables to prune, and how: records older than a maximum age, records
+//! beyond a maximum count, or both. The tables' keys must be ordered when serialized, with the
+//! oldest record first, so that expired records are always a range at the start of the table.
+//!
+//! [`Transaction::apply_retention`] prunes the tables once. [`Retention::sweep`] prunes them
+//! repeatedly, in its own write transaction each time, for running in the background.
+//!
+//! [`Transaction::apply_retention`]: crate::typed::transaction::write::Transaction::apply_retention
+
+use crate::indexing::{HasTable, Indexable};
+use crate::layers::serializers::OrderedWhenSerialized;
+use crate::typed::database::Database;
+use crate::typed::history::to_millis;
+use crate::typed::transaction::WriteTransaction as Transaction;
+use crate::{Codec, Error};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Converts a cutoff time into the key of the oldest record to keep.
+type CutoffKey = Arc<dyn Fn(SystemTime) -> Result<Vec<u8>, Error> + Send + Sync>;
+
+/// Removes records by their primary key bytes, keeping the record type's indexes in step. Returns
+/// the number of records removed.
+type Prune = fn(&mut Transaction, &[Vec<u8>]) -> Result<u64, Error>;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// How long, or how many, records of some tables are kept.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let retention = Retention::new()
+///     .keep_for::<u64, LogLine>(Duration::from_secs(30 * 86_400), unix_millis)
+///     .keep_last::<u64, Metric>(1_000_000);
+///
+/// let mut txn = db.write()?;
+/// let report = txn.apply_retention(&retention)?;
+/// txn.commit()?;
+/// ```
+#[derive(Clone, Default)]
+pub struct Retention {
+    /// The limits, applied in the order they were added.
+    pub(crate) rules: Vec<Rule>,
+}
+
+/// One table's limit.
+#[derive(Clone)]
+pub(crate) struct Rule {
+    /// The unqualified name of the table.
+    pub(crate) table_name: &'static str,
+
+    /// Which of the table's records have expired.
+    pub(crate) limit: Limit,
+
+    /// Removes expired records from the table.
+    pub(crate) prune: Prune,
+}
+
+/// Which of a table's records have expired.
+#[derive(Clone)]
+pub(crate) enum Limit {
+    /// Records whose key is before the key for `now - max_age`.
+    Age { max_age: Duration, cutoff_key: CutoffKey },
+
+    /// Every record but the newest `max_entries`.
+    Entries { max_entries: u64 },
+}
+
+/// The number of records a retention pass removed, by table.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RetentionReport {
+    /// Records removed from each table, keyed by unqualified table name.
+    pub removed: BTreeMap<&'static str, u64>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Retention {
+    /// Instantiates a retention policy that doesn't prune any tables.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /// Removes records of type `V` that are older than `max_age`.
+    ///
+    /// `cutoff_key` converts a time into the primary key of the first record at or after it. For
+    /// tables keyed by milliseconds since the Unix epoch, pass [`unix_millis`].
+    #[must_use]
+    pub fn keep_for<K, V>(
+        mut self,
+        max_age: Duration,
+        cutoff_key: impl Fn(SystemTime) -> K + Send + Sync + 'static,
+    ) -> Self
+    where
+        K: for<'o> OrderedWhenSerialized<'o> + Codec<K>,
+        V: for<'i> Indexable<'i> + Codec<V> + HasTable,
+    {
+        let cutoff_key: CutoffKey = Arc::new(move |cutoff| <K as Codec<K>>::serialize(&cutoff_key(cutoff)));
+        self.rules.push(Rule {
+            table_name: V::table_name(),
+            limit: Limit::Age { max_age, cutoff_key },
+            prune: prune::<V>,
+        });
+        self
+    }
+
+    /// Removes the oldest records of type `V`, so that no more than `max_entries` remain.
+    #[must_use]
+    pub fn keep_last<K, V>(mut self, max_entries: u64) -> Self
+    where
+        K: for<'o> OrderedWhenSerialized<'o> + Codec<K>,
+        V: for<'i> Indexable<'i> + Codec<V> + HasTable,
+    {
+        self.rules.push(Rule {
+            table_name: V::table_name(),
+            limit: Limit::Entries { max_entries },
+            prune: prune::<V>,
+        });
+        self
+    }
+
+    /// Applies the policy to `database` every `interval`, each time in a write transaction of its
+    /// own. Runs until a pass fails, and returns its error.
+    ///
+    /// `sleep` waits for a duration under the caller's async runtime, for example
+    /// `tokio::time::sleep`, so that `atlatl` doesn't depend on one.
+    ///
+    /// Each pass is blocking `redb` work, and holds the database's write lock while it runs. For
+    /// large tables, or on a single-threaded runtime, spawn the sweep where blocking is allowed,
+    /// or keep the interval short so that each pass has little to remove.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let db = Arc::new(db);
+    /// tokio::spawn(async move {
+    ///     let error = retention.sweep(&db, Duration::from_secs(60), tokio::time::sleep).await;
+    ///     tracing::error!("retention sweep stopped: {error:?}");
+    /// });
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Encoding a cutoff key, or decoding a record or key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub async fn sweep<S, F>(
+        &self,
+        database: &Database,
+        interval: Duration,
+        mut sleep: S,
+    ) -> Result<Infallible, Error>
+    where
+        S: FnMut(Duration) -> F,
+        F: Future<Output = ()>,
+    {
+        loop {
+            let mut txn = database.write()?;
+            let report = txn.apply_retention(self)?;
+            txn.commit()?;
+            tracing::debug!("retention sweep removed {} records", report.total());
+            sleep(interval).await;
+        }
+    }
+}
+
+impl RetentionReport {
+    /// Returns the number of records removed from every table.
+    #[must_use]
+    pub fn total(&self) -> u64 {
+        self.removed.values().sum()
+    }
+}
+
+impl Limit {
+    /// Returns the number of leading records to remove from a table of `len` records, of which
+    /// `before_cutoff` precede the cutoff key.
+    #[must_use]
+    pub(crate) const fn expired(&self, len: u64, before_cutoff: u64) -> u64 {
+        match self {
+            Self::Age { .. } => before_cutoff,
+            Self::Entries { max_entries } => len.saturating_sub(*max_entries),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl std::fmt::Debug for Retention {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let tables: Vec<_> = self.rules.iter().map(|rule| rule.table_name).collect();
+        f.debug_struct("Retention").field("tables", &tables).finish()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Converts a time to milliseconds since the Unix epoch, for tables keyed that way. Times before
+/// the epoch are clamped to it.
+#[must_use]
+pub fn unix_millis(time: SystemTime) -> u64 {
+    to_millis(time)
+}
+
+/// Removes records of type `V` by their primary key bytes, along with their secondary index
+/// entries, reverse index rows, and quota usage.
+fn prune<V>(txn: &mut Transaction, primary_keys: &[Vec<u8>]) -> Result<u64, Error>
+where
+    V: for<'i> Indexable<'i> + Codec<V> + HasTable,
+{
+    let mut removed = 0;
+    for primary_key_bytes in primary_keys {
+        if txn.delete_by_key_bytes::<V>(primary_key_bytes)?.is_some() {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::querying::Query;
+    use crate::typed::test_records::{Animal, Enclosure};
+
+    /// Returns the primary keys of the animals in an enclosure, as read through the index.
+    fn in_enclosure(txn: &Transaction, enclosure: &str) -> Vec<Vec<u8>> {
+        let lookup = Query::lookup(Enclosure(enclosure.into()));
+        let mut keys: Vec<Vec<u8>> =
+            txn.query::<u64, Animal>(lookup).unwrap().into_iter().collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    #[test]
+    fn removes_expired_records_and_their_index_entries() {
+        let db = Database::in_memory().unwrap();
+        let now = unix_millis(crate::clock::now());
+        let day = 86_400_000;
+
+        // Animals are keyed by when they arrived. Ten arrived last week, and five today:
+        let mut txn = db.write().unwrap();
+        txn.bulk_insert::<u64, Animal>((0..10).map(|n| {
+            Animal::new(now - 7 * day + n, "Old", if n % 2 == 0 { "Savannah" } else { "Arctic" })
+        })).unwrap();
+        txn.bulk_insert::<u64, Animal>((0..5).map(|n| Animal::new(now - n, "New", "Savannah")))
+            .unwrap();
+
+        let retention =
+            Retention::new().keep_for::<u64, Animal>(Duration::from_hours(24), unix_millis);
+        let report = txn.apply_retention(&retention).unwrap();
+        assert_eq!(report.removed.get("animals"), Some(&10));
+        assert_eq!(report.total(), 10);
+
+        let savannah: Vec<Vec<u8>> =
+            (0..5).rev().map(|n| u64::serialize(&(now - n)).unwrap()).collect();
+        assert_eq!(in_enclosure(&txn, "Savannah"), savannah);
+        assert!(in_enclosure(&txn, "Arctic").is_empty());
+
+        // A second pass has nothing left to remove:
+        assert_eq!(txn.apply_retention(&retention).unwrap().total(), 0);
+        txn.commit().unwrap();
+        assert!(db.read().unwrap().verify_indexes::<Animal>().unwrap().issues.is_empty());
+    }
+
+    #[test]
+    fn keeps_only_the_newest_records() {
+        let db = Database::in_memory().unwrap();
+        let mut txn = db.write().unwrap();
+        txn.bulk_insert::<u64, Animal>((1..=20).map(|id| {
+            Animal::new(id, "Lion", if id <= 15 { "Arctic" } else { "Savannah" })
+        })).unwrap();
+
+        let retention = Retention::new().keep_last::<u64, Animal>(8);
+        assert_eq!(txn.apply_retention(&retention).unwrap().removed.get("animals"), Some(&12));
+
+        let arctic: Vec<Vec<u8>> = (13..=15).map(|id| u64::serialize(&id).unwrap()).collect();
+        assert_eq!(in_enclosure(&txn, "Arctic"), arctic);
+        assert_eq!(in_enclosure(&txn, "Savannah").len(), 5);
+        txn.commit().unwrap();
+        assert!(db.read().unwrap().verify_indexes::<Animal>().unwrap().issues.is_empty());
+    }
+
+    #[test]
+    fn expired_records_are_a_prefix() {
+        let entries = Limit::Entries { max_entries: 10 };
+        assert_eq!(entries.expired(25, 0), 15);
+        assert_eq!(entries.expired(4, 0), 0);
+
+        let age = Limit::Age { max_age: Duration::ZERO, cutoff_key: Arc::new(|_| Ok(Vec::new())) };
+        assert_eq!(age.expired(25, 7), 7);
+    }
+}
@@ -0,0 +1,506 @@
+//! Key rotation, which moves every stored record from a retired encryption key to a new one while
+//! the database stays open.
+//!
+//! Records are re-encrypted in batches, each in its own write transaction, so that writers are
+//! only held up for one batch at a time. Every batch records how far the rotation has progressed
+//! in an internal table, in the same transaction, so an interrupted rotation picks up where it left
+//! off when [`Database::rotate_keys`] is called again with the same keys.
+//!
+//! Records are re-encrypted as they're stored: they're never decompressed or deserialized, and
+//! secondary index entries, which aren't encrypted, are left as they are.
+//!
+//! [`Database::rotate_keys`]: crate::typed::database::Database::rotate_keys
+
+use crate::Error;
+use crate::layers::core::descriptors::Direction;
+use crate::layers::core::{Bytes, Layer, LayerFailure};
+use crate::layers::encryptors::{AssociatedData, KeyBytes};
+use crate::layers::{Correctable, Encryptable, LayerStack};
+use crate::typed::Namespace;
+use redb::{ReadableTable, TableDefinition, TableHandle};
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Moves one stored value from the old key to the new key. Returns `None` if the value is already
+/// encrypted with the new key.
+type Rotator = fn(
+    &[u8],
+    &KeyBytes<'_>,
+    &KeyBytes<'_>,
+    &AssociatedData<'_>,
+) -> Result<Option<Vec<u8>>, LayerFailure>;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Name of the internal table that records the progress of an unfinished rotation, keyed by full
+/// table name. It's removed once the rotation finishes.
+pub const ROTATION_TABLE_NAME: &str = "__atlatl_key_rotation";
+
+/// Definition of the rotation progress table: full table name → encoded checkpoint.
+const ROTATION_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new(ROTATION_TABLE_NAME);
+
+/// Key of the row that holds a value sealed with the rotation's new key. A progress table whose
+/// seal can't be opened with the new key belongs to a different rotation, and is discarded.
+const SEAL_ROW: &str = "";
+
+/// Number of records re-encrypted in each write transaction, unless changed with
+/// [`KeyRotation::batch_size`].
+const DEFAULT_BATCH_SIZE: usize = 1_000;
+
+/// Marks a table whose records have all been re-encrypted.
+const CHECKPOINT_DONE: u8 = 1;
+
+/// Marks a table whose records have been re-encrypted up to, and including, the key that follows.
+const CHECKPOINT_AFTER: u8 = 0;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The record types to re-encrypt during a key rotation, and the keys to move them between.
+///
+/// Stored values don't record their type, so every primary table to be rotated must have its
+/// record type registered with [`KeyRotation::record`].
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let rotation = KeyRotation::new(retired_key, new_key)
+///     .record::<Creature>()
+///     .record::<Habitat>();
+/// let status = db.rotate_keys(&rotation, |progress| {
+///     println!("rotating `{}`: {:.0}%", progress.table, progress.fraction() * 100.0);
+/// })?;
+/// println!("{} records re-encrypted", status.entries_rotated);
+/// ```
+pub struct KeyRotation<'k> {
+    /// The key records are currently encrypted with.
+    old_key: KeyBytes<'k>,
+
+    /// The key records are re-encrypted with.
+    new_key: KeyBytes<'k>,
+
+    /// The namespace whose tables are rotated.
+    namespace: Namespace,
+
+    /// Number of records re-encrypted in each write transaction.
+    batch_size: usize,
+
+    /// Unqualified primary table name → rotator for its record type.
+    rotators: BTreeMap<&'static str, Rotator>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The progress of a key rotation, passed to the callback given to [`Database::rotate_keys`].
+///
+/// [`Database::rotate_keys`]: crate::typed::database::Database::rotate_keys
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RotationProgress {
+    /// Name of the table currently being rotated, without its namespace prefix. For example:
+    /// `"creatures"`.
+    pub table: String,
+
+    /// Number of tables that have been completely rotated, including tables that were finished by
+    /// an earlier, interrupted rotation.
+    pub tables_rotated: usize,
+
+    /// Total number of tables in the rotation.
+    pub tables_total: usize,
+
+    /// Number of records re-encrypted with the new key.
+    pub entries_rotated: u64,
+
+    /// Number of records that were already encrypted with the new key, and were left as they are.
+    pub entries_skipped: u64,
+
+    /// Number of write transactions committed.
+    pub batches_committed: u64,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// How far a table's rotation, or another batched rewrite of its records, got before it was
+/// interrupted.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Checkpoint {
+    /// Records up to, and including, this key have been rewritten.
+    After(Vec<u8>),
+
+    /// Every record has been rewritten.
+    Done,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Marks the value sealed into the progress table. It's encrypted in both directions.
+struct Seal;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<'k> KeyRotation<'k> {
+    /// Returns a rotation with no registered record types, that moves records in the default
+    /// namespace from `old_key` to `new_key`.
+    #[must_use]
+    pub fn new(old_key: KeyBytes<'k>, new_key: KeyBytes<'k>) -> Self {
+        Self {
+            old_key,
+            new_key,
+            namespace: Namespace::default(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            rotators: BTreeMap::new(),
+        }
+    }
+
+    /// Registers a record type, so that its primary table is rotated.
+    #[must_use]
+    pub fn record<V>(mut self) -> Self
+    where
+        V: crate::indexing::HasTable + Correctable + Encryptable + LayerStack + 'static,
+    {
+        self.rotators.insert(V::table_name(), rotate::<V>);
+        self
+    }
+
+    /// Rotates the tables of the given namespace, rather than the default namespace. For example,
+    /// a tenant's namespace, when the tenant's key is being replaced.
+    #[must_use]
+    pub fn in_namespace(mut self, namespace: Namespace) -> Self {
+        self.namespace = namespace;
+        self
+    }
+
+    /// Sets the number of records re-encrypted in each write transaction (defaults to `1000`).
+    /// Smaller batches hold up other writers for less time, but commit more often.
+    #[must_use]
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+}
+
+impl RotationProgress {
+    /// Returns an estimate of the rotation's progress, from `0.0` to `1.0`, based on the number of
+    /// tables rotated.
+    #[must_use]
+    pub fn fraction(&self) -> f64 {
+        if self.tables_total == 0 {
+            1.0
+        } else {
+            #[allow(clippy::cast_precision_loss, reason = "table counts are small")]
+            let fraction = self.tables_rotated as f64 / self.tables_total as f64;
+            fraction
+        }
+    }
+}
+
+impl Checkpoint {
+    /// Encodes the checkpoint for the progress table: a marker byte, followed by the last rotated
+    /// key if the table isn't done.
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        match self {
+            Self::After(key) => [&[CHECKPOINT_AFTER][..], key].concat(),
+            Self::Done => vec![CHECKPOINT_DONE],
+        }
+    }
+
+    /// Decodes a checkpoint from the progress table. Returns `None` if it's malformed.
+    pub(crate) fn decode(bytes: &[u8]) -> Option<Self> {
+        match bytes.split_first()? {
+            (&CHECKPOINT_AFTER, key) => Some(Self::After(key.to_vec())),
+            (&CHECKPOINT_DONE, []) => Some(Self::Done),
+            _ => None,
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl Encryptable for Seal {
+    const DIRECTION: Direction = Direction::Both;
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Re-encrypts every record of the tables registered with `rotation`, in batches of write
+/// transactions, resuming from the progress left by an interrupted rotation with the same new key.
+///
+/// # Errors
+///
+/// * Returns [`Error::KeyRotation`] if a record can't be decrypted with either key, or can't be
+///   re-encrypted.
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+pub(crate) fn rotate_tables(
+    db: &redb::Database,
+    rotation: &KeyRotation<'_>,
+    mut progress: impl FnMut(&RotationProgress),
+) -> Result<RotationProgress, Error> {
+    let mut checkpoints = resume(db, rotation)?;
+    let mut status = RotationProgress {
+        tables_total: rotation.rotators.len(),
+        ..RotationProgress::default()
+    };
+
+    for (&table_name, rotator) in &rotation.rotators {
+        let full_table_name = rotation.namespace.table_name(table_name).into_owned();
+        status.table = table_name.to_string();
+
+        let mut after = match checkpoints.remove(&full_table_name) {
+            Some(Checkpoint::Done) => {
+                status.tables_rotated += 1;
+                progress(&status);
+                continue;
+            },
+            Some(Checkpoint::After(key)) => Some(key),
+            None => None,
+        };
+
+        loop {
+            let txn = db.begin_write().map_err(Box::new)?;
+            let checkpoint = rotate_batch(
+                &txn,
+                rotation,
+                *rotator,
+                &full_table_name,
+                after.as_deref(),
+                &mut status,
+            )?;
+            txn.open_table(ROTATION_TABLE)?
+                .insert(&*full_table_name, &*checkpoint.encode())?;
+            txn.commit()?;
+            status.batches_committed += 1;
+
+            match checkpoint {
+                Checkpoint::After(key) => {
+                    after = Some(key);
+                    progress(&status);
+                },
+                Checkpoint::Done => break,
+            }
+        }
+
+        status.tables_rotated += 1;
+        progress(&status);
+    }
+
+    let txn = db.begin_write().map_err(Box::new)?;
+    txn.delete_table(ROTATION_TABLE)?;
+    txn.commit()?;
+    Ok(status)
+}
+
+/// Re-encrypts up to one batch of a table's records, starting after the given key, and returns
+/// the table's checkpoint once the batch is written.
+///
+/// # Errors
+///
+/// * See [`rotate_tables`].
+fn rotate_batch(
+    txn: &redb::WriteTransaction,
+    rotation: &KeyRotation<'_>,
+    rotator: Rotator,
+    full_table_name: &str,
+    after: Option<&[u8]>,
+    status: &mut RotationProgress,
+) -> Result<Checkpoint, Error> {
+    // Opening a table that doesn't exist would create it:
+    if !txn.list_tables()?.any(|table| table.name() == full_table_name) {
+        return Ok(Checkpoint::Done);
+    }
+
+    let mut table = txn.open_table(TableDefinition::<&[u8], &[u8]>::new(full_table_name))?;
+    let start = after.map_or(Bound::Unbounded, Bound::Excluded);
+    let bounds = (start, Bound::Unbounded);
+    let batch = table
+        .range::<&[u8]>(bounds)?
+        .take(rotation.batch_size)
+        .map(|entry| entry.map(|(key, value)| (key.value().to_vec(), value.value().to_vec())))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let Some((last_key, _)) = batch.last() else {
+        return Ok(Checkpoint::Done);
+    };
+    let checkpoint = if batch.len() < rotation.batch_size {
+        Checkpoint::Done
+    } else {
+        Checkpoint::After(last_key.clone())
+    };
+
+    for (key, value) in batch {
+        let associated_data = AssociatedData::new(full_table_name, &key);
+        let rotated = rotator(&value, &rotation.old_key, &rotation.new_key, &associated_data)
+            .map_err(|source| Error::KeyRotation {
+                table: full_table_name.to_string(),
+                key: key.clone(),
+                source,
+            })?;
+
+        match rotated {
+            Some(rotated) => {
+                table.insert(&*key, &*rotated)?;
+                status.entries_rotated += 1;
+            },
+            None => status.entries_skipped += 1,
+        }
+    }
+
+    Ok(checkpoint)
+}
+
+/// Returns the checkpoints left by an interrupted rotation to the same new key, keyed by full
+/// table name. Progress left by a rotation to a different key is discarded, and the progress table
+/// is sealed with this rotation's new key.
+///
+/// # Errors
+///
+/// * Returns [`Error::KeyRotation`] if the progress table couldn't be sealed.
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+fn resume(
+    db: &redb::Database,
+    rotation: &KeyRotation<'_>,
+) -> Result<BTreeMap<String, Checkpoint>, Error> {
+    let seal_data = AssociatedData::new(ROTATION_TABLE_NAME, &[]);
+    let txn = db.begin_write().map_err(Box::new)?;
+
+    let mut checkpoints = BTreeMap::new();
+    let mut sealed = false;
+    {
+        let table = txn.open_table(ROTATION_TABLE)?;
+        if let Some(seal) = table.get(SEAL_ROW)? {
+            sealed = Bytes::from_slice(seal.value())
+                .decrypt::<Seal>(rotation.new_key.reborrow(), &seal_data)
+                .is_ok();
+        }
+        if sealed {
+            for entry in table.iter()? {
+                let (table_name, checkpoint) = entry?;
+                if let Some(checkpoint) = Checkpoint::decode(checkpoint.value()) {
+                    checkpoints.insert(table_name.value().to_string(), checkpoint);
+                }
+            }
+        }
+    }
+
+    if !sealed {
+        txn.delete_table(ROTATION_TABLE)?;
+        let seal = Bytes::from_slice(&[])
+            .encrypt::<Seal>(rotation.new_key.reborrow(), &seal_data, None)
+            .map_err(|source| Error::KeyRotation {
+                table: ROTATION_TABLE_NAME.to_string(),
+                key: Vec::new(),
+                source: LayerFailure { layer: Layer::Encryption, source },
+            })?;
+        txn.open_table(ROTATION_TABLE)?.insert(SEAL_ROW, &*seal.into_bytes())?;
+    }
+
+    txn.commit()?;
+    Ok(checkpoints)
+}
+
+/// Moves a stored value from the old key to the new key with `V`'s layers, or returns `None` if
+/// it's already encrypted with the new key, which happens when a rotation is resumed.
+fn rotate<V>(
+    value_bytes: &[u8],
+    old_key: &KeyBytes<'_>,
+    new_key: &KeyBytes<'_>,
+    associated_data: &AssociatedData<'_>,
+) -> Result<Option<Vec<u8>>, LayerFailure>
+where
+    V: Correctable + Encryptable + LayerStack + 'static,
+{
+    let rotated = Bytes::reencrypt::<V>(
+        Bytes::from_slice(value_bytes),
+        old_key.reborrow(),
+        new_key.reborrow(),
+        associated_data,
+    );
+
+    match rotated {
+        Ok(rotated) => Ok(Some(rotated.into_bytes().into_owned())),
+        Err(failure) => {
+            let already_rotated = Bytes::from_slice(value_bytes)
+                .recover::<V>()
+                .and_then(|recovered| {
+                    recovered.decrypt::<V>(new_key.reborrow(), associated_data)
+                })
+                .is_ok();
+            if already_rotated { Ok(None) } else { Err(failure) }
+        },
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkpoints_round_trip() {
+        let after = Checkpoint::After(b"kelp".to_vec());
+        assert_eq!(Checkpoint::decode(&after.encode()), Some(after));
+        assert_eq!(Checkpoint::decode(&Checkpoint::Done.encode()), Some(Checkpoint::Done));
+        assert_eq!(Checkpoint::decode(&[CHECKPOINT_DONE, 7]), None);
+        assert_eq!(Checkpoint::decode(&[]), None);
+    }
+
+    #[test]
+    #[cfg(feature = "writes")]
+    fn typed_records_are_read_back_with_only_the_new_key() {
+        use crate::indexing::ReadableKeySet;
+        use crate::layers::encryptors::{KEY_SIZE, KeyId};
+        use crate::typed::database::Database;
+        use crate::typed::test_records::{Letter, Sender};
+
+        let old_key = KeyBytes::from_array(&[1; KEY_SIZE]).with_id(KeyId::new(1));
+        let new_key = KeyBytes::from_array(&[2; KEY_SIZE]).with_id(KeyId::new(2));
+        let letters = [
+            Letter::new(1, "Wile", "Acme order, please."),
+            Letter::new(2, "Wile", "The rocket skates arrived broken."),
+            Letter::new(3, "Road Runner", "Meep meep."),
+        ];
+
+        let mut db = Database::in_memory().unwrap();
+        db.set_record_key(&old_key);
+        let mut txn = db.write().unwrap();
+        txn.bulk_insert::<u64, Letter>(letters.clone()).unwrap();
+        txn.commit().unwrap();
+
+        let rotation = KeyRotation::new(old_key.reborrow(), new_key.reborrow())
+            .record::<Letter>()
+            .batch_size(2);
+        let status = db.rotate_keys(&rotation, |_| {}).unwrap();
+        assert_eq!((status.entries_rotated, status.entries_skipped), (3, 0));
+        assert_eq!(status.batches_committed, 2);
+
+        db.set_record_key(&new_key);
+        let txn = db.read().unwrap();
+        for letter in &letters {
+            assert_eq!(txn.get::<u64, Letter>(&letter.id).unwrap().as_ref(), Some(letter));
+        }
+        assert_eq!(txn.query::<u64, Letter>(Sender("Wile".into())).unwrap().len(), 2);
+
+        // Rotated records are tagged with the new key's ID:
+        let table = txn.open_raw_index_table("letters").unwrap().unwrap();
+        for entry in table.iter().unwrap() {
+            let (_, stored) = entry.unwrap();
+            let cipher_text = Bytes::from_slice(stored.value()).recover::<Letter>().unwrap();
+            assert_eq!(KeyId::of_cipher_text(cipher_text.as_ref()).unwrap(), new_key.id());
+        }
+        drop((table, txn));
+
+        db.set_record_key(&old_key);
+        let stale = db.read().unwrap().get::<u64, Letter>(&1);
+        assert!(matches!(stale, Err(Error::Layer(_))), "{stale:?}");
+    }
+}
@@ -0,0 +1,126 @@
+//! Resumable full-table scans, which read a huge table in batches across many short read
+//! transactions.
+//!
+//! Each call to the read transaction's `scan_resumable` method returns a [`ScanBatch`] of decoded
+//! entries and, unless the table has been read to the end, a [`ScanToken`] to pass to the next
+//! call. The token records the last key returned, so the next batch starts just after it, in
+//! whichever transaction reads it. Since every batch is read from its own snapshot, records written
+//! between batches are seen if their keys are after the token, and missed if they're before it.
+
+use crate::Error;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Where a resumable scan left off: the table it's scanning, and the last key it returned.
+///
+/// Tokens can be kept between processes, for example in a job queue, with [`ScanToken::to_bytes`]
+/// or `serde`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScanToken {
+    /// The qualified name of the table being scanned.
+    table: String,
+
+    /// The serialized key of the last entry returned.
+    after: Vec<u8>,
+}
+
+/// A batch of entries read by a resumable scan.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScanBatch<K, V> {
+    /// The decoded entries, in the table's key order.
+    pub entries: Vec<(K, V)>,
+
+    /// The token that reads the next batch, or `None` if the table has been read to the end.
+    pub token: Option<ScanToken>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl ScanToken {
+    /// Instantiates a token that resumes a scan of a table just after the given serialized key.
+    #[must_use]
+    pub(crate) const fn new(table: String, after: Vec<u8>) -> Self {
+        Self { table, after }
+    }
+
+    /// Returns the qualified name of the table being scanned.
+    #[must_use]
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// Returns the serialized key of the last entry returned, which the next batch starts after.
+    #[must_use]
+    pub fn after(&self) -> &[u8] {
+        &self.after
+    }
+
+    /// Checks that the token was issued for a scan of the given qualified table name.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::InvalidScanToken`] if the token is for another table.
+    pub fn check_table(&self, table: &str) -> Result<(), Error> {
+        if self.table == table {
+            Ok(())
+        } else {
+            Err(Error::InvalidScanToken {
+                reason: format!("token for `{}` can't resume a scan of `{table}`", self.table),
+            })
+        }
+    }
+
+    /// Encodes the token: the table name's length as a little-endian `u32`, the table name, then
+    /// the key.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let table_len = u32::try_from(self.table.len()).unwrap_or(u32::MAX);
+        let mut bytes = Vec::with_capacity(size_of::<u32>() + self.table.len() + self.after.len());
+        bytes.extend_from_slice(&table_len.to_le_bytes());
+        bytes.extend_from_slice(self.table.as_bytes());
+        bytes.extend_from_slice(&self.after);
+        bytes
+    }
+
+    /// Decodes a token encoded with [`ScanToken::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::InvalidScanToken`] if the bytes are truncated, or the table name isn't
+    ///   valid UTF-8.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let malformed = |reason: &str| Error::InvalidScanToken { reason: reason.to_string() };
+
+        let (table_len, rest) = bytes
+            .split_first_chunk::<{ size_of::<u32>() }>()
+            .ok_or_else(|| malformed("token is truncated"))?;
+        let table_len = usize::try_from(u32::from_le_bytes(*table_len)).unwrap_or(usize::MAX);
+        if rest.len() < table_len {
+            return Err(malformed("token is truncated"));
+        }
+
+        let (table, after) = rest.split_at(table_len);
+        let table = std::str::from_utf8(table).map_err(|_| malformed("table name isn't UTF-8"))?;
+        Ok(Self::new(table.to_string(), after.to_vec()))
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let token = ScanToken::new("tenant42.creatures".to_string(), vec![0, 1, 2, 0xFF]);
+        assert_eq!(ScanToken::from_bytes(&token.to_bytes()).unwrap(), token);
+        assert!(ScanToken::from_bytes(&token.to_bytes()[..6]).is_err());
+        assert!(token.check_table("creatures").is_err());
+    }
+}
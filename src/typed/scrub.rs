@@ -0,0 +1,187 @@
+//! Integrity scrubs, which read every stored record back through its layer stack to find records
+//! that are unreadable or corrupted, without mutating anything.
+
+use crate::layers::core::{Bytes, Layer, LayerFailure, Verified};
+use crate::layers::encryptors::{AssociatedData, KeyBytes};
+use crate::layers::{Compressible, Correctable, Encryptable, LayerStack, Serializable, Serializer};
+use std::collections::BTreeMap;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Reads one stored value back through a record type's read layers.
+type Verifier = fn(&[u8], KeyBytes<'_>, &AssociatedData<'_>) -> Result<Verified, LayerFailure>;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The record types to check during a scrub, and the key to decrypt them with.
+///
+/// Stored values don't record their type, so every primary table to be verified must have its
+/// record type registered with [`Scrubber::record`]. Other tables, including index tables, are
+/// listed in the report's [`ScrubReport::unverified_tables`].
+///
+/// Records are read back the way transactions store them: through the record type's
+/// [`RecordLayers`] if [`HasTable::layers`] declares them, and with its serializer alone
+/// otherwise.
+///
+/// [`RecordLayers`]: crate::typed::record_layers::RecordLayers
+/// [`HasTable::layers`]: crate::indexing::HasTable::layers
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let scrubber = Scrubber::new(key).record::<Creature>().record::<Habitat>();
+/// let report = db.read()?.scrub(&scrubber)?;
+/// for failure in &report.failures {
+///     eprintln!("{}: {} layer failed: {}", failure.table, failure.layer, failure.error);
+/// }
+/// ```
+pub struct Scrubber<'k> {
+    /// The key used to decrypt encrypted records.
+    key: KeyBytes<'k>,
+
+    /// Unqualified primary table name → verifier for its record type.
+    verifiers: BTreeMap<&'static str, Verifier>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A stored record that couldn't be read back during a scrub.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScrubFailure {
+    /// Name of the table the record is stored in, without its namespace prefix.
+    pub table: String,
+
+    /// The record's serialized primary key.
+    pub key: Vec<u8>,
+
+    /// The read layer that rejected the record. For example, [`Layer::Correction`] if the record
+    /// was damaged beyond what error correction could repair.
+    pub layer: Layer,
+
+    /// A description of the layer's error.
+    pub error: String,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The outcome of a scrub.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ScrubReport {
+    /// Tables whose records were read back, without their namespace prefix.
+    pub verified_tables: Vec<String>,
+
+    /// Tables that were skipped because no record type was registered for them, without their
+    /// namespace prefix.
+    pub unverified_tables: Vec<String>,
+
+    /// Number of records read back.
+    pub entries_scrubbed: u64,
+
+    /// Number of records that were readable only after error correction repaired them. These
+    /// records are still damaged on disk, and should be rewritten.
+    pub entries_recovered: u64,
+
+    /// Every record that couldn't be read back.
+    pub failures: Vec<ScrubFailure>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<'k> Scrubber<'k> {
+    /// Returns a scrubber with no registered record types, that decrypts records with the given
+    /// key.
+    #[must_use]
+    pub fn new(key: KeyBytes<'k>) -> Self {
+        Self { key, verifiers: BTreeMap::new() }
+    }
+
+    /// Registers a record type, so that its primary table is verified.
+    #[must_use]
+    pub fn record<V>(mut self) -> Self
+    where
+        V: crate::indexing::HasTable
+            + Correctable
+            + Encryptable
+            + Compressible
+            + LayerStack
+            + for<'b> Serializer<'b, V>
+            + Serializable
+            + 'static,
+    {
+        self.verifiers.insert(V::table_name(), verify::<V>);
+        self
+    }
+
+    /// Returns `true` if a record type was registered for the given table.
+    pub(crate) fn verifies(&self, table_name: &str) -> bool {
+        self.verifiers.contains_key(table_name)
+    }
+
+    /// Reads a stored value back through the read layers of the record type registered for the
+    /// given table. Returns `None` if no record type was registered for the table.
+    ///
+    /// `associated_data` is the full table name and the key the value is stored under.
+    pub(crate) fn verify(
+        &self,
+        table_name: &str,
+        associated_data: &AssociatedData<'_>,
+        value_bytes: &[u8],
+    ) -> Option<Result<Verified, LayerFailure>> {
+        let verifier = self.verifiers.get(table_name)?;
+        Some(verifier(value_bytes, self.key.reborrow(), associated_data))
+    }
+}
+
+impl ScrubReport {
+    /// Returns `true` if every scrubbed record was read back without needing repair.
+    #[must_use]
+    pub const fn is_clean(&self) -> bool {
+        self.failures.is_empty() && self.entries_recovered == 0
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Reads a stored value back through `V`'s read layers, or deserializes it if `V` has no
+/// [`RecordLayers`](crate::typed::record_layers::RecordLayers).
+fn verify<V>(
+    value_bytes: &[u8],
+    key: KeyBytes<'_>,
+    associated_data: &AssociatedData<'_>,
+) -> Result<Verified, LayerFailure>
+where
+    V: crate::indexing::HasTable
+        + Correctable
+        + Encryptable
+        + Compressible
+        + LayerStack
+        + for<'b> Serializer<'b, V>
+        + Serializable
+        + 'static,
+{
+    if V::layers().is_none() {
+        // Records without layers are stored untagged, as their serializer writes them:
+        return V::deserialize(Bytes::from_slice(value_bytes))
+            .map(|_| Verified { shards_recovered: None })
+            .map_err(|error| LayerFailure::at(Layer::Serialization)(error.into()));
+    }
+
+    #[cfg(feature = "compress-dictionaries")]
+    return Bytes::verify_read_layers::<V>(
+        Bytes::from_slice(value_bytes),
+        key,
+        associated_data,
+        None,
+    );
+
+    #[cfg(not(feature = "compress-dictionaries"))]
+    return Bytes::verify_read_layers::<V>(
+        Bytes::from_slice(value_bytes),
+        key,
+        associated_data,
+    );
+}
@@ -0,0 +1,440 @@
+//! An embedded HTTP server that lets another process, such as a dashboard or a read replica, follow
+//! a live database without sharing its file.
+//!
+//! # Endpoints
+//!
+//! * `GET /changes?since=<sequence>` streams every change after `since` from the change log, in the
+//!   incremental backup format. Followers replay it with [`Database::apply_changes`], and use the
+//!   summary's `until` as the next request's `since`. [`Follower`] does both.
+//!
+//! * `GET /query/<table>?filter=<filter>` evaluates a filter, in the
+//!   [`QueryParser`](crate::querying::QueryParser) language, against a table registered with
+//!   [`Server::queryable`], and answers with the matching records as JSON Lines.
+//!
+//! If the database has a [`RedactionPolicy`](crate::redaction::RedactionPolicy), changes and
+//! query results are redacted by it before they're sent.
+//!
+//! * `GET /metrics` answers with the database's
+//!   [`StatsReport`](crate::typed::stats_report::StatsReport) in the Prometheus text format, for
+//!   Prometheus or any compatible scraper.
+//!
+//! The server doesn't authenticate requests or encrypt connections. Bind it to a private address,
+//! or put it behind a reverse proxy that does.
+
+use crate::indexing::HasTable;
+use crate::querying::QueryParser;
+use crate::redaction::Redacted;
+use crate::typed::backup::DeltaSummary;
+use crate::typed::database::Database;
+use crate::typed::transaction::ReadTransaction;
+use crate::{Codec, Error};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Answers a query against one table: parses the filter, and writes the matching records to the
+/// body as JSON Lines.
+type QueryHandler = Box<dyn Fn(&ReadTransaction, &str) -> Result<Reply, Error> + Send + Sync>;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// An embedded HTTP server for a database. See the [module documentation](self) for its endpoints.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let mut db = Database::open("creatures.redb")?;
+/// db.enable_change_log()?;
+/// let parser = QueryParser::new().field("habitat", Habitat);
+/// let server = Server::new(Arc::new(db)).queryable::<u64, Creature>(parser);
+/// std::thread::spawn(move || server.serve("127.0.0.1:7878"));
+/// ```
+pub struct Server {
+    db: Arc<Database>,
+    queries: BTreeMap<&'static str, QueryHandler>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Follows a database served by a [`Server`], replaying its changes onto a local copy.
+///
+/// The local copy must start as a full backup of the served database, for example one taken with
+/// `Database::backup_to`, and `since` must be the last change sequence the backup includes.
+#[derive(Clone, Debug)]
+pub struct Follower {
+    /// Address of the server. For example: `"10.0.0.7:7878"`.
+    address: String,
+
+    /// The last change sequence that has been replayed.
+    since: u64,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// An HTTP response, before it's sent.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Reply {
+    pub(crate) status: u16,
+    pub(crate) content_type: &'static str,
+    pub(crate) body: Vec<u8>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Server {
+    /// Creates a server for a database. Only the change log is served until tables are made
+    /// queryable with [`Server::queryable`].
+    #[must_use]
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db, queries: BTreeMap::new() }
+    }
+
+    /// Answers `GET /query/<table>` requests for a record type, using `parser` to parse filters.
+    #[must_use]
+    pub fn queryable<K, V>(mut self, parser: QueryParser<V>) -> Self
+    where
+        K: Codec<K>,
+        V: serde::Serialize + Codec<V> + HasTable + Send + Sync + 'static,
+    {
+        let handler = move |txn: &ReadTransaction, filter: &str| -> Result<Reply, Error> {
+            let query = match parser.parse(filter) {
+                Ok(query) => query,
+                Err(error) => return Ok(Reply::text(400, &error.to_string())),
+            };
+
+            let primary_table = txn.open_table::<K, V>(V::table_name())?;
+            let mut body = Vec::new();
+            for primary_key_bytes in &txn.query::<K, V>(query)? {
+                let Some(record) = primary_table.get(&K::deserialize(primary_key_bytes)?)? else {
+                    continue;
+                };
+                let record = match txn.redaction_policy() {
+                    Some(policy) => match policy.apply(record) {
+                        Redacted::Keep(record) => record,
+                        Redacted::Drop => continue,
+                    },
+                    None => record,
+                };
+                serde_json::to_writer(&mut body, &record)?;
+                body.push(b'\n');
+            }
+            Ok(Reply { status: 200, content_type: "application/x-ndjson", body })
+        };
+
+        self.queries.insert(V::table_name(), Box::new(handler));
+        self
+    }
+
+    /// Listens on `address`, and answers requests one at a time until the listener fails.
+    ///
+    /// A response that couldn't be sent, for example because its client disconnected, is logged
+    /// and doesn't stop the server.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::External`] if `address` couldn't be bound.
+    pub fn serve(&self, address: impl ToSocketAddrs) -> Result<(), Error> {
+        let listener = tiny_http::Server::http(address).map_err(Error::External)?;
+        for request in listener.incoming_requests() {
+            let reply = if *request.method() == tiny_http::Method::Get {
+                self.route(request.url())
+            } else {
+                Reply::text(405, "only GET requests are supported")
+            };
+
+            let url = request.url().to_string();
+            if let Err(error) = reply.send(request) {
+                tracing::warn!("the response to `{url}` couldn't be sent: {error}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Answers a `GET` request for a URL path and query string. Errors are turned into error
+    /// responses, so that one bad request doesn't stop the server.
+    pub(crate) fn route(&self, url: &str) -> Reply {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let segments: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+        let since = parameter(query, "since").map_or(Ok(0), |since| since.parse());
+        let result = match segments[..] {
+            ["", "changes"] => since.map_or_else(
+                |_| Ok(Reply::text(400, "`since` must be a change sequence number")),
+                |since| self.changes(since),
+            ),
+            ["", "query", table] => self.queries.get(&*percent_decode(table)).map_or_else(
+                || Ok(Reply::text(404, "table isn't queryable")),
+                |handler| {
+                    let filter = parameter(query, "filter").unwrap_or_default();
+                    self.db.read().and_then(|txn| handler(&txn, &filter))
+                },
+            ),
+            ["", "metrics"] => self.metrics(),
+            _ => Ok(Reply::text(404, "no such endpoint")),
+        };
+
+        result.unwrap_or_else(|error| match error {
+            Error::ChangeLogNotEnabled => Reply::text(409, &error.to_string()),
+            error => Reply::text(500, &error.to_string()),
+        })
+    }
+
+    /// Answers a `GET /changes` request.
+    ///
+    /// # Errors
+    ///
+    /// * See [`Database::backup_incremental`].
+    fn changes(&self, since: u64) -> Result<Reply, Error> {
+        let mut body = Vec::new();
+        self.db.backup_incremental(since, &mut body)?;
+        Ok(Reply { status: 200, content_type: "application/octet-stream", body })
+    }
+
+    /// Answers a `GET /metrics` request.
+    ///
+    /// # Errors
+    ///
+    /// * See [`Database::stats_report`].
+    fn metrics(&self) -> Result<Reply, Error> {
+        let body = self.db.stats_report()?.to_prometheus().into_bytes();
+        Ok(Reply { status: 200, content_type: "text/plain; version=0.0.4", body })
+    }
+}
+
+impl Follower {
+    /// Creates a follower for the server at `address`, for a local copy that already has every
+    /// change up to and including `since`.
+    #[must_use]
+    pub fn new(address: impl Into<String>, since: u64) -> Self {
+        Self { address: address.into(), since }
+    }
+
+    /// Returns the last change sequence that has been replayed.
+    #[must_use]
+    pub const fn since(&self) -> u64 {
+        self.since
+    }
+
+    /// Fetches the changes made since the last poll from the server, and replays them onto `db`
+    /// in a single write transaction. Call this periodically to keep `db` up to date.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::ServerResponse`] if the server answered with an error.
+    ///
+    /// * Returns [`Error::Io`] if the server couldn't be reached.
+    ///
+    /// * See [`Database::apply_changes`].
+    pub fn poll(&mut self, db: &Database) -> Result<DeltaSummary, Error> {
+        let mut stream = TcpStream::connect(&*self.address)?;
+        write!(
+            stream,
+            "GET /changes?since={} HTTP/1.0\r\nHost: {}\r\n\r\n",
+            self.since, self.address,
+        )?;
+
+        let mut response = BufReader::new(stream);
+        let mut status_line = String::new();
+        response.read_line(&mut status_line)?;
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .unwrap_or(0);
+
+        // Headers end at the first empty line:
+        let mut header = String::new();
+        while response.read_line(&mut header)? > 2 {
+            header.clear();
+        }
+
+        if status != 200 {
+            let mut message = String::new();
+            response.read_to_string(&mut message)?;
+            return Err(Error::ServerResponse { status, message });
+        }
+
+        let summary = db.apply_changes(response)?;
+        self.since = summary.until;
+        Ok(summary)
+    }
+}
+
+impl Reply {
+    /// Returns a plain text response, for errors.
+    fn text(status: u16, message: &str) -> Self {
+        let body = message.as_bytes().to_vec();
+        Self { status, content_type: "text/plain; charset=utf-8", body }
+    }
+
+    /// Sends the response to the client that made `request`.
+    ///
+    /// # Errors
+    ///
+    /// * Returns an I/O error if the response couldn't be sent.
+    fn send(self, request: tiny_http::Request) -> Result<(), std::io::Error> {
+        let content_type =
+            tiny_http::Header::from_bytes(&b"Content-Type"[..], self.content_type.as_bytes())
+                .map_err(|()| std::io::Error::from(std::io::ErrorKind::InvalidData))?;
+        let response = tiny_http::Response::from_data(self.body)
+            .with_status_code(self.status)
+            .with_header(content_type);
+        request.respond(response)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Returns the percent-decoded value of a parameter in a URL's query string.
+fn parameter(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| percent_decode(value))
+}
+
+/// Decodes a percent-encoded URL component, where `+` is a space. Malformed escapes are kept as-is.
+fn percent_decode(component: &str) -> String {
+    let bytes = component.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut position = 0;
+    while position < bytes.len() {
+        match bytes[position] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let escape = bytes
+                    .get(position + 1..position + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                if let Some(byte) = escape {
+                    decoded.push(byte);
+                    position += 2;
+                } else {
+                    decoded.push(b'%');
+                }
+            },
+            byte => decoded.push(byte),
+        }
+        position += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_query_parameters() {
+        let query = "since=4&filter=habitat+%3D+%27Tide%20Pool%27";
+        assert_eq!(parameter(query, "since").as_deref(), Some("4"));
+        assert_eq!(parameter(query, "filter").as_deref(), Some("habitat = 'Tide Pool'"));
+        assert_eq!(parameter(query, "limit"), None);
+        assert_eq!(percent_decode("100%"), "100%");
+    }
+
+    #[test]
+    fn routes_requests_to_endpoints() {
+        let server = Server::new(Arc::new(Database::in_memory().unwrap()));
+        assert_eq!(server.route("/nowhere").status, 404);
+        assert_eq!(server.route("/query/animals?filter=").status, 404);
+        assert_eq!(server.route("/changes?since=soon").status, 400);
+        assert_eq!(server.route("/changes").status, 409);
+
+        let metrics = server.route("/metrics/");
+        assert_eq!(metrics.status, 200);
+        assert_eq!(metrics.content_type, "text/plain; version=0.0.4");
+    }
+
+    #[test]
+    fn queries_are_answered_with_redacted_records() {
+        use crate::redaction::{RedactionPolicy, mask};
+        use crate::typed::test_records::{Animal, Enclosure};
+
+        let mut db = Database::in_memory().unwrap();
+        let mut txn = db.write().unwrap();
+        txn.bulk_insert::<u64, Animal>([
+            Animal::new(1, "Coyote", "Desert"),
+            Animal::new(2, "Roadrunner", "Desert"),
+            Animal::new(3, "Otter", "River"),
+        ]).unwrap();
+        txn.commit().unwrap();
+        db.set_redaction_policy(RedactionPolicy::new().with(|mut animal: Animal| {
+            if animal.id == 2 {
+                return Redacted::Drop;
+            }
+            animal.name = mask(&animal.name);
+            Redacted::Keep(animal)
+        }));
+
+        let parser = QueryParser::new().field("enclosure", Enclosure);
+        let server = Server::new(Arc::new(db)).queryable::<u64, Animal>(parser);
+        let reply = server.route("/query/animals?filter=enclosure+%3D+%27Desert%27");
+        assert_eq!(reply.status, 200);
+        assert_eq!(reply.content_type, "application/x-ndjson");
+        let records: Vec<Animal> = reply
+            .body
+            .split(|&byte| byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(records, [Animal::new(1, "******", "Desert")]);
+
+        assert_eq!(server.route("/query/animals?filter=enclosure+%3D").status, 400);
+    }
+
+    #[test]
+    fn followers_replay_the_served_changes() {
+        use crate::typed::test_records::Animal;
+
+        let mut db = Database::in_memory().unwrap();
+        db.enable_change_log().unwrap();
+        let db = Arc::new(db);
+        let insert = |animal: Animal| {
+            let mut txn = db.write().unwrap();
+            txn.bulk_insert::<u64, Animal>([animal]).unwrap();
+            txn.commit().unwrap();
+        };
+        insert(Animal::new(1, "Coyote", "Desert"));
+
+        // Binding to port 0 finds a free port for the server:
+        let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let server = Server::new(Arc::clone(&db));
+        std::thread::spawn(move || server.serve(address));
+
+        // A client that disconnects without reading its response doesn't stop the server:
+        let replica = Database::in_memory().unwrap();
+        let mut follower = Follower::new(address.to_string(), 0);
+        let summary = (0..100)
+            .find_map(|_| {
+                if let Ok(mut stream) = TcpStream::connect(address) {
+                    drop(stream.write_all(b"GET /changes HTTP/1.0\r\n\r\n"));
+                }
+                std::thread::sleep(std::time::Duration::from_millis(10));
+                follower.poll(&replica).ok()
+            })
+            .unwrap();
+        assert_eq!(summary, DeltaSummary { since: 0, until: 1, changes: 1 });
+
+        insert(Animal::new(2, "Roadrunner", "Desert"));
+        assert_eq!(follower.poll(&replica).unwrap().changes, 1);
+        assert_eq!(follower.since(), 2);
+        assert_eq!(follower.poll(&replica).unwrap().changes, 0);
+
+        let txn = replica.read().unwrap();
+        assert_eq!(txn.get::<u64, Animal>(&1).unwrap(), Some(Animal::new(1, "Coyote", "Desert")));
+        let roadrunner = Animal::new(2, "Roadrunner", "Desert");
+        assert_eq!(txn.get::<u64, Animal>(&2).unwrap(), Some(roadrunner));
+    }
+}
@@ -0,0 +1,397 @@
+//! A read cache shared by every read transaction of a database in the process, so that hot records
+//! are decoded once instead of once per transaction.
+//!
+//! Entries are keyed by qualified table name and serialized primary key, and hold decoded records,
+//! so a hit skips the B-tree descent and the record's deserialization. The cache is enabled with
+//! `Database::enable_shared_cache`, and read through with the read transaction's `get_cached`
+//! method.
+//!
+//! Records with [`RecordLayers`] are cached after they've been passed back through them, so a
+//! cached record is read without its key. Tenants' records are kept apart by their qualified table
+//! names.
+//!
+//! [`RecordLayers`]: crate::typed::record_layers::RecordLayers
+//!
+//! The cache can also remember primary keys that have no record, with
+//! [`SharedCache::with_negative_capacity`]. Repeated lookups of a missing key, which are common in
+//! check-then-create flows, then skip key lookup in the table entirely.
+//!
+//! # Consistency
+//!
+//! A read transaction only uses the cache if nothing has been committed since it began, so that
+//! it never sees a record newer or older than its own snapshot. Every write transaction clears the
+//! cache when it commits, since it isn't known which tables it changed. The cache suits read-heavy
+//! workloads, where commits are rare compared to reads.
+
+use std::any::Any;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// Number of records kept by a cache created with [`SharedCache::default`].
+pub const DEFAULT_CAPACITY: usize = 10_000;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Identifies a cached record: its qualified table name and its serialized primary key.
+type CacheKey = (String, Vec<u8>);
+
+/// A decoded record, of whichever type its table holds.
+type CachedRecord = Box<dyn Any + Send + Sync>;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A bounded cache of decoded records, shared between read transactions. See the
+/// [module documentation](self).
+///
+/// A cache holds the records of one database. Don't enable the same cache on several databases:
+/// records are keyed by table name, so a table of one database would be served the records of a
+/// table of the same name in another.
+///
+/// # Example
+///
+/// ```rust
+/// use atlatl::indexing::Creature;
+/// use atlatl::typed::database::Database;
+/// use atlatl::typed::shared_cache::SharedCache;
+/// use std::sync::Arc;
+///
+/// let cache = Arc::new(SharedCache::new(50_000).with_negative_capacity(1_000));
+/// let mut db = Database::in_memory()?;
+/// db.enable_shared_cache(Arc::clone(&cache));
+///
+/// // The second look-up of a missing creature is served from the cache:
+/// assert!(db.read()?.get_cached::<u64, Creature>(&7)?.is_none());
+/// assert!(db.read()?.get_cached::<u64, Creature>(&7)?.is_none());
+/// assert_eq!(cache.stats().negative_hits, 1);
+///
+/// // Committing clears the cache:
+/// db.write()?.commit()?;
+/// assert_eq!(cache.stats().missing, 0);
+/// # Ok::<(), atlatl::Error>(())
+/// ```
+pub struct SharedCache {
+    state: Mutex<State>,
+    hits: AtomicU64,
+    negative_hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// The cached records, and the commits that invalidate them.
+struct State {
+    /// Maximum number of records kept.
+    capacity: usize,
+
+    /// Increases each time the cache is invalidated. Transactions that began at an earlier epoch
+    /// can't use the cache.
+    epoch: u64,
+
+    /// Number of write transactions that are committing right now. The cache can't be used until
+    /// they're done, because it isn't known which snapshot readers see.
+    committing: usize,
+
+    /// The cached records.
+    records: HashMap<CacheKey, CachedRecord>,
+
+    /// The order records were cached in, oldest first, for eviction.
+    order: VecDeque<CacheKey>,
+
+    /// Maximum number of missing keys remembered. Zero if missing keys aren't cached.
+    negative_capacity: usize,
+
+    /// Keys known to have no record.
+    missing: HashSet<CacheKey>,
+
+    /// The order missing keys were cached in, oldest first, for eviction.
+    missing_order: VecDeque<CacheKey>,
+
+    /// Number of times the cache was cleared by a commit.
+    invalidations: u64,
+}
+
+/// Clears the cache when a commit begins, and again when it's done, whether or not it succeeded.
+#[cfg(feature = "writes")]
+pub(crate) struct Invalidation<'c>(&'c SharedCache);
+
+// -------------------------------------------------------------------------------------------------
+//
+/// How well a [`SharedCache`] is doing, returned by [`SharedCache::stats`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SharedCacheStats {
+    /// Number of reads served from the cache, including reads of keys known to be missing.
+    pub hits: u64,
+
+    /// Number of reads of keys known to be missing, which were served from the cache.
+    pub negative_hits: u64,
+
+    /// Number of reads that had to go to the database.
+    pub misses: u64,
+
+    /// Number of records in the cache.
+    pub records: u64,
+
+    /// Number of keys in the cache that are known to have no record.
+    pub missing: u64,
+
+    /// Number of times the cache was cleared by a commit.
+    pub invalidations: u64,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl SharedCache {
+    /// Instantiates an empty cache that keeps up to `capacity` records. When it's full, the
+    /// records that were cached first are evicted first. Missing keys aren't cached.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        let state = State {
+            capacity,
+            epoch: 0,
+            committing: 0,
+            records: HashMap::new(),
+            order: VecDeque::new(),
+            negative_capacity: 0,
+            missing: HashSet::new(),
+            missing_order: VecDeque::new(),
+            invalidations: 0,
+        };
+        Self {
+            state: Mutex::new(state),
+            hits: AtomicU64::new(0),
+            negative_hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Also remembers up to `capacity` primary keys that have no record, so that reading them
+    /// again doesn't look them up in the table. Like records, missing keys are forgotten when any
+    /// write transaction commits, including one that creates their record.
+    #[must_use]
+    pub fn with_negative_capacity(mut self, capacity: usize) -> Self {
+        self.state.get_mut().unwrap_or_else(PoisonError::into_inner).negative_capacity = capacity;
+        self
+    }
+
+    /// Returns the cache's hit and miss counts, and its size.
+    #[must_use]
+    pub fn stats(&self) -> SharedCacheStats {
+        let state = self.lock();
+        SharedCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            negative_hits: self.negative_hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            records: state.records.len() as u64,
+            missing: state.missing.len() as u64,
+            invalidations: state.invalidations,
+        }
+    }
+
+    /// Removes every record and missing key from the cache.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Returns the current epoch. A read transaction records it just before it begins.
+    pub(crate) fn epoch(&self) -> u64 {
+        self.lock().epoch
+    }
+
+    /// Returns a cached record, if a transaction that began at `epoch` may use it. Returns
+    /// `Some(None)` if the key is known to have no record, and `None` if the table must be read.
+    #[allow(clippy::option_option, reason = "`Some(None)` is a cached answer of \"no record\"")]
+    pub(crate) fn get<V>(&self, epoch: u64, table: &str, key: &[u8]) -> Option<Option<V>>
+    where
+        V: Clone + 'static,
+    {
+        let state = self.lock();
+        if !state.usable_at(epoch) {
+            drop(state);
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+
+        let cache_key = (table.to_string(), key.to_vec());
+        let cached = if state.missing.contains(&cache_key) {
+            self.negative_hits.fetch_add(1, Ordering::Relaxed);
+            Some(None)
+        } else {
+            state
+                .records
+                .get(&cache_key)
+                .and_then(|record| record.downcast_ref::<V>())
+                .map(|record| Some(record.clone()))
+        };
+        drop(state);
+
+        if cached.is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+        cached
+    }
+
+    /// Caches a record read by a transaction that began at `epoch`, unless the cache has been
+    /// invalidated since, in which case the record may be out of date.
+    pub(crate) fn insert<V>(&self, epoch: u64, table: String, key: Vec<u8>, record: V)
+    where
+        V: Send + Sync + 'static,
+    {
+        let mut state = self.lock();
+        if !state.usable_at(epoch) || state.capacity == 0 {
+            return;
+        }
+
+        let cache_key = (table, key);
+        if state.records.insert(cache_key.clone(), Box::new(record)).is_none() {
+            state.order.push_back(cache_key);
+        }
+        while state.records.len() > state.capacity {
+            let Some(oldest) = state.order.pop_front() else { break };
+            state.records.remove(&oldest);
+        }
+        drop(state);
+    }
+
+    /// Remembers that a key had no record for a transaction that began at `epoch`, unless the
+    /// cache has been invalidated since, or missing keys aren't cached.
+    pub(crate) fn insert_missing(&self, epoch: u64, table: String, key: Vec<u8>) {
+        let mut state = self.lock();
+        if !state.usable_at(epoch) || state.negative_capacity == 0 {
+            return;
+        }
+
+        let cache_key = (table, key);
+        if state.missing.insert(cache_key.clone()) {
+            state.missing_order.push_back(cache_key);
+        }
+        while state.missing.len() > state.negative_capacity {
+            let Some(oldest) = state.missing_order.pop_front() else { break };
+            state.missing.remove(&oldest);
+        }
+        drop(state);
+    }
+
+    /// Invalidates the cache for a commit that's about to begin. The cache is invalidated again
+    /// when the returned guard is dropped, once the commit is done.
+    #[cfg(feature = "writes")]
+    pub(crate) fn begin_commit(&self) -> Invalidation<'_> {
+        let mut state = self.lock();
+        state.committing += 1;
+        state.invalidate();
+        drop(state);
+        Invalidation(self)
+    }
+
+    /// Takes the state's lock. The state is always consistent, so a poisoned lock is recovered.
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl State {
+    /// Returns `true` if a transaction that began at `epoch` may use the cache.
+    const fn usable_at(&self, epoch: u64) -> bool {
+        self.epoch == epoch && self.committing == 0
+    }
+
+    /// Removes every record and missing key, and moves to a new epoch.
+    #[cfg(feature = "writes")]
+    fn invalidate(&mut self) {
+        self.epoch += 1;
+        self.invalidations += 1;
+        self.clear();
+    }
+
+    /// Removes every record and missing key.
+    fn clear(&mut self) {
+        self.records.clear();
+        self.order.clear();
+        self.missing.clear();
+        self.missing_order.clear();
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl Default for SharedCache {
+    /// Instantiates an empty cache that keeps up to `10000` records.
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl std::fmt::Debug for SharedCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedCache").field("stats", &self.stats()).finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "writes")]
+impl Drop for Invalidation<'_> {
+    fn drop(&mut self) {
+        let mut state = self.0.lock();
+        state.committing -= 1;
+        state.invalidate();
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "writes"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commits_hide_records_from_older_transactions() {
+        let cache = SharedCache::new(2);
+        let epoch = cache.epoch();
+        cache.insert(epoch, "creatures".to_string(), vec![1], "coyote".to_string());
+        assert_eq!(cache.get::<String>(epoch, "creatures", &[1]), Some(Some("coyote".into())));
+        assert_eq!(cache.get::<u64>(epoch, "creatures", &[1]), None);
+
+        let commit = cache.begin_commit();
+        assert_eq!(cache.get::<String>(epoch, "creatures", &[1]), None);
+        cache.insert(cache.epoch(), "creatures".to_string(), vec![1], "stale".to_string());
+        drop(commit);
+
+        let epoch = cache.epoch();
+        assert_eq!(cache.get::<String>(epoch, "creatures", &[1]), None);
+        for key in 0..3 {
+            cache.insert(epoch, "creatures".to_string(), vec![key], key);
+        }
+        assert_eq!(cache.get::<u8>(epoch, "creatures", &[0]), None);
+        assert_eq!(cache.stats().records, 2);
+    }
+
+    #[test]
+    fn missing_keys_are_remembered_until_a_commit() {
+        let cache = SharedCache::new(2);
+        let epoch = cache.epoch();
+        cache.insert_missing(epoch, "creatures".to_string(), vec![9]);
+        assert_eq!(cache.get::<String>(epoch, "creatures", &[9]), None);
+
+        let cache = SharedCache::new(2).with_negative_capacity(1);
+        let epoch = cache.epoch();
+        cache.insert_missing(epoch, "creatures".to_string(), vec![8]);
+        cache.insert_missing(epoch, "creatures".to_string(), vec![9]);
+        assert_eq!(cache.get::<String>(epoch, "creatures", &[8]), None);
+        assert_eq!(cache.get::<String>(epoch, "creatures", &[9]), Some(None));
+        assert_eq!(cache.stats().negative_hits, 1);
+
+        drop(cache.begin_commit());
+        assert_eq!(cache.get::<String>(cache.epoch(), "creatures", &[9]), None);
+        assert_eq!(cache.stats().missing, 0);
+    }
+}
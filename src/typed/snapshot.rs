@@ -0,0 +1,548 @@
+//! Read-only views of the database: pinned snapshots of its current state, and views of the
+//! database as it was at a retained snapshot or change log sequence number.
+
+use crate::indexing::{HasPrimaryKey, HasTable};
+use crate::layers::encryptors::KeyBytes;
+use crate::typed::Namespace;
+use crate::typed::change_log::{CHANGE_LOG_TABLE, Change};
+use crate::typed::record_layers::{RecordContext, owned_key};
+use crate::typed::transaction::ReadTransaction;
+use crate::{Codec, Error};
+use redb::{ReadableTable, ReadableTableMetadata, TableDefinition};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A consistent view of the database, pinned when it was taken with [`Database::snapshot`].
+///
+/// A snapshot is a read transaction that remembers when it was begun. It can be held open across
+/// many queries, for example while a report is generated, and every query sees the database as it
+/// was when the snapshot was taken. It dereferences to a [`ReadTransaction`], so everything that
+/// can be done in a read transaction can be done in a snapshot.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let snapshot = db.snapshot()?;
+/// let tide_pools = snapshot.query::<u64, Creature>(Habitat::eq(&"Tide Pool"))?;
+/// let reefs = snapshot.query::<u64, Creature>(Habitat::eq(&"Reef"))?;
+/// if snapshot.is_stale(Duration::from_secs(60)) {
+///     println!("report is over a minute behind");
+/// }
+/// ```
+///
+/// # Notes
+///
+/// * Writers aren't blocked by a snapshot, but pages they free can't be reused while it's open, so
+///   the database file may grow while a snapshot is held for a long time. Use
+///   [`Snapshot::is_stale`] to decide when to take a fresh one.
+///
+/// [`Database::snapshot`]: crate::typed::database::Database::snapshot
+#[derive(Debug)]
+pub struct Snapshot {
+    txn: ReadTransaction,
+    taken_at: SystemTime,
+    sequence: Option<u64>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// Name of the internal table that stores retained snapshots: snapshot ID → the sequence number of
+/// the last change the snapshot can see.
+pub const SNAPSHOT_TABLE_NAME: &str = "__atlatl_snapshots";
+
+/// Definition of the retained snapshots table.
+pub(crate) const SNAPSHOT_TABLE: TableDefinition<u64, u64> =
+    TableDefinition::new(SNAPSHOT_TABLE_NAME);
+
+/// A primary table, opened for reading: serialized primary key → serialized record.
+type PrimaryTable = redb::ReadOnlyTable<&'static [u8], &'static [u8]>;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Identifies a snapshot retained with [`Database::retain_snapshot`].
+///
+/// Snapshot IDs are stable across restarts and can be stored, for example, alongside a nightly
+/// report so it can be regenerated later.
+///
+/// [`Database::retain_snapshot`]: crate::typed::database::Database::retain_snapshot
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct SnapshotId(pub(crate) u64);
+
+impl SnapshotId {
+    /// Returns the snapshot's ID as a `u64`, for storing it elsewhere.
+    #[must_use]
+    pub const fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl From<u64> for SnapshotId {
+    /// Converts a previously stored `u64` back into a `SnapshotId`.
+    fn from(id: u64) -> Self {
+        Self(id)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A point in the database's history that [`Database::read_at`] can open a view at.
+///
+/// [`Database::read_at`]: crate::typed::database::Database::read_at
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ReadPoint {
+    /// The point a snapshot was retained at, with [`Database::retain_snapshot`].
+    ///
+    /// [`Database::retain_snapshot`]: crate::typed::database::Database::retain_snapshot
+    Snapshot(SnapshotId),
+
+    /// The point just after the change with the given sequence number was committed. For example,
+    /// a [`Snapshot::sequence`] or a [`Change::sequence`]. `0` is the point before any change.
+    ///
+    /// [`Change::sequence`]: crate::typed::change_log::Change::sequence
+    Sequence(u64),
+}
+
+impl From<SnapshotId> for ReadPoint {
+    /// Reads at the point the snapshot was retained at.
+    fn from(snapshot_id: SnapshotId) -> Self {
+        Self::Snapshot(snapshot_id)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A read-only view of the database as it was at an earlier point in its change log, opened with
+/// [`Database::read_at`].
+///
+/// The view is backed by a read transaction, so writers aren't blocked while it's open. Records
+/// that were changed after the point are rewound by looking up their last change before it in the
+/// change log.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let last_week = db.retain_snapshot()?;
+/// // ...a week of writes later...
+/// let then = db.read_at(last_week)?.len::<Creature>()?;
+/// let now = db.read_at(ReadPoint::Sequence(db.snapshot()?.sequence().unwrap_or(0)))?;
+/// println!("{then} creatures last week, {} now", now.len::<Creature>()?);
+/// ```
+///
+/// # Notes
+///
+/// * Every look-up scans the part of the change log written after the point, so reads get slower
+///   the further back the point is.
+///
+/// * Records are rewound from the change log alone. A record that was written before the change
+///   log was enabled, and changed after the point, reads as missing.
+///
+/// [`Database::read_at`]: crate::typed::database::Database::read_at
+pub struct SnapshotView {
+    sequence: u64,
+    redb: redb::ReadTransaction,
+    namespace: Namespace,
+    record_key: Option<Arc<KeyBytes<'static>>>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Snapshot {
+    /// Pins a read transaction as a snapshot taken now. `sequence` is the last change log
+    /// sequence the transaction can see, if the database keeps a change log.
+    pub(crate) fn new(txn: ReadTransaction, sequence: Option<u64>) -> Self {
+        Self { txn, taken_at: crate::clock::now(), sequence }
+    }
+
+    /// Returns the wall-clock time the snapshot was taken at.
+    #[must_use]
+    pub const fn taken_at(&self) -> SystemTime {
+        self.taken_at
+    }
+
+    /// Returns the sequence number of the last change the snapshot can see, or `None` if the
+    /// database doesn't keep a change log. Two snapshots with the same sequence see the same data.
+    #[must_use]
+    pub const fn sequence(&self) -> Option<u64> {
+        self.sequence
+    }
+
+    /// Returns how long ago the snapshot was taken.
+    #[must_use]
+    pub fn age(&self) -> Duration {
+        crate::clock::since(self.taken_at)
+    }
+
+    /// Returns `true` if the snapshot was taken more than `max_age` ago.
+    #[must_use]
+    pub fn is_stale(&self, max_age: Duration) -> bool {
+        self.age() > max_age
+    }
+
+    /// Ends the snapshot, returning its read transaction.
+    #[must_use]
+    pub fn into_transaction(self) -> ReadTransaction {
+        self.txn
+    }
+}
+
+impl SnapshotView {
+    /// Opens a view, at the given change log sequence number, over a fresh read transaction.
+    pub(crate) fn new(redb: redb::ReadTransaction, sequence: u64) -> Self {
+        Self { sequence, redb, namespace: Namespace::default(), record_key: None }
+    }
+
+    /// Reads every table in the given namespace from now on.
+    #[must_use]
+    pub fn in_namespace(self, namespace: Namespace) -> Self {
+        Self { namespace, ..self }
+    }
+
+    /// Decrypts records that have [`RecordLayers`] with the given key from now on, instead of the
+    /// database's record key. For example, a tenant's key when reading in the tenant's namespace.
+    ///
+    /// [`RecordLayers`]: crate::typed::record_layers::RecordLayers
+    #[must_use]
+    pub fn with_record_key(self, key: &KeyBytes<'_>) -> Self {
+        Self { record_key: Some(owned_key(key)), ..self }
+    }
+
+    /// Decrypts records that have record layers with the database's shared record key.
+    #[must_use]
+    pub(crate) fn with_shared_record_key(self, record_key: Option<Arc<KeyBytes<'static>>>) -> Self {
+        Self { record_key, ..self }
+    }
+
+    /// Returns the sequence number of the last change the view can see.
+    #[must_use]
+    pub const fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Retrieves a value, as it was at the view's point, by the specified primary key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    ///
+    /// * Encoding the primary key fails,
+    /// * Decoding the value or a change log entry fails, or
+    /// * If a storage error occurs.
+    pub fn get<'pk, PK, V>(&self, primary_key: &PK) -> Result<Option<V>, Error>
+    where
+        PK: Codec<PK>,
+        V: HasTable + HasPrimaryKey<'pk, PK> + Codec<V>
+    {
+        let table_name = self.namespace.table_name(V::table_name());
+        let primary_key_bytes = PK::serialize(primary_key)?;
+
+        let context = self.record_context(&table_name);
+
+        let mut rewound = self.rewound(&table_name, Some(&primary_key_bytes))?;
+        if let Some(value) = rewound.remove(&primary_key_bytes) {
+            return value.map(|value| context.open(&primary_key_bytes, &value)).transpose();
+        }
+
+        let Some(primary_table) = self.open_primary_table(&table_name)? else { return Ok(None) };
+        if let Some(value) = primary_table.get(&*primary_key_bytes)? {
+            Ok(Some(context.open(&primary_key_bytes, value.value())?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Visits every record of type `V` in the primary table, as it was at the view's point, in
+    /// primary key order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    ///
+    /// * Decoding any value or change log entry fails, or
+    /// * If a storage error occurs.
+    pub fn for_each<V>(&self, mut visitor: impl FnMut(V)) -> Result<(), Error>
+    where
+        V: HasTable + Codec<V>
+    {
+        let table_name = self.namespace.table_name(V::table_name());
+        let context = self.record_context(&table_name);
+        let mut rewound = self.rewound(&table_name, None)?.into_iter().peekable();
+
+        if let Some(primary_table) = self.open_primary_table(&table_name)? {
+            for entry in primary_table.iter()? {
+                let (key, value) = entry?;
+                while let Some((rewound_key, earlier)) =
+                    rewound.next_if(|(rewound_key, _)| rewound_key.as_slice() < key.value())
+                {
+                    if let Some(earlier) = earlier {
+                        visitor(context.open(&rewound_key, &earlier)?);
+                    }
+                }
+                match rewound.next_if(|(rewound_key, _)| rewound_key.as_slice() == key.value()) {
+                    Some((_, Some(earlier))) => visitor(context.open(key.value(), &earlier)?),
+                    Some((_, None)) => {},
+                    None => visitor(context.open(key.value(), value.value())?),
+                }
+            }
+        }
+
+        for (rewound_key, earlier) in rewound {
+            if let Some(earlier) = earlier {
+                visitor(context.open(&rewound_key, &earlier)?);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of records of type `V`, as it was at the view's point.
+    ///
+    /// # Errors
+    ///
+    /// * Returns an error if a change log entry could not be decoded, or a storage error occurs.
+    pub fn len<V: HasTable>(&self) -> Result<u64, Error> {
+        let table_name = self.namespace.table_name(V::table_name());
+        let rewound = self.rewound(&table_name, None)?;
+        let Some(primary_table) = self.open_primary_table(&table_name)? else {
+            return Ok(rewound.values().filter(|earlier| earlier.is_some()).count() as u64);
+        };
+
+        let mut len = primary_table.len()?;
+        for (key, earlier) in rewound {
+            match (primary_table.get(&*key)?.is_some(), earlier.is_some()) {
+                (true, false) => len -= 1,
+                (false, true) => len += 1,
+                _ => {},
+            }
+        }
+        Ok(len)
+    }
+
+    /// Returns `true` if there were no records of type `V` at the view's point.
+    ///
+    /// # Errors
+    ///
+    /// * See [`SnapshotView::len`].
+    pub fn is_empty<V: HasTable>(&self) -> Result<bool, Error> {
+        Ok(self.len::<V>()? == 0)
+    }
+
+    /// Discards the view, ending its read transaction.
+    ///
+    /// # Errors
+    ///
+    /// * Returns an error if `redb` fails to close the underlying transaction.
+    pub fn close(self) -> Result<(), Error> {
+        Ok(self.redb.close().map_err(Box::new)?)
+    }
+
+    /// Returns the context that records of the table with the given full name are decoded in.
+    fn record_context(&self, table_name: &str) -> RecordContext {
+        RecordContext::new(self.record_key.clone(), table_name)
+    }
+
+    /// Opens a primary table by its full name. Returns `None` if the table doesn't exist now.
+    fn open_primary_table(
+        &self,
+        table_name: &str,
+    ) -> Result<Option<PrimaryTable>, Error> {
+        match self.redb.open_table(TableDefinition::new(table_name)) {
+            Ok(table) => Ok(Some(table)),
+            Err(redb::TableError::TableDoesNotExist(_)) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Returns the records of the named table that were changed after the view's point, by primary
+    /// key, as they were at the point. `None` means the record didn't exist at the point. If `only`
+    /// is given, no other record is returned.
+    fn rewound(
+        &self,
+        table_name: &str,
+        only: Option<&[u8]>,
+    ) -> Result<BTreeMap<Vec<u8>, Option<Vec<u8>>>, Error> {
+        let change_log = self.redb.open_table(CHANGE_LOG_TABLE)?;
+        let is_wanted = |change: &Change| {
+            change.table == table_name && only.is_none_or(|key| change.key == key)
+        };
+
+        // Records changed after the point, which are missing until a change before it is found:
+        let mut rewound = BTreeMap::new();
+        for entry in change_log.range(self.sequence.saturating_add(1)..)? {
+            let (sequence, bytes) = entry?;
+            let change = Change::decode(sequence.value(), bytes.value())?;
+            if is_wanted(&change) {
+                rewound.insert(change.key, None);
+            }
+        }
+
+        let mut unresolved: BTreeSet<Vec<u8>> = rewound.keys().cloned().collect();
+        for entry in change_log.range(..=self.sequence)?.rev() {
+            if unresolved.is_empty() {
+                break;
+            }
+            let (sequence, bytes) = entry?;
+            let change = Change::decode(sequence.value(), bytes.value())?;
+            if is_wanted(&change) && unresolved.remove(&change.key) {
+                rewound.insert(change.key, change.value);
+            }
+        }
+
+        Ok(rewound)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl std::ops::Deref for Snapshot {
+    type Target = ReadTransaction;
+
+    /// Returns the snapshot's read transaction.
+    fn deref(&self) -> &Self::Target {
+        &self.txn
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "serialize-messagepack"))]
+mod tests {
+    use super::*;
+    use crate::indexing::PrimaryKey;
+    use crate::typed::change_log::{encode_change, last_sequence};
+
+    #[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+    struct Creature { id: u64, name: String }
+
+    #[cfg(feature = "serde-safety")]
+    unsafe impl crate::layers::serializers::SafeForSerde for Creature {}
+
+    impl HasTable for Creature {
+        fn table_name() -> &'static str { "creatures" }
+    }
+
+    impl HasPrimaryKey<'_, u64> for Creature {
+        fn primary_key(&self) -> PrimaryKey<'_, u64> {
+            PrimaryKey::new(&self.id)
+        }
+    }
+
+    const CREATURES: TableDefinition<&[u8], &[u8]> = TableDefinition::new("creatures");
+
+    #[test]
+    fn rewinds_records_changed_after_the_point() {
+        let db = redb::Builder::new()
+            .create_with_backend(redb::backends::InMemoryBackend::new())
+            .unwrap();
+        let creature = |id: u64, name: &str| Creature { id, name: name.into() };
+
+        // Writes or deletes a record and logs the change, as an index-aware write would:
+        let write = |id: u64, record: Option<Creature>| {
+            let key = u64::serialize(&id).unwrap();
+            let value = record.map(|record| Creature::serialize(&record).unwrap());
+            let txn = db.begin_write().unwrap();
+            let mut creatures = txn.open_table(CREATURES).unwrap();
+            match &value {
+                Some(value) => { creatures.insert(&*key, &**value).unwrap(); },
+                None => { creatures.remove(&*key).unwrap(); },
+            }
+            let mut change_log = txn.open_table(CHANGE_LOG_TABLE).unwrap();
+            let sequence = last_sequence(&change_log).unwrap() + 1;
+            let change = encode_change("creatures", &key, value.as_deref(), None);
+            change_log.insert(sequence, &*change).unwrap();
+            drop((creatures, change_log));
+            txn.commit().unwrap();
+        };
+
+        write(1, Some(creature(1, "Coyote")));
+        write(2, Some(creature(2, "Road Runner")));
+        write(3, Some(creature(3, "Tarantula")));
+        write(3, None);
+
+        // Point 4: then one creature is renamed, one deleted, and one added.
+        write(1, Some(creature(1, "Wile E. Coyote")));
+        write(2, None);
+        write(4, Some(creature(4, "Gila Monster")));
+
+        let view = SnapshotView::new(db.begin_read().unwrap(), 4);
+        assert_eq!(view.get::<u64, Creature>(&1).unwrap(), Some(creature(1, "Coyote")));
+        assert_eq!(view.get::<u64, Creature>(&2).unwrap(), Some(creature(2, "Road Runner")));
+        assert_eq!(view.get::<u64, Creature>(&3).unwrap(), None);
+        assert_eq!(view.get::<u64, Creature>(&4).unwrap(), None);
+        assert_eq!(view.len::<Creature>().unwrap(), 2);
+
+        let mut names = Vec::new();
+        view.for_each(|creature: Creature| names.push(creature.name)).unwrap();
+        assert_eq!(names, ["Coyote", "Road Runner"]);
+
+        let view = SnapshotView::new(db.begin_read().unwrap(), 0);
+        assert!(view.is_empty::<Creature>().unwrap());
+
+        // Writers aren't blocked while a view is open:
+        write(5, Some(creature(5, "Roadrunner")));
+        view.close().unwrap();
+    }
+
+    #[cfg(feature = "writes")]
+    #[test]
+    fn retained_snapshots_are_read_from_the_change_log() {
+        let mut db = crate::typed::database::Database::in_memory().unwrap();
+        assert!(matches!(db.retain_snapshot(), Err(Error::ChangeLogNotEnabled)));
+        db.enable_change_log().unwrap();
+
+        let snapshot_id = db.retain_snapshot().unwrap();
+        assert_eq!(db.snapshots().unwrap(), [snapshot_id]);
+        assert_eq!(db.read_at(snapshot_id).unwrap().sequence(), 0);
+        assert!(matches!(
+            db.read_at(ReadPoint::Sequence(9)),
+            Err(Error::SequenceNotReached { sequence: 9, last: 0 })
+        ));
+
+        assert!(db.release_snapshot(snapshot_id).unwrap());
+        assert!(db.snapshots().unwrap().is_empty());
+        assert!(matches!(db.read_at(snapshot_id), Err(Error::SnapshotNotFound { snapshot: 1 })));
+    }
+
+    #[cfg(feature = "writes")]
+    #[test]
+    fn snapshots_know_their_age_and_sequence() {
+        use crate::typed::test_records::Animal;
+
+        let mut db = crate::typed::database::Database::in_memory().unwrap();
+        let snapshot = db.snapshot().unwrap();
+        assert_eq!(snapshot.sequence(), None);
+        assert!(snapshot.taken_at() <= SystemTime::now());
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(snapshot.age() >= Duration::from_millis(2));
+        assert!(snapshot.is_stale(Duration::from_millis(1)));
+        assert!(!snapshot.is_stale(Duration::from_hours(1)));
+        drop(snapshot);
+
+        db.enable_change_log().unwrap();
+        let insert = |animal: Animal| {
+            let mut txn = db.write().unwrap();
+            txn.bulk_insert::<u64, Animal>([animal]).unwrap();
+            txn.commit().unwrap();
+        };
+        insert(Animal::new(1, "Lion", "Savannah"));
+        let before = db.snapshot().unwrap();
+        insert(Animal::new(2, "Zebra", "Savannah"));
+        let after = db.snapshot().unwrap();
+        assert_eq!(after.sequence(), before.sequence().map(|sequence| sequence + 1));
+
+        // The earlier snapshot still reads the database as it was when it was taken:
+        let txn = before.into_transaction();
+        assert!(txn.get::<u64, Animal>(&1).unwrap().is_some());
+        assert_eq!(txn.get::<u64, Animal>(&2).unwrap(), None);
+    }
+}
@@ -0,0 +1,38 @@
+//! Field formatting for the `tracing` spans that wrap table operations and queries.
+
+use crate::DisplayKey;
+use std::fmt::{Display, Formatter};
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Shows a serialized key in a span's `key` field, as lowercase hexadecimal.
+///
+/// Keys longer than 32 bytes are truncated and end with `…`, so that large composite keys don't
+/// bloat traces.
+pub struct KeyField<'k>(pub &'k [u8]);
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl Display for KeyField<'_> {
+    /// Formats the key's bytes as lowercase hexadecimal, like [`DisplayKey`].
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        DisplayKey(self.0).fmt(f)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_shown_as_truncated_hex() {
+        assert_eq!(KeyField(&[0x00, 0xAB, 0x7F]).to_string(), "00ab7f");
+        assert_eq!(KeyField(&[0xFF; 40]).to_string(), format!("{}…", "ff".repeat(32)));
+    }
+}
@@ -0,0 +1,444 @@
+//! One serializable snapshot of everything that's worth monitoring about a database.
+//!
+//! Storage statistics live in `redb`, index statistics in an internal table, repair totals on the
+//! database handle, and layer totals in the metrics module.
+//! [`Database::stats_report`](crate::typed::database::Database::stats_report) gathers all of them
+//! into a [`StatsReport`], so that a server can expose them from one place: as JSON through
+//! `serde`, or in the Prometheus text format with [`StatsReport::to_prometheus`]. The embedded
+//! [`Server`](crate::typed::server::Server) answers `GET /metrics` with the latter.
+
+use crate::Error;
+use crate::indexing::{IndexStats, STATS_TABLE_NAME};
+use crate::layers::correctors::RepairTotals;
+#[cfg(feature = "metrics")]
+use crate::layers::metrics::{self, LayerTotals, layer_totals};
+use crate::typed::Namespace;
+use redb::{ReadableTable, ReadableTableMetadata, TableDefinition, TableHandle};
+use redb::MultimapTableHandle;
+use std::fmt::Write;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A snapshot of a database's storage, table, index, cache, repair, and layer statistics.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let report = db.stats_report()?;
+/// println!("{} fragmented bytes", report.storage.fragmented_bytes);
+/// let prometheus_text = report.to_prometheus();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(not(feature = "metrics"), derive(Eq))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StatsReport {
+    /// Storage statistics for the whole database file.
+    pub storage: StorageStats,
+
+    /// Statistics for every table, including indexes and internal tables, in name order.
+    pub tables: Vec<TableReport>,
+
+    /// Statistics for every index that has been analyzed, in every namespace.
+    pub indexes: Vec<IndexReport>,
+
+    /// Statistics for `redb`'s page cache.
+    pub cache: CacheReport,
+
+    /// Read-repair totals for every table that had a value repaired since the process started.
+    #[cfg(feature = "correctors")]
+    pub repairs: Vec<RepairReport>,
+
+    /// Running totals for every layer since the process started.
+    #[cfg(feature = "metrics")]
+    pub layers: Vec<LayerTotals>,
+}
+
+/// Storage statistics for the whole database file, as reported by `redb`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StorageStats {
+    /// The deepest b-tree traversal needed to reach a value, across all tables.
+    pub tree_height: u32,
+
+    /// The number of pages allocated in the file.
+    pub allocated_pages: u64,
+
+    /// The number of leaf pages that store user data.
+    pub leaf_pages: u64,
+
+    /// The number of branch pages in the b-trees that store user data.
+    pub branch_pages: u64,
+
+    /// The bytes of keys and values stored in leaf pages.
+    pub stored_bytes: u64,
+
+    /// The bytes used by `redb`'s own metadata.
+    pub metadata_bytes: u64,
+
+    /// The bytes that are allocated but unused, for example after deletes.
+    pub fragmented_bytes: u64,
+
+    /// The size of a page, in bytes.
+    pub page_size: usize,
+}
+
+/// Statistics for a single table.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TableReport {
+    /// The table's full name, including any namespace prefix.
+    pub name: String,
+
+    /// Whether the table is a multimap table.
+    pub multimap: bool,
+
+    /// The number of entries in the table.
+    pub entries: u64,
+
+    /// The deepest b-tree traversal needed to reach a value in the table.
+    pub tree_height: u32,
+
+    /// The number of leaf pages that store the table's data.
+    pub leaf_pages: u64,
+
+    /// The number of branch pages in the table's b-tree.
+    pub branch_pages: u64,
+
+    /// The bytes of keys and values stored in the table's leaf pages.
+    pub stored_bytes: u64,
+
+    /// The bytes used by `redb`'s metadata for the table.
+    pub metadata_bytes: u64,
+
+    /// The bytes that are allocated to the table but unused.
+    pub fragmented_bytes: u64,
+}
+
+/// Cardinality statistics for a single analyzed index.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct IndexReport {
+    /// The namespace the index is in, or `None` for the default namespace.
+    pub namespace: Option<String>,
+
+    /// The index's table name, without any namespace prefix.
+    pub index: String,
+
+    /// The index's statistics.
+    pub stats: IndexStats,
+}
+
+/// Statistics for `redb`'s page cache.
+///
+/// `redb` counts the pages it evicts from its cache, but not its hits or misses, so a steadily
+/// rising eviction count is the signal that the cache is too small for the working set.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CacheReport {
+    /// The number of pages evicted from the cache since the database was opened.
+    pub evictions: u64,
+}
+
+/// Read-repair totals for a single table.
+#[cfg(feature = "correctors")]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RepairReport {
+    /// The table's name.
+    pub table: &'static str,
+
+    /// The number of values that were repaired on read.
+    pub repairs: u64,
+
+    /// The total number of corrupted shards that were rebuilt.
+    pub shards_recovered: u64,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl StatsReport {
+    /// Renders the report in the Prometheus text exposition format, ready to be served on a
+    /// `/metrics` endpoint.
+    ///
+    /// Tables and indexes are labelled by `table` and `index`. Repair and layer totals use the same
+    /// names as the `metrics` feature's counters.
+    #[must_use]
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let storage = &self.storage;
+
+        let page_size = u64::try_from(storage.page_size).unwrap_or(u64::MAX);
+        for (name, help, value) in [
+            ("tree_height", "Deepest b-tree traversal", u64::from(storage.tree_height)),
+            ("allocated_pages", "Pages allocated in the file", storage.allocated_pages),
+            ("stored_bytes", "Bytes of keys and values stored", storage.stored_bytes),
+            ("metadata_bytes", "Bytes of redb metadata", storage.metadata_bytes),
+            ("fragmented_bytes", "Bytes allocated but unused", storage.fragmented_bytes),
+            ("page_size_bytes", "Size of a page", page_size),
+        ] {
+            let name = format!("atlatl_storage_{name}");
+            family(&mut out, &name, "gauge", help, [(String::new(), value)]);
+        }
+
+        let by_table = |value: fn(&TableReport) -> u64| {
+            self.tables
+                .iter()
+                .map(move |table| (format!("table=\"{}\"", escape(&table.name)), value(table)))
+        };
+        family(&mut out, "atlatl_table_entries", "gauge", "Entries in a table",
+            by_table(|table| table.entries));
+        family(&mut out, "atlatl_table_stored_bytes", "gauge", "Bytes stored by a table",
+            by_table(|table| table.stored_bytes));
+        family(&mut out, "atlatl_table_fragmented_bytes", "gauge", "Bytes unused by a table",
+            by_table(|table| table.fragmented_bytes));
+
+        let by_index = |value: fn(&IndexStats) -> u64| {
+            self.indexes.iter().map(move |index| {
+                let namespace = index.namespace.as_deref().unwrap_or_default();
+                let labels = format!(
+                    "namespace=\"{}\",index=\"{}\"",
+                    escape(namespace),
+                    escape(&index.index)
+                );
+                (labels, value(&index.stats))
+            })
+        };
+        family(&mut out, "atlatl_index_distinct_keys", "gauge", "Distinct keys in an index",
+            by_index(|stats| stats.distinct_keys));
+        family(&mut out, "atlatl_index_entries", "gauge", "Primary keys across an index's key sets",
+            by_index(|stats| stats.total_entries));
+
+        family(&mut out, "atlatl_cache_evictions_total", "counter", "Pages evicted from the cache",
+            [(String::new(), self.cache.evictions)]);
+
+        #[cfg(feature = "correctors")]
+        {
+            let by_table = |value: fn(&RepairReport) -> u64| {
+                self.repairs.iter().map(move |repair| {
+                    (format!("table=\"{}\"", escape(repair.table)), value(repair))
+                })
+            };
+            family(&mut out, "atlatl_ecc_repairs_total", "counter", "Values repaired on read",
+                by_table(|repair| repair.repairs));
+            family(&mut out, "atlatl_ecc_shards_recovered_total", "counter", "Shards rebuilt",
+                by_table(|repair| repair.shards_recovered));
+        }
+
+        #[cfg(feature = "metrics")]
+        self.write_layers(&mut out);
+
+        out
+    }
+
+    /// Renders the layer totals in the Prometheus text exposition format.
+    #[cfg(feature = "metrics")]
+    fn write_layers(&self, out: &mut String) {
+        let by_layer = |value: fn(&LayerTotals) -> u64| {
+            self.layers.iter().map(move |totals| {
+                (layer_labels(totals), value(totals))
+            })
+        };
+        family(out, metrics::LAYER_INPUT_BYTES, "counter", "Bytes passed into a storage layer",
+            by_layer(|totals| totals.input_bytes));
+        family(out, metrics::LAYER_OUTPUT_BYTES, "counter", "Bytes that came out of a layer",
+            by_layer(|totals| totals.output_bytes));
+        family(out, metrics::LAYER_ERRORS, "counter", "Values a storage layer failed to process",
+            by_layer(|totals| totals.errors));
+
+        let name = metrics::LAYER_DURATION_SECONDS;
+        let _ = writeln!(out, "# HELP {name} Time spent in a storage layer");
+        let _ = writeln!(out, "# TYPE {name} summary");
+        for totals in &self.layers {
+            let labels = layer_labels(totals);
+            let _ = writeln!(out, "{name}_sum{{{labels}}} {}", totals.seconds);
+            let _ = writeln!(out, "{name}_count{{{labels}}} {}", totals.values);
+        }
+    }
+}
+
+impl From<&redb::DatabaseStats> for StorageStats {
+    /// Copies `redb`'s database statistics.
+    fn from(stats: &redb::DatabaseStats) -> Self {
+        Self {
+            tree_height: stats.tree_height(),
+            allocated_pages: stats.allocated_pages(),
+            leaf_pages: stats.leaf_pages(),
+            branch_pages: stats.branch_pages(),
+            stored_bytes: stats.stored_bytes(),
+            metadata_bytes: stats.metadata_bytes(),
+            fragmented_bytes: stats.fragmented_bytes(),
+            page_size: stats.page_size(),
+        }
+    }
+}
+
+impl TableReport {
+    /// Copies `redb`'s statistics for a table.
+    fn new(name: String, multimap: bool, entries: u64, stats: &redb::TableStats) -> Self {
+        Self {
+            name,
+            multimap,
+            entries,
+            tree_height: stats.tree_height(),
+            leaf_pages: stats.leaf_pages(),
+            branch_pages: stats.branch_pages(),
+            stored_bytes: stats.stored_bytes(),
+            metadata_bytes: stats.metadata_bytes(),
+            fragmented_bytes: stats.fragmented_bytes(),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Gathers a [`StatsReport`] for a `redb` database.
+///
+/// Storage statistics are only available from a write transaction, which is begun and aborted
+/// without writing anything. Every other statistic is read from a single read transaction.
+///
+/// # Errors
+///
+/// * Returns [`Error::Corrupted`] if an index's stored statistics can't be decoded.
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+pub(crate) fn gather(redb: &redb::Database, repairs: &RepairTotals) -> Result<StatsReport, Error> {
+    let txn = redb.begin_write().map_err(Box::new)?;
+    let storage = StorageStats::from(&txn.stats()?);
+    txn.abort()?;
+
+    let txn = redb.begin_read().map_err(Box::new)?;
+
+    let mut tables = Vec::new();
+    let mut indexes = Vec::new();
+    for handle in txn.list_tables()? {
+        let name = handle.name().to_string();
+        let table = txn.open_untyped_table(handle)?;
+        tables.push(TableReport::new(name.clone(), false, table.len()?, &table.stats()?));
+
+        if let Some(namespace) = stats_table_namespace(&name) {
+            let stats_table = txn.open_table(TableDefinition::<&str, &[u8]>::new(&name))?;
+            for row in stats_table.iter()? {
+                let (index, stats) = row?;
+                indexes.push(IndexReport {
+                    namespace: namespace.map(str::to_string),
+                    index: index.value().to_string(),
+                    stats: IndexStats::from_bytes(stats.value())?,
+                });
+            }
+        }
+    }
+
+    for handle in txn.list_multimap_tables()? {
+        let name = handle.name().to_string();
+        let table = txn.open_untyped_multimap_table(handle)?;
+        tables.push(TableReport::new(name, true, table.len()?, &table.stats()?));
+    }
+
+    tables.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(StatsReport {
+        storage,
+        tables,
+        indexes,
+        cache: CacheReport { evictions: redb.cache_stats().evictions() },
+        #[cfg(feature = "correctors")]
+        repairs: repairs
+            .all()
+            .into_iter()
+            .map(|(table, stats)| RepairReport {
+                table,
+                repairs: stats.repairs,
+                shards_recovered: stats.shards_recovered,
+            })
+            .collect(),
+        #[cfg(feature = "metrics")]
+        layers: layer_totals(),
+    })
+}
+
+/// Returns `Some` with the namespace of an index statistics table, which is `None` for the default
+/// namespace, or returns `None` if the table isn't an index statistics table.
+#[allow(clippy::option_option, reason = "the default namespace has no name")]
+fn stats_table_namespace(table_name: &str) -> Option<Option<&str>> {
+    if table_name == STATS_TABLE_NAME {
+        return Some(None);
+    }
+
+    table_name
+        .strip_suffix(STATS_TABLE_NAME)?
+        .strip_suffix(Namespace::SEPARATOR)
+        .map(Some)
+}
+
+/// Writes one metric family in the Prometheus text exposition format. Each sample is its labels,
+/// without braces, and its value.
+fn family(
+    out: &mut String,
+    name: &str,
+    kind: &str,
+    help: &str,
+    samples: impl IntoIterator<Item = (String, u64)>,
+) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+    for (labels, value) in samples {
+        if labels.is_empty() {
+            let _ = writeln!(out, "{name} {value}");
+        } else {
+            let _ = writeln!(out, "{name}{{{labels}}} {value}");
+        }
+    }
+}
+
+/// Returns the `layer` and `operation` labels of a layer's totals.
+#[cfg(feature = "metrics")]
+fn layer_labels(totals: &LayerTotals) -> String {
+    format!("layer=\"{}\",operation=\"{}\"", totals.layer, totals.operation)
+}
+
+/// Escapes a Prometheus label value.
+fn escape(value: &str) -> String {
+    value.replace('\\', r"\\").replace('"', "\\\"").replace('\n', r"\n")
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statistics_tables_are_found_in_every_namespace() {
+        assert_eq!(stats_table_namespace(STATS_TABLE_NAME), Some(None));
+        assert_eq!(
+            stats_table_namespace(&format!("tenant_7.{STATS_TABLE_NAME}")),
+            Some(Some("tenant_7"))
+        );
+        assert_eq!(stats_table_namespace("creatures"), None);
+    }
+
+    #[test]
+    fn reports_render_as_prometheus_text() {
+        let report = StatsReport {
+            tables: vec![TableReport {
+                name: "creatures".into(),
+                entries: 3,
+                ..Default::default()
+            }],
+            cache: CacheReport { evictions: 12 },
+            ..Default::default()
+        };
+
+        let text = report.to_prometheus();
+        assert!(text.contains("# TYPE atlatl_table_entries gauge\n"));
+        assert!(text.contains("atlatl_table_entries{table=\"creatures\"} 3\n"));
+        assert!(text.contains("atlatl_cache_evictions_total 12\n"));
+        assert_eq!(escape("a\"b\\c"), "a\\\"b\\\\c");
+    }
+}
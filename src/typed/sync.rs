@@ -0,0 +1,606 @@
+//! Anti-entropy synchronization, which copies tables from a primary database to a replica by
+//! comparing Merkle digests of their key ranges, and only shipping the ranges that differ.
+//!
+//! The primary drives the sync with [`ReadTransaction::sync_to`], and the replica answers with
+//! [`WriteTransaction::sync_from`]. The two talk over a [`Transport`], which carries opaque frames,
+//! for example over a TCP stream or a message queue. [`channel`] provides an in-process transport.
+//!
+//! # Protocol
+//!
+//! 1. The primary sends its layer features, and the replica answers with its own. Stored bytes are
+//!    copied as-is, so both peers must be built with the same layers.
+//! 2. For every byte-keyed table in the primary's namespace, the primary sends the digest of the
+//!    table's full key range, and the replica answers whether its digest matches.
+//! 3. If a range differs and has at most [`SYNC_LEAF_ENTRIES`] entries, the primary ships the
+//!    range's entries. The replica replaces its entries in that range with them.
+//! 4. If a larger range differs, the primary splits it into [`SYNC_FANOUT`] smaller ranges and
+//!    compares each of them.
+//!
+//! Secondary index tables are byte-keyed tables too, so they're synchronized along with the
+//! records. The replica's tables that the primary doesn't have are left alone.
+//!
+//! # Bidirectional Sync
+//!
+//! Two peers that both accept writes are synchronized with [`WriteTransaction::sync_bidirectional`]
+//! and [`WriteTransaction::answer_bidirectional`], which compare the record tables registered with
+//! a [`Merger`]. Instead of shipping entries, the initiator ships its stamped versions of a
+//! differing range's records, including deletions. The other peer resolves every record that
+//! differs with its [`MergePolicy`](crate::typed::merge::MergePolicy), keeps the result, and sends
+//! back the versions the initiator must keep to match.
+//!
+//! # Frames
+//!
+//! All integers are little-endian, and all strings and byte strings are prefixed by their length
+//! as a `u32`. A range is a start bound and an end bound, each a flag byte that's followed by a key
+//! if it's `1`. Start bounds are inclusive and end bounds are exclusive.
+//!
+//! ```text
+//! hello:    HELLO (u8) | layer count (u32) | layer feature (string) ...
+//! digest:   DIGEST (u8) | table name (string) | range | entry count (u64) | root (32 bytes)
+//! matches:  MATCHES (u8) | matched (u8)
+//! entries:  ENTRIES (u8) | table name (string) | range | entry count (u32) | key | value ...
+//! applied:  APPLIED (u8) | removed entry count (u64)
+//! versions: VERSIONS (u8) | table name (string) | range | version count (u32) | version ...
+//! resolved: RESOLVED (u8) | version count (u32) | version ...
+//! done:     DONE (u8)
+//!
+//! version:  key | stamp (12 bytes) | deleted (u8) | value, if not deleted
+//! ```
+//!
+//! [`ReadTransaction::sync_to`]: crate::typed::transaction::ReadTransaction::sync_to
+//! [`WriteTransaction::sync_from`]: crate::typed::transaction::WriteTransaction::sync_from
+//! [`WriteTransaction::sync_bidirectional`]:
+//!     crate::typed::transaction::WriteTransaction::sync_bidirectional
+//! [`WriteTransaction::answer_bidirectional`]:
+//!     crate::typed::transaction::WriteTransaction::answer_bidirectional
+//! [`Merger`]: crate::typed::merge::Merger
+
+use crate::Error;
+use crate::typed::archive::layer_features;
+use crate::typed::digest::TableDigest;
+use crate::typed::merge::{HybridTimestamp, STAMP_LEN};
+use redb::ReadableTable;
+use std::ops::Bound;
+use std::sync::mpsc::{Receiver, Sender};
+
+// -------------------------------------------------------------------------------------------------
+
+/// Ranges with at most this many entries on the primary are shipped whole when they differ, rather
+/// than split further.
+pub const SYNC_LEAF_ENTRIES: u64 = 128;
+
+/// The number of smaller ranges a differing range is split into.
+pub const SYNC_FANOUT: u64 = 16;
+
+const TAG_DONE: u8 = 0;
+const TAG_HELLO: u8 = 1;
+const TAG_DIGEST: u8 = 2;
+const TAG_MATCHES: u8 = 3;
+const TAG_ENTRIES: u8 = 4;
+const TAG_APPLIED: u8 = 5;
+const TAG_VERSIONS: u8 = 6;
+const TAG_RESOLVED: u8 = 7;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Carries sync frames between a primary and a replica.
+///
+/// Frames must be delivered whole and in order. Each side sends a frame, then waits for the
+/// other's answer, so a transport doesn't need to buffer more than one frame in each direction.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// struct Tcp(std::net::TcpStream);
+///
+/// impl Transport for Tcp {
+///     fn send(&mut self, frame: Vec<u8>) -> Result<(), Error> {
+///         self.0.write_all(&(frame.len() as u32).to_le_bytes())?;
+///         Ok(self.0.write_all(&frame)?)
+///     }
+///
+///     fn recv(&mut self) -> Result<Vec<u8>, Error> {
+///         let mut len = [0; 4];
+///         self.0.read_exact(&mut len)?;
+///         let mut frame = vec![0; u32::from_le_bytes(len) as usize];
+///         self.0.read_exact(&mut frame)?;
+///         Ok(frame)
+///     }
+/// }
+/// ```
+pub trait Transport {
+    /// Sends a frame to the peer.
+    ///
+    /// # Errors
+    ///
+    /// * The frame couldn't be delivered. Usually [`Error::Io`].
+    fn send(&mut self, frame: Vec<u8>) -> Result<(), Error>;
+
+    /// Waits for the next frame from the peer.
+    ///
+    /// # Errors
+    ///
+    /// * The peer disconnected, or the frame couldn't be received. Usually [`Error::Io`].
+    fn recv(&mut self) -> Result<Vec<u8>, Error>;
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// One end of an in-process [`Transport`], created by [`channel`]. Useful for keeping a replica in
+/// the same process, and for testing.
+#[derive(Debug)]
+pub struct ChannelTransport {
+    sender: Sender<Vec<u8>>,
+    receiver: Receiver<Vec<u8>>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A summary of a sync, returned to both the primary and the replica.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SyncReport {
+    /// Number of tables compared.
+    pub tables: u64,
+
+    /// Number of key ranges whose digests were compared, including each table's full range.
+    pub ranges_compared: u64,
+
+    /// Number of differing key ranges whose entries were shipped to the replica.
+    pub ranges_shipped: u64,
+
+    /// Number of entries shipped to the replica. Entries in a shipped range are shipped even if
+    /// the replica already had them.
+    pub entries_shipped: u64,
+
+    /// Number of entries the replica removed because the primary doesn't have them.
+    pub entries_removed: u64,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A range of serialized keys. `None` bounds are unbounded, start bounds are inclusive, and end
+/// bounds are exclusive.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct KeyRange {
+    pub(crate) start: Option<Vec<u8>>,
+    pub(crate) end: Option<Vec<u8>>,
+}
+
+/// A peer's stamped version of a record, shipped during a bidirectional sync.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct ShippedVersion {
+    pub(crate) key: Vec<u8>,
+    pub(crate) value: Option<Vec<u8>>,
+    pub(crate) stamp: HybridTimestamp,
+}
+
+/// A message between sync peers.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Frame {
+    /// The sender's layer features.
+    Hello { layers: Vec<String> },
+
+    /// The primary's digest of a table's key range.
+    Digest { table: String, range: KeyRange, digest: TableDigest },
+
+    /// Whether the replica's digest of the range matched.
+    Matches(bool),
+
+    /// Every entry of a table's key range on the primary, in key order.
+    Entries { table: String, range: KeyRange, entries: Vec<(Vec<u8>, Vec<u8>)> },
+
+    /// The replica applied the shipped entries, and removed this many of its own.
+    Applied { removed: u64 },
+
+    /// Every stamped version of a table's records in a key range on the initiator, including
+    /// deletions, in key order.
+    Versions { table: String, range: KeyRange, versions: Vec<ShippedVersion> },
+
+    /// The resolved versions the initiator must keep, for the records where its shipped version
+    /// didn't win.
+    Resolved { versions: Vec<ShippedVersion> },
+
+    /// Every table has been compared.
+    Done,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl SyncReport {
+    /// Returns `true` if the replica was already identical to the primary.
+    #[must_use]
+    pub const fn was_in_sync(&self) -> bool {
+        self.ranges_shipped == 0
+    }
+}
+
+impl KeyRange {
+    /// Returns the range as bounds, for use with `redb`'s `range` methods.
+    pub(crate) fn bounds(&self) -> (Bound<&[u8]>, Bound<&[u8]>) {
+        (
+            self.start.as_deref().map_or(Bound::Unbounded, Bound::Included),
+            self.end.as_deref().map_or(Bound::Unbounded, Bound::Excluded),
+        )
+    }
+
+    /// Splits the range at the given keys, which must be in order and within the range.
+    pub(crate) fn split(self, boundaries: Vec<Vec<u8>>) -> Vec<Self> {
+        let mut ranges = Vec::with_capacity(boundaries.len() + 1);
+        let mut start = self.start;
+        for boundary in boundaries {
+            ranges.push(Self { start, end: Some(boundary.clone()) });
+            start = Some(boundary);
+        }
+        ranges.push(Self { start, end: self.end });
+        ranges
+    }
+}
+
+impl Frame {
+    /// Encodes the frame for a [`Transport`].
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::SyncProtocol`] if a field is larger than 4 GiB.
+    pub(crate) fn encode(&self) -> Result<Vec<u8>, Error> {
+        let mut frame = Vec::new();
+        match self {
+            Self::Hello { layers } => {
+                frame.push(TAG_HELLO);
+                frame.extend_from_slice(&len_u32(layers.len())?.to_le_bytes());
+                for layer in layers {
+                    put_prefixed(&mut frame, layer.as_bytes())?;
+                }
+            },
+            Self::Digest { table, range, digest } => {
+                frame.push(TAG_DIGEST);
+                put_prefixed(&mut frame, table.as_bytes())?;
+                put_range(&mut frame, range)?;
+                frame.extend_from_slice(&digest.entries.to_le_bytes());
+                frame.extend_from_slice(&digest.root);
+            },
+            Self::Matches(matched) => frame.extend_from_slice(&[TAG_MATCHES, u8::from(*matched)]),
+            Self::Entries { table, range, entries } => {
+                frame.push(TAG_ENTRIES);
+                put_prefixed(&mut frame, table.as_bytes())?;
+                put_range(&mut frame, range)?;
+                frame.extend_from_slice(&len_u32(entries.len())?.to_le_bytes());
+                for (key, value) in entries {
+                    put_prefixed(&mut frame, key)?;
+                    put_prefixed(&mut frame, value)?;
+                }
+            },
+            Self::Applied { removed } => {
+                frame.push(TAG_APPLIED);
+                frame.extend_from_slice(&removed.to_le_bytes());
+            },
+            Self::Versions { table, range, versions } => {
+                frame.push(TAG_VERSIONS);
+                put_prefixed(&mut frame, table.as_bytes())?;
+                put_range(&mut frame, range)?;
+                put_versions(&mut frame, versions)?;
+            },
+            Self::Resolved { versions } => {
+                frame.push(TAG_RESOLVED);
+                put_versions(&mut frame, versions)?;
+            },
+            Self::Done => frame.push(TAG_DONE),
+        }
+        Ok(frame)
+    }
+
+    /// Decodes a frame received from a [`Transport`].
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::SyncProtocol`] if the frame is truncated or malformed.
+    pub(crate) fn decode(mut frame: &[u8]) -> Result<Self, Error> {
+        let frame = &mut frame;
+        let decoded = match take_u8(frame)? {
+            TAG_HELLO => {
+                let count = u32::from_le_bytes(take_array(frame)?);
+                let layers = (0..count)
+                    .map(|_| take_string(frame))
+                    .collect::<Result<Vec<String>, Error>>()?;
+                Self::Hello { layers }
+            },
+            TAG_DIGEST => Self::Digest {
+                table: take_string(frame)?,
+                range: take_range(frame)?,
+                digest: TableDigest {
+                    entries: u64::from_le_bytes(take_array(frame)?),
+                    root: take_array(frame)?,
+                },
+            },
+            TAG_MATCHES => Self::Matches(take_u8(frame)? != 0),
+            TAG_ENTRIES => {
+                let table = take_string(frame)?;
+                let range = take_range(frame)?;
+                let count = u32::from_le_bytes(take_array(frame)?);
+                let entries = (0..count)
+                    .map(|_| Ok((take_prefixed(frame)?, take_prefixed(frame)?)))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                Self::Entries { table, range, entries }
+            },
+            TAG_APPLIED => Self::Applied { removed: u64::from_le_bytes(take_array(frame)?) },
+            TAG_VERSIONS => Self::Versions {
+                table: take_string(frame)?,
+                range: take_range(frame)?,
+                versions: take_versions(frame)?,
+            },
+            TAG_RESOLVED => Self::Resolved { versions: take_versions(frame)? },
+            TAG_DONE => Self::Done,
+            _ => return Err(Error::SyncProtocol { reason: "unrecognized frame tag" }),
+        };
+
+        if frame.is_empty() {
+            Ok(decoded)
+        } else {
+            Err(Error::SyncProtocol { reason: "frame has trailing bytes" })
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl Transport for ChannelTransport {
+    fn send(&mut self, frame: Vec<u8>) -> Result<(), Error> {
+        self.sender.send(frame).map_err(|_| disconnected())
+    }
+
+    fn recv(&mut self) -> Result<Vec<u8>, Error> {
+        self.receiver.recv().map_err(|_| disconnected())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Creates a pair of connected in-process transports: give one to the primary's
+/// [`ReadTransaction::sync_to`], and the other to the replica's [`WriteTransaction::sync_from`],
+/// on different threads.
+///
+/// [`ReadTransaction::sync_to`]: crate::typed::transaction::ReadTransaction::sync_to
+/// [`WriteTransaction::sync_from`]: crate::typed::transaction::WriteTransaction::sync_from
+#[must_use]
+pub fn channel() -> (ChannelTransport, ChannelTransport) {
+    let (primary_sender, replica_receiver) = std::sync::mpsc::channel();
+    let (replica_sender, primary_receiver) = std::sync::mpsc::channel();
+    (
+        ChannelTransport { sender: primary_sender, receiver: primary_receiver },
+        ChannelTransport { sender: replica_sender, receiver: replica_receiver },
+    )
+}
+
+/// Returns the keys that split a differing range of `entries` entries into [`SYNC_FANOUT`] evenly
+/// sized smaller ranges. Every smaller range has entries, so repeated splitting ends.
+///
+/// # Errors
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+pub(crate) fn split_points(
+    table: &impl ReadableTable<&'static [u8], &'static [u8]>,
+    range: &KeyRange,
+    entries: u64,
+) -> Result<Vec<Vec<u8>>, Error> {
+    let step = entries / SYNC_FANOUT;
+    let mut boundaries = Vec::new();
+    for (position, entry) in (0..).zip(table.range::<&[u8]>(range.bounds())?) {
+        if position > 0 && position % step == 0 {
+            boundaries.push(entry?.0.value().to_vec());
+            if boundaries.len() as u64 == SYNC_FANOUT - 1 {
+                break;
+            }
+        }
+    }
+    Ok(boundaries)
+}
+
+/// Returns this build's layer features, for the hello frame.
+pub(crate) fn hello() -> Frame {
+    Frame::Hello { layers: layer_features() }
+}
+
+/// Checks that a peer's hello frame lists the same layer features as this build.
+///
+/// # Errors
+///
+/// * Returns [`Error::SyncLayerMismatch`] if the peer uses different layers.
+///
+/// * Returns [`Error::SyncProtocol`] if the frame isn't a hello frame.
+pub(crate) fn check_hello(frame: Frame) -> Result<(), Error> {
+    let Frame::Hello { layers: remote } = frame else {
+        return Err(Error::SyncProtocol { reason: "expected a hello frame" });
+    };
+    let local = layer_features();
+    if remote == local {
+        Ok(())
+    } else {
+        Err(Error::SyncLayerMismatch { remote: remote.join(", "), local: local.join(", ") })
+    }
+}
+
+/// Sends a frame, and then waits for the peer's answer.
+///
+/// # Errors
+///
+/// * Returns [`Error::SyncProtocol`] if the answer is malformed.
+///
+/// * The transport failed, see [`Transport`].
+pub(crate) fn exchange(transport: &mut impl Transport, frame: &Frame) -> Result<Frame, Error> {
+    transport.send(frame.encode()?)?;
+    Frame::decode(&transport.recv()?)
+}
+
+/// The error returned when the other end of a [`ChannelTransport`] has been dropped.
+fn disconnected() -> Error {
+    Error::Io(std::io::ErrorKind::BrokenPipe.into())
+}
+
+/// Converts a length into the `u32` used by the frame format.
+fn len_u32(len: usize) -> Result<u32, Error> {
+    u32::try_from(len).map_err(|_| Error::SyncProtocol { reason: "field is larger than 4 GiB" })
+}
+
+/// Writes a length-prefixed byte string.
+fn put_prefixed(frame: &mut Vec<u8>, field: &[u8]) -> Result<(), Error> {
+    frame.extend_from_slice(&len_u32(field.len())?.to_le_bytes());
+    frame.extend_from_slice(field);
+    Ok(())
+}
+
+/// Writes a key range's bounds.
+fn put_range(frame: &mut Vec<u8>, range: &KeyRange) -> Result<(), Error> {
+    for bound in [&range.start, &range.end] {
+        match bound {
+            Some(key) => {
+                frame.push(1);
+                put_prefixed(frame, key)?;
+            },
+            None => frame.push(0),
+        }
+    }
+    Ok(())
+}
+
+/// Writes a list of stamped versions.
+fn put_versions(frame: &mut Vec<u8>, versions: &[ShippedVersion]) -> Result<(), Error> {
+    frame.extend_from_slice(&len_u32(versions.len())?.to_le_bytes());
+    for version in versions {
+        put_prefixed(frame, &version.key)?;
+        frame.extend_from_slice(&version.stamp.to_bytes());
+        match &version.value {
+            Some(value) => {
+                frame.push(0);
+                put_prefixed(frame, value)?;
+            },
+            None => frame.push(1),
+        }
+    }
+    Ok(())
+}
+
+/// Takes `len` bytes from the front of a frame.
+const fn take<'f>(frame: &mut &'f [u8], len: usize) -> Result<&'f [u8], Error> {
+    if frame.len() < len {
+        return Err(Error::SyncProtocol { reason: "frame is truncated" });
+    }
+    let (field, rest) = frame.split_at(len);
+    *frame = rest;
+    Ok(field)
+}
+
+/// Takes a fixed-size field from the front of a frame.
+fn take_array<const N: usize>(frame: &mut &[u8]) -> Result<[u8; N], Error> {
+    let mut array = [0; N];
+    array.copy_from_slice(take(frame, N)?);
+    Ok(array)
+}
+
+/// Takes a byte from the front of a frame.
+fn take_u8(frame: &mut &[u8]) -> Result<u8, Error> {
+    Ok(take_array::<1>(frame)?[0])
+}
+
+/// Takes a length-prefixed byte string from the front of a frame.
+fn take_prefixed(frame: &mut &[u8]) -> Result<Vec<u8>, Error> {
+    let len = u32::from_le_bytes(take_array(frame)?) as usize;
+    Ok(take(frame, len)?.to_vec())
+}
+
+/// Takes a length-prefixed UTF-8 string from the front of a frame.
+fn take_string(frame: &mut &[u8]) -> Result<String, Error> {
+    String::from_utf8(take_prefixed(frame)?)
+        .map_err(|_| Error::SyncProtocol { reason: "string is not UTF-8" })
+}
+
+/// Takes a list of stamped versions from the front of a frame.
+fn take_versions(frame: &mut &[u8]) -> Result<Vec<ShippedVersion>, Error> {
+    let count = u32::from_le_bytes(take_array(frame)?);
+    (0..count)
+        .map(|_| {
+            let key = take_prefixed(frame)?;
+            let stamp = HybridTimestamp::from_bytes(&take_array::<STAMP_LEN>(frame)?)
+                .ok_or(Error::SyncProtocol { reason: "stamp is truncated" })?;
+            let value = match take_u8(frame)? {
+                0 => Some(take_prefixed(frame)?),
+                1 => None,
+                _ => return Err(Error::SyncProtocol { reason: "unrecognized deletion flag" }),
+            };
+            Ok(ShippedVersion { key, value, stamp })
+        })
+        .collect()
+}
+
+/// Takes a key range's bounds from the front of a frame.
+fn take_range(frame: &mut &[u8]) -> Result<KeyRange, Error> {
+    let mut bounds = [None, None];
+    for bound in &mut bounds {
+        *bound = match take_u8(frame)? {
+            0 => None,
+            1 => Some(take_prefixed(frame)?),
+            _ => return Err(Error::SyncProtocol { reason: "unrecognized range bound" }),
+        };
+    }
+    let [start, end] = bounds;
+    Ok(KeyRange { start, end })
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_round_trip() {
+        let range = KeyRange { start: Some(b"coyote".to_vec()), end: None };
+        let frames = [
+            Frame::Hello { layers: vec!["compress-lz4".to_string()] },
+            Frame::Digest {
+                table: "creatures".to_string(),
+                range: range.clone(),
+                digest: TableDigest { root: [7; 32], entries: 3 },
+            },
+            Frame::Matches(false),
+            Frame::Entries {
+                table: "creatures".to_string(),
+                range,
+                entries: vec![(b"1".to_vec(), b"Coyote".to_vec())],
+            },
+            Frame::Applied { removed: 2 },
+            Frame::Versions {
+                table: "creatures".to_string(),
+                range: KeyRange::default(),
+                versions: vec![ShippedVersion {
+                    key: b"2".to_vec(),
+                    value: None,
+                    stamp: HybridTimestamp { millis: 9, counter: 1, node: 3 },
+                }],
+            },
+            Frame::Resolved { versions: Vec::new() },
+            Frame::Done,
+        ];
+        for frame in frames {
+            let encoded = frame.encode().unwrap();
+            assert_eq!(Frame::decode(&encoded).unwrap(), frame);
+            assert!(Frame::decode(&encoded[..encoded.len() - 1]).is_err());
+        }
+    }
+
+    #[test]
+    fn ranges_split_at_boundaries() {
+        let ranges = KeyRange::default().split(vec![b"b".to_vec(), b"d".to_vec()]);
+        assert_eq!(ranges.len(), 3);
+        assert_eq!(ranges[0], KeyRange { start: None, end: Some(b"b".to_vec()) });
+        assert_eq!(ranges[1], KeyRange { start: Some(b"b".to_vec()), end: Some(b"d".to_vec()) });
+        assert_eq!(ranges[2], KeyRange { start: Some(b"d".to_vec()), end: None });
+    }
+}
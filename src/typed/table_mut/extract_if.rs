@@ -1,8 +1,11 @@
 //! An iterator that removes entries from a table where a predicate applied to decoded keys and
 //! values returns `true`.
 
+use std::cell::RefCell;
 use std::marker::PhantomData;
-use crate::layers::serializers::Codec;
+use std::rc::Rc;
+use crate::Codec;
+use crate::Error;
 
 // -------------------------------------------------------------------------------------------------
 
@@ -13,21 +16,53 @@ type RedbExtractIf<'e> = redb::ExtractIf<
     'e,                                         // Lifetime
     &'static [u8],                              // Key
     &'static [u8],                              // Value
-    Box<dyn FnMut(&[u8], &[u8]) -> bool + 'e>   // Function
+    RawPredicate<'e>                            // Function
 >;
 
+/// A type alias for a predicate over an entry's raw key and value bytes.
+pub type RawPredicate<'p> = Box<dyn FnMut(&[u8], &[u8]) -> bool + 'p>;
+
+/// A type alias for an iterator that removes every entry in a range, returned by
+/// `OrderedTable::drain_range`.
+pub type Drain<'e, K, V> = ExtractIf<'e, K, V, fn(&K, &V) -> bool>;
+
+/// A type alias for the decode errors collected by a predicate, and shared with the caller.
+pub type DecodeFailures = Rc<RefCell<Vec<Error>>>;
+
 /// An iterator that removes entries from a table where a predicate applied to decoded keys and
 /// values returns `true`.
 ///
 /// This is a typed wrapper over redb's [`ExtractIf`] that decodes key-value pairs using [`Codec`].
+///
+/// Entries that can't be decoded are never passed to the predicate, and are kept in the table.
+/// Once every matching entry has been yielded, the iterator yields an [`Error::Undecodable`] for
+/// each of them, so that corrupt entries aren't silently skipped.
 pub struct ExtractIf<'e, K, V, F>
 where
     F: for<'f> FnMut(&K, &V) -> bool,
 {
     inner: RedbExtractIf<'e>,
+    failures: DecodeFailures,
     _phantom: std::marker::PhantomData<(K, V, F)>,
 }
 
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<'e, K, V, F> ExtractIf<'e, K, V, F>
+where
+    K: Codec<K>,
+    V: Codec<V>,
+    F: for<'f> FnMut(&K, &V) -> bool
+{
+    /// Wraps a redb-based extract-if iterator, whose predicate collects its decode errors in
+    /// `failures`. See [`decoding_predicate`].
+    pub fn new(inner: RedbExtractIf<'e>, failures: DecodeFailures) -> Self {
+        Self { inner, failures, _phantom: PhantomData }
+    }
+}
+
 // -------------------------------------------------------------------------------------------------
 //
 // Trait Implementations
@@ -47,29 +82,57 @@ where
     /// # Errors
     ///
     /// * Returns an error if decoding the key or value fails.
+    ///
+    /// * Returns an [`Error::Undecodable`] for every entry that couldn't be decoded for the
+    ///   predicate, after the matching entries. These entries are kept.
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner
-            .next()
-            .map(|entry| entry
-                .map_err(Into::into)
-                .and_then(|(k, v)| Ok((
-                    K::deserialize(k.value())?,
-                    V::deserialize(v.value())?,
-                )))
-            )
+        let Some(entry) = self.inner.next() else {
+            let mut failures = self.failures.borrow_mut();
+            return (!failures.is_empty()).then(|| Err(failures.remove(0)));
+        };
+
+        Some(entry
+            .map_err(Into::into)
+            .and_then(|(k, v)| Ok((
+                K::deserialize(k.value())?,
+                V::deserialize(v.value())?,
+            )))
+        )
     }
 }
 
-impl<'e, K, V, F> From<RedbExtractIf<'e>> for ExtractIf<'e, K, V, F>
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Wraps a predicate over decoded keys and values into one over raw bytes, for redb's `retain` and
+/// `extract_if` methods.
+///
+/// Entries that can't be decoded aren't passed to `predicate`. The raw predicate answers
+/// `undecodable` for them instead, which should keep them in the table, and pushes an
+/// [`Error::Undecodable`] onto `failures`.
+pub fn decoding_predicate<'p, K, V>(
+    table_name: &str,
+    mut predicate: impl FnMut(&K, &V) -> bool + 'p,
+    undecodable: bool,
+    failures: DecodeFailures,
+) -> RawPredicate<'p>
 where
     K: Codec<K>,
     V: Codec<V>,
-    F: for<'f> FnMut(&K, &V) -> bool
 {
-    /// Converts a redb-based extract-if iterator into a typed [`ExtractIf`] with decoding logic.
-    ///
-    /// This allows `ExtractIf` to be used seamlessly with redb’s native iterator interface.
-    fn from(inner: RedbExtractIf<'e>) -> Self {
-        Self { inner, _phantom: PhantomData }
-    }
-}
\ No newline at end of file
+    let table_name = table_name.to_string();
+    Box::new(move |k: &[u8], v: &[u8]| -> bool {
+        match K::deserialize(k).and_then(|k_dec| Ok((k_dec, V::deserialize(v)?))) {
+            Ok((k_dec, v_dec)) => predicate(&k_dec, &v_dec),
+            Err(error) => {
+                failures.borrow_mut().push(Error::Undecodable {
+                    table: table_name.clone(),
+                    key: k.to_vec(),
+                    source: Box::new(error),
+                });
+                undecodable
+            },
+        }
+    })
+}
@@ -4,12 +4,19 @@ mod extract_if;
 mod ordered_table;
 mod range;
 
+pub use crate::typed::table_mut::extract_if::Drain;
 pub use crate::typed::table_mut::ordered_table::OrderedTable;
 
+use crate::defaults::Defaults;
 use crate::indexing::HasPrimaryKey;
-use crate::typed::table_mut::{extract_if::ExtractIf, range::Range};
-use crate::{Codec, Error};
-use redb::{ReadableTable, ReadableTableMetadata, TableHandle};
+use crate::typed::table_mut::extract_if::{DecodeFailures, ExtractIf, RawPredicate, decoding_predicate};
+use crate::typed::table_mut::range::Range;
+#[cfg(feature = "tracing-spans")]
+use crate::typed::KeyField;
+use crate::{Codec, Error, ErrorContext};
+use redb::{ReadableTable, TableHandle};
+#[cfg(feature = "redb-pass-through")]
+use redb::ReadableTableMetadata;
 use std::marker::PhantomData;
 
 // -------------------------------------------------------------------------------------------------
@@ -55,6 +62,10 @@ where
 //
 // Method Implementations
 
+#[cfg_attr(
+    not(feature = "redb-pass-through"),
+    allow(clippy::elidable_lifetime_names, reason = "`'txn` is only named by pass-through methods")
+)]
 impl<'txn, K, V> TableMut<'txn, K, V>
 where
     K: Codec<K>,
@@ -84,6 +95,9 @@ where
     /// Entries are only removed if they are yielded by the iterator. If the iterator is dropped
     /// early, any remaining entries are preserved.
     ///
+    /// Entries that can't be decoded aren't passed to the predicate, and are preserved. The
+    /// iterator yields an [`Error::Undecodable`] for each of them after the matching entries.
+    ///
     /// ## Predicate Behavior
     ///
     /// | Want to Remove Record? | Then Return |
@@ -101,32 +115,31 @@ where
     /// # Notes
     ///
     /// * This method call is passed-through to the `redb` Rust embedded database.
+    ///
+    /// * Only the primary table is changed, so the removed records' secondary index entries are
+    ///   left behind. Use `Transaction::extract_if` to remove them too.
     #[cfg(feature = "redb-pass-through")]
     #[inline]
     #[allow(clippy::type_complexity, reason="required for lifetime bounds")]
     pub fn extract_if<F>(
         &mut self,
-        mut predicate: F,
+        predicate: F,
     ) -> Result<ExtractIf<'_, K, V, F>, Error>
     where
         F: for<'f> FnMut(&K, &V) -> bool + 'txn,
     {
-        let closure: Box<dyn for<'a, 'b> FnMut(&'a [u8], &'b [u8]) -> bool> = Box::new(
-            move |k: &[u8], v: &[u8]| -> bool {
-                match (K::deserialize(k), V::deserialize(v)) {
-                    (Ok(k_dec), Ok(v_dec)) => predicate(&k_dec, &v_dec),
-                    _ => false,
-                }
-            }
-        );
-
-        Ok(self.redb_table.extract_if(closure)?.into())
+        let failures = DecodeFailures::default();
+        let table_name = self.redb_table.name().to_string();
+        let closure = decoding_predicate(&table_name, predicate, false, failures.clone());
+        Ok(ExtractIf::new(self.redb_table.extract_if(closure)?, failures))
     }
 
     /// Applies a predicate to each key-value pair in the table and retains only those for which the
     /// predicate returns `true`.
     ///
-    /// Entries for which the predicate returns `false` are removed.
+    /// Entries for which the predicate returns `false` are removed. Entries that can't be decoded
+    /// aren't passed to the predicate, and are kept. Returns an [`Error::Undecodable`] for each of
+    /// them, so that corrupt entries can be reported or repaired.
     ///
     /// ## Predicate Behavior
     ///
@@ -137,34 +150,29 @@ where
     ///
     /// # Errors
     ///
-    /// Returns an error if:
-    ///
-    /// * Decoding any key or value fails, or
-    /// * If a storage-level error occurs.
+    /// * Returns an error if a storage-level error occurs.
     ///
     /// # Notes
     ///
     /// * This method call is passed-through to the `redb` Rust embedded database.
+    ///
+    /// * Only the primary table is changed, so the removed records' secondary index entries are
+    ///   left behind. Use `Transaction::retain` to remove them too.
     #[cfg(feature = "redb-pass-through")]
     #[inline]
     #[allow(clippy::type_complexity, reason="required for lifetime bounds")]
     pub fn retain<F>(
         &mut self,
-        mut predicate: F,
-    ) -> Result<(), Error>
+        predicate: F,
+    ) -> Result<Vec<Error>, Error>
     where
         F: for<'f> FnMut(&K, &V) -> bool + 'txn,
     {
-        let closure: Box<dyn for<'a, 'b> FnMut(&'a [u8], &'b [u8]) -> bool> = Box::new(
-            move |k: &[u8], v: &[u8]| -> bool {
-                match (K::deserialize(k), V::deserialize(v)) {
-                    (Ok(k_dec), Ok(v_dec)) => predicate(&k_dec, &v_dec),
-                    _ => false,
-                }
-            }
-        );
-
-        Ok(self.redb_table.retain(closure)?)
+        let failures = DecodeFailures::default();
+        let table_name = self.redb_table.name().to_string();
+        let closure = decoding_predicate(&table_name, predicate, true, failures.clone());
+        self.redb_table.retain(closure)?;
+        Ok(failures.take())
     }
 
     /// Inserts a new key-value pair into the table, replacing any existing entry with the same key.
@@ -173,6 +181,8 @@ where
     ///
     /// Returns the previous value if the key already existed, or `None` if it was newly inserted.
     ///
+    /// The value's [`Defaults`] are applied before it's serialized.
+    ///
     /// # Errors
     ///
     /// Returns an error if:
@@ -180,17 +190,20 @@ where
     /// * Encoding the key or value fails,
     /// * Decoding the previous value fails (if any), or
     /// * Insertion fails due to storage-related issues.
-    pub fn insert(&mut self, key: &K, value: &V) -> Result<Option<V>, Error> {
-        let key_bytes = K::serialize(key)?;
-        let value_bytes = V::serialize(value)?;
-        if let Some(value) = self.redb_table.insert(
-            key_bytes.as_slice(),
-            value_bytes.as_slice()
-        )? {
-            Ok(Some(V::deserialize(value.value())?))
-        } else {
-            Ok(None)
-        }
+    pub fn insert(&mut self, key: &K, value: &V) -> Result<Option<V>, Error>
+    where
+        V: Defaults
+    {
+        let key_bytes = K::serialize(key).map_err(self.during("insert", None))?;
+        #[cfg(feature = "tracing-spans")]
+        let _span = tracing::debug_span!(
+            "atlatl.insert",
+            table = self.redb_table.name(),
+            key = %KeyField(&key_bytes),
+        )
+        .entered();
+        let previous = V::serialize(&value.with_defaults()).and_then(|value_bytes| self.insert_by_key_bytes(&key_bytes, &value_bytes));
+        previous.map_err(self.during("insert", Some(&key_bytes)))
     }
 
     /// Inserts a new value into the table, using the value's own primary key.
@@ -210,7 +223,7 @@ where
     pub fn insert_keyed<'v>(&mut self, value: &'v V) -> Result<Option<V>, Error>
     where
         K: 'v,
-        V: HasPrimaryKey<'v, K>
+        V: HasPrimaryKey<'v, K> + Defaults
     {
         let primary_key = value.primary_key();
         self.insert(primary_key.as_ref(), value)
@@ -225,7 +238,8 @@ where
     /// in the table, the value is replaced, and the previous value is discarded.
     ///
     /// This method is more efficient than inserting values one at a time, as it avoids repeated
-    /// internal checks and amortizes encoding costs.
+    /// internal checks and amortizes encoding costs. Every entry is encoded before any is written,
+    /// and entries are written in key order, which is the cheapest order for `redb`'s B-tree.
     ///
     /// # Errors
     ///
@@ -238,17 +252,29 @@ where
     ///
     /// * Duplicate keys within the iterator will be inserted in sequence — the final value wins.
     /// * Does **not** return previous values. Use individual inserts if you need them.
+    /// * Does **not** maintain secondary indexes. Use the write transaction's `bulk_insert` method
+    ///   for that, which also groups index updates so that each key set is written once per batch.
     pub fn bulk_insert(
         &mut self,
         entries: impl IntoIterator<Item = (K, V)>
-    ) -> Result<(), Error> {
-        for (key, value) in entries {
-            let key_bytes = K::serialize(&key)?;
-            let value_bytes = V::serialize(&value)?;
-            // We discard previous value for performance; user can call `insert` manually if needed
-            let _ = self.redb_table.insert(key_bytes.as_slice(), value_bytes.as_slice())?;
-        }
-        Ok(())
+    ) -> Result<(), Error>
+    where
+        V: Defaults
+    {
+        #[cfg(feature = "tracing-spans")]
+        let _span = tracing::debug_span!(
+            "atlatl.bulk_insert",
+            table = self.redb_table.name(),
+        )
+        .entered();
+        let encoded = entries
+            .into_iter()
+            .map(|(key, mut value)| {
+                value.apply_defaults();
+                Ok((K::serialize(&key)?, V::serialize(&value)?))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        self.insert_sorted(encoded)
     }
 
     /// Inserts multiple values into the table, replacing any existing entries with the same keys.
@@ -260,7 +286,8 @@ where
     /// in the table, the value is replaced, and the previous value is discarded.
     ///
     /// This method is more efficient than inserting values one at a time, as it avoids repeated
-    /// internal checks and amortizes encoding costs.
+    /// internal checks and amortizes encoding costs. Every entry is encoded before any is written,
+    /// and entries are written in key order, which is the cheapest order for `redb`'s B-tree.
     ///
     /// # Errors
     ///
@@ -279,12 +306,29 @@ where
     ) -> Result<(), Error>
     where
         K: 'v,
-        V: HasPrimaryKey<'v, K> + 'v
+        V: HasPrimaryKey<'v, K> + Defaults + 'v
     {
-        for value in entries {
-            let primary_key = value.primary_key();
-            let key_bytes = primary_key.to_bytes()?;
-            let value_bytes = V::serialize(value)?;
+        #[cfg(feature = "tracing-spans")]
+        let _span = tracing::debug_span!(
+            "atlatl.bulk_insert",
+            table = self.redb_table.name(),
+        )
+        .entered();
+        let encoded = entries
+            .into_iter()
+            .map(|value| {
+                let key_bytes = value.primary_key().to_bytes()?;
+                Ok((key_bytes, V::serialize(&value.with_defaults())?))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        self.insert_sorted(encoded)
+    }
+
+    /// Writes encoded entries in key order. The sort is stable, so the last of several entries
+    /// with the same key is written last, and wins.
+    fn insert_sorted(&mut self, mut encoded: Vec<(Vec<u8>, Vec<u8>)>) -> Result<(), Error> {
+        encoded.sort_by(|a, b| a.0.cmp(&b.0));
+        for (key_bytes, value_bytes) in &encoded {
             // We discard previous value for performance; user can call `insert` manually if needed
             let _ = self.redb_table.insert(key_bytes.as_slice(), value_bytes.as_slice())?;
         }
@@ -303,12 +347,16 @@ where
     /// * Decoding the removed value fails (if any), or
     /// * Removal fails due to storage-related issues.
     pub fn remove(&mut self, key: &K) -> Result<Option<V>, Error> {
-        let key_bytes = K::serialize(key)?;
-        if let Some(value) = self.redb_table.remove(key_bytes.as_slice())? {
-            Ok(Some(V::deserialize(value.value())?))
-        } else {
-            Ok(None)
-        }
+        let key_bytes = K::serialize(key).map_err(self.during("remove", None))?;
+        #[cfg(feature = "tracing-spans")]
+        let _span = tracing::debug_span!(
+            "atlatl.remove",
+            table = self.redb_table.name(),
+            key = %KeyField(&key_bytes),
+        )
+        .entered();
+        let removed = self.remove_by_key_bytes(&key_bytes);
+        removed.map_err(self.during("remove", Some(&key_bytes)))
     }
 
     /// Removes a key-value pair from the table.
@@ -341,11 +389,103 @@ where
     /// * Decoding the value fails (if any), or
     /// * A storage error occurs.
     pub fn get(&self, key: &K) -> Result<Option<V>, Error> {
-        let key_bytes = K::serialize(key)?;
-        if let Some(value) = self.redb_table.get(key_bytes.as_slice())? {
-            Ok(Some(V::deserialize(value.value())?))
-        } else {
-            Ok(None)
+        let key_bytes = K::serialize(key).map_err(self.during("get", None))?;
+        #[cfg(feature = "tracing-spans")]
+        let _span = tracing::debug_span!(
+            "atlatl.get",
+            table = self.redb_table.name(),
+            key = %KeyField(&key_bytes),
+        )
+        .entered();
+        self.get_by_key_bytes(&key_bytes).map_err(self.during("get", Some(&key_bytes)))
+    }
+
+    /// Retrieves the value associated with the given key or, if there isn't one, inserts the value
+    /// returned by `default_fn` and returns it.
+    ///
+    /// The key is encoded once, for both the lookup and the insert. `default_fn` is only called if
+    /// the key is missing. The inserted value's [`Defaults`] are applied before it's serialized,
+    /// and the returned value has them applied too.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    ///
+    /// * Encoding the key or the new value fails,
+    /// * Decoding the existing value fails (if any), or
+    /// * A storage error occurs.
+    pub fn get_or_insert_with(
+        &mut self,
+        key: &K,
+        default_fn: impl FnOnce() -> V,
+    ) -> Result<V, Error>
+    where
+        V: Defaults
+    {
+        let key_bytes = K::serialize(key).map_err(self.during("get_or_insert_with", None))?;
+        #[cfg(feature = "tracing-spans")]
+        let _span = tracing::debug_span!(
+            "atlatl.get_or_insert_with",
+            table = self.redb_table.name(),
+            key = %KeyField(&key_bytes),
+        )
+        .entered();
+        let value = self.get_by_key_bytes(&key_bytes).and_then(|existing| {
+            if let Some(value) = existing {
+                return Ok(value);
+            }
+            let mut value = default_fn();
+            value.apply_defaults();
+            let value_bytes = V::serialize(&value)?;
+            self.insert_by_key_bytes(&key_bytes, &value_bytes)?;
+            Ok(value)
+        });
+        value.map_err(self.during("get_or_insert_with", Some(&key_bytes)))
+    }
+
+    /// Reads and decodes the value stored under a serialized key, if any.
+    fn get_by_key_bytes(&self, key_bytes: &[u8]) -> Result<Option<V>, Error> {
+        match self.redb_table.get(key_bytes)? {
+            Some(value) => Ok(Some(V::deserialize(value.value())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Stores a serialized value under a serialized key, and decodes the value it replaced, if
+    /// any.
+    fn insert_by_key_bytes(
+        &mut self,
+        key_bytes: &[u8],
+        value_bytes: &[u8],
+    ) -> Result<Option<V>, Error> {
+        match self.redb_table.insert(key_bytes, value_bytes)? {
+            Some(value) => Ok(Some(V::deserialize(value.value())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Removes the value stored under a serialized key, and decodes it, if any.
+    fn remove_by_key_bytes(&mut self, key_bytes: &[u8]) -> Result<Option<V>, Error> {
+        match self.redb_table.remove(key_bytes)? {
+            Some(value) => Ok(Some(V::deserialize(value.value())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns a closure that attaches the operation, this table, and the key (if it was encoded)
+    /// to an error, for use with `map_err`.
+    fn during<'c, E: Into<Error>>(
+        &'c self,
+        operation: &'static str,
+        key_bytes: Option<&'c [u8]>,
+    ) -> impl FnOnce(E) -> Error + 'c {
+        move |error| {
+            let context = ErrorContext::new(operation).table(self.redb_table.name());
+            let context = match key_bytes {
+                Some(key_bytes) => context.key(key_bytes),
+                None => context,
+            };
+            error.into().during(context)
         }
     }
 
@@ -426,10 +566,85 @@ where
     ///
     /// # Examples
     ///
-    /// ```rust
+    /// ```rust,ignore
     /// let typed_table: TableMut<MyKey, MyValue> = raw_table.into();
     /// ```
     fn from(table: RawTable<'txn>) -> Self {
         Self { redb_table: table, _phantom: PhantomData }
     }
-}
\ No newline at end of file
+}
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "redb-pass-through"))]
+mod tests {
+    use super::*;
+    use crate::typed::database::Database;
+    use crate::typed::test_records::Animal;
+    use redb::TableDefinition;
+
+    const ANIMALS: TableDefinition<&[u8], &[u8]> = TableDefinition::new("animals");
+
+    /// Fills a table with two animals and, under key `2`, a value that isn't an animal.
+    fn fill(table: &mut TableMut<u64, Animal>) {
+        table.insert(&1, &Animal::new(1, "Lion", "Savannah")).unwrap();
+        table.insert(&3, &Animal::new(3, "Zebra", "Savannah")).unwrap();
+        let key = u64::serialize(&2).unwrap();
+        table.redb_table.insert(&*key, [0xC1].as_slice()).unwrap();
+    }
+
+    #[test]
+    fn retain_keeps_and_reports_undecodable_entries() {
+        let db = Database::in_memory().unwrap();
+        let txn = db.write().unwrap();
+        let mut table = TableMut::<u64, Animal>::new(txn.open_redb_table(ANIMALS).unwrap());
+        fill(&mut table);
+
+        let failures = table.retain(|_, animal| animal.name != "Lion").unwrap();
+        assert!(matches!(
+            failures.as_slice(),
+            [Error::Undecodable { table, .. }] if table == "animals"
+        ));
+        assert_eq!(failures[0].key_bytes(), Some(u64::serialize(&2).unwrap().as_slice()));
+        assert_eq!(table.len().unwrap(), 2);
+        assert_eq!(table.get(&1).unwrap(), None);
+    }
+
+    #[test]
+    fn extract_if_yields_undecodable_entries_last() {
+        let db = Database::in_memory().unwrap();
+        let txn = db.write().unwrap();
+        let mut table = TableMut::<u64, Animal>::new(txn.open_redb_table(ANIMALS).unwrap());
+        fill(&mut table);
+
+        let extracted: Vec<_> = table.extract_if(|_, _| true).unwrap().collect();
+        assert!(matches!(extracted.as_slice(), [
+            Ok((1, _)),
+            Ok((3, _)),
+            Err(Error::Undecodable { .. }),
+        ]));
+        assert_eq!(table.len().unwrap(), 1);
+    }
+
+    #[test]
+    fn get_or_insert_with_inserts_only_missing_keys() {
+        let db = Database::in_memory().unwrap();
+        let txn = db.write().unwrap();
+        let mut table = TableMut::<u64, Animal>::new(txn.open_redb_table(ANIMALS).unwrap());
+        let lion = Animal::new(1, "Lion", "Savannah");
+
+        let inserted = table.get_or_insert_with(&1, || lion.clone()).unwrap();
+        assert_eq!(inserted, lion);
+        assert_eq!(table.get(&1).unwrap(), Some(lion.clone()));
+
+        let mut called = false;
+        let existing = table.get_or_insert_with(&1, || {
+            called = true;
+            Animal::new(1, "Tiger", "Jungle")
+        }).unwrap();
+        assert!(!called);
+        assert_eq!(existing, lion);
+        assert_eq!(table.len().unwrap(), 1);
+    }
+}
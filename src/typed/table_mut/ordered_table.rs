@@ -3,8 +3,11 @@
 
 use crate::layers::serializers::OrderedWhenSerialized;
 use crate::typed::{ResultEntry, table_mut::{ExtractIf, Range, TableMut}};
+use crate::typed::table_mut::{DecodeFailures, Drain, RawPredicate, RawTable, decoding_predicate};
+use crate::typed::cursor::Cursor;
+use crate::typed::estimate::{estimate_count, RangeEstimate};
 use crate::{Codec, Error};
-use redb::ReadableTable;
+use redb::{ReadableTable, ReadableTableMetadata, TableHandle};
 
 // -------------------------------------------------------------------------------------------------
 //
@@ -30,7 +33,7 @@ use redb::ReadableTable;
 /// | `LockPoisoned`  | A panic occurred while holding a database lock    | Restart process or retry operation    |
 pub trait OrderedTable<'txn, K, V, KR>
 where
-    K: OrderedWhenSerialized + Codec<K> + for<'a> std::borrow::Borrow<&'a [u8]>,
+    K: for<'o> OrderedWhenSerialized<'o> + Codec<K> + for<'a> std::borrow::Borrow<&'a [u8]>,
     V: Codec<V>,
     KR: for<'a> std::borrow::Borrow<&'a [u8]>
 {
@@ -38,7 +41,9 @@ where
     /// iterator over entries that match the predicate. Matching entries are removed **only if the
     /// iterator yields them**.
     ///
-    /// Entries that are skipped by the iterator will remain in the table.
+    /// Entries that are skipped by the iterator will remain in the table. Entries that can't be
+    /// decoded aren't passed to the predicate, and remain too. The iterator yields an
+    /// [`Error::Undecodable`] for each of them after the matching entries.
     ///
     /// ## Predicate Behavior
     ///
@@ -62,6 +67,9 @@ where
     /// Retains only the entries for which the predicate returns `true` within the specified range.
     /// All entries for which the predicate returns `false` are removed from the table.
     ///
+    /// Entries that can't be decoded aren't passed to the predicate, and are kept. Returns an
+    /// [`Error::Undecodable`] for each of them.
+    ///
     /// ## Predicate Behavior
     ///
     /// | Want to Keep Record? | Then Return |
@@ -77,10 +85,36 @@ where
         &mut self,
         range: impl std::ops::RangeBounds<KR>,
         predicate: F
-    ) -> Result<(), Error>
+    ) -> Result<Vec<Error>, Error>
     where
         F: for<'f> Fn(&K, &V) -> bool + 'txn;
 
+    /// Removes every entry within the specified range in a single pass, without decoding them, and
+    /// returns the number of entries removed.
+    ///
+    /// This suits time-based retention on ordered keys, such as removing every event older than a
+    /// cut-off timestamp. Only this table is changed, so any secondary index entries of the removed
+    /// records are left behind.
+    ///
+    /// # Errors
+    ///
+    /// * See [# Errors](#errors) for possible failure conditions.
+    fn delete_range(&mut self, range: impl std::ops::RangeBounds<KR>) -> Result<u64, Error>;
+
+    /// Removes every entry within the specified range, and returns an iterator over the removed
+    /// entries in key order. Entries are removed **only if the iterator yields them**, like
+    /// [`OrderedTable::extract_from_if`].
+    ///
+    /// Entries that can't be decoded are removed too, and yielded as errors.
+    ///
+    /// # Errors
+    ///
+    /// * See [# Errors](#errors) for possible failure conditions.
+    fn drain_range(
+        &mut self,
+        range: impl std::ops::RangeBounds<KR>
+    ) -> Result<Drain<'_, K, V>, Error>;
+
     /// Removes and returns the first key-value pair in the table, if present.
     ///
     /// # Errors
@@ -130,6 +164,26 @@ where
         reason="`Range` does implement `Iterator`, clippy may be confused by lifetime elision"
     )]
     fn iter(&self) -> Result<Range<'_, K, V>, Error>;
+
+    /// Returns a cursor over the table, which seeks to a key and steps forward and backward from
+    /// it. See [`Cursor`].
+    fn cursor(&self) -> Cursor<'_, K, V, RawTable<'txn>>;
+
+    /// Returns an approximate count of the keys within the specified range, without iterating over
+    /// the whole range.
+    ///
+    /// Small ranges are counted exactly. Larger ranges are estimated from the table's length and
+    /// the position of the range's bounds in the key space. This is suitable for displaying
+    /// "about 12,400 results" in a pagination UI, or for estimating the cost of a scan. See
+    /// [`RangeEstimate`] for details on accuracy.
+    ///
+    /// # Errors
+    ///
+    /// * See [# Errors](#errors) for possible failure conditions.
+    fn estimate_count(
+        &self,
+        range: impl std::ops::RangeBounds<KR> + Clone
+    ) -> Result<RangeEstimate, Error>;
 }
 
 // -------------------------------------------------------------------------------------------------
@@ -138,7 +192,7 @@ where
 
 impl<'txn, K, V, KR> OrderedTable<'txn, K, V, KR> for TableMut<'txn, K, V>
 where
-    K: Codec<K> + for<'a> std::borrow::Borrow<&'a [u8]> + OrderedWhenSerialized,
+    K: Codec<K> + for<'a> std::borrow::Borrow<&'a [u8]> + for<'o> OrderedWhenSerialized<'o>,
     V: Codec<V>,
     KR: for<'a> std::borrow::Borrow<&'a [u8]>
 {
@@ -162,26 +216,23 @@ where
     fn extract_from_if<F>(
         &mut self,
         range: impl std::ops::RangeBounds<KR>,
-        mut predicate: F
+        predicate: F
     ) -> Result<ExtractIf<'_, K, V, F>, Error>
     where
         F: for<'f> FnMut(&K, &V) -> bool + 'txn,
     {
-        let closure: Box<dyn for<'a, 'b> FnMut(&'a [u8], &'b [u8]) -> bool> = Box::new(
-            move |k: &[u8], v: &[u8]| -> bool {
-                match (K::deserialize(k), V::deserialize(v)) {
-                    (Ok(k_dec), Ok(v_dec)) => predicate(&k_dec, &v_dec),
-                    _ => false,
-                }
-            }
-        );
-
-        Ok(self.redb_table.extract_from_if(range, closure)?.into())
+        let failures = DecodeFailures::default();
+        let table_name = self.redb_table.name().to_string();
+        let closure = decoding_predicate(&table_name, predicate, false, failures.clone());
+        Ok(ExtractIf::new(self.redb_table.extract_from_if(range, closure)?, failures))
     }
 
     /// Retains only the entries for which the predicate returns `true` within the specified range.
     /// All entries for which the predicate returns `false` are removed from the table.
     ///
+    /// Entries that can't be decoded aren't passed to the predicate, and are kept. Returns an
+    /// [`Error::Undecodable`] for each of them.
+    ///
     /// ## Predicate Behavior
     ///
     /// | Want to Keep Record? | Then Return |
@@ -197,20 +248,42 @@ where
         &mut self,
         range: impl std::ops::RangeBounds<KR>,
         predicate: F
-    ) -> Result<(), Error>
+    ) -> Result<Vec<Error>, Error>
     where
         F: for<'f> Fn(&K, &V) -> bool + 'txn
     {
-        let closure: Box<dyn for<'a, 'b> Fn(&'a [u8], &'b [u8]) -> bool> = Box::new(
-            move |k: &[u8], v: &[u8]| -> bool {
-                match (K::deserialize(k), V::deserialize(v)) {
-                    (Ok(k_dec), Ok(v_dec)) => predicate(&k_dec, &v_dec),
-                    _ => false,
-                }
-            }
-        );
-
-        Ok(self.redb_table.retain_in(range, closure)?)
+        let failures = DecodeFailures::default();
+        let table_name = self.redb_table.name().to_string();
+        let closure = decoding_predicate(&table_name, predicate, true, failures.clone());
+        self.redb_table.retain_in(range, closure)?;
+        Ok(failures.take())
+    }
+
+    /// Removes every entry within the specified range in a single pass, and returns the number of
+    /// entries removed.
+    ///
+    /// # Errors
+    ///
+    /// * See [# Errors](#errors) for possible failure conditions.
+    fn delete_range(&mut self, range: impl std::ops::RangeBounds<KR>) -> Result<u64, Error> {
+        let len_before = self.redb_table.len()?;
+        self.redb_table.retain_in(range, |_, _| false)?;
+        Ok(len_before - self.redb_table.len()?)
+    }
+
+    /// Removes every entry within the specified range, and returns an iterator over the removed
+    /// entries in key order.
+    ///
+    /// # Errors
+    ///
+    /// * See [# Errors](#errors) for possible failure conditions.
+    fn drain_range(
+        &mut self,
+        range: impl std::ops::RangeBounds<KR>
+    ) -> Result<Drain<'_, K, V>, Error> {
+        let closure: RawPredicate = Box::new(|_, _| true);
+        let failures = DecodeFailures::default();
+        Ok(ExtractIf::new(self.redb_table.extract_from_if(range, closure)?, failures))
     }
 
     /// Removes and returns the first key-value pair in the table, if present.
@@ -222,8 +295,8 @@ where
         self.redb_table
             .pop_first()?
             .map(|(k_guard, v_guard)| Ok::<_, Error>((
-                K::deserialize(k_guard.value())?,
-                V::deserialize(v_guard.value())?,
+                <K as Codec<K>>::deserialize(k_guard.value())?,
+                <V as Codec<V>>::deserialize(v_guard.value())?,
             )))
             .transpose()
     }
@@ -237,8 +310,8 @@ where
         self.redb_table
             .pop_last()?
             .map(|(k_guard, v_guard)| Ok::<_, Error>((
-                K::deserialize(k_guard.value())?,
-                V::deserialize(v_guard.value())?,
+                <K as Codec<K>>::deserialize(k_guard.value())?,
+                <V as Codec<V>>::deserialize(v_guard.value())?,
             )))
             .transpose()
     }
@@ -259,8 +332,8 @@ where
             .map(|entry| entry
                 .map_err(Into::into)
                 .and_then(|(k, v)| Ok::<_, Error>((
-                    K::deserialize(k.value())?,
-                    V::deserialize(v.value())?,
+                    <K as Codec<K>>::deserialize(k.value())?,
+                    <V as Codec<V>>::deserialize(v.value())?,
                 )))
             ))
     }
@@ -274,8 +347,8 @@ where
         self.redb_table
             .first()?
             .map(|(k_guard, v_guard)| Ok::<_, Error>((
-                K::deserialize(k_guard.value())?,
-                V::deserialize(v_guard.value())?,
+                <K as Codec<K>>::deserialize(k_guard.value())?,
+                <V as Codec<V>>::deserialize(v_guard.value())?,
             )))
             .transpose()
     }
@@ -289,8 +362,8 @@ where
         self.redb_table
             .last()?
             .map(|(k_guard, v_guard)| Ok::<_, Error>((
-                K::deserialize(k_guard.value())?,
-                V::deserialize(v_guard.value())?,
+                <K as Codec<K>>::deserialize(k_guard.value())?,
+                <V as Codec<V>>::deserialize(v_guard.value())?,
             )))
             .transpose()
     }
@@ -305,4 +378,95 @@ where
     ) -> Result<Range<'_, K, V>, Error> {
         Ok(self.redb_table.iter()?.into())
     }
-}
\ No newline at end of file
+
+    /// Returns a cursor over the table, which seeks to a key and steps forward and backward from
+    /// it. See [`Cursor`].
+    fn cursor(&self) -> Cursor<'_, K, V, RawTable<'txn>> {
+        Cursor::new(&self.redb_table)
+    }
+
+    /// Returns an approximate count of the keys within the specified range, without iterating over
+    /// the whole range.
+    ///
+    /// # Errors
+    ///
+    /// * See [# Errors](#errors) for possible failure conditions.
+    fn estimate_count(
+        &self,
+        range: impl std::ops::RangeBounds<KR> + Clone
+    ) -> Result<RangeEstimate, Error> {
+        estimate_count(&self.redb_table, range)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "redb-pass-through"))]
+mod tests {
+    use super::*;
+    use crate::typed::database::Database;
+    use crate::typed::test_records::Animal;
+    use redb::TableDefinition;
+    use serde::{Deserialize, Serialize};
+
+    const ANIMALS: TableDefinition<&[u8], &[u8]> = TableDefinition::new("animals");
+
+    /// The serialized form of each `Tick`: small integers are encoded as a single byte.
+    static TICKS: [&[u8]; 5] = [&[0], &[1], &[2], &[3], &[4]];
+
+    /// A key that can be borrowed as its own serialized bytes, for use in ranges.
+    #[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+    struct Tick(u8);
+
+    #[cfg(feature = "serde-safety")]
+    unsafe impl crate::layers::serializers::SafeForSerde for Tick {}
+
+    impl OrderedWhenSerialized<'_> for Tick {}
+
+    impl<'a> std::borrow::Borrow<&'a [u8]> for Tick {
+        fn borrow(&self) -> &&'a [u8] {
+            &TICKS[usize::from(self.0)]
+        }
+    }
+
+    /// Fills a table with one animal under each tick.
+    fn fill(table: &mut TableMut<Tick, Animal>) {
+        for tick in 0..5 {
+            let animal = Animal::new(u64::from(tick), "Meerkat", "Burrows");
+            table.insert(&Tick(tick), &animal).unwrap();
+        }
+    }
+
+    #[test]
+    fn delete_range_counts_the_removed_entries() {
+        let db = Database::in_memory().unwrap();
+        let txn = db.write().unwrap();
+        let mut table = TableMut::<Tick, Animal>::new(txn.open_redb_table(ANIMALS).unwrap());
+        fill(&mut table);
+
+        assert_eq!(table.delete_range(Tick(1)..Tick(3)).unwrap(), 2);
+        assert_eq!(table.delete_range(Tick(1)..Tick(3)).unwrap(), 0);
+        assert_eq!(table.len().unwrap(), 3);
+        assert!(table.get(&Tick(2)).unwrap().is_none());
+        assert!(table.get(&Tick(3)).unwrap().is_some());
+    }
+
+    #[test]
+    fn drain_range_yields_the_removed_entries_in_order() {
+        let db = Database::in_memory().unwrap();
+        let txn = db.write().unwrap();
+        let mut table = TableMut::<Tick, Animal>::new(txn.open_redb_table(ANIMALS).unwrap());
+        fill(&mut table);
+
+        let drained: Vec<_> = table
+            .drain_range(Tick(3)..)
+            .unwrap()
+            .map(|entry| entry.unwrap().0)
+            .collect();
+        assert_eq!(drained, [Tick(3), Tick(4)]);
+        assert_eq!(table.len().unwrap(), 3);
+        assert!(table.get(&Tick(4)).unwrap().is_none());
+    }
+}
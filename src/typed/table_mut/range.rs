@@ -1,6 +1,6 @@
 //! A double-ended iterator over a range of decoded key-value pairs in a table.
 
-use crate::{codecs::Codec, typed::{RedbRange, ResultEntry}};
+use crate::{Codec, typed::{RedbRange, ResultEntry}};
 use std::marker::PhantomData;
 
 // -------------------------------------------------------------------------------------------------
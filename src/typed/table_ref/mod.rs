@@ -3,10 +3,11 @@
 mod ordered_table;
 mod range;
 mod redb;
+mod salvage;
 
 pub use crate::typed::table_ref::ordered_table::OrderedTable;
 
-use crate::{Codec, Error, typed::table_ref::range::Range};
+use crate::{Codec, Error, ErrorContext, typed::table_ref::range::Range};
 use ::redb::TableHandle;
 
 // -------------------------------------------------------------------------------------------------
@@ -87,29 +88,26 @@ where
                     table_name: self.redb_table.name().to_string(),
                     key: key_bytes.to_vec(),
                 })
-                .and_then(|serialized| V::deserialize(serialized.value()).map_err(Error::from))
+                .and_then(|serialized| V::deserialize(serialized.value())
+                    .map_err(self.during("get", Some(key_bytes)))
+                )
             )
     }
 
-
-
-
-    pub(crate) fn get_many_by_key_bytes(
-        &self,
-        keys: impl Iterator<Item = Vec<u8>>,
-    ) -> impl Iterator<Item = Result<V, Error>> {
-        keys
-            .map(|key_bytes| {
-                self.redb_table
-                    .get(key_bytes.as_slice())
-                    .map_err(Error::from)
-                    .and_then(|result| result
-                        .ok_or_else(|| Error::NotFound {
-                            table_name: self.redb_table.name().to_string(),
-                            key: key_bytes.clone(),
-                        })
-                        .and_then(|serialized| V::deserialize(serialized.value()).map_err(Error::from))
-                    )
-            })
+    /// Returns a closure that attaches the operation, this table, and the key (if it was encoded)
+    /// to an error, for use with `map_err`.
+    fn during<'c, E: Into<Error>>(
+        &'c self,
+        operation: &'static str,
+        key_bytes: Option<&'c [u8]>,
+    ) -> impl FnOnce(E) -> Error + 'c {
+        move |error| {
+            let context = ErrorContext::new(operation).table(self.redb_table.name());
+            let context = match key_bytes {
+                Some(key_bytes) => context.key(key_bytes),
+                None => context,
+            };
+            error.into().during(context)
+        }
     }
 }
\ No newline at end of file
@@ -3,6 +3,9 @@
 
 use crate::layers::serializers::OrderedWhenSerialized;
 use crate::typed::{ResultEntry, table_ref::Range, TableRef};
+use crate::typed::cursor::Cursor;
+use crate::typed::table_ref::RawReadOnlyTable;
+use crate::typed::estimate::{estimate_count, RangeEstimate};
 use crate::{Codec, Error};
 use redb::ReadableTable;
 
@@ -30,7 +33,7 @@ use redb::ReadableTable;
 /// | `LockPoisoned`  | A panic occurred while holding a database lock    | Restart process or retry operation    |
 pub trait OrderedTable<K, V, KR>
 where
-    K: OrderedWhenSerialized + Codec<K> + for<'a> std::borrow::Borrow<&'a [u8]>,
+    K: for<'o> OrderedWhenSerialized<'o> + Codec<K> + for<'a> std::borrow::Borrow<&'a [u8]>,
     V: Codec<V>,
     KR: for<'a> std::borrow::Borrow<&'a [u8]>
 {
@@ -69,6 +72,26 @@ where
         reason="`Range` does implement `Iterator`, clippy may be confused by lifetime elision"
     )]
     fn iter(&self) -> Result<Range<'_, K, V>, Error>;
+
+    /// Returns a cursor over the table, which seeks to a key and steps forward and backward from
+    /// it. See [`Cursor`].
+    fn cursor(&self) -> Cursor<'_, K, V, RawReadOnlyTable>;
+
+    /// Returns an approximate count of the keys within the specified range, without iterating over
+    /// the whole range.
+    ///
+    /// Small ranges are counted exactly. Larger ranges are estimated from the table's length and
+    /// the position of the range's bounds in the key space. This is suitable for displaying
+    /// "about 12,400 results" in a pagination UI, or for estimating the cost of a scan. See
+    /// [`RangeEstimate`] for details on accuracy.
+    ///
+    /// # Errors
+    ///
+    /// * See [# Errors](#errors) for possible failure conditions.
+    fn estimate_count(
+        &self,
+        range: impl std::ops::RangeBounds<KR> + Clone
+    ) -> Result<RangeEstimate, Error>;
 }
 
 // -------------------------------------------------------------------------------------------------
@@ -77,7 +100,7 @@ where
 
 impl<K, V, KR> OrderedTable<K, V, KR> for TableRef<K, V>
 where
-    K: Codec<K> + for<'a> std::borrow::Borrow<&'a [u8]> + OrderedWhenSerialized,
+    K: Codec<K> + for<'a> std::borrow::Borrow<&'a [u8]> + for<'o> OrderedWhenSerialized<'o>,
     V: Codec<V>,
     KR: for<'a> std::borrow::Borrow<&'a [u8]>
 {
@@ -97,8 +120,8 @@ where
             .map(|entry| entry
                 .map_err(Into::into)
                 .and_then(|(k, v)| Ok::<_, Error>((
-                    K::deserialize(k.value())?,
-                    V::deserialize(v.value())?,
+                    <K as Codec<K>>::deserialize(k.value())?,
+                    <V as Codec<V>>::deserialize(v.value())?,
                 )))
             ))
     }
@@ -112,8 +135,8 @@ where
         self.redb_table
             .first()?
             .map(|(k_guard, v_guard)| Ok::<_, Error>((
-                K::deserialize(k_guard.value())?,
-                V::deserialize(v_guard.value())?,
+                <K as Codec<K>>::deserialize(k_guard.value())?,
+                <V as Codec<V>>::deserialize(v_guard.value())?,
             )))
             .transpose()
     }
@@ -127,8 +150,8 @@ where
         self.redb_table
             .last()?
             .map(|(k_guard, v_guard)| Ok::<_, Error>((
-                K::deserialize(k_guard.value())?,
-                V::deserialize(v_guard.value())?,
+                <K as Codec<K>>::deserialize(k_guard.value())?,
+                <V as Codec<V>>::deserialize(v_guard.value())?,
             )))
             .transpose()
     }
@@ -143,4 +166,23 @@ where
     ) -> Result<Range<'_, K, V>, Error> {
         Ok(self.redb_table.iter()?.into())
     }
-}
\ No newline at end of file
+
+    /// Returns a cursor over the table, which seeks to a key and steps forward and backward from
+    /// it. See [`Cursor`].
+    fn cursor(&self) -> Cursor<'_, K, V, RawReadOnlyTable> {
+        Cursor::new(&self.redb_table)
+    }
+
+    /// Returns an approximate count of the keys within the specified range, without iterating over
+    /// the whole range.
+    ///
+    /// # Errors
+    ///
+    /// * See [# Errors](#errors) for possible failure conditions.
+    fn estimate_count(
+        &self,
+        range: impl std::ops::RangeBounds<KR> + Clone
+    ) -> Result<RangeEstimate, Error> {
+        estimate_count(&self.redb_table, range)
+    }
+}
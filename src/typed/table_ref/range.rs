@@ -1,4 +1,5 @@
-use crate::{codecs::Codec, typed::{RedbRange, ResultEntry}};
+use crate::{Codec, typed::{RedbRange, ResultEntry}};
+use crate::typed::projection::{Projected, Projection};
 use std::marker::PhantomData;
 
 // -------------------------------------------------------------------------------------------------
@@ -13,6 +14,22 @@ pub struct Range<'r, K, V> {
     _phantom: PhantomData<(K, V)>,
 }
 
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<'r, K, V> Range<'r, K, V> {
+    /// Converts this range into an iterator that decodes each value into the lighter projection
+    /// type `P` instead of the full `V` record.
+    ///
+    /// This is useful for streaming large tables to list endpoints, where only a few of each
+    /// record's fields are needed. See [`Projection`] for how to define a projection.
+    #[must_use]
+    pub fn map_while_deserializing<P: Projection<V>>(self) -> Projected<'r, K, V, P> {
+        Projected::new(self.inner)
+    }
+}
+
 // -------------------------------------------------------------------------------------------------
 //
 // Trait Implementations
@@ -2,6 +2,12 @@
 
 use crate::Codec;
 use crate::typed::TableRef;
+#[cfg(feature = "redb-pass-through")]
+use crate::Error;
+#[cfg(feature = "redb-pass-through")]
+use redb::{ReadableTableMetadata, TableHandle};
+#[cfg(feature = "tracing-spans")]
+use crate::typed::KeyField;
 
 // -------------------------------------------------------------------------------------------------
 //
@@ -27,12 +33,22 @@ where
     #[cfg(feature = "redb-pass-through")]
     #[inline]
     pub fn get(&self, key: &K) -> Result<Option<V>, Error> {
-        let key_bytes = K::serialize(key)?;
-        if let Some(value) = self.redb_table.get(key_bytes.as_slice())? {
-            Ok(Some(V::deserialize(value.value())?))
-        } else {
-            Ok(None)
-        }
+        let key_bytes = K::serialize(key).map_err(self.during("get", None))?;
+        #[cfg(feature = "tracing-spans")]
+        let _span = tracing::debug_span!(
+            "atlatl.get",
+            table = self.redb_table.name(),
+            key = %KeyField(&key_bytes),
+        )
+        .entered();
+        let value = self.redb_table
+            .get(key_bytes.as_slice())
+            .map_err(Error::from)
+            .and_then(|value| value
+                .map(|value| V::deserialize(value.value()))
+                .transpose()
+            );
+        value.map_err(self.during("get", Some(&key_bytes)))
     }
 
     /// Returns storage usage statistics for the table.
@@ -112,7 +128,7 @@ where
     ///
     /// # Examples
     ///
-    /// ```rust
+    /// ```rust,ignore
     /// let typed_table: TableMut<MyKey, MyValue> = raw_table.into();
     /// ```
     fn from(table: redb::ReadOnlyTable<&[u8], &[u8]>) -> Self {
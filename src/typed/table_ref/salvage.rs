@@ -0,0 +1,82 @@
+//! Salvage of stored values that can't be decoded, for manual recovery.
+
+use crate::layers::core::{Bytes, Diagnostics};
+use crate::layers::encryptors::{AssociatedData, KeyBytes};
+use crate::layers::{Compressible, Correctable, Encryptable, LayerStack, Serializable, Serializer};
+use crate::typed::TableRef;
+use redb::TableHandle;
+use crate::{Codec, Error};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<K, V> TableRef<K, V>
+where
+    K: Codec<K>,
+    V: Codec<V>
+{
+    /// Retrieves the raw stored bytes for the given key, along with diagnostics from reading them
+    /// back through every read layer. Returns `None` if the key doesn't exist.
+    ///
+    /// Use this when [`TableRef::get`] fails to decode a value. The [`Diagnostics`] report which
+    /// layer failed, whether error correction repaired or failed to repair the value, the checksum
+    /// state of every error correction shard, and the bytes that were passed to the failing layer.
+    /// For example, if a value was decrypted but failed to decompress, the salvaged bytes are the
+    /// decrypted, compressed bytes.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Some((raw, diagnostics)) = table.get_raw_with_diagnostics(&42, key)? {
+    ///     eprintln!("{} stored bytes, failed layer: {:?}", raw.len(), diagnostics.failed_layer());
+    ///     std::fs::write("creature-42.salvaged", &diagnostics.salvaged)?;
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Decoding failures are reported in the diagnostics, rather than returned as errors.
+    ///
+    /// * Encoding the key fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn get_raw_with_diagnostics(
+        &self,
+        key: &K,
+        encryption_key: KeyBytes<'_>,
+    ) -> Result<Option<(Vec<u8>, Diagnostics)>, Error>
+    where
+        V: Correctable
+            + Encryptable
+            + Compressible
+            + LayerStack
+            + for<'b> Serializer<'b, V>
+            + Serializable
+            + 'static,
+    {
+        let key_bytes = K::serialize(key)?;
+        let Some(value_guard) = self.redb_table.get(key_bytes.as_slice())? else {
+            return Ok(None);
+        };
+        let raw = value_guard.value().to_vec();
+        drop(value_guard);
+
+        let associated_data = AssociatedData::new(self.redb_table.name(), &key_bytes);
+        #[cfg(feature = "compress-dictionaries")]
+        let diagnostics = Bytes::diagnose_read_layers::<V>(
+            Bytes::from_slice(&raw),
+            encryption_key,
+            &associated_data,
+            None,
+        );
+        #[cfg(not(feature = "compress-dictionaries"))]
+        let diagnostics = Bytes::diagnose_read_layers::<V>(
+            Bytes::from_slice(&raw),
+            encryption_key,
+            &associated_data,
+        );
+
+        Ok(Some((raw, diagnostics)))
+    }
+}
@@ -0,0 +1,210 @@
+//! Strongly-typed table handles, and the `tables!` macro that declares them.
+
+use crate::Codec;
+use crate::typed::TableRef;
+use crate::typed::transaction::{Error, ReadTransaction};
+#[cfg(feature = "writes")]
+use crate::typed::transaction::WriteTransaction;
+use std::marker::PhantomData;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A table's name, bound to its key and value types.
+///
+/// Handles are usually declared with the [`tables!`] macro, so that a table's name and types are
+/// written down once rather than at every `open_table("creatures")` call.
+///
+/// [`tables!`]: crate::tables
+pub struct TableHandle<K, V> {
+    /// Name of the `redb` table. For example: `"creatures"`.
+    name: &'static str,
+
+    /// Binds the handle to its key and value types.
+    phantom: PhantomData<fn() -> (K, V)>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<K, V> TableHandle<K, V> {
+    /// Creates a handle for the table with the given name.
+    #[inline]
+    #[must_use]
+    pub const fn new(name: &'static str) -> Self {
+        Self { name, phantom: PhantomData }
+    }
+
+    /// Returns the name of the `redb` table. For example: `"creatures"`.
+    #[inline]
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<K: Codec<K>, V: Codec<V>> TableHandle<K, V> {
+    /// Opens the table for reading.
+    ///
+    /// # Errors
+    ///
+    /// * The table doesn't exist. Use `init_all` to create every table declared by [`tables!`].
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///
+    /// [`tables!`]: crate::tables
+    #[inline]
+    pub fn open(&self, txn: &ReadTransaction) -> Result<TableRef<K, V>, Error> {
+        txn.open_table(self.name)
+    }
+
+    /// Creates the table, if it doesn't already exist.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    #[cfg(feature = "writes")]
+    #[inline]
+    pub fn create(&self, txn: &WriteTransaction) -> Result<(), Error> {
+        txn.create_table(self.name)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+impl<K, V> Clone for TableHandle<K, V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V> Copy for TableHandle<K, V> {}
+
+impl<K, V> std::fmt::Debug for TableHandle<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TableHandle")
+            .field("name", &self.name)
+            .field("key", &std::any::type_name::<K>())
+            .field("value", &std::any::type_name::<V>())
+            .finish()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Macros
+
+/// Declares a database's tables, with their record and primary key types.
+///
+/// ```rust,ignore
+/// mod tables {
+///     atlatl::tables! {
+///         creatures: Creature<u64>,
+///         sightings: Sighting<Ulid>,
+///     }
+/// }
+///
+/// // Creates every table, typically when the database is first opened:
+/// let mut txn = db.begin_write()?;
+/// tables::init_all(&mut txn)?;
+/// txn.commit()?;
+///
+/// // Opens a table by its handle, rather than by name:
+/// let creatures: TableRef<u64, Creature> = tables::creatures.open(&db.begin_read()?)?;
+/// ```
+///
+/// # Expansion
+///
+/// For each `name: Record<PrimaryKey>` entry, a `name` constant holding a
+/// `TableHandle<PrimaryKey, Record>` for the `"name"` table is generated. The macro also
+/// generates:
+///
+/// * `TABLE_NAMES`, a registry listing the name of every declared table, in declaration order.
+/// * `init_all(&mut WriteTransaction)`, which creates every declared table that doesn't already
+///   exist. Only generated with the `writes` feature.
+///
+/// # Notes
+///
+/// * Invoke the macro in its own module, since it declares `TABLE_NAMES` and `init_all` items.
+#[macro_export]
+macro_rules! tables {
+    ($( $name:ident : $record:ident < $key:ty > ),* $(,)?) => {
+        $(
+            #[allow(non_upper_case_globals, reason = "handles are named after their tables")]
+            #[doc = concat!("Handle for the `", stringify!($name), "` table.")]
+            pub const $name: $crate::typed::TableHandle<$key, $record> =
+                $crate::typed::TableHandle::new(stringify!($name));
+        )*
+
+        /// Name of every table declared by `tables!`, in declaration order.
+        pub const TABLE_NAMES: &[&str] = &[$( stringify!($name) ),*];
+
+        $crate::__tables_init_all!($( $name ),*);
+    };
+}
+
+/// Generates the `init_all` function for [`tables!`]. Read-only builds have no write
+/// transactions, so nothing is generated without the `writes` feature.
+#[cfg(feature = "writes")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __tables_init_all {
+    ($( $name:ident ),*) => {
+        /// Creates every table declared by `tables!` that doesn't already exist.
+        ///
+        /// # Errors
+        ///
+        /// * A `redb` [storage error](crate::Error#storage-errors).
+        pub fn init_all(
+            txn: &mut $crate::typed::transaction::WriteTransaction
+        ) -> ::core::result::Result<(), $crate::typed::transaction::Error> {
+            $( $name.create(txn)?; )*
+            ::core::result::Result::Ok(())
+        }
+    };
+}
+
+/// Generates the `init_all` function for [`tables!`]. Read-only builds have no write
+/// transactions, so nothing is generated without the `writes` feature.
+#[cfg(not(feature = "writes"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __tables_init_all {
+    ($( $name:ident ),*) => {};
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "writes"))]
+mod tests {
+    use crate::typed::database::Database;
+
+    mod tables {
+        use crate::typed::test_records::Animal;
+
+        crate::tables! {
+            animals: Animal<u64>,
+            keepers: String<u32>,
+        }
+    }
+
+    #[test]
+    fn declares_a_handle_per_table_and_creates_them() {
+        assert_eq!(tables::TABLE_NAMES, ["animals", "keepers"]);
+        assert_eq!(tables::animals.name(), "animals");
+
+        let db = Database::in_memory().unwrap();
+        let mut txn = db.write().unwrap();
+        tables::init_all(&mut txn).unwrap();
+        txn.commit().unwrap();
+
+        let txn = db.read().unwrap();
+        assert!(tables::animals.open(&txn).unwrap().is_empty().unwrap());
+        assert!(tables::keepers.open(&txn).unwrap().is_empty().unwrap());
+    }
+}
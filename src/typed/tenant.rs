@@ -0,0 +1,138 @@
+//! Tenants, which pair a namespace with a tenant-specific encryption key.
+
+use crate::layers::encryptors::{KeyBytes, TenantKey};
+use crate::typed::Namespace;
+use std::sync::Arc;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// One tenant of a shared database: a [`Namespace`] that prefixes the tenant's table names, and a
+/// [`TenantKey`] that encrypts the tenant's records.
+///
+/// Records are encrypted if their type declares
+/// [`RecordLayers`](crate::typed::record_layers::RecordLayers) that encrypt.
+///
+/// The tenant's key is derived from the master key and the tenant's name, so records written by one
+/// tenant can't be decrypted with another tenant's key, even though both tenants' tables live in
+/// the same `redb` file.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let acme = Tenant::new("acme", &master_key);
+///
+/// let txn = db.write_as(&acme)?;
+/// txn.insert(&creature)?; // Written to `acme.creatures`, encrypted with Acme's key if
+///                         // `Creature`'s record layers encrypt.
+/// txn.commit()?;
+/// ```
+///
+/// # Notes
+///
+/// * The tenant's name is bound to its key. Renaming a tenant requires its records to be
+///   re-encrypted, not just its tables to be renamed.
+#[derive(Clone, Debug)]
+pub struct Tenant {
+    /// Prefix applied to the tenant's table names.
+    namespace: Namespace,
+
+    /// Key used to encrypt and decrypt the tenant's records.
+    key: Arc<TenantKey>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Tenant {
+    /// Creates the tenant with the given name, deriving its key from the master key.
+    ///
+    /// # Panics
+    ///
+    /// * The name is empty. Every tenant must have its own namespace.
+    #[must_use]
+    pub fn new(name: &str, master_key: &KeyBytes<'_>) -> Self {
+        assert!(!name.is_empty(), "a tenant's name must not be empty");
+        Self {
+            namespace: Namespace::new(name),
+            key: Arc::new(TenantKey::new(master_key, name)),
+        }
+    }
+
+    /// Returns the tenant's name. For example: `"acme"`.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        self.namespace.name().unwrap_or_default()
+    }
+
+    /// Returns the namespace that the tenant's tables are opened in.
+    #[inline]
+    #[must_use]
+    pub const fn namespace(&self) -> &Namespace {
+        &self.namespace
+    }
+
+    /// Returns the tenant's encryption key.
+    #[inline]
+    #[must_use]
+    pub fn key(&self) -> &TenantKey {
+        &self.key
+    }
+
+    /// Returns the namespace and key, for handing to a transaction.
+    pub(crate) fn parts(&self) -> (Namespace, Arc<TenantKey>) {
+        (self.namespace.clone(), Arc::clone(&self.key))
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "writes"))]
+mod tests {
+    use super::*;
+    use crate::layers::encryptors::KEY_SIZE;
+    use crate::typed::database::Database;
+    use crate::typed::test_records::Letter;
+    use crate::{Codec, Error};
+
+    const TEXT: &str = "The quarterly numbers are in.";
+
+    fn primary_key(id: u64) -> Vec<u8> {
+        <u64 as Codec<u64>>::serialize(&id).unwrap()
+    }
+
+    #[test]
+    fn records_are_encrypted_with_the_tenants_key() {
+        let master_key = KeyBytes::from_array(&[9; KEY_SIZE]);
+        let acme = Tenant::new("acme", &master_key);
+        let globex = Tenant::new("globex", &master_key);
+
+        let db = Database::in_memory().unwrap();
+        let mut txn = db.write_as(&acme).unwrap();
+        txn.bulk_insert::<u64, Letter>([Letter::new(1, "Wile", TEXT)]).unwrap();
+        txn.commit().unwrap();
+
+        // The stored value is ciphertext:
+        let txn = db.read().unwrap();
+        let table = txn.open_raw_index_table("acme.letters").unwrap().unwrap();
+        let stored = table.get(&*primary_key(1)).unwrap().unwrap();
+        assert!(!stored.value().windows(TEXT.len()).any(|window| window == TEXT.as_bytes()));
+
+        // Only Acme's key opens it:
+        let read = db.read_as(&acme).unwrap();
+        assert_eq!(read.get::<u64, Letter>(&1).unwrap(), Some(Letter::new(1, "Wile", TEXT)));
+
+        let in_acme = || db.read().unwrap().in_namespace(acme.namespace().clone());
+        let globex_key = globex.key().key_bytes();
+        assert!(matches!(
+            in_acme().with_record_key(&globex_key).get::<u64, Letter>(&1),
+            Err(Error::Layer(_)),
+        ));
+        assert!(matches!(
+            in_acme().get::<u64, Letter>(&1),
+            Err(Error::RecordKeyMissing { .. }),
+        ));
+    }
+}
@@ -0,0 +1,203 @@
+//! Record types shared by the typed layer's tests: zoo animals, indexed by their enclosure, and
+//! letters, which are stored through record layers, indexed by their sender, and keep history.
+
+use crate::defaults::Defaults;
+use crate::indexing::{
+    Dependent, HasDependents, HasPrimaryKey, HasTable, Index, IndexKind, IndexLookup, Indexable,
+    PrimaryKey, Reference, References
+};
+use crate::layers::compressors::Level as CompressionLevel;
+use crate::layers::correctors::Level as CorrectionLevel;
+use crate::layers::core::Direction;
+use crate::layers::{Compressible, Correctable, Encryptable, LayerStack, Serializable};
+use crate::typed::record_layers::RecordLayers;
+use crate::validation::Validate;
+use crate::{Codec, Error};
+use serde::{Deserialize, Serialize};
+
+// -------------------------------------------------------------------------------------------------
+//
+/// An animal, kept in the `animals` table and indexed by its enclosure.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Animal {
+    pub id: u64,
+    pub name: String,
+    pub enclosure: String,
+}
+
+/// Looks up animals by their enclosure.
+pub struct Enclosure(pub String);
+
+/// The `animals_by_enclosure` index.
+pub struct EnclosureIndex;
+
+/// A letter, kept in the `letters` table with its earlier versions in `letters_history`, and
+/// compressed, encrypted, and protected by its record layers.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Letter {
+    pub id: u64,
+    pub sender: String,
+    pub text: String,
+}
+
+/// Looks up letters by their sender.
+pub struct Sender(pub String);
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Animal {
+    /// Instantiates an animal.
+    pub fn new(id: u64, name: &str, enclosure: &str) -> Self {
+        Self { id, name: name.into(), enclosure: enclosure.into() }
+    }
+}
+
+impl Letter {
+    /// Instantiates a letter.
+    pub fn new(id: u64, sender: &str, text: &str) -> Self {
+        Self { id, sender: sender.into(), text: text.into() }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Trait Implementations
+
+#[cfg(feature = "serde-safety")]
+unsafe impl crate::layers::serializers::SafeForSerde for Animal {}
+
+impl HasTable for Animal {
+    fn table_name() -> &'static str { "animals" }
+}
+
+impl HasPrimaryKey<'_, u64> for Animal {
+    fn primary_key(&self) -> PrimaryKey<'_, u64> {
+        PrimaryKey::new(&self.id)
+    }
+}
+
+impl<'i> Indexable<'i> for Animal {
+    type Index = Enclosure;
+    type Indexes = [Enclosure; 1];
+
+    fn indexes(&'i self) -> Result<Self::Indexes, Error> {
+        Ok([Enclosure(self.enclosure.clone())])
+    }
+}
+
+impl References for Animal {
+    fn references(&self) -> Result<Vec<Reference>, Error> {
+        Ok(Vec::new())
+    }
+}
+
+impl HasDependents for Animal {
+    fn dependents() -> Vec<Dependent> {
+        Vec::new()
+    }
+}
+
+impl Defaults for Animal {}
+
+impl Validate for Animal {}
+
+impl IndexLookup for Enclosure {
+    type Record = Animal;
+
+    fn index_name(&self) -> &'static str {
+        "animals_by_enclosure"
+    }
+
+    fn index_kind(&self) -> &IndexKind {
+        &IndexKind::NonUnique
+    }
+
+    fn index_key_bytes(&self) -> Result<Vec<u8>, Error> {
+        <String as Codec<String>>::serialize(&self.0)
+    }
+}
+
+impl Index for EnclosureIndex {
+    type Record = Animal;
+    type Field = String;
+
+    fn index_name() -> &'static str { "animals_by_enclosure" }
+}
+
+#[cfg(feature = "serde-safety")]
+unsafe impl crate::layers::serializers::SafeForSerde for Letter {}
+
+impl HasTable for Letter {
+    fn table_name() -> &'static str { "letters" }
+    fn history_table_name() -> Option<&'static str> { Some("letters_history") }
+    fn layers() -> Option<RecordLayers<Self>> { Some(RecordLayers::of()) }
+}
+
+impl HasPrimaryKey<'_, u64> for Letter {
+    fn primary_key(&self) -> PrimaryKey<'_, u64> {
+        PrimaryKey::new(&self.id)
+    }
+}
+
+impl<'i> Indexable<'i> for Letter {
+    type Index = Sender;
+    type Indexes = [Sender; 1];
+
+    fn indexes(&'i self) -> Result<Self::Indexes, Error> {
+        Ok([Sender(self.sender.clone())])
+    }
+}
+
+impl References for Letter {
+    fn references(&self) -> Result<Vec<Reference>, Error> {
+        Ok(Vec::new())
+    }
+}
+
+impl HasDependents for Letter {
+    fn dependents() -> Vec<Dependent> {
+        Vec::new()
+    }
+}
+
+impl Defaults for Letter {}
+
+impl Validate for Letter {}
+
+impl Serializable for Letter {
+    const DIRECTION: Direction = Direction::Both;
+}
+
+impl Compressible for Letter {
+    const DIRECTION: Direction = Direction::Both;
+    const LEVEL: CompressionLevel = CompressionLevel::Minimum;
+}
+
+impl Encryptable for Letter {
+    const DIRECTION: Direction = Direction::Both;
+}
+
+impl Correctable for Letter {
+    const DIRECTION: Direction = Direction::Both;
+    const LEVEL: CorrectionLevel = CorrectionLevel::Minimum;
+}
+
+impl LayerStack for Letter {}
+
+impl IndexLookup for Sender {
+    type Record = Letter;
+
+    fn index_name(&self) -> &'static str {
+        "letters_by_sender"
+    }
+
+    fn index_kind(&self) -> &IndexKind {
+        &IndexKind::NonUnique
+    }
+
+    fn index_key_bytes(&self) -> Result<Vec<u8>, Error> {
+        <String as Codec<String>>::serialize(&self.0)
+    }
+}
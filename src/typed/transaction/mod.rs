@@ -1,7 +1,12 @@
 mod read;
+#[cfg(feature = "writes")]
 mod write;
-mod error;
+mod queries;
+mod verify;
 
 pub use crate::typed::transaction::read::Transaction as ReadTransaction;
+#[cfg(feature = "writes")]
 pub use crate::typed::transaction::write::Transaction as WriteTransaction;
-pub use crate::typed::transaction::error::Error;
\ No newline at end of file
+pub use crate::Error;
+pub(crate) use crate::typed::transaction::queries::{QueryEngine, QuerySource};
+pub(crate) use crate::typed::transaction::verify::find_index_issues;
\ No newline at end of file
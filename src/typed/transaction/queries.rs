@@ -0,0 +1,991 @@
+//! The query engine, which evaluates a `Query` inside either a read or a write transaction.
+
+use crate::indexing::{
+    AlignedBytes,
+    ArchivedKeySet,
+    HasTable,
+    IndexCorrection,
+    IndexKeyBytes,
+    IndexLookup,
+    IndexProtection,
+    KeySet,
+    PreparedIndexLookup,
+    ReadableKeySet,
+    UpgradableKeySet,
+    is_full_shard,
+    read_overflow_with,
+    shard_table_name
+};
+use ::redb::ReadableTable;
+use crate::typed::record_layers::RecordContext;
+use crate::querying::{DynLookup, DynMultiLookup, Query, SortDirection, StringMatch, TopK};
+use crate::{Codec, Error};
+use std::borrow::Cow;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A transaction whose tables can be read by the query engine.
+///
+/// This lets the same query engine run inside both read and write transactions. Queries run inside
+/// a write transaction see that transaction's uncommitted changes. Tables are opened in the
+/// transaction's [`Namespace`].
+///
+/// [`Namespace`]: crate::typed::Namespace
+pub trait QuerySource {
+    /// The raw table type opened by this transaction.
+    type Table<'t>: ReadableTable<&'static [u8], &'static [u8]> where Self: 't;
+
+    /// Opens a raw table by name.
+    ///
+    /// # Errors
+    ///
+    /// * The table could not be opened. Read transactions return an error if the table doesn't
+    ///   exist, while write transactions create it.
+    fn open_readable(&self, name: &str) -> Result<Self::Table<'_>, redb::TableError>;
+
+    /// Returns the context that the named table's records are read and written in: the table's
+    /// full name in the transaction's namespace, and the key that records with [`RecordLayers`]
+    /// are encrypted with.
+    ///
+    /// [`RecordLayers`]: crate::typed::record_layers::RecordLayers
+    fn record_context(&self, table_name: &str) -> RecordContext;
+
+    /// Decodes a `V` record read from its primary table, passing it back through `V`'s
+    /// [`RecordLayers`] if it has any.
+    ///
+    /// # Errors
+    ///
+    /// * See [`RecordContext::open`].
+    ///
+    /// [`RecordLayers`]: crate::typed::record_layers::RecordLayers
+    fn open_record<V: HasTable + Codec<V>>(
+        &self,
+        primary_key: &[u8],
+        value_bytes: &[u8],
+    ) -> Result<V, Error> {
+        self.record_context(V::table_name()).open(primary_key, value_bytes)
+    }
+
+    /// Returns the [`IndexProtection`] that the transaction's index tables are encrypted with, if
+    /// any.
+    fn index_protection(&self) -> Option<&IndexProtection> {
+        None
+    }
+
+    /// Returns the [`IndexCorrection`] that the transaction's index rows are protected with, if
+    /// any.
+    fn index_correction(&self) -> Option<&IndexCorrection> {
+        None
+    }
+
+    /// Returns the [`BlindIndex`] that the named index's secondary keys are written with, if any.
+    /// Only write transactions write search tokens: queries use a `BlindLookup` instead.
+    ///
+    /// [`BlindIndex`]: crate::layers::encryptors::BlindIndex
+    fn blind_index(&self, _index_name: &str) -> Option<&crate::layers::encryptors::BlindIndex> {
+        None
+    }
+
+    /// Returns the key that a serialized secondary key is stored under in the named index table.
+    /// This is its search token if the index is protected, see [`IndexProtection`].
+    fn stored_index_key(&self, index_name: &str, secondary_key_bytes: Vec<u8>) -> Vec<u8> {
+        match self.index_protection() {
+            Some(protection) => protection.secondary_key(index_name, secondary_key_bytes),
+            None => secondary_key_bytes,
+        }
+    }
+
+    /// Returns the key that an index look-up's secondary key is stored under.
+    ///
+    /// # Errors
+    ///
+    /// * Returns an error if the secondary key could not be serialized.
+    fn lookup_key<I: IndexLookup + ?Sized>(&self, index_lookup: &I) -> Result<Vec<u8>, Error> {
+        Ok(self.stored_index_key(index_lookup.index_name(), index_lookup.index_key_bytes()?))
+    }
+
+    /// Replaces the secondary keys of blind and protected indexes with the keys they're stored
+    /// under.
+    fn protect_index_keys(&self, index_keys: Vec<IndexKeyBytes>) -> Vec<IndexKeyBytes> {
+        let index_keys = index_keys
+            .into_iter()
+            .map(|index_key| match self.blind_index(index_key.index_name) {
+                Some(blind_index) => IndexKeyBytes {
+                    secondary_key_bytes: blind_index.token(&index_key.secondary_key_bytes).to_vec(),
+                    ..index_key
+                },
+                None => index_key,
+            })
+            .collect();
+        match self.index_protection() {
+            Some(protection) => protection.protect_index_keys(index_keys),
+            None => index_keys,
+        }
+    }
+
+    /// Encrypts an index row's value, such as a serialized `KeySet`, if the index is protected,
+    /// and then adds parity data if the index is corrected.
+    ///
+    /// # Errors
+    ///
+    /// * Encryption errors.
+    ///
+    /// * Error correction errors.
+    #[cfg(feature = "writes")]
+    fn seal_index_value(
+        &self,
+        index_name: &str,
+        row_key: &[u8],
+        value_bytes: Vec<u8>,
+    ) -> Result<Vec<u8>, Error> {
+        let value_bytes = match self.index_protection() {
+            Some(protection) => protection.seal(index_name, row_key, value_bytes)?,
+            None => value_bytes,
+        };
+        match self.index_correction() {
+            Some(correction) => correction.protect(index_name, value_bytes),
+            None => Ok(value_bytes),
+        }
+    }
+
+    /// Repairs an index row's value, such as a serialized `KeySet`, if the index is corrected,
+    /// and then decrypts it if the index is protected.
+    ///
+    /// # Errors
+    ///
+    /// * Error correction errors, for example if the value is corrupted beyond repair.
+    ///
+    /// * Decryption errors, for example if the value was moved from another row.
+    fn open_index_value<'v>(
+        &self,
+        index_name: &str,
+        row_key: &[u8],
+        value_bytes: &'v [u8],
+    ) -> Result<Cow<'v, [u8]>, Error> {
+        let value_bytes = match self.index_correction() {
+            Some(correction) => correction.recover(index_name, value_bytes)?,
+            None => Cow::Borrowed(value_bytes),
+        };
+        match (self.index_protection(), value_bytes) {
+            (Some(protection), Cow::Borrowed(value_bytes)) => {
+                protection.open(index_name, row_key, value_bytes)
+            },
+            (Some(protection), Cow::Owned(value_bytes)) => Ok(Cow::Owned(
+                protection.open(index_name, row_key, &value_bytes)?.into_owned()
+            )),
+            (None, value_bytes) => Ok(value_bytes),
+        }
+    }
+
+    /// Returns the primary keys in a key set's overflow shards, given the size of its first shard,
+    /// which is stored in the index table. Returns `None` if the key set isn't sharded, which is
+    /// known without reading anything unless the first shard is full. See
+    /// [`crate::indexing::KeySetCursor`].
+    ///
+    /// # Errors
+    ///
+    /// * Deserialization errors when instantiating a shard's `KeySet`.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    fn overflow_keys(
+        &self,
+        index_name: &str,
+        secondary_key_bytes: &[u8],
+        first_shard_len: usize,
+    ) -> Result<Option<KeySet>, Error> {
+        if !is_full_shard(first_shard_len) {
+            return Ok(None);
+        }
+
+        match self.open_readable(&shard_table_name(index_name)) {
+            Ok(shard_table) => Ok(Some(read_overflow_with(
+                &shard_table,
+                secondary_key_bytes,
+                |shard_key, shard_bytes| KeySet::from_bytes(
+                    &self.open_index_value(index_name, shard_key, shard_bytes)?
+                ),
+            )?)),
+            Err(redb::TableError::TableDoesNotExist(_)) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Evaluates a [`Query`] against the tables of a read or write transaction, returning the primary
+/// keys of the matching records.
+///
+/// Each table is dropped before a subquery is evaluated. Write transactions can't open the same
+/// table twice, and a query may refer to the same index more than once. For example:
+/// `Habitat("Tide Pool") OR Habitat("Coral Reef")`.
+pub struct QueryEngine<'t, T: QuerySource>(pub &'t T);
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl<T: QuerySource> QueryEngine<'_, T> {
+    /// Performs an intersection between a base query and an indexed filter, returning a set of
+    /// primary keys.
+    ///
+    /// For example, this could find animals that live in both a great barrier reef and a coral
+    /// cove.
+    ///
+    /// # Errors
+    ///
+    /// * The `redb::Table` that contains the index data could not be opened.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///
+    /// * Deserialization errors when instantiating a `&ArchivedKeySet` from the index entry.
+    ///   Invalid key set data.
+    #[inline]
+    fn handle_and_then<K, V>(
+        &self,
+        base_query: Query<V>,
+        filtering_index: &DynLookup<V>,
+    ) -> Result<KeySet, Error>
+    where
+        K: Codec<K>,
+        V: Codec<V> + HasTable,
+    {
+        // Evaluate the left-hand set of the `and` operation. For example, all creatures living in
+        // `Habitat("Great Barrier Reef")`. This is evaluated before the index table is opened,
+        // since the left-hand side may use the same index.
+        let query_result = self.query::<K, V>(base_query)?;
+
+        // Nothing can be in both sets if the left-hand set is empty:
+        if query_result.is_empty() {
+            return Ok(query_result);
+        }
+
+        // Open index table. Like accessing a map of the sanctuary’s habitats (for example, a coral
+        // reef or savanna).
+        let index_table = self.0.open_readable(filtering_index.index_name())?;
+        let index_key_bytes = self.0.lookup_key(filtering_index)?;
+
+        // Look for the specified index key (or secondary key) from the index table. For example, we
+        // might be searching for animals in `Habitat("Coral Cove")`.
+        if let Some(key_set_bytes) = index_table.get(&*index_key_bytes)? {
+            // Deserialize the key set (or collection of primary keys) from the index entry. For
+            // example, it could represent the creatures in the specified feeding ground
+            // `Habitat("Coral Cove")`. This is the right-hand set of the `and` operation.
+            let key_set_bytes = self.0.open_index_value(
+                filtering_index.index_name(),
+                &index_key_bytes,
+                key_set_bytes.value(),
+            )?;
+            let key_set_bytes = AlignedBytes::new(&key_set_bytes);
+            let filtering_keys = ArchivedKeySet::from_bytes(&key_set_bytes)?;
+
+            // A very large key set is sharded across several rows, which are gathered first:
+            let first_shard_len = filtering_keys.len();
+            let index_name = filtering_index.index_name();
+            if let Some(overflow) = self.0.overflow_keys(index_name, &index_key_bytes, first_shard_len)? {
+                return Ok(query_result.intersection(&filtering_keys.upgrade()?.union(overflow)));
+            }
+
+            // Perform intersection with the primary keys that: 1. result from the `base_query`
+            // (Great Barrier Reef) and 2. that are associated with the specified index key (Coral
+            // Cove). Now we would only have creatures that live in both the `Habitat("Rain
+            // Forest")` and in the `Habitat("Temperate Forest")`. For example, `"Clownfish"`,
+            // `"Parrotfish"`, `"Sea Turtle"`.
+            Ok(query_result.intersection(&filtering_keys))
+        } else {
+            // No index entry was found. An example scenario would be that the right-hand set
+            // `Habitat("Lunar Lagoon")` does not exist, and there would be no index entry for it.
+            //
+            // Since this place doesn't exist, no known creatures can live there, the intersection
+            // would result in nothing. Return an empty set.
+            tracing::debug!("index not found, or empty index encountered during operation");
+            Ok(KeySet::default())
+        }
+    }
+
+    /// Performs a union between a base query and an indexed filter, returning a set of primary
+    /// keys.
+    ///
+    /// For example, this could find all animals that live in either a great barrier reef or the
+    /// Serengeti plains.
+    ///
+    /// # Errors
+    ///
+    /// * The `redb::Table` that contains the index data could not be opened.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///
+    /// * Deserialization errors when instantiating a `&ArchivedKeySet` from the index entry.
+    ///   Invalid key set data.
+    #[inline]
+    fn handle_or_else<K, V>(
+        &self,
+        base_query: Query<V>,
+        extending_index: &DynLookup<V>,
+    ) -> Result<KeySet, Error>
+    where
+        K: Codec<K>,
+        V: Codec<V> + HasTable,
+    {
+        // Evaluate the left-hand set of the `or` operation. For example: it could produce the
+        // result of a `Habitat("Great Barrier Reef")` query. This is evaluated before the index
+        // table is opened, since the left-hand side may use the same index.
+        let query_result = self.query::<K, V>(base_query)?;
+
+        // Open index table. Like accessing a map of the sanctuary’s habitats (for example, a coral
+        // reef or savanna).
+        let index_table = self.0.open_readable(extending_index.index_name())?;
+        let index_key_bytes = self.0.lookup_key(extending_index)?;
+
+        // Attempt to get the specified index key (or secondary key) from the index table. For
+        // example, we might be looking for creatures that live in a `Habitat("Serengeti Plains")`.
+        if let Some(key_set_bytes) = index_table.get(&*index_key_bytes)? {
+            // Deserialize the key set (or collection of primary keys) from the index entry. This is
+            // the right-hand set of the `or` operation. For example it could represent the
+            // creatures in `Habitat("Serengeti Plains")`:
+            let key_set_bytes = self.0.open_index_value(
+                extending_index.index_name(),
+                &index_key_bytes,
+                key_set_bytes.value(),
+            )?;
+            let mut extending_keys = KeySet::from_bytes(&key_set_bytes)?;
+
+            // A very large key set is sharded across several rows, which are gathered first:
+            let first_shard_len = extending_keys.len();
+            let index_name = extending_index.index_name();
+            if let Some(overflow) = self.0.overflow_keys(index_name, &index_key_bytes, first_shard_len)? {
+                extending_keys = extending_keys.union(overflow);
+            }
+
+            // Perform union with the primary keys that: 1. result from the `base_query` (Great
+            // Barrier Reef) and 2. that are associated with the specified index key (Serengeti
+            // Plains). Now would have all creatures that live in the
+            // `Habitat("Great Barrier Reef")` or in the `Habitat("Serengeti Plains")`. For example,
+            // `"Clownfish"`, `"Parrotfish"`, `"Cheetah"`, or `"Gazelle"`.
+            //
+            // This following `len` check helps perform the union with the least amount of work:
+            if extending_keys.len() <= query_result.len() {
+                Ok(query_result.union(extending_keys))
+            } else {
+                Ok(extending_keys.union(query_result))
+            }
+        } else {
+            // No index entry was found, however, the left-hand side of the operation still needs
+            // to be evaluated. An example scenario would be that the right-hand set
+            // `Habitat("Lunar Lagoon")` does not exist, and there would be no index entry
+            // for it.
+            //
+            // However we still want to return the critters for the left-hand side
+            // `Habitat("Great Barrier Reef")`, like `"Clownfish"` and `"Sea Turtle"`.
+            Ok(query_result)
+        }
+    }
+
+    /// Performs a set difference between a base query and an indexed filter, returning a set of
+    /// primary keys.
+    ///
+    /// For example, this could find animals living in a coral reef but not in a savanna.
+    ///
+    /// # Errors
+    ///
+    /// * The `redb::Table` that contains the index data could not be opened.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///
+    /// * Deserialization errors when instantiating a `&ArchivedKeySet` from the index entry.
+    ///   Invalid key set data.
+    #[inline]
+    fn handle_difference_of<K, V>(
+        &self,
+        base_query: Query<V>,
+        filtering_index: &DynLookup<V>,
+    ) -> Result<KeySet, Error>
+    where
+        K: Codec<K>,
+        V: Codec<V> + HasTable,
+    {
+        // Evaluate the left-hand set of the `difference` operation. For example: it could produce
+        // the result of a `Habitat("Great Barrier Reef")` query. This is evaluated before the index
+        // table is opened, since the left-hand side may use the same index.
+        let query_result = self.query::<K, V>(base_query)?;
+
+        // Open index table. Like accessing a map of the sanctuary’s habitats (for example, a coral
+        // reef or savanna).
+        let index_table = self.0.open_readable(filtering_index.index_name())?;
+        let index_key_bytes = self.0.lookup_key(filtering_index)?;
+
+        // Attempt to get the specified index key (or secondary key) from the index table. For
+        // example, we might be looking for creatures that live in a `Habitat("Serengeti Plains")`.
+        if let Some(key_set_bytes) = index_table.get(&*index_key_bytes)? {
+            // Deserialize the key set (or collection of primary keys) from the index entry. This is
+            // the right-hand set of the `difference` operation. For example, it could represent the
+            // creatures in `Habitat("Serengeti Plains")`:
+            let key_set_bytes = self.0.open_index_value(
+                filtering_index.index_name(),
+                &index_key_bytes,
+                key_set_bytes.value(),
+            )?;
+            let key_set_bytes = AlignedBytes::new(&key_set_bytes);
+            let filtering_keys = ArchivedKeySet::from_bytes(&key_set_bytes)?;
+
+            // A very large key set is sharded across several rows, which are subtracted in turn:
+            let first_shard_len = filtering_keys.len();
+            let index_name = filtering_index.index_name();
+            if let Some(overflow) = self.0.overflow_keys(index_name, &index_key_bytes, first_shard_len)? {
+                return Ok(query_result.difference(&filtering_keys).difference(&overflow));
+            }
+
+            // Perform difference with the primary keys that: 1. result from the `base_query` (Great
+            // Barrier Reef) and 2. that are associated with the specified index key (Serengeti
+            // Plains). Now would have all creatures that live in the
+            // `Habitat("Great Barrier Reef")` and not in the `Habitat("Serengeti Plains")`.
+            Ok(query_result.difference(&filtering_keys))
+        } else {
+            // No index entry was found, however, the left-hand side of the operation still needs
+            // to be evaluated. An example scenario would be that the right-hand set
+            // `Habitat("Lunar Lagoon")` does not exist, and there would be no index entry
+            // for it.
+            //
+            // Since there is no right-hand set to subtract with, return the left-hand set as-is:
+            Ok(query_result)
+        }
+    }
+
+    /// When performing a `not` query (e.g. exclude records from a given index key), there are 3
+    /// possible outcomes when the exclusion key does not exist in the index.
+    ///
+    /// These outcomes are controlled by `Cargo.toml` features:
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// // Exclude all animals in the "Forest" habitat
+    /// Query::Not(Box::new(Habitat::new("Forest")))
+    /// ```
+    ///
+    /// Now suppose we query using an invalid or nonexistent key:
+    ///
+    /// ```rust,ignore
+    /// // "Sixteenth Moon of Mars" is not a known habitat
+    /// Query::Not(Box::new(Habitat::new("Sixteenth Moon of Mars")))
+    /// ```
+    ///
+    /// # Feature Flags and Behavior
+    ///
+    /// | Feature                    | Behavior                                                                     |
+    /// |----------------------------|------------------------------------------------------------------------------|
+    /// | `missing-not-return-empty` | Returns no records. Safer default for production                             |
+    /// | `missing-not-return-all`   | Returns all records (nothing to exclude). Default for `i-know-what-im-doing` |
+    /// | `missing-not-return-error` | Returns a hard error if the exclusion key is missing                         |
+    ///
+    /// # Recommendations
+    /// * Use `not-missing-return-empty` in production to avoid accidental overmatching.
+    /// * Use `not-missing-return-all` during development if you're doing exploratory queries.
+    /// * Use `not-missing-error` when running critical queries where exclusion failures should
+    ///   panic.
+    #[allow(
+        clippy::unused_self,
+        clippy::unnecessary_wraps,
+        reason = "depends on the selected `missing-not-*` feature"
+    )]
+    #[inline]
+    fn handle_empty_exclusion<K, V>(
+        &self,
+        index_table: &'static str,
+        _secondary_key: Option<Vec<u8>>,
+    ) -> Result<KeySet, Error>
+    where
+        K: Codec<K>,
+        V: Codec<V> + HasTable,
+    {
+        #[cfg(debug_assertions)]
+        tracing::warn!("an index key for `{index_table}` was not found or was empty");
+
+        #[cfg(feature = "missing-not-return-empty")]
+        return Ok(KeySet::default());
+
+        #[cfg(feature = "missing-not-return-all")]
+        return self.collect_all_keys::<K, V>();
+
+        #[cfg(feature = "missing-not-return-error")]
+        Err(Error::NotKeyMissing {
+            index: index_table,
+            secondary_key: _secondary_key,
+        })
+    }
+
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    #[inline]
+    fn handle_not<K, V>(
+        &self,
+        query: &DynLookup<V>,
+    ) -> Result<KeySet, Error>
+    where
+        K: Codec<K>,
+        V: Codec<V> + HasTable,
+    {
+        // Open the index table. For example, this could be the index that lists all `Habitat`s and
+        // the creatures in each habitat.
+        let index_table = self.0.open_readable(query.index_name())?;
+        let index_key_bytes = self.0.lookup_key(query)?;
+
+        // Attempt to get the index entry we will exclude. For example, if we're wanting to exclude
+        // forest critters, we're trying to get the index entry that lists all creatures in
+        // `Habitat("Forest")`.
+        if let Some(key_set_bytes) = index_table.get(&*index_key_bytes)? {
+            // At this point we'll have the primary keys for all the forest creatures we'd like to
+            // exclude. All of the creatures in `Habitat("Forest")`.
+            let key_set_bytes = self.0.open_index_value(
+                query.index_name(),
+                &index_key_bytes,
+                key_set_bytes.value(),
+            )?;
+            let key_set_bytes = AlignedBytes::new(&key_set_bytes);
+            let primary_keys_to_be_excluded = ArchivedKeySet::from_bytes(&key_set_bytes)?;
+
+            // A very large key set is sharded across several rows. The other shards are excluded
+            // too:
+            let overflow = self.0
+                .overflow_keys(query.index_name(), &index_key_bytes, primary_keys_to_be_excluded.len())?
+                .unwrap_or_default();
+
+            // If we were searching for all known creatures in `Habitat("Jupiter")`, the index would
+            // be either non-existent or empty. In this case, it might not be desirable to return
+            // all creatures not on Jupiter, since that would represent the entire database.
+            //
+            // We'll call `handle_empty_exclusion` to handle the empty or the non-existent set
+            // according to the crate's features and settings.
+            if primary_keys_to_be_excluded.is_empty() {
+                return self.handle_empty_exclusion::<K, V>(
+                    query.index_name(),
+                    Some(key_set_bytes.to_vec())
+                );
+            }
+
+            // Open the primary table. For example, this could be a table lists all creatures.
+            let primary_table = self.0.open_readable(query.table_name())?;
+
+            // This will iterate over every single entry in the database and filter out the ones
+            // in `primary_keys_to_be_excluded`. For example, if the caller's specified `not` index
+            // look-up was `Habitat("Tide Pool")`, all creatures except ones like `"Snail"`,
+            // `"Sea Star"` will be returned.
+            //
+            // The primary keys for these records will be returned to the caller in a `KeySet`
+            // collection. These keys can be used to retrieve the actual, full records from the
+            // database.
+            primary_table
+                .range::<&[u8]>(..)?
+                .filter_map(|result| result
+                    .map(|(key_guard, _)|
+                        if !primary_keys_to_be_excluded.contains(key_guard.value())
+                            && !overflow.contains(key_guard.value())
+                        {
+                            Some(key_guard.value().to_vec())
+                        } else {
+                            None
+                        }
+                    )
+                    .map_err(Into::into)
+                    .transpose()
+                )
+                .collect::<Result<KeySet, Error>>()
+        } else {
+            // If we were searching for all known creatures in `Habitat("Jupiter")`, the index would
+            // be either non-existent or empty. In this case, it might not be desirable to return
+            // all creatures not on Jupiter, since that would represent the entire database.
+            //
+            // We'll call `handle_empty_exclusion` to handle the empty or the non-existent set
+            // according to the crate's features and settings.
+            self.handle_empty_exclusion::<K, V>(
+                query.index_name(),
+                query.index_key_bytes().ok()
+            )
+        }
+    }
+
+    #[inline]
+    fn handle_not_in<K, V>(
+        &self,
+        query: &DynMultiLookup<V>,
+    ) -> Result<KeySet, Error>
+    where
+        K: Codec<K>,
+        V: Codec<V> + HasTable,
+    {
+        // None of these errors should happen. If they do, they represent an empty
+        // `IndexMultiLookup` which would likely be the result of a programming error in this
+        // crate:
+        let table_name = query.table_name().ok_or(Error::MissingPrimaryTableName)?;
+        let index_name = query.index_name().ok_or(Error::MissingIndexTableName)?;
+        let index_kind = query.index_kind().ok_or(Error::MissingIndexKind)?;
+
+        // Iterate over the index entries we want to exclude. For example, if we're wanting to
+        // exclude critters from `Habitat("Temperature Forest")` and `Habitat("Wetlands")`, we'll
+        // get the primary keys for them here in this clause:
+        let primary_keys_to_be_excluded: KeySet = query
+            // Rust doesn't allow iterators from traits (`-> impl Iterator`), and support for
+            // iterators from traits isn't great, at time of writing. However, we can iterate over a
+            // key-set returned from the `IndexMultiLookup` trait. So, let's do that:
+            .to_key_set()?
+            .into_iter()
+            .flat_map(|secondary_key_bytes| {
+                // The `PreparedIndexLookup` struct implements the `IndexLookup` trait and can be
+                // passed to `get_index_keys`. First, we'll repack the data returned from the
+                // `IndexMultiLookup` trait methods into a `PreparedIndexLookup` struct for
+                // forwarding.
+                let index_lookup = PreparedIndexLookup::new(
+                    index_name,
+                    *index_kind,
+                    secondary_key_bytes
+                );
+
+                // Get all of the primary keys for a single index entry. For example, this would be
+                // one of: `Habitat("Temperature Forest")` or `Habitat("Wetlands")` per iteration.
+                self.get_index_keys::<K, V, PreparedIndexLookup<V>>(&index_lookup)
+            })
+            .flatten()
+            .collect();
+
+        // If we were searching for all known creatures in `Habitat("Jupiter")`, the index would be
+        // either non-existent or empty. In this case, it might not be desirable to return all
+        // creatures not on Jupiter, since that would represent the entire database.
+        //
+        // We'll call `handle_empty_exclusion` to handle the empty or the non-existent set according
+        // to the crate's features and settings.
+        if primary_keys_to_be_excluded.is_empty() {
+            return self.handle_empty_exclusion::<K, V>(
+                index_name,
+                None
+            );
+        }
+
+        // This will iterate over every single entry in the database and filter out the ones in
+        // `primary_keys_to_be_excluded`. For example, if the caller's specified `not` index look-up
+        // was `Habitat("Tide Pool")`, all creatures except ones like `"Snail"`, `"Sea Star"` will
+        // be returned.
+        //
+        // The primary keys for these records will be returned to the caller in a `KeySet`
+        // collection. These keys can be used to retrieve the actual, full records from the
+        // database.
+        self.get_primary_keys_with_exclusions::<K>(table_name, &primary_keys_to_be_excluded)
+    }
+
+    #[inline]
+    fn handle_any_of<K, V>(
+        &self,
+        query: &DynMultiLookup<V>,
+    ) -> Result<KeySet, Error>
+    where
+        K: Codec<K>,
+        V: Codec<V> + HasTable,
+    {
+        // None of these errors should happen. If they do, they represent an empty
+        // `IndexMultiLookup` which would likely be the result of a programming error in this
+        // crate:
+        let index_name = query.index_name().ok_or(Error::MissingIndexTableName)?;
+        let index_kind = query.index_kind().ok_or(Error::MissingIndexKind)?;
+
+        // Iterate over the index entries we want to include. For example, if we're wanting to
+        // include any critters from `Habitat("Temperature Forest")` and `Habitat("Wetlands")`,
+        // we'll get the primary keys for them here in this clause:
+        let primary_keys_to_be_included = query
+            // Rust doesn't allow iterators from traits (`-> impl Iterator`), and support for
+            // iterators from traits isn't great, at time of writing. However, we can iterate over a
+            // key-set returned from the `IndexMultiLookup` trait. So, let's do that:
+            .to_key_set()?
+            .into_iter()
+            .flat_map(|secondary_key_bytes| {
+                // The `PreparedIndexLookup` struct implements the `IndexLookup` trait and can be
+                // passed to `get_index_keys`. First, we'll repack the data returned from the
+                // `IndexMultiLookup` trait methods into a `PreparedIndexLookup` struct for
+                // forwarding.
+                let index_lookup = PreparedIndexLookup::new(
+                    index_name,
+                    *index_kind,
+                    secondary_key_bytes
+                );
+
+                // Get all of the primary keys for a single index entry. For example, this would be
+                // one of: `Habitat("Temperature Forest")` or `Habitat("Wetlands")` per iteration.
+                self.get_index_keys::<K, V, PreparedIndexLookup<V>>(&index_lookup)
+            })
+            .flatten()
+            .collect();
+
+        Ok(primary_keys_to_be_included)
+    }
+
+    /// Scans the keys of a secondary index, returning the union of the key sets of every index
+    /// entry whose string key satisfies `is_match`. Primary records are never loaded.
+    ///
+    /// For example, matching `Species` keys starting with `"Sea "` would return the primary keys
+    /// for the `"Sea Star"`, the `"Sea Otter"`, and the `"Sea Urchin"` creatures.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///
+    /// * Deserialization errors when decoding an index key as a `String`, or when instantiating a
+    ///   `KeySet` from an index entry.
+    #[inline]
+    fn handle_string_match(
+        &self,
+        string_match: &StringMatch,
+        is_match: impl Fn(&StringMatch, &str) -> bool,
+    ) -> Result<KeySet, Error> {
+        let index_table = self.0.open_readable(string_match.index_name)?;
+
+        // Walk the index entries in key order. Each entry is a distinct value of the indexed field,
+        // so this is typically far smaller than the primary table:
+        index_table
+            .range::<&[u8]>(..)?
+            .filter_map(|result| result
+                .map_err(Into::into)
+                .and_then(|(index_key_bytes, key_set_bytes)| {
+                    let index_key = String::deserialize(index_key_bytes.value())?;
+                    if is_match(string_match, &index_key) {
+                        let key_set = KeySet::from_bytes(key_set_bytes.value())?;
+                        let overflow = self.0.overflow_keys(
+                            string_match.index_name,
+                            index_key_bytes.value(),
+                            key_set.len(),
+                        )?;
+                        Ok(Some(key_set.union(overflow.unwrap_or_default())))
+                    } else {
+                        Ok(None)
+                    }
+                })
+                .transpose()
+            )
+            .collect::<Result<KeySet, Error>>()
+    }
+
+    /// Evaluates a custom predicate against every record in the primary table, returning the
+    /// primary keys of the records for which it returned `true`.
+    ///
+    /// # Errors
+    ///
+    /// * Any error returned by the predicate. Evaluation stops at the first error.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///
+    /// * Deserialization errors when decoding a record.
+    #[cfg(feature = "custom-queries")]
+    #[inline]
+    fn handle_custom<V>(
+        &self,
+        predicate: &crate::querying::DynPredicate<V>,
+    ) -> Result<KeySet, Error>
+    where
+        V: Codec<V> + HasTable,
+    {
+        let primary_table = self.0.open_readable(V::table_name())?;
+        let context = self.0.record_context(V::table_name());
+
+        primary_table
+            .range::<&[u8]>(..)?
+            .filter_map(|result| result
+                .map_err(Into::into)
+                .and_then(|(key_guard, value_guard)| {
+                    let record: V = context.open(key_guard.value(), value_guard.value())?;
+                    Ok(predicate(&record)?.then(|| key_guard.value().to_vec()))
+                })
+                .transpose()
+            )
+            .collect::<Result<KeySet, Error>>()
+    }
+
+    /// Walks a non-unique secondary index from one end, returning the first `k` distinct primary
+    /// keys in index order. Iteration stops as soon as `k` keys have been found.
+    ///
+    /// For example, walking a `CreatedAt` index in descending order with a `k` of 10 would return
+    /// the primary keys of the ten newest records.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///
+    /// * Deserialization errors when instantiating a `KeySet` from an index entry.
+    pub fn top_k_keys(&self, top_k: &TopK) -> Result<Vec<Vec<u8>>, Error> {
+        let mut primary_keys: Vec<Vec<u8>> = Vec::with_capacity(top_k.k);
+        if top_k.k == 0 {
+            return Ok(primary_keys);
+        }
+
+        let index_table = self.0.open_readable(top_k.index_name)?;
+        let range = index_table.range::<&[u8]>(..)?;
+        let entries: Box<dyn Iterator<Item = _>> = match top_k.direction {
+            SortDirection::Ascending => Box::new(range),
+            SortDirection::Descending => Box::new(range.rev()),
+        };
+
+        let mut seen = KeySet::default();
+        for entry in entries {
+            let (secondary_key, key_set_bytes) = entry?;
+
+            // Primary keys within an entry are unordered, so they're sorted to keep the selection
+            // deterministic:
+            let key_set = KeySet::from_bytes(&self.0.open_index_value(
+                top_k.index_name,
+                secondary_key.value(),
+                key_set_bytes.value(),
+            )?)?;
+            let overflow = self.0
+                .overflow_keys(top_k.index_name, secondary_key.value(), key_set.len())?
+                .unwrap_or_default();
+            let mut entry_keys: Vec<Vec<u8>> = key_set.union(overflow).into_iter().collect();
+            entry_keys.sort_unstable();
+            if top_k.direction == SortDirection::Descending {
+                entry_keys.reverse();
+            }
+
+            for primary_key in entry_keys {
+                if seen.contains(&primary_key) {
+                    continue;
+                }
+                seen.insert(primary_key.clone());
+                primary_keys.push(primary_key);
+                if primary_keys.len() == top_k.k {
+                    return Ok(primary_keys);
+                }
+            }
+        }
+
+        Ok(primary_keys)
+    }
+
+    /// Returns all primary keys for a secondary index look-up.
+    ///
+    /// For example, `Habitat("Temperate Forest")` might return the primary keys for the `"Black
+    /// Bear"`, the `"Deer"`, and the `"Squirrel"` creatures.
+    #[inline]
+    fn get_index_keys<K, V, I>(
+        &self,
+        index_lookup: &I,
+    ) -> Result<KeySet, Error>
+    where
+        K: Codec<K>,
+        V: Codec<V>,
+        I: IndexLookup + ?Sized
+    {
+        let index_table = self.0.open_readable(index_lookup.index_name())?;
+        let index_key_bytes = self.0.lookup_key(index_lookup)?;
+
+        let key_set = index_table.get(&*index_key_bytes)?
+            .map(|index_bytes| KeySet::from_bytes(&self.0.open_index_value(
+                index_lookup.index_name(),
+                &index_key_bytes,
+                index_bytes.value(),
+            )?))
+            .transpose()?
+            .unwrap_or_default();
+
+        let overflow = self.0.overflow_keys(index_lookup.index_name(), &index_key_bytes, key_set.len())?;
+        Ok(key_set.union(overflow.unwrap_or_default()))
+    }
+
+    /// Returns all primary keys in the primary table, excluding the primary keys listed in the
+    /// provided `KeySet`.
+    ///
+    /// This is used for unary `not` and similar operators.
+    #[inline]
+    fn get_primary_keys_with_exclusions<K>(
+        &self,
+        primary_table_name: &'static str,
+        exclusions: &impl ReadableKeySet
+    ) -> Result<KeySet, Error>
+    where
+        K: Codec<K>
+    {
+        let primary_table = self.0.open_readable(primary_table_name)?;
+
+        primary_table
+            .range::<&[u8]>(..)?
+            .filter_map(|result| result
+                .map(|(key_guard, _)| if exclusions.contains(key_guard.value()) {
+                    None
+                } else {
+                    Some(key_guard.value().to_vec())
+                })
+                .map_err(Into::into)
+                .transpose()
+            )
+            .collect::<Result<KeySet, Error>>()
+    }
+
+    /// Evaluates a query, returning the primary keys of the matching records.
+    ///
+    /// # Errors
+    ///
+    /// * See the read transaction's `query` method.
+    pub fn query<K, V>(
+        &self,
+        query: impl Into<Query<V>>,
+    ) -> Result<KeySet, Error>
+    where
+        K: Codec<K>,
+        V: Codec<V> + HasTable,
+    {
+        #[cfg(feature = "tracing-spans")]
+        let span = tracing::debug_span!(
+            "atlatl.query",
+            table = V::table_name(),
+            matches = tracing::field::Empty,
+        )
+        .entered();
+
+        let query: Query<V> = query.into();
+
+        let key_set = match query {
+            Query::Lookup(index_lookup) =>
+                self.get_index_keys::<K, V, DynLookup<V>>(&*index_lookup)?,
+
+            Query::Not(index_lookup) =>
+                self.handle_not::<K, V>(&*index_lookup)?,
+
+            Query::And(base_query, filtering_index) =>
+                self.handle_and_then::<K, V>(*base_query, &*filtering_index)?,
+
+            Query::Difference(base_query, filtering_index) =>
+                self.handle_difference_of::<K, V>(*base_query, &*filtering_index)?,
+
+            Query::Or(base_query, extending_index) =>
+                self.handle_or_else::<K, V>(*base_query, &*extending_index)?,
+
+            Query::Xor(base_query, extending_index) =>
+                self.handle_or_else::<K, V>(*base_query, &*extending_index)?,
+
+            Query::AnyOf(index_multi_lookup) =>
+                self.handle_any_of::<K, V>(&*index_multi_lookup)?,
+
+            Query::NotIn(index_multi_lookup) =>
+                self.handle_not_in::<K, V>(&*index_multi_lookup)?,
+
+            Query::Group(inner) => self.query::<K, V>(*inner)?,
+
+            Query::StartsWith(string_match) =>
+                self.handle_string_match(&string_match, StringMatch::is_prefix_of)?,
+
+            Query::TopK(top_k) => self.top_k_keys(&top_k)?.into_iter().collect(),
+
+            #[cfg(feature = "custom-queries")]
+            Query::Custom(predicate) => self.handle_custom::<V>(predicate.as_ref())?,
+
+            Query::Contains(string_match) => {
+                #[cfg(debug_assertions)]
+                tracing::warn!(
+                    "substring query on `{}` scans every key in the index",
+                    string_match.index_name
+                );
+                self.handle_string_match(&string_match, StringMatch::is_substring_of)?
+            },
+        };
+
+        #[cfg(feature = "tracing-spans")]
+        span.record("matches", key_set.len());
+
+        Ok(key_set)
+    }
+}
\ No newline at end of file
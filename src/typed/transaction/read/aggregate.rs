@@ -0,0 +1,177 @@
+//! Read transaction methods that execute aggregations.
+
+use crate::indexing::{HasTable, KeySet, ReadableKeySet};
+use crate::querying::{Accumulator, Aggregate, AggregateGroup};
+use crate::typed::transaction::read::{RedbReadOnlyTable, Transaction};
+use crate::typed::transaction::QuerySource;
+use crate::{Codec, Error};
+use redb::{ReadableTable, ReadableTableMetadata, TableDefinition};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Executes an aggregation, returning one [`AggregateGroup`] per group.
+    ///
+    /// * Ungrouped aggregates return a single group.
+    ///
+    /// * Grouped aggregates return one group per index entry, in index key order. Groups with no
+    ///   matching records are omitted.
+    ///
+    /// Records are read and folded one at a time. Aggregates that only count records are computed
+    /// from the index key sets and the filter's key set, without reading the primary table.
+    ///
+    /// # Errors
+    ///
+    /// * Returns any error from executing the aggregate's filter query.
+    ///
+    /// * Deserialization errors when decoding a record or a key set.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn aggregate<K, V>(&self, aggregate: Aggregate<V>) -> Result<Vec<AggregateGroup>, Error>
+    where
+        K: Codec<K>,
+        V: Codec<V> + HasTable,
+    {
+        let Aggregate { filter, group_by, measures } = aggregate;
+        let is_count_only = measures.iter().all(|measure| measure.field().is_none());
+        let filter = filter.map(|query| self.query::<K, V>(query)).transpose()?;
+
+        let primary_table: RedbReadOnlyTable =
+            self.redb.open_table(
+                TableDefinition::new(&self.namespace.table_name(V::table_name()))
+            )?;
+
+        let context = self.record_context(V::table_name());
+
+        // Folds the records for the given primary keys into a group:
+        let fold = |keys: &mut dyn Iterator<Item = Vec<u8>>| -> Result<Accumulator, Error> {
+            let mut accumulator = Accumulator::new(measures.len());
+            for primary_key_bytes in keys {
+                if let Some(value_guard) = primary_table.get(primary_key_bytes.as_slice())? {
+                    let record = context.open(&primary_key_bytes, value_guard.value())?;
+                    accumulator.push(&measures, &record);
+                }
+            }
+            Ok(accumulator)
+        };
+
+        let Some(index_name) = group_by else {
+            let accumulator = match (filter, is_count_only) {
+                (Some(keys), true) => Accumulator::counted(measures.len(), keys.len() as u64),
+                (Some(keys), false) => fold(&mut keys.into_iter())?,
+                (None, true) => Accumulator::counted(measures.len(), primary_table.len()?),
+                (None, false) => {
+                    let mut accumulator = Accumulator::new(measures.len());
+                    for entry in primary_table.iter()? {
+                        let (key_guard, value_guard) = entry?;
+                        let record = context.open(key_guard.value(), value_guard.value())?;
+                        accumulator.push(&measures, &record);
+                    }
+                    accumulator
+                },
+            };
+            return Ok(vec![accumulator.finish(&measures, None)]);
+        };
+
+        let Some(index_table) = self.open_raw_index_table(index_name)? else {
+            return Ok(Vec::new());
+        };
+
+        let mut groups = Vec::new();
+        for entry in index_table.iter()? {
+            let (secondary_key_guard, key_set_guard) = entry?;
+            let mut keys = KeySet::from_bytes(&self.open_index_value(
+                index_name,
+                secondary_key_guard.value(),
+                key_set_guard.value(),
+            )?)?;
+            if let Some(overflow) =
+                self.overflow_keys(index_name, secondary_key_guard.value(), keys.len())?
+            {
+                keys = keys.union(overflow);
+            }
+            if let Some(filter) = &filter {
+                keys.intersect_with(filter);
+            }
+            if keys.is_empty() {
+                continue;
+            }
+
+            let accumulator = if is_count_only {
+                Accumulator::counted(measures.len(), keys.len() as u64)
+            } else {
+                fold(&mut keys.into_iter())?
+            };
+
+            groups.push(accumulator.finish(&measures, Some(secondary_key_guard.value().to_vec())));
+        }
+
+        Ok(groups)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "writes"))]
+mod tests {
+    use crate::querying::{AggregateValue, Query};
+    use crate::typed::database::Database;
+    use crate::typed::test_records::{Animal, Enclosure, EnclosureIndex};
+
+    fn database() -> Database {
+        let db = Database::in_memory().unwrap();
+        let mut txn = db.write().unwrap();
+        txn.bulk_insert::<u64, Animal>([
+            Animal::new(1, "Lion", "Savannah"),
+            Animal::new(2, "Zebra", "Savannah"),
+            Animal::new(6, "Giraffe", "Savannah"),
+            Animal::new(4, "Penguin", "Arctic"),
+        ]).unwrap();
+        txn.commit().unwrap();
+        db
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss, reason = "test ids are small")]
+    fn measures_fold_the_filtered_records() {
+        let db = database();
+        let aggregate = Query::<Animal>::aggregate()
+            .filter(Enclosure("Savannah".into()))
+            .count()
+            .sum(|animal| animal.id as f64)
+            .min(|animal| animal.id as f64)
+            .max(|animal| animal.id as f64)
+            .average(|animal| animal.id as f64);
+
+        let groups = db.read().unwrap().aggregate::<u64, Animal>(aggregate).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].key::<String>().unwrap(), None);
+        assert_eq!(groups[0].values(), [
+            AggregateValue::Count(3),
+            AggregateValue::Sum(9.0),
+            AggregateValue::Min(Some(1.0)),
+            AggregateValue::Max(Some(6.0)),
+            AggregateValue::Average(Some(3.0)),
+        ]);
+    }
+
+    #[test]
+    fn groups_follow_the_index_key_order() {
+        let db = database();
+        let aggregate = Query::<Animal>::aggregate().group_by(EnclosureIndex).count();
+
+        let groups = db.read().unwrap().aggregate::<u64, Animal>(aggregate).unwrap();
+        let counts: Vec<(String, AggregateValue)> = groups
+            .iter()
+            .map(|group| (group.key().unwrap().unwrap(), group.values()[0]))
+            .collect();
+        assert_eq!(counts, [
+            ("Arctic".to_string(), AggregateValue::Count(1)),
+            ("Savannah".to_string(), AggregateValue::Count(3)),
+        ]);
+    }
+}
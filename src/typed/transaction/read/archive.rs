@@ -0,0 +1,157 @@
+//! Read transaction methods that export tables to a portable archive.
+
+use crate::Error;
+use crate::redaction::Redacted;
+use crate::typed::archive::{ArchiveSummary, ArchiveWriter, ExportScope, KeyKind};
+use crate::typed::record_layers::RecordContext;
+use crate::typed::transaction::QuerySource;
+use crate::typed::transaction::read::Transaction;
+use redb::{ReadableTable, TableDefinition, TableHandle};
+use std::collections::BTreeSet;
+use std::io::Write;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Exports tables to a portable archive, which can be imported into another database with the
+    /// write transaction's `import` method, even one created by a different version of `redb`.
+    ///
+    /// Tables are read from the transaction's namespace, and archived without their namespace
+    /// prefix, so an archive can be imported into a different namespace. The export is a
+    /// consistent snapshot of the database as of the start of this transaction.
+    ///
+    /// Records are redacted by the transaction's [`RedactionPolicy`], see
+    /// [`Transaction::with_redaction_policy`]. Tables that the policy leaves out aren't archived,
+    /// and neither are the secondary index tables of redacted records.
+    ///
+    /// [`RedactionPolicy`]: crate::redaction::RedactionPolicy
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let file = std::fs::File::create("creatures.atlatl")?;
+    /// let summary = db.read()?.export(ExportScope::Tables(tables::TABLE_NAMES), file)?;
+    /// println!("exported {} records", summary.entries);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * A named table doesn't exist, or has key or value types that `atlatl` doesn't create.
+    ///
+    /// * Returns [`Error::Io`] if the archive could not be written.
+    ///
+    /// * Decoding a record, or encoding its redacted copy, fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn export(
+        &self,
+        scope: ExportScope<'_>,
+        writer: impl Write,
+    ) -> Result<ArchiveSummary, Error> {
+        let mut table_names: Vec<String> = match scope {
+            ExportScope::Tables(table_names) =>
+                table_names.iter().map(ToString::to_string).collect(),
+            ExportScope::Everything => self.redb
+                .list_tables()?
+                .filter_map(|table| self.namespace.strip(table.name()).map(String::from))
+                .collect(),
+        };
+
+        // Redacted tables are exported first, so that their records' index tables are known:
+        let policy = self.redaction_policy.as_ref();
+        if let Some(policy) = policy {
+            table_names.sort_by_key(|name| !policy.redacts_table(&self.namespace.table_name(name)));
+        }
+
+        let mut archive = ArchiveWriter::new(writer)?;
+        let mut index_tables = BTreeSet::new();
+        for table_name in table_names {
+            let full_name = self.namespace.table_name(&table_name);
+            if policy.is_some_and(|policy| !policy.keeps_table(&full_name))
+                || index_tables.contains(&*full_name)
+            {
+                continue;
+            }
+            self.export_table(&mut archive, &table_name, &mut index_tables)?;
+        }
+        archive.finish()
+    }
+
+    /// Writes one table and its entries to an archive, detecting the table's key type. The index
+    /// tables of redacted records are added to `index_tables`.
+    ///
+    /// # Errors
+    ///
+    /// * See [`Transaction::export`].
+    fn export_table(
+        &self,
+        archive: &mut ArchiveWriter<impl Write>,
+        table_name: &str,
+        index_tables: &mut BTreeSet<String>,
+    ) -> Result<(), Error> {
+        let full_name = self.namespace.table_name(table_name);
+        let context = self.record_context(table_name);
+
+        match self.redb.open_table(TableDefinition::<&[u8], &[u8]>::new(&full_name)) {
+            Ok(table) => {
+                archive.table(table_name, KeyKind::Bytes)?;
+                for entry in table.iter()? {
+                    let (key, value) = entry?;
+                    let key = key.value();
+                    self.archive_entry(archive, &context, key, value.value(), index_tables)?;
+                }
+                return Ok(());
+            },
+            Err(redb::TableError::TableTypeMismatch { .. }) => {},
+            Err(error) => return Err(error.into()),
+        }
+
+        match self.redb.open_table(TableDefinition::<&str, &[u8]>::new(&full_name)) {
+            Ok(table) => {
+                archive.table(table_name, KeyKind::Str)?;
+                for entry in table.iter()? {
+                    let (key, value) = entry?;
+                    let key = key.value().as_bytes();
+                    self.archive_entry(archive, &context, key, value.value(), index_tables)?;
+                }
+                return Ok(());
+            },
+            Err(redb::TableError::TableTypeMismatch { .. }) => {},
+            Err(error) => return Err(error.into()),
+        }
+
+        let table = self.redb.open_table(TableDefinition::<u64, &[u8]>::new(&full_name))?;
+        archive.table(table_name, KeyKind::U64)?;
+        for entry in table.iter()? {
+            let (key, value) = entry?;
+            let key = key.value().to_le_bytes();
+            self.archive_entry(archive, &context, &key, value.value(), index_tables)?;
+        }
+        Ok(())
+    }
+
+    /// Writes one entry to an archive, redacted by the transaction's redaction policy. The index
+    /// tables of a redacted record are added to `index_tables`.
+    ///
+    /// # Errors
+    ///
+    /// * See [`Transaction::export`].
+    fn archive_entry(
+        &self,
+        archive: &mut ArchiveWriter<impl Write>,
+        context: &RecordContext,
+        key: &[u8],
+        value: &[u8],
+        index_tables: &mut BTreeSet<String>,
+    ) -> Result<(), Error> {
+        match &self.redaction_policy {
+            Some(policy) => match policy.apply_to_table(context, key, value, index_tables)? {
+                Redacted::Keep(value) => archive.entry(key, &value),
+                Redacted::Drop => Ok(()),
+            },
+            None => archive.entry(key, value),
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! Read transaction methods that read the audit log.
+
+use crate::Error;
+use crate::typed::audit::{AUDIT_LOG_TABLE, AuditEntries};
+use crate::typed::transaction::read::Transaction;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Returns the audit log entries made after the given sequence number, oldest first. Pass `0`
+    /// to read the whole log. If the audit log isn't enabled, there are no entries.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// for entry in db.read()?.audit_log(0)? {
+    ///     let entry = entry?;
+    ///     if let Some(actor) = &entry.actor {
+    ///         println!("{actor} changed `{}` at {:?}", entry.table, entry.timestamp);
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn audit_log(&self, since_sequence: u64) -> Result<AuditEntries, Error> {
+        match self.redb.open_table(AUDIT_LOG_TABLE) {
+            Ok(audit_log) => Ok(AuditEntries::new(Some(
+                audit_log.range(since_sequence.saturating_add(1)..)?
+            ))),
+            Err(redb::TableError::TableDoesNotExist(_)) => Ok(AuditEntries::new(None)),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
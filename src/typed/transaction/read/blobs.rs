@@ -0,0 +1,94 @@
+//! Read transaction methods that read blobs.
+
+use crate::Error;
+use crate::typed::blobs::{
+    BLOB_CHUNKS_TABLE_NAME, BLOB_MANIFESTS_TABLE_NAME, BlobReader, CHUNK_HASH_SIZE, Manifest,
+    decode_chunk,
+};
+use crate::typed::transaction::read::Transaction;
+use redb::TableDefinition;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Starts reading the named blob. The returned [`BlobReader`] loads the blob's chunks one at a
+    /// time, as they're read.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let mut reader = txn.blob_reader("videos/coyote.mp4")?;
+    /// std::io::copy(&mut reader, &mut std::fs::File::create("coyote.mp4")?)?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::BlobNotFound`] if there's no blob with the given name.
+    ///
+    /// * Returns [`Error::MalformedBlob`] if the blob's manifest can't be decoded.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn blob_reader(&self, name: &str) -> Result<BlobReader<'_>, Error> {
+        let manifest = self
+            .blob_manifest(name)?
+            .ok_or_else(|| Error::BlobNotFound { name: name.to_string() })?;
+        Ok(BlobReader::new(self, name, manifest))
+    }
+
+    /// Returns the length of the named blob in bytes, or `None` if there's no such blob.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::MalformedBlob`] if the blob's manifest can't be decoded.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn blob_len(&self, name: &str) -> Result<Option<u64>, Error> {
+        Ok(self.blob_manifest(name)?.map(|manifest| manifest.len))
+    }
+
+    /// Returns a stored chunk's data, or `None` if the chunk isn't stored.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn read_blob_chunk(
+        &self,
+        hash: &[u8; CHUNK_HASH_SIZE],
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let table_name = self.namespace.table_name(BLOB_CHUNKS_TABLE_NAME);
+        let chunk_table =
+            match self.redb.open_table(TableDefinition::<&[u8], &[u8]>::new(&table_name)) {
+                Ok(chunk_table) => chunk_table,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(None),
+                Err(error) => return Err(error.into()),
+            };
+
+        Ok(chunk_table
+            .get(hash.as_slice())?
+            .and_then(|row| decode_chunk(row.value()).map(|(_, chunk)| chunk.to_vec())))
+    }
+
+    /// Returns the named blob's manifest, or `None` if there's no such blob.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::MalformedBlob`] if the manifest can't be decoded.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    fn blob_manifest(&self, name: &str) -> Result<Option<Manifest>, Error> {
+        let table_name = self.namespace.table_name(BLOB_MANIFESTS_TABLE_NAME);
+        let manifest_table =
+            match self.redb.open_table(TableDefinition::<&str, &[u8]>::new(&table_name)) {
+                Ok(manifest_table) => manifest_table,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(None),
+                Err(error) => return Err(error.into()),
+            };
+
+        manifest_table
+            .get(name)?
+            .map(|manifest| Manifest::decode(name, manifest.value()))
+            .transpose()
+    }
+}
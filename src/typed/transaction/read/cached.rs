@@ -0,0 +1,63 @@
+//! Read transaction methods that read records through the database's shared cache.
+
+use crate::indexing::HasTable;
+use crate::typed::transaction::QuerySource;
+use crate::typed::transaction::read::Transaction;
+use crate::{Codec, Error};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Reads a record by its primary key, through the database's shared cache. Returns `None` if
+    /// there's no record with the primary key. See [`crate::typed::shared_cache`].
+    ///
+    /// A cached record is returned without touching the table. Otherwise, the record is read and
+    /// decoded as usual, and cached for other read transactions. If the shared cache isn't
+    /// enabled, or something has been committed since this transaction began, the record is read
+    /// from the table without the cache.
+    ///
+    /// If the cache was built [`with_negative_capacity`], primary keys that have no record are
+    /// cached too, and returning `None` for them again is nearly free.
+    ///
+    /// [`with_negative_capacity`]: crate::typed::shared_cache::SharedCache::with_negative_capacity
+    ///
+    /// # Errors
+    ///
+    /// * Encoding the primary key, or decoding the record fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn get_cached<K, V>(&self, primary_key: &K) -> Result<Option<V>, Error>
+    where
+        K: Codec<K>,
+        V: Codec<V> + HasTable + Clone + Send + Sync + 'static,
+    {
+        let primary_key_bytes = K::serialize(primary_key)?;
+        let table_name = self.namespace.table_name(V::table_name());
+        if let Some((cache, epoch)) = &self.shared_cache
+            && let Some(cached) = cache.get::<V>(*epoch, &table_name, &primary_key_bytes)
+        {
+            return Ok(cached);
+        }
+
+        let table = self.open_raw_index_table(V::table_name())?;
+        let value = table
+            .as_ref()
+            .map(|table| table.get(&*primary_key_bytes))
+            .transpose()?
+            .flatten();
+        let Some(value) = value else {
+            if let Some((cache, epoch)) = &self.shared_cache {
+                cache.insert_missing(*epoch, table_name.into_owned(), primary_key_bytes);
+            }
+            return Ok(None);
+        };
+
+        let record: V = self.open_record(&primary_key_bytes, value.value())?;
+        if let Some((cache, epoch)) = &self.shared_cache {
+            cache.insert(*epoch, table_name.into_owned(), primary_key_bytes, record.clone());
+        }
+        Ok(Some(record))
+    }
+}
@@ -0,0 +1,55 @@
+//! Read transaction methods that verify the hash chains of the change log and the audit log.
+
+use crate::Error;
+use crate::typed::audit::AUDIT_LOG_TABLE;
+use crate::typed::chain::{ChainHead, ChainReport, Log, verify_log};
+use crate::typed::change_log::CHANGE_LOG_TABLE;
+use crate::typed::transaction::read::Transaction;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Verifies that no entry of the given log has been modified, removed, or reordered since it
+    /// was recorded. A log that doesn't exist verifies as empty.
+    ///
+    /// Pass an `anchor`, a [`ChainHead`] from an earlier report that was kept outside of the
+    /// database, to also detect entries removed from the end of the log. See the
+    /// [`chain`](crate::typed::chain) module.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let report = db.read()?.verify_chain(Log::Audit, last_head.as_ref())?;
+    /// println!("{} entries verified", report.chained);
+    /// last_head = report.head;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::ChainBroken`] if the log has been tampered with.
+    ///
+    /// * Returns [`Error::MalformedChangeLog`] or [`Error::MalformedAuditLog`] if an entry is
+    ///   truncated or malformed.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn verify_chain(&self, log: Log, anchor: Option<&ChainHead>) -> Result<ChainReport, Error> {
+        let definition = match log {
+            Log::Changes => CHANGE_LOG_TABLE,
+            Log::Audit => AUDIT_LOG_TABLE,
+        };
+        match self.redb.open_table(definition) {
+            Ok(table) => verify_log(log, &table, anchor),
+            Err(redb::TableError::TableDoesNotExist(_)) => anchor.map_or_else(
+                || Ok(ChainReport::default()),
+                |anchor| Err(Error::ChainBroken {
+                    log: log.name(),
+                    sequence: anchor.sequence,
+                    reason: "entries were truncated",
+                }),
+            ),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
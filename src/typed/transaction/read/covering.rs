@@ -0,0 +1,49 @@
+//! Read transaction methods that answer look-ups from covering indexes, without reading the
+//! primary table.
+
+use crate::indexing::{IndexLookup, covering_prefix};
+use crate::typed::transaction::read::Transaction;
+use crate::typed::transaction::QuerySource;
+use crate::{Codec, Error};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Returns the primary key and stored projection of every record matching an index look-up,
+    /// read entirely from a covering table. See [`Covered`].
+    ///
+    /// Records are returned in primary key byte order. A missing covering table has no records.
+    ///
+    /// # Errors
+    ///
+    /// * Encoding the look-up's secondary key fails, or decoding a primary key or projection fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///
+    /// [`Covered`]: crate::indexing::Covered
+    pub fn covered<K, P>(
+        &self,
+        covering_table_name: &str,
+        lookup: &impl IndexLookup,
+    ) -> Result<Vec<(K, P)>, Error>
+    where
+        K: Codec<K>,
+        P: Codec<P>,
+    {
+        let Some(covering_table) = self.open_raw_index_table(covering_table_name)? else {
+            return Ok(Vec::new());
+        };
+
+        let prefix = covering_prefix(&self.lookup_key(lookup)?)?;
+        let mut covered = Vec::new();
+        for entry in covering_table.range::<&[u8]>(&*prefix..)? {
+            let (key, projection) = entry?;
+            let Some(primary_key_bytes) = key.value().strip_prefix(&*prefix) else { break };
+            covered.push((K::deserialize(primary_key_bytes)?, P::deserialize(projection.value())?));
+        }
+
+        Ok(covered)
+    }
+}
@@ -0,0 +1,43 @@
+//! Read transaction methods that read delta-encoded records.
+
+use crate::typed::delta::{
+    BASE_ROW, DELTA_ROW, DeltaEncoded, apply_delta, delta_table_name, row_key
+};
+use crate::typed::transaction::read::Transaction;
+use crate::{Codec, Error};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Reads a delta-encoded record, reconstructing it from its base and delta. Returns `None` if
+    /// there's no record with the primary key. See [`crate::typed::delta`].
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Corrupted`] if the record's delta can't be applied to its base.
+    ///
+    /// * Encoding the primary key, or decoding the record fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn get_delta_encoded<K, V>(&self, primary_key: &K) -> Result<Option<V>, Error>
+    where
+        K: Codec<K>,
+        V: Codec<V> + DeltaEncoded,
+    {
+        let Some(table) = self.open_raw_index_table(&delta_table_name(V::table_name()))? else {
+            return Ok(None);
+        };
+
+        let primary_key_bytes = K::serialize(primary_key)?;
+        let Some(base) = table.get(&*row_key(&primary_key_bytes, BASE_ROW))? else {
+            return Ok(None);
+        };
+
+        match table.get(&*row_key(&primary_key_bytes, DELTA_ROW))? {
+            Some(delta) => Ok(Some(V::deserialize(&apply_delta(base.value(), delta.value())?)?)),
+            None => Ok(Some(V::deserialize(base.value())?)),
+        }
+    }
+}
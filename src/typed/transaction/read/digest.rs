@@ -0,0 +1,59 @@
+//! Read transaction methods that compute Merkle digests of tables.
+
+use crate::Error;
+use crate::indexing::HasTable;
+use crate::typed::digest::{TableDigest, digest_range};
+use crate::typed::transaction::read::{RedbReadOnlyTable, Transaction};
+use redb::TableDefinition;
+use std::ops::RangeBounds;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Computes a Merkle digest over every entry in `V`'s primary table.
+    ///
+    /// Compare digests of the same table in two databases to cheaply check whether they hold the
+    /// same records. If they differ, use [`Transaction::range_digest`] to narrow down which key
+    /// ranges differ before synchronizing. A table that doesn't exist yet has an empty digest.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let primary = primary_db.read()?.table_digest::<Creature>()?;
+    /// let replica = replica_db.read()?.table_digest::<Creature>()?;
+    /// if primary != replica {
+    ///     println!("replica is out of date");
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn table_digest<V: HasTable>(&self) -> Result<TableDigest, Error> {
+        self.range_digest::<V>(..)
+    }
+
+    /// Computes a Merkle digest over the entries of `V`'s primary table whose serialized primary
+    /// keys are within the given range.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn range_digest<'r, V: HasTable>(
+        &self,
+        range: impl RangeBounds<&'r [u8]> + 'r,
+    ) -> Result<TableDigest, Error> {
+        let primary_table: RedbReadOnlyTable =
+            match self.redb.open_table(
+                TableDefinition::new(&self.namespace.table_name(V::table_name()))
+            ) {
+                Ok(table) => table,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(TableDigest::empty()),
+                Err(error) => return Err(error.into()),
+            };
+
+        digest_range(&primary_table, range)
+    }
+}
@@ -0,0 +1,37 @@
+//! Read transaction methods that read record history.
+
+use crate::indexing::HasTable;
+use crate::typed::history::Historied;
+use crate::typed::transaction::QuerySource;
+use crate::typed::transaction::read::Transaction;
+use crate::{Codec, Error};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Opens a view of `V`'s current records and their history, which can read a record's earlier
+    /// versions, or a record as it was at a given time.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::HistoryNotDeclared`] if the record type doesn't declare a history table
+    ///   with [`HasTable::history_table_name`].
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn historied<K, V>(&self) -> Result<Historied<K, V>, Error>
+    where
+        K: Codec<K>,
+        V: Codec<V> + HasTable,
+    {
+        let history_table_name = V::history_table_name()
+            .ok_or(Error::HistoryNotDeclared { table: V::table_name() })?;
+
+        Ok(Historied::new(
+            self.open_raw_index_table(V::table_name())?,
+            self.open_raw_index_table(history_table_name)?,
+            self.record_context(V::table_name()),
+        ))
+    }
+}
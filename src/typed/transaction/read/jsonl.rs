@@ -0,0 +1,77 @@
+//! Read transaction methods that export records as JSON Lines.
+
+use crate::indexing::HasTable;
+use crate::redaction::Redacted;
+use crate::typed::transaction::QuerySource;
+use crate::typed::transaction::read::{RedbReadOnlyTable, Transaction};
+use crate::{Codec, Error};
+use redb::{ReadableTable, TableDefinition};
+use std::io::Write;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Writes every record in `V`'s primary table as [JSON Lines](https://jsonlines.org/): one
+    /// JSON object per line, in primary key order. Returns the number of records written.
+    ///
+    /// Unlike an archive, the output doesn't depend on the serializer, compressor, or encryptor
+    /// features, so it can be inspected with tools such as `jq`, loaded into other systems, or
+    /// imported into any `atlatl` database with the write transaction's `import_jsonl` method.
+    ///
+    /// Secondary indexes aren't exported, since they're rebuilt from the records on import.
+    ///
+    /// Records are redacted by the transaction's [`RedactionPolicy`], see
+    /// [`Transaction::with_redaction_policy`]. Dropped records aren't written or counted.
+    ///
+    /// [`RedactionPolicy`]: crate::redaction::RedactionPolicy
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let file = std::fs::File::create("creatures.jsonl")?;
+    /// let exported = db.read()?.export_jsonl::<Creature>(file)?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Json`] if a record can't be represented as JSON. For example, a map
+    ///   with non-string keys.
+    ///
+    /// * Returns [`Error::Io`] if the output could not be written.
+    ///
+    /// * Decoding a record fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn export_jsonl<V>(&self, writer: impl Write) -> Result<u64, Error>
+    where
+        V: serde::Serialize + Codec<V> + HasTable + 'static,
+    {
+        let primary_table: RedbReadOnlyTable =
+            self.redb.open_table(
+                TableDefinition::new(&self.namespace.table_name(V::table_name()))
+            )?;
+
+        let context = self.record_context(V::table_name());
+        let mut writer = std::io::BufWriter::new(writer);
+        let mut exported = 0;
+        for entry in primary_table.iter()? {
+            let (key_guard, value_guard) = entry?;
+            let record: V = context.open(key_guard.value(), value_guard.value())?;
+            let record = match &self.redaction_policy {
+                Some(policy) => match policy.apply(record) {
+                    Redacted::Keep(record) => record,
+                    Redacted::Drop => continue,
+                },
+                None => record,
+            };
+            serde_json::to_writer(&mut writer, &record)?;
+            writer.write_all(b"\n")?;
+            exported += 1;
+        }
+
+        writer.flush()?;
+        Ok(exported)
+    }
+}
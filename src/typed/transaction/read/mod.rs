@@ -1,11 +1,39 @@
 //! Read transaction methods that are routed directly to `redb`.
 
-mod queries;
+mod aggregate;
+mod archive;
+mod audit;
+#[cfg(feature = "blobs")]
+mod blobs;
+mod cached;
+#[cfg(feature = "hash-chain")]
+mod chain;
+mod covering;
+mod delta;
+#[cfg(feature = "digest")]
+mod digest;
+mod history;
+#[cfg(feature = "serde")]
+mod jsonl;
 mod non_unique;
+mod scan;
+mod scrub;
+mod stats;
+#[cfg(feature = "sync")]
+mod sync;
+mod verify;
 
 use crate::Codec;
-use crate::typed::TableRef;
-use crate::typed::transaction::Error;
+use crate::indexing::{HasTable, IndexCorrection, IndexProtection, KeySet};
+use crate::layers::correctors::RepairTotals;
+use crate::layers::encryptors::{KeyBytes, TenantKey};
+use crate::querying::{Query, TopK};
+use crate::redaction::RedactionPolicy;
+use crate::typed::record_layers::{RecordContext, owned_key};
+use crate::typed::shared_cache::SharedCache;
+use crate::typed::{Namespace, TableRef, Tenant};
+use crate::typed::transaction::{Error, QueryEngine, QuerySource};
+use std::sync::Arc;
 
 // -------------------------------------------------------------------------------------------------
 
@@ -16,8 +44,55 @@ pub type RedbReadOnlyTable = redb::ReadOnlyTable<&'static [u8], &'static [u8]>;
 /// A wrapper around a `redb` read transaction.
 ///
 /// Read-only transactions may exist concurrently with writes
+///
+/// Every table is opened in the transaction's [`Namespace`], which is the default namespace unless
+/// another is selected with [`Transaction::in_namespace`].
+///
+/// A transaction begun for a [`Tenant`] also carries the tenant's encryption key, which is returned
+/// by [`Transaction::tenant_key`]. Records with [`RecordLayers`] are decrypted with it, or with the
+/// database's record key in other transactions, see [`Transaction::with_record_key`].
+///
+/// Secondary keys and key sets of the indexes selected by an [`IndexProtection`] are decrypted,
+/// see [`Transaction::with_index_protection`]. Key sets of the indexes selected by an
+/// [`IndexCorrection`] are repaired from their parity data, see
+/// [`Transaction::with_index_correction`].
+///
+/// Records exported from a transaction are redacted by its [`RedactionPolicy`], see
+/// [`Transaction::with_redaction_policy`]. Values repaired on read are counted in its
+/// [`RepairTotals`], see [`Transaction::with_repair_totals`].
+///
+/// [`RecordLayers`]: crate::typed::record_layers::RecordLayers
 #[derive(Debug)]
-pub struct Transaction(redb::ReadTransaction);
+pub struct Transaction {
+    /// The wrapped `redb` read transaction.
+    redb: redb::ReadTransaction,
+
+    /// The namespace that tables are opened in.
+    namespace: Namespace,
+
+    /// The encryption key of the tenant the transaction was begun for, if any.
+    tenant_key: Option<Arc<TenantKey>>,
+
+    /// The key that records with record layers are decrypted with: the tenant's key, or the
+    /// database's record key.
+    record_key: Option<Arc<KeyBytes<'static>>>,
+
+    /// Selects the indexes whose secondary keys and key sets are decrypted.
+    index_protection: Option<Arc<IndexProtection>>,
+
+    /// Selects the indexes whose key sets are repaired from their parity data.
+    index_correction: Option<Arc<IndexCorrection>>,
+
+    /// The shared cache that `get_cached` reads are served from, and the cache epoch taken before
+    /// the transaction began.
+    shared_cache: Option<(Arc<SharedCache>, u64)>,
+
+    /// The redaction policy applied to exported records.
+    redaction_policy: Option<Arc<RedactionPolicy>>,
+
+    /// Counts the values repaired by error correction as they're read.
+    repair_totals: Option<Arc<RepairTotals>>,
+}
 
 // -------------------------------------------------------------------------------------------------
 //
@@ -31,23 +106,192 @@ impl Transaction {
         redb.into()
     }
 
+    /// Opens every table in the given namespace from now on. For example, in the `tenant42`
+    /// namespace, `creatures` is opened as `tenant42.creatures`.
+    #[inline]
+    #[must_use]
+    pub fn in_namespace(self, namespace: Namespace) -> Self {
+        Self { namespace, ..self }
+    }
+
+    /// Opens every table in the tenant's namespace from now on, and carries the tenant's
+    /// encryption key for the records that are read and written.
+    #[inline]
+    #[must_use]
+    pub fn for_tenant(self, tenant: &Tenant) -> Self {
+        let (namespace, key) = tenant.parts();
+        let record_key = Some(owned_key(&key.key_bytes()));
+        Self { namespace, tenant_key: Some(key), record_key, ..self }
+    }
+
+    /// Decrypts records with [`RecordLayers`] with the given key from now on. Transactions begun
+    /// with `Database::read` carry the database's record key already, and tenants' transactions
+    /// carry the tenant's key.
+    ///
+    /// [`RecordLayers`]: crate::typed::record_layers::RecordLayers
+    #[inline]
+    #[must_use]
+    pub fn with_record_key(self, key: &KeyBytes<'_>) -> Self {
+        Self { record_key: Some(owned_key(key)), ..self }
+    }
+
+    /// Decrypts records with the given shared key from now on, see
+    /// [`Transaction::with_record_key`].
+    #[inline]
+    #[must_use]
+    pub(crate) fn with_shared_record_key(self, key: Option<Arc<KeyBytes<'static>>>) -> Self {
+        Self { record_key: key, ..self }
+    }
+
+    /// Returns the namespace that tables are opened in.
+    #[inline]
+    #[must_use]
+    pub const fn namespace(&self) -> &Namespace {
+        &self.namespace
+    }
+
+    /// Returns the encryption key of the tenant this transaction was begun for, or `None` if it
+    /// wasn't begun for a tenant.
+    #[inline]
+    #[must_use]
+    pub fn tenant_key(&self) -> Option<&TenantKey> {
+        self.tenant_key.as_deref()
+    }
+
+    /// Reads the secondary keys and key sets of the indexes selected by the `IndexProtection`
+    /// through the encryption layer from now on.
+    #[inline]
+    #[must_use]
+    pub fn with_index_protection(self, protection: Arc<IndexProtection>) -> Self {
+        Self { index_protection: Some(protection), ..self }
+    }
+
+    /// Repairs the key sets of the indexes selected by the `IndexCorrection` from their parity data
+    /// as they're read from now on.
+    #[inline]
+    #[must_use]
+    pub fn with_index_correction(self, correction: Arc<IndexCorrection>) -> Self {
+        Self { index_correction: Some(correction), ..self }
+    }
+
+    /// Serves `get_cached` reads from the shared cache from now on, at the cache epoch taken
+    /// before the transaction began.
+    #[inline]
+    #[must_use]
+    pub(crate) fn with_shared_cache(self, cache: Option<(Arc<SharedCache>, u64)>) -> Self {
+        Self { shared_cache: cache, ..self }
+    }
+
+    /// Applies the `RedactionPolicy` to every record exported with `export` or `export_jsonl` from
+    /// now on. Transactions begun with `Database::read` carry the database's policy already.
+    #[inline]
+    #[must_use]
+    pub fn with_redaction_policy(self, policy: Arc<RedactionPolicy>) -> Self {
+        Self { redaction_policy: Some(policy), ..self }
+    }
+
+    /// Returns the redaction policy applied to exports, if any.
+    #[inline]
+    #[must_use]
+    pub fn redaction_policy(&self) -> Option<&RedactionPolicy> {
+        self.redaction_policy.as_deref()
+    }
+
+    /// Counts the values repaired by error correction as they're read from now on, in the given
+    /// totals. Transactions begun with `Database::read` count them in the database's totals.
+    #[inline]
+    #[must_use]
+    pub fn with_repair_totals(self, totals: Arc<RepairTotals>) -> Self {
+        Self { repair_totals: Some(totals), ..self }
+    }
+
     /// Open the given table
     ///
+    /// # Errors
+    ///
+    /// * The table doesn't exist, or was created with different key or value types.
+    ///
     /// # Notes
     ///
     /// * This method call is passed-through to the `redb` Rust embedded database.
+    ///
+    /// * The table is opened in the transaction's namespace.
     #[inline]
     pub fn open_table<K, V>(&self, name: &str) -> Result<TableRef<K, V>, Error>
     where
         K: Codec<K>,
         V: Codec<V>,
     {
-        let table_definition = redb::TableDefinition::<&[u8], &[u8]>::new(name);
-        Ok(TableRef::new(self.0.open_table(table_definition)?))
+        let table_name = self.namespace.table_name(name);
+        let table_definition = redb::TableDefinition::<&[u8], &[u8]>::new(&table_name);
+        Ok(TableRef::new(self.redb.open_table(table_definition)?))
+    }
+
+    /// Evaluates a query, returning the primary keys of the matching records.
+    ///
+    /// # Errors
+    ///
+    /// * The `redb::Table` that contains the index data could not be opened.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///
+    /// * Deserialization errors when instantiating a key set from an index entry, or when decoding
+    ///   an index key or record.
+    pub fn query<K, V>(&self, query: impl Into<Query<V>>) -> Result<KeySet, crate::Error>
+    where
+        K: Codec<K>,
+        V: Codec<V> + HasTable,
+    {
+        QueryEngine(self).query::<K, V>(query)
+    }
+
+    /// Returns the first `k` records in the order of a secondary index, as primary key and record
+    /// pairs. For example, the ten newest records by a `CreatedAt` index.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///
+    /// * Deserialization errors when instantiating a key set from an index entry, or when decoding
+    ///   a primary key or record.
+    pub fn top_k<K, V>(&self, top_k: &TopK) -> Result<Vec<(K, V)>, crate::Error>
+    where
+        K: Codec<K>,
+        V: Codec<V> + HasTable,
+    {
+        let table = self.open_table::<K, V>(V::table_name())?;
+
+        QueryEngine(self)
+            .top_k_keys(top_k)?
+            .into_iter()
+            .map(|primary_key_bytes| Ok((
+                K::deserialize(&primary_key_bytes)?,
+                table.get_by_key_bytes(&primary_key_bytes)?,
+            )))
+            .collect()
+    }
+
+    /// Opens a raw index table by name, returning `None` if the table has not been created yet.
+    ///
+    /// Used by query planning and maintenance tools that inspect index entries without decoding
+    /// them.
+    pub(crate) fn open_raw_index_table(
+        &self,
+        name: &str
+    ) -> Result<Option<RedbReadOnlyTable>, crate::Error> {
+        match self.open_readable(name) {
+            Ok(table) => Ok(Some(table)),
+            Err(redb::TableError::TableDoesNotExist(_)) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
     }
 
     /// Open the given table
     ///
+    /// # Errors
+    ///
+    /// * The table doesn't exist, or was created with different key or value types.
+    ///
     /// # Notes
     ///
     /// * This method call is passed directly to the `redb` key-value store.
@@ -61,11 +305,15 @@ impl Transaction {
         K: redb::Key + 'static,
         V: redb::Value + 'static
     {
-        Ok(self.0.open_table(definition)?)
+        Ok(self.redb.open_table(definition)?)
     }
 
     /// Open the given table without a type
     ///
+    /// # Errors
+    ///
+    /// * The table doesn't exist.
+    ///
     /// # Notes
     ///
     /// * This method call is passed directly to the `redb` key-value store.
@@ -75,11 +323,15 @@ impl Transaction {
         &self,
         handle: impl redb::TableHandle,
     ) -> Result<redb::ReadOnlyUntypedTable, Error> {
-        Ok(self.0.open_untyped_table(handle)?)
+        Ok(self.redb.open_untyped_table(handle)?)
     }
 
     /// Open the given table
     ///
+    /// # Errors
+    ///
+    /// * The table doesn't exist, or was created with different key or value types.
+    ///
     /// # Notes
     ///
     /// * This method call is passed directly to the `redb` key-value store.
@@ -93,11 +345,15 @@ impl Transaction {
         K: redb::Key + 'static,
         V: redb::Key + 'static
     {
-        Ok(self.0.open_multimap_table(definition)?)
+        Ok(self.redb.open_multimap_table(definition)?)
     }
 
     /// Open the given table
     ///
+    /// # Errors
+    ///
+    /// * The table doesn't exist.
+    ///
     /// # Notes
     ///
     /// * This method call is passed directly to the `redb` key-value store.
@@ -107,11 +363,15 @@ impl Transaction {
         &self,
         handle: impl redb::MultimapTableHandle,
     ) -> Result<redb::ReadOnlyUntypedMultimapTable, Error> {
-        Ok(self.0.open_untyped_multimap_table(handle)?)
+        Ok(self.redb.open_untyped_multimap_table(handle)?)
     }
 
     /// List all the tables
     ///
+    /// # Errors
+    ///
+    /// * The list of tables couldn't be read from storage.
+    ///
     /// # Notes
     ///
     /// * This method call is passed directly to the `redb` key-value store.
@@ -120,11 +380,15 @@ impl Transaction {
     pub fn list_tables(
         &self
     ) -> Result<impl Iterator<Item = redb::UntypedTableHandle>, Error> {
-        Ok(self.0.list_tables()?)
+        Ok(self.redb.list_tables()?)
     }
 
     /// List all the multimap tables
     ///
+    /// # Errors
+    ///
+    /// * The list of tables couldn't be read from storage.
+    ///
     /// # Notes
     ///
     /// * This method call is passed directly to the `redb` key-value store.
@@ -133,7 +397,7 @@ impl Transaction {
     pub fn list_multimap_tables(
         &self
     ) -> Result<impl Iterator<Item = redb::UntypedMultimapTableHandle>, Error> {
-        Ok(self.0.list_multimap_tables()?)
+        Ok(self.redb.list_multimap_tables()?)
     }
 
     /// Close the transaction
@@ -145,6 +409,10 @@ impl Transaction {
     /// Returns `ReadTransactionStillInUse` error if a table or other object retrieved from the
     /// transaction still references this transaction
     ///
+    /// # Errors
+    ///
+    /// * A table or other object opened from the transaction is still in use.
+    ///
     /// # Notes
     ///
     /// * This method call is passed directly to the `redb` key-value store.
@@ -152,7 +420,7 @@ impl Transaction {
     pub fn close(
         self
     ) -> Result<(), Error> {
-        Ok(self.0.close().map_err(Box::new)?)
+        Ok(self.redb.close().map_err(Box::new)?)
     }
 }
 
@@ -163,6 +431,40 @@ impl Transaction {
 impl From<redb::ReadTransaction> for Transaction {
     /// Converts a `redb` read transaction into an `atlatl` read transaction.
     fn from(redb: redb::ReadTransaction) -> Self {
-        Self(redb)
+        Self {
+            redb,
+            namespace: Namespace::default(),
+            tenant_key: None,
+            record_key: None,
+            index_protection: None,
+            index_correction: None,
+            shared_cache: None,
+            redaction_policy: None,
+            repair_totals: None,
+        }
+    }
+}
+
+impl QuerySource for Transaction {
+    type Table<'t> = RedbReadOnlyTable;
+
+    /// Opens a raw table by name, in the transaction's namespace.
+    fn open_readable(&self, name: &str) -> Result<Self::Table<'_>, redb::TableError> {
+        self.redb.open_table(redb::TableDefinition::new(&self.namespace.table_name(name)))
+    }
+
+    /// Returns the context of the named table's records, in the transaction's namespace.
+    fn record_context(&self, table_name: &str) -> RecordContext {
+        RecordContext::new(self.record_key.clone(), self.namespace.table_name(table_name))
+    }
+
+    /// Returns the transaction's index protection, if any.
+    fn index_protection(&self) -> Option<&IndexProtection> {
+        self.index_protection.as_deref()
+    }
+
+    /// Returns the transaction's index correction, if any.
+    fn index_correction(&self) -> Option<&IndexCorrection> {
+        self.index_correction.as_deref()
     }
 }
\ No newline at end of file
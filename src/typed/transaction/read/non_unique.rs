@@ -1,10 +1,8 @@
 use crate::indexing::HasPrimaryKey;
 use crate::indexing::HasTable;
-use crate::indexing::IndexLookup;
-use crate::indexing::KeySet;
-use crate::indexing::ReadableKeySet;
 use crate::typed::TableRef;
 use crate::typed::transaction::read::RedbReadOnlyTable;
+use crate::typed::transaction::QuerySource;
 use crate::typed::transaction::read::Transaction;
 use crate::{Codec, Error};
 use redb::TableDefinition;
@@ -16,18 +14,26 @@ use redb::TableDefinition;
 impl Transaction {
 
     /// Opens a read-only typed table by name.
+    ///
+    /// # Errors
+    ///
+    /// * The table doesn't exist, or was created with different key or value types.
     pub fn table<K, V>(&self, name: &str) -> Result<TableRef<K, V>, Error>
     where
         K: Codec<K>,
         V: Codec<V>,
     {
-        let table_definition = TableDefinition::<&[u8], &[u8]>::new(name);
-        let redb_table = self.0.open_table(table_definition)?;
+        let table_name = self.namespace.table_name(name);
+        let table_definition = TableDefinition::<&[u8], &[u8]>::new(&table_name);
+        let redb_table = self.redb.open_table(table_definition)?;
         Ok(TableRef::new(redb_table))
     }
 
     /// Retrieves a value by the specified primary key, if it exists.
     ///
+    /// If the value had to be repaired using error correction, a
+    /// [`RepairEvent`](crate::layers::correctors::RepairEvent) is reported for this table and key.
+    ///
     /// # Errors
     ///
     /// Returns an error if:
@@ -44,12 +50,21 @@ impl Transaction {
         PK: Codec<PK>,
         V: HasTable + HasPrimaryKey<'pk, PK> + Codec<V>
     {
-        let primary_table: RedbReadOnlyTable = self.0.open_table(
-            TableDefinition::new(V::table_name())
+        let primary_table: RedbReadOnlyTable = self.redb.open_table(
+            TableDefinition::new(&self.namespace.table_name(V::table_name()))
         )?;
 
-        if let Some(value) = primary_table.get(&*PK::serialize(primary_key)?)? {
-            Ok(Some(V::deserialize(value.value())?))
+        let primary_key_bytes = PK::serialize(primary_key)?;
+        if let Some(value) = primary_table.get(&*primary_key_bytes)? {
+            // Any error correction repairs performed while decoding are reported against this
+            // table and key:
+            let value = crate::layers::correctors::with_repair_context(
+                self.repair_totals.as_ref(),
+                V::table_name(),
+                &primary_key_bytes,
+                || self.open_record::<V>(&primary_key_bytes, value.value())
+            )?;
+            Ok(Some(value))
         } else {
             Ok(None)
         }
@@ -57,6 +72,7 @@ impl Transaction {
 
 
 
+    /*
     /// Retrieves a value by the specified index key, if it exists.
     ///
     /// This `get` implementation is specifically for performing look-ups using unique indicies.
@@ -75,19 +91,18 @@ impl Transaction {
     /// * Decoding the value fails,
     /// * Opening a table fails, or
     /// * If a storage error occurs.
-    /*
     fn get_unique<I>(&self, index_key: &I) -> Result<Option<I::Field>, Error>
     where
         I: IndexableKey,
     {
-        let index_table: RedbReadOnlyTable = self.0.open_table(
-            TableDefinition::new(index_key.index_name())
+        let index_table: RedbReadOnlyTable = self.redb.open_table(
+            TableDefinition::new(&self.namespace.table_name(index_key.index_name()))
         )?;
 
         match index_table.get(&*index_key.to_bytes()?)? {
             Some(primary_key_bytes) => {
-                let primary_table: RedbReadOnlyTable = self.0.open_table(
-                    TableDefinition::new(I::table_name())
+                let primary_table: RedbReadOnlyTable = self.redb.open_table(
+                    TableDefinition::new(&self.namespace.table_name(I::table_name()))
                 )?;
 
                 let result = match primary_table.get(primary_key_bytes.value())? {
@@ -129,8 +144,8 @@ impl Transaction {
         V: Codec<V>,
         I: IndexableKey,
     {
-        let index_table: RedbReadOnlyTable = self.0.open_table(
-            TableDefinition::new(index_key.index_name())
+        let index_table: RedbReadOnlyTable = self.redb.open_table(
+            TableDefinition::new(&self.namespace.table_name(index_key.index_name()))
         )?;
 
         // Lookup the serialized index set (the set of primary keys)
@@ -142,8 +157,8 @@ impl Transaction {
         let keys = KeySet::from_bytes(index_bytes.value())?;
 
         // Prepare the primary table for fetching actual records
-        let redb_primary_table: redb::RedbReadOnlyTable::<&[u8], &[u8]> = self.0.open_table(
-            TableDefinition::new(I::table_name())
+        let redb_primary_table: redb::RedbReadOnlyTable::<&[u8], &[u8]> = self.redb.open_table(
+            TableDefinition::new(&self.namespace.table_name(I::table_name()))
         )?;
 
         let primary_table = TableRef::<K, V>::new(redb_primary_table);
@@ -152,7 +167,7 @@ impl Transaction {
 
         Ok(Some(iterator))
     }
-*/
+
 
     /// Returns an iterator over all primary keys' bytes in the database.
     ///
@@ -172,7 +187,9 @@ impl Transaction {
         K: Codec<K>
     {
         let primary_table: RedbReadOnlyTable =
-            self.0.open_table(TableDefinition::new(primary_table_name))?;
+            self.redb.open_table(
+                TableDefinition::new(&self.namespace.table_name(primary_table_name))
+            )?;
 
         let primary_keys_bytes_iterator = primary_table
             .range::<&[u8]>(..)?
@@ -229,15 +246,17 @@ impl Transaction {
         K: Codec<K>
     {
         let primary_table: RedbReadOnlyTable =
-            self.0.open_table(TableDefinition::new(primary_table_name))?;
+            self.redb.open_table(
+                TableDefinition::new(&self.namespace.table_name(primary_table_name))
+            )?;
 
         let primary_key_iterator = primary_table
             .range::<&[u8]>(..)?
             .filter_map(|result| result
-                .map(|(key_guard, _)| if !exclusions.contains(key_guard.value()) {
-                    Some(key_guard.value().to_vec())
-                } else {
+                .map(|(key_guard, _)| if exclusions.contains(key_guard.value()) {
                     None
+                } else {
+                    Some(key_guard.value().to_vec())
                 })
                 .map_err(Into::into)
                 .transpose()
@@ -289,15 +308,7 @@ impl Transaction {
         V: Codec<V>,
         I: IndexLookup + ?Sized
     {
-        let index_table: RedbReadOnlyTable =
-            self.0.open_table(TableDefinition::new(index_lookup.index_name()))?;
-
-        let keys_iterator = index_table.get(&*index_lookup.index_key_bytes()?)?
-            .map(|index_bytes| KeySet::from_bytes(index_bytes.value()))
-            .transpose()?
-            .unwrap_or_default()
-            .0
-            .into_iter();
+        let keys_iterator = self.get_index_keys::<K, V, I>(index_lookup)?.into_iter();
 
         Ok(keys_iterator)
     }
@@ -317,17 +328,26 @@ impl Transaction {
         I: IndexLookup + ?Sized
     {
         let index_table: RedbReadOnlyTable =
-            self.0.open_table(TableDefinition::new(index_lookup.index_name()))?;
-
-        let key_set = index_table.get(&*index_lookup.index_key_bytes()?)?
-            .map(|index_bytes| KeySet::from_bytes(index_bytes.value()))
+            self.redb.open_table(
+                TableDefinition::new(&self.namespace.table_name(index_lookup.index_name()))
+            )?;
+        let index_key_bytes = self.lookup_key(&*index_lookup)?;
+
+        let key_set = index_table.get(&*index_key_bytes)?
+            .map(|index_bytes| KeySet::from_bytes(&self.open_index_value(
+                index_lookup.index_name(),
+                &index_key_bytes,
+                index_bytes.value(),
+            )?))
             .transpose()?
             .unwrap_or_default();
 
-        Ok(key_set)
+        // A very large key set is sharded across several rows:
+        let overflow = self.overflow_keys(index_lookup.index_name(), &index_key_bytes, key_set.len())?;
+        Ok(key_set.union(overflow.unwrap_or_default()))
     }
 
-/*
+
     pub fn get_index_values<K, V, I>(
         &self,
         index_lookup: &I
@@ -385,6 +405,7 @@ where
 
 
 
+/*
 pub struct NonUniqueResultIterator<K, V>
 where
     K: Codec<K>,
@@ -407,6 +428,7 @@ where
         Some(self.table.get_by_key_bytes(&key_bytes))
     }
 }
+*/
 
 
 
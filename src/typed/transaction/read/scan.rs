@@ -0,0 +1,167 @@
+//! Read transaction methods that scan a table in batches, across many read transactions.
+
+use crate::indexing::HasTable;
+use crate::typed::scan::{ScanBatch, ScanToken};
+use crate::typed::transaction::QuerySource;
+use crate::typed::transaction::read::Transaction;
+use crate::{Codec, Error};
+use redb::TableDefinition;
+use std::ops::Bound;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Reads the next batch of up to `batch_size` records of type `V`, in the table's key order.
+    ///
+    /// Pass `None` to start a scan, and then the token from each batch to read the next, in a new
+    /// read transaction each time if need be. This lets a background job work through a huge table
+    /// without holding one read transaction open for the whole scan. The returned token is `None`
+    /// once the table has been read to the end. See [`crate::typed::scan`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let mut token = None;
+    /// loop {
+    ///     let batch = db.read()?.scan_resumable::<u64, Creature>(token, 1_000)?;
+    ///     process(batch.entries);
+    ///     match batch.token {
+    ///         Some(next) => token = Some(next),
+    ///         None => break,
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::InvalidScanToken`] if the token was issued for another table, or in
+    ///   another namespace.
+    ///
+    /// * Decoding a key or record fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    #[allow(clippy::needless_pass_by_value, reason = "each batch's token is handed back in turn")]
+    pub fn scan_resumable<K, V>(
+        &self,
+        token: Option<ScanToken>,
+        batch_size: usize,
+    ) -> Result<ScanBatch<K, V>, Error>
+    where
+        K: Codec<K>,
+        V: Codec<V> + HasTable,
+    {
+        let table_name = self.namespace.table_name(V::table_name());
+        if let Some(token) = &token {
+            token.check_table(&table_name)?;
+        }
+
+        let table = match self.redb.open_table(TableDefinition::<&[u8], &[u8]>::new(&table_name)) {
+            Ok(table) => table,
+            Err(redb::TableError::TableDoesNotExist(_)) =>
+                return Ok(ScanBatch { entries: Vec::new(), token: None }),
+            Err(error) => return Err(error.into()),
+        };
+
+        let start = token.as_ref().map_or(Bound::Unbounded, |token| Bound::Excluded(token.after()));
+        let mut range = table.range::<&[u8]>((start, Bound::Unbounded))?;
+
+        let context = self.record_context(V::table_name());
+        // The batch size may be far larger than the table, so it's not preallocated:
+        let mut entries = Vec::new();
+        let mut last_key = None;
+        for entry in range.by_ref().take(batch_size.max(1)) {
+            let (key_guard, value_guard) = entry?;
+            let key = K::deserialize(key_guard.value())?;
+            entries.push((key, context.open(key_guard.value(), value_guard.value())?));
+            last_key = Some(key_guard.value().to_vec());
+        }
+
+        // The table has been read to the end if nothing follows the batch:
+        let token = match (last_key, range.next().is_some()) {
+            (Some(after), true) => Some(ScanToken::new(table_name.into_owned(), after)),
+            _ => None,
+        };
+
+        Ok(ScanBatch { entries, token })
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "writes"))]
+mod tests {
+    use crate::typed::database::Database;
+    use crate::typed::scan::ScanToken;
+    use crate::typed::test_records::Animal;
+    use std::collections::BTreeMap;
+
+    /// Scans the animals table to the end, calling `between` before each batch after the first.
+    fn scan_all(db: &Database, mut between: impl FnMut(usize)) -> BTreeMap<u64, usize> {
+        let mut seen = BTreeMap::new();
+        let mut token: Option<ScanToken> = None;
+        for batch_number in 0.. {
+            if batch_number > 0 {
+                between(batch_number);
+            }
+            let batch = db.read().unwrap().scan_resumable::<u64, Animal>(token, 7).unwrap();
+            assert!(batch.entries.len() <= 7);
+            for (id, animal) in batch.entries {
+                assert_eq!(id, animal.id);
+                *seen.entry(id).or_default() += 1;
+            }
+            match batch.token {
+                Some(next) => token = Some(next),
+                None => break,
+            }
+        }
+        seen
+    }
+
+    #[test]
+    fn returns_every_record_once_across_batches() {
+        let db = Database::in_memory().unwrap();
+        let mut txn = db.write().unwrap();
+        txn.bulk_insert::<u64, Animal>((1..=50).map(|id| Animal::new(id, "Lion", "Savannah")))
+            .unwrap();
+        txn.commit().unwrap();
+
+        let seen = scan_all(&db, |_| {});
+        assert_eq!(seen.keys().copied().collect::<Vec<_>>(), (1..=50).collect::<Vec<_>>());
+        assert!(seen.values().all(|count| *count == 1));
+
+        let whole = db.read().unwrap().scan_resumable::<u64, Animal>(None, usize::MAX).unwrap();
+        assert_eq!((whole.entries.len(), whole.token), (50, None));
+
+        let empty = Database::in_memory().unwrap();
+        assert!(scan_all(&empty, |_| panic!("an empty table has one batch")).is_empty());
+    }
+
+    #[test]
+    fn concurrent_inserts_are_seen_once_if_after_the_token() {
+        let db = Database::in_memory().unwrap();
+        let mut txn = db.write().unwrap();
+        txn.bulk_insert::<u64, Animal>((10..=60).map(|id| Animal::new(id, "Lion", "Savannah")))
+            .unwrap();
+        txn.commit().unwrap();
+
+        // Between batches, one record is inserted before every key, and one after:
+        let seen = scan_all(&db, |batch_number| {
+            let batch_number = batch_number as u64;
+            let mut txn = db.write().unwrap();
+            txn.bulk_insert::<u64, Animal>([
+                Animal::new(batch_number, "Early", "Arctic"),
+                Animal::new(200 + batch_number, "Late", "Arctic"),
+            ]).unwrap();
+            txn.commit().unwrap();
+        });
+
+        assert!(seen.values().all(|count| *count == 1));
+        assert!((10..=60).all(|id| seen.contains_key(&id)));
+        assert!(seen.keys().any(|id| *id > 200));
+        assert!(!seen.keys().any(|id| *id < 10));
+    }
+}
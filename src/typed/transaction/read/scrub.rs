@@ -0,0 +1,137 @@
+//! Read transaction methods that scrub stored records for corruption.
+
+use crate::Error;
+use crate::layers::encryptors::AssociatedData;
+use crate::typed::scrub::{ScrubFailure, ScrubReport, Scrubber};
+use crate::typed::transaction::read::{RedbReadOnlyTable, Transaction};
+use redb::{ReadableTable, TableDefinition, TableHandle};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Reads every record in the transaction's namespace back through its layer stack, which
+    /// verifies error correction checksums, decrypts, decompresses, and deserializes each record,
+    /// and reports every record that couldn't be read along with the layer that failed.
+    ///
+    /// Nothing is written, and records repaired by error correction are only counted, not
+    /// rewritten. Tables without a record type registered with the [`Scrubber`] are skipped, and
+    /// listed in [`ScrubReport::unverified_tables`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let scrubber = Scrubber::new(key).record::<Creature>();
+    /// let report = db.read()?.scrub(&scrubber)?;
+    /// assert!(report.is_clean());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Unreadable records are reported in [`ScrubReport::failures`], rather than returned as
+    /// errors.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn scrub(&self, scrubber: &Scrubber<'_>) -> Result<ScrubReport, Error> {
+        let mut report = ScrubReport::default();
+        let table_names: Vec<String> = self.redb
+            .list_tables()?
+            .filter_map(|table| self.namespace.strip(table.name()).map(String::from))
+            .collect();
+
+        for table_name in table_names {
+            let full_table_name = self.namespace.table_name(&table_name);
+            let table: RedbReadOnlyTable =
+                match self.redb.open_table(TableDefinition::new(&full_table_name)) {
+                    Ok(table) => table,
+                    // Internal tables, such as the index statistics, aren't record tables:
+                    Err(redb::TableError::TableTypeMismatch { .. }) => {
+                        report.unverified_tables.push(table_name);
+                        continue;
+                    },
+                    Err(error) => return Err(error.into()),
+                };
+
+            if !scrubber.verifies(&table_name) {
+                report.unverified_tables.push(table_name);
+                continue;
+            }
+
+            for entry in table.iter()? {
+                let (key_guard, value_guard) = entry?;
+                report.entries_scrubbed += 1;
+                let associated_data = AssociatedData::new(&full_table_name, key_guard.value());
+                match scrubber.verify(&table_name, &associated_data, value_guard.value()) {
+                    Some(Ok(verified)) if verified.shards_recovered.is_some() =>
+                        report.entries_recovered += 1,
+                    Some(Err(failure)) => report.failures.push(ScrubFailure {
+                        table: table_name.clone(),
+                        key: key_guard.value().to_vec(),
+                        layer: failure.layer,
+                        error: failure.source.to_string(),
+                    }),
+                    _ => {},
+                }
+            }
+
+            report.verified_tables.push(table_name);
+        }
+
+        Ok(report)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "writes", feature = "redb-pass-through"))]
+mod tests {
+    use super::*;
+    use crate::Codec;
+    use crate::layers::core::Layer;
+    use crate::layers::encryptors::{KEY_SIZE, KeyBytes};
+    use crate::typed::database::Database;
+    use crate::typed::test_records::{Animal, Letter};
+
+    const LETTERS: TableDefinition<&[u8], &[u8]> = TableDefinition::new("letters");
+
+    fn primary_key(id: u64) -> Vec<u8> {
+        <u64 as Codec<u64>>::serialize(&id).unwrap()
+    }
+
+    #[test]
+    fn records_written_by_transactions_are_verified() {
+        let key = KeyBytes::from_array(&[4; KEY_SIZE]);
+        let mut db = Database::in_memory().unwrap();
+        db.set_record_key(&key);
+        let mut txn = db.write().unwrap();
+        txn.bulk_insert::<u64, Letter>([
+            Letter::new(1, "Wile", "Acme order, please."),
+            Letter::new(2, "Wile", "The rocket skates arrived broken."),
+        ]).unwrap();
+        txn.insert::<u64, Animal>(&Animal::new(1, "Coyote", "Desert")).unwrap();
+        txn.commit().unwrap();
+
+        let scrubber = Scrubber::new(KeyBytes::from_array(&key)).record::<Letter>();
+        let report = db.read().unwrap().scrub(&scrubber).unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report.verified_tables, ["letters"]);
+        assert!(report.unverified_tables.iter().any(|table| table == "animals"));
+        assert_eq!(report.entries_scrubbed, 2);
+
+        // A value moved to another row fails its authentication check:
+        let txn = db.write().unwrap();
+        let mut letters = txn.open_redb_table(LETTERS).unwrap();
+        let moved = letters.get(&*primary_key(1)).unwrap().unwrap().value().to_vec();
+        letters.insert(&*primary_key(2), &*moved).unwrap();
+        drop(letters);
+        txn.commit().unwrap();
+
+        let report = db.read().unwrap().scrub(&scrubber).unwrap();
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].key, primary_key(2));
+        assert_eq!(report.failures[0].layer, Layer::Encryption);
+    }
+}
@@ -0,0 +1,47 @@
+//! Read transaction methods that return secondary index statistics.
+
+use crate::Error;
+use crate::indexing::{Index, IndexStats, STATS_TABLE_NAME};
+use crate::typed::transaction::read::Transaction;
+use redb::TableDefinition;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Returns the statistics for an index, or `None` if the index has never been analyzed with
+    /// the write transaction's `analyze` method.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Corrupted`] if the stored statistics can't be decoded.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn index_stats<I: Index>(&self) -> Result<Option<IndexStats>, Error> {
+        self.index_stats_by_name(I::index_name())
+    }
+
+    /// Returns the statistics for the named index, or `None` if it has never been analyzed.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Corrupted`] if the stored statistics can't be decoded.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn index_stats_by_name(&self, index_name: &str) -> Result<Option<IndexStats>, Error> {
+        let table_name = self.namespace.table_name(STATS_TABLE_NAME);
+        let stats_table = match self.redb.open_table(
+            TableDefinition::<&str, &[u8]>::new(&table_name)
+        ) {
+            Ok(stats_table) => stats_table,
+            Err(redb::TableError::TableDoesNotExist(_)) => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+
+        stats_table
+            .get(index_name)?
+            .map(|row| IndexStats::from_bytes(row.value()))
+            .transpose()
+    }
+}
@@ -0,0 +1,217 @@
+//! Read transaction methods that drive an anti-entropy sync, as the primary.
+
+use crate::Error;
+use crate::typed::digest::digest_range;
+use crate::typed::sync::{
+    Frame, KeyRange, SYNC_LEAF_ENTRIES, SyncReport, Transport, check_hello, exchange, hello,
+    split_points,
+};
+use crate::typed::transaction::read::{RedbReadOnlyTable, Transaction};
+use redb::{TableDefinition, TableHandle};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Synchronizes a replica with this database, as of the start of this transaction. The replica
+    /// must be answering on the other end of `transport` with the write transaction's `sync_from`
+    /// method.
+    ///
+    /// Every byte-keyed table in the transaction's namespace, including secondary indexes, is
+    /// compared by Merkle digest. Only the key ranges that differ are shipped, so syncing a
+    /// replica that's nearly up to date is cheap. See the [`sync`](crate::typed::sync) module for
+    /// the protocol.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let (mut primary_end, mut replica_end) = sync::channel();
+    /// let replica = std::thread::spawn(move || {
+    ///     let mut txn = replica_db.write()?;
+    ///     txn.sync_from(&mut replica_end)?;
+    ///     txn.commit()
+    /// });
+    /// let report = primary_db.read()?.sync_to(&mut primary_end)?;
+    /// replica.join().unwrap()?;
+    /// println!("shipped {} entries", report.entries_shipped);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::SyncLayerMismatch`] if the replica was built with different serializer,
+    ///   compressor, encryptor, corrector, or key set features.
+    ///
+    /// * Returns [`Error::SyncProtocol`] if the replica answered with a malformed or unexpected
+    ///   frame.
+    ///
+    /// * The transport failed, see [`Transport`].
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn sync_to(&self, transport: &mut impl Transport) -> Result<SyncReport, Error> {
+        check_hello(exchange(transport, &hello())?)?;
+
+        let table_names: Vec<String> = self.redb
+            .list_tables()?
+            .filter_map(|table| self.namespace.strip(table.name()).map(String::from))
+            .collect();
+
+        let mut report = SyncReport::default();
+        for table_name in table_names {
+            // Only byte-keyed tables are synchronized, which skips `atlatl`'s change log:
+            let table: RedbReadOnlyTable =
+                match self.redb.open_table(
+                    TableDefinition::new(&self.namespace.table_name(&table_name))
+                ) {
+                    Ok(table) => table,
+                    Err(redb::TableError::TableTypeMismatch { .. }) => continue,
+                    Err(error) => return Err(error.into()),
+                };
+            report.tables += 1;
+            sync_range(transport, &table, &table_name, KeyRange::default(), &mut report)?;
+        }
+
+        transport.send(Frame::Done.encode()?)?;
+        Ok(report)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Compares a key range with the replica's, and if it differs, either ships its entries or splits
+/// it into smaller ranges and compares those.
+///
+/// # Errors
+///
+/// * See [`Transaction::sync_to`].
+fn sync_range(
+    transport: &mut impl Transport,
+    table: &RedbReadOnlyTable,
+    table_name: &str,
+    range: KeyRange,
+    report: &mut SyncReport,
+) -> Result<(), Error> {
+    let digest = digest_range(table, range.bounds())?;
+    report.ranges_compared += 1;
+
+    let frame = Frame::Digest { table: table_name.to_string(), range: range.clone(), digest };
+    match exchange(transport, &frame)? {
+        Frame::Matches(true) => return Ok(()),
+        Frame::Matches(false) => {},
+        _ => return Err(Error::SyncProtocol { reason: "expected a matches frame" }),
+    }
+
+    if digest.entries <= SYNC_LEAF_ENTRIES {
+        let entries = table
+            .range::<&[u8]>(range.bounds())?
+            .map(|entry| {
+                let (key, value) = entry?;
+                Ok((key.value().to_vec(), value.value().to_vec()))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let shipped = entries.len() as u64;
+
+        let frame = Frame::Entries { table: table_name.to_string(), range, entries };
+        let Frame::Applied { removed } = exchange(transport, &frame)? else {
+            return Err(Error::SyncProtocol { reason: "expected an applied frame" });
+        };
+        report.ranges_shipped += 1;
+        report.entries_shipped += shipped;
+        report.entries_removed += removed;
+        return Ok(());
+    }
+
+    let boundaries = split_points(table, &range, digest.entries)?;
+    for smaller_range in range.split(boundaries) {
+        sync_range(transport, table, table_name, smaller_range, report)?;
+    }
+    Ok(())
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "writes"))]
+mod tests {
+    use crate::Error;
+    use crate::querying::Query;
+    use crate::typed::database::Database;
+    use crate::typed::sync::{SyncReport, channel};
+    use crate::typed::test_records::{Animal, Enclosure};
+    use std::collections::BTreeSet;
+
+    /// Every animal, and the primary keys of the animals in each enclosure.
+    type Contents = (Vec<(u64, Animal)>, Vec<BTreeSet<Vec<u8>>>);
+
+    /// Syncs the replica with the primary, as two threads talking over a channel.
+    fn sync(primary: &Database, replica: &Database) -> SyncReport {
+        let (mut primary_end, mut replica_end) = channel();
+        std::thread::scope(|scope| {
+            let replica = scope.spawn(move || {
+                let mut txn = replica.write()?;
+                let report = txn.sync_from(&mut replica_end)?;
+                txn.commit()?;
+                Ok::<_, Error>(report)
+            });
+            let report = primary.read().unwrap().sync_to(&mut primary_end).unwrap();
+            assert_eq!(replica.join().unwrap().unwrap(), report);
+            report
+        })
+    }
+
+    /// Returns every animal, and the primary keys of the animals in each enclosure, as read
+    /// through the index.
+    fn contents(db: &Database) -> Contents {
+        let txn = db.read().unwrap();
+        let animals = txn.scan_resumable::<u64, Animal>(None, usize::MAX).unwrap().entries;
+        let enclosures = ["Savannah", "Arctic", "Reef"].map(|enclosure| {
+            let lookup = Query::lookup(Enclosure(enclosure.into()));
+            txn.query::<u64, Animal>(lookup).unwrap().into_iter().collect()
+        });
+        (animals, enclosures.into())
+    }
+
+    #[test]
+    fn replicas_match_the_primary_after_each_sync() {
+        let primary = Database::in_memory().unwrap();
+        let replica = Database::in_memory().unwrap();
+        let mut txn = primary.write().unwrap();
+        txn.bulk_insert::<u64, Animal>((1..=400).map(|id| {
+            Animal::new(id, "Lion", if id % 2 == 0 { "Savannah" } else { "Arctic" })
+        })).unwrap();
+        txn.commit().unwrap();
+
+        // A replica that has records the primary doesn't loses them:
+        let mut txn = replica.write().unwrap();
+        txn.bulk_insert::<u64, Animal>([Animal::new(999, "Stray", "Reef")]).unwrap();
+        txn.commit().unwrap();
+
+        let first = sync(&primary, &replica);
+        assert!(!first.was_in_sync());
+        assert!(first.entries_removed >= 1);
+        assert_eq!(contents(&replica), contents(&primary));
+
+        // Later, only the ranges that changed are shipped:
+        let mut txn = primary.write().unwrap();
+        txn.bulk_insert::<u64, Animal>([
+            Animal::new(7, "Lion", "Reef"),
+            Animal::new(401, "Penguin", "Arctic"),
+        ]).unwrap();
+        txn.delete_matching::<u64, Animal>(Query::lookup(Enclosure("Savannah".into())), Some(1))
+            .unwrap();
+        txn.commit().unwrap();
+
+        let second = sync(&primary, &replica);
+        assert!(!second.was_in_sync());
+        assert!(second.entries_shipped < first.entries_shipped);
+        assert_eq!(contents(&replica), contents(&primary));
+        assert_eq!(contents(&replica).0.len(), 400);
+
+        let third = sync(&primary, &replica);
+        assert!(third.was_in_sync());
+        assert_eq!(third.entries_shipped, 0);
+    }
+}
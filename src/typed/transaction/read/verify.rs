@@ -0,0 +1,30 @@
+//! Read transaction methods that cross-check secondary indexes against their primary table.
+
+use crate::indexing::{HasTable, Indexable, IndexReport};
+use crate::typed::transaction::find_index_issues;
+use crate::typed::transaction::read::Transaction;
+use crate::{Codec, Error};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Cross-checks every secondary index of a record type against its primary table, reporting
+    /// index entries that are missing, entries that point to records that don't exist or no
+    /// longer have that key, and `Unique` indexes with more than one record per key.
+    ///
+    /// Nothing is changed. Use the write transaction's `repair_indexes` method to fix the issues.
+    ///
+    /// # Errors
+    ///
+    /// * Decoding a record or key set fails, or encoding a secondary key fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn verify_indexes<V>(&self) -> Result<IndexReport, Error>
+    where
+        V: for<'i> Indexable<'i> + Codec<V> + HasTable,
+    {
+        find_index_issues::<V, _>(self)
+    }
+}
@@ -0,0 +1,138 @@
+//! Cross-checks a record type's secondary indexes against its primary table, inside either a read
+//! or a write transaction.
+
+use crate::indexing::{
+    HasTable, Indexable, IndexIssue, IndexKeyBytes, IndexKind, IndexReport, KeySet, ReadableKeySet
+};
+use crate::typed::transaction::QuerySource;
+use crate::{Codec, Error};
+use redb::ReadableTable;
+use std::collections::{BTreeMap, BTreeSet};
+
+// -------------------------------------------------------------------------------------------------
+
+/// Primary keys grouped by serialized secondary key.
+type Entries = BTreeMap<Vec<u8>, BTreeSet<Vec<u8>>>;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Finds every inconsistency between a record type's primary table and the secondary indexes its
+/// records participate in.
+///
+/// The expected index entries of every record, and then each index table in turn, are held in
+/// memory while checking. Index tables that no record refers to aren't checked.
+///
+/// # Errors
+///
+/// * Decoding a record or key set fails, or encoding a secondary key fails.
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+pub fn find_index_issues<V, T>(source: &T) -> Result<IndexReport, Error>
+where
+    V: for<'i> Indexable<'i> + Codec<V> + HasTable,
+    T: QuerySource,
+{
+    let mut report = IndexReport::default();
+    let mut expected: BTreeMap<&'static str, (IndexKind, Entries)> = BTreeMap::new();
+
+    let primary_table = match source.open_readable(V::table_name()) {
+        Ok(primary_table) => primary_table,
+        Err(redb::TableError::TableDoesNotExist(_)) => return Ok(report),
+        Err(error) => return Err(error.into()),
+    };
+    let context = source.record_context(V::table_name());
+    for entry in primary_table.iter()? {
+        let (key_guard, value_guard) = entry?;
+        report.records_checked += 1;
+        let record: V = context.open(key_guard.value(), value_guard.value())?;
+        let index_keys = IndexKeyBytes::of(&record)?;
+        for index_key in source.protect_index_keys(index_keys) {
+            expected
+                .entry(index_key.index_name)
+                .or_insert_with(|| (index_key.index_kind, BTreeMap::new()))
+                .1
+                .entry(index_key.secondary_key_bytes)
+                .or_default()
+                .insert(key_guard.value().to_vec());
+        }
+    }
+    drop(primary_table);
+
+    let empty = BTreeSet::new();
+
+    for (index_name, (index_kind, expected_entries)) in expected {
+        let actual_entries = read_entries(source, index_name, index_kind)?;
+
+        for (secondary_key_bytes, primary_keys) in &expected_entries {
+            if index_kind == IndexKind::Unique && primary_keys.len() > 1 {
+                report.issues.push(IndexIssue::UniqueViolation {
+                    index_name,
+                    secondary_key_bytes: secondary_key_bytes.clone(),
+                    primary_keys: primary_keys.iter().cloned().collect(),
+                });
+                continue;
+            }
+
+            let actual = actual_entries.get(secondary_key_bytes).unwrap_or(&empty);
+            for primary_key_bytes in primary_keys.difference(actual) {
+                report.issues.push(IndexIssue::MissingEntry {
+                    index_name,
+                    index_kind,
+                    secondary_key_bytes: secondary_key_bytes.clone(),
+                    primary_key_bytes: primary_key_bytes.clone(),
+                });
+            }
+        }
+
+        for (secondary_key_bytes, primary_keys) in &actual_entries {
+            let expected = expected_entries.get(secondary_key_bytes).unwrap_or(&empty);
+            for primary_key_bytes in primary_keys.difference(expected) {
+                report.issues.push(IndexIssue::DanglingKey {
+                    index_name,
+                    index_kind,
+                    secondary_key_bytes: secondary_key_bytes.clone(),
+                    primary_key_bytes: primary_key_bytes.clone(),
+                });
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Reads every entry of an index table. A missing index table has no entries.
+fn read_entries<T: QuerySource>(
+    source: &T,
+    index_name: &str,
+    index_kind: IndexKind,
+) -> Result<Entries, Error> {
+    let index_table = match source.open_readable(index_name) {
+        Ok(index_table) => index_table,
+        Err(redb::TableError::TableDoesNotExist(_)) => return Ok(Entries::new()),
+        Err(error) => return Err(error.into()),
+    };
+
+    let mut entries = Entries::new();
+    for entry in index_table.iter()? {
+        let (secondary_key, value) = entry?;
+        let primary_keys = match index_kind {
+            IndexKind::Unique => {
+                let primary_key_bytes =
+                    source.open_index_value(index_name, secondary_key.value(), value.value())?;
+                BTreeSet::from([primary_key_bytes.into_owned()])
+            },
+            IndexKind::NonUnique => {
+                let key_set = KeySet::from_bytes(
+                    &source.open_index_value(index_name, secondary_key.value(), value.value())?
+                )?;
+                let overflow = source.overflow_keys(index_name, secondary_key.value(), key_set.len())?;
+                key_set.union(overflow.unwrap_or_default()).into_iter().collect()
+            },
+        };
+        entries.insert(secondary_key.value().to_vec(), primary_keys);
+    }
+
+    Ok(entries)
+}
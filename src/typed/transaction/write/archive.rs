@@ -0,0 +1,113 @@
+//! Write transaction methods that import tables from a portable archive.
+
+use crate::Error;
+use crate::typed::archive::{ArchiveItem, ArchiveReader, ArchiveSummary, KeyKind};
+use crate::typed::transaction::write::Transaction;
+use redb::TableDefinition;
+use std::io::Read;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A table being imported, opened with its archived key type.
+enum ImportTable<'txn> {
+    Bytes(redb::Table<'txn, &'static [u8], &'static [u8]>),
+    Str(redb::Table<'txn, &'static str, &'static [u8]>),
+    U64(redb::Table<'txn, u64, &'static [u8]>),
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Imports every table in an archive written by the read transaction's `export` method.
+    ///
+    /// Tables are created in the transaction's namespace if they don't already exist. Archived
+    /// entries overwrite existing entries with the same key, and other existing entries are left
+    /// alone. Nothing is visible to other transactions until this transaction is committed.
+    ///
+    /// If the archive was exported with `ExportScope::Tables`, rebuild the imported tables'
+    /// secondary indexes with `rebuild_index` afterwards.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::ArchiveLayerMismatch`] if the archive was written by a build with
+    ///   different serializer, compressor, encryptor, corrector, or key set features.
+    ///
+    /// * Returns [`Error::MalformedArchive`] if the archive is truncated or malformed. The
+    ///   transaction should be aborted in this case.
+    ///
+    /// * Returns [`Error::Io`] if the archive could not be read.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn import(&mut self, reader: impl Read) -> Result<ArchiveSummary, Error> {
+        let mut archive = ArchiveReader::new(std::io::BufReader::new(reader))?;
+        let mut current: Option<ImportTable<'_>> = None;
+
+        while let Some(item) = archive.next_item()? {
+            match item {
+                ArchiveItem::Table { name, key_kind } => {
+                    // Only one handle to a table may be open at a time:
+                    drop(current.take());
+                    current = Some(self.open_import_table(&name, key_kind)?);
+                },
+                ArchiveItem::Entry { key, value } => {
+                    let Some(table) = &mut current else {
+                        return Err(Error::MalformedArchive { reason: "entry precedes its table" });
+                    };
+                    table.insert(&key, &value)?;
+                },
+            }
+        }
+
+        Ok(archive.into_summary())
+    }
+
+    /// Opens (creating if needed) a table in the transaction's namespace, with the given key type.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    fn open_import_table(&self, name: &str, key_kind: KeyKind) -> Result<ImportTable<'_>, Error> {
+        let full_name = self.namespace.table_name(name);
+        let table = match key_kind {
+            KeyKind::Bytes => ImportTable::Bytes(self.redb.open_table(
+                TableDefinition::new(&full_name)
+            )?),
+            KeyKind::Str => ImportTable::Str(self.redb.open_table(
+                TableDefinition::new(&full_name)
+            )?),
+            KeyKind::U64 => ImportTable::U64(self.redb.open_table(
+                TableDefinition::new(&full_name)
+            )?),
+        };
+        Ok(table)
+    }
+}
+
+impl ImportTable<'_> {
+    /// Inserts an archived entry, decoding its key for the table's key type.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::MalformedArchive`] if the key isn't valid for the table's key type.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        match self {
+            Self::Bytes(table) => { table.insert(key, value)?; },
+            Self::Str(table) => {
+                let key = std::str::from_utf8(key)
+                    .map_err(|_| Error::MalformedArchive { reason: "string key is not UTF-8" })?;
+                table.insert(key, value)?;
+            },
+            Self::U64(table) => {
+                let key = key
+                    .try_into()
+                    .map_err(|_| Error::MalformedArchive { reason: "integer key is not 8 bytes" })?;
+                table.insert(u64::from_le_bytes(key), value)?;
+            },
+        }
+        Ok(())
+    }
+}
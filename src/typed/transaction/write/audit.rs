@@ -0,0 +1,94 @@
+//! Write transaction methods that append to the audit log.
+
+use crate::Error;
+use crate::typed::audit::{AUDIT_LOG_TABLE, Actor, Operation, encode_audit_entry};
+use crate::typed::change_log::last_sequence;
+#[cfg(feature = "hash-chain")]
+use crate::typed::chain::{Log, next_link};
+use crate::typed::history::now_millis;
+use crate::typed::transaction::write::Transaction;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Records the given actor as the author of this transaction's record writes and deletions in
+    /// the audit log.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let mut txn = db.write()?.acting_as(Actor::User("u-1042".to_string()));
+    /// txn.insert::<u64, Creature>(&creature)?;
+    /// txn.commit()?;
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn acting_as(mut self, actor: Actor) -> Self {
+        self.actor = Some(actor);
+        self
+    }
+
+    /// Returns the actor this transaction's changes are attributed to, if one was set with
+    /// [`Transaction::acting_as`].
+    #[inline]
+    #[must_use]
+    pub const fn actor(&self) -> Option<&Actor> {
+        self.actor.as_ref()
+    }
+
+    /// Enables or disables recording this transaction's record writes and deletions in the audit
+    /// log (defaults to the database's setting, see [`Database::enable_audit_log`]).
+    ///
+    /// The same writes are audited as are recorded in the change log: see
+    /// [`Transaction::set_change_log`].
+    ///
+    /// [`Database::enable_audit_log`]: crate::typed::database::Database::enable_audit_log
+    #[inline]
+    pub const fn set_audit_log(&mut self, enabled: bool) {
+        self.audit_log = enabled;
+    }
+
+    /// Returns `true` if this transaction's record writes and deletions are recorded in the audit
+    /// log.
+    #[inline]
+    #[must_use]
+    pub const fn audit_log_enabled(&self) -> bool {
+        self.audit_log
+    }
+
+    /// Appends a record write or deletion in the given table to the audit log, if the audit log
+    /// is enabled for this transaction.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn audit_change(
+        &self,
+        table_name: &str,
+        key_bytes: &[u8],
+        operation: Operation,
+    ) -> Result<(), Error> {
+        if !self.audit_log {
+            return Ok(());
+        }
+
+        let mut audit_log = self.redb.open_table(AUDIT_LOG_TABLE)?;
+        let sequence = last_sequence(&audit_log)? + 1;
+        let table_name = self.namespace.table_name(table_name);
+        let millis = now_millis();
+        let encode = |chain| {
+            let actor = self.actor.as_ref();
+            encode_audit_entry(actor, &table_name, key_bytes, operation, millis, chain)
+        };
+
+        #[cfg(feature = "hash-chain")]
+        let chain = Some(next_link(Log::Audit, &audit_log, sequence, &encode(None))?);
+        #[cfg(not(feature = "hash-chain"))]
+        let chain = None;
+
+        audit_log.insert(sequence, &*encode(chain.as_ref()))?;
+        Ok(())
+    }
+}
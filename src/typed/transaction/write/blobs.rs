@@ -0,0 +1,148 @@
+//! Write transaction methods that store and delete blobs.
+
+use crate::Error;
+use crate::typed::blobs::{
+    BLOB_CHUNKS_TABLE_NAME, BLOB_MANIFESTS_TABLE_NAME, BlobInfo, BlobWriter, CHUNK_HASH_SIZE,
+    Manifest, decode_chunk, encode_chunk,
+};
+use crate::typed::transaction::write::Transaction;
+use redb::{ReadableTable, TableDefinition};
+use std::io::Read;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Starts writing the named blob. Bytes written to the returned [`BlobWriter`] are split into
+    /// chunks and stored as they arrive, and the blob replaces any existing blob with the same name
+    /// once the writer is finished.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let mut writer = txn.blob_writer("videos/coyote.mp4");
+    /// std::io::copy(&mut std::fs::File::open("coyote.mp4")?, &mut writer)?;
+    /// let info = writer.finish()?;
+    /// println!("{} bytes in {} chunks", info.len, info.chunks);
+    /// ```
+    #[must_use]
+    pub fn blob_writer(&mut self, name: &str) -> BlobWriter<'_> {
+        BlobWriter::new(self, name)
+    }
+
+    /// Stores the named blob, reading it from `reader` until its end. Replaces any existing blob
+    /// with the same name.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Io`] if the reader fails.
+    ///
+    /// * Returns [`Error::MalformedBlob`] if the replaced blob's manifest can't be decoded.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn put_blob(&mut self, name: &str, mut reader: impl Read) -> Result<BlobInfo, Error> {
+        let mut writer = self.blob_writer(name);
+        std::io::copy(&mut reader, &mut writer)
+            .map_err(|error| error.downcast::<Error>().unwrap_or_else(Error::Io))?;
+        writer.finish()
+    }
+
+    /// Deletes the named blob, and releases its chunks. Returns `false` if there was no such blob.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::MalformedBlob`] if the blob's manifest can't be decoded.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn delete_blob(&mut self, name: &str) -> Result<bool, Error> {
+        let mut manifest_table: redb::Table<&str, &[u8]> = self.redb.open_table(
+            TableDefinition::new(&self.namespace.table_name(BLOB_MANIFESTS_TABLE_NAME))
+        )?;
+        let Some(manifest) = manifest_table
+            .remove(name)?
+            .map(|manifest| Manifest::decode(name, manifest.value()))
+            .transpose()?
+        else {
+            return Ok(false);
+        };
+        drop(manifest_table);
+
+        self.release_blob_chunks(&manifest)?;
+        Ok(true)
+    }
+
+    /// Adds a reference to a stored chunk, or stores it with a single reference if it isn't stored
+    /// yet. Returns `true` if the chunk was stored.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn retain_blob_chunk(
+        &self,
+        hash: &[u8; CHUNK_HASH_SIZE],
+        chunk: &[u8],
+    ) -> Result<bool, Error> {
+        let mut chunk_table: redb::Table<&[u8], &[u8]> = self.redb.open_table(
+            TableDefinition::new(&self.namespace.table_name(BLOB_CHUNKS_TABLE_NAME))
+        )?;
+        let references = chunk_table
+            .get(hash.as_slice())?
+            .and_then(|row| decode_chunk(row.value()).map(|(references, _)| references));
+
+        chunk_table.insert(
+            hash.as_slice(),
+            &*encode_chunk(references.unwrap_or_default() + 1, chunk),
+        )?;
+        Ok(references.is_none())
+    }
+
+    /// Removes a reference to each of a manifest's chunks, and removes the chunks that are no
+    /// longer referenced.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn release_blob_chunks(&self, manifest: &Manifest) -> Result<(), Error> {
+        let mut chunk_table: redb::Table<&[u8], &[u8]> = self.redb.open_table(
+            TableDefinition::new(&self.namespace.table_name(BLOB_CHUNKS_TABLE_NAME))
+        )?;
+
+        for (hash, _) in &manifest.chunks {
+            let row = chunk_table
+                .get(hash.as_slice())?
+                .and_then(|row| decode_chunk(row.value()).map(|(references, chunk)| {
+                    (references, chunk.to_vec())
+                }));
+            match row {
+                Some((references, chunk)) if references > 1 => {
+                    chunk_table.insert(hash.as_slice(), &*encode_chunk(references - 1, &chunk))?;
+                },
+                _ => { chunk_table.remove(hash.as_slice())?; },
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stores a blob's manifest, and returns the manifest it replaced, if any.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::MalformedBlob`] if the replaced manifest can't be decoded.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn put_blob_manifest(
+        &self,
+        name: &str,
+        manifest: &Manifest,
+    ) -> Result<Option<Manifest>, Error> {
+        let mut manifest_table: redb::Table<&str, &[u8]> = self.redb.open_table(
+            TableDefinition::new(&self.namespace.table_name(BLOB_MANIFESTS_TABLE_NAME))
+        )?;
+        manifest_table
+            .insert(name, &*manifest.encode())?
+            .map(|replaced| Manifest::decode(name, replaced.value()))
+            .transpose()
+    }
+}
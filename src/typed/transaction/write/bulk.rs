@@ -0,0 +1,430 @@
+//! Write transaction methods that insert many records at once, with their secondary index entries
+//! grouped so that each is rewritten once per batch.
+
+use crate::defaults::Defaults;
+use crate::indexing::{
+    HasPrimaryKey, HasTable, Indexable, IndexKeyBytes, IndexKind, References, covering_key
+};
+use crate::typed::transaction::write::Transaction;
+use crate::typed::transaction::QuerySource;
+use crate::validation::Validate;
+use crate::{Codec, Error};
+use redb::{ReadableTable, TableDefinition};
+use std::collections::BTreeMap;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A record prepared for a bulk insert.
+struct Prepared {
+    primary_key_bytes: Vec<u8>,
+    value_bytes: Vec<u8>,
+    index_keys: Vec<IndexKeyBytes>,
+}
+
+/// Index entry → primary keys added and removed.
+type IndexChanges = BTreeMap<(&'static str, Vec<u8>), EntryChanges>;
+
+/// Covering table → covering key → projection, or `None` to remove it.
+type CoveringChanges = BTreeMap<&'static str, BTreeMap<Vec<u8>, Option<Vec<u8>>>>;
+
+/// The primary keys added to and removed from one secondary index entry by a bulk insert.
+struct EntryChanges {
+    index_kind: IndexKind,
+    added: Vec<Vec<u8>>,
+    removed: Vec<Vec<u8>>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl EntryChanges {
+    /// Instantiates the changes to an index entry, before any primary keys are added or removed.
+    const fn new(index_kind: IndexKind) -> Self {
+        Self { index_kind, added: Vec::new(), removed: Vec::new() }
+    }
+}
+
+impl Transaction {
+    /// Inserts many records, with their secondary index entries, reverse index rows, and change log
+    /// entries. Records replace any existing record with the same primary key. Returns the number
+    /// of records written.
+    ///
+    /// This does what [`Transaction::import_jsonl`] does for each record, but batches the work:
+    ///
+    /// * Records are serialized up front, and written to the primary table in primary key order.
+    ///
+    /// * Secondary index changes are grouped by index entry, so that each key set is read,
+    ///   modified, and written once for the whole batch, rather than once per record.
+    ///
+    /// * The batch is charged against the table's quota once.
+    ///
+    /// A primary key that appears more than once keeps its last record. The batch is held in
+    /// memory, so very large imports should be split into batches, or use a [`BulkLoader`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let mut txn = db.write()?;
+    /// let written = txn.bulk_insert::<u64, Creature>(creatures)?;
+    /// txn.commit()?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::ConstraintViolations`] if a record is invalid. Nothing is written in
+    ///   this case.
+    ///
+    /// * Returns [`Error::ForeignKeyViolation`] if a record refers to a primary key that doesn't
+    ///   exist. Nothing is written in this case.
+    ///
+    /// * Returns [`Error::IndexCollision`] if two records of the batch, or a record of the batch
+    ///   and an existing record, have the same key in a `Unique` index. Nothing is written in this
+    ///   case.
+    ///
+    /// * Returns [`Error::QuotaExceeded`] if the batch would grow the table past its quota.
+    ///   Nothing is written in this case.
+    ///
+    /// * Encoding a record or a secondary key fails, or decoding a previous record or a key set
+    ///   fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///
+    /// [`BulkLoader`]: crate::typed::bulk::BulkLoader
+    pub fn bulk_insert<K, V>(&mut self, records: impl IntoIterator<Item = V>) -> Result<u64, Error>
+    where
+        K: Codec<K>,
+        V: for<'v> HasPrimaryKey<'v, K>
+            + for<'i> Indexable<'i>
+            + References
+            + Defaults
+            + Validate
+            + Codec<V>
+            + HasTable,
+    {
+        let context = self.record_context(V::table_name());
+        let mut batch = Vec::new();
+        for record in records {
+            let record = record.with_defaults();
+            record.check()?;
+            self.check_references(record.as_ref())?;
+            let primary_key_bytes = record.as_ref().primary_key().to_bytes()?;
+            batch.push(Prepared {
+                value_bytes: self.seal_record_in(&context, &primary_key_bytes, record.as_ref())?,
+                primary_key_bytes,
+                index_keys: self.protect_index_keys(IndexKeyBytes::of(record.as_ref())?),
+            });
+        }
+
+        // Reversed before the stable sort, so that the last record for a primary key is the one
+        // kept:
+        batch.reverse();
+        batch.sort_by(|a, b| a.primary_key_bytes.cmp(&b.primary_key_bytes));
+        batch.dedup_by(|a, b| a.primary_key_bytes == b.primary_key_bytes);
+
+        let (entries, covering_rows) = self.index_changes::<V>(&batch)?;
+
+        // `Unique` index entries are checked before anything is written:
+        for ((index_name, secondary_key_bytes), changes) in &entries {
+            if changes.index_kind == IndexKind::Unique {
+                self.check_unique_entry(index_name, secondary_key_bytes, changes)?;
+            }
+        }
+
+        let writes: Vec<(&[u8], Option<&[u8]>)> = batch
+            .iter()
+            .map(|prepared| (&*prepared.primary_key_bytes, Some(&*prepared.value_bytes)))
+            .collect();
+        let table_name = self.namespace.table_name(V::table_name());
+        self.charge_quota(&table_name, &writes)?;
+
+        for prepared in &batch {
+            self.record_history(
+                V::table_name(),
+                V::history_table_name(),
+                &prepared.primary_key_bytes,
+                false,
+            )?;
+        }
+
+        let mut primary_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&table_name))?;
+        for prepared in &batch {
+            primary_table.insert(&*prepared.primary_key_bytes, &*prepared.value_bytes)?;
+        }
+        drop(primary_table);
+
+        for ((index_name, secondary_key_bytes), changes) in &entries {
+            let (old_set_size, new_set_size) = match changes.index_kind {
+                IndexKind::Unique =>
+                    self.write_unique_entry(index_name, secondary_key_bytes, changes)?,
+                IndexKind::NonUnique => self.update_key_set(
+                    index_name,
+                    secondary_key_bytes,
+                    &changes.added,
+                    &changes.removed,
+                )?,
+            };
+            self.resize_index_stats(index_name, old_set_size, new_set_size)?;
+        }
+
+        for (covering_table_name, rows) in covering_rows {
+            let mut covering_table: redb::Table<&[u8], &[u8]> =
+                self.redb.open_table(
+                    TableDefinition::new(&self.namespace.table_name(covering_table_name))
+                )?;
+            for (key, projection_bytes) in rows {
+                match projection_bytes {
+                    Some(projection_bytes) => covering_table.insert(&*key, &*projection_bytes)?,
+                    None => covering_table.remove(&*key)?,
+                };
+            }
+        }
+
+        for prepared in &batch {
+            if let Some(reverse_index_name) = V::reverse_index_name() {
+                self.set_reverse_index_row(
+                    reverse_index_name,
+                    &prepared.primary_key_bytes,
+                    &prepared.index_keys,
+                )?;
+            }
+            self.record_change(
+                V::table_name(),
+                &prepared.primary_key_bytes,
+                Some(&prepared.value_bytes),
+            )?;
+        }
+
+        Ok(batch.len() as u64)
+    }
+
+    /// Compares a prepared batch with the records it replaces, and groups the secondary index
+    /// entries and covering rows to be added and removed.
+    ///
+    /// # Errors
+    ///
+    /// * Decoding a replaced record fails, or a secondary key or covering key can't be encoded.
+    ///
+    /// * The primary table can't be read.
+    fn index_changes<V>(&self, batch: &[Prepared]) -> Result<(IndexChanges, CoveringChanges), Error>
+    where
+        V: for<'i> Indexable<'i> + Codec<V> + HasTable,
+    {
+        // Index entry → primary keys added and removed, and covering table → covering key →
+        // projection, or `None` to remove it:
+        let mut entries = IndexChanges::new();
+        let mut covering_rows = CoveringChanges::new();
+
+        let primary_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(
+                TableDefinition::new(&self.namespace.table_name(V::table_name()))
+            )?;
+        let context = self.record_context(V::table_name());
+        for prepared in batch {
+            let old_index_keys = match primary_table.get(&*prepared.primary_key_bytes)? {
+                Some(previous) => {
+                    let previous: V = context.open(&prepared.primary_key_bytes, previous.value())?;
+                    self.protect_index_keys(IndexKeyBytes::of(&previous)?)
+                },
+                None => Vec::new(),
+            };
+
+            // Entries whose projection changed are rewritten, rather than removed:
+            let added = prepared.index_keys.iter().filter(|key| !old_index_keys.contains(key));
+            for index_key in added {
+                entries
+                    .entry((index_key.index_name, index_key.secondary_key_bytes.clone()))
+                    .or_insert_with(|| EntryChanges::new(index_key.index_kind))
+                    .added
+                    .push(prepared.primary_key_bytes.clone());
+                if let Some(covering) = &index_key.covering {
+                    covering_rows.entry(covering.table_name).or_default().insert(
+                        covering_key(&index_key.secondary_key_bytes, &prepared.primary_key_bytes)?,
+                        Some(covering.projection_bytes.clone()),
+                    );
+                }
+            }
+
+            let removed = old_index_keys.into_iter().filter(|key| {
+                !prepared.index_keys.iter().any(|new_key| new_key.is_same_entry(key))
+            });
+            for index_key in removed {
+                if let Some(covering) = &index_key.covering {
+                    covering_rows.entry(covering.table_name).or_default().insert(
+                        covering_key(&index_key.secondary_key_bytes, &prepared.primary_key_bytes)?,
+                        None,
+                    );
+                }
+                entries
+                    .entry((index_key.index_name, index_key.secondary_key_bytes))
+                    .or_insert_with(|| EntryChanges::new(index_key.index_kind))
+                    .removed
+                    .push(prepared.primary_key_bytes.clone());
+            }
+        }
+        drop(primary_table);
+
+        Ok((entries, covering_rows))
+    }
+
+    /// Writes a batch for a [`BulkLoader`]: the records' primary table rows only, in primary key
+    /// order, committed with relaxed durability so that they're only persisted by a later durable
+    /// commit.
+    ///
+    /// # Errors
+    ///
+    /// * See [`BulkLoader::finish`].
+    ///
+    /// [`BulkLoader`]: crate::typed::bulk::BulkLoader
+    /// [`BulkLoader::finish`]: crate::typed::bulk::BulkLoader::finish
+    pub(crate) fn load_batch<K, V>(&mut self, batch: &[V]) -> Result<(), Error>
+    where
+        K: Codec<K>,
+        V: for<'v> HasPrimaryKey<'v, K> + References + Defaults + Validate + Codec<V> + HasTable,
+    {
+        let context = self.record_context(V::table_name());
+        let mut entries = Vec::with_capacity(batch.len());
+        for record in batch {
+            let record = record.with_defaults();
+            record.check()?;
+            self.check_references(record.as_ref())?;
+            let primary_key_bytes = record.as_ref().primary_key().to_bytes()?;
+            let value_bytes = self.seal_record_in(&context, &primary_key_bytes, record.as_ref())?;
+            entries.push((primary_key_bytes, value_bytes));
+        }
+
+        self.redb.set_durability(redb::Durability::None);
+        self.insert_sealed::<V>(entries.iter().map(|(key, value)| (&**key, &**value)))
+    }
+
+    /// Writes encoded records to `V`'s primary table in primary key order, which is the cheapest
+    /// order for `redb`'s B-tree. The sort is stable, so the last of several records with the same
+    /// primary key is written last, and wins.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn insert_sealed<'e, V: HasTable>(
+        &self,
+        entries: impl IntoIterator<Item = (&'e [u8], &'e [u8])>,
+    ) -> Result<(), Error> {
+        let mut entries: Vec<_> = entries.into_iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut primary_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(
+                TableDefinition::new(&self.namespace.table_name(V::table_name()))
+            )?;
+        for (key_bytes, value_bytes) in entries {
+            primary_table.insert(key_bytes, value_bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that a bulk insert's changes to a `Unique` index entry leave it pointing to at most
+    /// one primary key.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::IndexCollision`] if more than one record of the batch adds the entry, or
+    ///   if it points to an existing record that the batch doesn't move off it.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    fn check_unique_entry(
+        &self,
+        index_name: &'static str,
+        secondary_key_bytes: &[u8],
+        changes: &EntryChanges,
+    ) -> Result<(), Error> {
+        let collision = || Error::IndexCollision {
+            index: index_name,
+            key: secondary_key_bytes.to_vec(),
+        };
+
+        let [primary_key_bytes] = changes.added.as_slice() else {
+            return if changes.added.is_empty() { Ok(()) } else { Err(collision()) };
+        };
+
+        let index_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&self.namespace.table_name(index_name)))?;
+        if let Some(entry) = index_table.get(secondary_key_bytes)? {
+            let existing = self.open_index_value(index_name, secondary_key_bytes, entry.value())?;
+            if *existing != **primary_key_bytes && !changes.removed.contains(&existing.to_vec()) {
+                return Err(collision());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes a bulk insert's changes to a `Unique` index entry, which have been checked with
+    /// [`Transaction::check_unique_entry`]. Returns the entry's size before and after.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    fn write_unique_entry(
+        &self,
+        index_name: &str,
+        secondary_key_bytes: &[u8],
+        changes: &EntryChanges,
+    ) -> Result<(usize, usize), Error> {
+        let mut index_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&self.namespace.table_name(index_name)))?;
+
+        if let Some(primary_key_bytes) = changes.added.first() {
+            let sealed =
+                self.seal_index_value(index_name, secondary_key_bytes, primary_key_bytes.clone())?;
+            let existed = index_table.insert(secondary_key_bytes, &*sealed)?.is_some();
+            return Ok((usize::from(existed), 1));
+        }
+
+        let Some(entry) = index_table.get(secondary_key_bytes)? else { return Ok((0, 0)) };
+        let points_to_removed = changes.removed.contains(
+            &self.open_index_value(index_name, secondary_key_bytes, entry.value())?.to_vec()
+        );
+        drop(entry);
+        if points_to_removed {
+            index_table.remove(secondary_key_bytes)?;
+            Ok((1, 0))
+        } else {
+            Ok((1, 1))
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use crate::indexing::ReadableKeySet;
+    use crate::querying::Query;
+    use crate::typed::database::Database;
+    use crate::typed::test_records::{Animal, Enclosure};
+
+    #[test]
+    fn the_last_record_for_a_primary_key_wins() {
+        let db = Database::in_memory().unwrap();
+        let mut txn = db.write().unwrap();
+        txn.bulk_insert::<u64, Animal>([Animal::new(1, "Lion", "Savannah")]).unwrap();
+
+        let written = txn.bulk_insert::<u64, Animal>([
+            Animal::new(1, "Lion", "Desert"),
+            Animal::new(2, "Toucan", "Jungle"),
+            Animal::new(1, "Lion", "Jungle"),
+        ]).unwrap();
+        assert_eq!(written, 2);
+
+        for (enclosure, len) in [("Savannah", 0), ("Desert", 0), ("Jungle", 2)] {
+            let animals = txn.query::<u64, Animal>(Query::lookup(Enclosure(enclosure.into())));
+            assert_eq!(animals.unwrap().len(), len, "{enclosure}");
+        }
+        txn.commit().unwrap();
+
+        let lion = db.read().unwrap().get::<u64, Animal>(&1).unwrap();
+        assert_eq!(lion, Some(Animal::new(1, "Lion", "Jungle")));
+    }
+}
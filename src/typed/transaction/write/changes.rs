@@ -0,0 +1,78 @@
+//! Write transaction methods that append to the change-data-capture log.
+
+use crate::Error;
+use crate::typed::audit::Operation;
+use crate::typed::change_log::{CHANGE_LOG_TABLE, encode_change, last_sequence};
+#[cfg(feature = "hash-chain")]
+use crate::typed::chain::{Log, next_link};
+use crate::typed::transaction::write::Transaction;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Enables or disables recording this transaction's record writes and deletions in the change
+    /// log (defaults to the database's setting, see [`Database::enable_change_log`]).
+    ///
+    /// Only index-aware record writes, such as [`Transaction::insert`], [`Transaction::remove`],
+    /// and [`Transaction::update_matching`], are logged. Writes made directly to a [`TableMut`] or
+    /// a `redb` table bypass the log.
+    ///
+    /// [`Database::enable_change_log`]: crate::typed::database::Database::enable_change_log
+    /// [`TableMut`]: crate::typed::TableMut
+    #[inline]
+    pub const fn set_change_log(&mut self, enabled: bool) {
+        self.change_log = enabled;
+    }
+
+    /// Returns `true` if this transaction's record writes and deletions are recorded in the change
+    /// log.
+    #[inline]
+    #[must_use]
+    pub const fn change_log_enabled(&self) -> bool {
+        self.change_log
+    }
+
+    /// Appends a record write (`Some` value) or deletion (`None` value) in the given table to the
+    /// change log, if the change log is enabled for this transaction. The change is also stamped
+    /// with the merge clock, if one is set, and recorded in the audit log, if it's enabled.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn record_change(
+        &self,
+        table_name: &str,
+        key_bytes: &[u8],
+        value_bytes: Option<&[u8]>,
+    ) -> Result<(), Error> {
+        #[cfg(feature = "sync")]
+        self.stamp_change(table_name, key_bytes, value_bytes.is_none())?;
+
+        let operation = if value_bytes.is_some() { Operation::Write } else { Operation::Delete };
+        self.audit_change(table_name, key_bytes, operation)?;
+
+        if !self.change_log {
+            return Ok(());
+        }
+
+        let mut change_log = self.redb.open_table(CHANGE_LOG_TABLE)?;
+        let sequence = last_sequence(&change_log)? + 1;
+        let table_name = self.namespace.table_name(table_name);
+
+        #[cfg(feature = "hash-chain")]
+        let chain = Some(next_link(
+            Log::Changes,
+            &change_log,
+            sequence,
+            &encode_change(&table_name, key_bytes, value_bytes, None),
+        )?);
+        #[cfg(not(feature = "hash-chain"))]
+        let chain = None;
+
+        let change = encode_change(&table_name, key_bytes, value_bytes, chain.as_ref());
+        change_log.insert(sequence, &*change)?;
+        Ok(())
+    }
+}
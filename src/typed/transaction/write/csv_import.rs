@@ -0,0 +1,179 @@
+//! Write transaction methods that import records from CSV files.
+
+use crate::defaults::Defaults;
+use crate::indexing::{HasPrimaryKey, HasTable, Indexable, IndexKeyBytes, References};
+use crate::typed::csv_import::{CSV_BATCH_SIZE, CsvMapping};
+use crate::typed::transaction::write::Transaction;
+use crate::typed::transaction::QuerySource;
+use crate::validation::Validate;
+use crate::{Codec, Error};
+use redb::{ReadableTable, TableDefinition};
+use std::io::Read;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Builds a record from every row of a CSV file, and writes the records to their primary
+    /// table, along with their secondary index entries. Returns the number of records written.
+    ///
+    /// The first row must be a header row. Each header is mapped to a record field by `mapping`,
+    /// and the row is deserialized into a record with `serde`. Each record has its [`Defaults`]
+    /// applied, and is checked against its [`Validate`] rules and references.
+    ///
+    /// Rows are read and written in batches of [`CSV_BATCH_SIZE`] records with
+    /// [`TableMut::bulk_insert_keyed`], so the file can be larger than memory. Records replace any
+    /// existing record with the same primary key.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let mapping = CsvMapping::new().column("Common Name", "name");
+    /// let mut txn = db.write()?;
+    /// let imported = txn.import_csv::<u64, Creature>(File::open("creatures.csv")?, &mapping)?;
+    /// txn.commit()?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// All errors stop the import, and the transaction should be aborted.
+    ///
+    /// * Returns [`Error::Csv`], with the row's position, if a row can't be read or built into a
+    ///   record.
+    ///
+    /// * Returns [`Error::ConstraintViolations`] if a record is invalid.
+    ///
+    /// * Returns [`Error::ForeignKeyViolation`] if a record refers to a primary key that doesn't
+    ///   exist.
+    ///
+    /// * Returns [`Error::IndexCollision`] if a record collides with another in a `Unique` index.
+    ///
+    /// * Returns [`Error::QuotaExceeded`] if a batch would grow the table past its quota.
+    ///
+    /// * Encoding a record or a secondary key fails, or decoding a previous record fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn import_csv<K, V>(
+        &mut self,
+        reader: impl Read,
+        mapping: &CsvMapping,
+    ) -> Result<u64, Error>
+    where
+        K: Codec<K>,
+        V: serde::de::DeserializeOwned
+            + for<'v> HasPrimaryKey<'v, K>
+            + for<'i> Indexable<'i>
+            + References
+            + Defaults
+            + Validate
+            + Codec<V>
+            + HasTable,
+    {
+        let mut reader = mapping.reader(reader);
+        let fields = mapping.fields(reader.headers()?);
+
+        let mut imported = 0;
+        let mut batch: Vec<V> = Vec::with_capacity(CSV_BATCH_SIZE);
+        for row in reader.records() {
+            let record: V = row?.deserialize(Some(&fields))?;
+            let record = record.with_defaults().into_owned();
+            record.check()?;
+            self.check_references(&record)?;
+            batch.push(record);
+
+            if batch.len() == CSV_BATCH_SIZE {
+                imported += self.write_csv_batch::<K, V>(&mut batch)?;
+            }
+        }
+
+        imported += self.write_csv_batch::<K, V>(&mut batch)?;
+        Ok(imported)
+    }
+
+    /// Writes and drains a batch of checked records: stale secondary index entries of replaced
+    /// records are removed, the records are bulk inserted, and then their new index entries,
+    /// reverse index rows, and change log entries are written.
+    ///
+    /// # Errors
+    ///
+    /// * See [`Transaction::import_csv`].
+    fn write_csv_batch<K, V>(&self, batch: &mut Vec<V>) -> Result<u64, Error>
+    where
+        K: Codec<K>,
+        V: for<'v> HasPrimaryKey<'v, K> + for<'i> Indexable<'i> + Defaults + Codec<V> + HasTable,
+    {
+        let primary_keys = batch
+            .iter()
+            .map(|record| record.primary_key().to_bytes())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Index entries of the records being replaced, which may be stale after the batch:
+        let table_name = self.namespace.table_name(V::table_name());
+        let primary_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&table_name))?;
+        let context = self.record_context(V::table_name());
+        let mut old_index_keys = Vec::with_capacity(batch.len());
+        for primary_key_bytes in &primary_keys {
+            old_index_keys.push(match primary_table.get(&**primary_key_bytes)? {
+                Some(previous) => {
+                    let previous: V = context.open(primary_key_bytes, previous.value())?;
+                    self.protect_index_keys(IndexKeyBytes::of(&previous)?)
+                },
+                None => Vec::new(),
+            });
+        }
+        drop(primary_table);
+
+        let value_bytes = primary_keys
+            .iter()
+            .zip(batch.iter())
+            .map(|(primary_key_bytes, record)| {
+                self.seal_record_in(&context, primary_key_bytes, record)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let writes: Vec<(&[u8], Option<&[u8]>)> = primary_keys
+            .iter()
+            .zip(&value_bytes)
+            .map(|(key, value)| (&**key, Some(&**value)))
+            .collect();
+        self.charge_quota(&table_name, &writes)?;
+
+        let history_table_name = V::history_table_name();
+        for primary_key_bytes in &primary_keys {
+            self.record_history(V::table_name(), history_table_name, primary_key_bytes, false)?;
+        }
+
+        for ((primary_key_bytes, record), old_index_keys) in
+            primary_keys.iter().zip(batch.iter()).zip(old_index_keys)
+        {
+            let new_index_keys = self.protect_index_keys(IndexKeyBytes::of(record)?);
+            let removed: Vec<IndexKeyBytes> = old_index_keys
+                .into_iter()
+                .filter(|key| !new_index_keys.iter().any(|new_key| new_key.is_same_entry(key)))
+                .collect();
+            self.remove_index_keys(primary_key_bytes, &removed)?;
+        }
+
+        self.insert_sealed::<V>(
+            primary_keys.iter().zip(&value_bytes).map(|(key, value)| (&**key, &**value))
+        )?;
+
+        for ((primary_key_bytes, record), value_bytes) in
+            primary_keys.iter().zip(batch.iter()).zip(&value_bytes)
+        {
+            let new_index_keys = self.protect_index_keys(IndexKeyBytes::of(record)?);
+            self.add_index_keys(primary_key_bytes, &new_index_keys)?;
+            if let Some(reverse_index_name) = V::reverse_index_name() {
+                self.set_reverse_index_row(reverse_index_name, primary_key_bytes, &new_index_keys)?;
+            }
+            if self.change_log_enabled() || self.audit_log_enabled() {
+                self.record_change(V::table_name(), primary_key_bytes, Some(&**value_bytes))?;
+            }
+        }
+
+        let written = batch.len() as u64;
+        batch.clear();
+        Ok(written)
+    }
+}
@@ -0,0 +1,167 @@
+//! Write transaction methods that write delta-encoded records.
+
+use crate::typed::delta::{
+    BASE_ROW, DELTA_ROW, DeltaEncoded, Stored, apply_delta, delta_table_name, encode_delta, row_key
+};
+use crate::typed::transaction::write::Transaction;
+use crate::{Codec, Error};
+use redb::{ReadableTable, TableDefinition};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Writes a delta-encoded record, replacing any existing record with the same primary key.
+    /// See [`crate::typed::delta`].
+    ///
+    /// If the record has a base, only its delta against the base is written, unless the delta is
+    /// larger than the record type's consolidation ratio allows, in which case the record is
+    /// written as a new base. Returns how the record was stored.
+    ///
+    /// The record is stored as its [`Codec`] serializes it, without layers, and isn't indexed or
+    /// recorded in the change log. See the module's [scope](crate::typed::delta#scope).
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::DeltaEncodingLayered`] if the record type declares layers, which would
+    ///   otherwise be silently left off.
+    ///
+    /// * Encoding the primary key or record fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    #[allow(
+        clippy::cast_precision_loss,
+        reason = "the consolidation ratio is a rough threshold, so precision isn't needed"
+    )]
+    pub fn put_delta_encoded<K, V>(&mut self, primary_key: &K, value: &V) -> Result<Stored, Error>
+    where
+        K: Codec<K>,
+        V: Codec<V> + DeltaEncoded,
+    {
+        if V::layers().is_some() {
+            return Err(Error::DeltaEncodingLayered { table: V::table_name() });
+        }
+
+        let primary_key_bytes = K::serialize(primary_key)?;
+        let value_bytes = V::serialize(value)?;
+        let base_key = row_key(&primary_key_bytes, BASE_ROW);
+        let delta_key = row_key(&primary_key_bytes, DELTA_ROW);
+
+        let mut table: redb::Table<&[u8], &[u8]> = self.redb.open_table(TableDefinition::new(
+            &self.namespace.table_name(&delta_table_name(V::table_name()))
+        ))?;
+
+        let delta = table
+            .get(&*base_key)?
+            .map(|base| encode_delta(base.value(), &value_bytes));
+        match delta {
+            Some(delta)
+                if delta.len() as f64 <= value_bytes.len() as f64 * V::consolidation_ratio() =>
+            {
+                table.insert(&*delta_key, &*delta)?;
+                Ok(Stored::Delta { bytes: delta.len() as u64 })
+            },
+            _ => {
+                table.insert(&*base_key, &*value_bytes)?;
+                table.remove(&*delta_key)?;
+                Ok(Stored::Base { bytes: value_bytes.len() as u64 })
+            },
+        }
+    }
+
+    /// Removes a delta-encoded record. Returns `true` if it existed.
+    ///
+    /// # Errors
+    ///
+    /// * Encoding the primary key fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn remove_delta_encoded<K, V>(&mut self, primary_key: &K) -> Result<bool, Error>
+    where
+        K: Codec<K>,
+        V: Codec<V> + DeltaEncoded,
+    {
+        let primary_key_bytes = K::serialize(primary_key)?;
+        let mut table: redb::Table<&[u8], &[u8]> = self.redb.open_table(TableDefinition::new(
+            &self.namespace.table_name(&delta_table_name(V::table_name()))
+        ))?;
+        table.remove(&*row_key(&primary_key_bytes, DELTA_ROW))?;
+        Ok(table.remove(&*row_key(&primary_key_bytes, BASE_ROW))?.is_some())
+    }
+
+    /// Folds every delta-encoded record of type `V` that has a delta into a new base, and removes
+    /// its delta. Returns the number of records consolidated.
+    ///
+    /// Updates consolidate on their own once their delta grows large enough, so this is only
+    /// needed to make reads of every record as cheap as possible, for example before an archive.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Corrupted`] if a delta can't be applied to its base.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn consolidate_deltas<V: DeltaEncoded>(&mut self) -> Result<u64, Error> {
+        let mut table: redb::Table<&[u8], &[u8]> = self.redb.open_table(TableDefinition::new(
+            &self.namespace.table_name(&delta_table_name(V::table_name()))
+        ))?;
+
+        // A base isn't always next to its delta, since a longer primary key can sort between
+        // them, so each delta's base is looked up:
+        let mut consolidated: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+        for row in table.iter()? {
+            let (key, delta) = row?;
+            let Some((&DELTA_ROW, primary_key_bytes)) = key.value().split_last() else { continue };
+            if let Some(base) = table.get(&*row_key(primary_key_bytes, BASE_ROW))? {
+                let value_bytes = apply_delta(base.value(), delta.value())?;
+                consolidated.push((primary_key_bytes.to_vec(), value_bytes));
+            }
+        }
+
+        for (primary_key_bytes, value_bytes) in &consolidated {
+            table.insert(&*row_key(primary_key_bytes, BASE_ROW), &**value_bytes)?;
+            table.remove(&*row_key(primary_key_bytes, DELTA_ROW))?;
+        }
+
+        Ok(consolidated.len() as u64)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use crate::typed::database::Database;
+    use crate::typed::delta::{DeltaEncoded, Stored};
+    use crate::typed::test_records::{Animal, Letter};
+    use crate::Error;
+
+    impl DeltaEncoded for Animal {}
+    impl DeltaEncoded for Letter {}
+
+    #[test]
+    fn updates_are_stored_as_deltas_and_read_back() {
+        let db = Database::in_memory().unwrap();
+        let mut txn = db.write().unwrap();
+        let lion = Animal::new(1, &"Lion ".repeat(40), "Savannah");
+        assert!(matches!(txn.put_delta_encoded(&1_u64, &lion), Ok(Stored::Base { .. })));
+        let moved = Animal::new(1, &"Lion ".repeat(40), "Savanna");
+        assert!(matches!(txn.put_delta_encoded(&1_u64, &moved), Ok(Stored::Delta { .. })));
+        txn.commit().unwrap();
+
+        let txn = db.read().unwrap();
+        assert_eq!(txn.get_delta_encoded::<u64, Animal>(&1).unwrap(), Some(moved));
+        assert_eq!(txn.get_delta_encoded::<u64, Animal>(&2).unwrap(), None);
+    }
+
+    #[test]
+    fn layered_records_are_refused() {
+        let db = Database::in_memory().unwrap();
+        let mut txn = db.write().unwrap();
+        let letter = Letter::new(1, "Ada", "Hello");
+        let result = txn.put_delta_encoded(&1_u64, &letter);
+        assert!(matches!(result, Err(Error::DeltaEncodingLayered { table: "letters" })));
+    }
+}
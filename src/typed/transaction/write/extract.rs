@@ -0,0 +1,166 @@
+//! Write transaction methods that remove the records a predicate selects, along with their
+//! secondary index entries.
+
+use crate::indexing::{HasTable, Indexable};
+use crate::typed::transaction::QuerySource;
+use crate::typed::transaction::write::Transaction;
+use crate::{Codec, Error};
+use redb::{ReadableTable, TableDefinition};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Removes every record for which the predicate returns `true`, along with its secondary index
+    /// entries, reverse index row, and quota usage, and returns the removed records in ascending
+    /// primary key order.
+    ///
+    /// Unlike [`TableMut::extract_if`], which only removes records from their primary table, this
+    /// keeps every index of the record type in step. The removals are recorded in the change log,
+    /// audit log, and history, like [`Transaction::delete_matching`].
+    ///
+    /// ## Predicate Behavior
+    ///
+    /// | Want to Remove Record? | Then Return |
+    /// |------------------------|-------------|
+    /// | Yes                    | `true`      |
+    /// | No                     | `false`     |
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let mut txn = db.write()?;
+    /// let extinct = txn.extract_if::<u64, Creature>(|_, creature| creature.population == 0)?;
+    /// txn.commit()?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Decoding a primary key or record fails. Nothing is removed in this case.
+    ///
+    /// * Encoding a secondary key fails, or decoding a key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///
+    /// [`TableMut::extract_if`]: crate::typed::TableMut::extract_if
+    pub fn extract_if<K, V>(
+        &mut self,
+        mut predicate: impl FnMut(&K, &V) -> bool,
+    ) -> Result<Vec<(K, V)>, Error>
+    where
+        K: Codec<K>,
+        V: for<'i> Indexable<'i> + Codec<V> + HasTable,
+    {
+        // Every record is checked before any is removed, since the table can't be modified while
+        // it's iterated:
+        let mut selected: Vec<(Vec<u8>, K)> = Vec::new();
+        let primary_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(
+                TableDefinition::new(&self.namespace.table_name(V::table_name()))
+            )?;
+        let context = self.record_context(V::table_name());
+        for entry in primary_table.iter()? {
+            let (key_guard, value_guard) = entry?;
+            let key = K::deserialize(key_guard.value())?;
+            if predicate(&key, &context.open(key_guard.value(), value_guard.value())?) {
+                selected.push((key_guard.value().to_vec(), key));
+            }
+        }
+        drop(primary_table);
+
+        let mut extracted = Vec::with_capacity(selected.len());
+        for (primary_key_bytes, key) in selected {
+            if let Some(record) = self.delete_by_key_bytes::<V>(&primary_key_bytes)? {
+                extracted.push((key, record));
+            }
+        }
+
+        Ok(extracted)
+    }
+
+    /// Keeps only the records for which the predicate returns `true`, removing the rest along with
+    /// their secondary index entries, reverse index rows, and quota usage. Returns the number of
+    /// records removed.
+    ///
+    /// Unlike [`TableMut::retain`], which only removes records from their primary table, this
+    /// keeps every index of the record type in step. See [`Transaction::extract_if`].
+    ///
+    /// ## Predicate Behavior
+    ///
+    /// | Want to Keep Record? | Then Return |
+    /// |----------------------|-------------|
+    /// | Yes                  | `true`      |
+    /// | No                   | `false`     |
+    ///
+    /// # Errors
+    ///
+    /// * Decoding a primary key or record fails. Nothing is removed in this case.
+    ///
+    /// * Encoding a secondary key fails, or decoding a key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///
+    /// [`TableMut::retain`]: crate::typed::TableMut::retain
+    pub fn retain<K, V>(&mut self, mut keep: impl FnMut(&K, &V) -> bool) -> Result<u64, Error>
+    where
+        K: Codec<K>,
+        V: for<'i> Indexable<'i> + Codec<V> + HasTable,
+    {
+        Ok(self.extract_if::<K, V>(|key, value| !keep(key, value))?.len() as u64)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use crate::indexing::{Index, ReadableKeySet};
+    use crate::querying::Query;
+    use crate::typed::database::Database;
+    use crate::typed::test_records::{Animal, Enclosure, EnclosureIndex};
+    use crate::typed::transaction::WriteTransaction;
+
+    fn insert_animals(txn: &mut WriteTransaction) {
+        txn.bulk_insert::<u64, Animal>([
+            Animal::new(1, "Lion", "Savannah"),
+            Animal::new(2, "Penguin", "Arctic"),
+            Animal::new(3, "Zebra", "Savannah"),
+        ]).unwrap();
+    }
+
+    #[test]
+    fn extracting_removes_index_entries() {
+        let db = Database::in_memory().unwrap();
+        let mut txn = db.write().unwrap();
+        insert_animals(&mut txn);
+
+        let extracted = txn.extract_if::<u64, Animal>(|_, animal| animal.name != "Zebra").unwrap();
+        assert_eq!(extracted, [
+            (1, Animal::new(1, "Lion", "Savannah")),
+            (2, Animal::new(2, "Penguin", "Arctic")),
+        ]);
+        let savannah = txn.query::<u64, Animal>(Query::lookup(Enclosure("Savannah".into())));
+        assert_eq!(savannah.unwrap().len(), 1);
+        txn.commit().unwrap();
+
+        let enclosures = EnclosureIndex::keys(&db.read().unwrap())
+            .unwrap()
+            .collect::<Result<Vec<String>, _>>()
+            .unwrap();
+        assert_eq!(enclosures, ["Savannah"]);
+    }
+
+    #[test]
+    fn retain_counts_the_removed_records() {
+        let db = Database::in_memory().unwrap();
+        let mut txn = db.write().unwrap();
+        insert_animals(&mut txn);
+
+        assert_eq!(txn.retain::<u64, Animal>(|_, animal| animal.enclosure == "Arctic").unwrap(), 2);
+        let savannah = txn.query::<u64, Animal>(Query::lookup(Enclosure("Savannah".into())));
+        assert!(savannah.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,74 @@
+//! Write transaction methods that keep record history.
+
+use crate::Error;
+use crate::typed::history::{
+    decode_history_row, encode_history_row, history_key, now_millis, to_millis
+};
+use crate::typed::transaction::write::Transaction;
+use redb::{ReadableTable, TableDefinition};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Appends a record's current version to its history table, before the record is replaced or
+    /// deleted. Does nothing if the record type doesn't declare a history table.
+    ///
+    /// If the record doesn't exist, an entry recording its absence is appended instead, unless
+    /// it's being deleted, which changes nothing. Each version is current from the end of the
+    /// version before it until now.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::HistoryRowInvalid`] if the record's latest history row is too short.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn record_history(
+        &self,
+        table_name: &'static str,
+        history_table_name: Option<&str>,
+        primary_key_bytes: &[u8],
+        deleting: bool,
+    ) -> Result<(), Error> {
+        let Some(history_table_name) = history_table_name else {
+            return Ok(());
+        };
+
+        let primary_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&self.namespace.table_name(table_name)))?;
+        let current = primary_table.get(primary_key_bytes)?.map(|value| value.value().to_vec());
+        drop(primary_table);
+        if deleting && current.is_none() {
+            return Ok(());
+        }
+
+        let mut history_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(
+                TableDefinition::new(&self.namespace.table_name(history_table_name))
+            )?;
+
+        let start = history_key(primary_key_bytes, 0);
+        let end = history_key(primary_key_bytes, u64::MAX);
+        let latest = history_table
+            .range::<&[u8]>(&*start..=&*end)?
+            .next_back()
+            .transpose()?
+            .map(|(key, row)| decode_history_row(key.value(), row.value()));
+        let (number, valid_from) = match latest {
+            Some(Some((version, _))) => (version.number + 1, version.valid_until),
+            Some(None) => return Err(Error::HistoryRowInvalid { table: table_name }),
+            None => (1, std::time::UNIX_EPOCH),
+        };
+
+        // A version never ends before it began, even if the wall clock goes backwards:
+        let valid_from = to_millis(valid_from);
+        let valid_until = now_millis().max(valid_from);
+
+        history_table.insert(
+            &*history_key(primary_key_bytes, number),
+            &*encode_history_row(valid_from, valid_until, current.as_deref()),
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,431 @@
+//! Write transaction methods that maintain secondary index tables.
+
+use crate::defaults::Defaults;
+use crate::indexing::{
+    HasTable, Index, Indexable, IndexKeyBytes, IndexKind, KeySet, SHARD_CAPACITY, STATS_TABLE_NAME,
+    covering_key, shard_key, shard_table_name
+};
+use crate::typed::transaction::write::Transaction;
+use crate::typed::transaction::QuerySource;
+use crate::{Codec, Error};
+use redb::{ReadableTable, TableDefinition};
+use std::collections::BTreeMap;
+
+/// Covering table name → serialized covering keys and projections to be written to it.
+type CoveringRows = BTreeMap<&'static str, Vec<(Vec<u8>, Vec<u8>)>>;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Truncates an index table and repopulates it from every record in the primary table.
+    ///
+    /// Use this after a migration, after manual index maintenance was interrupted, or to build a
+    /// new index for records that were written before it existed. Returns the number of distinct
+    /// secondary keys written. If the index has statistics, they're refreshed. If it's a covering
+    /// index, its covering tables are rebuilt too.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let mut txn = db.write()?;
+    /// let habitats = txn.rebuild_index::<Creature, HabitatIndex>()?;
+    /// txn.commit()?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::IndexCollision`] if the index is `Unique` and more than one record has
+    ///   the same secondary key. The index is left untouched in this case.
+    ///
+    /// * Decoding a record fails, or encoding a secondary key or key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn rebuild_index<V, I>(&mut self) -> Result<u64, Error>
+    where
+        V: for<'i> Indexable<'i> + Codec<V> + HasTable,
+        I: Index<Record = V>,
+    {
+        let index_name = I::index_name();
+        let index_kind = I::index_kind();
+
+        // Secondary key → primary keys, collected before the index table is touched:
+        let mut entries: BTreeMap<Vec<u8>, Vec<Vec<u8>>> = BTreeMap::new();
+        // Covering table → covering key → projection:
+        let mut covering_rows: CoveringRows = BTreeMap::new();
+        let primary_table_name = self.namespace.table_name(V::table_name());
+        let primary_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&primary_table_name))?;
+        let context = self.record_context(V::table_name());
+        for entry in primary_table.iter()? {
+            let (key_guard, value_guard) = entry?;
+            let record: V = context.open(key_guard.value(), value_guard.value())?;
+            let index_keys = self.protect_index_keys(IndexKeyBytes::of(&record)?);
+            for index_key in index_keys.into_iter().filter(|key| key.index_name == index_name) {
+                if let Some(covering) = index_key.covering {
+                    covering_rows.entry(covering.table_name).or_default().push((
+                        covering_key(&index_key.secondary_key_bytes, key_guard.value())?,
+                        covering.projection_bytes,
+                    ));
+                }
+                let primary_keys = entries.entry(index_key.secondary_key_bytes.clone()).or_default();
+                if index_kind == IndexKind::Unique && !primary_keys.is_empty() {
+                    return Err(Error::IndexCollision {
+                        index: index_name,
+                        key: index_key.secondary_key_bytes,
+                    });
+                }
+                primary_keys.push(key_guard.value().to_vec());
+            }
+        }
+        drop(primary_table);
+
+        // Shard table key → key set, for key sets too large for a single row:
+        let mut shard_rows: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+        let index_table_name = self.namespace.table_name(index_name);
+        self.redb.delete_table(TableDefinition::<&[u8], &[u8]>::new(&index_table_name))?;
+        let mut index_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&index_table_name))?;
+        for (secondary_key_bytes, primary_keys) in &mut entries {
+            match index_kind {
+                IndexKind::Unique => {
+                    let primary_key_bytes = self.seal_index_value(
+                        index_name,
+                        secondary_key_bytes,
+                        primary_keys[0].clone(),
+                    )?;
+                    index_table.insert(&**secondary_key_bytes, &*primary_key_bytes)?;
+                },
+                IndexKind::NonUnique => {
+                    primary_keys.sort_unstable();
+                    primary_keys.dedup();
+                    for (shard, keys) in (0_u32..).zip(primary_keys.chunks(SHARD_CAPACITY)) {
+                        let key_set_bytes = keys.iter().cloned().collect::<KeySet>().to_bytes()?;
+                        if shard == 0 {
+                            let key_set_bytes = self.seal_index_value(
+                                index_name,
+                                secondary_key_bytes,
+                                key_set_bytes,
+                            )?;
+                            index_table.insert(&**secondary_key_bytes, &*key_set_bytes)?;
+                        } else {
+                            let row_key = shard_key(secondary_key_bytes, shard)?;
+                            let key_set_bytes =
+                                self.seal_index_value(index_name, &row_key, key_set_bytes)?;
+                            shard_rows.push((row_key, key_set_bytes));
+                        }
+                    }
+                },
+            }
+        }
+        drop(index_table);
+
+        let shard_table_name = shard_table_name(index_name);
+        let shard_table_name = self.namespace.table_name(&shard_table_name);
+        self.redb.delete_table(TableDefinition::<&[u8], &[u8]>::new(&shard_table_name))?;
+        if !shard_rows.is_empty() {
+            let mut shard_table: redb::Table<&[u8], &[u8]> =
+                self.redb.open_table(TableDefinition::new(&shard_table_name))?;
+            for (key, key_set_bytes) in shard_rows {
+                shard_table.insert(&*key, &*key_set_bytes)?;
+            }
+        }
+
+        for (covering_table_name, rows) in covering_rows {
+            self.redb.delete_table(TableDefinition::<&[u8], &[u8]>::new(
+                &self.namespace.table_name(covering_table_name)
+            ))?;
+            let mut covering_table: redb::Table<&[u8], &[u8]> =
+                self.redb.open_table(
+                    TableDefinition::new(&self.namespace.table_name(covering_table_name))
+                )?;
+            for (key, projection_bytes) in rows {
+                covering_table.insert(&*key, &*projection_bytes)?;
+            }
+        }
+
+        let stats_table: redb::Table<&str, &[u8]> =
+            self.redb.open_table(
+                TableDefinition::new(&self.namespace.table_name(STATS_TABLE_NAME))
+            )?;
+        let has_stats = stats_table.get(index_name)?.is_some();
+        drop(stats_table);
+        if has_stats {
+            self.analyze_index(index_name, index_kind)?;
+        }
+
+        Ok(entries.len() as u64)
+    }
+
+    /// Adds a primary key to the secondary index entries of a written record.
+    ///
+    /// `Unique` index entries are checked before anything is written, so a collision leaves every
+    /// index untouched. Statistics of analyzed indexes are updated to match, and projections of
+    /// covering indexes are written or replaced.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::IndexCollision`] if a `Unique` index entry already points to a different
+    ///   primary key.
+    ///
+    /// * Decoding or encoding a key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn add_index_keys(
+        &self,
+        primary_key_bytes: &[u8],
+        index_keys: &[IndexKeyBytes],
+    ) -> Result<(), Error> {
+        let unique_keys = index_keys.iter().filter(|key| matches!(key.index_kind, IndexKind::Unique));
+        for index_key in unique_keys {
+            let index_table: redb::Table<&[u8], &[u8]> =
+                self.redb.open_table(
+                    TableDefinition::new(&self.namespace.table_name(index_key.index_name))
+                )?;
+
+            if let Some(entry) = index_table.get(&*index_key.secondary_key_bytes)? {
+                let existing = self.open_index_value(
+                    index_key.index_name,
+                    &index_key.secondary_key_bytes,
+                    entry.value(),
+                )?;
+                if *existing != *primary_key_bytes {
+                    return Err(Error::IndexCollision {
+                        index: index_key.index_name,
+                        key: index_key.secondary_key_bytes.clone(),
+                    });
+                }
+            }
+        }
+
+        for index_key in index_keys {
+            let secondary_key_bytes = &*index_key.secondary_key_bytes;
+            let (old_set_size, new_set_size) = match index_key.index_kind {
+                IndexKind::Unique => {
+                    let mut index_table: redb::Table<&[u8], &[u8]> =
+                        self.redb.open_table(TableDefinition::new(
+                            &self.namespace.table_name(index_key.index_name)
+                        ))?;
+                    let sealed = self.seal_index_value(
+                        index_key.index_name,
+                        secondary_key_bytes,
+                        primary_key_bytes.to_vec(),
+                    )?;
+                    let existed = index_table.insert(secondary_key_bytes, &*sealed)?.is_some();
+                    (usize::from(existed), 1)
+                },
+                IndexKind::NonUnique => self.insert_into_key_set(
+                    index_key.index_name,
+                    secondary_key_bytes,
+                    primary_key_bytes,
+                )?,
+            };
+
+            if let Some(covering) = &index_key.covering {
+                let mut covering_table: redb::Table<&[u8], &[u8]> =
+                    self.redb.open_table(TableDefinition::new(
+                        &self.namespace.table_name(covering.table_name)
+                    ))?;
+                covering_table.insert(
+                    &*covering_key(secondary_key_bytes, primary_key_bytes)?,
+                    &*covering.projection_bytes,
+                )?;
+            }
+
+            self.resize_index_stats(index_key.index_name, old_set_size, new_set_size)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes a record to its primary table, replacing any existing record with the same primary
+    /// key, and rewrites its secondary index entries, reverse index row, and change log entry to
+    /// match.
+    ///
+    /// The record's [`Defaults`] are applied first, and `check` is called with the result before
+    /// anything is written, so that callers can check its validation rules and references.
+    ///
+    /// # Errors
+    ///
+    /// * Returns any error returned by `check`. Nothing is written in this case.
+    ///
+    /// * Returns [`Error::IndexCollision`] if a `Unique` index entry already points to a different
+    ///   primary key. Nothing is written in this case.
+    ///
+    /// * Returns [`Error::QuotaExceeded`] if the record would grow its table past its quota.
+    ///   Nothing is written in this case.
+    ///
+    /// * Encoding the record or a secondary key fails, or decoding the previous record or a key set
+    ///   fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn write_indexed<V>(
+        &self,
+        primary_key_bytes: &[u8],
+        record: &V,
+        check: impl FnOnce(&V) -> Result<(), Error>,
+    ) -> Result<(), Error>
+    where
+        V: for<'i> Indexable<'i> + Defaults + Codec<V> + HasTable,
+    {
+        let record = record.with_defaults();
+        check(record.as_ref())?;
+        let value_bytes = self.seal_record(primary_key_bytes, record.as_ref())?;
+        let new_index_keys = self.protect_index_keys(IndexKeyBytes::of(record.as_ref())?);
+        let table_name = self.namespace.table_name(V::table_name());
+
+        let primary_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&table_name))?;
+        let old_index_keys = match primary_table.get(primary_key_bytes)? {
+            Some(previous) => {
+                let previous: V = self.open_record(primary_key_bytes, previous.value())?;
+                self.protect_index_keys(IndexKeyBytes::of(&previous)?)
+            },
+            None => Vec::new(),
+        };
+        drop(primary_table);
+
+        // Entries whose projection changed are rewritten, rather than removed:
+        let added: Vec<IndexKeyBytes> = new_index_keys
+            .iter()
+            .filter(|key| !old_index_keys.contains(key))
+            .cloned()
+            .collect();
+        let removed: Vec<IndexKeyBytes> = old_index_keys
+            .into_iter()
+            .filter(|key| !new_index_keys.iter().any(|new_key| new_key.is_same_entry(key)))
+            .collect();
+
+        let before = self.charge_quota(&table_name, &[(primary_key_bytes, Some(&*value_bytes))])?;
+        if let Err(error) = self.add_index_keys(primary_key_bytes, &added) {
+            self.refund_quota(&table_name, before)?;
+            return Err(error);
+        }
+        self.remove_index_keys(primary_key_bytes, &removed)?;
+        if let Some(reverse_index_name) = V::reverse_index_name() {
+            self.set_reverse_index_row(reverse_index_name, primary_key_bytes, &new_index_keys)?;
+        }
+        self.record_history(V::table_name(), V::history_table_name(), primary_key_bytes, false)?;
+
+        let mut primary_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&table_name))?;
+        primary_table.insert(primary_key_bytes, &*value_bytes)?;
+        drop(primary_table);
+
+        self.record_change(V::table_name(), primary_key_bytes, Some(&*value_bytes))
+    }
+
+    /// Removes a primary key from the secondary index entries of a deleted record.
+    ///
+    /// * `Unique` index entries are removed if they point to the primary key.
+    ///
+    /// * `NonUnique` index entries have the primary key removed from their key set. Entries whose
+    ///   key set becomes empty are removed entirely.
+    ///
+    /// Statistics of analyzed indexes are updated to match, and projections of covering indexes
+    /// are removed.
+    ///
+    /// # Errors
+    ///
+    /// * Decoding or encoding a key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn remove_index_keys(
+        &self,
+        primary_key_bytes: &[u8],
+        index_keys: &[IndexKeyBytes],
+    ) -> Result<(), Error> {
+        for index_key in index_keys {
+            self.remove_index_entry(
+                primary_key_bytes,
+                index_key.index_name,
+                index_key.index_kind,
+                &index_key.secondary_key_bytes,
+                index_key.covering.as_ref().map(|covering| covering.table_name),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes a primary key from a single secondary index entry, along with its projection if
+    /// the index is a covering index. See [`Transaction::remove_index_keys`].
+    ///
+    /// # Errors
+    ///
+    /// * Decoding or encoding a key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn remove_index_entry(
+        &self,
+        primary_key_bytes: &[u8],
+        index_name: &str,
+        index_kind: IndexKind,
+        secondary_key_bytes: &[u8],
+        covering_table_name: Option<&str>,
+    ) -> Result<(), Error> {
+        if let Some(covering_table_name) = covering_table_name {
+            let mut covering_table: redb::Table<&[u8], &[u8]> =
+                self.redb.open_table(
+                    TableDefinition::new(&self.namespace.table_name(covering_table_name))
+                )?;
+            covering_table.remove(&*covering_key(secondary_key_bytes, primary_key_bytes)?)?;
+        }
+
+        let (old_set_size, new_set_size) = match index_kind {
+            IndexKind::Unique => {
+                let mut index_table: redb::Table<&[u8], &[u8]> =
+                    self.redb.open_table(
+                        TableDefinition::new(&self.namespace.table_name(index_name))
+                    )?;
+                let Some(entry) = index_table.get(secondary_key_bytes)? else { return Ok(()) };
+                let points_here =
+                    *self.open_index_value(index_name, secondary_key_bytes, entry.value())?
+                        == *primary_key_bytes;
+                drop(entry);
+                if points_here {
+                    index_table.remove(secondary_key_bytes)?;
+                    (1, 0)
+                } else {
+                    (1, 1)
+                }
+            },
+            IndexKind::NonUnique =>
+                self.remove_from_key_set(index_name, secondary_key_bytes, primary_key_bytes)?,
+        };
+
+        self.resize_index_stats(index_name, old_set_size, new_set_size)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use crate::indexing::ReadableKeySet;
+    use crate::querying::Query;
+    use crate::typed::database::Database;
+    use crate::typed::test_records::{Animal, Enclosure, EnclosureIndex};
+
+    #[test]
+    fn repopulates_an_index_from_the_primary_table() {
+        let db = Database::in_memory().unwrap();
+        let mut txn = db.write().unwrap();
+        for animal in [
+            Animal::new(1, "Lion", "Savannah"),
+            Animal::new(2, "Penguin", "Arctic"),
+            Animal::new(3, "Zebra", "Savannah"),
+        ] {
+            txn.insert(&animal).unwrap();
+        }
+        let savannah = || Query::lookup(Enclosure("Savannah".into()));
+        assert!(txn.query::<u64, Animal>(savannah()).unwrap().is_empty());
+
+        assert_eq!(txn.rebuild_index::<Animal, EnclosureIndex>().unwrap(), 2);
+        assert_eq!(txn.query::<u64, Animal>(savannah()).unwrap().len(), 2);
+        txn.commit().unwrap();
+    }
+}
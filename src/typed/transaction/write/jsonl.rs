@@ -0,0 +1,131 @@
+//! Write transaction methods that import records from JSON Lines.
+
+use crate::defaults::Defaults;
+use crate::indexing::{HasPrimaryKey, HasTable, Indexable, References};
+use crate::typed::transaction::write::Transaction;
+use crate::validation::Validate;
+use crate::{Codec, Error};
+use std::io::{BufRead, Read};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Reads [JSON Lines](https://jsonlines.org/), one JSON object per line, and writes each line
+    /// as a record. Returns the number of records written.
+    ///
+    /// Input can come from the read transaction's `export_jsonl` method, another system, or a
+    /// hand-written fixture. Blank lines are skipped. Each record has its [`Defaults`] applied, is
+    /// checked against its [`Validate`] rules and references, and is written with its secondary
+    /// index entries. Records replace any existing record with the same primary key.
+    ///
+    /// Records are streamed, so the input can be larger than memory. Nothing is visible to other
+    /// transactions until this transaction is committed.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let mut txn = db.write()?;
+    /// let imported = txn.import_jsonl::<u64, Creature>(File::open("fixtures/creatures.jsonl")?)?;
+    /// txn.commit()?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// All errors stop the import, and the transaction should be aborted.
+    ///
+    /// * Returns [`Error::MalformedJsonLine`], with the line number, if a line isn't a valid
+    ///   record.
+    ///
+    /// * Returns [`Error::ConstraintViolations`] if a record is invalid.
+    ///
+    /// * Returns [`Error::ForeignKeyViolation`] if a record refers to a primary key that doesn't
+    ///   exist.
+    ///
+    /// * Returns [`Error::IndexCollision`] if a record collides with another in a `Unique` index.
+    ///
+    /// * Returns [`Error::Io`] if the input could not be read.
+    ///
+    /// * Encoding a record or a secondary key fails, or decoding a previous record fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn import_jsonl<K, V>(&mut self, reader: impl Read) -> Result<u64, Error>
+    where
+        K: Codec<K>,
+        V: serde::de::DeserializeOwned
+            + for<'v> HasPrimaryKey<'v, K>
+            + for<'i> Indexable<'i>
+            + References
+            + Defaults
+            + Validate
+            + Codec<V>
+            + HasTable,
+    {
+        let mut imported = 0;
+        for (line, text) in (1_u64..).zip(std::io::BufReader::new(reader).lines()) {
+            let text = text?;
+            if text.trim().is_empty() {
+                continue;
+            }
+
+            let record: V = serde_json::from_str(&text)
+                .map_err(|source| Error::MalformedJsonLine { line, source })?;
+            let primary_key_bytes = record.primary_key().to_bytes()?;
+            self.write_indexed(&primary_key_bytes, &record, |record| {
+                record.check()?;
+                self.check_references(record)
+            })?;
+            imported += 1;
+        }
+
+        Ok(imported)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexing::ReadableKeySet;
+    use crate::querying::Query;
+    use crate::typed::database::Database;
+    use crate::typed::test_records::{Animal, Enclosure};
+
+    #[test]
+    fn exported_lines_import_with_their_index_entries() {
+        let source = Database::in_memory().unwrap();
+        let mut txn = source.write().unwrap();
+        txn.bulk_insert::<u64, Animal>([
+            Animal::new(1, "Lion", "Savannah"),
+            Animal::new(2, "Penguin", "Arctic"),
+        ]).unwrap();
+        txn.commit().unwrap();
+
+        let mut lines = Vec::new();
+        assert_eq!(source.read().unwrap().export_jsonl::<Animal>(&mut lines).unwrap(), 2);
+        assert_eq!(String::from_utf8(lines.clone()).unwrap().lines().count(), 2);
+
+        let target = Database::in_memory().unwrap();
+        let mut txn = target.write().unwrap();
+        assert_eq!(txn.import_jsonl::<u64, Animal>(lines.as_slice()).unwrap(), 2);
+        txn.commit().unwrap();
+
+        let txn = target.read().unwrap();
+        let arctic = txn.query::<u64, Animal>(Query::lookup(Enclosure("Arctic".into()))).unwrap();
+        assert_eq!(arctic.len(), 1);
+        assert_eq!(txn.get::<u64, Animal>(&1).unwrap(), Some(Animal::new(1, "Lion", "Savannah")));
+    }
+
+    #[test]
+    fn a_malformed_line_reports_its_line_number() {
+        let db = Database::in_memory().unwrap();
+        let mut txn = db.write().unwrap();
+        let input = "{\"id\":1,\"name\":\"Lion\",\"enclosure\":\"Savannah\"}\n\n{\"id\":2}\n";
+        let result = txn.import_jsonl::<u64, Animal>(input.as_bytes());
+        assert!(matches!(result, Err(Error::MalformedJsonLine { line: 3, .. })));
+    }
+}
@@ -0,0 +1,485 @@
+//! Write transaction methods that stamp changes with a merge clock, and synchronize two peers in
+//! both directions.
+
+use crate::Error;
+use crate::typed::digest::digest_range;
+use crate::typed::merge::{
+    CLOCK_TABLE, HybridTimestamp, Merger, Resolution, Version, clock_key, decode_clock_row,
+    encode_clock_row,
+};
+use crate::typed::sync::{
+    Frame, KeyRange, SYNC_LEAF_ENTRIES, ShippedVersion, SyncReport, Transport, check_hello,
+    exchange, hello, split_points,
+};
+use crate::typed::transaction::write::Transaction;
+use redb::{ReadableTable, TableDefinition};
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Sets the merge clock node ID that this transaction's record writes and deletions are stamped
+    /// with, or `None` to stop stamping them (defaults to the database's setting, see
+    /// [`Database::enable_merge_clock`]).
+    ///
+    /// [`Database::enable_merge_clock`]: crate::typed::database::Database::enable_merge_clock
+    #[inline]
+    pub const fn set_merge_clock(&mut self, node: Option<u16>) {
+        self.node_id = node;
+    }
+
+    /// Returns the merge clock node ID that this transaction's record writes and deletions are
+    /// stamped with, if any.
+    #[inline]
+    #[must_use]
+    pub const fn merge_clock(&self) -> Option<u16> {
+        self.node_id
+    }
+
+    /// Synchronizes this database with a peer in both directions. The peer must be answering on
+    /// the other end of `transport` with [`Transaction::answer_bidirectional`], and registered the
+    /// same record types with its [`Merger`].
+    ///
+    /// Each registered table is compared by Merkle digest. For every record that differs, the peer
+    /// resolves the conflict with the table's merge policy, and both peers keep the result, along
+    /// with its stamp. Secondary index entries are updated as merged records are written. Nothing
+    /// is visible to other transactions until this transaction is committed, and the peer's
+    /// transaction should only be committed if this one succeeds.
+    ///
+    /// The returned report counts the versions this peer shipped, and the deletions it applied.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let merger = Merger::new().record::<Creature>(LastWriterWins);
+    /// let mut txn = db.write()?;
+    /// txn.sync_bidirectional(&mut transport, &merger)?;
+    /// txn.commit()?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// All errors stop the sync, and the transaction should be aborted.
+    ///
+    /// * Returns [`Error::SyncLayerMismatch`] if the peer was built with different serializer,
+    ///   compressor, encryptor, corrector, or key set features.
+    ///
+    /// * Returns [`Error::SyncProtocol`] if the peer sent a malformed or unexpected frame.
+    ///
+    /// * The transport failed, see [`Transport`].
+    ///
+    /// * Decoding or encoding a merged record, a secondary key, or a key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn sync_bidirectional(
+        &mut self,
+        transport: &mut impl Transport,
+        merger: &Merger,
+    ) -> Result<SyncReport, Error> {
+        check_hello(exchange(transport, &hello())?)?;
+
+        let mut report = SyncReport::default();
+        for table_name in merger.table_names() {
+            report.tables += 1;
+            self.merge_range(transport, merger, table_name, KeyRange::default(), &mut report)?;
+        }
+
+        transport.send(Frame::Done.encode()?)?;
+        Ok(report)
+    }
+
+    /// Answers a bidirectional sync driven by a peer's [`Transaction::sync_bidirectional`], until
+    /// the peer has compared every registered table. Records that differ are resolved here, with
+    /// the policies registered in `merger`.
+    ///
+    /// # Errors
+    ///
+    /// All errors stop the sync, and the transaction should be aborted.
+    ///
+    /// * Returns [`Error::SyncProtocol`] if the peer sent a malformed or unexpected frame, or a
+    ///   table that isn't registered with `merger`.
+    ///
+    /// * See [`Transaction::sync_bidirectional`].
+    pub fn answer_bidirectional(
+        &mut self,
+        transport: &mut impl Transport,
+        merger: &Merger,
+    ) -> Result<SyncReport, Error> {
+        let peer_hello = Frame::decode(&transport.recv()?)?;
+        transport.send(hello().encode()?)?;
+        check_hello(peer_hello)?;
+
+        let mut report = SyncReport::default();
+        let mut current_table = String::new();
+        loop {
+            match Frame::decode(&transport.recv()?)? {
+                Frame::Digest { table, range, digest } => {
+                    if table != current_table {
+                        report.tables += 1;
+                        current_table.clone_from(&table);
+                    }
+                    report.ranges_compared += 1;
+                    let table: redb::Table<&[u8], &[u8]> =
+                        self.redb.open_table(
+                            TableDefinition::new(&self.namespace.table_name(&table))
+                        )?;
+                    let matched = digest_range(&table, range.bounds())? == digest;
+                    transport.send(Frame::Matches(matched).encode()?)?;
+                },
+                Frame::Versions { table, range, versions } => {
+                    report.ranges_shipped += 1;
+                    let resolved =
+                        self.resolve_range(merger, &table, &range, versions, &mut report)?;
+                    report.entries_shipped += resolved.len() as u64;
+                    transport.send(Frame::Resolved { versions: resolved }.encode()?)?;
+                },
+                Frame::Done => return Ok(report),
+                _ => return Err(Error::SyncProtocol { reason: "unexpected frame" }),
+            }
+        }
+    }
+
+    /// Stamps a record write or deletion with the next merge clock timestamp, if a merge clock is
+    /// set for this transaction.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn stamp_change(
+        &self,
+        table_name: &str,
+        key_bytes: &[u8],
+        deleted: bool,
+    ) -> Result<(), Error> {
+        let Some(node) = self.node_id else {
+            return Ok(());
+        };
+
+        let mut clocks = self.redb.open_table(CLOCK_TABLE)?;
+        let last = clocks
+            .get(&[][..])?
+            .and_then(|row| HybridTimestamp::from_bytes(row.value()))
+            .unwrap_or_default();
+        let stamp = HybridTimestamp::next(last, node);
+
+        clocks.insert(&[][..], &stamp.to_bytes()[..])?;
+        let key = clock_key(&self.namespace.table_name(table_name), key_bytes);
+        clocks.insert(&*key, &encode_clock_row(stamp, deleted)[..])?;
+        Ok(())
+    }
+
+    /// Compares a key range of a registered table with the peer's, and if it differs, either ships
+    /// its versions for the peer to resolve, or splits it into smaller ranges and compares those.
+    ///
+    /// # Errors
+    ///
+    /// * See [`Transaction::sync_bidirectional`].
+    fn merge_range(
+        &mut self,
+        transport: &mut impl Transport,
+        merger: &Merger,
+        table_name: &str,
+        range: KeyRange,
+        report: &mut SyncReport,
+    ) -> Result<(), Error> {
+        let table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&self.namespace.table_name(table_name)))?;
+        let digest = digest_range(&table, range.bounds())?;
+        report.ranges_compared += 1;
+
+        let frame = Frame::Digest { table: table_name.to_string(), range: range.clone(), digest };
+        match exchange(transport, &frame)? {
+            Frame::Matches(true) => return Ok(()),
+            Frame::Matches(false) => {},
+            _ => return Err(Error::SyncProtocol { reason: "expected a matches frame" }),
+        }
+
+        if digest.entries > SYNC_LEAF_ENTRIES {
+            let boundaries = split_points(&table, &range, digest.entries)?;
+            drop(table);
+            for smaller_range in range.split(boundaries) {
+                self.merge_range(transport, merger, table_name, smaller_range, report)?;
+            }
+            return Ok(());
+        }
+        drop(table);
+
+        let versions: Vec<ShippedVersion> = self
+            .local_versions(table_name, &range)?
+            .into_iter()
+            .map(|(key, (value, stamp))| ShippedVersion { key, value, stamp })
+            .collect();
+        report.ranges_shipped += 1;
+        report.entries_shipped += versions.len() as u64;
+
+        let frame = Frame::Versions { table: table_name.to_string(), range, versions };
+        let Frame::Resolved { versions } = exchange(transport, &frame)? else {
+            return Err(Error::SyncProtocol { reason: "expected a resolved frame" });
+        };
+        for version in versions {
+            self.apply_version(merger, table_name, &version, report)?;
+        }
+        Ok(())
+    }
+
+    /// Resolves the initiator's versions of a key range against this database's, keeps the
+    /// results, and returns the versions the initiator must keep to match.
+    ///
+    /// # Errors
+    ///
+    /// * See [`Transaction::answer_bidirectional`].
+    fn resolve_range(
+        &mut self,
+        merger: &Merger,
+        table_name: &str,
+        range: &KeyRange,
+        remote_versions: Vec<ShippedVersion>,
+        report: &mut SyncReport,
+    ) -> Result<Vec<ShippedVersion>, Error> {
+        let Some(registered) = merger.table(table_name) else {
+            return Err(Error::SyncProtocol { reason: "table has no merge policy" });
+        };
+        let open = |txn: &Self, key: &[u8], value: Option<&[u8]>| {
+            value.map(|value| (registered.open)(txn, key, value)).transpose()
+        };
+
+        let mut local_versions = self.local_versions(table_name, range)?;
+        let mut reply = Vec::new();
+        for remote in remote_versions {
+            let Some((local_value, local_stamp)) = local_versions.remove(&remote.key) else {
+                self.apply_version(merger, table_name, &remote, report)?;
+                continue;
+            };
+            if local_value == remote.value && local_stamp == remote.stamp {
+                continue;
+            }
+
+            let local_bytes = open(self, &remote.key, local_value.as_deref())?;
+            let remote_bytes = open(self, &remote.key, remote.value.as_deref())?;
+            let local = Version { value: local_bytes.as_deref(), stamp: local_stamp };
+            let theirs = Version { value: remote_bytes.as_deref(), stamp: remote.stamp };
+            match registered.policy.resolve(&local, &theirs)? {
+                Resolution::Local => reply.push(ShippedVersion {
+                    key: remote.key,
+                    value: local_value,
+                    stamp: local_stamp,
+                }),
+                Resolution::Remote => self.apply_version(merger, table_name, &remote, report)?,
+                Resolution::Merged(value) => {
+                    // A merged record is a new write, so it's stamped after both versions:
+                    let value = (registered.seal)(self, &remote.key, &value)?;
+                    let combined = ShippedVersion {
+                        key: remote.key,
+                        value: Some(value),
+                        stamp: HybridTimestamp::next(
+                            local_stamp.max(remote.stamp),
+                            self.node_id.unwrap_or_default(),
+                        ),
+                    };
+                    self.apply_version(merger, table_name, &combined, report)?;
+                    reply.push(combined);
+                },
+            }
+        }
+
+        // Records the initiator has never seen:
+        reply.extend(
+            local_versions
+                .into_iter()
+                .map(|(key, (value, stamp))| ShippedVersion { key, value, stamp }),
+        );
+        Ok(reply)
+    }
+
+    /// Returns this database's stamped versions of a registered table's records in a key range,
+    /// including deletions, keyed by primary key.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    #[allow(clippy::type_complexity, reason = "a private map of versions by key")]
+    fn local_versions(
+        &self,
+        table_name: &str,
+        range: &KeyRange,
+    ) -> Result<BTreeMap<Vec<u8>, (Option<Vec<u8>>, HybridTimestamp)>, Error> {
+        let full_name = self.namespace.table_name(table_name);
+        let table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&full_name))?;
+        let clocks = self.redb.open_table(CLOCK_TABLE)?;
+
+        let mut versions = BTreeMap::new();
+        for entry in table.range::<&[u8]>(range.bounds())? {
+            let (key, value) = entry?;
+            let stamp = clocks
+                .get(&*clock_key(&full_name, key.value()))?
+                .and_then(|row| decode_clock_row(row.value()))
+                .map(|(stamp, _deleted)| stamp)
+                .unwrap_or_default();
+            versions.insert(key.value().to_vec(), (Some(value.value().to_vec()), stamp));
+        }
+
+        // Deletions are only recorded in the clock table, under the table's key prefix:
+        let prefix = clock_key(&full_name, &[]);
+        let start = clock_key(&full_name, range.start.as_deref().unwrap_or_default());
+        let end = range.end.as_deref().map_or_else(
+            || {
+                let mut end = prefix.clone();
+                if let Some(separator) = end.last_mut() {
+                    *separator = 1;
+                }
+                end
+            },
+            |end| clock_key(&full_name, end),
+        );
+        let bounds = (Bound::Included(&*start), Bound::Excluded(&*end));
+        for entry in clocks.range::<&[u8]>(bounds)? {
+            let (key, row) = entry?;
+            if let Some((stamp, true)) = decode_clock_row(row.value()) {
+                versions.insert(key.value()[prefix.len()..].to_vec(), (None, stamp));
+            }
+        }
+        Ok(versions)
+    }
+
+    /// Writes or deletes a record with a resolved version, through the index-aware write paths,
+    /// and gives it the version's stamp.
+    ///
+    /// # Errors
+    ///
+    /// * See [`Transaction::sync_bidirectional`].
+    fn apply_version(
+        &mut self,
+        merger: &Merger,
+        table_name: &str,
+        version: &ShippedVersion,
+        report: &mut SyncReport,
+    ) -> Result<(), Error> {
+        let Some(registered) = merger.table(table_name) else {
+            return Err(Error::SyncProtocol { reason: "table has no merge policy" });
+        };
+        (registered.apply)(self, &version.key, version.value.as_deref())?;
+        if version.value.is_none() {
+            report.entries_removed += 1;
+        }
+
+        // The write was stamped as a new local change, so it's given the resolved stamp instead,
+        // and the clock is moved past it:
+        let mut clocks = self.redb.open_table(CLOCK_TABLE)?;
+        let key = clock_key(&self.namespace.table_name(table_name), &version.key);
+        clocks.insert(&*key, &encode_clock_row(version.stamp, version.value.is_none())[..])?;
+        let last = clocks
+            .get(&[][..])?
+            .and_then(|row| HybridTimestamp::from_bytes(row.value()))
+            .unwrap_or_default();
+        if version.stamp > last {
+            clocks.insert(&[][..], &version.stamp.to_bytes()[..])?;
+        }
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "writes"))]
+mod tests {
+    use crate::querying::Query;
+    use crate::typed::database::Database;
+    use crate::typed::merge::{LastWriterWins, Merger};
+    use crate::typed::sync::{SyncReport, channel};
+    use crate::typed::test_records::{Animal, Enclosure};
+    use std::collections::BTreeSet;
+    use std::time::Duration;
+
+    /// Every animal, and the primary keys of the animals in each enclosure.
+    type Contents = (Vec<(u64, Animal)>, Vec<BTreeSet<Vec<u8>>>);
+
+    /// Merges two peers, as two threads talking over a channel, and commits both.
+    fn merge(local: &Database, remote: &Database) -> SyncReport {
+        let merger = Merger::new().record::<Animal>(LastWriterWins);
+        let (mut local_end, mut remote_end) = channel();
+        std::thread::scope(|scope| {
+            let remote = scope.spawn(|| {
+                let mut txn = remote.write()?;
+                txn.answer_bidirectional(&mut remote_end, &merger)?;
+                txn.commit()
+            });
+            let mut txn = local.write().unwrap();
+            let report = txn.sync_bidirectional(&mut local_end, &merger).unwrap();
+            txn.commit().unwrap();
+            remote.join().unwrap().unwrap();
+            report
+        })
+    }
+
+    /// Returns every animal, and the primary keys of the animals in each enclosure, as read
+    /// through the index.
+    fn contents(db: &Database) -> Contents {
+        let txn = db.read().unwrap();
+        let animals = txn.scan_resumable::<u64, Animal>(None, usize::MAX).unwrap().entries;
+        let enclosures = ["Savannah", "Arctic", "Reef"].map(|enclosure| {
+            let lookup = Query::lookup(Enclosure(enclosure.into()));
+            txn.query::<u64, Animal>(lookup).unwrap().into_iter().collect()
+        });
+        (animals, enclosures.into())
+    }
+
+    /// Writes an animal on a peer, in a millisecond of its own.
+    fn write(db: &Database, animal: &Animal) {
+        std::thread::sleep(Duration::from_millis(3));
+        let mut txn = db.write().unwrap();
+        txn.bulk_insert::<u64, Animal>([animal.clone()]).unwrap();
+        txn.commit().unwrap();
+    }
+
+    #[test]
+    fn diverged_peers_converge() {
+        let mut left = Database::in_memory().unwrap();
+        let mut right = Database::in_memory().unwrap();
+        left.enable_merge_clock(1).unwrap();
+        right.enable_merge_clock(2).unwrap();
+
+        let mut txn = left.write().unwrap();
+        txn.bulk_insert::<u64, Animal>((1..=100).map(|id| {
+            Animal::new(id, "Lion", if id == 9 { "Reef" } else { "Savannah" })
+        })).unwrap();
+        txn.commit().unwrap();
+        assert!(!merge(&left, &right).was_in_sync());
+        assert_eq!(contents(&right), contents(&left));
+
+        // Each peer changes records of its own, both change #7, the right peer last, and the left
+        // peer deletes #9, the only animal in the reef so far:
+        write(&left, &Animal::new(3, "Lion", "Arctic"));
+        write(&right, &Animal::new(5, "Lion", "Reef"));
+        write(&right, &Animal::new(101, "Penguin", "Arctic"));
+        write(&left, &Animal::new(7, "Lion", "Arctic"));
+        write(&right, &Animal::new(7, "Lion", "Reef"));
+        std::thread::sleep(Duration::from_millis(3));
+        let mut txn = left.write().unwrap();
+        txn.delete_matching::<u64, Animal>(Query::lookup(Enclosure("Reef".into())), None)
+            .unwrap();
+        txn.commit().unwrap();
+
+        assert!(!merge(&left, &right).was_in_sync());
+        let (animals, enclosures) = contents(&left);
+        assert_eq!(contents(&right), (animals.clone(), enclosures));
+        let animal = |id| animals.iter().find(|(key, _)| *key == id).map(|(_, animal)| animal);
+        assert_eq!(animal(3), Some(&Animal::new(3, "Lion", "Arctic")));
+        assert_eq!(animal(5), Some(&Animal::new(5, "Lion", "Reef")));
+        assert_eq!(animal(7), Some(&Animal::new(7, "Lion", "Reef")));
+        assert_eq!(animal(9), None);
+        assert_eq!(animal(101), Some(&Animal::new(101, "Penguin", "Arctic")));
+        assert_eq!(animals.len(), 100);
+        for db in [&left, &right] {
+            assert!(db.read().unwrap().verify_indexes::<Animal>().unwrap().issues.is_empty());
+        }
+
+        assert!(merge(&left, &right).was_in_sync());
+        assert!(merge(&right, &left).was_in_sync());
+    }
+}
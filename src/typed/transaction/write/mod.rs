@@ -1,6 +1,43 @@
 //! Write transaction methods that are routed directly to `redb`.
 
-use crate::typed::transaction::Error;
+mod archive;
+mod audit;
+#[cfg(feature = "blobs")]
+mod blobs;
+mod bulk;
+mod changes;
+#[cfg(feature = "csv-import")]
+mod csv_import;
+mod delta;
+mod extract;
+mod history;
+mod indexes;
+#[cfg(feature = "serde")]
+mod jsonl;
+#[cfg(feature = "sync")]
+mod merge;
+mod nonces;
+mod queries;
+mod quotas;
+mod references;
+mod reserialize;
+mod retention;
+mod reverse;
+mod shards;
+mod stats;
+#[cfg(feature = "sync")]
+mod sync;
+mod verify;
+
+use crate::indexing::{IndexCorrection, IndexProtection};
+use crate::layers::encryptors::{BlindIndex, KeyBytes, NonceCounter, TenantKey};
+use crate::typed::audit::Actor;
+use crate::typed::record_layers::{RecordContext, owned_key};
+use crate::typed::shared_cache::SharedCache;
+use crate::typed::{Namespace, Tenant};
+use crate::typed::transaction::{Error, QuerySource};
+use std::collections::BTreeMap;
+use std::sync::Arc;
 
 // -------------------------------------------------------------------------------------------------
 //
@@ -9,7 +46,75 @@ use crate::typed::transaction::Error;
 /// A read/write transaction
 ///
 /// Only a single write [`Transaction`] may exist at a time
-pub struct Transaction(redb::WriteTransaction);
+///
+/// Every table is opened in the transaction's [`Namespace`], which is the default namespace unless
+/// another is selected with [`Transaction::in_namespace`].
+///
+/// A transaction begun for a [`Tenant`] also carries the tenant's encryption key, which is returned
+/// by [`Transaction::tenant_key`]. Records with [`RecordLayers`] are encrypted with it, or with the
+/// database's record key in other transactions, see [`Transaction::with_record_key`].
+///
+/// Record writes and deletions are recorded in the change log if it's enabled, see
+/// [`Transaction::set_change_log`]. They're also stamped with a merge clock for bidirectional sync,
+/// if a node ID was set with [`Transaction::set_merge_clock`], and recorded in the audit log if
+/// it's enabled, see [`Transaction::set_audit_log`], along with the transaction's [`Actor`].
+///
+/// Secondary keys and key sets of the indexes selected by an [`IndexProtection`] are encrypted,
+/// see [`Transaction::with_index_protection`]. Key sets of the indexes selected by an
+/// [`IndexCorrection`] are written with parity data, see [`Transaction::with_index_correction`].
+/// Secondary keys of the indexes given a [`BlindIndex`] are written as search tokens, see
+/// [`Transaction::with_blind_index`].
+///
+/// With the `tracing-spans` feature, the transaction holds an `atlatl.write_transaction` span that
+/// stays open until the transaction is committed, aborted, or dropped. The commit is traced as a
+/// child of it.
+///
+/// [`RecordLayers`]: crate::typed::record_layers::RecordLayers
+pub struct Transaction {
+    /// The wrapped `redb` write transaction.
+    redb: redb::WriteTransaction,
+
+    /// The namespace that tables are opened in.
+    namespace: Namespace,
+
+    /// The encryption key of the tenant the transaction was begun for, if any.
+    tenant_key: Option<Arc<TenantKey>>,
+
+    /// The key that records with record layers are encrypted with: the tenant's key, or the
+    /// database's record key.
+    record_key: Option<Arc<KeyBytes<'static>>>,
+
+    /// The counter that nonces are drawn from, for records encrypted with counter-based nonces.
+    nonce_counter: Option<Arc<NonceCounter>>,
+
+    /// Whether record writes and deletions are recorded in the change log.
+    change_log: bool,
+
+    /// This peer's node ID, which record writes are stamped with for bidirectional sync.
+    #[cfg_attr(not(feature = "sync"), allow(dead_code, reason = "only read by anti-entropy sync"))]
+    node_id: Option<u16>,
+
+    /// Whether record writes and deletions are recorded in the audit log.
+    audit_log: bool,
+
+    /// Who audit log entries are attributed to.
+    actor: Option<Actor>,
+
+    /// Selects the indexes whose secondary keys and key sets are encrypted.
+    index_protection: Option<Arc<IndexProtection>>,
+
+    /// Selects the indexes whose key sets are written with parity data.
+    index_correction: Option<Arc<IndexCorrection>>,
+
+    /// The `atlatl.write_transaction` span, with the `tracing-spans` feature.
+    span: tracing::Span,
+
+    /// The shared cache that's invalidated when the transaction commits.
+    shared_cache: Option<Arc<SharedCache>>,
+
+    /// Index name → the blind index its secondary keys are written as search tokens with.
+    blind_indexes: BTreeMap<&'static str, BlindIndex>,
+}
 
 // -------------------------------------------------------------------------------------------------
 //
@@ -23,6 +128,111 @@ impl Transaction {
         redb.into()
     }
 
+    /// Opens every table in the given namespace from now on. For example, in the `tenant42`
+    /// namespace, `creatures` is opened as `tenant42.creatures`.
+    #[inline]
+    #[must_use]
+    pub fn in_namespace(mut self, namespace: Namespace) -> Self {
+        if let Some(name) = namespace.name() {
+            self.span.record("namespace", name);
+        }
+        self.namespace = namespace;
+        self
+    }
+
+    /// Opens every table in the tenant's namespace from now on, and carries the tenant's
+    /// encryption key for the records that are read and written.
+    #[inline]
+    #[must_use]
+    pub fn for_tenant(mut self, tenant: &Tenant) -> Self {
+        let (namespace, key) = tenant.parts();
+        if let Some(name) = namespace.name() {
+            self.span.record("namespace", name);
+        }
+        self.namespace = namespace;
+        self.record_key = Some(owned_key(&key.key_bytes()));
+        self.tenant_key = Some(key);
+        self
+    }
+
+    /// Encrypts records with [`RecordLayers`] with the given key from now on. Transactions begun
+    /// with `Database::write` carry the database's record key already, and tenants' transactions
+    /// carry the tenant's key.
+    ///
+    /// [`RecordLayers`]: crate::typed::record_layers::RecordLayers
+    #[inline]
+    #[must_use]
+    pub fn with_record_key(mut self, key: &KeyBytes<'_>) -> Self {
+        self.record_key = Some(owned_key(key));
+        self
+    }
+
+    /// Encrypts records with the given shared key from now on, see
+    /// [`Transaction::with_record_key`].
+    #[inline]
+    pub(crate) fn set_record_key(&mut self, key: Option<Arc<KeyBytes<'static>>>) {
+        self.record_key = key;
+    }
+
+    /// Draws counter-based nonces from the database's nonce counter, rather than starting a new
+    /// counter for each one.
+    #[inline]
+    pub(crate) fn set_nonce_counter(&mut self, counter: Option<Arc<NonceCounter>>) {
+        self.nonce_counter = counter;
+    }
+
+    /// Returns the namespace that tables are opened in.
+    #[inline]
+    #[must_use]
+    pub const fn namespace(&self) -> &Namespace {
+        &self.namespace
+    }
+
+    /// Returns the encryption key of the tenant this transaction was begun for, or `None` if it
+    /// wasn't begun for a tenant.
+    #[inline]
+    #[must_use]
+    pub fn tenant_key(&self) -> Option<&TenantKey> {
+        self.tenant_key.as_deref()
+    }
+
+    /// Encrypts the secondary keys and key sets of the indexes selected by the `IndexProtection`
+    /// from now on. Every transaction that reads or writes those indexes must carry it.
+    #[inline]
+    #[must_use]
+    pub fn with_index_protection(mut self, protection: Arc<IndexProtection>) -> Self {
+        self.index_protection = Some(protection);
+        self
+    }
+
+    /// Adds parity data to the key sets of the indexes selected by the `IndexCorrection` from now
+    /// on. Every transaction that reads or writes those indexes must carry it.
+    #[inline]
+    #[must_use]
+    pub fn with_index_correction(mut self, correction: Arc<IndexCorrection>) -> Self {
+        self.index_correction = Some(correction);
+        self
+    }
+
+    /// Writes the secondary keys of the named index as [`BlindIndex`] search tokens from now on,
+    /// rather than as plain text. The index is queried with a [`BlindLookup`] built from the same
+    /// `BlindIndex`, and every transaction that writes the index must carry it.
+    ///
+    /// [`BlindLookup`]: crate::indexing::BlindLookup
+    #[inline]
+    #[must_use]
+    pub fn with_blind_index(mut self, index_name: &'static str, blind_index: &BlindIndex) -> Self {
+        self.blind_indexes.insert(index_name, blind_index.clone());
+        self
+    }
+
+    /// Invalidates the shared cache when this transaction commits, so that read transactions
+    /// begun afterwards don't see records it replaced.
+    #[inline]
+    pub(crate) fn set_shared_cache(&mut self, cache: Option<Arc<SharedCache>>) {
+        self.shared_cache = cache;
+    }
+
     /// Creates a snapshot of the current database state, which can be used to rollback the
     /// database. This savepoint will exist until it is deleted with `[delete_savepoint()]`.
     ///
@@ -32,17 +242,26 @@ impl Transaction {
     /// Returns `[SavepointError::InvalidSavepoint]`, if the transaction is “dirty” (any tables have
     /// been opened) or if the transaction’s durability is less than `[Durability::Immediate]`
     ///
+    /// # Errors
+    ///
+    /// * A table has already been opened in this transaction, or the transaction's durability is
+    ///   less than `Durability::Immediate`.
+    ///
     /// # Notes
     ///
     /// * This method call is passed-through to the `redb` Rust embedded database.
     #[cfg(feature = "redb-pass-through")]
     #[inline]
     pub fn persistent_savepoint(&self) -> Result<u64, Error> {
-    	Ok(self.0.persistent_savepoint()?)
+    	Ok(self.redb.persistent_savepoint()?)
     }
 
     /// Get a persistent savepoint given its id
     ///
+    /// # Errors
+    ///
+    /// * There's no persistent savepoint with the given id, or it couldn't be read from storage.
+    ///
     /// # Notes
     ///
     /// * This method call is passed-through to the `redb` Rust embedded database.
@@ -52,7 +271,7 @@ impl Transaction {
 	    &self,
 	    id: u64
 	) -> Result<redb::Savepoint, Error> {
-    	Ok(self.0.get_persistent_savepoint(id)?)
+    	Ok(self.redb.get_persistent_savepoint(id)?)
     }
 
     /// Delete the given persistent savepoint.
@@ -62,6 +281,10 @@ impl Transaction {
     /// Returns `true` if the savepoint existed Returns `[SavepointError::InvalidSavepoint]` if the
     /// transaction’s durability is less than `[Durability::Immediate]`
     ///
+    /// # Errors
+    ///
+    /// * The transaction's durability is less than `Durability::Immediate`.
+    ///
     /// # Notes
     ///
     /// * This method call is passed-through to the `redb` Rust embedded database.
@@ -71,18 +294,22 @@ impl Transaction {
 	    &self,
 	    id: u64,
 	) -> Result<bool, Error> {
-    	Ok(self.0.delete_persistent_savepoint(id)?)
+    	Ok(self.redb.delete_persistent_savepoint(id)?)
     }
 
     /// List all persistent savepoints
     ///
+    /// # Errors
+    ///
+    /// * The list of savepoints couldn't be read from storage.
+    ///
     /// # Notes
     ///
     /// * This method call is passed-through to the `redb` Rust embedded database.
     #[cfg(feature = "redb-pass-through")]
     #[inline]
 	pub fn list_persistent_savepoints(&self) -> Result<impl Iterator<Item = u64>, Error> {
-		Ok(self.0.list_persistent_savepoints()?)
+		Ok(self.redb.list_persistent_savepoints()?)
 	}
 
     /// Creates a snapshot of the current database state, which can be used to rollback the database
@@ -92,13 +319,17 @@ impl Transaction {
     /// Returns `[SavepointError::InvalidSavepoint]`, if the transaction is “dirty” (any tables have
     /// been opened)
     ///
+    /// # Errors
+    ///
+    /// * A table has already been opened in this transaction.
+    ///
     /// # Notes
     ///
     /// * This method call is passed-through to the `redb` Rust embedded database.
     #[cfg(feature = "redb-pass-through")]
     #[inline]
 	pub fn ephemeral_savepoint(&self) -> Result<redb::Savepoint, Error> {
-		Ok(self.0.ephemeral_savepoint()?)
+		Ok(self.redb.ephemeral_savepoint()?)
 	}
 
 	/// Restore the state of the database to the given
@@ -107,6 +338,11 @@ impl Transaction {
 	/// Calling this method invalidates all
 	/// [Savepoint](https://docs.rs/redb/latest/redb/struct.Savepoint.html)s created after savepoint
     ///
+    /// # Errors
+    ///
+    /// * The savepoint was invalidated, for example by restoring an older savepoint, or belongs to
+    ///   another database.
+    ///
     /// # Notes
     ///
     /// * This method call is passed-through to the `redb` Rust embedded database.
@@ -116,13 +352,13 @@ impl Transaction {
 	    &mut self,
 	    savepoint: &redb::Savepoint
 	) -> Result<(), Error> {
-		Ok(self.0.restore_savepoint(savepoint)?)
+		Ok(self.redb.restore_savepoint(savepoint)?)
 	}
 
 	/// Set the desired durability level for writes made in this transaction Defaults to
 	/// [Durability::Immediate](https://docs.rs/redb/latest/redb/enum.Durability.html#variant.Immediate)
 	///
-	/// Will panic if the durability is reduced below [Durability::Immediate] after a persistent
+	/// Will panic if the durability is reduced below [`Durability::Immediate`] after a persistent
 	/// savepoint has been created or deleted.
     ///
     /// # Notes
@@ -131,7 +367,7 @@ impl Transaction {
     #[cfg(feature = "redb-pass-through")]
     #[inline]
 	pub fn set_durability(&mut self, durability: redb::Durability) {
-		self.0.set_durability(durability)
+		self.redb.set_durability(durability);
 	}
 
 
@@ -181,7 +417,7 @@ impl Transaction {
     #[cfg(feature = "redb-pass-through")]
     #[inline]
     pub fn set_two_phase_commit(&mut self, enabled: bool) {
-        self.0.set_two_phase_commit(enabled)
+        self.redb.set_two_phase_commit(enabled);
     }
 
     /// Enable or disable quick-repair (defaults to disabled)
@@ -201,13 +437,17 @@ impl Transaction {
     #[cfg(feature = "redb-pass-through")]
     #[inline]
     pub fn set_quick_repair(&mut self, enabled: bool) {
-        self.0.set_quick_repair(enabled)
+        self.redb.set_quick_repair(enabled);
     }
 
     /// Open the given table
     ///
     /// The table will be created if it does not exist
     ///
+    /// # Errors
+    ///
+    /// * The table was created with different key or value types, or couldn't be created.
+    ///
     /// # Notes
     ///
     /// * This method call is passed-through to the `redb` Rust embedded database.
@@ -221,13 +461,17 @@ impl Transaction {
     	K: redb::Key + 'static,
     	V: redb::Value + 'static
     {
-        Ok(self.0.open_table(definition)?)
+        Ok(self.redb.open_table(definition)?)
     }
 
     /// Open the given table
     ///
     /// The table will be created if it does not exist
     ///
+    /// # Errors
+    ///
+    /// * The table was created with different key or value types, or couldn't be created.
+    ///
     /// # Notes
     ///
     /// * This method call is passed-through to the `redb` Rust embedded database.
@@ -241,11 +485,30 @@ impl Transaction {
     	K: redb::Key + 'static,
     	V: redb::Key + 'static
     {
-        Ok(self.0.open_multimap_table(definition)?)
+        Ok(self.redb.open_multimap_table(definition)?)
+    }
+
+    /// Creates the given table in the transaction's namespace, if it doesn't already exist. Its
+    /// records can then be read, even before any have been written.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    #[inline]
+    pub fn create_table(&self, name: &str) -> Result<(), Error> {
+        self.redb.open_table(
+            redb::TableDefinition::<&[u8], &[u8]>::new(&self.namespace.table_name(name))
+        )?;
+        Ok(())
     }
 
     /// Rename the given table
     ///
+    /// # Errors
+    ///
+    /// * There's no table with the old name, a table with the new name already exists, or the table
+    ///   is open in this transaction.
+    ///
     /// # Notes
     ///
     /// * This method call is passed-through to the `redb` Rust embedded database.
@@ -256,11 +519,16 @@ impl Transaction {
         definition: impl redb::TableHandle,
         new_name: impl redb::TableHandle
     ) -> Result<(), Error> {
-    	Ok(self.0.rename_table(definition, new_name)?)
+    	Ok(self.redb.rename_table(definition, new_name)?)
     }
 
     /// Rename the given multimap table
     ///
+    /// # Errors
+    ///
+    /// * There's no table with the old name, a table with the new name already exists, or the table
+    ///   is open in this transaction.
+    ///
     /// # Notes
     ///
     /// * This method call is passed-through to the `redb` Rust embedded database.
@@ -271,13 +539,17 @@ impl Transaction {
 	    definition: impl redb::MultimapTableHandle,
 	    new_name: impl redb::MultimapTableHandle
 	) -> Result<(), Error> {
-		Ok(self.0.rename_multimap_table(definition, new_name)?)
+		Ok(self.redb.rename_multimap_table(definition, new_name)?)
 	}
 
     /// Delete the given table
     ///
 	/// Returns a bool indicating whether the table existed
     ///
+    /// # Errors
+    ///
+    /// * The table is open in this transaction, or is a multimap table.
+    ///
     /// # Notes
     ///
     /// * This method call is passed-through to the `redb` Rust embedded database.
@@ -287,13 +559,17 @@ impl Transaction {
 	    &self,
 	    definition: impl redb::TableHandle
 	) -> Result<bool, Error> {
-		Ok(self.0.delete_table(definition)?)
+		Ok(self.redb.delete_table(definition)?)
 	}
 
     /// Delete the given table
     ///
 	/// Returns a bool indicating whether the table existed
     ///
+    /// # Errors
+    ///
+    /// * The table is open in this transaction, or isn't a multimap table.
+    ///
     /// # Notes
     ///
     /// * This method call is passed-through to the `redb` Rust embedded database.
@@ -303,11 +579,15 @@ impl Transaction {
 	    &self,
 	    definition: impl redb::MultimapTableHandle
 	) -> Result<bool, Error> {
-		Ok(self.0.delete_multimap_table(definition)?)
+		Ok(self.redb.delete_multimap_table(definition)?)
 	}
 
     /// List all the tables
     ///
+    /// # Errors
+    ///
+    /// * The list of tables couldn't be read from storage.
+    ///
     /// # Notes
     ///
     /// * This method call is passed-through to the `redb` Rust embedded database.
@@ -316,11 +596,15 @@ impl Transaction {
 	pub fn list_tables(
 	    &self
 	) -> Result<impl Iterator<Item = redb::UntypedTableHandle> + '_, Error> {
-		Ok(self.0.list_tables()?)
+		Ok(self.redb.list_tables()?)
 	}
 
     /// List all the multimap tables
     ///
+    /// # Errors
+    ///
+    /// * The list of tables couldn't be read from storage.
+    ///
     /// # Notes
     ///
     /// * This method call is passed-through to the `redb` Rust embedded database.
@@ -329,7 +613,7 @@ impl Transaction {
 	pub fn list_multimap_tables(
 	    &self
 	) -> Result<impl Iterator<Item = redb::UntypedMultimapTableHandle> + '_, Error> {
-		Ok(self.0.list_multimap_tables()?)
+		Ok(self.redb.list_multimap_tables()?)
 	}
 
     /// Commit the transaction
@@ -338,35 +622,51 @@ impl Transaction {
     /// durable as consistent with the [Durability](https://docs.rs/redb/latest/redb/enum.Durability.html)
     /// level set by [Self::set_durability](https://docs.rs/redb/latest/redb/struct.WriteTransaction.html#method.set_durability)
     ///
+    /// # Errors
+    ///
+    /// * A table opened from this transaction is still in use, or the changes couldn't be written
+    ///   to storage.
+    ///
     /// # Notes
     ///
     /// * This method call is passed-through to the `redb` Rust embedded database.
     #[inline]
 	pub fn commit(self) -> Result<(), Error> {
-		Ok(self.0.commit()?)
+		#[cfg(feature = "tracing-spans")]
+		let _span = tracing::debug_span!(parent: &self.span, "atlatl.commit").entered();
+		let _invalidation = self.shared_cache.as_deref().map(SharedCache::begin_commit);
+		Ok(self.redb.commit()?)
 	}
 
     /// Abort the transaction
     ///
 	/// All writes performed in this transaction will be rolled back
     ///
+    /// # Errors
+    ///
+    /// * The rollback couldn't be written to storage.
+    ///
     /// # Notes
     ///
     /// * This method call is passed-through to the `redb` Rust embedded database.
     #[inline]
 	pub fn abort(self) -> Result<(), Error> {
-		Ok(self.0.abort()?)
+		Ok(self.redb.abort()?)
 	}
 
     /// Retrieves information about storage usage in the database
     ///
+    /// # Errors
+    ///
+    /// * The statistics couldn't be read from storage.
+    ///
     /// # Notes
     ///
     /// * This method call is passed-through to the `redb` Rust embedded database.
     #[cfg(feature = "redb-pass-through")]
     #[inline]
 	pub fn stats(self) -> Result<redb::DatabaseStats, Error> {
-		Ok(self.0.stats()?)
+		Ok(self.redb.stats()?)
 	}
 }
 
@@ -377,6 +677,98 @@ impl Transaction {
 impl From<redb::WriteTransaction> for Transaction {
     /// Converts a `redb` write transaction into an `atlatl` write transaction.
     fn from(redb: redb::WriteTransaction) -> Self {
-        Self(redb)
+        #[cfg(feature = "tracing-spans")]
+        let span = tracing::debug_span!(
+            "atlatl.write_transaction",
+            namespace = tracing::field::Empty,
+        );
+        #[cfg(not(feature = "tracing-spans"))]
+        let span = tracing::Span::none();
+
+        Self {
+            redb,
+            namespace: Namespace::default(),
+            tenant_key: None,
+            record_key: None,
+            nonce_counter: None,
+            change_log: false,
+            node_id: None,
+            audit_log: false,
+            actor: None,
+            index_protection: None,
+            index_correction: None,
+            span,
+            shared_cache: None,
+            blind_indexes: BTreeMap::new(),
+        }
+    }
+}
+
+impl QuerySource for Transaction {
+    type Table<'t> = redb::Table<'t, &'static [u8], &'static [u8]>;
+
+    /// Opens a raw table by name, in the transaction's namespace. The table is created if it
+    /// doesn't exist.
+    fn open_readable(&self, name: &str) -> Result<Self::Table<'_>, redb::TableError> {
+        self.redb.open_table(redb::TableDefinition::new(&self.namespace.table_name(name)))
+    }
+
+    /// Returns the context of the named table's records, in the transaction's namespace.
+    fn record_context(&self, table_name: &str) -> RecordContext {
+        RecordContext::new(self.record_key.clone(), self.namespace.table_name(table_name))
     }
-}
\ No newline at end of file
+
+    /// Returns the transaction's index protection, if any.
+    fn index_protection(&self) -> Option<&IndexProtection> {
+        self.index_protection.as_deref()
+    }
+
+    /// Returns the transaction's index correction, if any.
+    fn index_correction(&self) -> Option<&IndexCorrection> {
+        self.index_correction.as_deref()
+    }
+
+    /// Returns the blind index that the named index's secondary keys are written with, if any.
+    fn blind_index(&self, index_name: &str) -> Option<&BlindIndex> {
+        self.blind_indexes.get(index_name)
+    }
+}
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexing::{BlindLookup, Habitat, IndexKeyBytes, IndexKind, IndexLookup};
+    use crate::layers::encryptors::KeyBytes;
+    use crate::typed::database::Database;
+
+    #[test]
+    fn writes_the_search_token_that_a_blind_lookup_queries() {
+        let key = KeyBytes::from_array(&[7_u8; 32]);
+        let habitat_index = BlindIndex::new(&key, "creatures_by_habitat");
+        let plain_text = Habitat("Desert".into()).index_key_bytes().unwrap();
+        let index_keys = || {
+            ["creatures_by_habitat", "creatures_by_species"].map(|index_name| IndexKeyBytes {
+                index_name,
+                index_kind: IndexKind::NonUnique,
+                secondary_key_bytes: plain_text.clone(),
+                covering: None,
+            })
+        };
+
+        let db = Database::in_memory().unwrap();
+        let plain = db.write().unwrap();
+        let written = plain.protect_index_keys(index_keys().into());
+        assert_eq!(written[0].secondary_key_bytes, plain_text);
+        drop(plain);
+
+        let blind = db.write().unwrap().with_blind_index("creatures_by_habitat", &habitat_index);
+        let written = blind.protect_index_keys(index_keys().into());
+        let lookup = BlindLookup::new(Habitat("Desert".into()), &habitat_index);
+        assert_eq!(written[0].secondary_key_bytes, lookup.index_key_bytes().unwrap());
+        assert_ne!(written[0].secondary_key_bytes, plain_text);
+        assert_eq!(written[1].secondary_key_bytes, plain_text);
+    }
+}
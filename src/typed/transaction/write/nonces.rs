@@ -0,0 +1,259 @@
+//! Write transaction methods that draw counter-based nonces, and seal records with them.
+
+use crate::indexing::{Dependent, HasTable};
+use crate::layers::Encryptable;
+use crate::layers::encryptors::{Nonce, NonceCounter, NonceStrategy};
+use crate::typed::nonce_counter::{NONCE_COUNTER_TABLE, persisted_position};
+use crate::typed::record_layers::RecordContext;
+use crate::typed::transaction::QuerySource;
+use crate::typed::transaction::write::Transaction;
+use crate::{Codec, Error};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Draws the nonce to encrypt a `V` value with, or returns `None` to have the encryptor
+    /// generate a random one, according to `V`'s [`Encryptable::NONCE_STRATEGY`].
+    ///
+    /// A counter-based nonce's position is persisted in this transaction, so it's never handed out
+    /// again once the value it encrypts is committed, even after a restart. See the
+    /// [`nonce_counter`](crate::typed::nonce_counter) module.
+    ///
+    /// Records with [`RecordLayers`] draw their nonces automatically. This is for values encrypted
+    /// outside of them.
+    ///
+    /// [`RecordLayers`]: crate::typed::record_layers::RecordLayers
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let counter = db.nonce_counter()?;
+    /// let txn = db.write()?;
+    /// let nonce = txn.next_nonce::<Creature>(&counter)?;
+    /// let bytes = Bytes::apply_write_layers(&creature, key, &associated_data, nonce, None)?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::NonceCounter`] once every counter value has been handed out. The key
+    ///   must be rotated before anything else is encrypted with it.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn next_nonce<V: Encryptable>(
+        &self,
+        counter: &NonceCounter,
+    ) -> Result<Option<Nonce<'static>>, Error> {
+        if V::NONCE_STRATEGY == NonceStrategy::Random {
+            return Ok(None);
+        }
+        self.draw_nonce(counter).map(Some)
+    }
+
+    /// Encodes a `V` record to be written to its primary table under the given primary key,
+    /// passing it through `V`'s record layers if it has any. See [`Transaction::seal_record_in`].
+    ///
+    /// # Errors
+    ///
+    /// * See [`Transaction::seal_record_in`].
+    pub(crate) fn seal_record<V: HasTable + Codec<V>>(
+        &self,
+        primary_key: &[u8],
+        record: &V,
+    ) -> Result<Vec<u8>, Error> {
+        self.seal_record_in(&self.record_context(V::table_name()), primary_key, record)
+    }
+
+    /// Encodes a `V` record in the given context, passing it through `V`'s record layers if it
+    /// has any. Record types that encrypt with [`NonceStrategy::Counter`] draw their nonce from
+    /// the database's nonce counter, and its position is persisted in this transaction.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::NonceCounter`] once every counter value has been handed out.
+    ///
+    /// * See [`RecordContext::seal`].
+    pub(crate) fn seal_record_in<V: HasTable + Codec<V>>(
+        &self,
+        context: &RecordContext,
+        primary_key: &[u8],
+        record: &V,
+    ) -> Result<Vec<u8>, Error> {
+        let nonce_strategy = V::layers()
+            .map_or(NonceStrategy::Random, |layers| layers.nonce_strategy());
+        context.seal(primary_key, record, self.record_nonce(nonce_strategy)?)
+    }
+
+    /// Draws the nonce to re-encode a dependent record with, when its reference is cleared.
+    ///
+    /// # Errors
+    ///
+    /// * See [`Transaction::seal_record_in`].
+    pub(crate) fn dependent_nonce(
+        &self,
+        dependent: &Dependent,
+    ) -> Result<Option<Nonce<'static>>, Error> {
+        self.record_nonce(dependent.nonce_strategy)
+    }
+
+    /// Draws a nonce from the database's nonce counter for records encrypted with counter-based
+    /// nonces, or returns `None` to have the encryptor generate a random one.
+    fn record_nonce(&self, nonce_strategy: NonceStrategy) -> Result<Option<Nonce<'static>>, Error> {
+        match nonce_strategy {
+            NonceStrategy::Random => Ok(None),
+            NonceStrategy::Counter => {
+                if let Some(counter) = &self.nonce_counter {
+                    return self.draw_nonce(counter).map(Some);
+                }
+                // Transactions that weren't begun by a `Database` start a counter from the
+                // persisted position:
+                let persisted = persisted_position(&self.redb.open_table(NONCE_COUNTER_TABLE)?)?;
+                self.draw_nonce(&NonceCounter::new(persisted)).map(Some)
+            },
+        }
+    }
+
+    /// Draws a nonce from the given counter, and persists the counter's position in this
+    /// transaction.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::NonceCounter`] once every counter value has been handed out.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    fn draw_nonce(&self, counter: &NonceCounter) -> Result<Nonce<'static>, Error> {
+        let mut table = self.redb.open_table(NONCE_COUNTER_TABLE)?;
+        let persisted = persisted_position(&table)?;
+        let nonce = counter.next()?;
+        // Counters started by other handles may be ahead of this one, so the persisted position
+        // only ever moves forward:
+        table.insert("", counter.position().max(persisted))?;
+        Ok(nonce)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(all(test, feature = "writes"))]
+mod tests {
+    use crate::defaults::Defaults;
+    use crate::indexing::{HasPrimaryKey, HasTable, PrimaryKey, Reference, References};
+    use crate::layers::core::Direction;
+    use crate::layers::encryptors::{FORMAT_MARKER, KEY_SIZE, KeyBytes, NONCE_SIZE, NonceStrategy};
+    use crate::layers::compressors::Level as CompressionLevel;
+    use crate::layers::correctors::Level as CorrectionLevel;
+    use crate::layers::{Compressible, Correctable, Encryptable, LayerStack, Serializable};
+    use crate::typed::database::Database;
+    use crate::typed::record_layers::RecordLayers;
+    use crate::validation::Validate;
+    use crate::{Codec, Error};
+    use serde::{Deserialize, Serialize};
+
+    /// A receipt, encrypted with nonces drawn from the database's nonce counter.
+    #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+    struct Receipt {
+        id: u64,
+        total: u64,
+    }
+
+    #[cfg(feature = "serde-safety")]
+    unsafe impl crate::layers::serializers::SafeForSerde for Receipt {}
+
+    impl HasTable for Receipt {
+        fn table_name() -> &'static str { "receipts" }
+        fn layers() -> Option<RecordLayers<Self>> { Some(RecordLayers::of()) }
+    }
+
+    impl HasPrimaryKey<'_, u64> for Receipt {
+        fn primary_key(&self) -> PrimaryKey<'_, u64> {
+            PrimaryKey::new(&self.id)
+        }
+    }
+
+    impl References for Receipt {
+        fn references(&self) -> Result<Vec<Reference>, Error> {
+            Ok(Vec::new())
+        }
+    }
+
+    impl Defaults for Receipt {}
+
+    impl Validate for Receipt {}
+
+    impl Serializable for Receipt {
+        const DIRECTION: Direction = Direction::Both;
+    }
+
+    impl Compressible for Receipt {
+        const DIRECTION: Direction = Direction::None;
+        const LEVEL: CompressionLevel = CompressionLevel::Minimum;
+    }
+
+    impl Encryptable for Receipt {
+        const DIRECTION: Direction = Direction::Both;
+        const NONCE_STRATEGY: NonceStrategy = NonceStrategy::Counter;
+    }
+
+    impl Correctable for Receipt {
+        const DIRECTION: Direction = Direction::None;
+        const LEVEL: CorrectionLevel = CorrectionLevel::Minimum;
+    }
+
+    impl LayerStack for Receipt {}
+
+    /// Returns the counter half of the nonce a stored record was sealed with.
+    fn counter_position(stored: &[u8]) -> u64 {
+        let end = stored.len() - FORMAT_MARKER.len() - 2;
+        let nonce = &stored[end - NONCE_SIZE..end];
+        u64::from_be_bytes(nonce[NONCE_SIZE - 8..].try_into().unwrap())
+    }
+
+    fn insert_receipts(db: &Database, ids: std::ops::Range<u64>) {
+        let mut txn = db.write().unwrap();
+        for id in ids {
+            txn.insert::<u64, Receipt>(&Receipt { id, total: id * 100 }).unwrap();
+        }
+        txn.commit().unwrap();
+    }
+
+    #[test]
+    fn counter_nonces_are_not_reused_after_reopening() {
+        let directory = std::env::temp_dir().join(format!("atlatl-nonces-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("receipts.redb");
+        let key = KeyBytes::from_array(&[9; KEY_SIZE]);
+
+        drop(redb::Database::create(&path).unwrap());
+        let mut db = Database::open(&path).unwrap();
+        db.set_record_key(&key);
+        insert_receipts(&db, 0..3);
+        drop(db);
+
+        let mut db = Database::open(&path).unwrap();
+        db.set_record_key(&key);
+        insert_receipts(&db, 3..6);
+
+        let txn = db.read().unwrap();
+        for id in 0..6 {
+            let receipt = txn.get::<u64, Receipt>(&id).unwrap();
+            assert_eq!(receipt, Some(Receipt { id, total: id * 100 }));
+        }
+        let table = txn.open_raw_index_table("receipts").unwrap().unwrap();
+        let mut positions = (0..6_u64)
+            .map(|id| {
+                let primary_key = <u64 as Codec<u64>>::serialize(&id).unwrap();
+                counter_position(table.get(&*primary_key).unwrap().unwrap().value())
+            })
+            .collect::<Vec<_>>();
+        positions.sort_unstable();
+        positions.dedup();
+        assert_eq!(positions.len(), 6);
+
+        drop(txn);
+        drop(db);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,223 @@
+//! Write transaction methods that evaluate queries, and update or delete their results.
+
+use crate::defaults::Defaults;
+use crate::indexing::{HasTable, Indexable, IndexKeyBytes, KeySet};
+use crate::querying::{Query, UpdateReport};
+use crate::validation::Validate;
+use crate::typed::transaction::{QueryEngine, QuerySource};
+use crate::typed::transaction::write::Transaction;
+use crate::{Codec, Error};
+use redb::{ReadableTable, TableDefinition};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Evaluates a query, returning the primary keys of the matching records. The query sees this
+    /// transaction's uncommitted changes.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///
+    /// * Deserialization errors when instantiating a key set from an index entry, or when decoding
+    ///   an index key or record.
+    pub fn query<K, V>(&self, query: impl Into<Query<V>>) -> Result<KeySet, Error>
+    where
+        K: Codec<K>,
+        V: Codec<V> + HasTable,
+    {
+        QueryEngine(self).query::<K, V>(query)
+    }
+
+    /// Deletes the records matching a query, along with their secondary index entries. Returns the
+    /// number of records deleted.
+    ///
+    /// At most `limit` records are deleted, in ascending primary key order, if a limit is given.
+    ///
+    /// # Errors
+    ///
+    /// * Returns any error from evaluating the query.
+    ///
+    /// * Encoding a secondary key fails, or decoding a record or key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn delete_matching<K, V>(
+        &mut self,
+        query: impl Into<Query<V>>,
+        limit: Option<usize>,
+    ) -> Result<u64, Error>
+    where
+        K: Codec<K>,
+        V: for<'i> Indexable<'i> + Codec<V> + HasTable,
+    {
+        let mut primary_keys: Vec<Vec<u8>> = self.query::<K, V>(query)?.into_iter().collect();
+        primary_keys.sort_unstable();
+        primary_keys.truncate(limit.unwrap_or(usize::MAX));
+
+        let mut deleted = 0;
+        for primary_key_bytes in primary_keys {
+            if self.delete_by_key_bytes::<V>(&primary_key_bytes)?.is_some() {
+                deleted += 1;
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    /// Applies a closure to every record matching a query and writes each record back, rewriting
+    /// only the secondary index entries whose keys changed, and the record's reverse index row if
+    /// it has one.
+    ///
+    /// For each record, `check` runs first, then `update`, then the record's [`Defaults`] and
+    /// [`Validate`] rules. If any of them fail, a `Unique` index collides, or the record would grow
+    /// its table past its quota, the record is left unchanged and the failure is collected in the
+    /// report.
+    ///
+    /// # Errors
+    ///
+    /// * Returns any error from evaluating the query.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///   These abort the whole update, and the transaction should be aborted.
+    pub fn update_matching<K, V>(
+        &mut self,
+        query: impl Into<Query<V>>,
+        mut update: impl FnMut(&mut V) -> Result<(), Error>,
+        check: impl Fn(&K, &V) -> Result<(), Error>,
+    ) -> Result<UpdateReport<K>, Error>
+    where
+        K: Codec<K>,
+        V: for<'i> Indexable<'i> + Defaults + Validate + Codec<V> + HasTable,
+    {
+        let mut primary_keys: Vec<Vec<u8>> = self.query::<K, V>(query)?.into_iter().collect();
+        primary_keys.sort_unstable();
+
+        let table_name = self.namespace.table_name(V::table_name());
+        let mut report = UpdateReport { updated: 0, failures: Vec::new() };
+        for primary_key_bytes in primary_keys {
+            let primary_table: redb::Table<&[u8], &[u8]> =
+                self.redb.open_table(TableDefinition::new(&table_name))?;
+            let Some(value_guard) = primary_table.get(&*primary_key_bytes)? else { continue };
+            let value_bytes = value_guard.value().to_vec();
+            drop(value_guard);
+            drop(primary_table);
+
+            // Decoding, checking, and updating failures only affect this record:
+            let prepared = (|| {
+                let mut record: V = self.open_record(&primary_key_bytes, &value_bytes)?;
+                check(&K::deserialize(&primary_key_bytes)?, &record)?;
+                let old_index_keys = IndexKeyBytes::of(&record)?;
+                update(&mut record)?;
+                let record = record.with_defaults().into_owned();
+                record.check()?;
+                let new_index_keys = IndexKeyBytes::of(&record)?;
+                let new_value_bytes = self.seal_record(&primary_key_bytes, &record)?;
+                Ok::<_, Error>((new_value_bytes, old_index_keys, new_index_keys))
+            })();
+
+            let (new_value_bytes, old_index_keys, new_index_keys) = match prepared {
+                Ok(prepared) => prepared,
+                Err(error) => {
+                    report.failures.push((K::deserialize(&primary_key_bytes)?, error));
+                    continue;
+                },
+            };
+            let old_index_keys = self.protect_index_keys(old_index_keys);
+            let new_index_keys = self.protect_index_keys(new_index_keys);
+
+            // Entries whose projection changed are rewritten, rather than removed:
+            let added: Vec<IndexKeyBytes> = new_index_keys
+                .iter()
+                .filter(|key| !old_index_keys.contains(key))
+                .cloned()
+                .collect();
+            let removed: Vec<IndexKeyBytes> = old_index_keys
+                .into_iter()
+                .filter(|key| !new_index_keys.iter().any(|new_key| new_key.is_same_entry(key)))
+                .collect();
+
+            let charged =
+                self.charge_quota(&table_name, &[(&*primary_key_bytes, Some(&*new_value_bytes))]);
+            let before = match charged {
+                Ok(before) => before,
+                Err(error @ Error::QuotaExceeded { .. }) => {
+                    report.failures.push((K::deserialize(&primary_key_bytes)?, error));
+                    continue;
+                },
+                Err(error) => return Err(error),
+            };
+            match self.add_index_keys(&primary_key_bytes, &added) {
+                Ok(()) => {},
+                Err(error @ Error::IndexCollision { .. }) => {
+                    self.refund_quota(&table_name, before)?;
+                    report.failures.push((K::deserialize(&primary_key_bytes)?, error));
+                    continue;
+                },
+                Err(error) => return Err(error),
+            }
+            self.remove_index_keys(&primary_key_bytes, &removed)?;
+            if let Some(reverse_index_name) = V::reverse_index_name() {
+                self.set_reverse_index_row(reverse_index_name, &primary_key_bytes, &new_index_keys)?;
+            }
+            self.record_history(
+                V::table_name(),
+                V::history_table_name(),
+                &primary_key_bytes,
+                false,
+            )?;
+
+            let mut primary_table: redb::Table<&[u8], &[u8]> =
+                self.redb.open_table(TableDefinition::new(&table_name))?;
+            primary_table.insert(&*primary_key_bytes, &*new_value_bytes)?;
+            drop(primary_table);
+
+            self.record_change(V::table_name(), &primary_key_bytes, Some(&*new_value_bytes))?;
+            report.updated += 1;
+        }
+
+        Ok(report)
+    }
+
+    /// Deletes a single record by its serialized primary key, along with its secondary index
+    /// entries. Returns the deleted record, if it existed.
+    ///
+    /// # Errors
+    ///
+    /// * Encoding a secondary key fails, or decoding the record or a key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn delete_by_key_bytes<V>(
+        &self,
+        primary_key_bytes: &[u8],
+    ) -> Result<Option<V>, Error>
+    where
+        V: for<'i> Indexable<'i> + Codec<V> + HasTable,
+    {
+        let table_name = self.namespace.table_name(V::table_name());
+        self.charge_quota(&table_name, &[(primary_key_bytes, None)])?;
+        self.record_history(V::table_name(), V::history_table_name(), primary_key_bytes, true)?;
+
+        let mut primary_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&table_name))?;
+
+        let Some(removed) = primary_table
+            .remove(primary_key_bytes)?
+            .map(|removed| self.open_record(primary_key_bytes, removed.value()))
+            .transpose()?
+        else {
+            return Ok(None);
+        };
+        drop(primary_table);
+
+        self.record_change(V::table_name(), primary_key_bytes, None)?;
+        let index_keys = self.protect_index_keys(IndexKeyBytes::of(&removed)?);
+        self.remove_index_keys(primary_key_bytes, &index_keys)?;
+        if let Some(reverse_index_name) = V::reverse_index_name() {
+            self.set_reverse_index_row(reverse_index_name, primary_key_bytes, &[])?;
+        }
+
+        Ok(Some(removed))
+    }
+}
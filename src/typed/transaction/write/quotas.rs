@@ -0,0 +1,157 @@
+//! Write transaction methods that set and enforce per-table quotas.
+
+use crate::Error;
+use crate::typed::quota::{QUOTA_TABLE, Quota, QuotaUsage};
+use crate::typed::transaction::write::Transaction;
+use redb::{ReadableTable, ReadableTableMetadata, TableDefinition};
+use std::collections::BTreeMap;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Sets a table's quota in the transaction's namespace, replacing any quota it already had.
+    /// Returns the table's current usage, which may already be over the new limits.
+    ///
+    /// The table is scanned to measure its usage, which is then kept up to date by index-aware
+    /// record writes and deletions, such as [`Transaction::insert`] and [`Transaction::remove`].
+    /// Writes made directly to a [`TableMut`] or a `redb` table bypass the quota, and aren't
+    /// counted. Setting the quota again measures the table afresh.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let mut txn = db.write()?.for_tenant(&tenant);
+    /// let usage = txn.set_quota("creatures", Quota::new().max_entries(10_000))?;
+    /// txn.commit()?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    ///
+    /// [`TableMut`]: crate::typed::TableMut
+    pub fn set_quota(&mut self, table_name: &str, quota: Quota) -> Result<QuotaUsage, Error> {
+        let table_name = self.namespace.table_name(table_name);
+        let mut usage = QuotaUsage { quota, entries: 0, bytes: 0 };
+
+        let table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&table_name))?;
+        usage.entries = table.len()?;
+        for entry in table.iter()? {
+            let (key, value) = entry?;
+            usage.bytes += (key.value().len() + value.value().len()) as u64;
+        }
+        drop(table);
+
+        self.redb.open_table(QUOTA_TABLE)?.insert(&*table_name, &*usage.to_bytes())?;
+        Ok(usage)
+    }
+
+    /// Removes a table's quota in the transaction's namespace. Returns the quota, if it had one.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Corrupted`] if the stored quota can't be decoded.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn remove_quota(&mut self, table_name: &str) -> Result<Option<Quota>, Error> {
+        let mut quota_table = self.redb.open_table(QUOTA_TABLE)?;
+        let removed = quota_table.remove(&*self.namespace.table_name(table_name))?;
+        removed.map(|row| Ok(QuotaUsage::from_bytes(row.value())?.quota)).transpose()
+    }
+
+    /// Returns a table's quota in the transaction's namespace and how much of it is used, or
+    /// `None` if the table doesn't have a quota.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Corrupted`] if the stored quota can't be decoded.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn quota_usage(&self, table_name: &str) -> Result<Option<QuotaUsage>, Error> {
+        self.stored_quota_usage(&self.namespace.table_name(table_name))
+    }
+
+    /// Returns the quota and usage of a table, by its full, namespaced name.
+    ///
+    /// # Errors
+    ///
+    /// * See [`Transaction::quota_usage`].
+    fn stored_quota_usage(&self, table_name: &str) -> Result<Option<QuotaUsage>, Error> {
+        let quota_table = self.redb.open_table(QUOTA_TABLE)?;
+        let row = quota_table.get(table_name)?;
+        row.map(|row| QuotaUsage::from_bytes(row.value())).transpose()
+    }
+
+    /// Checks that a batch of record writes (`Some` value) and deletions (`None` value) fits in
+    /// the quota of the table with the given full, namespaced name, and counts them against it.
+    /// Must be called before the records are written. Tables without a quota are left alone.
+    ///
+    /// A key written more than once in the batch is counted once, with its last value. Returns the
+    /// table's usage before the batch, to give back with [`Transaction::refund_quota`] if the
+    /// batch isn't written after all.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::QuotaExceeded`] if the batch would grow the table past its quota. The
+    ///   quota's usage is left unchanged in this case, so the records must not be written.
+    ///
+    /// * Returns [`Error::Corrupted`] if the stored quota can't be decoded.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn charge_quota(
+        &self,
+        table_name: &str,
+        writes: &[(&[u8], Option<&[u8]>)],
+    ) -> Result<Option<QuotaUsage>, Error> {
+        let Some(before) = self.stored_quota_usage(table_name)? else { return Ok(None) };
+
+        // The stored size of each key in the batch, before any of the batch was written:
+        let table: redb::Table<&[u8], &[u8]> = self.redb.open_table(
+            TableDefinition::new(table_name)
+        )?;
+        let mut sizes: BTreeMap<&[u8], (Option<u64>, Option<u64>)> = BTreeMap::new();
+        for (key, value) in writes {
+            let size = value.map(|value| (key.len() + value.len()) as u64);
+            if let Some((_, new_size)) = sizes.get_mut(key) {
+                *new_size = size;
+            } else {
+                let old_size = table
+                    .get(*key)?
+                    .map(|previous| (key.len() + previous.value().len()) as u64);
+                sizes.insert(key, (old_size, size));
+            }
+        }
+        drop(table);
+
+        let mut after = before;
+        for (old_size, new_size) in sizes.into_values() {
+            after.entries = (after.entries + u64::from(new_size.is_some()))
+                .saturating_sub(u64::from(old_size.is_some()));
+            after.bytes = (after.bytes + new_size.unwrap_or_default())
+                .saturating_sub(old_size.unwrap_or_default());
+        }
+        before.check(table_name, &after)?;
+
+        self.redb.open_table(QUOTA_TABLE)?.insert(table_name, &*after.to_bytes())?;
+        Ok(Some(before))
+    }
+
+    /// Restores the usage of the table with the given full, namespaced name to what it was before
+    /// a batch was charged with [`Transaction::charge_quota`], because the batch wasn't written.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn refund_quota(
+        &self,
+        table_name: &str,
+        before: Option<QuotaUsage>,
+    ) -> Result<(), Error> {
+        let Some(before) = before else { return Ok(()) };
+        self.redb.open_table(QUOTA_TABLE)?.insert(table_name, &*before.to_bytes())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,450 @@
+//! Write transaction methods that enforce foreign-key references between tables.
+
+use crate::defaults::Defaults;
+use crate::validation::Validate;
+use crate::indexing::{
+    Dependent, HasDependents, HasPrimaryKey, Indexable, IndexKeyBytes, OnDelete,
+    References
+};
+use crate::typed::transaction::write::Transaction;
+use crate::typed::transaction::QuerySource;
+use crate::{Codec, Error};
+use redb::{ReadableTable, TableDefinition};
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The action to take on a dependent record when the record it refers to is deleted.
+enum DependentAction {
+    /// Delete the dependent record, and handle its own dependents in turn.
+    Cascade {
+        key_bytes: Vec<u8>,
+        index_keys: Vec<IndexKeyBytes>,
+        dependents: fn() -> Vec<Dependent>,
+    },
+
+    /// Replace the dependent record with a copy whose reference has been cleared.
+    Tombstone { key_bytes: Vec<u8>, value_bytes: Vec<u8> },
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Inserts a record into its primary table, after checking that every primary key it refers to
+    /// exists.
+    ///
+    /// The record's [`Defaults`] are applied before its references are checked and before it's
+    /// serialized. Returns the previous record if the primary key already existed.
+    ///
+    /// After its defaults are applied, the record is checked against its [`Validate`] rules.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::ConstraintViolations`] listing every failed rule if the record is
+    ///   invalid. Nothing is written in this case.
+    ///
+    /// * Returns [`Error::ForeignKeyViolation`] if a referenced primary key doesn't exist. Nothing
+    ///   is written in this case.
+    ///
+    /// * Returns [`Error::QuotaExceeded`] if the record would grow its table past its quota.
+    ///   Nothing is written in this case.
+    ///
+    /// * Encoding the primary key or record fails, or decoding the previous record fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn insert<'v, K, V>(&mut self, value: &'v V) -> Result<Option<V>, Error>
+    where
+        K: Codec<K> + 'v,
+        V: HasPrimaryKey<'v, K> + References + Defaults + Validate + Codec<V>,
+    {
+        let primary_key_bytes = value.primary_key().to_bytes()?;
+        let value = value.with_defaults();
+        value.check()?;
+        self.check_references(value.as_ref())?;
+        let value_bytes = self.seal_record(&primary_key_bytes, value.as_ref())?;
+        let table_name = self.namespace.table_name(V::table_name());
+        self.charge_quota(&table_name, &[(&*primary_key_bytes, Some(&*value_bytes))])?;
+        self.record_history(V::table_name(), V::history_table_name(), &primary_key_bytes, false)?;
+
+        let mut primary_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&table_name))?;
+
+        let previous = primary_table
+            .insert(&*primary_key_bytes, &*value_bytes)?
+            .map(|previous| self.open_record(&primary_key_bytes, previous.value()))
+            .transpose()?;
+        drop(primary_table);
+
+        self.record_change(V::table_name(), &primary_key_bytes, Some(&*value_bytes))?;
+        Ok(previous)
+    }
+
+    /// Checks that every primary key the record refers to exists.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::ForeignKeyViolation`] for the first reference whose primary key doesn't
+    ///   exist.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn check_references<V: References>(&self, value: &V) -> Result<(), Error> {
+        for reference in value.references()? {
+            let Some(key_bytes) = reference.key_bytes else { continue };
+
+            let parent_table_name = self.namespace.table_name(reference.parent_table);
+            let exists = match self.redb.open_table(
+                TableDefinition::<&[u8], &[u8]>::new(&parent_table_name)
+            ) {
+                Ok(parent_table) => parent_table.get(&*key_bytes)?.is_some(),
+                Err(redb::TableError::TableDoesNotExist(_)) => false,
+                Err(error) => return Err(error.into()),
+            };
+
+            if !exists {
+                return Err(Error::ForeignKeyViolation {
+                    table: V::table_name(),
+                    reference: reference.name,
+                    parent_table: reference.parent_table,
+                    key: key_bytes,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes a record from its primary table, handling every record that refers to it according
+    /// to the reference's [`OnDelete`] policy. Returns the removed record, if it existed.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::ReferencedByDependent`] if a dependent record refers to the record with
+    ///   an [`OnDelete::Restrict`] policy. Dependents further down a cascade may already have been
+    ///   modified when this error is returned, so the transaction should be aborted.
+    ///
+    /// * Encoding the primary key fails, or decoding a record fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn remove<K, V>(&mut self, primary_key: &K) -> Result<Option<V>, Error>
+    where
+        K: Codec<K>,
+        V: HasDependents + Codec<V>,
+    {
+        let primary_key_bytes = K::serialize(primary_key)?;
+        self.handle_dependents(V::table_name(), &primary_key_bytes, &V::dependents())?;
+        let table_name = self.namespace.table_name(V::table_name());
+        self.charge_quota(&table_name, &[(&*primary_key_bytes, None)])?;
+        self.record_history(V::table_name(), V::history_table_name(), &primary_key_bytes, true)?;
+
+        let mut primary_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&table_name))?;
+
+        let Some(removed) = primary_table
+            .remove(&*primary_key_bytes)?
+            .map(|removed| self.open_record(&primary_key_bytes, removed.value()))
+            .transpose()?
+        else {
+            return Ok(None);
+        };
+        drop(primary_table);
+
+        self.record_change(V::table_name(), &primary_key_bytes, None)?;
+        Ok(Some(removed))
+    }
+
+    /// Deletes a record, its entries in all of its secondary indexes, and every record that refers
+    /// to it. Returns the deleted record, if it existed.
+    ///
+    /// Dependents are found using [`HasDependents`] and handled according to their reference's
+    /// [`OnDelete`] policy. Cascaded dependents have their own index entries removed if they were
+    /// declared using [`Dependent::with_indexes`], or if their record type has a reverse index.
+    ///
+    /// All changes are made in this transaction, so they're committed or rolled back together.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::ReferencedByDependent`] if a dependent record refers to the record with
+    ///   an [`OnDelete::Restrict`] policy. The transaction should be aborted in this case.
+    ///
+    /// * Encoding the primary key or a secondary key fails, or decoding a record or key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn delete_cascade<K, V>(&mut self, primary_key: &K) -> Result<Option<V>, Error>
+    where
+        K: Codec<K>,
+        V: HasDependents + for<'i> Indexable<'i> + Codec<V>,
+    {
+        let primary_key_bytes = K::serialize(primary_key)?;
+
+        let primary_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(
+                TableDefinition::new(&self.namespace.table_name(V::table_name()))
+            )?;
+        let Some(value) = primary_table
+            .get(&*primary_key_bytes)?
+            .map(|value| self.open_record(&primary_key_bytes, value.value()))
+            .transpose()?
+        else {
+            return Ok(None);
+        };
+        drop(primary_table);
+
+        self.handle_dependents(V::table_name(), &primary_key_bytes, &V::dependents())?;
+        let index_keys = self.protect_index_keys(IndexKeyBytes::of(&value)?);
+        self.remove_index_keys(&primary_key_bytes, &index_keys)?;
+        if let Some(reverse_index_name) = V::reverse_index_name() {
+            self.set_reverse_index_row(reverse_index_name, &primary_key_bytes, &[])?;
+        }
+        let table_name = self.namespace.table_name(V::table_name());
+        self.charge_quota(&table_name, &[(&*primary_key_bytes, None)])?;
+        self.record_history(V::table_name(), V::history_table_name(), &primary_key_bytes, true)?;
+
+        let mut primary_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&table_name))?;
+        primary_table.remove(&*primary_key_bytes)?;
+        drop(primary_table);
+
+        self.record_change(V::table_name(), &primary_key_bytes, None)?;
+        Ok(Some(value))
+    }
+
+    /// Finds every dependent record that refers to the given primary key, and applies its
+    /// reference's [`OnDelete`] policy.
+    ///
+    /// All dependents of one table are checked before any of them are modified, so a `Restrict`
+    /// violation leaves that table untouched.
+    ///
+    /// # Errors
+    ///
+    /// * See [`Transaction::remove`].
+    pub(crate) fn handle_dependents(
+        &mut self,
+        parent_table: &'static str,
+        primary_key_bytes: &[u8],
+        dependents: &[Dependent],
+    ) -> Result<(), Error> {
+        for dependent in dependents {
+            let dependent_table: redb::Table<&[u8], &[u8]> =
+                self.redb.open_table(
+                    TableDefinition::new(&self.namespace.table_name(dependent.table_name))
+                )?;
+
+            // Collect the actions first, since the table can't be modified while it's iterated:
+            let context = self.record_context(dependent.table_name);
+            let mut actions = Vec::new();
+            for entry in dependent_table.iter()? {
+                let (key_guard, value_guard) = entry?;
+                let (key_bytes, value_bytes) = (key_guard.value(), value_guard.value());
+                for reference in (dependent.references)(&context, key_bytes, value_bytes)? {
+                    if reference.parent_table != parent_table
+                        || reference.key_bytes.as_deref() != Some(primary_key_bytes)
+                    {
+                        continue;
+                    }
+
+                    match reference.on_delete {
+                        OnDelete::Restrict => return Err(Error::ReferencedByDependent {
+                            parent_table,
+                            table: dependent.table_name,
+                            reference: reference.name,
+                            key: primary_key_bytes.to_vec(),
+                        }),
+                        OnDelete::Cascade => actions.push(DependentAction::Cascade {
+                            key_bytes: key_bytes.to_vec(),
+                            index_keys: self.protect_index_keys(
+                                (dependent.index_keys)(&context, key_bytes, value_bytes)?
+                            ),
+                            dependents: dependent.dependents,
+                        }),
+                        OnDelete::Tombstone => actions.push(DependentAction::Tombstone {
+                            key_bytes: key_bytes.to_vec(),
+                            value_bytes: (dependent.clear_reference)(
+                                &context,
+                                key_bytes,
+                                value_bytes,
+                                reference.name,
+                                self.dependent_nonce(dependent)?,
+                            )?,
+                        }),
+                    }
+                }
+            }
+            drop(dependent_table);
+
+            let dependent_table_name = self.namespace.table_name(dependent.table_name);
+            for action in actions {
+                match action {
+                    DependentAction::Cascade { key_bytes, index_keys, dependents } => {
+                        self.handle_dependents(dependent.table_name, &key_bytes, &dependents())?;
+                        self.remove_index_keys(&key_bytes, &index_keys)?;
+                        if let Some(reverse_index_name) = dependent.reverse_index_name {
+                            self.remove_reverse_indexed_keys(reverse_index_name, &key_bytes)?;
+                        }
+                        self.charge_quota(&dependent_table_name, &[(&*key_bytes, None)])?;
+                        self.record_history(
+                            dependent.table_name,
+                            dependent.history_table_name,
+                            &key_bytes,
+                            true,
+                        )?;
+                        let mut dependent_table: redb::Table<&[u8], &[u8]> =
+                            self.redb.open_table(TableDefinition::new(&dependent_table_name))?;
+                        dependent_table.remove(&*key_bytes)?;
+                        drop(dependent_table);
+                        self.record_change(dependent.table_name, &key_bytes, None)?;
+                    },
+                    DependentAction::Tombstone { key_bytes, value_bytes } => {
+                        self.charge_quota(
+                            &dependent_table_name,
+                            &[(&*key_bytes, Some(&*value_bytes))],
+                        )?;
+                        self.record_history(
+                            dependent.table_name,
+                            dependent.history_table_name,
+                            &key_bytes,
+                            false,
+                        )?;
+                        let mut dependent_table: redb::Table<&[u8], &[u8]> =
+                            self.redb.open_table(TableDefinition::new(&dependent_table_name))?;
+                        dependent_table.insert(&*key_bytes, &*value_bytes)?;
+                        drop(dependent_table);
+                        self.record_change(dependent.table_name, &key_bytes, Some(&*value_bytes))?;
+                    },
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexing::{HasTable, PrimaryKey, Reference};
+    use crate::typed::database::Database;
+
+    #[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+    struct Customer { id: u64 }
+
+    #[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+    struct Coupon { id: u64 }
+
+    #[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+    struct Order { id: u64, customer_id: u64, coupon_id: Option<u64> }
+
+    #[cfg(feature = "serde-safety")]
+    unsafe impl crate::layers::serializers::SafeForSerde for Customer {}
+    #[cfg(feature = "serde-safety")]
+    unsafe impl crate::layers::serializers::SafeForSerde for Coupon {}
+    #[cfg(feature = "serde-safety")]
+    unsafe impl crate::layers::serializers::SafeForSerde for Order {}
+
+    impl HasTable for Customer { fn table_name() -> &'static str { "customers" } }
+    impl HasTable for Coupon { fn table_name() -> &'static str { "coupons" } }
+    impl HasTable for Order { fn table_name() -> &'static str { "orders" } }
+
+    impl HasPrimaryKey<'_, u64> for Customer {
+        fn primary_key(&self) -> PrimaryKey<'_, u64> { PrimaryKey::new(&self.id) }
+    }
+
+    impl HasPrimaryKey<'_, u64> for Coupon {
+        fn primary_key(&self) -> PrimaryKey<'_, u64> { PrimaryKey::new(&self.id) }
+    }
+
+    impl HasPrimaryKey<'_, u64> for Order {
+        fn primary_key(&self) -> PrimaryKey<'_, u64> { PrimaryKey::new(&self.id) }
+    }
+
+    impl References for Customer {
+        fn references(&self) -> Result<Vec<Reference>, Error> { Ok(Vec::new()) }
+    }
+
+    impl References for Coupon {
+        fn references(&self) -> Result<Vec<Reference>, Error> { Ok(Vec::new()) }
+    }
+
+    impl References for Order {
+        fn references(&self) -> Result<Vec<Reference>, Error> {
+            let customer_id = Some(&self.customer_id);
+            let coupon_id = self.coupon_id.as_ref();
+            Ok(vec![
+                Reference::to::<Customer, _>("customer_id", customer_id, OnDelete::Restrict)?,
+                Reference::to::<Coupon, _>("coupon_id", coupon_id, OnDelete::Tombstone)?,
+            ])
+        }
+
+        fn clear_reference(&mut self, name: &'static str) {
+            if name == "coupon_id" { self.coupon_id = None; }
+        }
+    }
+
+    impl HasDependents for Customer {
+        fn dependents() -> Vec<Dependent> { vec![Dependent::of::<Order>()] }
+    }
+
+    impl HasDependents for Coupon {
+        fn dependents() -> Vec<Dependent> { vec![Dependent::of::<Order>()] }
+    }
+
+    impl Defaults for Customer {}
+    impl Defaults for Coupon {}
+    impl Defaults for Order {}
+    impl Validate for Customer {}
+    impl Validate for Coupon {}
+    impl Validate for Order {}
+
+    fn database() -> Database {
+        let db = Database::in_memory().unwrap();
+        let mut txn = db.write().unwrap();
+        txn.insert(&Customer { id: 1 }).unwrap();
+        txn.insert(&Coupon { id: 10 }).unwrap();
+        txn.insert(&Order { id: 100, customer_id: 1, coupon_id: Some(10) }).unwrap();
+        txn.commit().unwrap();
+        db
+    }
+
+    #[test]
+    fn inserting_a_dangling_reference_fails() {
+        let db = database();
+        let mut txn = db.write().unwrap();
+        let order = Order { id: 101, customer_id: 2, coupon_id: None };
+        let result = txn.insert(&order);
+        assert!(matches!(
+            result,
+            Err(Error::ForeignKeyViolation {
+                reference: "customer_id",
+                parent_table: "customers",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn restrict_blocks_removing_a_referenced_record() {
+        let db = database();
+        let mut txn = db.write().unwrap();
+        let result = txn.remove::<u64, Customer>(&1);
+        assert!(matches!(
+            result,
+            Err(Error::ReferencedByDependent { table: "orders", reference: "customer_id", .. })
+        ));
+    }
+
+    #[test]
+    fn tombstone_clears_the_reference_and_keeps_the_record() {
+        let db = database();
+        let mut txn = db.write().unwrap();
+        assert_eq!(txn.remove::<u64, Coupon>(&10).unwrap(), Some(Coupon { id: 10 }));
+        txn.commit().unwrap();
+
+        let orders = db.read().unwrap().open_table::<u64, Order>("orders").unwrap();
+        let order = orders.get(&100).unwrap();
+        assert_eq!(order, Some(Order { id: 100, customer_id: 1, coupon_id: None }));
+    }
+}
@@ -0,0 +1,126 @@
+//! Write transaction methods that rewrite records in the current write format.
+
+use crate::defaults::Defaults;
+use crate::indexing::{HasTable, Indexable};
+use crate::typed::reserialization::{RESERIALIZATION_TABLE, ReserializationProgress};
+use crate::typed::rotation::Checkpoint;
+use crate::typed::transaction::QuerySource;
+use crate::typed::transaction::write::Transaction;
+use crate::{Codec, Error};
+use redb::{ReadableTable, TableDefinition};
+use std::ops::Bound;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Rewrites up to one batch of `V`'s records in the current write format, starting after the
+    /// checkpoint left by the previous batch, and records the new checkpoint.
+    ///
+    /// Each record is decoded in the format it was written in, and encoded again in the current
+    /// format unless it's already in it. Records whose primary key encodes differently are moved
+    /// to the new key, along with their secondary index entries. Once the last batch is written,
+    /// the table's secondary indexes are repaired, which updates entries whose secondary key
+    /// encodes differently.
+    ///
+    /// # Errors
+    ///
+    /// * Decoding a record or primary key fails, or encoding one in the current format fails.
+    ///
+    /// * Returns [`Error::IndexCollision`] if a moved record collides with a `Unique` index entry.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn reserialize_batch<K, V>(
+        &mut self,
+        batch_size: usize,
+        status: &mut ReserializationProgress,
+    ) -> Result<Checkpoint, Error>
+    where
+        K: Codec<K>,
+        V: for<'i> Indexable<'i> + Defaults + Codec<V> + HasTable,
+    {
+        let full_table_name = self.namespace.table_name(V::table_name()).into_owned();
+        let progress_table = self.redb.open_table(RESERIALIZATION_TABLE)?;
+        let after = progress_table
+            .get(&*full_table_name)?
+            .and_then(|checkpoint| Checkpoint::decode(checkpoint.value()));
+        drop(progress_table);
+
+        let primary_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&full_table_name))?;
+        let bounds = match &after {
+            Some(Checkpoint::After(key)) => (Bound::Excluded(&**key), Bound::Unbounded),
+            _ => (Bound::Unbounded, Bound::Unbounded),
+        };
+        let batch = primary_table
+            .range::<&[u8]>(bounds)?
+            .take(batch_size)
+            .map(|entry| entry.map(|(key, value)| (key.value().to_vec(), value.value().to_vec())))
+            .collect::<Result<Vec<_>, _>>()?;
+        drop(primary_table);
+
+        let checkpoint = match batch.last() {
+            Some((last_key, _)) if batch.len() == batch_size => Checkpoint::After(last_key.clone()),
+            _ => Checkpoint::Done,
+        };
+
+        let context = self.record_context(V::table_name());
+        for (primary_key_bytes, value_bytes) in batch {
+            let (record, in_current_format) =
+                context.open_in_current_format::<V>(&primary_key_bytes, &value_bytes)?;
+            let new_primary_key_bytes = K::serialize(&K::deserialize(&primary_key_bytes)?)?;
+            if in_current_format && new_primary_key_bytes == primary_key_bytes {
+                status.entries_unchanged += 1;
+                continue;
+            }
+
+            if new_primary_key_bytes != primary_key_bytes {
+                self.move_record::<V>(&primary_key_bytes)?;
+                status.keys_moved += 1;
+            }
+            self.write_indexed(&new_primary_key_bytes, &record, |_| Ok(()))?;
+            status.entries_rewritten += 1;
+        }
+
+        let mut progress_table = self.redb.open_table(RESERIALIZATION_TABLE)?;
+        match &checkpoint {
+            Checkpoint::After(_) => {
+                progress_table.insert(&*full_table_name, &*checkpoint.encode())?;
+            },
+            Checkpoint::Done => {
+                progress_table.remove(&*full_table_name)?;
+                drop(progress_table);
+                status.index_entries_repaired += self.repair_indexes::<V>()?.repaired;
+            },
+        }
+
+        Ok(checkpoint)
+    }
+
+    /// Removes a record stored under its old primary key encoding, along with its reverse index
+    /// row and the secondary index entries that row lists. Entries of record types without a
+    /// reverse index are pruned when the indexes are repaired, after the last batch.
+    ///
+    /// # Errors
+    ///
+    /// * Decoding the reverse index row or a key set fails, or encoding a key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    fn move_record<V: HasTable>(&self, primary_key_bytes: &[u8]) -> Result<(), Error> {
+        let table_name = self.namespace.table_name(V::table_name());
+        self.charge_quota(&table_name, &[(primary_key_bytes, None)])?;
+        self.record_history(V::table_name(), V::history_table_name(), primary_key_bytes, true)?;
+        let mut primary_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&table_name))?;
+        primary_table.remove(primary_key_bytes)?;
+        drop(primary_table);
+
+        self.record_change(V::table_name(), primary_key_bytes, None)?;
+        if let Some(reverse_index_name) = V::reverse_index_name() {
+            self.remove_reverse_indexed_keys(reverse_index_name, primary_key_bytes)?;
+        }
+
+        Ok(())
+    }
+}
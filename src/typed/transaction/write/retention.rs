@@ -0,0 +1,71 @@
+//! Write transaction methods that prune tables under a retention policy.
+
+use crate::Error;
+use crate::typed::retention::{Limit, Retention, RetentionReport, Rule};
+use crate::typed::transaction::write::Transaction;
+use redb::{ReadableTable, ReadableTableMetadata, TableDefinition};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Removes the records that have expired under a retention policy from the transaction's
+    /// namespace, and returns how many were removed from each table.
+    ///
+    /// Expired records are a range at the start of each table, since keys are ordered oldest
+    /// first. They're removed along with their secondary index entries, reverse index rows, and
+    /// quota usage, and the removals are recorded in the change log, audit log, and history, like
+    /// [`Transaction::delete_matching`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let retention = Retention::new()
+    ///     .keep_for::<u64, LogLine>(Duration::from_secs(86_400), unix_millis);
+    ///
+    /// let mut txn = db.write()?;
+    /// let report = txn.apply_retention(&retention)?;
+    /// txn.commit()?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Encoding a cutoff key, or decoding a record or key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn apply_retention(&mut self, retention: &Retention) -> Result<RetentionReport, Error> {
+        let now = crate::clock::now();
+        let mut report = RetentionReport::default();
+        for rule in &retention.rules {
+            let expired = self.expired_keys(rule, now)?;
+            let removed = (rule.prune)(self, &expired)?;
+            *report.removed.entry(rule.table_name).or_default() += removed;
+        }
+        Ok(report)
+    }
+
+    /// Returns the primary key bytes of a table's expired records, oldest first.
+    fn expired_keys(&self, rule: &Rule, now: SystemTime) -> Result<Vec<Vec<u8>>, Error> {
+        let table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(
+                TableDefinition::new(&self.namespace.table_name(rule.table_name))
+            )?;
+
+        let before_cutoff = match &rule.limit {
+            Limit::Age { max_age, cutoff_key } => {
+                let cutoff = now.checked_sub(*max_age).unwrap_or(UNIX_EPOCH);
+                table.range::<&[u8]>(..&*cutoff_key(cutoff)?)?.count() as u64
+            },
+            Limit::Entries { .. } => 0,
+        };
+
+        let expired = rule.limit.expired(table.len()?, before_cutoff);
+        table
+            .iter()?
+            .take(usize::try_from(expired).unwrap_or(usize::MAX))
+            .map(|entry| Ok(entry?.0.value().to_vec()))
+            .collect()
+    }
+}
@@ -0,0 +1,170 @@
+//! Write transaction methods that maintain reverse index tables, which map each record's primary
+//! key to the secondary index entries it appears in.
+
+use crate::Error;
+use crate::indexing::{HasTable, Indexable, IndexKeyBytes, ReverseEntry};
+use crate::typed::transaction::write::Transaction;
+use crate::typed::transaction::QuerySource;
+use crate::Codec;
+use redb::{ReadableTable, TableDefinition};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Deletes a record and its secondary index entries without reading the record, using its
+    /// type's reverse index to find the entries. Returns `true` if the record existed.
+    ///
+    /// The record isn't decoded, so this works even if it no longer decodes, for example after its
+    /// type has changed incompatibly.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::ReverseIndexNotDeclared`] if the record type doesn't declare a reverse
+    ///   index with [`HasTable::reverse_index_name`].
+    ///
+    /// * Encoding the primary key fails, or decoding a reverse index row or key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn delete_indexed<K, V>(&mut self, primary_key: &K) -> Result<bool, Error>
+    where
+        K: Codec<K>,
+        V: HasTable,
+    {
+        let reverse_index_name = V::reverse_index_name()
+            .ok_or(Error::ReverseIndexNotDeclared { table: V::table_name() })?;
+        let primary_key_bytes = K::serialize(primary_key)?;
+        let table_name = self.namespace.table_name(V::table_name());
+        self.charge_quota(&table_name, &[(&*primary_key_bytes, None)])?;
+        self.record_history(V::table_name(), V::history_table_name(), &primary_key_bytes, true)?;
+
+        let mut primary_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&table_name))?;
+        let existed = primary_table.remove(&*primary_key_bytes)?.is_some();
+        drop(primary_table);
+
+        if existed {
+            self.record_change(V::table_name(), &primary_key_bytes, None)?;
+        }
+
+        self.remove_reverse_indexed_keys(reverse_index_name, &primary_key_bytes)?;
+        Ok(existed)
+    }
+
+    /// Rebuilds a record type's reverse index by scanning its primary table.
+    ///
+    /// Reverse index rows are kept up to date by `atlatl`'s index-aware writes. Call this after
+    /// declaring a reverse index for a table that already has records. Returns the number of rows
+    /// written. Does nothing if the record type doesn't declare a reverse index.
+    ///
+    /// # Errors
+    ///
+    /// * Decoding a record or encoding a secondary key fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn rebuild_reverse_index<V>(&mut self) -> Result<u64, Error>
+    where
+        V: for<'i> Indexable<'i> + Codec<V> + HasTable,
+    {
+        let Some(reverse_index_name) = V::reverse_index_name() else { return Ok(0) };
+
+        let primary_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(
+                TableDefinition::new(&self.namespace.table_name(V::table_name()))
+            )?;
+        let context = self.record_context(V::table_name());
+        let mut rows = Vec::new();
+        for entry in primary_table.iter()? {
+            let (key_guard, value_guard) = entry?;
+            let record: V = context.open(key_guard.value(), value_guard.value())?;
+            let index_keys = self.protect_index_keys(IndexKeyBytes::of(&record)?);
+            rows.push((key_guard.value().to_vec(), ReverseEntry::encode_row(&index_keys)?));
+        }
+        drop(primary_table);
+
+        self.redb.delete_table(TableDefinition::<&[u8], &[u8]>::new(
+            &self.namespace.table_name(reverse_index_name)
+        ))?;
+        let mut reverse_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(
+                TableDefinition::new(&self.namespace.table_name(reverse_index_name))
+            )?;
+        let mut written = 0;
+        for (primary_key_bytes, row) in rows {
+            if !row.is_empty() {
+                reverse_table.insert(&*primary_key_bytes, &*row)?;
+                written += 1;
+            }
+        }
+
+        Ok(written)
+    }
+
+    /// Replaces a record's reverse index row with its current secondary index keys. A record with
+    /// no index keys has its row removed.
+    ///
+    /// # Errors
+    ///
+    /// * Encoding the reverse index row fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn set_reverse_index_row(
+        &self,
+        reverse_index_name: &str,
+        primary_key_bytes: &[u8],
+        index_keys: &[IndexKeyBytes],
+    ) -> Result<(), Error> {
+        let mut reverse_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(
+                TableDefinition::new(&self.namespace.table_name(reverse_index_name))
+            )?;
+
+        if index_keys.is_empty() {
+            reverse_table.remove(primary_key_bytes)?;
+        } else {
+            reverse_table.insert(primary_key_bytes, &*ReverseEntry::encode_row(index_keys)?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes a record's reverse index row, and the primary key from every secondary index entry
+    /// the row lists. Returns `true` if the record had a row.
+    ///
+    /// # Errors
+    ///
+    /// * Decoding the reverse index row or a key set fails, or encoding a key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn remove_reverse_indexed_keys(
+        &self,
+        reverse_index_name: &str,
+        primary_key_bytes: &[u8],
+    ) -> Result<bool, Error> {
+        let mut reverse_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(
+                TableDefinition::new(&self.namespace.table_name(reverse_index_name))
+            )?;
+        let Some(entries) = reverse_table
+            .remove(primary_key_bytes)?
+            .map(|row| ReverseEntry::decode_row(row.value()))
+            .transpose()?
+        else {
+            return Ok(false);
+        };
+        drop(reverse_table);
+
+        for entry in entries {
+            self.remove_index_entry(
+                primary_key_bytes,
+                &entry.index_name,
+                entry.index_kind,
+                &entry.secondary_key_bytes,
+                entry.covering_table_name.as_deref(),
+            )?;
+        }
+
+        Ok(true)
+    }
+}
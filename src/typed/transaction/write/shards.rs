@@ -0,0 +1,395 @@
+//! Write transaction methods that add and remove primary keys in key sets, sharding very large key
+//! sets across multiple rows. See [`crate::indexing::KeySetCursor`].
+
+use crate::indexing::{
+    AlignedBytes, ArchivedKeySet, KeySet, ReadableKeySet, SHARD_CAPACITY, is_full_shard, shard_key,
+    shard_table_name
+};
+use crate::typed::transaction::write::Transaction;
+use crate::typed::transaction::QuerySource;
+use crate::Error;
+use redb::{ReadableTable, TableDefinition};
+use std::collections::{BTreeMap, BTreeSet};
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The shards of a key set that adding or removing one primary key touches: the shard that holds
+/// the primary key, if any, and the last shard. Every other shard is full, and is left unread.
+#[derive(Default)]
+struct ShardLookup {
+    /// Number of shards. `0` if the key set doesn't exist.
+    count: usize,
+
+    /// Number of the shard that holds the primary key, if any.
+    holder: Option<usize>,
+
+    /// The holder and last shards, by shard number.
+    shards: BTreeMap<usize, KeySet>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl ShardLookup {
+    /// Returns the number of primary keys across all shards. Every shard but the last is full, so
+    /// it's known from the last shard alone.
+    fn len(&self) -> usize {
+        self.count.checked_sub(1).map_or(0, |last| {
+            last * SHARD_CAPACITY + self.shards.get(&last).map_or(0, ReadableKeySet::len)
+        })
+    }
+}
+
+impl Transaction {
+    /// Adds a primary key to a secondary key's key set. Returns the key set's size before and
+    /// after.
+    ///
+    /// The primary key is added to the last shard, or to a new shard if the last one is full. Only
+    /// that shard is rewritten, and only that shard is decoded. See
+    /// [`Transaction::lookup_shards`].
+    ///
+    /// # Errors
+    ///
+    /// * Decoding or encoding a key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn insert_into_key_set(
+        &self,
+        index_name: &str,
+        secondary_key_bytes: &[u8],
+        primary_key_bytes: &[u8],
+    ) -> Result<(usize, usize), Error> {
+        let mut lookup = self.lookup_shards(index_name, secondary_key_bytes, primary_key_bytes)?;
+        let old_len = lookup.len();
+        if lookup.holder.is_some() {
+            return Ok((old_len, old_len));
+        }
+
+        let (shard, mut key_set) = match lookup.shards.pop_last() {
+            Some((last, key_set)) if !is_full_shard(key_set.len()) => (last, key_set),
+            _ => (lookup.count, KeySet::default()),
+        };
+        key_set.insert(primary_key_bytes.to_vec());
+        self.write_shard(index_name, secondary_key_bytes, shard, Some(&key_set))?;
+
+        Ok((old_len, old_len + 1))
+    }
+
+    /// Removes a primary key from a secondary key's key set. Returns the key set's size before and
+    /// after. A key set that becomes empty is removed entirely.
+    ///
+    /// To keep every shard but the last one full, a primary key is moved from the last shard into
+    /// the shard that the primary key was removed from. At most two shards are decoded and
+    /// rewritten. See [`Transaction::lookup_shards`].
+    ///
+    /// # Errors
+    ///
+    /// * Decoding or encoding a key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn remove_from_key_set(
+        &self,
+        index_name: &str,
+        secondary_key_bytes: &[u8],
+        primary_key_bytes: &[u8],
+    ) -> Result<(usize, usize), Error> {
+        let mut lookup = self.lookup_shards(index_name, secondary_key_bytes, primary_key_bytes)?;
+        let old_len = lookup.len();
+        let Some(holder) = lookup.holder else {
+            return Ok((old_len, old_len));
+        };
+
+        let last = lookup.count - 1;
+        let mut last_shard = lookup.shards.remove(&last).unwrap_or_default();
+        if holder == last {
+            last_shard.remove(primary_key_bytes);
+        } else {
+            let mut holder_shard = lookup.shards.remove(&holder).unwrap_or_default();
+            holder_shard.remove(primary_key_bytes);
+            let mut last_keys = last_shard.into_iter();
+            if let Some(moved_key) = last_keys.next() {
+                holder_shard.insert(moved_key);
+            }
+            last_shard = last_keys.collect();
+            self.write_shard(index_name, secondary_key_bytes, holder, Some(&holder_shard))?;
+        }
+
+        let last_shard = Some(&last_shard).filter(|shard| !shard.is_empty());
+        self.write_shard(index_name, secondary_key_bytes, last, last_shard)?;
+
+        Ok((old_len, old_len - 1))
+    }
+
+    /// Adds and removes many primary keys in a secondary key's key set at once. Returns the key
+    /// set's size before and after. A key set that becomes empty is removed entirely.
+    ///
+    /// The key set is read once, and each shard that changed is written once, however many primary
+    /// keys were added or removed. Shards are kept full like [`Transaction::insert_into_key_set`]
+    /// and [`Transaction::remove_from_key_set`] keep them.
+    ///
+    /// # Errors
+    ///
+    /// * Decoding or encoding a key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn update_key_set(
+        &self,
+        index_name: &str,
+        secondary_key_bytes: &[u8],
+        added: &[Vec<u8>],
+        removed: &[Vec<u8>],
+    ) -> Result<(usize, usize), Error> {
+        let mut shards = self.read_shards(index_name, secondary_key_bytes)?;
+        let old_shard_count = shards.len();
+        let old_len: usize = shards.iter().map(ReadableKeySet::len).sum();
+
+        // Shards that were modified, or emptied and dropped:
+        let mut changed: BTreeSet<usize> = BTreeSet::new();
+        for primary_key_bytes in removed {
+            let Some(holder) = shards.iter().position(|shard| shard.contains(primary_key_bytes))
+            else {
+                continue;
+            };
+
+            shards[holder].remove(primary_key_bytes);
+            changed.insert(holder);
+            let last = shards.len() - 1;
+            if holder != last {
+                let mut last_keys = std::mem::take(&mut shards[last]).into_iter();
+                if let Some(moved_key) = last_keys.next() {
+                    shards[holder].insert(moved_key);
+                }
+                shards[last] = last_keys.collect();
+                changed.insert(last);
+            }
+            if shards[last].is_empty() {
+                shards.pop();
+            }
+        }
+
+        for primary_key_bytes in added {
+            if shards.iter().any(|shard| shard.contains(primary_key_bytes)) {
+                continue;
+            }
+            if shards.last().is_none_or(|shard| is_full_shard(shard.len())) {
+                shards.push(KeySet::default());
+            }
+            let last = shards.len() - 1;
+            shards[last].insert(primary_key_bytes.clone());
+            changed.insert(last);
+        }
+
+        changed.extend(shards.len()..old_shard_count);
+        for shard in changed {
+            self.write_shard(index_name, secondary_key_bytes, shard, shards.get(shard))?;
+        }
+
+        Ok((old_len, shards.iter().map(ReadableKeySet::len).sum()))
+    }
+
+    /// Finds the shards of a secondary key's key set that adding or removing a primary key touches,
+    /// and decodes only those: the shard holding the primary key, and the last shard. Shards of a
+    /// protected index are decrypted, see [`crate::indexing::IndexProtection`].
+    ///
+    /// The last shard is found with a reverse range over the shard table. The holder is found by
+    /// checking the other shards in order, without decoding them, until one contains the primary
+    /// key.
+    ///
+    /// # Errors
+    ///
+    /// * Decoding a key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    fn lookup_shards(
+        &self,
+        index_name: &str,
+        secondary_key_bytes: &[u8],
+        primary_key_bytes: &[u8],
+    ) -> Result<ShardLookup, Error> {
+        let index_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&self.namespace.table_name(index_name)))?;
+        let Some(first_shard) = index_table.get(secondary_key_bytes)? else {
+            return Ok(ShardLookup::default());
+        };
+        let first_shard =
+            self.open_index_value(index_name, secondary_key_bytes, first_shard.value())?;
+        let first_shard = AlignedBytes::new(&first_shard);
+        let first_view = ArchivedKeySet::from_bytes(&first_shard)?;
+
+        let mut lookup = ShardLookup { count: 1, ..ShardLookup::default() };
+        if first_view.contains(primary_key_bytes) {
+            lookup.holder = Some(0);
+        }
+        if !is_full_shard(first_view.len()) {
+            lookup.shards.insert(0, KeySet::from_bytes(&first_shard)?);
+            return Ok(lookup);
+        }
+
+        let shard_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(
+                TableDefinition::new(&self.namespace.table_name(&shard_table_name(index_name)))
+            )?;
+        let first = shard_key(secondary_key_bytes, 1)?;
+        let last = shard_key(secondary_key_bytes, u32::MAX)?;
+        let mut shards = shard_table.range::<&[u8]>(&*first..=&*last)?;
+
+        // Shard numbers are the last four bytes of shard keys:
+        let shard_number = |row_key: &[u8]| -> usize {
+            row_key.last_chunk::<4>().map_or(0, |number| u32::from_be_bytes(*number) as usize)
+        };
+
+        let Some(last_shard) = shards.next_back() else {
+            lookup.shards.insert(0, KeySet::from_bytes(&first_shard)?);
+            return Ok(lookup);
+        };
+        let (row_key, shard_bytes) = last_shard?;
+        let last = shard_number(row_key.value());
+        let last_shard = KeySet::from_bytes(
+            &self.open_index_value(index_name, row_key.value(), shard_bytes.value())?
+        )?;
+        lookup.count = last + 1;
+        if lookup.holder.is_none() && last_shard.contains(primary_key_bytes) {
+            lookup.holder = Some(last);
+        }
+        lookup.shards.insert(last, last_shard);
+
+        if lookup.holder == Some(0) {
+            lookup.shards.insert(0, KeySet::from_bytes(&first_shard)?);
+        }
+        if lookup.holder.is_none() {
+            for shard in shards {
+                let (row_key, shard_bytes) = shard?;
+                let shard_bytes =
+                    self.open_index_value(index_name, row_key.value(), shard_bytes.value())?;
+                let shard_bytes = AlignedBytes::new(&shard_bytes);
+                if ArchivedKeySet::from_bytes(&shard_bytes)?.contains(primary_key_bytes) {
+                    let holder = shard_number(row_key.value());
+                    lookup.holder = Some(holder);
+                    lookup.shards.insert(holder, KeySet::from_bytes(&shard_bytes)?);
+                    break;
+                }
+            }
+        }
+
+        Ok(lookup)
+    }
+
+    /// Reads every shard of a secondary key's key set, in order. A missing key set has no shards.
+    /// Shards of a protected index are decrypted, see [`crate::indexing::IndexProtection`].
+    ///
+    /// # Errors
+    ///
+    /// * Decoding a key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    fn read_shards(&self, index_name: &str, secondary_key_bytes: &[u8]) -> Result<Vec<KeySet>, Error> {
+        let index_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&self.namespace.table_name(index_name)))?;
+        let Some(first_shard) = index_table.get(secondary_key_bytes)? else {
+            return Ok(Vec::new());
+        };
+
+        let mut shards = vec![KeySet::from_bytes(
+            &self.open_index_value(index_name, secondary_key_bytes, first_shard.value())?
+        )?];
+        if is_full_shard(shards[0].len()) {
+            let shard_table_name = shard_table_name(index_name);
+            let shard_table: redb::Table<&[u8], &[u8]> =
+                self.redb.open_table(
+                    TableDefinition::new(&self.namespace.table_name(&shard_table_name))
+                )?;
+            let first = shard_key(secondary_key_bytes, 1)?;
+            let last = shard_key(secondary_key_bytes, u32::MAX)?;
+            for shard in shard_table.range::<&[u8]>(&*first..=&*last)? {
+                let (row_key, shard_bytes) = shard?;
+                shards.push(KeySet::from_bytes(
+                    &self.open_index_value(index_name, row_key.value(), shard_bytes.value())?
+                )?);
+            }
+        }
+
+        Ok(shards)
+    }
+
+    /// Writes one shard of a secondary key's key set, or removes it if `key_set` is `None`. Shard
+    /// #0 is stored in the index table, and the rest in the index's shard table. Shards of a
+    /// protected index are encrypted.
+    ///
+    /// # Errors
+    ///
+    /// * Encoding the key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    fn write_shard(
+        &self,
+        index_name: &str,
+        secondary_key_bytes: &[u8],
+        shard: usize,
+        key_set: Option<&KeySet>,
+    ) -> Result<(), Error> {
+        let (table_name, key) = if shard == 0 {
+            (index_name.to_string(), secondary_key_bytes.to_vec())
+        } else {
+            let shard = u32::try_from(shard).map_err(|_| Error::BufferTooLargeForTarget {
+                buffer_len: shard,
+                target_len: u32::MAX as usize,
+            })?;
+            (shard_table_name(index_name), shard_key(secondary_key_bytes, shard)?)
+        };
+
+        let mut table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&self.namespace.table_name(&table_name)))?;
+        match key_set {
+            Some(key_set) => {
+                let key_set_bytes = self.seal_index_value(index_name, &key, key_set.to_bytes()?)?;
+                table.insert(&*key, &*key_set_bytes)?;
+            },
+            None => { table.remove(&*key)?; },
+        }
+
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::typed::database::Database;
+
+    const INDEX: &str = "creatures_by_habitat";
+
+    fn primary_key(id: usize) -> Vec<u8> {
+        id.to_be_bytes().to_vec()
+    }
+
+    #[test]
+    fn keys_are_added_to_and_removed_from_their_shards() {
+        let db = Database::in_memory().unwrap();
+        let txn = db.write().unwrap();
+        let len = SHARD_CAPACITY * 2 + 10;
+        let keys: Vec<Vec<u8>> = (0..len).map(primary_key).collect();
+        assert_eq!(txn.update_key_set(INDEX, b"Desert", &keys, &[]).unwrap(), (0, len));
+
+        // Present keys aren't added again, and new keys go to the last shard:
+        assert_eq!(txn.insert_into_key_set(INDEX, b"Desert", &primary_key(5)).unwrap(), (len, len));
+        let added = primary_key(len);
+        assert_eq!(txn.insert_into_key_set(INDEX, b"Desert", &added).unwrap(), (len, len + 1));
+
+        // A key removed from a full shard is replaced by one from the last shard:
+        let removed = primary_key(SHARD_CAPACITY + 3);
+        assert_eq!(txn.remove_from_key_set(INDEX, b"Desert", &removed).unwrap(), (len + 1, len));
+        assert_eq!(txn.remove_from_key_set(INDEX, b"Desert", &removed).unwrap(), (len, len));
+
+        let shards = txn.read_shards(INDEX, b"Desert").unwrap();
+        let lens: Vec<usize> = shards.iter().map(ReadableKeySet::len).collect();
+        assert_eq!(lens, [SHARD_CAPACITY, SHARD_CAPACITY, 10]);
+        assert!(shards.iter().all(|shard| !shard.contains(&removed)));
+        assert!(shards.iter().any(|shard| shard.contains(&added)));
+        assert_eq!(txn.lookup_shards(INDEX, b"Desert", &primary_key(1)).unwrap().holder, Some(0));
+    }
+}
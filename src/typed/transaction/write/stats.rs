@@ -0,0 +1,112 @@
+//! Write transaction methods that gather and maintain secondary index statistics.
+
+use crate::Error;
+use crate::indexing::{
+    AlignedBytes, ArchivedKeySet, Index, IndexKind, IndexStats, ReadableKeySet, STATS_TABLE_NAME
+};
+use crate::typed::transaction::write::Transaction;
+use crate::typed::transaction::QuerySource;
+use redb::{ReadableTable, TableDefinition};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Scans an index table and stores fresh [`IndexStats`] for it.
+    ///
+    /// Once an index has been analyzed, its statistics are kept up to date as records are written
+    /// and deleted, so this only needs to be called again if the index was changed outside of
+    /// `atlatl`, or to correct drift.
+    ///
+    /// # Errors
+    ///
+    /// * Deserialization errors when instantiating a key set from an index entry.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn analyze<I: Index>(&mut self) -> Result<IndexStats, Error> {
+        self.analyze_index(I::index_name(), I::index_kind())
+    }
+
+    /// Scans the named index table and stores fresh [`IndexStats`] for it. See
+    /// [`Transaction::analyze`].
+    ///
+    /// # Errors
+    ///
+    /// * Deserialization errors when instantiating a key set from an index entry.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn analyze_index(
+        &mut self,
+        index_name: &'static str,
+        index_kind: IndexKind,
+    ) -> Result<IndexStats, Error> {
+        let mut stats = IndexStats::default();
+
+        let index_table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&self.namespace.table_name(index_name)))?;
+        for entry in index_table.iter()? {
+            let (secondary_key, value) = entry?;
+            match index_kind {
+                IndexKind::Unique => stats.record(1),
+                IndexKind::NonUnique => {
+                    let key_set_bytes =
+                        self.open_index_value(index_name, secondary_key.value(), value.value())?;
+                    let key_set_bytes = AlignedBytes::new(&key_set_bytes);
+                    let first_shard_len = ArchivedKeySet::from_bytes(&key_set_bytes)?.len();
+                    let overflow_len = self
+                        .overflow_keys(index_name, secondary_key.value(), first_shard_len)?
+                        .map_or(0, |overflow| overflow.len());
+                    stats.record((first_shard_len + overflow_len) as u64);
+                },
+            }
+        }
+        drop(index_table);
+
+        let mut stats_table: redb::Table<&str, &[u8]> =
+            self.redb.open_table(
+                TableDefinition::new(&self.namespace.table_name(STATS_TABLE_NAME))
+            )?;
+        stats_table.insert(index_name, &*stats.to_bytes())?;
+
+        Ok(stats)
+    }
+
+    /// Records that one of an index's key sets changed size. Sizes of zero mean the key set didn't
+    /// exist, or no longer exists.
+    ///
+    /// Indexes that have never been analyzed have no statistics, and are left that way.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`Error::Corrupted`] if the stored statistics can't be decoded.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub(crate) fn resize_index_stats(
+        &self,
+        index_name: &str,
+        old_set_size: usize,
+        new_set_size: usize,
+    ) -> Result<(), Error> {
+        if old_set_size == new_set_size {
+            return Ok(());
+        }
+
+        let mut stats_table: redb::Table<&str, &[u8]> =
+            self.redb.open_table(
+                TableDefinition::new(&self.namespace.table_name(STATS_TABLE_NAME))
+            )?;
+
+        let Some(mut stats) = stats_table
+            .get(index_name)?
+            .map(|row| IndexStats::from_bytes(row.value()))
+            .transpose()?
+        else {
+            return Ok(());
+        };
+
+        stats.resize(old_set_size as u64, new_set_size as u64);
+        stats_table.insert(index_name, &*stats.to_bytes())?;
+        Ok(())
+    }
+}
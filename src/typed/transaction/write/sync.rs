@@ -0,0 +1,100 @@
+//! Write transaction methods that answer an anti-entropy sync, as the replica.
+
+use crate::Error;
+use crate::typed::digest::digest_range;
+use crate::typed::sync::{Frame, KeyRange, SyncReport, Transport, check_hello, hello};
+use crate::typed::transaction::write::Transaction;
+use redb::{ReadableTable, TableDefinition};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Synchronizes this database with a primary, which must be driving the sync on the other end
+    /// of `transport` with the read transaction's `sync_to` method. Returns once the primary has
+    /// compared every table.
+    ///
+    /// Differing key ranges are replaced with the primary's entries, including secondary index
+    /// entries. Nothing is visible to other transactions until this transaction is committed. See
+    /// the [`sync`](crate::typed::sync) module for the protocol.
+    ///
+    /// # Errors
+    ///
+    /// All errors stop the sync, and the transaction should be aborted.
+    ///
+    /// * Returns [`Error::SyncLayerMismatch`] if the primary was built with different serializer,
+    ///   compressor, encryptor, corrector, or key set features.
+    ///
+    /// * Returns [`Error::SyncProtocol`] if the primary sent a malformed or unexpected frame.
+    ///
+    /// * The transport failed, see [`Transport`].
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn sync_from(&mut self, transport: &mut impl Transport) -> Result<SyncReport, Error> {
+        let primary_hello = Frame::decode(&transport.recv()?)?;
+        transport.send(hello().encode()?)?;
+        check_hello(primary_hello)?;
+
+        let mut report = SyncReport::default();
+        let mut current_table = String::new();
+        loop {
+            match Frame::decode(&transport.recv()?)? {
+                Frame::Digest { table, range, digest } => {
+                    if table != current_table {
+                        report.tables += 1;
+                        current_table.clone_from(&table);
+                    }
+                    report.ranges_compared += 1;
+                    let table: redb::Table<&[u8], &[u8]> =
+                        self.redb.open_table(
+                            TableDefinition::new(&self.namespace.table_name(&table))
+                        )?;
+                    let matched = digest_range(&table, range.bounds())? == digest;
+                    transport.send(Frame::Matches(matched).encode()?)?;
+                },
+                Frame::Entries { table, range, entries } => {
+                    report.ranges_shipped += 1;
+                    report.entries_shipped += entries.len() as u64;
+                    let removed = self.replace_range(&table, &range, &entries)?;
+                    report.entries_removed += removed;
+                    transport.send(Frame::Applied { removed }.encode()?)?;
+                },
+                Frame::Done => return Ok(report),
+                _ => return Err(Error::SyncProtocol { reason: "unexpected frame" }),
+            }
+        }
+    }
+
+    /// Replaces a table's entries in a key range with the primary's entries, which are in key
+    /// order. Returns the number of entries removed because the primary doesn't have them.
+    ///
+    /// # Errors
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    fn replace_range(
+        &self,
+        table_name: &str,
+        range: &KeyRange,
+        entries: &[(Vec<u8>, Vec<u8>)],
+    ) -> Result<u64, Error> {
+        let mut table: redb::Table<&[u8], &[u8]> =
+            self.redb.open_table(TableDefinition::new(&self.namespace.table_name(table_name)))?;
+
+        let mut stale = Vec::new();
+        for entry in table.range::<&[u8]>(range.bounds())? {
+            let key = entry?.0.value().to_vec();
+            if entries.binary_search_by(|(shipped, _)| shipped.cmp(&key)).is_err() {
+                stale.push(key);
+            }
+        }
+        for key in &stale {
+            table.remove(key.as_slice())?;
+        }
+
+        for (key, value) in entries {
+            table.insert(key.as_slice(), value.as_slice())?;
+        }
+        Ok(stale.len() as u64)
+    }
+}
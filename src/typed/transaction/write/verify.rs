@@ -0,0 +1,76 @@
+//! Write transaction methods that repair inconsistent secondary indexes.
+
+use crate::indexing::{HasTable, Indexable, IndexIssue, IndexKeyBytes, IndexReport};
+use crate::typed::transaction::find_index_issues;
+use crate::typed::transaction::write::Transaction;
+use crate::{Codec, Error};
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl Transaction {
+    /// Cross-checks every secondary index of a record type against its primary table, and repairs
+    /// the inconsistencies that were found.
+    ///
+    /// * Dangling index entries are pruned.
+    ///
+    /// * Missing index entries are added. Projections of covering indexes aren't restored, so
+    ///   rebuild covering indexes with `rebuild_index` instead.
+    ///
+    /// * `Unique` violations are reported, but can't be repaired: all but one of the records
+    ///   involved must be changed or deleted.
+    ///
+    /// The returned report lists every issue found, and how many were repaired.
+    ///
+    /// # Errors
+    ///
+    /// * Decoding a record or key set fails, or encoding a secondary key or key set fails.
+    ///
+    /// * A `redb` [storage error](crate::Error#storage-errors).
+    pub fn repair_indexes<V>(&mut self) -> Result<IndexReport, Error>
+    where
+        V: for<'i> Indexable<'i> + Codec<V> + HasTable,
+    {
+        let mut report = find_index_issues::<V, _>(self)?;
+
+        // Prune first, so that a dangling `Unique` entry doesn't block the missing one:
+        for issue in &report.issues {
+            if let IndexIssue::DanglingKey {
+                index_name,
+                index_kind,
+                secondary_key_bytes,
+                primary_key_bytes
+            } = issue {
+                self.remove_index_entry(
+                    primary_key_bytes,
+                    index_name,
+                    *index_kind,
+                    secondary_key_bytes,
+                    None,
+                )?;
+                report.repaired += 1;
+            }
+        }
+
+        for issue in &report.issues {
+            if let IndexIssue::MissingEntry {
+                index_name,
+                index_kind,
+                secondary_key_bytes,
+                primary_key_bytes
+            } = issue {
+                let index_key = IndexKeyBytes {
+                    index_name,
+                    index_kind: *index_kind,
+                    secondary_key_bytes: secondary_key_bytes.clone(),
+                    covering: None,
+                };
+                self.add_index_keys(primary_key_bytes, &[index_key])?;
+                report.repaired += 1;
+            }
+        }
+
+        Ok(report)
+    }
+}
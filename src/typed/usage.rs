@@ -0,0 +1,327 @@
+//! Disk usage accounting per table and per index, for capacity planning.
+//!
+//! `redb` knows how many bytes each table occupies, but not what they're spent on. A usage report
+//! adds the average key and value sizes of every table, sampled from its first entries, and breaks
+//! each registered record table down further:
+//!
+//! * How many of its stored bytes are layer tails and parity rather than values, estimated from
+//!   the record type's [`PipelineDescription`] and the sampled value sizes.
+//!
+//! * Which index tables belong to it, and how many bytes each of them occupies.
+
+use crate::Error;
+use crate::indexing::{HasTable, Index, shard_table_name};
+use crate::layers::{Compressible, Correctable, Encryptable, LayerStack, Layers};
+use crate::layers::{PipelineDescription, Serializable};
+use crate::typed::Namespace;
+use redb::{ReadableTable, ReadableTableMetadata, TableDefinition, TableHandle};
+use std::collections::BTreeMap;
+
+// -------------------------------------------------------------------------------------------------
+//
+// Constants
+
+/// Number of entries sampled from each table, unless changed with [`UsageOptions::sample_size`].
+pub const DEFAULT_SAMPLE_SIZE: usize = 256;
+
+// -------------------------------------------------------------------------------------------------
+//
+/// The record types and indexes to break down in a usage report.
+///
+/// Stored values don't record their type, so tables are only broken down further than `redb`'s own
+/// statistics once their record type is registered with [`UsageOptions::record`], and their
+/// indexes with [`UsageOptions::index`].
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let options = UsageOptions::new()
+///     .record::<Creature>()
+///     .index::<HabitatIndex>()
+///     .index::<DietIndex>();
+///
+/// for table in db.usage_with(&options)?.tables {
+///     println!("{}: {} bytes", table.name, table.stored_bytes);
+///     println!("{} bytes in indexes", table.index_bytes());
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct UsageOptions {
+    /// Registered record tables, keyed by unqualified table name.
+    records: BTreeMap<&'static str, RecordLayout>,
+
+    /// Number of entries sampled from each table.
+    sample_size: usize,
+}
+
+/// What's known about a registered record table.
+#[derive(Clone, Debug, Default)]
+struct RecordLayout {
+    /// The record type's write layers, if the record type was registered.
+    pipeline: Option<PipelineDescription>,
+
+    /// Unqualified names of the tables that index the record table.
+    indexes: Vec<String>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Disk usage of every table in a database, returned by `Database::usage`.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Usage {
+    /// Every table that isn't an index of a registered record table, in name order.
+    pub tables: Vec<TableUsage>,
+}
+
+/// Disk usage of a single table.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TableUsage {
+    /// The table's full name, including any namespace prefix.
+    pub name: String,
+
+    /// The number of entries in the table.
+    pub entries: u64,
+
+    /// The bytes of keys and values stored in the table's leaf pages.
+    pub stored_bytes: u64,
+
+    /// The bytes that `redb` spends on the table beyond its keys and values: branch pages, page
+    /// metadata, and fragmented space.
+    pub redb_overhead_bytes: u64,
+
+    /// The average key size in bytes, or `None` if the table's keys aren't byte slices.
+    pub average_key_len: Option<f64>,
+
+    /// The average stored value size in bytes, or `None` if the table's values aren't byte slices.
+    pub average_value_len: Option<f64>,
+
+    /// The estimated bytes spent on layer tails and parity across every value, or `None` if the
+    /// table's record type isn't registered.
+    pub layer_overhead_bytes: Option<u64>,
+
+    /// Whether the averages and layer overhead were computed from every entry, rather than
+    /// extrapolated from a sample.
+    pub is_exact: bool,
+
+    /// Usage of the table's indexes, including overflow shards and the reverse index, if its
+    /// record type and indexes were registered.
+    pub indexes: Vec<Self>,
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Method Implementations
+
+impl UsageOptions {
+    /// Instantiates options that don't register any record type, so that every table is reported
+    /// with `redb`'s statistics and sampled sizes only.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { records: BTreeMap::new(), sample_size: DEFAULT_SAMPLE_SIZE }
+    }
+
+    /// Registers a record type, so that its table's layer overhead is estimated and its reverse
+    /// index, if any, is reported under it.
+    #[must_use]
+    pub fn record<V>(mut self) -> Self
+    where
+        V: HasTable + Serializable + Compressible + Encryptable + Correctable + LayerStack,
+    {
+        let layout = self.records.entry(V::table_name()).or_default();
+        layout.pipeline = Some(Layers::describe::<V>());
+        layout.indexes.extend(V::reverse_index_name().map(str::to_string));
+        self
+    }
+
+    /// Registers an index, so that its table and overflow shards are reported under its record's
+    /// table.
+    #[must_use]
+    pub fn index<I: Index>(mut self) -> Self {
+        let layout = self.records.entry(<I::Record as HasTable>::table_name()).or_default();
+        layout.indexes.push(I::index_name().to_string());
+        layout.indexes.push(shard_table_name(I::index_name()));
+        self
+    }
+
+    /// Sets the number of entries sampled from each table. Tables with no more entries than this
+    /// are measured exactly. Defaults to [`DEFAULT_SAMPLE_SIZE`].
+    #[must_use]
+    pub const fn sample_size(mut self, sample_size: usize) -> Self {
+        self.sample_size = sample_size;
+        self
+    }
+}
+
+impl Default for UsageOptions {
+    /// Instantiates options that don't register any record type.
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Usage {
+    /// Returns the total bytes occupied by every table and index, including `redb`'s overhead.
+    #[must_use]
+    pub fn total_bytes(&self) -> u64 {
+        self.tables.iter().map(TableUsage::total_bytes).sum()
+    }
+
+    /// Returns the usage of the named table, by its full name.
+    #[must_use]
+    pub fn table(&self, name: &str) -> Option<&TableUsage> {
+        self.tables.iter().find(|table| table.name == name)
+    }
+}
+
+impl TableUsage {
+    /// Returns the bytes occupied by the table and its indexes, including `redb`'s overhead.
+    #[must_use]
+    pub fn total_bytes(&self) -> u64 {
+        self.stored_bytes + self.redb_overhead_bytes + self.index_bytes()
+    }
+
+    /// Returns the bytes occupied by the table's indexes, including `redb`'s overhead.
+    #[must_use]
+    pub fn index_bytes(&self) -> u64 {
+        self.indexes.iter().map(Self::total_bytes).sum()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Functions
+
+/// Measures every table of a `redb` database, and groups registered indexes under their record
+/// tables, in every namespace.
+///
+/// # Errors
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+pub(crate) fn measure_usage(
+    redb: &redb::Database,
+    options: &UsageOptions,
+) -> Result<Usage, Error> {
+    let txn = redb.begin_read().map_err(Box::new)?;
+
+    let mut tables = BTreeMap::new();
+    for handle in txn.list_tables()? {
+        let name = handle.name().to_string();
+        let table = txn.open_untyped_table(handle)?;
+        let stats = table.stats()?;
+        tables.insert(name.clone(), TableUsage {
+            name,
+            entries: table.len()?,
+            stored_bytes: stats.stored_bytes(),
+            redb_overhead_bytes: stats.metadata_bytes() + stats.fragmented_bytes(),
+            ..TableUsage::default()
+        });
+    }
+
+    // Sample sizes, and estimate layer overhead for registered record tables:
+    for usage in tables.values_mut() {
+        let layout = options.records.get(unqualified(&usage.name));
+        let pipeline = layout.and_then(|layout| layout.pipeline.as_ref());
+        sample(&txn, usage, pipeline, options.sample_size)?;
+    }
+
+    // Move each registered record table's indexes under it:
+    let record_names: Vec<String> = tables
+        .keys()
+        .filter(|name| options.records.contains_key(unqualified(name)))
+        .cloned()
+        .collect();
+    for record_name in record_names {
+        let prefix = &record_name[..record_name.len() - unqualified(&record_name).len()];
+        let indexes = options.records[unqualified(&record_name)]
+            .indexes
+            .iter()
+            .filter_map(|index_name| tables.remove(&format!("{prefix}{index_name}")))
+            .collect();
+        if let Some(record_table) = tables.get_mut(&record_name) {
+            record_table.indexes = indexes;
+        }
+    }
+
+    Ok(Usage { tables: tables.into_values().collect() })
+}
+
+/// Samples a table's first entries to measure its average key and value sizes and, if its record
+/// type's pipeline is known, its layer overhead. Tables whose keys or values aren't byte slices,
+/// such as some internal tables, aren't sampled.
+///
+/// # Errors
+///
+/// * A `redb` [storage error](crate::Error#storage-errors).
+fn sample(
+    txn: &redb::ReadTransaction,
+    usage: &mut TableUsage,
+    pipeline: Option<&PipelineDescription>,
+    sample_size: usize,
+) -> Result<(), Error> {
+    let definition = TableDefinition::<&[u8], &[u8]>::new(&usage.name);
+    let table = match txn.open_table(definition) {
+        Ok(table) => table,
+        Err(redb::TableError::TableTypeMismatch { .. }) => return Ok(()),
+        Err(error) => return Err(error.into()),
+    };
+
+    let (mut sampled, mut key_bytes, mut value_bytes, mut tail_bytes) = (0_u64, 0, 0, 0);
+    for entry in table.iter()?.take(sample_size) {
+        let (key, value) = entry?;
+        sampled += 1;
+        key_bytes += key.value().len() as u64;
+        value_bytes += value.value().len() as u64;
+        if let Some(pipeline) = pipeline {
+            tail_bytes += pipeline.tail_overhead(value.value().len()) as u64;
+        }
+    }
+
+    usage.is_exact = sampled == usage.entries;
+    if sampled == 0 {
+        usage.layer_overhead_bytes = pipeline.map(|_| 0);
+        return Ok(());
+    }
+
+    #[allow(clippy::cast_precision_loss, reason = "averages don't need every digit")]
+    let (sampled_f64, entries_f64) = (sampled as f64, usage.entries as f64);
+    #[allow(clippy::cast_precision_loss, reason = "averages don't need every digit")]
+    let (key_f64, value_f64) = (key_bytes as f64, value_bytes as f64);
+    usage.average_key_len = Some(key_f64 / sampled_f64);
+    usage.average_value_len = Some(value_f64 / sampled_f64);
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss,
+        reason = "the estimate is non-negative, and far below `u64::MAX`"
+    )]
+    let extrapolate = |bytes: u64| (bytes as f64 / sampled_f64 * entries_f64) as u64;
+    usage.layer_overhead_bytes = pipeline.map(|_| extrapolate(tail_bytes));
+
+    Ok(())
+}
+
+/// Returns a table's name without its namespace prefix.
+fn unqualified(table_name: &str) -> &str {
+    table_name
+        .rsplit_once(Namespace::SEPARATOR)
+        .map_or(table_name, |(_, name)| name)
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn namespaces_are_stripped_from_table_names() {
+        assert_eq!(unqualified("creatures"), "creatures");
+        assert_eq!(unqualified("tenant_7.creatures"), "creatures");
+        assert_eq!(unqualified("region.tenant_7.creatures_by_habitat"), "creatures_by_habitat");
+    }
+}
@@ -0,0 +1,307 @@
+//! Per-type validation rules that are checked just before a record is serialized on insert or
+//! update.
+//!
+//! Enforcing rules at the storage boundary, rather than at each call site, means a bad record can't
+//! be written by some code path that forgot to validate. Every failed rule is reported at once, so
+//! a form or API handler can show all of the problems together.
+
+use std::fmt::Debug;
+use std::ops::{Bound, RangeBounds};
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Checks a record's fields against a set of rules before it's written.
+///
+/// Validation runs on every insert and update, after [`Defaults`](crate::defaults::Defaults) are
+/// applied and before the record is serialized. If any rule fails, nothing is written and
+/// [`Error::ConstraintViolations`](crate::Error::ConstraintViolations) is returned.
+///
+/// # Example
+///
+/// ```rust
+/// use atlatl::validation::{Validate, Validator};
+///
+/// struct User { name: String, age: u8 }
+///
+/// impl Validate for User {
+///     fn validate(&self, validator: &mut Validator) {
+///         validator
+///             .non_empty("name", &self.name)
+///             .range("age", &self.age, 13..=120);
+///     }
+/// }
+///
+/// let user = User { name: String::new(), age: 7 };
+/// let violations = user.check().unwrap_err();
+/// assert_eq!(violations.len(), 2);
+/// ```
+pub trait Validate {
+    /// Records every failed rule with the validator. The default implementation checks nothing.
+    fn validate(&self, _validator: &mut Validator) {}
+
+    /// Runs the record's rules and returns every failed rule.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`ConstraintViolations`] listing every failed rule, if any rule failed.
+    fn check(&self) -> Result<(), ConstraintViolations> {
+        let mut validator = Validator::default();
+        self.validate(&mut validator);
+        validator.finish()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// A single failed validation rule.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Violation {
+    /// Name of the field that failed the rule. For example: `"email"`.
+    pub field: &'static str,
+
+    /// Name of the rule that failed. For example: `"range"`, `"non_empty"`, or `"regex"`.
+    pub rule: &'static str,
+
+    /// Human-readable description of the failure. For example: `"must be within 13..=120, got 7"`.
+    pub message: String,
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}` {}", self.field, self.message)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Every rule that a record failed, returned by [`Validate::check`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ConstraintViolations(Vec<Violation>);
+
+impl ConstraintViolations {
+    /// Returns the failed rules, in the order they were checked.
+    #[must_use]
+    pub fn violations(&self) -> &[Violation] {
+        &self.0
+    }
+
+    /// Returns the number of failed rules.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if no rules failed.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns `true` if the named field failed any rule.
+    #[must_use]
+    pub fn contains_field(&self, field: &str) -> bool {
+        self.0.iter().any(|violation| violation.field == field)
+    }
+}
+
+impl std::fmt::Display for ConstraintViolations {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} constraint violation(s)", self.0.len())?;
+        for (index, violation) in self.0.iter().enumerate() {
+            let separator = if index == 0 { ": " } else { "; " };
+            write!(f, "{separator}{violation}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConstraintViolations {}
+
+impl IntoIterator for ConstraintViolations {
+    type Item = Violation;
+    type IntoIter = std::vec::IntoIter<Violation>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Collects failed rules while a record is validated.
+///
+/// Rules are chainable, and every rule is checked even after one has failed.
+#[derive(Debug, Default)]
+pub struct Validator {
+    violations: Vec<Violation>,
+}
+
+impl Validator {
+    /// Checks that a value is within a range. For example: `validator.range("age", &age, 13..=120)`.
+    pub fn range<T, R>(&mut self, field: &'static str, value: &T, range: R) -> &mut Self
+    where
+        T: PartialOrd + Debug,
+        R: RangeBounds<T>,
+    {
+        if !range.contains(value) {
+            let start = match range.start_bound() {
+                Bound::Included(start) | Bound::Excluded(start) => format!("{start:?}"),
+                Bound::Unbounded => String::new(),
+            };
+            let end = match range.end_bound() {
+                Bound::Included(end) => format!("..={end:?}"),
+                Bound::Excluded(end) => format!("..{end:?}"),
+                Bound::Unbounded => "..".to_string(),
+            };
+            self.fail(field, "range", format!("must be within {start}{end}, got {value:?}"));
+        }
+        self
+    }
+
+    /// Checks that a string or collection isn't empty.
+    pub fn non_empty<T: IsEmpty + ?Sized>(&mut self, field: &'static str, value: &T) -> &mut Self {
+        if value.is_empty() {
+            self.fail(field, "non_empty", "must not be empty".to_string());
+        }
+        self
+    }
+
+    /// Checks that a string matches a regular expression. Patterns should be compiled once, for
+    /// example in a `static LazyLock<Regex>`, rather than on every validation.
+    #[cfg(feature = "validate-regex")]
+    pub fn matches(
+        &mut self,
+        field: &'static str,
+        value: &str,
+        pattern: &regex::Regex
+    ) -> &mut Self {
+        if !pattern.is_match(value) {
+            self.fail(field, "regex", format!("must match the pattern `{}`", pattern.as_str()));
+        }
+        self
+    }
+
+    /// Checks an arbitrary condition. For example:
+    /// `validator.check("end", end >= start, "must not be before the start")`.
+    pub fn check(
+        &mut self,
+        field: &'static str,
+        condition: bool,
+        message: impl Into<String>
+    ) -> &mut Self {
+        if !condition {
+            self.fail(field, "custom", message.into());
+        }
+        self
+    }
+
+    /// Records a failed rule.
+    pub fn fail(&mut self, field: &'static str, rule: &'static str, message: String) -> &mut Self {
+        self.violations.push(Violation { field, rule, message });
+        self
+    }
+
+    /// Returns every failed rule.
+    ///
+    /// # Errors
+    ///
+    /// * Returns [`ConstraintViolations`] if any rule failed.
+    pub fn finish(self) -> Result<(), ConstraintViolations> {
+        if self.violations.is_empty() {
+            Ok(())
+        } else {
+            Err(ConstraintViolations(self.violations))
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+/// Strings and collections that can be checked with [`Validator::non_empty`].
+pub trait IsEmpty {
+    /// Returns `true` if the value is empty.
+    fn is_empty(&self) -> bool;
+}
+
+impl IsEmpty for str {
+    fn is_empty(&self) -> bool {
+        Self::is_empty(self)
+    }
+}
+
+impl IsEmpty for String {
+    fn is_empty(&self) -> bool {
+        Self::is_empty(self)
+    }
+}
+
+impl<T> IsEmpty for [T] {
+    fn is_empty(&self) -> bool {
+        <[T]>::is_empty(self)
+    }
+}
+
+impl<T> IsEmpty for Vec<T> {
+    fn is_empty(&self) -> bool {
+        Self::is_empty(self)
+    }
+}
+
+impl<T> IsEmpty for Option<T> {
+    fn is_empty(&self) -> bool {
+        self.is_none()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+//
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Sighting {
+        species: String,
+        count: u32,
+        tags: Vec<String>,
+    }
+
+    impl Validate for Sighting {
+        fn validate(&self, validator: &mut Validator) {
+            validator
+                .non_empty("species", &self.species)
+                .range("count", &self.count, 1..=1_000)
+                .non_empty("tags", &self.tags);
+        }
+    }
+
+    #[test]
+    fn valid_record_passes() {
+        let sighting = Sighting { species: "Heron".into(), count: 3, tags: vec!["wetland".into()] };
+        assert!(sighting.check().is_ok());
+    }
+
+    #[test]
+    fn every_failed_rule_is_reported() {
+        let sighting = Sighting { species: String::new(), count: 0, tags: Vec::new() };
+        let violations = sighting.check().unwrap_err();
+        assert_eq!(violations.len(), 3);
+        assert!(violations.contains_field("species"));
+        assert_eq!(violations.violations()[1].message, "must be within 1..=1000, got 0");
+        assert_eq!(
+            violations.to_string(),
+            "3 constraint violation(s): `species` must not be empty; \
+            `count` must be within 1..=1000, got 0; `tags` must not be empty"
+        );
+    }
+
+    #[cfg(feature = "validate-regex")]
+    #[test]
+    fn regex_rule() {
+        let pattern = regex::Regex::new(r"^[a-z]+@[a-z]+\.[a-z]+$").unwrap();
+        let mut validator = Validator::default();
+        validator.matches("email", "jane@example.com", &pattern).matches("email", "jane", &pattern);
+        assert_eq!(validator.finish().unwrap_err().len(), 1);
+    }
+}